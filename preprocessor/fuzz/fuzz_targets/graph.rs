@@ -0,0 +1,14 @@
+use logseq_to_quartz::page::{parse_properties, Page, PageIndex};
+
+/// A few pages for links, block refs, tags and queries to resolve against
+pub fn index() -> PageIndex {
+    let page = |name: &str, content: &str| {
+        let (properties, _) = parse_properties(content);
+        Page { name: name.to_string(), properties, content: content.to_string(), ..Default::default() }
+    };
+    PageIndex::new(vec![
+        page("Alpha", "tags:: beta\n\n- TODO task [[Gamma/Delta]]\n  id:: 6650a1b2-0000-4000-8000-000000000001"),
+        page("Gamma/Delta", "status:: é\n\n- 🌱 $5 ((6650a1b2-0000-4000-8000-000000000001))"),
+        page("日本語", "alias:: 🌱\n\n- DONE [[Alpha]]"),
+    ])
+}
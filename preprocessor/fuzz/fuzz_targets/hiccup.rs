@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use logseq_to_quartz::hiccup;
+
+fuzz_target!(|text: &str| {
+    if let Some((nodes, _)) = hiccup::parse(text) {
+        hiccup::render(&nodes);
+    }
+});
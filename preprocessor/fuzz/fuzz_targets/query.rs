@@ -0,0 +1,18 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use logseq_to_quartz::page::PageIndex;
+use logseq_to_quartz::{query, query_parser};
+use std::sync::LazyLock;
+
+mod graph;
+
+static INDEX: LazyLock<PageIndex> = LazyLock::new(graph::index);
+
+fuzz_target!(|text: &str| {
+    let _ = query_parser::parse(text);
+    match query::execute(&format!("{{{{query {}}}}}", text), &INDEX) {
+        Ok(results) => query::results_to_markdown(&results, text),
+        Err(error) => query::unsupported_to_markdown(text, &error),
+    };
+});
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use logseq_to_quartz::content;
+use logseq_to_quartz::page::PageIndex;
+use std::sync::LazyLock;
+
+mod graph;
+
+static INDEX: LazyLock<PageIndex> = LazyLock::new(graph::index);
+
+fuzz_target!(|text: &str| {
+    content::transform(text, &INDEX);
+});
@@ -0,0 +1,116 @@
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::page::{Page, PageIndex};
+use crate::references;
+use crate::resolver;
+use crate::source::Hidden;
+
+/// Link graph report (`--analyze`)
+pub const ANALYSIS_FILE: &str = "_analysis.json";
+
+/// Pages listed under `most_linked`
+const MOST_LINKED: usize = 20;
+
+/// A page and how many pages link to it
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct LinkCount {
+    pub page: String,
+    pub inbound: usize,
+}
+
+/// What `--analyze` found in the graph; page names as in the graph
+#[derive(Debug, Default, serde::Serialize)]
+pub struct Analysis {
+    /// Published pages and journals
+    pub pages: usize,
+    /// Links between them (a page linking another several times counts once)
+    pub links: usize,
+    /// Pages no other page or journal links to (the home page excepted)
+    pub orphans: Vec<String>,
+    /// Pages with the most inbound links, most first
+    pub most_linked: Vec<LinkCount>,
+    /// Pages that link to no other page
+    pub dead_ends: Vec<String>,
+    /// Files in assets/ that no page or journal refers to
+    pub unused_assets: Vec<String>,
+}
+
+/// Analyze the link graph of the published pages. Links and tags count as
+/// links (through aliases); journals count as linking pages but are never
+/// orphans or dead ends themselves.
+pub fn analyze(index: &PageIndex, input_dir: &Path, home: &str, hidden: &Hidden, include_private: bool) -> Analysis {
+    let pages: Vec<&Page> = index.iter().filter(|p| include_private || !p.is_private()).collect();
+    let by_key: HashMap<String, &Page> = pages.iter().map(|p| (resolver::normalize_key(&p.name), *p)).collect();
+
+    let mut inbound: BTreeMap<&str, HashSet<&str>> = pages.iter().map(|p| (p.name.as_str(), HashSet::new())).collect();
+    let mut outbound: HashMap<&str, usize> = HashMap::new();
+    for page in &pages {
+        let targets: HashSet<&str> = references::references(page, index)
+            .iter()
+            .filter_map(|key| by_key.get(key))
+            .map(|target| target.name.as_str())
+            .filter(|target| *target != page.name)
+            .collect();
+        outbound.insert(&page.name, targets.len());
+        for target in targets {
+            inbound.entry(target).or_default().insert(&page.name);
+        }
+    }
+
+    let home = resolver::find_page(home, index).map(|p| p.name.as_str());
+    let is_journal = |name: &str| name.starts_with("journals/");
+    let mut most_linked: Vec<LinkCount> = inbound
+        .iter()
+        .filter(|(_, from)| !from.is_empty())
+        .map(|(page, from)| LinkCount { page: page.to_string(), inbound: from.len() })
+        .collect();
+    most_linked.sort_by(|a, b| b.inbound.cmp(&a.inbound).then_with(|| a.page.cmp(&b.page)));
+    most_linked.truncate(MOST_LINKED);
+
+    Analysis {
+        pages: pages.len(),
+        links: outbound.values().sum(),
+        orphans: inbound
+            .iter()
+            .filter(|(page, from)| from.is_empty() && !is_journal(page) && Some(**page) != home)
+            .map(|(page, _)| page.to_string())
+            .collect(),
+        most_linked,
+        dead_ends: inbound
+            .keys()
+            .filter(|page| !is_journal(page) && outbound.get(*page).copied().unwrap_or(0) == 0)
+            .map(|page| page.to_string())
+            .collect(),
+        unused_assets: unused_assets(index, input_dir, hidden),
+    }
+}
+
+/// Asset paths (relative to assets/) not mentioned in any page, private ones
+/// included, as written or with spaces as `%20`
+fn unused_assets(index: &PageIndex, input_dir: &Path, hidden: &Hidden) -> Vec<String> {
+    let assets_dir = input_dir.join("assets");
+    let mut unused: Vec<String> = walkdir::WalkDir::new(&assets_dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| !hidden.contains(e.path()))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| Some(e.path().strip_prefix(&assets_dir).ok()?.to_string_lossy().replace('\\', "/")))
+        .collect();
+    unused.retain(|asset| {
+        let written = format!("assets/{}", asset);
+        let encoded = written.replace(' ', "%20");
+        !index.iter().any(|p| p.content.contains(&written) || p.content.contains(&encoded))
+    });
+    unused
+}
+
+/// Write `_analysis.json`
+pub fn write_report(output_dir: &Path, analysis: &Analysis) -> Result<()> {
+    fs::write(output_dir.join(ANALYSIS_FILE), serde_json::to_string_pretty(analysis)?)?;
+    Ok(())
+}
+
@@ -0,0 +1,147 @@
+use anyhow::Result;
+use chrono::{Local, NaiveDate};
+use lazy_static::lazy_static;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use rayon::prelude::*;
+use regex::{Captures, Regex};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::external;
+
+/// Snapshot cache, relative to the graph root, when `--archive-cache` isn't given
+pub const DEFAULT_CACHE: &str = "logseq/archive-cache.json";
+
+/// Days before a URL without a snapshot is looked up (or saved) again
+const RETRY_DAYS: i64 = 30;
+
+/// Wayback Machine endpoints
+const AVAILABILITY_API: &str = "https://archive.org/wayback/available?url=";
+const SAVE_URL: &str = "https://web.archive.org/save/";
+const SNAPSHOT_URL: &str = "https://web.archive.org/web/";
+
+lazy_static! {
+    // [label](https://...) but not ![image](...) (groups: lead, link, url)
+    static ref EXTERNAL_LINK_RE: Regex = Regex::new(r"(^|[^!\]])(\[[^\]\n]*\]\((https?://[^)\s]+)\))").unwrap();
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct CacheEntry {
+    /// Wayback Machine URL of the closest snapshot, if there is one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    snapshot: Option<String>,
+    /// `YYYY-MM-DD` of the lookup
+    checked: String,
+}
+
+/// Snapshot lookups kept between builds, so the archive.org API is asked
+/// about each URL once (URLs without a snapshot are retried after a month)
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveCache {
+    path: PathBuf,
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+impl ArchiveCache {
+    /// Load the cache; a missing or unreadable file starts an empty one
+    pub fn load(path: &Path) -> Self {
+        let entries = fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        ArchiveCache { path: path.to_path_buf(), entries }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.entries)?)?;
+        Ok(())
+    }
+
+    /// Cached snapshot of a URL
+    pub fn snapshot(&self, url: &str) -> Option<&str> {
+        self.entries.get(url)?.snapshot.as_deref()
+    }
+
+    fn needs_lookup(&self, url: &str, today: NaiveDate) -> bool {
+        match self.entries.get(url) {
+            None => true,
+            Some(entry) if entry.snapshot.is_some() => false,
+            Some(entry) => NaiveDate::parse_from_str(&entry.checked, "%Y-%m-%d")
+                .map_or(true, |date| (today - date).num_days() >= RETRY_DAYS),
+        }
+    }
+}
+
+/// Snapshot URL for each URL: from the cache, else asked from the Wayback
+/// Machine (in parallel). With `save`, URLs without a snapshot are submitted
+/// for capture and linked to the capture of that day. With `offline` only
+/// the cache is used.
+pub fn resolve(urls: &BTreeSet<String>, cache: &mut ArchiveCache, save: bool, offline: bool) -> BTreeMap<String, String> {
+    let today = Local::now().date_naive();
+    if !offline {
+        let agent = external::agent();
+        let missing: Vec<&String> = urls.iter().filter(|url| cache.needs_lookup(url, today)).collect();
+        let found: Vec<(String, Option<String>)> = missing
+            .par_iter()
+            .map(|url| {
+                let snapshot = lookup(&agent, url).or_else(|| if save { capture(&agent, url, today) } else { None });
+                (url.to_string(), snapshot)
+            })
+            .collect();
+        for (url, snapshot) in found {
+            cache.entries.insert(url, CacheEntry { snapshot, checked: today.format("%Y-%m-%d").to_string() });
+        }
+    }
+    urls.iter()
+        .filter_map(|url| cache.snapshot(url).map(|snapshot| (url.clone(), snapshot.to_string())))
+        .collect()
+}
+
+/// Append a small "archived" link after each external link and bare URL with a snapshot
+pub fn annotate(content: &str, snapshots: &BTreeMap<String, String>) -> String {
+    if snapshots.is_empty() {
+        return content.to_string();
+    }
+    let content = EXTERNAL_LINK_RE.replace_all(content, |caps: &Captures| match snapshots.get(&caps[3]) {
+        Some(snapshot) => format!("{}{}{}", &caps[1], &caps[2], archived_link(snapshot)),
+        None => caps[0].to_string(),
+    });
+    external::replace_bare_urls(&content, |url| {
+        snapshots.get(url).map(|snapshot| format!("{}{}", url, archived_link(snapshot)))
+    })
+}
+
+fn archived_link(snapshot: &str) -> String {
+    format!(" <sup class=\"archived\">[archived]({})</sup>", snapshot)
+}
+
+/// Closest snapshot from the availability API
+fn lookup(agent: &ureq::Agent, url: &str) -> Option<String> {
+    let api = format!("{}{}", AVAILABILITY_API, utf8_percent_encode(url, NON_ALPHANUMERIC));
+    let json = agent.get(&api).call().ok()?.body_mut().read_to_string().ok()?;
+    parse_availability(&json)
+}
+
+/// Snapshot URL from an availability API response
+/// (`{"archived_snapshots": {"closest": {"available": true, "url": ...}}}`)
+pub fn parse_availability(json: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(json).ok()?;
+    let closest = value.get("archived_snapshots")?.get("closest")?;
+    if closest.get("available").and_then(|a| a.as_bool()) != Some(true) {
+        return None;
+    }
+    let snapshot = closest.get("url")?.as_str()?;
+    // The API answers with http:// links
+    Some(snapshot.replacen("http://", "https://", 1))
+}
+
+/// Ask the Wayback Machine to capture a URL; the Wayback URL for today
+/// redirects to the capture once it is done
+fn capture(agent: &ureq::Agent, url: &str, today: NaiveDate) -> Option<String> {
+    agent.get(&format!("{}{}", SAVE_URL, url)).call().ok()?;
+    Some(format!("{}{}/{}", SNAPSHOT_URL, today.format("%Y%m%d"), url))
+}
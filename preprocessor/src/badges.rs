@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use crate::escape;
+use crate::frontmatter;
+use crate::properties;
+
+/// A status-like property value shown as a badge
+#[derive(Debug, Clone, PartialEq)]
+pub struct Badge {
+    pub key: String,
+    pub value: String,
+    /// CSS class, e.g. `status-active`
+    pub class: String,
+}
+
+/// Badges for the configured properties (`status:: active, [[blocked]]` gives two)
+pub fn badges(properties: &HashMap<String, String>, keys: &[String]) -> Vec<Badge> {
+    let mut result = Vec::new();
+    for key in keys {
+        let key = key.trim().to_lowercase();
+        let Some(value) = properties.get(&key) else {
+            continue;
+        };
+        for value in properties::list_texts(value) {
+            result.push(Badge {
+                class: format!("{}-{}", class_part(&key), class_part(&value)),
+                key: key.clone(),
+                value,
+            });
+        }
+    }
+    result
+}
+
+/// Add badge properties and their `cssclasses` to frontmatter
+pub fn insert_frontmatter(fm: &str, badges: &[Badge]) -> String {
+    let mut fm = fm.to_string();
+    let mut seen: Vec<&str> = Vec::new();
+    for badge in badges {
+        if seen.contains(&badge.key.as_str()) {
+            continue;
+        }
+        seen.push(&badge.key);
+        let values: Vec<String> = badges
+            .iter()
+            .filter(|b| b.key == badge.key)
+            .map(|b| b.value.clone())
+            .collect();
+        fm = frontmatter::insert_list(&fm, &badge.key, &values);
+    }
+    let classes: Vec<String> = badges.iter().map(|b| b.class.clone()).collect();
+    frontmatter::insert_list(&fm, "cssclasses", &classes)
+}
+
+/// Badge line shown at the top of the page body
+pub fn render_line(badges: &[Badge]) -> String {
+    let spans: Vec<String> = badges
+        .iter()
+        .map(|b| {
+            format!(
+                "<span class=\"badge {}\" title=\"{}\">{}</span>",
+                b.class,
+                escape::html(&b.key),
+                escape::html(&capitalize(&b.value))
+            )
+        })
+        .collect();
+    format!("{}\n\n", spans.join(" "))
+}
+
+/// Lowercase, dash-separated CSS class fragment
+fn class_part(s: &str) -> String {
+    s.to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        None => String::new(),
+        Some(c) => c.to_uppercase().collect::<String>() + chars.as_str(),
+    }
+}
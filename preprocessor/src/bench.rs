@@ -0,0 +1,193 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::config::Config;
+use crate::content;
+use crate::page;
+use crate::progress;
+use crate::query;
+use crate::rng::Rng;
+
+/// Size of a synthetic graph (`bench --pages N --links L --queries Q --journals J`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GraphSpec {
+    pub pages: usize,
+    /// Links to other pages on each page
+    pub links: usize,
+    /// Pages with a query block
+    pub queries: usize,
+    /// Journal days, counting back from 2025-01-01
+    pub journals: usize,
+}
+
+const TOPICS: usize = 20;
+const STATUSES: [&str; 4] = ["active", "done", "paused", "idea"];
+
+/// Query blocks the generated pages cycle through
+fn query_block(i: usize) -> String {
+    let topic = i % TOPICS;
+    match i % 5 {
+        0 => format!("{{{{query (page-tags [[topic-{}]])}}}}", topic),
+        1 => format!("{{{{query (and (page-property status \"{}\") (page-tags [[topic-{}]]))}}}}", STATUSES[i % 4], topic),
+        2 => "{{query (task TODO DOING)}}".to_string(),
+        3 => format!("{{{{query [[{}]]}}}}", page_name(i * 7)),
+        _ => format!("{{{{query (and [[topic-{}]] (task TODO))}}}}", topic),
+    }
+}
+
+fn page_name(i: usize) -> String {
+    format!("Page {:05}", i)
+}
+
+/// Write a synthetic graph to `dir`: pages with properties, tags, tasks, links
+/// and code, the first `queries` of them with a query block, and journals
+/// linking to pages. Returns the number of files written.
+pub fn generate(dir: &Path, spec: &GraphSpec) -> Result<usize> {
+    let mut rng = Rng(42);
+    fs::create_dir_all(dir.join("pages"))?;
+    fs::create_dir_all(dir.join("journals"))?;
+    fs::create_dir_all(dir.join("logseq"))?;
+    fs::write(
+        dir.join("logseq/config.edn"),
+        format!("{{:meta/version 1\n :default-home {{:page \"{}\"}}\n :journal/file-name-format \"yyyy_MM_dd\"}}\n", page_name(0)),
+    )?;
+
+    for i in 0..spec.pages {
+        let mut content = format!(
+            "tags:: topic-{}\nstatus:: {}\ntype:: [[kind-{}]]\n\n",
+            i % TOPICS,
+            STATUSES[i % STATUSES.len()],
+            i % 3
+        );
+        if i < spec.queries {
+            content.push_str(&format!("- Related\n  - {}\n", query_block(i)));
+        }
+        for l in 0..spec.links {
+            let target = page_name(rng.below(spec.pages));
+            match l % 4 {
+                0 => content.push_str(&format!("- See [[{}]] for the details of item {}\n", target, l)),
+                1 => content.push_str(&format!("- TODO follow up on [[{}]] #topic-{}\n", target, rng.below(TOPICS))),
+                2 => content.push_str(&format!("  - nested note with **bold**, `code` and [[{}|an alias]]\n", target)),
+                _ => content.push_str(&format!("- DONE read [[{}]] ({} of {}) $x^{}$\n", target, l, spec.links, l)),
+            }
+        }
+        content.push_str("- ```rust\n  fn main() { println!(\"[[not a link]]\"); }\n  ```\n");
+        fs::write(dir.join("pages").join(format!("{}.md", page_name(i))), content)?;
+    }
+
+    let start = chrono::NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+    for day in 0..spec.journals {
+        let date = start - chrono::Duration::days(day as i64);
+        let content = format!(
+            "- Worked on [[{}]] #topic-{}\n- TODO review [[{}]]\n",
+            page_name(rng.below(spec.pages)),
+            rng.below(TOPICS),
+            page_name(rng.below(spec.pages))
+        );
+        fs::write(dir.join("journals").join(format!("{}.md", date.format("%Y_%m_%d"))), content)?;
+    }
+    Ok(1 + spec.pages + spec.journals)
+}
+
+/// Min, median and max of a measurement over the runs, in milliseconds
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Summary {
+    pub min: f64,
+    pub median: f64,
+    pub max: f64,
+}
+
+impl Summary {
+    fn of(mut samples: Vec<f64>) -> Self {
+        samples.sort_by(|a, b| a.total_cmp(b));
+        Summary { min: samples[0], median: samples[samples.len() / 2], max: samples[samples.len() - 1] }
+    }
+}
+
+/// Results of `bench`, also written as JSON with `--json` to track them over time
+#[derive(Debug, serde::Serialize)]
+pub struct Report {
+    pub pages: usize,
+    pub links: usize,
+    pub queries: usize,
+    pub journals: usize,
+    pub runs: usize,
+    /// Whole build, then each build phase, then content transforms and query
+    /// execution measured on their own
+    pub timings: BTreeMap<String, Summary>,
+}
+
+/// Generate a graph (into `graph`, kept, or a temporary folder) and build it
+/// `runs` times, timing the whole build, its phases, the content transform of
+/// every page and the execution of every query.
+pub fn run(spec: &GraphSpec, runs: usize, graph: Option<&Path>) -> Result<Report> {
+    progress::hide();
+    let temp = std::env::temp_dir().join(format!("logseq-to-quartz-bench-{}", std::process::id()));
+    let graph_dir = graph.map_or_else(|| temp.join("graph"), Path::to_path_buf);
+    generate(&graph_dir, spec).with_context(|| format!("writing the graph to {}", graph_dir.display()))?;
+
+    // The builds' own progress lines would bury the report
+    let level = log::max_level();
+    log::set_max_level(log::LevelFilter::Error);
+    let result = measure(spec, runs.max(1), &graph_dir, &temp.join("out"));
+    log::set_max_level(level);
+    let _ = fs::remove_dir_all(&temp);
+    result
+}
+
+fn measure(spec: &GraphSpec, runs: usize, graph_dir: &Path, output_dir: &Path) -> Result<Report> {
+    let config = Config { input_dir: graph_dir.to_path_buf(), output_dir: output_dir.to_path_buf(), ..Default::default() };
+    let mut samples: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    let mut record = |name: &str, ms: f64| samples.entry(name.to_string()).or_default().push(ms);
+
+    for _ in 0..runs {
+        let _ = fs::remove_dir_all(output_dir);
+        let start = Instant::now();
+        let stats = crate::run_preprocessor(&config)?;
+        record("build", millis(start));
+        for phase in &stats.phases {
+            record(&format!("build/{}", phase.phase), phase.ms as f64);
+        }
+
+        let index = page::build_graph_index(graph_dir)?;
+        let start = Instant::now();
+        for page in index.iter() {
+            content::transform(&page.content, &index);
+        }
+        record("transform", millis(start));
+
+        let blocks: Vec<String> = (0..spec.queries).map(query_block).collect();
+        let start = Instant::now();
+        for block in &blocks {
+            let _ = query::execute(block, &index);
+        }
+        record("queries", millis(start));
+    }
+    let _ = fs::remove_dir_all(output_dir);
+
+    Ok(Report {
+        pages: spec.pages,
+        links: spec.links,
+        queries: spec.queries,
+        journals: spec.journals,
+        runs,
+        timings: samples.into_iter().map(|(name, ms)| (name, Summary::of(ms))).collect(),
+    })
+}
+
+fn millis(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
+}
+
+/// Report lines: one row per measurement
+pub fn table(report: &Report) -> Vec<String> {
+    let mut lines = vec![format!("{:<24} {:>10} {:>10} {:>10}", "", "min", "median", "max")];
+    for (name, s) in &report.timings {
+        lines.push(format!("{:<24} {:>8.1}ms {:>8.1}ms {:>8.1}ms", name, s.min, s.median, s.max));
+    }
+    lines
+}
+
@@ -0,0 +1,84 @@
+use anyhow::{bail, Result};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+
+lazy_static! {
+    // Obsidian callout header: > [!NOTE], > [!tip]- Title (also inside bullets and nested quotes)
+    static ref CALLOUT_RE: Regex = Regex::new(r"(?m)^([ \t]*(?:-[ \t]+)?(?:>[ \t]*)+)\[!([\w-]+)\]").unwrap();
+}
+
+/// Callout-like constructs recognized out of the box (`#+BEGIN_<KIND>`, `> [!kind]`)
+/// and their Quartz callout type. `None` renders a plain blockquote.
+const DEFAULT_CALLOUTS: &[(&str, Option<&str>)] = &[
+    ("quote", None),
+    ("note", Some("note")),
+    ("tip", Some("tip")),
+    ("warning", Some("warning")),
+    ("important", Some("important")),
+    ("caution", Some("caution")),
+    ("example", Some("example")),
+    ("pinned", Some("pinned")),
+];
+
+/// Mapping from Logseq callout kinds to Quartz callout types (`--callout-map`).
+/// Covers `#+BEGIN_<KIND>` directives and existing `> [!KIND]` callouts, so a
+/// new style only needs an entry here rather than its own conversion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalloutMap {
+    types: HashMap<String, Option<String>>,
+}
+
+impl Default for CalloutMap {
+    fn default() -> Self {
+        CalloutMap {
+            types: DEFAULT_CALLOUTS
+                .iter()
+                .map(|(kind, to)| (kind.to_string(), to.map(str::to_string)))
+                .collect(),
+        }
+    }
+}
+
+impl CalloutMap {
+    /// Defaults plus overrides like `warning=danger,pinned=info,quote=quote`.
+    /// `none` as the type renders a plain blockquote; unknown kinds are added
+    /// (`custom=info` makes `#+BEGIN_CUSTOM` an info callout).
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut map = CalloutMap::default();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let Some((kind, to)) = entry.split_once('=') else {
+                bail!("invalid callout mapping '{}' (expected KIND=TYPE)", entry);
+            };
+            let (kind, to) = (kind.trim().to_lowercase(), to.trim().to_lowercase());
+            if kind.is_empty() || to.is_empty() {
+                bail!("invalid callout mapping '{}' (expected KIND=TYPE)", entry);
+            }
+            map.types.insert(kind, (to != "none").then_some(to));
+        }
+        Ok(map)
+    }
+
+    /// Quartz type for a kind: `Some(None)` for a plain blockquote, `None` if
+    /// the kind isn't a callout at all
+    pub fn get(&self, kind: &str) -> Option<Option<&str>> {
+        self.types.get(&kind.to_lowercase()).map(|t| t.as_deref())
+    }
+
+    /// Header line of a callout of this kind (`> [!tip] Title`), or `None` for a plain blockquote
+    pub fn header(&self, kind: &str, title: &str) -> Option<String> {
+        let to = self.get(kind).flatten()?;
+        Some(if title.is_empty() { format!("> [!{}]", to) } else { format!("> [!{}] {}", to, title) })
+    }
+
+    /// Rewrite existing `> [!KIND]` callouts whose kind maps to another type.
+    /// Unmapped kinds, and kinds mapped to a plain blockquote, are left as written.
+    pub fn apply(&self, content: &str) -> String {
+        CALLOUT_RE
+            .replace_all(content, |caps: &Captures| match self.get(&caps[2]).flatten() {
+                Some(to) if !to.eq_ignore_ascii_case(&caps[2]) => format!("{}[!{}]", &caps[1], to),
+                _ => caps[0].to_string(),
+            })
+            .into_owned()
+    }
+}
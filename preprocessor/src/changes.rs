@@ -0,0 +1,65 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::escape;
+use crate::journals;
+use crate::page::{Page, PageIndex};
+use crate::resolver;
+
+/// Page listing recent changes, at the output root (`--changes`)
+pub const CHANGES_FILE: &str = "changes.md";
+
+/// Build the recent changes page: pages modified in the `days` days up to
+/// `today` (git history, or file time), grouped by day, newest first. Pages
+/// first committed that day are marked new. Journals, unlisted pages and
+/// drafts are left out.
+pub fn build(index: &PageIndex, days: usize, today: NaiveDate, include_private: bool) -> String {
+    let since = today - chrono::Duration::days(days.saturating_sub(1) as i64);
+    let mut by_day: BTreeMap<NaiveDate, Vec<&Page>> = BTreeMap::new();
+    for page in index.iter() {
+        if page.name.starts_with("journals/") || page.is_unlisted() || (!include_private && page.is_private()) {
+            continue;
+        }
+        let Some(modified) = page.modified.as_deref().and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()) else {
+            continue;
+        };
+        if modified >= since && modified <= today {
+            by_day.entry(modified).or_default().push(page);
+        }
+    }
+
+    let mut content = format!("---\ntitle: {}\n---\n\n", escape::yaml_quoted("Recent changes"));
+    if by_day.is_empty() {
+        content.push_str(&format!("No pages changed in the last {} days.\n", days));
+        return content;
+    }
+    for (day, mut pages) in by_day.into_iter().rev() {
+        let date = day.format("%Y-%m-%d").to_string();
+        let title = journals::parse_journal_date(&date).map_or(date.clone(), |(_, title)| title);
+        content.push_str(&format!("## {}\n\n", title));
+        // Same day by name so the order doesn't depend on load order
+        pages.sort_by_key(|page| page.name_lower.as_str());
+        for page in pages {
+            let new = if page.created.as_deref() == Some(date.as_str()) { " (new)" } else { "" };
+            content.push_str(&format!("- [[{}|{}]]{}\n", resolver::link_target(page), page.title(), new));
+        }
+        content.push('\n');
+    }
+    content
+}
+
+/// Write `changes.md`, unless a page of the graph is published there.
+/// Returns the number of pages listed.
+pub fn write(output_dir: &Path, index: &PageIndex, days: usize, today: NaiveDate, include_private: bool) -> Result<Option<usize>> {
+    let path = output_dir.join(CHANGES_FILE);
+    if path.exists() {
+        return Ok(None);
+    }
+    let content = build(index, days, today, include_private);
+    fs::write(path, &content)?;
+    Ok(Some(content.lines().filter(|line| line.starts_with("- [[")).count()))
+}
+
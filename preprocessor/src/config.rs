@@ -1,30 +1,237 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use crate::callouts::CalloutMap;
+use crate::content::TagStyle;
+use crate::embeds::EmbedStyle;
+use crate::favorites::{self, FavoritesOutput};
+use crate::feed::FeedConfig;
+use crate::home::{self, HomeMode};
+use crate::hooks::Hooks;
+use crate::icons::IconMap;
+use crate::index_file::IndexFormat;
+use crate::journals::DatePages;
+use crate::outline::CollapsedStyle;
+use crate::page::AliasStyle;
+use crate::pdf::PdfFrame;
+use crate::properties::PageProperties;
+use crate::related::{self, RelatedOutput};
+use crate::query::TagMatch;
+use crate::resolver::{Fallback, SlugStyle};
+use crate::rollups::Rollup;
+use crate::social;
+use crate::source::Hidden;
+use crate::staging::OutputMode;
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub input_dir: PathBuf,
+    /// The graph folder as given: `input_dir` too, unless `--at-revision` builds
+    /// from a snapshot. Files kept between builds (the link and archive caches) go here.
+    pub graph_root: PathBuf,
     pub output_dir: PathBuf,
+    /// Quartz checkout whose `quartz.config.ts` is patched (`--quartz-dir`)
+    pub quartz_dir: Option<PathBuf>,
+    /// How the build reaches `output_dir` (staged and swapped in, synced, or in place)
+    pub output_mode: OutputMode,
     pub include_private: bool,
     pub create_stubs: bool,
-    pub verbose: bool,
+    /// Stub page body template (contents of `--stub-template`)
+    pub stub_template: Option<String>,
+    /// Abort on the first page error instead of skipping the page
+    pub strict: bool,
+    /// Report the time spent in each build phase (`--timings`)
+    pub timings: bool,
+    /// Aggregate journal blocks by tag into journals/by-tag (empty list = all tags)
+    pub journal_tags: Option<Vec<String>>,
+    /// Render config.edn `:default-queries` for journals on the journals index
+    pub journal_queries: bool,
+    /// Week and month roll-up pages in journals/ (`--journal-rollups`)
+    pub journal_rollups: Vec<Rollup>,
+    /// Link each journal to the same date in earlier years (`--on-this-day`)
+    pub on_this_day: bool,
+    /// Generate a contents section for pages with several headings
+    pub toc: bool,
+    /// Inline `#tag`s: kept as text, linked to tag pages or stripped (`--inline-tags`)
+    pub inline_tags: TagStyle,
+    /// Properties shown as status badges (e.g. `status`)
+    pub badge_properties: Vec<String>,
+    /// Inject a badge line at the top of the page body
+    pub badge_line: bool,
+    /// Show page properties at the top of the body (table or list)
+    pub page_properties: PageProperties,
+    /// Callout kinds (`#+BEGIN_WARNING`, `> [!NOTE]`) → Quartz callout types
+    pub callout_map: CalloutMap,
+    /// YouTube, Vimeo and tweet embeds as iframes or plain links
+    pub embeds: EmbedStyle,
+    /// Levels of children inlined with a block embed (`None` = the whole subtree)
+    pub embed_depth: Option<usize>,
+    /// Blocks collapsed in Logseq shown open or folded in `<details>` (`--collapsed`)
+    pub collapsed: CollapsedStyle,
+    /// Seed of random query results (`--seed`; `None` = a new one each build)
+    pub seed: Option<u64>,
+    /// Note the build date under query results (`--query-dates`)
+    pub query_dates: bool,
+    /// How tag queries compare tags (`--tag-match`)
+    pub tag_match: TagMatch,
+    /// What links naming no page resolve to (`--link-fallback`)
+    pub link_fallback: Fallback,
+    /// Aliases as frontmatter `aliases:` or redirect pages (`--alias-style`)
+    pub alias_style: AliasStyle,
+    /// What to publish for a page named like a journal day (`--date-pages`)
+    pub date_pages: DatePages,
+    /// Size of embedded PDF viewers
+    pub pdf_frame: PdfFrame,
+    /// Reduce raw HTML and hiccup output to an allowlist of tags and attributes
+    pub sanitize_html: bool,
+    /// Words in the `description` derived for pages without `description::` (0 = none)
+    pub description_words: usize,
+    /// `wordcount:`/`readingTime:` frontmatter and content totals (`--word-count`)
+    pub word_count: bool,
+    /// Fetch external link titles and link bare URLs with them (`--link-titles`)
+    pub link_titles: bool,
+    /// Link title cache (default: `logseq/link-cache.json` in the graph)
+    pub link_cache: Option<PathBuf>,
+    /// Link archive.org snapshots after external links (`--archive-links`)
+    pub archive_links: bool,
+    /// Submit URLs without a snapshot for capture (`--archive-save`)
+    pub archive_save: bool,
+    /// Snapshot cache (default: `logseq/archive-cache.json` in the graph)
+    pub archive_cache: Option<PathBuf>,
+    /// Use cached link titles and snapshots only, never the network
+    pub offline: bool,
     pub home_override: Option<String>,
+    /// What `index.md` shows (`--home-mode`)
+    pub home_mode: HomeMode,
+    /// Journals on a `--home-mode journals` home page
+    pub home_journals: usize,
     pub title_override: Option<String>,
     pub favorites_override: Option<Vec<String>>,
+    /// What favorites are published as (`--favorites-outputs`)
+    pub favorites_outputs: Vec<FavoritesOutput>,
     pub site_name_override: Option<String>,
+    pub suggest_tags: bool,
+    /// Write the link graph report `_analysis.json` (`--analyze`)
+    pub analyze: bool,
+    /// Write `previews.json` for hover popovers
+    pub previews: bool,
+    /// Write the page index `pages.json` / `pages.ndjson` (`--emit-index`)
+    pub emit_index: Option<IndexFormat>,
+    /// Related pages as frontmatter or `related.json` (`--related`)
+    pub related: Option<RelatedOutput>,
+    /// Related pages kept per page (`--related-count`)
+    pub related_count: usize,
+    /// Write `events.json` with open scheduled items and deadlines
+    pub events: bool,
+    /// Write `calendar.ics` with journal days and open scheduled items
+    pub emit_ics: bool,
+    /// Atom feed of recent changes (`--feed`)
+    pub feed: Option<FeedConfig>,
+    /// Days of changes listed in `changes.md` (`--changes`, `--changes-days`)
+    pub changes: Option<usize>,
+    /// Write `random.json` with the paths of listed pages (`--random`)
+    pub random: bool,
+    /// Write `sitemap.xml` and `robots.txt` (needs `base_url`)
+    pub sitemap: bool,
+    /// Published site URL, for absolute links (`--base-url`)
+    pub base_url: Option<String>,
+    /// Whether the generated Quartz config should load analytics
+    pub analytics: bool,
+    pub slug_map: Option<PathBuf>,
+    /// Spelling of name-derived output paths for non-ASCII page names
+    pub slug_style: SlugStyle,
+    /// Affected pages listed per warning in the summary (0 = all)
+    pub warning_pages: usize,
+    /// Graph-wide linked-reference filters (config.edn `:filters`)
+    pub reference_filters: Vec<(String, bool)>,
+    /// Files and folders excluded from the graph (config.edn `:hidden`)
+    pub hidden: Hidden,
+    /// Language of pages without `lang::` or a language suffix (config.edn `:preferred-language`)
+    pub default_language: String,
+    /// Emoji → asset / icon class mapping for `icon::` (`--icon-map`, `logseq/icons.json`)
+    pub icon_map: IconMap,
+    /// Shell commands run before/after the build and on failure
+    pub hooks: Hooks,
+}
+
+impl Config {
+    /// A cache kept between builds: `path` when given, else `file` in the graph
+    /// folder (never in an `--at-revision` snapshot, which is removed after the build)
+    pub fn cache_path(&self, path: Option<&Path>, file: &str) -> PathBuf {
+        path.map_or_else(|| self.graph_root.join(file), Path::to_path_buf)
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             input_dir: PathBuf::from("."),
+            graph_root: PathBuf::from("."),
             output_dir: PathBuf::from("quartz-content"),
+            quartz_dir: None,
+            output_mode: OutputMode::default(),
             include_private: false,
             create_stubs: true,
-            verbose: false,
+            stub_template: None,
+            strict: false,
+            timings: false,
+            journal_tags: None,
+            journal_queries: false,
+            journal_rollups: Vec::new(),
+            on_this_day: false,
+            toc: false,
+            inline_tags: TagStyle::default(),
+            badge_properties: vec!["status".to_string()],
+            badge_line: false,
+            page_properties: PageProperties::default(),
+            callout_map: CalloutMap::default(),
+            embeds: EmbedStyle::default(),
+            embed_depth: None,
+            collapsed: CollapsedStyle::default(),
+            seed: None,
+            query_dates: false,
+            tag_match: TagMatch::default(),
+            link_fallback: Fallback::default(),
+            alias_style: AliasStyle::default(),
+            date_pages: DatePages::default(),
+            pdf_frame: PdfFrame::default(),
+            sanitize_html: false,
+            description_words: social::DESCRIPTION_WORDS,
+            word_count: false,
+            link_titles: false,
+            link_cache: None,
+            archive_links: false,
+            archive_save: false,
+            archive_cache: None,
+            offline: false,
             home_override: None,
+            home_mode: HomeMode::default(),
+            home_journals: home::HOME_JOURNALS,
             title_override: None,
             favorites_override: None,
+            favorites_outputs: favorites::DEFAULT_OUTPUTS.to_vec(),
             site_name_override: None,
+            suggest_tags: false,
+            analyze: false,
+            previews: false,
+            emit_index: None,
+            related: None,
+            related_count: related::RELATED_COUNT,
+            events: false,
+            emit_ics: false,
+            feed: None,
+            changes: None,
+            random: false,
+            sitemap: false,
+            base_url: None,
+            analytics: true,
+            slug_map: None,
+            slug_style: SlugStyle::default(),
+            warning_pages: 5,
+            reference_filters: Vec::new(),
+            hidden: Hidden::default(),
+            default_language: "en".to_string(),
+            icon_map: IconMap::default(),
+            hooks: Hooks::default(),
         }
     }
 }
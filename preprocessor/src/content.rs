@@ -1,11 +1,25 @@
 use lazy_static::lazy_static;
 use regex::{Captures, Regex};
 
+use crate::archive;
+use crate::callouts::CalloutMap;
+use crate::embeds;
+use crate::escape;
+use crate::external;
+use crate::hiccup;
+use crate::outline::{self, Block, CollapsedStyle, Outline};
 use crate::page::PageIndex;
+use crate::pdf;
+use crate::properties;
+use crate::resolver;
+use crate::sanitize;
+use crate::schedule;
+use crate::toc;
+use crate::warnings::Diagnostic;
 
 lazy_static! {
     // Logseq system properties to remove completely (not user data)
-    // Note: query-* properties (query-table, query-properties, query-sort-by, query-sort-desc)
+    // Note: query-* properties (query-table, query-properties, query-sort-by, query-sort-desc, query-view)
     // are handled by query processing, not removed here
     static ref SYSTEM_PROPS_RE: Regex = Regex::new(
         r"(?m)^(\s*)(?:-\s*)?(collapsed|logseq\.order-list-type|id):: .+$"
@@ -19,8 +33,14 @@ lazy_static! {
         r"(?m)^(\s*)(?:-\s*)?([\w-]+):: (.+)$"
     ).unwrap();
 
-    // Logseq image size attributes {:height N, :width N}
-    static ref IMAGE_SIZE_RE: Regex = Regex::new(r"\{:height\s+\d+,?\s*:width\s+\d+\}").unwrap();
+    // Logseq image with attributes: ![alt](src){:height 300, :width 400}
+    static ref IMAGE_ATTRS_RE: Regex = Regex::new(r"!\[([^\]]*)\]\(([^)\s]+)\)\{(:[^}\n]*)\}").unwrap();
+
+    // One size attribute: :width 400, :height 300px, :width "50%"
+    static ref IMAGE_DIM_RE: Regex = Regex::new(r#":(width|height)\s+"?(\d+(?:\.\d+)?)(px|%)?"?"#).unwrap();
+
+    // Size attributes left without an image: {:height N, :width N}
+    static ref IMAGE_SIZE_RE: Regex = Regex::new(r"\{:(?:height|width)\s[^}\n]*\}").unwrap();
 
     // Empty bullet lines (just "- " or "-" with optional whitespace)
     static ref EMPTY_BULLET_RE: Regex = Regex::new(r"(?m)^(\s*)-\s*$").unwrap();
@@ -31,6 +51,9 @@ lazy_static! {
     // Currency patterns like $100, $50,000, $1.99, $10k, $7M ($ followed by digits, optional suffix)
     static ref DOLLAR_CURRENCY_RE: Regex = Regex::new(r"(^|[^\\])\$(\d[\d,.]*[kKmMbB]?)").unwrap();
 
+    // Text between two dollars that is a ticker or an amount, not math: $ETH/$BTC, $5,$6
+    static ref NOT_MATH_RE: Regex = Regex::new(r"^(?:[A-Z]{2,}[A-Z0-9]*|\d[\d,.]*[kKmMbB]?)[^\w\s]*$").unwrap();
+
     // Markdown link with wikilink URL: [text]([[Page]]) -> [text](Page)
     static ref MD_LINK_WIKILINK_RE: Regex = Regex::new(r"\[([^\]]+)\]\(\[\[([^\]]+)\]\]\)").unwrap();
 
@@ -46,9 +69,7 @@ lazy_static! {
     // Query syntax - captures indentation and optional list marker
     static ref QUERY_RE: Regex = Regex::new(r"(?m)^(\s*)(-\s*)?\{\{query[^\}]*\}\}").unwrap();
 
-    // YouTube/video/pdf embeds
-    static ref YOUTUBE_RE: Regex = Regex::new(r"\{\{youtube\s+([^\}]+)\}\}").unwrap();
-    static ref VIDEO_RE: Regex = Regex::new(r"\{\{video\s+([^\}]+)\}\}").unwrap();
+    // PDF embeds
     static ref PDF_RE: Regex = Regex::new(r"\{\{pdf\s+([^\}]+)\}\}").unwrap();
     // PDF files embedded using image syntax ![name.pdf](path.pdf) or ![](path.pdf)
     static ref IMAGE_PDF_RE: Regex = Regex::new(r"!\[[^\]]*\]\(([^\)]+\.pdf)\)").unwrap();
@@ -56,17 +77,22 @@ lazy_static! {
     // Renderer
     static ref RENDERER_RE: Regex = Regex::new(r"\{\{renderer\s+[^\}]+\}\}").unwrap();
 
+    // Any other {{macro ...}} left after known macros are converted (name captured)
+    static ref MACRO_RE: Regex = Regex::new(r"\{\{\s*([\w-]+)[^}]*\}\}").unwrap();
+
     // Cloze
     static ref CLOZE_RE: Regex = Regex::new(r"\{\{cloze\s+([^\}]+)\}\}").unwrap();
 
-    // Hiccup/EDN syntax (Clojure-style [:tag ...] blocks) - matches balanced brackets
-    static ref HICCUP_LINE_RE: Regex = Regex::new(r"(?m)^(\s*-\s*)?\[:\w").unwrap();
+    // Logseq highlight: ^^text^^
+    static ref HIGHLIGHT_RE: Regex = Regex::new(r"\^\^([^\^\n]+?)\^\^").unwrap();
 
-    // Extract text content from hiccup
-    static ref HICCUP_TEXT_RE: Regex = Regex::new(r#""([^"]+)""#).unwrap();
-    static ref HICCUP_H2_RE: Regex = Regex::new(r#"\[:h2\s+"([^"]+)"\]"#).unwrap();
-    static ref HICCUP_H3_RE: Regex = Regex::new(r#"\[:h3\s+"([^"]+)"\]"#).unwrap();
-    static ref HICCUP_LI_RE: Regex = Regex::new(r#"\[:li\s+"([^"]+)"\]"#).unwrap();
+    // Emphasis spans by marker, with the HTML tag they fall back to
+    static ref EMPHASIS_RES: Vec<(char, Regex, &'static str)> = vec![
+        ('*', Regex::new(r"\*\*([^*\n]+?)\*\*").unwrap(), "strong"),
+        ('_', Regex::new(r"__([^_\n]+?)__").unwrap(), "strong"),
+        ('*', Regex::new(r"\*([^*\n]+?)\*").unwrap(), "em"),
+        ('_', Regex::new(r"_([^_\n]+?)_").unwrap(), "em"),
+    ];
 
     // Task markers
     static ref DONE_RE: Regex = Regex::new(r"(?m)^(\s*)-\s+DONE\s+").unwrap();
@@ -82,18 +108,259 @@ lazy_static! {
     static ref PRIORITY_B_RE: Regex = Regex::new(r"\[#B\]").unwrap();
     static ref PRIORITY_C_RE: Regex = Regex::new(r"\[#C\]").unwrap();
 
-    // Schedule/deadline
-    static ref SCHEDULED_RE: Regex = Regex::new(r"SCHEDULED:\s*<([^>]+)>").unwrap();
-    static ref DEADLINE_RE: Regex = Regex::new(r"DEADLINE:\s*<([^>]+)>").unwrap();
+    // Inline code spans: `code`, ``code with ` inside``
+    static ref INLINE_CODE_RE: Regex = Regex::new(r"``[^\n]+?``|`[^`\n]+`").unwrap();
+
+    // Org-mode export blocks: #+BEGIN_EXPORT html ... #+END_EXPORT (optionally as a bullet)
+    static ref EXPORT_BLOCK_RE: Regex = Regex::new(
+        r"(?ims)^([ \t]*)(-[ \t]+)?#\+BEGIN_EXPORT(?:[ \t]+(\w+))?[ \t]*\n(.*?)^[ \t]*#\+END_EXPORT[ \t]*$"
+    ).unwrap();
+
+    // Inline tags: #tag or #[[multi word tag]] at the start of a line or after whitespace
+    // (not `# Heading`, `#+BEGIN_…`, `[#A]` or URL fragments)
+    static ref INLINE_TAG_RE: Regex = Regex::new(
+        r"(^|\s)#(?:\[\[([^\]]+)\]\]|([\p{L}\p{N}_](?:[\p{L}\p{N}_/.-]*[\p{L}\p{N}_])?))"
+    ).unwrap();
+
+    // Org-mode block directives: #+BEGIN_TIP ... #+END_TIP, #+BEGIN_SRC rust ... #+END_SRC
+    static ref DIRECTIVE_RE: Regex = Regex::new(
+        r"(?ims)^([ \t]*)(-[ \t]+)?#\+BEGIN_(\w+)(?:[ \t]+([^\n]*?))?[ \t]*\n(.*?)^[ \t]*#\+END_(\w+)[ \t]*$"
+    ).unwrap();
+
+    // Multi-word tags: #[[multi word tag]]
+    static ref MULTI_WORD_TAG_RE: Regex = Regex::new(r"(^|\s)#\[\[([^\]]+)\]\]").unwrap();
 
     // Wikilinks (for adding pages/ prefix)
-    static ref WIKILINK_RE: Regex = Regex::new(r"(!\s*)?\[\[([^\]|]+)(\|[^\]]*)?\]\]").unwrap();
+    // The alias pipe may be escaped (`[[page\|alias]]`, as in query tables)
+    static ref WIKILINK_RE: Regex = Regex::new(r"(!\s*)?\[\[([^\]|]+?)(\\?\|[^\]]*)?\]\]").unwrap();
+
+}
+
+/// Result of transforming a page: markdown body plus data destined for frontmatter
+#[derive(Debug, Default)]
+pub struct Transformed {
+    pub content: String,
+    /// Page names from queries with `query-output:: frontmatter`
+    pub listing: Vec<String>,
+    /// Problems fixed or skipped along the way (malformed tables, unknown macros)
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Wikilink targets in raw page content that don't resolve to any indexed page
+pub fn unresolved_links(content: &str, page_index: &PageIndex) -> Vec<String> {
+    // [[...]] in code stays text, so it isn't a link either
+    let content = &protect_code(content, &mut Vec::new());
+    let mut links: Vec<String> = Vec::new();
+    for caps in WIKILINK_RE.captures_iter(content) {
+        // A link to a section ([[page#heading]], ![[page#heading]]) needs only its page;
+        // a missing heading is reported by `missing_anchors`
+        let link = resolver::split_anchor(caps[2].trim(), page_index).0;
+        if link.is_empty() || link.contains("://") || links.iter().any(|l| l == link) {
+            continue;
+        }
+        if resolver::resolve(link, page_index).is_none() {
+            links.push(link.to_string());
+        }
+    }
+    links
+}
+
+/// Links in raw page content that name no page but were resolved by a guess
+/// (`--link-fallback prefix` or `fuzzy`): the link as written and the page it
+/// now points at, for auditing
+pub fn fallback_links(content: &str, page_index: &PageIndex) -> Vec<(String, String)> {
+    let content = &protect_code(content, &mut Vec::new());
+    let mut links: Vec<(String, String)> = Vec::new();
+    for caps in WIKILINK_RE.captures_iter(content) {
+        let link = resolver::split_anchor(caps[2].trim(), page_index).0;
+        let link = link.strip_prefix("pages/").unwrap_or(link);
+        let Some(resolved) = resolver::resolve(link, page_index).filter(|r| r.kind.is_guess()) else {
+            continue;
+        };
+        if !links.iter().any(|(l, _)| l == link) {
+            links.push((link.to_string(), resolved.page.name.clone()));
+        }
+    }
+    links
+}
+
+/// Section links (`[[page#heading]]`) in raw page content whose page exists
+/// but has no such heading, as written
+pub fn missing_anchors(content: &str, page_index: &PageIndex) -> Vec<String> {
+    let content = &protect_code(content, &mut Vec::new());
+    let mut missing: Vec<String> = Vec::new();
+    for caps in WIKILINK_RE.captures_iter(content) {
+        let link = caps[2].trim();
+        let (page, Some(heading)) = resolver::split_anchor(link, page_index) else {
+            continue;
+        };
+        let Some(target) = resolver::resolve(page.strip_prefix("pages/").unwrap_or(page), page_index) else {
+            continue;
+        };
+        if !toc::has_heading(&target.page.content, heading) && !missing.iter().any(|l| l == link) {
+            missing.push(link.to_string());
+        }
+    }
+    missing
+}
+
+/// How inline `#tag`s are published (`--inline-tags`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum TagStyle {
+    /// Leave `#tag` in the text as written
+    #[default]
+    Keep,
+    /// Link to the Quartz tag page (`[[tags/tag|tag]]`) and list in frontmatter `tags`
+    Link,
+    /// Remove from the text; the tag is only listed in frontmatter `tags`
+    Strip,
+}
+
+/// Apply the tag style to inline `#tag`s and `#[[multi word tag]]`s outside code blocks.
+/// Returns the tags found (as written, first spelling wins), which belong in
+/// frontmatter. `Keep` leaves the outline alone and returns no tags.
+pub fn inline_tags(outline: &mut Outline, style: TagStyle) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    if style != TagStyle::Keep {
+        outline.map_text(|text| tag_text(text, style, &mut tags));
+    }
+    tags
+}
+
+/// [`inline_tags`] on the lines of one block
+fn tag_text(text: &str, style: TagStyle, tags: &mut Vec<String>) -> String {
+    let mut in_code = false;
+    text.split('\n')
+        .map(|line| {
+            if outline::is_fence(line) {
+                in_code = !in_code;
+            }
+            if in_code {
+                return line.to_string();
+            }
+
+            let mut out = String::new();
+            let mut last = 0;
+            for caps in INLINE_TAG_RE.captures_iter(line) {
+                let whole = caps.get(0).unwrap();
+                let prefix = &caps[1];
+                let tag = caps.get(2).or(caps.get(3)).unwrap().as_str().trim();
+                if tag.is_empty() {
+                    continue;
+                }
+                if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                    tags.push(tag.to_string());
+                }
 
+                match style {
+                    TagStyle::Link => {
+                        out.push_str(&line[last..whole.start()]);
+                        out.push_str(prefix);
+                        out.push_str(&tag_link(tag));
+                        last = whole.end();
+                    }
+                    _ => {
+                        out.push_str(&line[last..whole.start()]);
+                        last = whole.end();
+                        // A tag opening the line takes the following space with it
+                        if prefix.is_empty() {
+                            last += line[last..].len() - line[last..].trim_start().len();
+                        }
+                    }
+                }
+            }
+            out.push_str(&line[last..]);
+            out
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Wikilink to a tag's Quartz tag page, labeled like Quartz's own `#tag` links
+/// (a `#` in the label would stop Quartz from parsing the wikilink)
+pub fn tag_link(tag: &str) -> String {
+    format!("[[tags/{}|{}]]", resolver::quartz_slug(tag), tag)
 }
 
 /// Transform Logseq content to Quartz-compatible format
 pub fn transform(content: &str, page_index: &PageIndex) -> String {
-    let mut result = content.to_string();
+    transform_page(content, page_index).content
+}
+
+/// Transform Logseq content, also returning data that belongs in frontmatter
+pub fn transform_page(content: &str, page_index: &PageIndex) -> Transformed {
+    transform_outline(outline::parse(content), page_index)
+}
+
+/// Transform a page's outline: block embeds and folding work on the tree, the
+/// text transforms on each block's own lines, then the tree is rendered once
+pub fn transform_outline(mut outline: Outline, page_index: &PageIndex) -> Transformed {
+    let mut diagnostics = Vec::new();
+
+    // Block embeds are grafted into the tree first, so the embedded blocks get every transform below
+    embed_blocks(&mut outline, page_index, &mut diagnostics);
+
+    // With --collapsed details, folded blocks (embedded ones too) become <details> elements
+    if page_index.collapsed() == CollapsedStyle::Details {
+        outline.fold_collapsed();
+    }
+
+    // Export blocks, code and the like are set aside as placeholders until the tree is rendered
+    let mut export_blocks = Vec::new();
+    let mut listing: Vec<String> = Vec::new();
+    outline.map_text(|text| {
+        let (text, queried) = transform_text(text, page_index, &mut export_blocks, &mut diagnostics);
+        for name in queried {
+            if !listing.contains(&name) {
+                listing.push(name);
+            }
+        }
+        text
+    });
+
+    for name in unknown_macros(&outline) {
+        diagnostics.push(Diagnostic::new("macro", format!("unknown macro {{{{{}}}}} left as text", name)));
+    }
+
+    Transformed {
+        content: restore_export_blocks(&outline.render(), &export_blocks),
+        listing,
+        diagnostics,
+    }
+}
+
+/// The text transforms, on the lines of one block (or the lines before the
+/// first bullet). Returns the text and the page names listed by frontmatter queries.
+fn transform_text(
+    text: &str,
+    page_index: &PageIndex,
+    export_blocks: &mut Vec<String>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> (String, Vec<String>) {
+    // Export blocks pass through untouched; set aside until the end
+    let sanitize_html = page_index.sanitize_html();
+    let mut result = protect_export_blocks(text, sanitize_html, export_blocks, diagnostics);
+
+    // #+BEGIN_TIP/NOTE/... become callouts; #+BEGIN_SRC code is set aside like export blocks
+    result = convert_directives(&result, page_index.callouts(), export_blocks, diagnostics);
+
+    // Code fences and inline code are set aside too: no transform below may touch them
+    result = protect_code(&result, export_blocks);
+
+    // So is LaTeX math ($$...$$, $...$), leaving only currency and tickers to escape
+    result = protect_math(&result, export_blocks);
+
+    // Hiccup blocks ([:div ...]) become sanitized HTML, set aside the same way
+    result = convert_hiccup(&result, sanitize_html, export_blocks);
+
+    // With --sanitize-html, raw HTML in the text is reduced to the allowlist too
+    // (before any transform below adds HTML of its own)
+    if sanitize_html {
+        result = sanitize::html(&result);
+    }
+
+    // Existing > [!KIND] callouts follow the same mapping (e.g. warning → danger)
+    result = page_index.callouts().apply(&result);
 
     // Remove system properties (not user data)
     result = SYSTEM_PROPS_RE.replace_all(&result, "").to_string();
@@ -102,7 +369,8 @@ pub fn transform(content: &str, page_index: &PageIndex) -> String {
     result = LOGBOOK_RE.replace_all(&result, "").to_string();
 
     // Execute queries FIRST (before user props transformation destroys query options)
-    result = process_queries_with_options(&result, page_index);
+    let (queried, listing) = process_queries_with_options(&result, page_index, diagnostics);
+    result = queried;
 
     // Convert user inline properties to readable format: key:: value → - **Key:** value
     // Skip query-* properties as they've been consumed by query processing
@@ -118,29 +386,24 @@ pub fn transform(content: &str, page_index: &PageIndex) -> String {
             }
 
             // Convert key-with-dashes to Title Case
-            let formatted_key: String = key
-                .split('-')
-                .map(|word| {
-                    let mut chars = word.chars();
-                    match chars.next() {
-                        Some(first) => first.to_uppercase().chain(chars).collect(),
-                        None => String::new(),
-                    }
-                })
-                .collect::<Vec<_>>()
-                .join(" ");
-            format!("{}- **{}:** {}", indent, formatted_key, value)
+            format!("{}- **{}:** {}", indent, properties::label(key), properties::render(value))
         })
         .to_string();
 
-    // Strip Logseq image size attributes
+    // Logseq image sizes → Obsidian size suffix or <img>; strip any left over
+    result = convert_image_sizes(&result);
     result = IMAGE_SIZE_RE.replace_all(&result, "").to_string();
 
     // Remove empty bullet lines
     result = EMPTY_BULLET_RE.replace_all(&result, "").to_string();
 
     // Fix tables - extract from bullet points and format as proper markdown tables
-    result = fix_tables(&result);
+    result = fix_tables(&result, diagnostics);
+
+    // #[[multi word tag]] → tag page link, like Quartz renders #tag (left alone it is a wikilink with a stray #)
+    result = MULTI_WORD_TAG_RE
+        .replace_all(&result, |caps: &Captures| format!("{}{}", &caps[1], tag_link(caps[2].trim())))
+        .to_string();
 
     // Escape $ signs for LaTeX compatibility, but NOT inside wikilinks
     // Strategy: protect wikilinks with placeholders, escape $, restore wikilinks
@@ -160,15 +423,29 @@ pub fn transform(content: &str, page_index: &PageIndex) -> String {
             let alias = caps.get(3).map_or("", |m| m.as_str());
 
             // Remove pages/ prefix since pages are now at content root
-            let clean_link = if link.starts_with("pages/") {
-                &link[6..] // Remove "pages/" prefix
-            } else {
-                link
-            };
+            let clean_link = link.strip_prefix("pages/").unwrap_or(link);
+
+            // Links to a section keep their heading anchor: [[page#heading]], ![[page#heading]];
+            // only the page is resolved
+            let (clean_link, heading) = resolver::split_anchor(clean_link, page_index);
+            let anchor = heading.map_or(String::new(), |heading| format!("#{}", heading));
+
+            // A PDF's hls__ annotation page: link the PDF itself
+            if embed.is_empty() {
+                if let Some(file) = page_index.pdf_file(clean_link) {
+                    let label = alias.trim_start_matches(['\\', '|']);
+                    let label = if label.is_empty() { pdf::display_name(file) } else { label.to_string() };
+                    return format!("[📄 {}]({})", label, file);
+                }
+            }
 
-            // Try to find a matching page using prefix matching
+            // Resolve through the shared resolver (aliases, namespaces, journals, slugs, prefix match)
             // e.g., "visit us" should match "visit" if "visit" exists but "visit us" doesn't
-            let final_link = find_best_page_match(clean_link, page_index);
+            let final_link = resolver::link_target_for(clean_link, page_index);
+            let final_link = final_link.as_str();
+            // Label as written, with %XX escapes decoded (`[[My%20Page]]` shows "My Page")
+            let label = resolver::decode(clean_link);
+            let clean_link = label.as_str();
 
             // Handle embed syntax (!)
             let is_embed = !embed.is_empty();
@@ -183,11 +460,15 @@ pub fn transform(content: &str, page_index: &PageIndex) -> String {
                 // KaTeX won't see $...$ patterns inside HTML tag attributes
                 let display = if !alias.is_empty() {
                     // Remove leading | from alias
-                    alias.trim_start_matches('|').to_string()
+                    alias.trim_start_matches(['\\', '|']).to_string()
                 } else if final_link != clean_link {
-                    clean_link.to_string()
+                    format!("{}{}", clean_link, anchor)
                 } else {
-                    final_link.to_string()
+                    format!("{}{}", final_link, anchor)
+                };
+                let href = match heading {
+                    Some(heading) => format!("{}#{}", final_link, toc::anchor(heading)),
+                    None => final_link.to_string(),
                 };
 
                 let slug = final_link.to_lowercase();
@@ -199,25 +480,25 @@ pub fn transform(content: &str, page_index: &PageIndex) -> String {
 
                 format!(
                     r#"<a href="{}" class="{}" data-slug="{}">{}</a>"#,
-                    final_link, class, slug, display
+                    href, class, slug, display
                 )
             } else if is_embed {
                 // Embed syntax - keep as wikilink with $ (embeds are handled differently)
                 if final_link != clean_link && alias.is_empty() {
-                    format!("{}[[{}|{}]]", embed, final_link, clean_link)
+                    format!("{}[[{}{}|{}]]", embed, final_link, anchor, clean_link)
                 } else if !alias.is_empty() {
-                    format!("{}[[{}{}]]", embed, final_link, alias)
+                    format!("{}[[{}{}{}]]", embed, final_link, anchor, alias)
                 } else {
-                    format!("{}[[{}]]", embed, final_link)
+                    format!("{}[[{}{}]]", embed, final_link, anchor)
                 }
             } else {
                 // Regular wikilinks without $ - keep as wikilinks
                 if final_link != clean_link && alias.is_empty() {
-                    format!("[[{}|{}]]", final_link, clean_link)
+                    format!("[[{}{}|{}{}]]", final_link, anchor, clean_link, anchor)
                 } else if !alias.is_empty() {
-                    format!("[[{}{}]]", final_link, alias)
+                    format!("[[{}{}{}]]", final_link, anchor, alias)
                 } else {
-                    format!("[[{}]]", final_link)
+                    format!("[[{}{}]]", final_link, anchor)
                 }
             }
         })
@@ -228,34 +509,43 @@ pub fn transform(content: &str, page_index: &PageIndex) -> String {
         .replace_all(&result, "*Block embed - view in Logseq*")
         .to_string();
 
-    // Block references
+    // Block references (PDF highlights quote their text and link to the PDF page)
     result = BLOCK_REF_RE
-        .replace_all(&result, "[→ block](#^$1)")
+        .replace_all(&result, |caps: &Captures| match page_index.pdf_highlight(&caps[1]) {
+            Some(highlight) => pdf::highlight_ref(highlight),
+            None => format!("[→ block](#^{})", &caps[1]),
+        })
         .to_string();
 
     // Media embeds
-    result = YOUTUBE_RE.replace_all(&result, "![$1]($1)").to_string();
-    result = VIDEO_RE.replace_all(&result, "![$1]($1)").to_string();
-    // PDF embed - use iframe for embedding
-    result = PDF_RE.replace_all(&result, r#"<iframe src="$1" width="100%" height="600px" style="border: 1px solid #333; border-radius: 4px;"></iframe>"#).to_string();
+    result = embeds::convert(&result, page_index.embeds());
+    // PDF embed - use iframe for embedding, with a link to open the file
+    let frame = page_index.pdf_frame();
+    result = PDF_RE.replace_all(&result, |caps: &Captures| pdf::embed(caps[1].trim(), frame)).to_string();
     // PDF embedded as image syntax ![name.pdf](path.pdf) - also convert to iframe
-    result = IMAGE_PDF_RE.replace_all(&result, r#"<iframe src="$1" width="100%" height="600px" style="border: 1px solid #333; border-radius: 4px;"></iframe>"#).to_string();
+    result = IMAGE_PDF_RE.replace_all(&result, |caps: &Captures| pdf::embed(&caps[1], frame)).to_string();
 
     // Renderer placeholder
+    for renderer in RENDERER_RE.find_iter(&result) {
+        diagnostics.push(Diagnostic::new("macro", format!("dropped unsupported {}", renderer.as_str())));
+    }
     result = RENDERER_RE.replace_all(&result, "`[renderer]`").to_string();
 
-    // Hiccup/EDN syntax - convert to markdown
-    result = convert_hiccup_to_markdown(&result);
-
     // Cloze to highlight
     result = CLOZE_RE.replace_all(&result, "==$1==").to_string();
 
+    // Formatting markers: ^^highlight^^, then emphasis CommonMark would not render
+    result = HIGHLIGHT_RE.replace_all(&result, "==$1==").to_string();
+    result = fix_emphasis(&result);
+
     // Task markers
     result = DONE_RE.replace_all(&result, "$1- [x] ").to_string();
     result = TODO_RE.replace_all(&result, "$1- [ ] ").to_string();
     result = NOW_RE.replace_all(&result, "$1- [ ] 🔄 ").to_string();
     result = DOING_RE.replace_all(&result, "$1- [ ] 🔄 ").to_string();
-    result = LATER_RE.replace_all(&result, "$1- [ ] 📅 ").to_string();
+    // NOW/LATER graphs use LATER as their plain TODO; otherwise it's a deferred task
+    let later = if page_index.workflow().later_is_todo() { "$1- [ ] " } else { "$1- [ ] 📅 " };
+    result = LATER_RE.replace_all(&result, later).to_string();
     result = WAITING_RE.replace_all(&result, "$1- [ ] ⏳ ").to_string();
     result = CANCELLED_RE.replace_all(&result, "$1- [x] ❌ ").to_string();
 
@@ -264,17 +554,385 @@ pub fn transform(content: &str, page_index: &PageIndex) -> String {
     result = PRIORITY_B_RE.replace_all(&result, "🟡").to_string();
     result = PRIORITY_C_RE.replace_all(&result, "🟢").to_string();
 
-    // Schedule/deadline
-    result = SCHEDULED_RE
-        .replace_all(&result, "📅 Scheduled: $1")
-        .to_string();
-    result = DEADLINE_RE
-        .replace_all(&result, "⏰ Deadline: $1")
-        .to_string();
+    // Schedule/deadline, with repeaters in words
+    result = schedule::render(&result);
+
+    // Bare external URLs with a fetched title → [Title](url) (--link-titles)
+    result = external::link_bare_urls(&result, page_index.link_previews());
+
+    // archive.org snapshot after each external link (--archive-links)
+    result = archive::annotate(&result, page_index.archived_links());
+
+    (result, listing)
+}
+
+/// Nested block embeds followed before giving up (embeds of embeds of ...)
+const MAX_EMBED_NESTING: usize = 4;
+
+/// `{{embed ((id))}}` → the embedded block's text with a link to its page, and
+/// its children (up to `--embed-depth` levels) grafted below the embedding block.
+/// Embeds of missing blocks, embed cycles and embeds in code are left as written.
+fn embed_blocks(outline: &mut Outline, page_index: &PageIndex, diagnostics: &mut Vec<Diagnostic>) {
+    fn embed_in(blocks: &mut [Block], page_index: &PageIndex, diagnostics: &mut Vec<Diagnostic>) {
+        for block in blocks {
+            let mut grafts = Vec::new();
+            let mut in_code = false;
+            for (at, line) in block.lines.iter_mut().enumerate() {
+                if code_fence(line).is_some() {
+                    in_code = !in_code;
+                }
+                if in_code || !BLOCK_EMBED_RE.is_match(line) {
+                    continue;
+                }
+                let embedded = embed_text(line, page_index, &mut Vec::new(), diagnostics);
+                let mut lines = embedded.split('\n').map(str::to_string);
+                *line = lines.next().unwrap_or_default();
+                grafts.push((at, lines.collect::<Vec<_>>()));
+            }
+            // The grafted blocks are embedded already: only the block's own children are left
+            embed_in(&mut block.children, page_index, diagnostics);
+            // Last first, so the lines before keep their place
+            for (at, lines) in grafts.into_iter().rev() {
+                block.graft(at, &lines);
+            }
+        }
+    }
+    // Lines before the first bullet take the embedded blocks as text
+    if outline.preamble.iter().any(|line| BLOCK_EMBED_RE.is_match(line)) {
+        let preamble = embed_text(&outline.preamble.join("\n"), page_index, &mut Vec::new(), diagnostics);
+        outline.preamble = preamble.split('\n').map(str::to_string).collect();
+    }
+    embed_in(&mut outline.blocks, page_index, diagnostics);
+}
 
+/// Block embeds of a piece of text, expanded in place; the embedded text can
+/// hold embeds of its own (`seen`: the blocks being embedded around it)
+fn embed_text(content: &str, page_index: &PageIndex, seen: &mut Vec<String>, diagnostics: &mut Vec<Diagnostic>) -> String {
+    if !content.contains("{{embed") {
+        return content.to_string();
+    }
+    let mut out: Vec<String> = Vec::new();
+    let mut in_code = false;
+    for line in content.lines() {
+        if code_fence(line).is_some() {
+            in_code = !in_code;
+        }
+        if in_code || !BLOCK_EMBED_RE.is_match(line) {
+            out.push(line.to_string());
+            continue;
+        }
+
+        let prefix = &line[..line.len() - line.trim_start().len()];
+        let mut ids = Vec::new();
+        let mut children = Vec::new();
+        let text = BLOCK_EMBED_RE.replace_all(line, |caps: &Captures| {
+            let id = caps[1].trim().to_lowercase();
+            let found = page_index
+                .block_page(&id)
+                .and_then(|page| Some((page, page.outline().find(&id)?.subtree(page_index.embed_depth()))));
+            match found {
+                Some(_) if seen.contains(&id) || seen.len() >= MAX_EMBED_NESTING => {
+                    diagnostics.push(Diagnostic::new("block-embed", format!("embed of (({})) nested in itself", id)));
+                    caps[0].to_string()
+                }
+                Some((page, subtree)) => {
+                    children.extend(subtree.children.iter().map(|child| format!("{}{}", prefix, child)));
+                    ids.push(id);
+                    format!("{} [[{}|↗]]", subtree.text, page.name)
+                }
+                None => {
+                    diagnostics.push(Diagnostic::new("block-embed", format!("embedded block (({})) not found", id)));
+                    caps[0].to_string()
+                }
+            }
+        });
+
+        let mut chunk = std::iter::once(text.into_owned()).chain(children).collect::<Vec<_>>().join("\n");
+        // The embedded text can hold embeds of its own
+        if !ids.is_empty() {
+            let depth = seen.len();
+            seen.extend(ids);
+            chunk = embed_text(&chunk, page_index, seen, diagnostics);
+            seen.truncate(depth);
+        }
+        out.push(chunk);
+    }
+    let mut result = out.join("\n");
+    if content.ends_with('\n') {
+        result.push('\n');
+    }
     result
 }
 
+/// Replace `#+BEGIN_EXPORT` blocks with placeholders and render them:
+/// `html` blocks become raw HTML, other backends (latex, ascii) a code block.
+/// The rendered blocks are added to `blocks`, indexed by placeholder.
+/// With `sanitize`, HTML blocks are reduced to the allowlist (see [`sanitize::html`]).
+fn protect_export_blocks(content: &str, sanitize: bool, blocks: &mut Vec<String>, diagnostics: &mut Vec<Diagnostic>) -> String {
+    let protected = EXPORT_BLOCK_RE.replace_all(content, |caps: &Captures| {
+        let indent = &caps[1];
+        let bullet = caps.get(2).map_or("", |m| m.as_str());
+        let backend = caps.get(3).map_or("", |m| m.as_str()).to_lowercase();
+        let mut body = dedent(caps[4].trim_end_matches('\n'));
+        if sanitize && backend == "html" {
+            body = sanitize::html(&body);
+        }
+
+        // Continuation lines line up with the block's bullet text
+        let pad = format!("{}{}", indent, " ".repeat(bullet.len()));
+        let lines: Vec<String> = if backend == "html" {
+            // One HTML block: a blank line would end it early
+            body.lines().filter(|l| !l.trim().is_empty()).map(str::to_string).collect()
+        } else {
+            diagnostics.push(Diagnostic::new(
+                "export",
+                format!("#+BEGIN_EXPORT {} shown as code", if backend.is_empty() { "(no backend)" } else { &backend }),
+            ));
+            std::iter::once(format!("```{}", backend))
+                .chain(body.lines().map(str::to_string))
+                .chain(std::iter::once("```".to_string()))
+                .collect()
+        };
+        let rendered = lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| if i == 0 { line.clone() } else { format!("{}{}", pad, line) })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let placeholder = format!("{}{}\x00EXPORT{}\x00", indent, bullet, blocks.len());
+        blocks.push(rendered);
+        placeholder
+    });
+    protected.into_owned()
+}
+
+/// Convert Org-mode block directives: callout kinds (`callouts`: TIP, NOTE,
+/// WARNING, ... by default) to callouts (`> [!tip]`) or, for QUOTE, a blockquote;
+/// SRC to a fenced code block (set aside in `blocks` so its text isn't
+/// transformed) and COMMENT to nothing. Unknown directives are left as text.
+fn convert_directives(
+    content: &str,
+    callouts: &CalloutMap,
+    blocks: &mut Vec<String>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> String {
+    DIRECTIVE_RE
+        .replace_all(content, |caps: &Captures| {
+            let kind = caps[3].to_lowercase();
+            if !caps[6].eq_ignore_ascii_case(&kind) {
+                return caps[0].to_string();
+            }
+            let indent = &caps[1];
+            let bullet = caps.get(2).map_or("", |m| m.as_str());
+            let args = caps.get(4).map_or("", |m| m.as_str().trim());
+            let body = dedent(caps[5].trim_end_matches('\n'));
+            let pad = format!("{}{}", indent, " ".repeat(bullet.len()));
+
+            match kind.as_str() {
+                "src" => {
+                    // Language is the first argument (`#+BEGIN_SRC rust :results output`)
+                    let fence = format!("```{}", args.split_whitespace().next().unwrap_or(""));
+                    let code = std::iter::once(fence)
+                        .chain(body.lines().map(|l| format!("{}{}", pad, l)))
+                        .chain(std::iter::once(format!("{}```", pad)))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    let placeholder = format!("{}{}\x00EXPORT{}\x00", indent, bullet, blocks.len());
+                    blocks.push(code);
+                    return placeholder;
+                }
+                "comment" => return String::new(),
+                _ if callouts.get(&kind).is_none() => {
+                    diagnostics.push(Diagnostic::new("directive", format!("#+BEGIN_{} left as text", kind.to_uppercase())));
+                    return caps[0].to_string();
+                }
+                _ => {}
+            }
+
+            let lines: Vec<String> = callouts
+                .header(&kind, args)
+                .into_iter()
+                .chain(body.lines().map(|l| format!("> {}", l).trim_end().to_string()))
+                .collect();
+            lines
+                .iter()
+                .enumerate()
+                .map(|(i, line)| if i == 0 { format!("{}{}{}", indent, bullet, line) } else { format!("{}{}", pad, line) })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .into_owned()
+}
+
+/// Content with code blocks and inline code set aside, for reading the links
+/// and tags of a page: `[[x]]` in code is text, not a reference
+pub fn without_code(content: &str) -> String {
+    protect_code(content, &mut Vec::new())
+}
+
+/// Set aside fenced code blocks (``` or ~~~, optionally opening a bullet) and
+/// inline code spans in `blocks`, leaving placeholders, so dollar escaping,
+/// properties, task markers, wikilinks etc. never run inside code.
+/// A fence without a closing line is left as text.
+fn protect_code(content: &str, blocks: &mut Vec<String>) -> String {
+    let lines: Vec<&str> = content.split('\n').collect();
+    let mut out: Vec<String> = Vec::with_capacity(lines.len());
+    let mut i = 0;
+    while i < lines.len() {
+        if let Some((prefix, fence)) = code_fence(lines[i]) {
+            let close = (i + 1..lines.len()).find(|&j| {
+                let trimmed = lines[j].trim();
+                trimmed.starts_with(fence) && trimmed.trim_start_matches(&fence[..1]).is_empty()
+            });
+            if let Some(close) = close {
+                let code = std::iter::once(&lines[i][prefix.len()..])
+                    .chain(lines[i + 1..=close].iter().copied())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                out.push(format!("{}\x00EXPORT{}\x00", prefix, blocks.len()));
+                blocks.push(code);
+                i = close + 1;
+                continue;
+            }
+        }
+        let line = INLINE_CODE_RE.replace_all(lines[i], |caps: &Captures| {
+            blocks.push(caps[0].to_string());
+            format!("\x00EXPORT{}\x00", blocks.len() - 1)
+        });
+        out.push(line.into_owned());
+        i += 1;
+    }
+    out.join("\n")
+}
+
+/// Set aside LaTeX math in `blocks`, like code: display math `$$...$$` (may span
+/// lines within a block) and inline math `$...$` on one line, opening before a
+/// non-space and closing at the next `$` after a non-space and not before a digit
+/// (Pandoc's rule, so `$5 and $10` is not math). Tickers and amounts between two
+/// dollars (`$ETH/$BTC`) stay text. `\$` and wikilinks (`[[$BOOT]]`) are skipped.
+fn protect_math(content: &str, blocks: &mut Vec<String>) -> String {
+    let bytes = content.as_bytes();
+    let mut out = String::with_capacity(content.len());
+    let (mut last, mut i) = (0, 0);
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'[' if content[i..].starts_with("[[") => {
+                i += match content[i..].find("]]") {
+                    Some(end) if !content[i..i + end].contains('\n') => end + 2,
+                    _ => 2,
+                };
+            }
+            b'$' => match math_end(&content[i..]) {
+                Some(len) => {
+                    out.push_str(&content[last..i]);
+                    out.push_str(&format!("\x00EXPORT{}\x00", blocks.len()));
+                    blocks.push(content[i..i + len].to_string());
+                    i += len;
+                    last = i;
+                }
+                None => i += 1,
+            },
+            _ => i += 1,
+        }
+    }
+    out.push_str(&content[last..]);
+    out
+}
+
+/// Length of the math span starting at `text` (which starts with `$`), if it is one
+fn math_end(text: &str) -> Option<usize> {
+    if let Some(body) = text.strip_prefix("$$") {
+        let close = body.find("$$")?;
+        let math = &body[..close];
+        let crosses_block = math.contains("\n\n") || math.lines().skip(1).any(|l| l.trim_start().starts_with("- "));
+        return (!math.trim().is_empty() && !crosses_block).then_some(close + 4);
+    }
+    let body = &text[1..];
+    let line = &body[..body.find('\n').unwrap_or(body.len())];
+    if line.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let close = line.match_indices('$').map(|(pos, _)| pos).find(|&pos| !line[..pos].ends_with('\\'))?;
+    let math = &line[..close];
+    let space_before_close = math.ends_with(char::is_whitespace);
+    let before_digit = line[close + 1..].starts_with(|c: char| c.is_ascii_digit());
+    if math.is_empty() || space_before_close || before_digit || math.contains("[[") || NOT_MATH_RE.is_match(math) {
+        return None;
+    }
+    Some(close + 2)
+}
+
+/// Convert blocks that are a hiccup form (`[:div ...]`, optionally as a bullet
+/// and over several lines) to HTML, set aside in `blocks`. Block elements drop
+/// the bullet; a form that doesn't parse, or has text after it, is left as is.
+/// With `sanitize`, the HTML is reduced to the allowlist (see [`sanitize::html`]).
+fn convert_hiccup(content: &str, sanitize: bool, blocks: &mut Vec<String>) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut rest = content;
+    while !rest.is_empty() {
+        let line_len = rest.find('\n').map_or(rest.len(), |i| i + 1);
+        let indent = &rest[..rest.len() - rest.trim_start_matches([' ', '\t']).len()];
+        let after_indent = &rest[indent.len()..];
+        let bullet = ["- ", "-\t"].into_iter().find(|b| after_indent.starts_with(b)).unwrap_or("");
+        let form = &after_indent[bullet.len()..];
+        if form.starts_with("[:") {
+            if let Some((nodes, len)) = hiccup::parse(form) {
+                let tail = &form[len..];
+                let tail_len = tail.find('\n').unwrap_or(tail.len());
+                if tail[..tail_len].trim().is_empty() {
+                    out.push_str(indent);
+                    if !hiccup::is_block(&nodes) {
+                        out.push_str(bullet);
+                    }
+                    out.push_str(&format!("\x00EXPORT{}\x00", blocks.len()));
+                    let html = hiccup::render(&nodes);
+                    blocks.push(if sanitize { sanitize::html(&html) } else { html });
+                    rest = &tail[tail_len..];
+                    continue;
+                }
+            }
+        }
+        out.push_str(&rest[..line_len]);
+        rest = &rest[line_len..];
+    }
+    out
+}
+
+/// Opening fence of a code block: the indent/bullet before it and the fence (``` or ~~~, 3 or more)
+fn code_fence(line: &str) -> Option<(&str, &str)> {
+    let text = line.trim_start();
+    let text = text.strip_prefix('-').map_or(text, |rest| if rest.starts_with([' ', '\t']) { rest.trim_start() } else { text });
+    let marker = text.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = text.len() - text.trim_start_matches(marker).len();
+    (len >= 3).then(|| (&line[..line.len() - text.len()], &text[..len]))
+}
+
+/// Put rendered export blocks back in place of their placeholders
+fn restore_export_blocks(content: &str, blocks: &[String]) -> String {
+    let mut result = content.to_string();
+    // Latest first: a block can hold placeholders of earlier ones (math inside hiccup)
+    for (i, block) in blocks.iter().enumerate().rev() {
+        result = result.replace(&format!("\x00EXPORT{}\x00", i), block);
+    }
+    result
+}
+
+/// Remove the indentation shared by all non-blank lines
+fn dedent(text: &str) -> String {
+    let indent = text
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    text.lines()
+        .map(|l| l.get(indent..).unwrap_or(l.trim_start()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 /// Escape dollar signs for LaTeX compatibility, but NOT inside wikilinks
 /// Wikilinks like [[$BOOT]] must keep $ unescaped to match page names
 fn escape_dollars_outside_wikilinks(content: &str) -> String {
@@ -321,11 +979,17 @@ fn escape_dollars_outside_wikilinks(content: &str) -> String {
 }
 
 /// Process queries with context-aware options (query-properties::, query-sort-by::, etc.)
-fn process_queries_with_options(content: &str, page_index: &crate::page::PageIndex) -> String {
+/// Returns the content plus page names collected by `query-output:: frontmatter` queries
+fn process_queries_with_options(
+    content: &str,
+    page_index: &crate::page::PageIndex,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> (String, Vec<String>) {
     use crate::query;
 
-    let lines: Vec<&str> = content.lines().collect();
+    let lines: Vec<&str> = content.split('\n').collect();
     let mut result_lines: Vec<String> = Vec::new();
+    let mut listing: Vec<String> = Vec::new();
     let mut i = 0;
 
     while i < lines.len() {
@@ -355,6 +1019,8 @@ fn process_queries_with_options(content: &str, page_index: &crate::page::PageInd
                     || prev_line.contains("query-sort-by::")
                     || prev_line.contains("query-sort-desc::")
                     || prev_line.contains("query-table::")
+                    || prev_line.contains("query-output::")
+                    || prev_line.contains("query-view::")
                 {
                     context = format!("{}\n{}", prev_line, context);
                 } else {
@@ -363,19 +1029,52 @@ fn process_queries_with_options(content: &str, page_index: &crate::page::PageInd
             }
 
             // Parse options from context
-            let options = query::parse_query_options(&context);
+            let mut options = query::parse_query_options(&context);
+            options.shuffled = query::is_shuffled(query_str);
+
+            // Execute query and render results; (all-page-tags) lists tags instead of pages
+            let all_tags = query::is_all_page_tags(query_str);
+            let results = if all_tags { Ok(Vec::new()) } else { query::execute(query_str, page_index) };
+            let results = match results {
+                Ok(results) => results,
+                // A query that can't be read is shown as written in a warning callout
+                Err(error) => {
+                    diagnostics.push(Diagnostic::new("query", format!("unsupported query: {}", error)));
+                    result_lines.push(String::new());
+                    for line in query::unsupported_to_markdown(query_str, &error).lines() {
+                        result_lines.push(format!("{}{}", indent, line));
+                    }
+                    result_lines.push(String::new());
+                    i += 1;
+                    continue;
+                }
+            };
+
+            // Frontmatter output: collect names, render nothing in the body
+            if options.output == query::QueryOutput::Frontmatter {
+                for name in query::results_to_listing(&results, &options) {
+                    if !listing.contains(&name) {
+                        listing.push(name);
+                    }
+                }
+                i += 1;
+                continue;
+            }
 
-            // Execute query and render results
-            let results = query::execute(query_str, page_index);
-            let output = query::results_to_markdown_with_options(&results, query_str, &options);
+            let output = if all_tags {
+                query::tag_listing(page_index, query_str)
+            } else {
+                query::results_to_markdown_with_options(&results, query_str, &options)
+            };
 
             // Format output with proper indentation
-            let formatted_output = if output.contains('|') && output.contains("---") {
-                // Table output - needs blank line before for markdown to recognize it
-                // Tables should NOT have list markers, just indentation
+            let block_view = options.view.is_some_and(|view| view.is_block()) && !results.is_empty();
+            let formatted_output = if block_view || (output.contains('|') && output.contains("---")) {
+                // Table, card and embed output - needs blank line before for markdown to recognize it
+                // These should NOT have list markers, just indentation
                 let table_lines: Vec<_> = output
                     .lines()
-                    .map(|line| format!("{}{}", indent, line))
+                    .map(|line| if line.is_empty() { String::new() } else { format!("{}{}", indent, line) })
                     .collect();
                 // Add blank line before table for proper markdown parsing
                 format!("\n{}", table_lines.join("\n"))
@@ -402,257 +1101,24 @@ fn process_queries_with_options(content: &str, page_index: &crate::page::PageInd
             for output_line in formatted_output.lines() {
                 result_lines.push(output_line.to_string());
             }
-        } else {
-            result_lines.push(line.to_string());
-        }
-
-        i += 1;
-    }
-
-    result_lines.join("\n")
-}
-
-/// Convert Logseq hiccup syntax to HTML
-fn convert_hiccup_to_markdown(content: &str) -> String {
-    let mut result = String::new();
-    let mut in_multiline_hiccup = false;
-    let mut hiccup_buffer = String::new();
-    let mut hiccup_indent = String::new();
-
-    for line in content.lines() {
-        let trimmed = line.trim();
-
-        // Check if this starts a hiccup block
-        if !in_multiline_hiccup && (trimmed.starts_with("[:") || trimmed.starts_with("- [:")) {
-            let hiccup_start = if trimmed.starts_with("- ") {
-                hiccup_indent = line.chars().take_while(|c| c.is_whitespace()).collect();
-                &trimmed[2..]
-            } else {
-                hiccup_indent = String::new();
-                trimmed
-            };
-
-            // Check if hiccup is complete on one line (balanced brackets)
-            let mut bracket_count = 0;
-            for c in hiccup_start.chars() {
-                match c {
-                    '[' => bracket_count += 1,
-                    ']' => bracket_count -= 1,
-                    _ => {}
-                }
+            // Raw HTML (the card grid wrapper) runs to the next blank line
+            if block_view {
+                result_lines.push(String::new());
             }
-
-            if bracket_count == 0 {
-                // Single-line hiccup
-                let html = hiccup_to_html(hiccup_start);
-                result.push_str(&hiccup_indent);
-                // Don't add list marker for block elements
-                if !is_block_element(&html) {
-                    result.push_str("- ");
+            if let Some(date) = page_index.query_date() {
+                if !block_view {
+                    result_lines.push(String::new());
                 }
-                result.push_str(&html);
-                result.push('\n');
-            } else {
-                // Multi-line hiccup starts
-                in_multiline_hiccup = true;
-                hiccup_buffer = hiccup_start.to_string();
-            }
-        } else if in_multiline_hiccup {
-            // Continue collecting multi-line hiccup
-            hiccup_buffer.push(' ');
-            hiccup_buffer.push_str(trimmed);
-
-            // Check if hiccup is now complete
-            let mut bracket_count = 0;
-            for c in hiccup_buffer.chars() {
-                match c {
-                    '[' => bracket_count += 1,
-                    ']' => bracket_count -= 1,
-                    _ => {}
-                }
-            }
-
-            if bracket_count == 0 {
-                in_multiline_hiccup = false;
-                let html = hiccup_to_html(&hiccup_buffer);
-                result.push_str(&hiccup_indent);
-                // Don't add list marker for block elements
-                if !is_block_element(&html) {
-                    result.push_str("- ");
-                }
-                result.push_str(&html);
-                result.push('\n');
-                hiccup_buffer.clear();
+                result_lines.push(format!("{}{}", indent, query::freshness_note(date)));
             }
         } else {
-            result.push_str(line);
-            result.push('\n');
-        }
-    }
-
-    // Handle unclosed hiccup
-    if !hiccup_buffer.is_empty() {
-        result.push_str(&hiccup_indent);
-        result.push_str("- ");
-        result.push_str(&hiccup_to_html(&hiccup_buffer));
-        result.push('\n');
-    }
-
-    // Remove trailing newline if original didn't have one
-    if !content.ends_with('\n') && result.ends_with('\n') {
-        result.pop();
-    }
-
-    result
-}
-
-/// Check if HTML string is a block-level element (shouldn't be wrapped in list)
-fn is_block_element(html: &str) -> bool {
-    html.starts_with("<ul")
-        || html.starts_with("<ol")
-        || html.starts_with("<div")
-        || html.starts_with("<table")
-        || html.starts_with("<h1")
-        || html.starts_with("<h2")
-        || html.starts_with("<h3")
-        || html.starts_with("<h4")
-        || html.starts_with("<blockquote")
-        || html.starts_with("<pre")
-}
-
-/// Convert hiccup syntax to HTML
-fn hiccup_to_html(hiccup: &str) -> String {
-    let hiccup = hiccup.trim();
-
-    // Parse the tag and check for attributes
-    if !hiccup.starts_with("[:") {
-        return format!("`{}`", hiccup);
-    }
-
-    // Extract tag name
-    let after_bracket = &hiccup[2..];
-    let tag_end = after_bracket.find(|c: char| c.is_whitespace() || c == ']').unwrap_or(after_bracket.len());
-    let tag = &after_bracket[..tag_end];
-
-    // Get the rest (attributes + content)
-    let rest = &after_bracket[tag_end..].trim_end_matches(']').trim();
-
-    // Check for attributes map {:key "value" ...}
-    let (attrs_html, content_start) = if rest.starts_with('{') {
-        // Find matching closing brace
-        let mut brace_count = 0;
-        let mut attr_end = 0;
-        for (i, c) in rest.char_indices() {
-            match c {
-                '{' => brace_count += 1,
-                '}' => {
-                    brace_count -= 1;
-                    if brace_count == 0 {
-                        attr_end = i + 1;
-                        break;
-                    }
-                }
-                _ => {}
-            }
-        }
-        let attr_str = &rest[1..attr_end - 1]; // Remove { }
-        let attrs = parse_hiccup_attrs(attr_str);
-        (attrs, &rest[attr_end..])
-    } else {
-        (String::new(), *rest)
-    };
-
-    let content = parse_hiccup_content(content_start);
-
-    // Handle special tags
-    match tag {
-        "ul" | "ol" => {
-            let mut items = Vec::new();
-            for caps in HICCUP_LI_RE.captures_iter(hiccup) {
-                if let Some(text) = caps.get(1) {
-                    items.push(format!("<li>{}</li>", text.as_str()));
-                }
-            }
-            if !items.is_empty() {
-                return format!("<{}{}>{}</{}>", tag, attrs_html, items.join(""), tag);
-            }
-        }
-        _ => {}
-    }
-
-    // Generate HTML
-    if content.is_empty() && attrs_html.is_empty() {
-        format!("<{}/>", tag)
-    } else {
-        format!("<{}{}>{}</{}>", tag, attrs_html, content, tag)
-    }
-}
-
-/// Parse hiccup attributes {:key "value" :key2 "value2"}
-fn parse_hiccup_attrs(attrs: &str) -> String {
-    lazy_static::lazy_static! {
-        static ref ATTR_RE: Regex = Regex::new(r#":(\w+)\s+"([^"]+)""#).unwrap();
-    }
-
-    let mut result = String::new();
-    for caps in ATTR_RE.captures_iter(attrs) {
-        if let (Some(key), Some(value)) = (caps.get(1), caps.get(2)) {
-            result.push(' ');
-            result.push_str(key.as_str());
-            result.push_str("=\"");
-            result.push_str(value.as_str());
-            result.push('"');
+            result_lines.push(line.to_string());
         }
-    }
-    result
-}
 
-/// Parse content inside hiccup element (handles nested elements and strings)
-fn parse_hiccup_content(content: &str) -> String {
-    let content = content.trim();
-    let mut result = String::new();
-    let mut i = 0;
-    let chars: Vec<char> = content.chars().collect();
-
-    while i < chars.len() {
-        if chars[i] == '"' {
-            // Parse quoted string
-            i += 1;
-            let start = i;
-            while i < chars.len() && chars[i] != '"' {
-                i += 1;
-            }
-            result.push_str(&chars[start..i].iter().collect::<String>());
-            i += 1; // skip closing quote
-        } else if chars[i] == '[' && i + 1 < chars.len() && chars[i + 1] == ':' {
-            // Parse nested hiccup element
-            let start = i;
-            let mut bracket_count = 0;
-            while i < chars.len() {
-                match chars[i] {
-                    '[' => bracket_count += 1,
-                    ']' => {
-                        bracket_count -= 1;
-                        if bracket_count == 0 {
-                            i += 1;
-                            break;
-                        }
-                    }
-                    _ => {}
-                }
-                i += 1;
-            }
-            let nested: String = chars[start..i].iter().collect();
-            result.push_str(&hiccup_to_html(&nested));
-        } else if !chars[i].is_whitespace() {
-            // Skip other characters
-            i += 1;
-        } else {
-            i += 1;
-        }
+        i += 1;
     }
 
-    result
+    (result_lines.join("\n"), listing)
 }
 
 /// Fix tables embedded in Logseq bullet points
@@ -661,8 +1127,8 @@ fn parse_hiccup_content(content: &str) -> String {
 /// \t  |------|------|
 /// \t  | val1 | val2 |
 /// This function adds separator rows if missing while preserving document structure
-fn fix_tables(content: &str) -> String {
-    let lines: Vec<&str> = content.lines().collect();
+fn fix_tables(content: &str, diagnostics: &mut Vec<Diagnostic>) -> String {
+    let lines: Vec<&str> = content.split('\n').collect();
     let mut result = Vec::new();
     let mut i = 0;
 
@@ -724,6 +1190,7 @@ fn fix_tables(content: &str) -> String {
                     let continuation_prefix = get_continuation_prefix(&first_line_prefix);
                     let separator = format!("{}|{}|", continuation_prefix, vec!["---"; col_count].join("|"));
                     result.push(separator);
+                    diagnostics.push(Diagnostic::new("table", "added missing table separator row"));
                 }
             }
 
@@ -734,6 +1201,10 @@ fn fix_tables(content: &str) -> String {
                 if is_separator_row(content) {
                     let sep_col_count = content.matches('|').count().saturating_sub(1);
                     if sep_col_count != header_col_count {
+                        diagnostics.push(Diagnostic::new(
+                            "table",
+                            format!("dropped separator row with {} columns (header has {})", sep_col_count, header_col_count),
+                        ));
                         continue; // Skip malformed separator
                     }
                 }
@@ -748,6 +1219,110 @@ fn fix_tables(content: &str) -> String {
     result.join("\n")
 }
 
+/// Keep Logseq image sizes (`![a](x.png){:height 300, :width 400}`): pixel
+/// widths become an Obsidian size suffix Quartz reads (`![a|400x300](x.png)`),
+/// height-only and percentage sizes an HTML `<img>` with a style. Other
+/// attributes are dropped.
+fn convert_image_sizes(content: &str) -> String {
+    IMAGE_ATTRS_RE
+        .replace_all(content, |caps: &Captures| {
+            let (alt, src) = (&caps[1], &caps[2]);
+            let (mut width, mut height) = (None, None);
+            for dim in IMAGE_DIM_RE.captures_iter(&caps[3]) {
+                let value: f64 = dim[2].parse().unwrap_or_default();
+                let size = match dim.get(3).map(|unit| unit.as_str()) {
+                    Some("%") => format!("{}%", value),
+                    _ => format!("{}", value.round()),
+                };
+                if &dim[1] == "width" { width = Some(size) } else { height = Some(size) }
+            }
+            let percent = |size: &Option<String>| size.as_ref().is_some_and(|s| s.ends_with('%'));
+            match (&width, &height) {
+                (None, None) => format!("![{}]({})", alt, src),
+                (Some(w), None) if !percent(&width) => format!("![{}|{}]({})", alt, w, src),
+                (Some(w), Some(h)) if !percent(&width) && !percent(&height) => format!("![{}|{}x{}]({})", alt, w, h, src),
+                _ => {
+                    let css = |name: &str, size: &Option<String>| {
+                        size.as_ref().map(|s| format!("{}: {}{}", name, s, if s.ends_with('%') { "" } else { "px" }))
+                    };
+                    let style: Vec<String> = [css("width", &width), css("height", &height)].into_iter().flatten().collect();
+                    format!(r#"<img src="{}" alt="{}" style="{}">"#, escape::html(src), escape::html(alt), style.join("; "))
+                }
+            }
+        })
+        .into_owned()
+}
+
+/// Emphasis Logseq renders but CommonMark (Quartz) doesn't, as HTML tags:
+/// a delimiter with punctuation inside and a letter outside (`**Note:**text`,
+/// `中文**「强调」**中文`) fails CommonMark's flanking rules, and `_` never
+/// opens inside a word (`中文_斜体_中文`). Emphasis that already renders, and
+/// `_` between ASCII letters or digits (`snake_case_name`), is left alone.
+fn fix_emphasis(content: &str) -> String {
+    let mut result = content.to_string();
+    for (marker, re, tag) in EMPHASIS_RES.iter() {
+        let text = result.clone();
+        result = re
+            .replace_all(&text, |caps: &Captures| {
+                let whole = caps.get(0).unwrap();
+                let inner = &caps[1];
+                let before = text[..whole.start()].chars().next_back();
+                let after = text[whole.end()..].chars().next();
+                let touches_marker = before == Some(*marker) || after == Some(*marker);
+                let padded = inner.starts_with(char::is_whitespace) || inner.ends_with(char::is_whitespace);
+                let intraword_ascii = *marker == '_'
+                    && (before.is_some_and(|c| c.is_ascii_alphanumeric()) || after.is_some_and(|c| c.is_ascii_alphanumeric()));
+                if touches_marker || padded || intraword_ascii || commonmark_emphasis(*marker, before, inner, after) {
+                    return whole.as_str().to_string();
+                }
+                format!("<{}>{}</{}>", tag, inner, tag)
+            })
+            .into_owned();
+    }
+    result
+}
+
+/// Would CommonMark render this delimiter pair as emphasis (flanking rules)?
+fn commonmark_emphasis(marker: char, before: Option<char>, inner: &str, after: Option<char>) -> bool {
+    let is_punct = |c: char| !c.is_alphanumeric() && !c.is_whitespace();
+    let space_or_punct = |c: Option<char>| c.is_none_or(|c| c.is_whitespace() || is_punct(c));
+    let (first, last) = (inner.chars().next(), inner.chars().next_back());
+
+    let open_left = !space_or_punct(first) || (first.is_some_and(is_punct) && space_or_punct(before));
+    let open_right = !before.is_none_or(char::is_whitespace) && (!before.is_some_and(is_punct) || space_or_punct(first));
+    let close_right = !space_or_punct(last) || (last.is_some_and(is_punct) && space_or_punct(after));
+    let close_left = !after.is_none_or(char::is_whitespace) && (!after.is_some_and(is_punct) || space_or_punct(last));
+
+    if marker == '_' {
+        open_left && (!open_right || before.is_some_and(is_punct)) && close_right && (!close_left || after.is_some_and(is_punct))
+    } else {
+        open_left && close_right
+    }
+}
+
+/// Names of `{{macro}}` calls still in the converted outline, outside code blocks
+fn unknown_macros(outline: &Outline) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut in_code = false;
+    let blocks = outline.iter().flat_map(|block| &block.lines);
+    for line in outline.preamble.iter().chain(blocks) {
+        if outline::is_fence(line) {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        for caps in MACRO_RE.captures_iter(line) {
+            let name = caps[1].to_string();
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
 /// Check if a line is a markdown table separator row (only |, -, :, spaces)
 fn is_separator_row(line: &str) -> bool {
     let trimmed = line.trim();
@@ -785,102 +1360,3 @@ fn get_continuation_prefix(first_prefix: &str) -> String {
         first_prefix.to_string()
     }
 }
-
-/// Find the best matching page for a wikilink using alias and prefix matching
-/// Handles:
-/// 1. Exact page name match
-/// 2. Exact alias match (e.g., "cv/districts" matches page with alias "cv/districts")
-/// 3. Namespace alias expansion (e.g., "cv/districts" → "cyber valley/districts" if "cv" is alias for "cyber valley")
-/// 4. Prefix matching (e.g., "visit us" matches "visit" if "visit us" doesn't exist)
-fn find_best_page_match<'a>(link: &'a str, page_index: &[crate::page::Page]) -> &'a str {
-    let link_lower = link.to_lowercase();
-    let link_normalized = link_lower.replace(' ', "-").replace('_', "-");
-
-    // 1. Check for exact page name match
-    for page in page_index {
-        let page_name = page.name.to_lowercase();
-        let page_normalized = page_name.replace(' ', "-").replace('_', "-");
-
-        if page_name == link_lower || page_normalized == link_normalized {
-            return link; // Exact match, return original
-        }
-    }
-
-    // 2. Check for exact alias match
-    for page in page_index {
-        for alias in &page.aliases {
-            let alias_lower = alias.to_lowercase();
-            let alias_normalized = alias_lower.replace(' ', "-").replace('_', "-");
-
-            if alias_lower == link_lower || alias_normalized == link_normalized {
-                // Found alias match - return the page name
-                return Box::leak(page.name.clone().into_boxed_str());
-            }
-        }
-    }
-
-    // 3. Namespace alias expansion: if link is "prefix/suffix", check if "prefix" is an alias
-    if link.contains('/') {
-        let parts: Vec<&str> = link.splitn(2, '/').collect();
-        if parts.len() == 2 {
-            let prefix = parts[0];
-            let suffix = parts[1];
-            let prefix_lower = prefix.to_lowercase();
-
-            // Find what page "prefix" is an alias for
-            for page in page_index {
-                for alias in &page.aliases {
-                    if alias.to_lowercase() == prefix_lower {
-                        // Found: prefix is alias for page.name
-                        // Now look for "page.name/suffix"
-                        let expanded_link = format!("{}/{}", page.name, suffix);
-                        let expanded_lower = expanded_link.to_lowercase();
-
-                        // Check if expanded link matches any page
-                        for target_page in page_index {
-                            let target_name = target_page.name.to_lowercase();
-                            if target_name == expanded_lower {
-                                return Box::leak(target_page.name.clone().into_boxed_str());
-                            }
-                            // Also check aliases of target page
-                            for target_alias in &target_page.aliases {
-                                if target_alias.to_lowercase() == link_lower {
-                                    return Box::leak(target_page.name.clone().into_boxed_str());
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    // 4. Prefix matching: "visit us" matches "visit" if "visit" exists
-    let mut best_match: Option<&str> = None;
-    let mut best_len = 0;
-
-    let link_words = link_lower.replace('-', " ").replace('_', " ");
-
-    for page in page_index {
-        let page_name = page.name.to_lowercase();
-        let page_words = page_name.replace('-', " ").replace('_', " ");
-
-        // Check if link starts with page name followed by a space
-        if link_words.len() > page_words.len()
-            && link_words.starts_with(&page_words)
-            && link_words.chars().nth(page_words.len()) == Some(' ')
-        {
-            if page_words.len() > best_len {
-                best_len = page_words.len();
-                best_match = Some(&page.name);
-            }
-        }
-    }
-
-    // Return the best match or original link
-    if let Some(matched) = best_match {
-        Box::leak(matched.to_string().into_boxed_str())
-    } else {
-        link
-    }
-}
@@ -0,0 +1,180 @@
+use regex::Regex;
+
+/// Nesting deeper than this isn't anything anyone wrote by hand; give up instead of recursing
+const MAX_DEPTH: usize = 64;
+
+/// An EDN form, as far as hiccup and config.edn settings need
+#[derive(Debug, Clone, PartialEq)]
+pub enum Form {
+    /// Vectors and sets
+    Vector(Vec<Form>),
+    List(Vec<Form>),
+    Map(Vec<(Form, Form)>),
+    Str(String),
+    Keyword(String),
+    /// Numbers, symbols, `true`/`false`/`nil`, characters
+    Atom(String),
+}
+
+impl Form {
+    /// Value of a keyword key in a map (`:title` in `{:title "x"}`)
+    pub fn get(&self, key: &str) -> Option<&Form> {
+        match self {
+            Form::Map(pairs) => pairs
+                .iter()
+                .find(|(k, _)| matches!(k, Form::Keyword(k) if k == key))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Text of a string or keyword
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Form::Str(s) | Form::Keyword(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Items of a vector, set or list (nothing for other forms)
+    pub fn items(&self) -> &[Form] {
+        match self {
+            Form::Vector(items) | Form::List(items) => items,
+            _ => &[],
+        }
+    }
+}
+
+/// Read the form at the start of `src`. Returns the form and the number of
+/// bytes it takes, or `None` if `src` doesn't start with a complete form.
+pub fn read(src: &str) -> Option<(Form, usize)> {
+    let mut reader = Reader { src, pos: 0 };
+    let form = reader.form(0)?;
+    Some((form, reader.pos))
+}
+
+/// Value of a setting in config.edn (`:hidden` → `["/archive"]`), skipping
+/// commented-out lines. The first occurrence that reads as a form wins.
+pub fn setting(config: &str, key: &str) -> Option<Form> {
+    let config: String = config
+        .lines()
+        .map(|line| if line.trim_start().starts_with(';') { "" } else { line })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let key_re = Regex::new(&format!(r"(?:^|[\s{{,]){}\s", regex::escape(key))).ok()?;
+    let value = key_re
+        .find_iter(&config)
+        .find_map(|m| read(&config[m.end()..]).map(|(form, _)| form));
+    value
+}
+
+struct Reader<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    /// Skip whitespace, commas (whitespace in EDN) and `;` comments
+    fn skip_space(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || c == ',' {
+                self.bump();
+            } else if c == ';' {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.bump();
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn form(&mut self, depth: usize) -> Option<Form> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        self.skip_space();
+        match self.peek()? {
+            '[' => self.seq(']', depth).map(Form::Vector),
+            '(' => self.seq(')', depth).map(Form::List),
+            '{' => {
+                let items = self.seq('}', depth)?;
+                if items.len() % 2 != 0 {
+                    return None;
+                }
+                let mut items = items.into_iter();
+                let mut pairs = Vec::new();
+                while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                    pairs.push((key, value));
+                }
+                Some(Form::Map(pairs))
+            }
+            // Sets read as vectors: order doesn't matter to anything read here
+            '#' if self.src[self.pos + 1..].starts_with('{') => {
+                self.bump();
+                self.seq('}', depth).map(Form::Vector)
+            }
+            '"' => self.string().map(Form::Str),
+            ':' => {
+                self.bump();
+                let name = self.token();
+                (!name.is_empty()).then_some(Form::Keyword(name))
+            }
+            ']' | ')' | '}' => None,
+            _ => {
+                let atom = self.token();
+                (!atom.is_empty()).then_some(Form::Atom(atom))
+            }
+        }
+    }
+
+    /// Forms up to the closing delimiter (the opening one is at `pos`)
+    fn seq(&mut self, close: char, depth: usize) -> Option<Vec<Form>> {
+        self.bump();
+        let mut items = Vec::new();
+        loop {
+            self.skip_space();
+            if self.peek()? == close {
+                self.bump();
+                return Some(items);
+            }
+            items.push(self.form(depth + 1)?);
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.bump();
+        let mut text = String::new();
+        loop {
+            match self.bump()? {
+                '"' => return Some(text),
+                '\\' => match self.bump()? {
+                    'n' => text.push('\n'),
+                    't' => text.push('\t'),
+                    c => text.push(c),
+                },
+                c => text.push(c),
+            }
+        }
+    }
+
+    /// A symbol, number or keyword name: up to whitespace or a delimiter
+    fn token(&mut self) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| !c.is_whitespace() && !"[](){}\",;".contains(c)) {
+            self.bump();
+        }
+        self.src[start..self.pos].to_string()
+    }
+}
+
@@ -0,0 +1,119 @@
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+use crate::escape;
+
+lazy_static! {
+    // {{video url}}, {{youtube url}} and {{vimeo url}}
+    static ref VIDEO_MACRO_RE: Regex = Regex::new(r"\{\{(?:video|youtube|vimeo)\s+([^\}]+?)\s*\}\}").unwrap();
+
+    // {{tweet url}} and {{twitter url}}
+    static ref TWEET_MACRO_RE: Regex = Regex::new(r"\{\{(?:tweet|twitter)\s+([^\}]+?)\s*\}\}").unwrap();
+
+    // twitter.com/user/status/123 or x.com/user/status/123 (groups: user, post id)
+    static ref TWEET_URL_RE: Regex = Regex::new(
+        r"^(?:https?://)?(?:www\.|mobile\.)?(?:twitter|x)\.com/(\w+)/status(?:es)?/(\d+)"
+    ).unwrap();
+
+    // YouTube watch, short, live, embed and youtu.be URLs (group 1: video id)
+    static ref YOUTUBE_URL_RE: Regex = Regex::new(
+        r"^(?:https?://)?(?:www\.|m\.)?(?:youtube(?:-nocookie)?\.com/(?:watch\?(?:[^#]*&)?v=|embed/|shorts/|live/|v/)|youtu\.be/)([\w-]{11})"
+    ).unwrap();
+
+    // A bare YouTube id: {{youtube dQw4w9WgXcQ}}
+    static ref YOUTUBE_ID_RE: Regex = Regex::new(r"^[\w-]{11}$").unwrap();
+
+    // vimeo.com/123, player.vimeo.com/video/123 (group 1: video id)
+    static ref VIMEO_URL_RE: Regex = Regex::new(r"^(?:https?://)?(?:www\.|player\.)?vimeo\.com/(?:video/)?(\d+)").unwrap();
+
+    // Start time in a video URL: t=90, t=1m30s, start=90
+    static ref START_RE: Regex = Regex::new(r"[?&#](?:t|start)=(?:(\d+)h)?(?:(\d+)m)?(?:(\d+)s?)?").unwrap();
+}
+
+/// Video files played with `<video>` rather than embedded from a provider
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "webm", "ogv", "ogg", "mov", "m4v"];
+
+/// How third-party embeds (YouTube, Vimeo, tweets) are rendered (`--embeds`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum EmbedStyle {
+    /// Player or post iframe
+    #[default]
+    Iframe,
+    /// A link only: nothing is loaded from the provider until it is clicked
+    Link,
+}
+
+/// Convert embed macros: `{{video}}`, `{{youtube}}` and `{{vimeo}}` (see
+/// [`video`]), and `{{tweet}}`/`{{twitter}}` (see [`tweet`])
+pub fn convert(content: &str, style: EmbedStyle) -> String {
+    let content = VIDEO_MACRO_RE.replace_all(content, |caps: &Captures| video(&caps[1], style));
+    TWEET_MACRO_RE
+        .replace_all(&content, |caps: &Captures| tweet(&caps[1], style))
+        .into_owned()
+}
+
+/// Markup for one video URL or asset path: video files (local assets or
+/// remote) become `<video controls>`, YouTube and Vimeo links a
+/// privacy-enhanced player iframe (youtube-nocookie.com, Vimeo `dnt=1`) or,
+/// with [`EmbedStyle::Link`], a link. Other URLs keep image syntax, for
+/// providers Quartz embeds itself.
+pub fn video(url: &str, style: EmbedStyle) -> String {
+    let youtube_id = match YOUTUBE_URL_RE.captures(url) {
+        Some(caps) => Some(caps[1].to_string()),
+        None => YOUTUBE_ID_RE.is_match(url).then(|| url.to_string()),
+    };
+    if let Some(id) = youtube_id {
+        if style == EmbedStyle::Link {
+            let watch = if url == id { format!("https://www.youtube.com/watch?v={}", id) } else { url.to_string() };
+            return format!("[▶ YouTube video]({})", watch);
+        }
+        let start = start_seconds(url).map_or(String::new(), |s| format!("?start={}", s));
+        return iframe(&format!("https://www.youtube-nocookie.com/embed/{}{}", id, start));
+    }
+    if let Some(caps) = VIMEO_URL_RE.captures(url) {
+        if style == EmbedStyle::Link {
+            return format!("[▶ Vimeo video]({})", url);
+        }
+        let start = start_seconds(url).map_or(String::new(), |s| format!("#t={}s", s));
+        return iframe(&format!("https://player.vimeo.com/video/{}?dnt=1{}", &caps[1], start));
+    }
+    if is_video_file(url) {
+        return format!(r#"<video controls preload="metadata" src="{}" style="width: 100%;"></video>"#, escape::html(url));
+    }
+    format!("![{}]({})", url, url)
+}
+
+/// Markup for a tweet (`twitter.com` or `x.com` post URL): the post's embed
+/// iframe (with `dnt=true`) or, with [`EmbedStyle::Link`] or a URL that isn't
+/// a post, a link naming the author
+pub fn tweet(url: &str, style: EmbedStyle) -> String {
+    match TWEET_URL_RE.captures(url) {
+        Some(caps) if style == EmbedStyle::Iframe => format!(
+            r#"<iframe src="https://platform.twitter.com/embed/Tweet.html?id={}&amp;dnt=true" width="100%" style="max-width: 550px; height: 500px; border: 0;"></iframe>"#,
+            &caps[2]
+        ),
+        Some(caps) => format!("[𝕏 Post by @{}]({})", &caps[1], url),
+        None => format!("[𝕏 Post]({})", url),
+    }
+}
+
+fn iframe(src: &str) -> String {
+    format!(
+        r#"<iframe src="{}" width="100%" style="aspect-ratio: 16 / 9; border: 0; border-radius: 4px;" allow="fullscreen; picture-in-picture" allowfullscreen></iframe>"#,
+        escape::html(src)
+    )
+}
+
+fn is_video_file(url: &str) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.rsplit_once('.')
+        .is_some_and(|(_, ext)| VIDEO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Start time in seconds from `t=`/`start=` (`90`, `90s`, `1m30s`, `1h2m3s`)
+fn start_seconds(url: &str) -> Option<u64> {
+    let caps = START_RE.captures(url)?;
+    let part = |i: usize| caps.get(i).and_then(|m| m.as_str().parse::<u64>().ok()).unwrap_or(0);
+    let seconds = part(1) * 3600 + part(2) * 60 + part(3);
+    (seconds > 0).then_some(seconds)
+}
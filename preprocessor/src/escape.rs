@@ -0,0 +1,72 @@
+/// Escape text for a double-quoted YAML scalar (without the quotes)
+pub fn yaml(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Double-quoted YAML scalar
+pub fn yaml_quoted(s: &str) -> String {
+    format!("\"{}\"", yaml(s))
+}
+
+/// YAML scalar for list items: left bare when that's unambiguous, quoted otherwise
+/// (leading indicators like `%`, `>`, `&`, `*`, `!`, or `: ` / ` #` inside)
+pub fn yaml_scalar(s: &str) -> String {
+    const INDICATORS: &[char] = &[
+        '-', '?', ':', ',', '[', ']', '{', '}', '#', '&', '*', '!', '|', '>', '\'', '"', '%', '@', '`',
+    ];
+    let needs_quotes = s.is_empty()
+        || s.starts_with(INDICATORS)
+        || s.starts_with(' ')
+        || s.ends_with(' ')
+        || s.ends_with(':')
+        || s.contains(": ")
+        || s.contains(" #")
+        || s.chars().any(|c| c.is_control())
+        || matches!(s.to_lowercase().as_str(), "true" | "false" | "yes" | "no" | "null" | "~");
+    if needs_quotes {
+        yaml_quoted(s)
+    } else {
+        s.to_string()
+    }
+}
+
+/// Escape text for HTML content and attribute values
+pub fn html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escape plain text placed in generated markdown so `<` / `>` aren't read as HTML tags
+pub fn markdown_text(s: &str) -> String {
+    s.replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Escape a markdown table cell: pipes, angle brackets and line breaks
+pub fn table_cell(s: &str) -> String {
+    markdown_text(s)
+        .replace('|', "&#124;")
+        .replace("\r\n", " ")
+        .replace('\n', " ")
+}
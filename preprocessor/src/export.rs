@@ -0,0 +1,201 @@
+use anyhow::Result;
+use base64::Engine;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use std::fs;
+use std::path::Path;
+
+use crate::content;
+use crate::escape;
+use crate::page::{self, Page, PageIndex};
+use crate::resolver;
+
+lazy_static! {
+    // Wikilinks left by content::transform (embeds render as links in the archive)
+    static ref WIKILINK_RE: Regex = Regex::new(r"!?\[\[([^\]|]+)(?:\|([^\]]*))?\]\]").unwrap();
+
+    // HTML anchors emitted for $ pages
+    static ref INTERNAL_ANCHOR_RE: Regex = Regex::new(r#"<a href="([^"]+)" class="internal[^"]*"[^>]*>"#).unwrap();
+
+    // Asset references in rendered HTML (src="../assets/x.png", href="assets/doc.pdf")
+    static ref ASSET_ATTR_RE: Regex = Regex::new(r#"(src|href)="(?:\.\./)?(assets/[^"]+)""#).unwrap();
+}
+
+/// Export the transformed graph (or one namespace) as a single self-contained HTML file.
+/// Returns the number of pages written.
+pub fn single_html(
+    input_dir: &Path,
+    output_file: &Path,
+    namespace: Option<&str>,
+    include_private: bool,
+) -> Result<usize> {
+    let mut index = page::build_graph_index(input_dir)?;
+    if !include_private {
+        index = page::drop_private_blocks(index);
+    }
+
+    let mut selected: Vec<&Page> = index
+        .iter()
+        .filter(|p| include_private || !p.is_private())
+        .filter(|p| in_namespace(p, namespace))
+        .collect();
+
+    // Pages alphabetically, then journals newest first
+    selected.sort_by(|a, b| {
+        let a_journal = a.name.starts_with("journals/");
+        let b_journal = b.name.starts_with("journals/");
+        a_journal
+            .cmp(&b_journal)
+            .then_with(|| if a_journal { b.name.cmp(&a.name) } else { a.name_lower.cmp(&b.name_lower) })
+    });
+
+    let exported: Vec<String> = selected.iter().map(|p| p.name_lower.clone()).collect();
+    let assets_dir = input_dir.join("assets");
+
+    let mut toc = String::new();
+    let mut sections = String::new();
+    for page in &selected {
+        let title = page_title(page);
+        let id = anchor_id(&page.name);
+        toc.push_str(&format!("<li><a href=\"#{}\">{}</a></li>\n", id, escape::html(&title)));
+
+        let body = render_page(page, &index, &exported);
+        let body = inline_assets(&body, &assets_dir);
+        sections.push_str(&format!(
+            "<section id=\"{}\">\n<h1>{}</h1>\n{}</section>\n",
+            id,
+            escape::html(&title),
+            body
+        ));
+    }
+
+    let doc_title = namespace.unwrap_or("Logseq export");
+    let html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<nav>\n<h1>{}</h1>\n<ul>\n{}</ul>\n</nav>\n<main>\n{}</main>\n</body>\n</html>\n",
+        escape::html(doc_title),
+        STYLE,
+        escape::html(doc_title),
+        toc,
+        sections
+    );
+
+    if let Some(parent) = output_file.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    fs::write(output_file, html)?;
+
+    Ok(selected.len())
+}
+
+/// Transform a page and render it to HTML with links pointing at in-document anchors
+fn render_page(page: &Page, index: &PageIndex, exported: &[String]) -> String {
+    let (_, body) = page::parse_properties(&page.content);
+    let markdown = content::transform(&body, index);
+
+    // Internal links become in-document anchors (or plain text if not exported)
+    let markdown = WIKILINK_RE.replace_all(&markdown, |caps: &Captures| {
+        let target = caps[1].trim();
+        let display = caps.get(2).map_or(target, |m| m.as_str());
+        link_html(target, display, exported)
+    });
+    let markdown = INTERNAL_ANCHOR_RE.replace_all(&markdown, |caps: &Captures| {
+        if exported.contains(&caps[1].to_lowercase()) {
+            format!("<a href=\"#{}\">", anchor_id(&caps[1]))
+        } else {
+            "<a>".to_string()
+        }
+    });
+
+    let mut options = pulldown_cmark::Options::empty();
+    options.insert(pulldown_cmark::Options::ENABLE_TABLES);
+    options.insert(pulldown_cmark::Options::ENABLE_STRIKETHROUGH);
+    options.insert(pulldown_cmark::Options::ENABLE_TASKLISTS);
+    let parser = pulldown_cmark::Parser::new_ext(&markdown, options);
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, parser);
+    html
+}
+
+/// Link to an exported page, or a marked span for pages outside the archive
+fn link_html(target: &str, display: &str, exported: &[String]) -> String {
+    if exported.contains(&target.to_lowercase()) {
+        format!("<a href=\"#{}\">{}</a>", anchor_id(target), escape::html(display))
+    } else {
+        format!("<span class=\"missing\">{}</span>", escape::html(display))
+    }
+}
+
+/// Replace asset references with base64 data URIs
+fn inline_assets(html: &str, assets_dir: &Path) -> String {
+    ASSET_ATTR_RE
+        .replace_all(html, |caps: &Captures| {
+            let attr = &caps[1];
+            let relative = resolver::decode(caps[2].trim_start_matches("assets/"));
+            let path = resolver::join_site_path(assets_dir, &relative);
+            match fs::read(&path) {
+                Ok(bytes) => format!(
+                    "{}=\"data:{};base64,{}\"",
+                    attr,
+                    mime_type(&path),
+                    base64::engine::general_purpose::STANDARD.encode(bytes)
+                ),
+                Err(_) => caps[0].to_string(),
+            }
+        })
+        .to_string()
+}
+
+/// MIME type from a file extension (common asset types)
+fn mime_type(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Page title from title:: property or page name
+fn page_title(page: &Page) -> String {
+    page.properties
+        .get("title")
+        .cloned()
+        .unwrap_or_else(|| page.name.trim_start_matches("journals/").to_string())
+}
+
+/// Stable in-document anchor for a page name
+fn anchor_id(name: &str) -> String {
+    let slug: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+    format!("page-{}", slug)
+}
+
+/// Namespace filter: the namespace page itself and everything below it
+fn in_namespace(page: &Page, namespace: Option<&str>) -> bool {
+    match namespace {
+        None => true,
+        Some(ns) => {
+            let ns = ns.to_lowercase();
+            page.name_lower == ns || page.name_lower.starts_with(&format!("{}/", ns))
+        }
+    }
+}
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:52rem;margin:0 auto;padding:1rem;line-height:1.5}\
+nav ul{columns:2}section{border-top:1px solid #ccc;margin-top:2rem}img{max-width:100%}\
+table{border-collapse:collapse}td,th{border:1px solid #ccc;padding:.25rem .5rem}.missing{color:#888}";
@@ -0,0 +1,288 @@
+use anyhow::Result;
+use chrono::{Local, NaiveDate};
+use lazy_static::lazy_static;
+use rayon::prelude::*;
+use regex::{Captures, Regex};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::outline;
+use crate::page::{self, PageIndex};
+
+/// Titles and descriptions of the site's external links (`--link-titles`)
+pub const LINK_PREVIEWS_FILE: &str = "_link_previews.json";
+
+/// Fetch cache, relative to the graph root, when `--link-cache` isn't given
+pub const DEFAULT_CACHE: &str = "logseq/link-cache.json";
+
+/// Days before a URL whose fetch found no title is tried again
+const RETRY_DAYS: i64 = 7;
+
+/// Bytes of a page read for its metadata (titles live in `<head>`)
+const MAX_BODY: u64 = 256 * 1024;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+lazy_static! {
+    // Any http(s) URL
+    static ref URL_RE: Regex = Regex::new(r#"https?://[^\s<>"'`\[\]{}|\\^]+"#).unwrap();
+
+    // A bare URL: at the start of a line or after whitespace, so not the target of
+    // [label](url), <url>, an HTML attribute or a wikilink (groups: lead, url)
+    static ref BARE_URL_RE: Regex = Regex::new(r#"(?m)(^|[ \t])(https?://[^\s<>"'`\[\]{}|\\^]+)"#).unwrap();
+
+    static ref INLINE_CODE_RE: Regex = Regex::new(r"`[^`\n]+`").unwrap();
+
+    // HTML metadata
+    static ref META_RE: Regex = Regex::new(r"(?is)<meta\s[^>]*>").unwrap();
+    static ref META_ATTR_RE: Regex = Regex::new(r#"(?is)([\w:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap();
+    static ref TITLE_RE: Regex = Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap();
+    static ref ENTITY_RE: Regex = Regex::new(r"&(#[xX][0-9a-fA-F]+|#\d+|amp|lt|gt|quot|apos|nbsp);").unwrap();
+}
+
+/// What a linked page says about itself (`og:title`, `<title>`, `og:description`, `og:image`)
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LinkPreview {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct CacheEntry {
+    #[serde(flatten)]
+    preview: LinkPreview,
+    /// `YYYY-MM-DD` of the fetch
+    fetched: String,
+}
+
+/// Fetched link metadata kept between builds, so each URL is fetched once
+/// (pages without a title are retried after a week)
+#[derive(Debug, Clone, Default)]
+pub struct LinkCache {
+    path: PathBuf,
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+impl LinkCache {
+    /// Load the cache; a missing or unreadable file starts an empty one
+    pub fn load(path: &Path) -> Self {
+        let entries = fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        LinkCache { path: path.to_path_buf(), entries }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.entries)?)?;
+        Ok(())
+    }
+
+    /// Cached metadata for a URL, if it was fetched before
+    pub fn get(&self, url: &str) -> Option<&LinkPreview> {
+        self.entries.get(url).map(|entry| &entry.preview)
+    }
+
+    /// Not cached yet, or fetched without finding a title more than `RETRY_DAYS` ago
+    fn needs_fetch(&self, url: &str, today: NaiveDate) -> bool {
+        match self.entries.get(url) {
+            None => true,
+            Some(entry) if entry.preview.title.is_some() => false,
+            Some(entry) => NaiveDate::parse_from_str(&entry.fetched, "%Y-%m-%d")
+                .map_or(true, |date| (today - date).num_days() >= RETRY_DAYS),
+        }
+    }
+
+    fn insert(&mut self, url: String, preview: LinkPreview, today: NaiveDate) {
+        self.entries.insert(url, CacheEntry { preview, fetched: today.format("%Y-%m-%d").to_string() });
+    }
+}
+
+/// External URLs in page content, outside code
+pub fn urls(content: &str) -> Vec<String> {
+    let mut urls = Vec::new();
+    let mut in_code = false;
+    for line in content.lines() {
+        if outline::is_fence(line) {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        let line = INLINE_CODE_RE.replace_all(line, "");
+        urls.extend(URL_RE.find_iter(&line).map(|m| trim_url(m.as_str()).to_string()));
+    }
+    urls
+}
+
+/// All external URLs of the published pages and journals
+pub fn collect(index: &PageIndex, include_private: bool) -> BTreeSet<String> {
+    index
+        .iter()
+        .filter(|p| include_private || !p.is_private())
+        .flat_map(|p| urls(&page::parse_properties(&p.content).1))
+        .collect()
+}
+
+/// Metadata for each URL: from the cache, else fetched (in parallel) and
+/// added to it. With `offline` nothing is fetched and uncached URLs are left out.
+pub fn resolve(urls: &BTreeSet<String>, cache: &mut LinkCache, offline: bool) -> BTreeMap<String, LinkPreview> {
+    let today = Local::now().date_naive();
+    if !offline {
+        let agent = agent();
+        let missing: Vec<&String> = urls.iter().filter(|url| cache.needs_fetch(url, today)).collect();
+        let fetched: Vec<(String, LinkPreview)> = missing
+            .par_iter()
+            .map(|url| (url.to_string(), fetch(&agent, url).unwrap_or_default()))
+            .collect();
+        for (url, preview) in fetched {
+            cache.insert(url, preview, today);
+        }
+    }
+    urls.iter()
+        .filter_map(|url| cache.get(url).map(|preview| (url.clone(), preview.clone())))
+        .collect()
+}
+
+/// HTTP client for link fetches: one timeout for the whole request
+pub fn agent() -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .timeout_global(Some(TIMEOUT))
+        .user_agent(concat!("logseq-to-quartz/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .into()
+}
+
+/// Write `_link_previews.json` (URLs with a title). Returns the number of entries.
+pub fn write(output_dir: &Path, previews: &BTreeMap<String, LinkPreview>) -> Result<usize> {
+    let titled: BTreeMap<&String, &LinkPreview> = previews.iter().filter(|(_, p)| p.title.is_some()).collect();
+    fs::write(output_dir.join(LINK_PREVIEWS_FILE), serde_json::to_string(&titled)?)?;
+    Ok(titled.len())
+}
+
+/// Turn bare URLs into `[Title](url)` links, for URLs with a known title
+pub fn link_bare_urls(content: &str, previews: &BTreeMap<String, LinkPreview>) -> String {
+    if previews.is_empty() {
+        return content.to_string();
+    }
+    replace_bare_urls(content, |url| {
+        let title = previews.get(url)?.title.as_deref()?;
+        Some(format!("[{}]({})", escape_label(title), url))
+    })
+}
+
+/// Replace bare URLs (not link targets, attributes or `<url>`) with what
+/// `replace` returns for them; URLs it returns `None` for stay as they are
+pub fn replace_bare_urls(content: &str, replace: impl Fn(&str) -> Option<String>) -> String {
+    BARE_URL_RE
+        .replace_all(content, |caps: &Captures| {
+            let url = trim_url(&caps[2]);
+            let rest = &caps[2][url.len()..];
+            match replace(url) {
+                Some(replacement) => format!("{}{}{}", &caps[1], replacement, rest),
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Title, description and image from a page's `<head>`: Open Graph and
+/// Twitter tags first, then `<title>` and `<meta name="description">`
+pub fn parse(html: &str) -> LinkPreview {
+    let mut meta: BTreeMap<String, String> = BTreeMap::new();
+    for tag in META_RE.find_iter(html) {
+        let mut key = None;
+        let mut value = None;
+        for attr in META_ATTR_RE.captures_iter(tag.as_str()) {
+            let text = attr.get(2).or(attr.get(3)).map_or("", |v| v.as_str());
+            match attr[1].to_lowercase().as_str() {
+                "property" | "name" => key = Some(text.to_lowercase()),
+                "content" => value = Some(text.to_string()),
+                _ => {}
+            }
+        }
+        if let (Some(key), Some(value)) = (key, value) {
+            meta.entry(key).or_insert(value);
+        }
+    }
+    let first = |keys: &[&str]| {
+        keys.iter()
+            .filter_map(|k| meta.get(*k))
+            .map(|v| clean(v))
+            .find(|v| !v.is_empty())
+    };
+
+    LinkPreview {
+        title: first(&["og:title", "twitter:title"])
+            .or_else(|| TITLE_RE.captures(html).map(|caps| clean(&caps[1])).filter(|t| !t.is_empty())),
+        description: first(&["og:description", "twitter:description", "description"]),
+        image: first(&["og:image", "twitter:image"]),
+    }
+}
+
+fn fetch(agent: &ureq::Agent, url: &str) -> Option<LinkPreview> {
+    let mut response = agent.get(url).header("Accept", "text/html").call().ok()?;
+    let is_html = response
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("html"));
+    if !is_html {
+        return None;
+    }
+    let mut body = Vec::new();
+    response.body_mut().as_reader().take(MAX_BODY).read_to_end(&mut body).ok()?;
+    Some(parse(&String::from_utf8_lossy(&body)))
+}
+
+/// Drop sentence punctuation and unbalanced closing parentheses after a URL
+fn trim_url(url: &str) -> &str {
+    let mut url = url.trim_end_matches(['.', ',', ';', ':', '!', '?', '*', '_', '~']);
+    while url.ends_with(')') && url.matches(')').count() > url.matches('(').count() {
+        url = url[..url.len() - 1].trim_end_matches(['.', ',', ';', ':', '!', '?']);
+    }
+    url
+}
+
+/// Decode entities and collapse whitespace
+fn clean(text: &str) -> String {
+    let decoded = ENTITY_RE.replace_all(text, |caps: &Captures| {
+        let code = match &caps[1] {
+            "amp" => Some('&' as u32),
+            "lt" => Some('<' as u32),
+            "gt" => Some('>' as u32),
+            "quot" => Some('"' as u32),
+            "apos" => Some('\'' as u32),
+            "nbsp" => Some(' ' as u32),
+            num => match num.strip_prefix("#x").or_else(|| num.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => num[1..].parse().ok(),
+            },
+        };
+        code.and_then(char::from_u32).map_or(String::new(), String::from)
+    });
+    decoded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A fetched title as link text: markdown and LaTeX characters escaped
+fn escape_label(title: &str) -> String {
+    let mut out = String::with_capacity(title.len());
+    for c in title.chars() {
+        if matches!(c, '\\' | '[' | ']' | '*' | '_' | '`' | '$' | '|' | '<' | '>' | '~' | '=' | '^' | '#') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
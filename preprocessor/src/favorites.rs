@@ -1,9 +1,18 @@
 use anyhow::Result;
 use lazy_static::lazy_static;
 use regex::Regex;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
+use crate::config::Config;
+use crate::escape;
+use crate::icons::{Icon, IconMap};
+use crate::page::PageIndex;
+use crate::resolver;
+use crate::source;
+use crate::warnings;
+
 lazy_static! {
     // Match :favorites [...] in EDN
     static ref FAVORITES_RE: Regex = Regex::new(r":favorites\s+\[([\s\S]*?)\]").unwrap();
@@ -16,73 +25,144 @@ lazy_static! {
 
     // Match :meta/title "..." in EDN (optional site title)
     static ref SITE_TITLE_RE: Regex = Regex::new(r#":meta/title\s+"([^"]+)""#).unwrap();
+
+    // Match :preferred-language "..." and :journal/page-title-format "..." in EDN
+    static ref LANGUAGE_RE: Regex = Regex::new(r#":preferred-language\s+"([^"]+)""#).unwrap();
+    static ref JOURNAL_TITLE_FORMAT_RE: Regex = Regex::new(r#":journal/page-title-format\s+"([^"]+)""#).unwrap();
+
+    // Match :ui/...color... settings: :ui/radix-color :purple, :ui/accent-color "#6b4cd9"
+    static ref UI_COLOR_RE: Regex = Regex::new(r#":ui/([\w-]*color[\w-]*)\s+(?:"([^"]*)"|:?([\w#-]+))"#).unwrap();
+}
+
+/// Logseq's journal title format, used when config.edn doesn't set one
+const DEFAULT_DATE_FORMAT: &str = "MMM do, yyyy";
+
+/// Version of the `_site_config.json` schema. Bump on breaking changes
+/// (removed or retyped fields); adding optional fields keeps the version.
+pub const SITE_CONFIG_SCHEMA_VERSION: u32 = 2;
+
+/// Favorites manifest written to the output root (`--favorites-outputs json`)
+pub const FAVORITES_FILE: &str = "favorites.json";
+
+/// What favorites are published as (`--favorites-outputs`, any combination)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum FavoritesOutput {
+    /// A redirect page per favorite in favorites/, listed in the Explorer
+    Pages,
+    /// favorites/index.md, linking straight to the pages
+    Index,
+    /// `favorites.json`: the favorites in config order with icons and paths
+    Json,
 }
 
-/// Process favorites from logseq/config.edn or override list
+/// Default `--favorites-outputs`
+pub const DEFAULT_OUTPUTS: &[FavoritesOutput] = &[FavoritesOutput::Pages, FavoritesOutput::Index];
+
+/// One entry of `favorites.json`
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Favorite {
+    /// Name as listed in config.edn `:favorites`
+    pub name: String,
+    /// Output path of the page
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// Mapped icon asset (see `--icon-map`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon_src: Option<String>,
+    /// Mapped icon font class
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon_class: Option<String>,
+}
+
+/// Process favorites from logseq/config.edn or override list, writing the
+/// `outputs` asked for. Returns the number of favorites found.
 pub fn process_favorites(
     config_path: &Path,
     favorites_output: &Path,
     pages_output: &Path,
+    page_index: &PageIndex,
     favorites_override: Option<&Vec<String>>,
+    icons: &IconMap,
+    outputs: &[FavoritesOutput],
 ) -> Result<usize> {
-    // Use override if provided, otherwise extract from config.edn
-    let favorites = if let Some(overrides) = favorites_override {
-        overrides.clone()
-    } else {
-        let content = fs::read_to_string(config_path)?;
-        extract_favorites(&content)
-    };
+    let favorites = favorite_names(config_path, favorites_override)?;
     if favorites.is_empty() {
         return Ok(0);
     }
 
     // Create favorites index
     let mut index_content = String::from("---\ntitle: \"⭐ Favorites\"\n---\n\n");
+    let mut manifest = Vec::new();
 
-    let mut count = 0;
     for fav in &favorites {
-        // Check if page exists - try original name first, then with namespace separator
-        let page_path = if fav.contains('/') {
-            // Namespace page like "Projects/Web App" -> "Projects/Web App.md"
-            pages_output.join(format!("{}.md", fav))
-        } else {
-            pages_output.join(format!("{}.md", fav))
-        };
+        // Check if page exists at its output path (namespaces are folders, slug:: overrides)
+        let target = resolver::resolve_output_name(fav, page_index);
+        let page_path = resolver::output_path(pages_output, &target);
 
         if !page_path.exists() {
-            eprintln!("Favorite page not found: {:?}", page_path);
+            warnings::global().warn("favorite", &format!("favorite page not found: {}", fav), None);
             continue;
         }
 
         // Get icon from page if exists
         let icon = get_page_icon(&page_path).unwrap_or_default();
+        let mapped_icon = icons.get(&icon);
+        let label = if icon.is_empty() || mapped_icon.is_some() { fav.clone() } else { format!("{} {}", icon, fav) };
 
         // Create redirect file in favorites folder (shows in Explorer, redirects to actual page)
-        let slug = fav.to_lowercase().replace(' ', "-").replace('/', "-");
-        let fav_path = favorites_output.join(format!("{}.md", slug));
-        let fav_content = format!(
-            "---\ntitle: \"{}{}\"\nredirect: \"{}\"\n---\n",
-            if icon.is_empty() { String::new() } else { format!("{} ", icon) },
-            fav,
-            fav
-        );
-        fs::write(&fav_path, fav_content)?;
-
-        count += 1;
+        if outputs.contains(&FavoritesOutput::Pages) {
+            let slug = fav.to_lowercase().replace([' ', '/'], "-");
+            let fav_path = favorites_output.join(format!("{}.md", slug));
+            let mut fav_content = format!(
+                "---\ntitle: {}\nredirect: {}\n",
+                escape::yaml_quoted(&label),
+                escape::yaml_quoted(&target)
+            );
+            if let Some(mapped) = mapped_icon {
+                let (key, value) = mapped.frontmatter_field();
+                fav_content.push_str(&format!("icon: {}\n{}: {}\n", escape::yaml_quoted(&icon), key, escape::yaml_quoted(value)));
+            }
+            fav_content.push_str("---\n");
+            fs::write(&fav_path, fav_content)?;
+        }
 
         // Add to index - link directly to the actual page (like Logseq does)
-        index_content.push_str(&format!(
-            "- [[{}|{}{}]]\n",
-            fav,
-            if icon.is_empty() { String::new() } else { format!("{} ", icon) },
-            fav
-        ));
+        let prefix = mapped_icon.map(|m| format!("{} ", m.html(&icon))).unwrap_or_default();
+        index_content.push_str(&format!("- {}[[{}|{}]]\n", prefix, target, escape::markdown_text(&label)));
+
+        manifest.push(Favorite {
+            name: fav.clone(),
+            path: target,
+            icon_src: match mapped_icon {
+                Some(Icon::Asset { src, .. }) => Some(src.clone()),
+                _ => None,
+            },
+            icon_class: match mapped_icon {
+                Some(Icon::Class(class)) => Some(class.clone()),
+                _ => None,
+            },
+            icon: Some(icon).filter(|i| !i.is_empty()),
+        });
+    }
+
+    if outputs.contains(&FavoritesOutput::Index) {
+        fs::write(favorites_output.join("index.md"), index_content)?;
+    }
+    if outputs.contains(&FavoritesOutput::Json) {
+        fs::write(pages_output.join(FAVORITES_FILE), serde_json::to_string_pretty(&manifest)?)?;
     }
 
-    // Write index
-    fs::write(favorites_output.join("index.md"), index_content)?;
+    Ok(manifest.len())
+}
 
-    Ok(count)
+/// Favorite page names: the override list if provided, otherwise config.edn :favorites
+pub fn favorite_names(config_path: &Path, favorites_override: Option<&Vec<String>>) -> Result<Vec<String>> {
+    if let Some(overrides) = favorites_override {
+        return Ok(overrides.clone());
+    }
+    let content = source::read_to_string(config_path)?;
+    Ok(extract_favorites(&content))
 }
 
 /// Extract favorites from config.edn content
@@ -103,7 +183,7 @@ fn extract_favorites(content: &str) -> Vec<String> {
 
 /// Get icon from page frontmatter or properties
 fn get_page_icon(page_path: &Path) -> Option<String> {
-    let content = fs::read_to_string(page_path).ok()?;
+    let content = source::read_to_string(page_path).ok()?;
 
     // Check for icon:: property
     for line in content.lines().take(20) {
@@ -122,7 +202,7 @@ fn get_page_icon(page_path: &Path) -> Option<String> {
 /// Extract default home page from config.edn
 /// Returns the page name from :default-home {:page "..."}
 pub fn get_default_home(config_path: &Path) -> Option<String> {
-    let content = fs::read_to_string(config_path).ok()?;
+    let content = source::read_to_string(config_path).ok()?;
 
     // Process line by line to skip comments
     for line in content.lines() {
@@ -143,7 +223,7 @@ pub fn get_default_home(config_path: &Path) -> Option<String> {
 /// Extract site title from config.edn
 /// Tries :meta/title first, then falls back to :default-home page name
 pub fn get_site_title(config_path: &Path) -> Option<String> {
-    let content = fs::read_to_string(config_path).ok()?;
+    let content = source::read_to_string(config_path).ok()?;
 
     // Process line by line to skip comments
     for line in content.lines() {
@@ -162,13 +242,162 @@ pub fn get_site_title(config_path: &Path) -> Option<String> {
     get_default_home(config_path)
 }
 
-/// Site configuration extracted from Logseq config
-#[derive(serde::Serialize)]
+/// First match of a string setting in config.edn, skipping commented lines
+fn find_setting(config_path: &Path, re: &Regex) -> Option<String> {
+    let content = source::read_to_string(config_path).ok()?;
+    content
+        .lines()
+        .filter(|line| !line.trim().starts_with(';'))
+        .find_map(|line| re.captures(line).map(|caps| caps[1].to_string()))
+}
+
+/// Site locale: config.edn `:preferred-language`, default `en-US`
+pub fn locale(config_path: &Path) -> String {
+    find_setting(config_path, &LANGUAGE_RE).unwrap_or_else(|| "en-US".to_string())
+}
+
+/// Site configuration written to `_site_config.json` for the Quartz config generator
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
 pub struct SiteConfig {
+    /// Schema version of this file (see `SITE_CONFIG_SCHEMA_VERSION`)
+    pub schema_version: u32,
+    /// Site title (`--title`, config.edn `:meta/title`, or the home page name)
     pub page_title: String,
+    /// Page shown at the site root (`--home` or config.edn `:default-home`)
     pub home_page: String,
+    /// Site name for meta tags (`--site-name`)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub site_name: Option<String>,
+    /// Site locale (config.edn `:preferred-language`, default `en-US`)
+    pub locale: String,
+    /// Format of displayed dates (config.edn `:journal/page-title-format`, default `MMM do, yyyy`)
+    pub date_format: String,
+    /// Theme colors from config.edn `:ui/*color*` settings, keyed without the
+    /// `ui/` prefix (`:ui/radix-color :purple` → `radix-color: purple`)
+    pub theme: BTreeMap<String, String>,
+    #[serde(flatten)]
+    pub context: SiteContext,
+}
+
+/// Parts of the site config that come from the build rather than config.edn
+#[derive(Debug, Default, serde::Serialize, schemars::JsonSchema)]
+pub struct SiteContext {
+    /// Published site URL (`--base-url`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Whether the site should load analytics (off with `--no-analytics`)
+    pub analytics: bool,
+    /// Namespace tree of published pages
+    pub navigation: Vec<NavNode>,
+    /// Favorite page names, in config order
+    pub favorites: Vec<String>,
+    pub journals: JournalsInfo,
+    pub features: Features,
+}
+
+/// Node in the navigation tree (one namespace level)
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, schemars::JsonSchema)]
+pub struct NavNode {
+    /// Last segment of the page name
+    pub title: String,
+    /// Output path of the page, if a page exists at this level
+    /// (in `sidebar.json`, also the URL of an external link)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<NavNode>,
+}
+
+/// Journal settings
+#[derive(Debug, Default, serde::Serialize, schemars::JsonSchema)]
+pub struct JournalsInfo {
+    /// Whether any journals were published
+    pub enabled: bool,
+    /// Number of journal pages published
+    pub count: usize,
+    /// Output path of the journal index, when journals were published
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index_path: Option<String>,
+    /// Logseq journal title format (config.edn `:journal/page-title-format`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_title_format: Option<String>,
+}
+
+/// Preprocessor features enabled for this build
+#[derive(Debug, Default, serde::Serialize, schemars::JsonSchema)]
+pub struct Features {
+    pub include_private: bool,
+    pub create_stubs: bool,
+    pub suggest_tags: bool,
+    pub slug_map: bool,
+    pub toc: bool,
+    pub journal_tags: bool,
+    pub previews: bool,
+    pub emit_index: bool,
+    pub related: bool,
+}
+
+impl Features {
+    pub fn from_config(config: &Config) -> Self {
+        Features {
+            include_private: config.include_private,
+            create_stubs: config.create_stubs,
+            suggest_tags: config.suggest_tags,
+            slug_map: config.slug_map.is_some(),
+            toc: config.toc,
+            journal_tags: config.journal_tags.is_some(),
+            previews: config.previews,
+            emit_index: config.emit_index.is_some(),
+            related: config.related.is_some(),
+        }
+    }
+}
+
+/// Build the navigation tree from the namespaces of published (non-journal) pages
+pub fn navigation_tree(page_index: &PageIndex, include_private: bool) -> Vec<NavNode> {
+    let mut root: Vec<NavNode> = Vec::new();
+
+    for page in page_index.iter() {
+        if page.name.starts_with("journals/") {
+            continue;
+        }
+        let private = page.is_private();
+        if private && !include_private {
+            continue;
+        }
+
+        let mut level = &mut root;
+        let segments: Vec<&str> = page.name.split('/').collect();
+        for (i, segment) in segments.iter().enumerate() {
+            let pos = match level.iter().position(|n| n.title.eq_ignore_ascii_case(segment)) {
+                Some(pos) => pos,
+                None => {
+                    level.push(NavNode { title: segment.to_string(), ..Default::default() });
+                    level.len() - 1
+                }
+            };
+            if i == segments.len() - 1 {
+                level[pos].path = Some(resolver::link_target(page).to_string());
+            }
+            level = &mut level[pos].children;
+        }
+    }
+
+    sort_nav(&mut root);
+    root
+}
+
+fn sort_nav(nodes: &mut [NavNode]) {
+    nodes.sort_by_key(|n| n.title.to_lowercase());
+    for node in nodes {
+        sort_nav(&mut node.children);
+    }
+}
+
+/// JSON Schema for `_site_config.json`
+pub fn site_config_schema() -> String {
+    let schema = schemars::schema_for!(SiteConfig);
+    serde_json::to_string_pretty(&schema).unwrap_or_default()
 }
 
 /// Write site configuration to JSON file for Quartz config generation
@@ -178,6 +407,7 @@ pub fn write_site_config(
     home_override: Option<&str>,
     title_override: Option<&str>,
     site_name_override: Option<&str>,
+    mut context: SiteContext,
 ) -> Option<SiteConfig> {
     let home_page = home_override
         .map(|s| s.to_string())
@@ -189,10 +419,24 @@ pub fn write_site_config(
         .or_else(|| get_site_title(config_path))
         .unwrap_or_else(|| home_page.clone());
 
+    if context.journals.page_title_format.is_none() {
+        context.journals.page_title_format = find_setting(config_path, &JOURNAL_TITLE_FORMAT_RE);
+    }
+    let date_format = context
+        .journals
+        .page_title_format
+        .clone()
+        .unwrap_or_else(|| DEFAULT_DATE_FORMAT.to_string());
+
     let site_config = SiteConfig {
+        schema_version: SITE_CONFIG_SCHEMA_VERSION,
         page_title: capitalize_first(&page_title),
         home_page: home_page.clone(),
         site_name: site_name_override.map(|s| s.to_string()),
+        locale: locale(config_path),
+        date_format,
+        theme: ui_colors(config_path),
+        context,
     };
 
     // Write to JSON file
@@ -202,6 +446,22 @@ pub fn write_site_config(
     Some(site_config)
 }
 
+/// Color settings under `:ui/` in config.edn, skipping commented lines
+fn ui_colors(config_path: &Path) -> BTreeMap<String, String> {
+    let Ok(content) = source::read_to_string(config_path) else {
+        return BTreeMap::new();
+    };
+    content
+        .lines()
+        .filter(|line| !line.trim().starts_with(';'))
+        .flat_map(|line| UI_COLOR_RE.captures_iter(line))
+        .filter_map(|caps| {
+            let value = caps.get(2).or(caps.get(3))?.as_str();
+            Some((caps[1].to_string(), value.to_string()))
+        })
+        .collect()
+}
+
 /// Capitalize first letter of a string
 fn capitalize_first(s: &str) -> String {
     let mut chars = s.chars();
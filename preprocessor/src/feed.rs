@@ -0,0 +1,123 @@
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+
+use crate::escape;
+use crate::page::{self, Page, PageIndex};
+use crate::previews;
+use crate::resolver;
+
+/// Atom feed written to the output root (`--feed`)
+pub const FEED_FILE: &str = "feed.xml";
+
+/// Characters of plain text in an entry's summary
+const EXCERPT_LENGTH: usize = 400;
+
+/// Which date orders the feed and counts as an entry's date (`--feed-sort`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum FeedSort {
+    /// Last change (git history, or file time)
+    #[default]
+    Modified,
+    /// First commit
+    Created,
+}
+
+/// Feed settings (`--feed`, `--feed-limit`, `--feed-sort`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedConfig {
+    /// Number of entries, newest first
+    pub limit: usize,
+    pub sort: FeedSort,
+}
+
+impl Default for FeedConfig {
+    fn default() -> Self {
+        FeedConfig { limit: 20, sort: FeedSort::Modified }
+    }
+}
+
+/// Build an Atom feed of the most recently changed (or created) pages and
+/// journals. Pages without a date, unlisted pages and drafts are left out. Links are absolute with
+/// `base_url` (`--base-url`), site-relative otherwise.
+pub fn build(
+    index: &PageIndex,
+    title: &str,
+    base_url: Option<&str>,
+    feed: &FeedConfig,
+    include_private: bool,
+) -> String {
+    let base = base_url.map_or(String::new(), |b| b.trim_end_matches('/').to_string());
+    let date = |page: &Page| match feed.sort {
+        FeedSort::Modified => page.modified.clone().or_else(|| page.created.clone()),
+        FeedSort::Created => page.created.clone().or_else(|| page.modified.clone()),
+    };
+
+    let mut pages: Vec<(String, &Page)> = index
+        .iter()
+        .filter(|p| (include_private || !p.is_private()) && !p.is_unlisted())
+        .filter_map(|p| date(p).map(|d| (d, p)))
+        .collect();
+    // Newest first; same day by name so the order doesn't depend on load order
+    pages.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.name.cmp(&b.1.name)));
+    pages.truncate(feed.limit);
+
+    let updated = pages.first().map_or("1970-01-01".to_string(), |(d, _)| d.clone());
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!("  <title>{}</title>\n", escape::html(title)));
+    xml.push_str(&format!("  <id>{}</id>\n", escape::html(&id(&base, ""))));
+    xml.push_str(&format!("  <link href=\"{}/\"/>\n", escape::html(&base)));
+    xml.push_str(&format!("  <link rel=\"self\" href=\"{}/{}\"/>\n", escape::html(&base), FEED_FILE));
+    xml.push_str(&format!("  <updated>{}</updated>\n", timestamp(&updated)));
+    xml.push_str(&format!("  <author><name>{}</name></author>\n", escape::html(title)));
+    xml.push_str("  <generator>logseq-to-quartz</generator>\n");
+
+    for (date, page) in pages {
+        let slug = resolver::site_path(page);
+        let (_, body) = page::parse_properties(&page.content);
+        let summary = previews::truncate(&previews::plain_text(&body), EXCERPT_LENGTH);
+        xml.push_str("  <entry>\n");
+        xml.push_str(&format!("    <title>{}</title>\n", escape::html(&page.title())));
+        xml.push_str(&format!("    <link href=\"{}/{}\"/>\n", escape::html(&base), escape::html(&slug)));
+        xml.push_str(&format!("    <id>{}</id>\n", escape::html(&id(&base, &slug))));
+        if let Some(created) = &page.created {
+            xml.push_str(&format!("    <published>{}</published>\n", timestamp(created)));
+        }
+        xml.push_str(&format!("    <updated>{}</updated>\n", timestamp(&date)));
+        if !summary.is_empty() {
+            xml.push_str(&format!("    <summary>{}</summary>\n", escape::html(&summary)));
+        }
+        xml.push_str("  </entry>\n");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// Write `feed.xml`. Returns the number of entries.
+pub fn write(
+    output_dir: &Path,
+    index: &PageIndex,
+    title: &str,
+    base_url: Option<&str>,
+    feed: &FeedConfig,
+    include_private: bool,
+) -> Result<usize> {
+    let xml = build(index, title, base_url, feed, include_private);
+    fs::write(output_dir.join(FEED_FILE), &xml)?;
+    Ok(xml.matches("<entry>").count())
+}
+
+/// Entry id: the page URL, or a URN when the site URL isn't known (ids must be absolute)
+fn id(base: &str, slug: &str) -> String {
+    if base.is_empty() {
+        format!("urn:logseq-to-quartz:{}", slug)
+    } else {
+        format!("{}/{}", base, slug)
+    }
+}
+
+/// RFC 3339 timestamp for a `YYYY-MM-DD` date
+fn timestamp(date: &str) -> String {
+    format!("{}T00:00:00Z", date)
+}
+
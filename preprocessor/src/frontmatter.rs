@@ -1,60 +1,71 @@
 use std::collections::HashMap;
 
-/// Generate YAML frontmatter from Logseq properties
+use crate::escape;
+use crate::icons::IconMap;
+use crate::properties;
+use crate::resolver;
+use crate::timezone;
+
+/// Generate YAML frontmatter from Logseq properties.
+/// `icon::` emoji found in `icons` are replaced with an asset or icon class.
 pub fn generate(
     filename: &str,
     properties: &HashMap<String, String>,
     git_dates: Option<(&str, &str)>,
+    icons: &IconMap,
 ) -> String {
     let mut fm = String::from("---\n");
 
-    // Title
-    let title = if let Some(icon) = properties.get("icon") {
-        format!("{} {}", icon, properties.get("title").map_or(filename.replace('_', " "), |t| t.clone()))
-    } else {
-        properties.get("title").map_or(filename.replace('_', " "), |t| t.clone())
+    // Title (a mapped icon is rendered by the theme, so it stays out of the title)
+    let icon = properties.get("icon");
+    let mapped_icon = icon.and_then(|i| icons.get(i));
+    let base_title = properties.get("title").map_or_else(|| resolver::decode(&filename.replace('_', " ")), |t| t.clone());
+    let title = match icon {
+        Some(icon) if mapped_icon.is_none() => format!("{} {}", icon, base_title),
+        _ => base_title,
     };
-    fm.push_str(&format!("title: \"{}\"\n", escape_yaml(&title)));
+    fm.push_str(&format!("title: \"{}\"\n", escape::yaml(&title)));
 
-    // Icon (separate field)
-    if let Some(icon) = properties.get("icon") {
-        fm.push_str(&format!("icon: \"{}\"\n", escape_yaml(icon)));
+    // Icon (separate field), plus icon-src / icon-class when mapped
+    if let Some(icon) = icon {
+        fm.push_str(&format!("icon: \"{}\"\n", escape::yaml(icon)));
+    }
+    if let Some(mapped) = mapped_icon {
+        let (key, value) = mapped.frontmatter_field();
+        fm.push_str(&format!("{}: {}\n", key, escape::yaml_quoted(value)));
     }
 
     // Tags
-    if let Some(tags) = properties.get("tags") {
-        let tags: Vec<&str> = tags
-            .split(',')
-            .map(|t| t.trim().trim_start_matches("[[").trim_end_matches("]]"))
-            .filter(|t| !t.is_empty())
-            .collect();
-        if !tags.is_empty() {
-            fm.push_str("tags:\n");
-            for tag in tags {
-                fm.push_str(&format!("  - {}\n", tag));
-            }
+    let tags = property_tags(properties);
+    if !tags.is_empty() {
+        fm.push_str("tags:\n");
+        for tag in tags {
+            fm.push_str(&format!("  - {}\n", escape::yaml_scalar(&tag)));
         }
     }
 
     // Aliases
     if let Some(alias) = properties.get("alias") {
-        let aliases = parse_aliases(alias);
+        let aliases = properties::list_texts(alias);
         if !aliases.is_empty() {
             fm.push_str("aliases:\n");
             for a in aliases {
-                fm.push_str(&format!("  - {}\n", a));
+                fm.push_str(&format!("  - {}\n", escape::yaml_scalar(&a)));
             }
         }
     }
 
     // Description
     if let Some(desc) = properties.get("description") {
-        fm.push_str(&format!("description: \"{}\"\n", escape_yaml(desc)));
+        fm.push_str(&format!("description: \"{}\"\n", escape::yaml(&properties::plain(desc))));
     }
 
-    // Git dates (from batch lookup)
-    if let Some((modified, created)) = git_dates {
+    // Dates: created::/updated::/date:: properties, else git (or file) dates
+    let (modified, created) = page_dates(properties, git_dates);
+    if let Some(modified) = modified {
         fm.push_str(&format!("modified: {}\n", modified));
+    }
+    if let Some(created) = created {
         fm.push_str(&format!("created: {}\n", created));
     }
 
@@ -62,53 +73,87 @@ pub fn generate(
     fm
 }
 
-/// Parse aliases, handling wikilinks and comma separation
-fn parse_aliases(alias_str: &str) -> Vec<String> {
-    let mut aliases = Vec::new();
-    let mut current = String::new();
-    let mut in_wikilink = false;
-    let chars: Vec<char> = alias_str.chars().collect();
-    let mut i = 0;
-
-    while i < chars.len() {
-        let c = chars[i];
-        let next = chars.get(i + 1).copied();
-
-        if c == '[' && next == Some('[') {
-            in_wikilink = true;
-            i += 2;
-            continue;
-        }
+/// Tags from the `tags::` property, as written (`[[brackets]]` and `#` removed)
+pub fn property_tags(properties: &HashMap<String, String>) -> Vec<String> {
+    properties.get("tags").map_or_else(Vec::new, |tags| properties::list_texts(tags))
+}
 
-        if c == ']' && next == Some(']') {
-            in_wikilink = false;
-            i += 2;
-            continue;
-        }
+/// Inline tags not already listed in the `tags::` property
+pub fn new_tags(properties: &HashMap<String, String>, inline: &[String]) -> Vec<String> {
+    let listed = property_tags(properties);
+    inline
+        .iter()
+        .filter(|tag| !listed.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+        .cloned()
+        .collect()
+}
 
-        if c == ',' && !in_wikilink {
-            let trimmed = current.trim().to_string();
-            if !trimmed.is_empty() {
-                aliases.push(trimmed);
-            }
-            current.clear();
-            i += 1;
-            continue;
-        }
+/// Page (modified, created) dates as `YYYY-MM-DD`.
+/// `updated::` (or `modified::`) and `created::` properties override file dates;
+/// `date::` stands in for both when they are missing.
+pub fn page_dates(
+    properties: &HashMap<String, String>,
+    file_dates: Option<(&str, &str)>,
+) -> (Option<String>, Option<String>) {
+    let date_property = |keys: &[&str]| keys.iter().find_map(|k| properties.get(*k).and_then(|v| parse_date_value(v)));
+
+    let modified = date_property(&["updated", "modified", "date"])
+        .or_else(|| file_dates.map(|(m, _)| m.to_string()));
+    let created = date_property(&["created", "date"])
+        .or_else(|| file_dates.map(|(_, c)| c.to_string()));
+    (modified, created)
+}
+
+/// Parse a date property value (`2024-01-15`, `[[Jan 15th, 2024]]`, `2024-01-15T10:00`,
+/// `2024-01-15T23:30:00Z` as the day in `--timezone`)
+fn parse_date_value(value: &str) -> Option<String> {
+    let value = value.trim().trim_start_matches("[[").trim_end_matches("]]").trim();
+    timezone::current().parse_date(value).map(|d| d.format("%Y-%m-%d").to_string())
+}
 
-        current.push(c);
-        i += 1;
+/// Insert a list field (e.g. `listing:`) before the closing `---` of generated frontmatter.
+/// If the field already exists (e.g. `aliases:` from properties), items are appended to it.
+pub fn insert_list(frontmatter: &str, key: &str, items: &[String]) -> String {
+    if items.is_empty() {
+        return frontmatter.to_string();
     }
 
-    let trimmed = current.trim().to_string();
-    if !trimmed.is_empty() {
-        aliases.push(trimmed);
+    let items_yaml: String = items
+        .iter()
+        .map(|item| format!("  - \"{}\"\n", escape::yaml(item)))
+        .collect();
+
+    // Append to an existing list field
+    let header = format!("\n{}:\n", key);
+    if let Some(pos) = frontmatter.find(&header) {
+        let list_start = pos + header.len();
+        let list_len: usize = frontmatter[list_start..]
+            .split_inclusive('\n')
+            .take_while(|line| line.starts_with("  - "))
+            .map(str::len)
+            .sum();
+        let insert_at = list_start + list_len;
+        return format!(
+            "{}{}{}",
+            &frontmatter[..insert_at],
+            items_yaml,
+            &frontmatter[insert_at..]
+        );
     }
 
-    aliases
+    let field = format!("{}:\n{}", key, items_yaml);
+    match frontmatter.strip_suffix("---\n") {
+        Some(head) => format!("{}{}---\n", head, field),
+        None => format!("{}{}", frontmatter, field),
+    }
 }
 
-/// Escape special characters for YAML strings
-fn escape_yaml(s: &str) -> String {
-    s.replace('\\', "\\\\").replace('"', "\\\"")
+/// Insert a `key: value` field (value already YAML) before the closing `---`
+pub fn insert_field(frontmatter: &str, key: &str, value: &str) -> String {
+    let field = format!("{}: {}\n", key, value);
+    match frontmatter.strip_suffix("---\n") {
+        Some(head) => format!("{}{}---\n", head, field),
+        None => format!("{}{}", frontmatter, field),
+    }
 }
+
@@ -0,0 +1,246 @@
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::timezone;
+
+/// File path (relative to the graph root, `/`-separated) → (modified, created) as `YYYY-MM-DD`
+pub type FileDates = HashMap<String, (String, String)>;
+
+lazy_static! {
+    // History is walked once per root, even though pages, journals and the pipeline all ask
+    static ref CACHE: Mutex<HashMap<PathBuf, Arc<FileDates>>> = Mutex::new(HashMap::new());
+}
+
+/// Created/modified dates for all markdown files under `root`.
+///
+/// Dates come from git history (author dates, following renames) when `root`
+/// is inside a git repository; files without history use filesystem timestamps.
+pub fn file_dates(root: &Path) -> Arc<FileDates> {
+    let key = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    if let Some(dates) = CACHE.lock().unwrap_or_else(|e| e.into_inner()).get(&key) {
+        return dates.clone();
+    }
+
+    // Files without history (not a repo, untracked, synced in) use file metadata
+    let mut dates = history_dates(&key, None).unwrap_or_default();
+    for (path, file_dates) in filesystem_dates(&key) {
+        dates.entry(path).or_insert(file_dates);
+    }
+    let dates = Arc::new(dates);
+    CACHE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(key, dates.clone());
+    dates
+}
+
+/// Lookup key for a file: path relative to the root with `/` separators (as git reports them)
+pub fn relative_key(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .map(|p| p.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_default()
+}
+
+/// Walk the history of the repository containing `root`, newest commit first,
+/// starting at `revision` (default: HEAD).
+/// Returns `None` if `root` is not in a repository (or the repository has no commits).
+fn history_dates(root: &Path, revision: Option<&str>) -> Option<FileDates> {
+    let repo = gix::discover(root).ok()?;
+    let workdir = repo.workdir()?.canonicalize().ok()?;
+    let prefix = root
+        .strip_prefix(&workdir)
+        .ok()?
+        .to_string_lossy()
+        .replace('\\', "/");
+    let prefix = if prefix.is_empty() { prefix } else { format!("{}/", prefix) };
+
+    let tip = match revision {
+        Some(revision) => repo.rev_parse_single(revision).ok()?.object().ok()?.peel_to_commit().ok()?.id,
+        None => repo.head_id().ok()?.detach(),
+    };
+    let walk = repo
+        .rev_walk([tip])
+        .sorting(gix::revision::walk::Sorting::ByCommitTime(Default::default()))
+        .all()
+        .ok()?;
+
+    let mut diff_options = gix::diff::Options::default();
+    diff_options.track_path();
+    diff_options.track_rewrites(Some(gix::diff::Rewrites::default()));
+
+    let mut dates = FileDates::new();
+    // Older path → path at HEAD, for files renamed later in history
+    let mut renamed: HashMap<String, String> = HashMap::new();
+
+    // Shallow clones end in missing parents; everything before that point is unknown
+    for info in walk.map_while(Result::ok) {
+        let Ok(commit) = info.object() else { break };
+        let Some(date) = commit_date(&commit) else { continue };
+        let Ok(tree) = commit.tree() else { break };
+        let parent_tree = info
+            .parent_ids()
+            .next()
+            .and_then(|id| id.object().ok())
+            .and_then(|obj| obj.try_into_commit().ok())
+            .and_then(|c| c.tree().ok());
+
+        let Ok(changes) = repo.diff_tree_to_tree(parent_tree.as_ref(), &tree, diff_options) else {
+            continue;
+        };
+
+        for change in changes {
+            use gix::object::tree::diff::ChangeDetached as Change;
+            let (location, source) = match &change {
+                Change::Addition { location, .. } | Change::Modification { location, .. } => {
+                    (location.to_string(), None)
+                }
+                Change::Rewrite { location, source_location, copy: false, .. } => {
+                    (location.to_string(), Some(source_location.to_string()))
+                }
+                _ => continue,
+            };
+            if !location.ends_with(".md") {
+                continue;
+            }
+
+            let current = renamed.get(&location).cloned().unwrap_or(location);
+            if let Some(source) = source {
+                renamed.insert(source, current.clone());
+            }
+
+            let Some(relative) = current.strip_prefix(&prefix) else { continue };
+            let entry = dates
+                .entry(relative.to_string())
+                .or_insert_with(|| (date.clone(), date.clone()));
+            // Walking newest → oldest: first sighting is the modified date, last is created
+            entry.1 = date.clone();
+        }
+    }
+
+    if dates.is_empty() {
+        None
+    } else {
+        Some(dates)
+    }
+}
+
+/// The graph as it was at a git revision (`--at-revision`), extracted to a
+/// temporary directory that is removed on drop. File dates for the snapshot
+/// come from history up to that revision, not from the working tree.
+#[derive(Debug)]
+pub struct Snapshot {
+    dir: PathBuf,
+    /// Short id of the resolved commit
+    pub commit: String,
+    /// Author date of the resolved commit (`YYYY-MM-DD`)
+    pub date: String,
+}
+
+impl Snapshot {
+    /// Extract the tree of `root` at `revision` (a commit, tag, branch or
+    /// expression like `HEAD~10`). `root` may be a subdirectory of the repository.
+    pub fn create(root: &Path, revision: &str) -> Result<Snapshot> {
+        let root = root
+            .canonicalize()
+            .with_context(|| format!("graph directory {} not found", root.display()))?;
+        let repo = gix::discover(&root)
+            .with_context(|| format!("{} is not inside a git repository", root.display()))?;
+        let workdir = repo
+            .workdir()
+            .context("--at-revision needs a repository with a working tree")?
+            .canonicalize()?;
+        let prefix = root.strip_prefix(&workdir)?.to_path_buf();
+
+        let commit = repo
+            .rev_parse_single(revision)
+            .with_context(|| format!("unknown revision '{}'", revision))?
+            .object()?
+            .peel_to_commit()
+            .with_context(|| format!("'{}' does not name a commit", revision))?;
+        let short_id = commit.id.to_hex_with_len(7).to_string();
+        let date = commit_date(&commit).unwrap_or_default();
+
+        let mut tree = commit.tree()?;
+        if !prefix.as_os_str().is_empty() {
+            let entry = tree
+                .peel_to_entry_by_path(&prefix)?
+                .with_context(|| format!("{} does not exist at {}", prefix.display(), revision))?;
+            tree = entry.object()?.try_into_tree()?;
+        }
+
+        let dir = std::env::temp_dir().join(format!("logseq-to-quartz-{}-{}", short_id, std::process::id()));
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+        let snapshot = Snapshot { dir, commit: short_id, date };
+
+        // Symlinks and submodules are skipped: they point outside the revision
+        for entry in tree.traverse().breadthfirst.files()? {
+            if !entry.mode.is_blob() {
+                continue;
+            }
+            let path = snapshot.dir.join(entry.filepath.to_string());
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, &repo.find_object(entry.oid)?.data)
+                .with_context(|| format!("writing {}", path.display()))?;
+        }
+
+        // Every extracted file is tracked; files beyond a shallow clone's history
+        // get the revision's own date rather than the extraction time
+        let mut dates = history_dates(&root, Some(revision)).unwrap_or_default();
+        for path in filesystem_dates(&snapshot.dir).into_keys() {
+            dates
+                .entry(path)
+                .or_insert_with(|| (snapshot.date.clone(), snapshot.date.clone()));
+        }
+        CACHE
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(snapshot.dir.canonicalize()?, Arc::new(dates));
+
+        Ok(snapshot)
+    }
+
+    /// Graph root of the extracted revision
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Author date of a commit, as the day in `--timezone`
+fn commit_date(commit: &gix::Commit<'_>) -> Option<String> {
+    let time = commit.author().ok()?.time().ok()?;
+    let instant = chrono::DateTime::from_timestamp(time.seconds, 0)?;
+    Some(timezone::current().date(instant).format("%Y-%m-%d").to_string())
+}
+
+/// Dates from file metadata, for files git knows nothing about
+fn filesystem_dates(root: &Path) -> FileDates {
+    walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "md"))
+        .filter_map(|e| {
+            let meta = fs::metadata(e.path()).ok()?;
+            let modified = meta.modified().ok()?;
+            let created = meta.created().unwrap_or(modified);
+            let format = |t: std::time::SystemTime| {
+                timezone::current().date(t.into()).format("%Y-%m-%d").to_string()
+            };
+            Some((relative_key(e.path(), root), (format(modified), format(created))))
+        })
+        .collect()
+}
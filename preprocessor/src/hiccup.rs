@@ -0,0 +1,206 @@
+use crate::edn::{self, Form};
+use crate::escape;
+
+/// Elements rendered without a closing tag
+const VOID_TAGS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr",
+];
+
+/// Elements that start an HTML block (no list marker in front of them)
+const BLOCK_TAGS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "details", "dl", "div", "fieldset", "figure", "footer", "form",
+    "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "nav", "ol", "p", "pre", "section", "table", "ul",
+];
+
+/// Elements dropped with everything in them: they run script or load other
+/// documents (`srcdoc`, `data`), or change how the page's URLs resolve
+const DROPPED_TAGS: &[&str] = &["script", "iframe", "object", "embed", "base", "meta"];
+
+/// Attributes holding a URL, checked for `javascript:` and similar schemes
+const URL_ATTRS: &[&str] = &["href", "src", "action", "formaction", "xlink:href", "poster", "data"];
+
+/// An HTML node built from hiccup
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Element {
+        tag: String,
+        attrs: Vec<(String, String)>,
+        children: Vec<Node>,
+    },
+    Text(String),
+}
+
+/// Parse the hiccup form at the start of `src` (`[:div {:class "x"} "text" [:b "bold"]]`).
+/// Returns the nodes and the number of bytes the form takes, or `None` if `src`
+/// doesn't start with a complete, well-formed form.
+pub fn parse(src: &str) -> Option<(Vec<Node>, usize)> {
+    let (form, len) = edn::read(src)?;
+    Some((nodes(&form), len))
+}
+
+/// Render nodes as HTML; text and attribute values are escaped
+pub fn render(nodes: &[Node]) -> String {
+    let mut html = String::new();
+    for node in nodes {
+        render_node(node, &mut html);
+    }
+    html
+}
+
+/// Does the HTML start with a block element?
+pub fn is_block(nodes: &[Node]) -> bool {
+    matches!(nodes.first(), Some(Node::Element { tag, .. }) if BLOCK_TAGS.contains(&tag.as_str()))
+}
+
+/// Nodes for a form: `[:tag attrs? children...]` is an element, any other
+/// vector or list a fragment, strings and atoms text (`nil` nothing)
+fn nodes(form: &Form) -> Vec<Node> {
+    match form {
+        Form::Vector(items) => match items.split_first() {
+            Some((Form::Keyword(tag), rest)) => element(tag, rest),
+            _ => items.iter().flat_map(nodes).collect(),
+        },
+        Form::List(items) => items.iter().flat_map(nodes).collect(),
+        Form::Str(text) => vec![Node::Text(text.clone())],
+        Form::Atom(atom) if atom == "nil" => Vec::new(),
+        Form::Atom(atom) => vec![Node::Text(atom.clone())],
+        Form::Keyword(name) => vec![Node::Text(name.clone())],
+        Form::Map(_) => Vec::new(),
+    }
+}
+
+/// An element from its tag keyword (`div.note#intro` shorthand) and the rest of its vector
+fn element(tag: &str, rest: &[Form]) -> Vec<Node> {
+    let (name, mut classes, mut id) = split_tag(tag);
+    let children: Vec<Node> = rest
+        .iter()
+        .skip(matches!(rest.first(), Some(Form::Map(_))) as usize)
+        .flat_map(nodes)
+        .collect();
+    if DROPPED_TAGS.contains(&name.to_ascii_lowercase().as_str()) {
+        return Vec::new();
+    }
+    if !valid_name(&name) {
+        return children;
+    }
+
+    let mut attrs: Vec<(String, String)> = Vec::new();
+    if let Some(Form::Map(pairs)) = rest.first() {
+        for (key, value) in pairs {
+            let key = match key {
+                Form::Keyword(k) | Form::Str(k) | Form::Atom(k) => k.to_lowercase(),
+                _ => continue,
+            };
+            let Some(value) = attr_value(value) else {
+                continue;
+            };
+            match key.as_str() {
+                "class" => classes.push(value),
+                "id" => id = Some(value),
+                _ if safe_attr(&key, &value) => attrs.push((key, value)),
+                _ => {}
+            }
+        }
+    }
+    if let Some(id) = id {
+        attrs.insert(0, ("id".to_string(), id));
+    }
+    if !classes.is_empty() {
+        attrs.insert(0, ("class".to_string(), classes.join(" ")));
+    }
+    vec![Node::Element { tag: name, attrs, children }]
+}
+
+/// `div.a.b#main` → (`div`, [`a`, `b`], `main`)
+fn split_tag(tag: &str) -> (String, Vec<String>, Option<String>) {
+    let end = tag.find(['.', '#']).unwrap_or(tag.len());
+    let (mut classes, mut id) = (Vec::new(), None);
+    let mut rest = &tag[end..];
+    while let Some(marker) = rest.chars().next() {
+        let part_end = rest[1..].find(['.', '#']).map_or(rest.len(), |i| i + 1);
+        let part = &rest[1..part_end];
+        if !part.is_empty() {
+            if marker == '.' {
+                classes.push(part.to_string());
+            } else {
+                id = Some(part.to_string());
+            }
+        }
+        rest = &rest[part_end..];
+    }
+    (tag[..end].to_lowercase(), classes, id)
+}
+
+/// Attribute value as text: strings and keywords as is, vectors joined with
+/// spaces (`:class ["a" "b"]`), maps as CSS (`:style {:color "red"}`).
+/// `false`/`nil` leave the attribute out; `true` is an empty (boolean) value.
+fn attr_value(value: &Form) -> Option<String> {
+    match value {
+        Form::Str(s) | Form::Keyword(s) => Some(s.clone()),
+        Form::Atom(a) if a == "false" || a == "nil" => None,
+        Form::Atom(a) if a == "true" => Some(String::new()),
+        Form::Atom(a) => Some(a.clone()),
+        Form::Vector(items) | Form::List(items) => {
+            Some(items.iter().filter_map(attr_value).collect::<Vec<_>>().join(" "))
+        }
+        Form::Map(pairs) => Some(
+            pairs
+                .iter()
+                .filter_map(|(k, v)| {
+                    let k = match k {
+                        Form::Keyword(k) | Form::Str(k) => k,
+                        _ => return None,
+                    };
+                    attr_value(v).map(|v| format!("{}: {};", k, v))
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+    }
+}
+
+/// Sanitization: no event handlers (`on*`), no `srcdoc` documents, no script
+/// URLs, only plain attribute names
+fn safe_attr(key: &str, value: &str) -> bool {
+    if !valid_name(key) || key.starts_with("on") || key == "srcdoc" {
+        return false;
+    }
+    if URL_ATTRS.contains(&key) {
+        let scheme: String = value
+            .chars()
+            .filter(|c| !c.is_whitespace() && !c.is_control())
+            .take(16)
+            .collect::<String>()
+            .to_lowercase();
+        if scheme.starts_with("javascript:") || scheme.starts_with("vbscript:") || scheme.starts_with("data:text/html") {
+            return false;
+        }
+    }
+    true
+}
+
+fn valid_name(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "-_:".contains(c))
+}
+
+fn render_node(node: &Node, html: &mut String) {
+    match node {
+        Node::Text(text) => html.push_str(&escape::html(text)),
+        Node::Element { tag, attrs, children } => {
+            html.push('<');
+            html.push_str(tag);
+            for (key, value) in attrs {
+                html.push_str(&format!(" {}=\"{}\"", key, escape::html(value)));
+            }
+            html.push('>');
+            if VOID_TAGS.contains(&tag.as_str()) {
+                return;
+            }
+            for child in children {
+                render_node(child, html);
+            }
+            html.push_str(&format!("</{}>", tag));
+        }
+    }
+}
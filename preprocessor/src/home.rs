@@ -0,0 +1,132 @@
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+
+use crate::escape;
+use crate::journals;
+use crate::page::{Page, PageIndex};
+use crate::resolver;
+
+/// What the site root (`index.md`) shows (`--home-mode`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum HomeMode {
+    /// A copy of the home page
+    #[default]
+    Copy,
+    /// The home page embedded (`![[home]]`), so it keeps a single URL
+    Embed,
+    /// A redirect to the home page
+    Redirect,
+    /// The latest journal, embedded
+    Journal,
+    /// The most recent journals, newest first, as Logseq opens on its journals
+    Journals,
+}
+
+/// Journals on a `--home-mode journals` home page (`--home-journals`)
+pub const HOME_JOURNALS: usize = 10;
+
+/// Write `index.md` for the home page `home` as `mode` says (`journals`: the
+/// number of entries in `HomeMode::Journals`). Returns what the index shows,
+/// for the log. Without a published home page (or journal), the index is a
+/// short welcome page linking to `home`.
+pub fn write_index(
+    output_dir: &Path,
+    index: &PageIndex,
+    home: &str,
+    mode: HomeMode,
+    journals: usize,
+    include_private: bool,
+) -> Result<String> {
+    let index_path = output_dir.join("index.md");
+    if mode == HomeMode::Journals {
+        let recent: Vec<&Page> = recent_journals(index, include_private).take(journals).collect();
+        if !recent.is_empty() {
+            fs::write(&index_path, journal_stream(&recent))?;
+            return Ok(format!("{} recent journals", recent.len()));
+        }
+    }
+    let target = match mode {
+        HomeMode::Journal | HomeMode::Journals => recent_journals(index, include_private).next(),
+        _ => resolver::resolve(home, index)
+            .filter(|r| !r.kind.is_guess())
+            .map(|r| r.page)
+            .filter(|page| include_private || !page.is_private()),
+    };
+    // The copy needs the page's output (a page that failed to process has none)
+    let target = target.filter(|page| {
+        mode != HomeMode::Copy || resolver::output_path(output_dir, resolver::link_target(page)).exists()
+    });
+    let Some(page) = target else {
+        fs::write(&index_path, welcome(home))?;
+        return Ok(format!("home page '{}' not found", home));
+    };
+
+    let name = resolver::link_target(page);
+    match mode {
+        HomeMode::Copy => {
+            fs::copy(resolver::output_path(output_dir, name), &index_path)?;
+            Ok(format!("copied from: {}", name))
+        }
+        HomeMode::Embed | HomeMode::Journal | HomeMode::Journals => {
+            fs::write(&index_path, embed(page))?;
+            Ok(format!("embedding: {}", name))
+        }
+        HomeMode::Redirect => {
+            fs::write(&index_path, redirect(page))?;
+            Ok(format!("redirecting to: {}", name))
+        }
+    }
+}
+
+/// Index page when there is no home page to show
+fn welcome(home: &str) -> String {
+    format!("---\ntitle: {}\n---\n\n# Welcome\n\nSee [[{}]]\n", escape::yaml_quoted(home), home)
+}
+
+/// Index page embedding a page
+pub fn embed(page: &Page) -> String {
+    format!(
+        "---\ntitle: {}\n---\n\n![[{}]]\n",
+        escape::yaml_quoted(&page.title()),
+        resolver::link_target(page)
+    )
+}
+
+/// Index page forwarding to a page: Quartz `redirect` frontmatter, a meta
+/// refresh for hosts that serve the markdown output as is, and a link
+pub fn redirect(page: &Page) -> String {
+    let url = resolver::site_path(page);
+    format!(
+        "---\ntitle: {}\nredirect: {}\n---\n\n<meta http-equiv=\"refresh\" content=\"0; url={}\">\n\nGo to [[{}|{}]].\n",
+        escape::yaml_quoted(&page.title()),
+        escape::yaml_quoted(&url),
+        escape::html(&url),
+        resolver::link_target(page),
+        page.title()
+    )
+}
+
+/// Home page listing journals like the journals index: a linked heading per
+/// day, then the day embedded, newest first
+pub fn journal_stream(journals: &[&Page]) -> String {
+    let mut content = String::from("---\ntitle: \"📅 Journals\"\n---\n\n");
+    for page in journals {
+        let target = resolver::link_target(page);
+        content.push_str(&format!("## [[{}|{}]]\n\n![[{}]]\n\n---\n\n", target, page.title(), target));
+    }
+    content.push_str("[[journals/index|All journals]]\n");
+    content
+}
+
+/// Published journals, most recent first
+fn recent_journals(index: &PageIndex, include_private: bool) -> impl Iterator<Item = &Page> {
+    let mut dated: Vec<(String, &Page)> = index
+        .iter()
+        .filter(|p| include_private || !p.is_private())
+        .filter_map(|p| Some((journals::parse_journal_date(p.name.strip_prefix("journals/")?)?.0, p)))
+        .collect();
+    dated.sort_by(|a, b| b.0.cmp(&a.0));
+    dated.into_iter().map(|(_, page)| page)
+}
+
@@ -0,0 +1,104 @@
+use anyhow::{bail, Context, Result};
+use log::info;
+use std::path::Path;
+use std::process::Command;
+
+use crate::config::Config;
+use crate::staging;
+
+/// Stats file written to the output root after each build, passed to hooks
+pub const STATS_FILE: &str = "_stats.json";
+
+/// Shell commands run around a build (`--pre-build`, `--post-build`, `--on-error`)
+#[derive(Debug, Clone, Default)]
+pub struct Hooks {
+    /// Before any output is written; a failure aborts the build
+    pub pre_build: Option<String>,
+    /// After a successful build; a failure fails the run
+    pub post_build: Option<String>,
+    /// When the build (or another hook) fails; its own failure is only logged
+    pub on_error: Option<String>,
+}
+
+impl Hooks {
+    pub fn pre_build(&self, config: &Config) -> Result<()> {
+        match &self.pre_build {
+            Some(command) => run("pre_build", command, &env(config, None)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn post_build(&self, config: &Config) -> Result<()> {
+        match &self.post_build {
+            Some(command) => run("post_build", command, &env(config, None)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn on_error(&self, config: &Config, error: &anyhow::Error) {
+        if let Some(command) = &self.on_error {
+            if let Err(e) = run("on_error", command, &env(config, Some(error))) {
+                log::error!("{:#}", e);
+            }
+        }
+    }
+}
+
+/// Environment passed to hook commands:
+/// - `LOGSEQ_QUARTZ_INPUT` / `LOGSEQ_QUARTZ_OUTPUT`: graph and output directories
+/// - `LOGSEQ_QUARTZ_STATS`: path of `_stats.json`, when it was written
+/// - `LOGSEQ_QUARTZ_ERROR`: the error message (`on_error` only)
+fn env(config: &Config, error: Option<&anyhow::Error>) -> Vec<(&'static str, String)> {
+    let mut vars = vec![
+        ("LOGSEQ_QUARTZ_INPUT", config.input_dir.display().to_string()),
+        ("LOGSEQ_QUARTZ_OUTPUT", config.output_dir.display().to_string()),
+    ];
+    // A failed staged build never reached the output directory
+    let stats = match error {
+        Some(_) if config.output_mode != staging::OutputMode::InPlace => staging::staging_dir(&config.output_dir).join(STATS_FILE),
+        _ => config.output_dir.join(STATS_FILE),
+    };
+    if stats.exists() {
+        vars.push(("LOGSEQ_QUARTZ_STATS", stats.display().to_string()));
+    }
+    if let Some(error) = error {
+        vars.push(("LOGSEQ_QUARTZ_ERROR", format!("{:#}", error)));
+    }
+    vars
+}
+
+/// Run a hook command through the platform shell, failing on a non-zero exit
+fn run(name: &str, command: &str, env: &[(&'static str, String)]) -> Result<()> {
+    info!("Running {} hook: {}", name, command);
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let status = shell
+        .arg(command)
+        .env("LOGSEQ_QUARTZ_HOOK", name)
+        .envs(env.iter().map(|(k, v)| (*k, v)))
+        .status()
+        .with_context(|| format!("failed to start {} hook", name))?;
+    if !status.success() {
+        bail!("{} hook failed ({}): {}", name, status, command);
+    }
+    Ok(())
+}
+
+/// Write build stats as pretty JSON for hooks and CI
+pub fn write_stats(output_dir: &Path, stats: &impl serde::Serialize) -> Result<()> {
+    std::fs::write(output_dir.join(STATS_FILE), serde_json::to_string_pretty(stats)?)?;
+    Ok(())
+}
+
+/// A field of the `_stats.json` the last build left in `output_dir`, if any
+pub fn previous_stat<T: serde::de::DeserializeOwned>(output_dir: &Path, field: &str) -> Option<T> {
+    let stats: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(output_dir.join(STATS_FILE)).ok()?).ok()?;
+    serde_json::from_value(stats.get(field)?.clone()).ok()
+}
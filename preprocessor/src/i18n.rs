@@ -0,0 +1,144 @@
+use anyhow::Result;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::page::{Page, PageIndex};
+use crate::properties;
+use crate::resolver;
+
+/// Language switcher data written to the output root: for each page with
+/// translations, the site path of every language's variant
+pub const LANGUAGES_FILE: &str = "_languages.json";
+
+lazy_static! {
+    // Language variant of a page: `Garden.zh`, `Garden.pt-BR` (groups: base name, language, primary subtag)
+    static ref VARIANT_RE: Regex = Regex::new(r"^(.+)\.(([a-z]{2})(?:-[A-Za-z]{2,4})?)$").unwrap();
+}
+
+/// ISO 639-1 codes: a name suffix is a language only if it is one of these,
+/// so `Node.js` or `Chart.js` next to a `Node` page stay pages of their own
+const LANGUAGE_CODES: &[&str] = &[
+    "aa", "ab", "ae", "af", "ak", "am", "an", "ar", "as", "av", "ay", "az", "ba", "be", "bg", "bi", "bm", "bn",
+    "bo", "br", "bs", "ca", "ce", "ch", "co", "cr", "cs", "cu", "cv", "cy", "da", "de", "dv", "dz", "ee", "el",
+    "en", "eo", "es", "et", "eu", "fa", "ff", "fi", "fj", "fo", "fr", "fy", "ga", "gd", "gl", "gn", "gu", "gv",
+    "ha", "he", "hi", "ho", "hr", "ht", "hu", "hy", "hz", "ia", "id", "ie", "ig", "ii", "ik", "io", "is", "it",
+    "iu", "ja", "jv", "ka", "kg", "ki", "kj", "kk", "kl", "km", "kn", "ko", "kr", "ks", "ku", "kv", "kw", "ky",
+    "la", "lb", "lg", "li", "ln", "lo", "lt", "lu", "lv", "mg", "mh", "mi", "mk", "ml", "mn", "mr", "ms", "mt",
+    "my", "na", "nb", "nd", "ne", "ng", "nl", "nn", "no", "nr", "nv", "ny", "oc", "oj", "om", "or", "os", "pa",
+    "pi", "pl", "ps", "pt", "qu", "rm", "rn", "ro", "ru", "rw", "sa", "sc", "sd", "se", "sg", "si", "sk", "sl",
+    "sm", "sn", "so", "sq", "sr", "ss", "st", "su", "sv", "sw", "ta", "te", "tg", "th", "ti", "tk", "tl", "tn",
+    "to", "tr", "ts", "tt", "tw", "ty", "ug", "uk", "ur", "uz", "ve", "vi", "vo", "wa", "wo", "xh", "yi", "yo",
+    "za", "zh", "zu",
+];
+
+/// Language of pages without `lang::` or a language suffix: the primary
+/// subtag of config.edn `:preferred-language` (`en-US` → `en`)
+pub fn default_language(locale: &str) -> String {
+    locale.split(['-', '_']).next().unwrap_or(locale).to_lowercase()
+}
+
+/// Base name and language of a `Garden.zh` name
+fn split_variant(name: &str) -> Option<(&str, &str)> {
+    let caps = VARIANT_RE.captures(name)?;
+    if !LANGUAGE_CODES.contains(&caps.get(3)?.as_str()) {
+        return None;
+    }
+    Some((caps.get(1)?.as_str(), caps.get(2)?.as_str()))
+}
+
+/// Lowercase name of the page this one translates: the `translation-of::`
+/// page, or `Garden` for a `Garden.zh` page when `Garden` exists. Journals
+/// have no translations.
+pub fn translation_of(page: &Page, index: &PageIndex) -> Option<String> {
+    if page.name.starts_with("journals/") {
+        return None;
+    }
+    let base = match page.properties.get("translation-of") {
+        Some(target) => resolver::find_page(&properties::plain(target), index)?,
+        None => resolver::find_page(split_variant(&page.name)?.0, index)?,
+    };
+    (base.name_lower != page.name_lower).then(|| base.name_lower.clone())
+}
+
+/// Language of a page: `lang::`, the suffix of a `Garden.zh` translation, or `default`
+pub fn language(page: &Page, index: &PageIndex, default: &str) -> String {
+    if let Some(lang) = page.properties.get("lang") {
+        return properties::plain(lang).to_lowercase();
+    }
+    match split_variant(&page.name) {
+        Some((_, lang)) if translation_of(page, index).is_some() => lang.to_lowercase(),
+        _ => default.to_string(),
+    }
+}
+
+/// Title of a translation without `title::`: the name without its language
+/// suffix (`Garden.zh` → `Garden`)
+pub fn variant_title(page: &Page, index: &PageIndex) -> Option<String> {
+    if page.properties.contains_key("title") || page.properties.contains_key("translation-of") {
+        return None;
+    }
+    translation_of(page, index)?;
+    split_variant(&page.name).map(|(base, _)| base.to_string())
+}
+
+/// A page and its translations, the original first, then by language. Empty
+/// when the page has no (published) translations.
+pub fn variants<'a>(page: &'a Page, index: &'a PageIndex, include_private: bool) -> Vec<&'a Page> {
+    let key = translation_of(page, index).unwrap_or_else(|| page.name_lower.clone());
+    let Some(base) = resolver::find_page(&key, index) else {
+        return Vec::new();
+    };
+    let mut translations: Vec<&Page> = index
+        .translations(&key)
+        .filter(|p| include_private || !p.is_private())
+        .collect();
+    if translations.is_empty() || (!include_private && base.is_private()) {
+        return Vec::new();
+    }
+    translations.sort_by_key(|p| p.name_lower.as_str());
+    let mut variants = vec![base];
+    variants.extend(translations);
+    variants
+}
+
+/// `Also in:` line linking to the other languages of a page
+pub fn switcher(page: &Page, variants: &[&Page], index: &PageIndex, default: &str) -> String {
+    let links: Vec<String> = variants
+        .iter()
+        .filter(|p| p.name != page.name)
+        .map(|p| format!("[[{}|{}]]", resolver::link_target(p), language(p, index, default)))
+        .collect();
+    format!("*Also in: {}*\n\n", links.join(" · "))
+}
+
+/// Language → site path of each variant, for every page with translations
+pub fn build(index: &PageIndex, default: &str, include_private: bool) -> BTreeMap<String, BTreeMap<String, String>> {
+    let mut languages = BTreeMap::new();
+    for page in index.iter().filter(|p| include_private || !p.is_private()) {
+        let variants = variants(page, index, include_private);
+        if variants.is_empty() {
+            continue;
+        }
+        let paths = variants
+            .iter()
+            .map(|p| (language(p, index, default), resolver::site_path(p)))
+            .collect();
+        languages.insert(resolver::site_path(page), paths);
+    }
+    languages
+}
+
+/// Write `_languages.json` when some page has translations. Returns the
+/// number of pages with translations.
+pub fn write(output_dir: &Path, index: &PageIndex, default: &str, include_private: bool) -> Result<usize> {
+    let languages = build(index, default, include_private);
+    if languages.is_empty() {
+        return Ok(0);
+    }
+    fs::write(output_dir.join(LANGUAGES_FILE), serde_json::to_string_pretty(&languages)?)?;
+    Ok(languages.len())
+}
+
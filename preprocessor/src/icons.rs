@@ -0,0 +1,140 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::escape;
+use crate::warnings;
+
+/// Where mapped icon assets are published, relative to the output root
+pub const ICONS_OUTPUT_DIR: &str = "assets/icons";
+
+/// File extensions treated as icon assets; any other value is an icon font class
+const ASSET_EXTENSIONS: &[&str] = &["svg", "png", "webp", "gif", "jpg", "jpeg", "ico"];
+
+/// What an `icon::` emoji is replaced with
+#[derive(Debug, Clone, PartialEq)]
+pub enum Icon {
+    /// Image asset: source file and its site path (`/assets/icons/rocket.svg`)
+    Asset { source: PathBuf, src: String },
+    /// Icon font class, e.g. `ph ph-rocket`
+    Class(String),
+}
+
+impl Icon {
+    /// Inline HTML for the icon (alt text / label is the original emoji)
+    pub fn html(&self, emoji: &str) -> String {
+        match self {
+            Icon::Asset { src, .. } => format!(
+                "<img class=\"icon\" src=\"{}\" alt=\"{}\">",
+                escape::html(src),
+                escape::html(emoji)
+            ),
+            Icon::Class(class) => format!(
+                "<i class=\"icon {}\" aria-label=\"{}\"></i>",
+                escape::html(class),
+                escape::html(emoji)
+            ),
+        }
+    }
+
+    /// Frontmatter field describing the icon: `icon-src` or `icon-class`
+    pub fn frontmatter_field(&self) -> (&'static str, &str) {
+        match self {
+            Icon::Asset { src, .. } => ("icon-src", src),
+            Icon::Class(class) => ("icon-class", class),
+        }
+    }
+}
+
+/// Emoji → icon mapping, loaded from a JSON object such as
+/// `{"🚀": "icons/rocket.svg", "⭐": "ph ph-star"}`.
+/// Asset paths are relative to the mapping file.
+#[derive(Debug, Clone, Default)]
+pub struct IconMap {
+    icons: HashMap<String, Icon>,
+}
+
+impl IconMap {
+    /// Load a mapping file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("reading icon map {}", path.display()))?;
+        let raw: HashMap<String, String> = serde_json::from_str(&content)
+            .with_context(|| format!("parsing icon map {}", path.display()))?;
+        let base = path.parent().unwrap_or(Path::new("."));
+        Ok(Self::from_entries(raw, base))
+    }
+
+    /// Build a mapping from `emoji → value` pairs, resolving asset paths against `base`
+    pub fn from_entries(entries: HashMap<String, String>, base: &Path) -> Self {
+        let icons = entries
+            .into_iter()
+            .filter(|(emoji, value)| !emoji.trim().is_empty() && !value.trim().is_empty())
+            .map(|(emoji, value)| {
+                let value = value.trim();
+                let icon = if is_asset(value) {
+                    let file_name = Path::new(value)
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    Icon::Asset {
+                        source: base.join(value),
+                        src: format!("/{}/{}", ICONS_OUTPUT_DIR, file_name),
+                    }
+                } else {
+                    Icon::Class(value.to_string())
+                };
+                (normalize(&emoji), icon)
+            })
+            .collect();
+        Self { icons }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.icons.is_empty()
+    }
+
+    /// Mapped icon for an `icon::` value, ignoring emoji variation selectors
+    pub fn get(&self, emoji: &str) -> Option<&Icon> {
+        self.icons.get(&normalize(emoji))
+    }
+
+    /// Copy the mapped asset files to `<output>/assets/icons`. Missing files are reported as warnings.
+    pub fn copy_assets(&self, output_dir: &Path) -> Result<usize> {
+        let target_dir = output_dir.join(ICONS_OUTPUT_DIR);
+        let mut copied = 0;
+        for icon in self.icons.values() {
+            let Icon::Asset { source, .. } = icon else { continue };
+            let Some(file_name) = source.file_name() else { continue };
+            if !source.is_file() {
+                warnings::global().warn("icon", &format!("icon asset not found: {}", source.display()), None);
+                continue;
+            }
+            fs::create_dir_all(&target_dir)?;
+            fs::copy(source, target_dir.join(file_name))?;
+            copied += 1;
+        }
+        Ok(copied)
+    }
+}
+
+/// Mapping file for a graph: the explicit path, else `logseq/icons.json` if present
+pub fn map_path(input_dir: &Path, explicit: Option<&Path>) -> Option<PathBuf> {
+    match explicit {
+        Some(path) => Some(path.to_path_buf()),
+        None => Some(input_dir.join("logseq/icons.json")).filter(|p| p.exists()),
+    }
+}
+
+fn is_asset(value: &str) -> bool {
+    Path::new(value)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .is_some_and(|ext| ASSET_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// `❤️` and `❤` are the same icon: drop variation selectors and surrounding space
+fn normalize(emoji: &str) -> String {
+    emoji.trim().chars().filter(|c| *c != '\u{FE0F}' && *c != '\u{FE0E}').collect()
+}
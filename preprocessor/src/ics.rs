@@ -0,0 +1,168 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::journals;
+use crate::page::{self, Page, PageIndex};
+use crate::previews;
+use crate::resolver;
+use crate::schedule::{self, Event, Kind};
+
+/// Calendar written to the output root (`--emit-ics`)
+pub const ICS_FILE: &str = "calendar.ics";
+
+/// Longest content line, in bytes, before it is folded (RFC 5545 §3.1)
+const MAX_LINE: usize = 75;
+
+/// Build the calendar: an all-day event per published journal day (summary:
+/// its first block) and one per open SCHEDULED/DEADLINE item. `base_url`
+/// (`--base-url`) makes event URLs absolute; without it they are site-relative.
+pub fn calendar(index: &PageIndex, name: &str, base_url: Option<&str>, include_private: bool) -> String {
+    let url = |slug: &str| match base_url {
+        Some(base) => format!("{}/{}", base.trim_end_matches('/'), slug),
+        None => format!("/{}", slug),
+    };
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//logseq-to-quartz//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        format!("X-WR-CALNAME:{}", text(name)),
+    ];
+
+    let mut days: Vec<(String, &Page)> = index
+        .iter()
+        .filter(|p| include_private || !p.is_private())
+        .filter_map(|p| {
+            let (date, _) = journals::parse_journal_date(p.name.strip_prefix("journals/")?)?;
+            Some((date, p))
+        })
+        .collect();
+    days.sort_by(|a, b| a.0.cmp(&b.0));
+    for (date, page) in days {
+        let day = date.replace('-', "");
+        let slug = resolver::site_path(page);
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:journal-{}@logseq-to-quartz", date),
+            format!("DTSTAMP:{}T000000Z", day),
+            format!("DTSTART;VALUE=DATE:{}", day),
+            format!("SUMMARY:{}", text(&first_block(page).unwrap_or_else(|| journal_title(&date)))),
+            format!("URL:{}", url(&slug)),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+
+    // Same page, kind and date more than once: number them so UIDs stay unique
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for event in schedule::events(index, include_private) {
+        let key = format!("{}-{}-{}", kind_name(event.kind), event.date, event.slug);
+        let n = seen.entry(key.clone()).and_modify(|n| *n += 1).or_insert(0);
+        let uid = if *n == 0 { key } else { format!("{}-{}", key, n) };
+        lines.extend(vevent(&event, &uid, &url(&event.slug)));
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold(line)).collect::<Vec<_>>().join("")
+}
+
+/// Write `calendar.ics`. Returns the number of events.
+pub fn write(
+    output_dir: &Path,
+    index: &PageIndex,
+    name: &str,
+    base_url: Option<&str>,
+    include_private: bool,
+) -> Result<usize> {
+    let calendar = calendar(index, name, base_url, include_private);
+    fs::write(output_dir.join(ICS_FILE), &calendar)?;
+    Ok(calendar.matches("BEGIN:VEVENT").count())
+}
+
+fn vevent(event: &Event, uid: &str, url: &str) -> Vec<String> {
+    let day = event.date.replace('-', "");
+    let start = match &event.time {
+        // Floating local time: the graph has no timezone
+        Some(time) => format!("DTSTART:{}T{:0>4}00", day, time.replace(':', "")),
+        None => format!("DTSTART;VALUE=DATE:{}", day),
+    };
+    let icon = if event.kind == Kind::Deadline { "⏰" } else { "📅" };
+    let mut lines = vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}@logseq-to-quartz", uid),
+        format!("DTSTAMP:{}T000000Z", day),
+        start,
+        format!("SUMMARY:{}", text(&format!("{} {}", icon, event.text))),
+        format!("DESCRIPTION:{}", text(&event.page)),
+        format!("CATEGORIES:{}", kind_name(event.kind)),
+        format!("URL:{}", url),
+    ];
+    if let Some(rule) = event.repeat.as_deref().and_then(rrule) {
+        lines.push(format!("RRULE:{}", rule));
+    }
+    lines.push("END:VEVENT".to_string());
+    lines
+}
+
+/// Recurrence rule for a Logseq repeater (`.+2w` → `FREQ=WEEKLY;INTERVAL=2`).
+/// Calendars can't express "after completion", so all repeater kinds repeat from the date.
+fn rrule(repeater: &str) -> Option<String> {
+    let spec = repeater.trim_start_matches(['.', '+']);
+    let (count, unit) = spec.split_at(spec.len().checked_sub(1)?);
+    let freq = match unit {
+        "h" => "HOURLY",
+        "d" => "DAILY",
+        "w" => "WEEKLY",
+        "m" => "MONTHLY",
+        "y" => "YEARLY",
+        _ => return None,
+    };
+    let interval: u32 = count.parse().ok()?;
+    Some(format!("FREQ={};INTERVAL={}", freq, interval))
+}
+
+fn kind_name(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Scheduled => "scheduled",
+        Kind::Deadline => "deadline",
+    }
+}
+
+/// Plain text of the first block with text, for a journal day's summary
+fn first_block(page: &Page) -> Option<String> {
+    let (_, body) = page::parse_properties(&page.content);
+    body.lines()
+        .filter(|line| line.starts_with("- "))
+        .map(previews::plain_text)
+        .find(|text| !text.is_empty())
+}
+
+fn journal_title(date: &str) -> String {
+    journals::parse_journal_date(date).map_or(date.to_string(), |(_, title)| title)
+}
+
+/// Escape a TEXT value: backslash, semicolon, comma and newlines
+fn text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// A content line with CRLF, folded at 75 bytes (continuations start with a space)
+fn fold(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + 8);
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+    out
+}
@@ -0,0 +1,93 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::page::{Page, PageIndex};
+use crate::resolver;
+
+/// Index written by `--emit-index` (or `--emit-index json`)
+pub const INDEX_FILE: &str = "pages.json";
+
+/// Index written by `--emit-index ndjson`, one page per line
+pub const INDEX_NDJSON_FILE: &str = "pages.ndjson";
+
+/// Layout of the page index file
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexFormat {
+    /// One JSON array (pages.json)
+    Json,
+    /// One JSON object per line (pages.ndjson), for streaming and line tools
+    Ndjson,
+}
+
+impl IndexFormat {
+    pub fn file_name(self) -> &'static str {
+        match self {
+            IndexFormat::Json => INDEX_FILE,
+            IndexFormat::Ndjson => INDEX_NDJSON_FILE,
+        }
+    }
+}
+
+/// A page of the index as scripts and site components read it
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Entry {
+    pub name: String,
+    pub title: String,
+    /// Quartz slug the page is published under
+    pub slug: String,
+    pub aliases: Vec<String>,
+    pub tags: Vec<String>,
+    pub namespace: Option<String>,
+    /// Page properties as written (`key:: value`), keys in lower case
+    pub properties: BTreeMap<String, String>,
+    pub created: Option<String>,
+    pub modified: Option<String>,
+    pub journal: bool,
+}
+
+/// Entries of all published pages and journals, by name
+pub fn build(index: &PageIndex, include_private: bool) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = index
+        .iter()
+        .filter(|p| include_private || !p.is_private())
+        .map(entry)
+        .collect();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    entries
+}
+
+/// Write the index in `format` to the output root. Returns the number of pages.
+pub fn write(output_dir: &Path, index: &PageIndex, format: IndexFormat, include_private: bool) -> Result<usize> {
+    let entries = build(index, include_private);
+    let body = match format {
+        IndexFormat::Json => serde_json::to_string_pretty(&entries)?,
+        IndexFormat::Ndjson => {
+            let mut lines = String::new();
+            for entry in &entries {
+                lines.push_str(&serde_json::to_string(entry)?);
+                lines.push('\n');
+            }
+            lines
+        }
+    };
+    fs::write(output_dir.join(format.file_name()), body)?;
+    Ok(entries.len())
+}
+
+fn entry(page: &Page) -> Entry {
+    Entry {
+        name: page.name.clone(),
+        title: page.title(),
+        slug: resolver::site_path(page),
+        aliases: page.aliases.clone(),
+        tags: page.tags.clone(),
+        namespace: page.namespace.clone(),
+        properties: page.properties.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+        created: page.created.clone(),
+        modified: page.modified.clone(),
+        journal: page.name.starts_with("journals/"),
+    }
+}
+
@@ -1,17 +1,63 @@
 use anyhow::Result;
+use chrono::NaiveDate;
 use lazy_static::lazy_static;
 use regex::Regex;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
 use crate::config::Config;
 use crate::content;
-use crate::page::{parse_properties, PageIndex};
+use crate::edn::{self, Form};
+use crate::escape;
+use crate::frontmatter;
+use crate::metrics;
+use crate::on_this_day::OnThisDay;
+use crate::outline;
+use crate::page::{parse_properties, with_toc, PageIndex};
+use crate::properties;
+use crate::progress;
+use crate::query;
+use crate::resolver;
+use crate::rollups;
+use crate::social;
+use crate::source;
+use crate::warnings;
 
 lazy_static! {
     // Journal date patterns
     static ref DATE_UNDERSCORE_RE: Regex = Regex::new(r"^(\d{4})_(\d{2})_(\d{2})$").unwrap();
     static ref DATE_DASH_RE: Regex = Regex::new(r"^(\d{4})-(\d{2})-(\d{2})$").unwrap();
+
+    // Tags used in a block: #tag, #[[multi word tag]]
+    static ref BLOCK_TAG_RE: Regex = Regex::new(r#"(?:^|\s)#(?:\[\[([^\]]+)\]\]|([^\s#\[\],.!?;:"'()]+))"#).unwrap();
+
+    // tags:: property inside a block
+    static ref BLOCK_TAGS_PROP_RE: Regex = Regex::new(r"(?m)^\s*(?:-\s+)?tags::\s*(.+)$").unwrap();
+}
+
+/// Output file (in journals/) of the tag aggregation page
+pub const TAG_INDEX_NAME: &str = "by-tag";
+
+/// What to publish when a page is named like a journal day (`pages/2025-01-01.md`
+/// next to `journals/2025_01_01.md`, `--date-pages`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum DatePages {
+    /// The journal with the page's blocks appended
+    #[default]
+    Concat,
+    /// The journal as it is; the page is dropped
+    PreferJournal,
+    /// The page's content at the journal's URL; the journal is dropped
+    PreferPage,
+}
+
+/// A query from config.edn `:default-queries {:journals [...]}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefaultQuery {
+    pub title: String,
+    /// Simple query (`(task NOW DOING)`)
+    pub query: String,
 }
 
 /// Process all journal files
@@ -24,25 +70,32 @@ pub fn process_journals(
     let mut count = 0;
     let mut entries = Vec::new();
 
-    for entry in fs::read_dir(journals_dir)? {
-        let entry = entry?;
-        let path = entry.path();
+    // In file name order, whatever order the filesystem lists them in
+    let mut paths = fs::read_dir(journals_dir)?.map(|entry| entry.map(|e| e.path())).collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
 
-        if path.extension().map_or(false, |ext| ext == "md") {
+    let on_this_day = config.on_this_day.then(|| OnThisDay::new(page_index, config.include_private));
+
+    let bar = progress::bar("journals", paths.len());
+    for path in paths {
+        bar.inc(1);
+        if path.extension().is_some_and(|ext| ext == "md") && !config.hidden.contains(&path) {
             if let Some(filename) = path.file_stem() {
                 let filename = filename.to_string_lossy();
 
                 if let Some((date, title)) = parse_journal_date(&filename) {
-                    match process_journal_file(&path, output_dir, &date, &title, page_index, config) {
+                    match process_journal_file(&path, output_dir, &date, &title, page_index, on_this_day.as_ref(), config) {
                         Ok(true) => {
                             entries.push((date.clone(), title.clone(), filename.to_string()));
                             count += 1;
                         }
                         Ok(false) => {}
+                        Err(e) if config.strict => {
+                            return Err(e.context(format!("failed to process {}", path.display())));
+                        }
                         Err(e) => {
-                            if config.verbose {
-                                eprintln!("Error processing journal {:?}: {}", path, e);
-                            }
+                            let page = resolver::journal_output_name(&date);
+                            warnings::global().error(&format!("{}: {}", path.display(), e), Some(&page));
                         }
                     }
                 }
@@ -50,16 +103,31 @@ pub fn process_journals(
         }
     }
 
-    // Create journal index
+    bar.finish_and_clear();
+
+    // Create journal index, with the graph's default journal queries on top
     if !entries.is_empty() {
-        create_journal_index(output_dir, &entries)?;
+        let queries = if config.journal_queries {
+            default_queries(&config.input_dir.join("logseq/config.edn"))
+        } else {
+            Vec::new()
+        };
+        create_journal_index(output_dir, &entries, &queries, page_index)?;
+
+        let days: Vec<String> = entries.iter().map(|(date, _, _)| date.clone()).collect();
+        rollups::write(output_dir, &days, &config.journal_rollups)?;
+    }
+
+    // Journal blocks grouped by tag and month
+    if let Some(tags) = &config.journal_tags {
+        create_tag_index(output_dir, page_index, tags, config.include_private)?;
     }
 
     Ok(count)
 }
 
 /// Parse journal filename to date and title
-fn parse_journal_date(filename: &str) -> Option<(String, String)> {
+pub fn parse_journal_date(filename: &str) -> Option<(String, String)> {
     let months = [
         "January", "February", "March", "April", "May", "June",
         "July", "August", "September", "October", "November", "December",
@@ -71,7 +139,7 @@ fn parse_journal_date(filename: &str) -> Option<(String, String)> {
         let month: usize = caps.get(2)?.as_str().parse().ok()?;
         let day: usize = caps.get(3)?.as_str().parse().ok()?;
 
-        if month >= 1 && month <= 12 && day >= 1 && day <= 31 {
+        if (1..=12).contains(&month) && (1..=31).contains(&day) {
             let date = format!("{}-{:02}-{:02}", year, month, day);
             let title = format!("{} {}, {}", months[month - 1], day, year);
             return Some((date, title));
@@ -84,7 +152,7 @@ fn parse_journal_date(filename: &str) -> Option<(String, String)> {
         let month: usize = caps.get(2)?.as_str().parse().ok()?;
         let day: usize = caps.get(3)?.as_str().parse().ok()?;
 
-        if month >= 1 && month <= 12 && day >= 1 && day <= 31 {
+        if (1..=12).contains(&month) && (1..=31).contains(&day) {
             let date = format!("{}-{:02}-{:02}", year, month, day);
             let title = format!("{} {}, {}", months[month - 1], day, year);
             return Some((date, title));
@@ -101,68 +169,285 @@ fn process_journal_file(
     date: &str,
     title: &str,
     page_index: &PageIndex,
+    on_this_day: Option<&OnThisDay>,
     config: &Config,
 ) -> Result<bool> {
-    let content = fs::read_to_string(source_path)?;
+    // Files of the same day are published once, merged (see `merge_duplicates`),
+    // and a page named after the day may have been merged in (see `merge_date_pages`)
+    let indexed = page_index.with_slug(&resolver::journal_output_name(date));
+    if indexed.is_some_and(|page| page.duplicates.iter().any(|file| file == source_path)) {
+        return Ok(false);
+    }
+    // The index read the file already
+    let content = match indexed.filter(|page| page.source.as_deref() == Some(source_path)) {
+        Some(page) => page.content.clone(),
+        None => source::read_page(source_path)?,
+    };
     let (properties, remaining) = parse_properties(&content);
 
     // Skip private journals
+    if !config.include_private && properties::is_private(&properties) {
+        return Ok(false);
+    }
+    // Private blocks go from the tree; the links of what is left are checked below
+    let mut blocks = outline::parse(&remaining);
     if !config.include_private {
-        if let Some(private) = properties.get("private") {
-            if private.to_lowercase() == "true" {
-                return Ok(false);
-            }
-        }
+        blocks.drop_private();
     }
+    let remaining = blocks.render();
 
     // Generate frontmatter
     let mut frontmatter = format!(
         "---\ntitle: \"{}\"\ndate: {}\n",
-        title.replace('"', "\\\""),
+        escape::yaml(title),
         date
     );
 
     // Add tags if present
-    if let Some(tags) = properties.get("tags") {
+    let tags = frontmatter::property_tags(&properties);
+    if !tags.is_empty() {
         frontmatter.push_str("tags:\n");
-        for tag in tags.split(',') {
-            let tag = tag
-                .trim()
-                .trim_start_matches("[[")
-                .trim_end_matches("]]");
-            if !tag.is_empty() {
-                frontmatter.push_str(&format!("  - {}\n", tag));
-            }
+        for tag in tags {
+            frontmatter.push_str(&format!("  - {}\n", escape::yaml_scalar(&tag)));
         }
     }
 
     frontmatter.push_str("---\n");
 
+    // Links to pages that don't exist (stubs fill these in when enabled)
+    if !config.create_stubs {
+        let page_name = resolver::journal_output_name(date);
+        for link in content::unresolved_links(&remaining, page_index) {
+            warnings::global().warn("broken-link", &link, Some(&page_name));
+        }
+    }
+    // Section links to headings the page doesn't have, and links resolved by a guess
+    for link in content::missing_anchors(&remaining, page_index) {
+        warnings::global().warn("broken-anchor", &link, Some(&resolver::journal_output_name(date)));
+    }
+    for (link, page) in content::fallback_links(&remaining, page_index) {
+        warnings::global().warn("link-fallback", &format!("{} → {}", link, page), Some(&resolver::journal_output_name(date)));
+    }
+
+    // Inline #tags: linked or stripped per --inline-tags, and listed in frontmatter
+    let inline_tags = content::inline_tags(&mut blocks, config.inline_tags);
+    let frontmatter = frontmatter::insert_list(&frontmatter, "tags", &frontmatter::new_tags(&properties, &inline_tags));
+
     // Transform content
-    let transformed = content::transform(&remaining, page_index);
+    let transformed = content::transform_outline(blocks, page_index);
+    warnings::global().page_diagnostics(&transformed.diagnostics, &resolver::journal_output_name(date));
+    let frontmatter = frontmatter::insert_list(&frontmatter, "listing", &transformed.listing);
+    let frontmatter = social::insert_frontmatter(
+        &frontmatter,
+        &properties,
+        &transformed.content,
+        config.description_words,
+        config.base_url.as_deref(),
+    );
+    let frontmatter = if config.word_count {
+        metrics::insert_frontmatter(&frontmatter, metrics::record(&config.output_dir, &transformed.content))
+    } else {
+        frontmatter
+    };
 
-    // Write output
-    let output_path = output_dir.join(format!("{}.md", date));
-    let output = format!("{}\n{}", frontmatter, transformed);
+    // Write output (output_dir is the journals/ folder)
+    let output_path = resolver::output_path(output_dir, date);
+    let body = with_toc(transformed.content, &properties, config);
+    let body = properties::metadata_block(&properties, &content, config.page_properties, page_index) + &body;
+    // Links to the same day in earlier years (--on-this-day)
+    let section = on_this_day
+        .zip(NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .and_then(|(days, date)| days.section(date));
+    let body = match section {
+        Some(section) => format!("{}\n{}", body.trim_end(), section),
+        None => body,
+    };
+    let output = format!("{}\n{}", frontmatter, body);
     fs::write(output_path, output)?;
 
     Ok(true)
 }
 
-/// Create journal index page with embedded content
-fn create_journal_index(output_dir: &Path, entries: &[(String, String, String)]) -> Result<()> {
+/// Journal queries from config.edn `:default-queries`. Advanced (datalog)
+/// queries can't be run here; they are skipped with a `default-query` warning.
+pub fn default_queries(config_path: &Path) -> Vec<DefaultQuery> {
+    let Some(queries) = source::read_to_string(config_path)
+        .ok()
+        .and_then(|config| edn::setting(&config, ":default-queries"))
+    else {
+        return Vec::new();
+    };
+    let Some(journals) = queries.get("journals") else {
+        return Vec::new();
+    };
+    journals
+        .items()
+        .iter()
+        .filter_map(|entry| {
+            let title = entry.get("title").map(form_text).unwrap_or_default();
+            match entry.get("query") {
+                Some(Form::Str(query)) => {
+                    let query = query.trim().to_string();
+                    let title = if title.is_empty() { query.clone() } else { title };
+                    Some(DefaultQuery { title, query })
+                }
+                _ => {
+                    let name = if title.is_empty() { "(untitled)" } else { &title };
+                    warnings::global().warn("default-query", &format!("{}: only simple queries can be rendered", name), None);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Text of a title: a string, or the strings in a hiccup title (`[:h3 "🔨 NOW"]`)
+fn form_text(form: &Form) -> String {
+    match form {
+        Form::Str(text) => text.clone(),
+        Form::Vector(items) | Form::List(items) => {
+            items.iter().map(form_text).filter(|t| !t.is_empty()).collect::<Vec<_>>().join(" ")
+        }
+        _ => String::new(),
+    }
+}
+
+/// Create journal index page with embedded content, after the default queries' results
+fn create_journal_index(
+    output_dir: &Path,
+    entries: &[(String, String, String)],
+    queries: &[DefaultQuery],
+    page_index: &PageIndex,
+) -> Result<()> {
     let mut sorted = entries.to_vec();
     sorted.sort_by(|a, b| b.0.cmp(&a.0)); // Sort by date descending
 
     let mut content = String::from("---\ntitle: \"📅 Journals\"\n---\n\n");
 
+    for default_query in queries {
+        content.push_str(&format!("## {}\n\n", default_query.title));
+        match query::execute(&default_query.query, page_index) {
+            Ok(results) => content.push_str(&query::results_to_markdown(&results, &default_query.query)),
+            Err(error) => content.push_str(&query::unsupported_to_markdown(&default_query.query, &error)),
+        }
+        if let Some(date) = page_index.query_date() {
+            content.push_str(&format!("\n\n{}", query::freshness_note(date)));
+        }
+        content.push_str("\n\n---\n\n");
+    }
+
     for (date, title, _) in sorted {
         // Add heading with link, then embed the journal content
-        content.push_str(&format!("## [[journals/{}|{} - {}]]\n\n", date, date, title));
-        content.push_str(&format!("![[journals/{}]]\n\n---\n\n", date));
+        let target = resolver::journal_output_name(&date);
+        content.push_str(&format!("## [[{}|{} - {}]]\n\n", target, date, title));
+        content.push_str(&format!("![[{}]]\n\n---\n\n", target));
     }
 
     fs::write(output_dir.join("index.md"), content)?;
 
     Ok(())
 }
+
+/// Create the tag aggregation page: journal blocks grouped by the tags they use, then by month.
+/// `tags` limits the page to those tags (empty = every tag used in journals).
+pub fn create_tag_index(
+    output_dir: &Path,
+    page_index: &PageIndex,
+    tags: &[String],
+    include_private: bool,
+) -> Result<usize> {
+    let wanted: Vec<String> = tags.iter().map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty()).collect();
+
+    // tag → date → blocks
+    let mut by_tag: BTreeMap<String, BTreeMap<String, Vec<String>>> = BTreeMap::new();
+    for page in page_index.iter() {
+        let Some(file_name) = page.name.strip_prefix("journals/") else { continue };
+        let Some((date, _)) = parse_journal_date(file_name) else { continue };
+        if !include_private && page.is_private() {
+            continue;
+        }
+
+        let (_, body) = parse_properties(&page.content);
+        let mut body = outline::parse(&body);
+        if !include_private {
+            body.drop_private();
+        }
+        for block in top_level_blocks(&body) {
+            for tag in block_tags(&block) {
+                if !wanted.is_empty() && !wanted.contains(&tag) {
+                    continue;
+                }
+                by_tag
+                    .entry(tag)
+                    .or_default()
+                    .entry(date.clone())
+                    .or_default()
+                    .push(block.clone());
+            }
+        }
+    }
+
+    let mut content = String::from("---\ntitle: \"🏷️ Journals by Tag\"\n---\n\n");
+    for (tag, days) in &by_tag {
+        content.push_str(&format!("## {}\n\n", tag));
+        let mut month = String::new();
+        // Newest first, like the journal index
+        for (date, blocks) in days.iter().rev() {
+            let date_month = month_title(date);
+            if date_month != month {
+                content.push_str(&format!("### {}\n\n", date_month));
+                month = date_month;
+            }
+            let title = parse_journal_date(date).map_or(date.clone(), |(_, title)| title);
+            content.push_str(&format!("- [[{}|{}]]\n", resolver::journal_output_name(date), title));
+            for block in blocks {
+                let block = content::transform(block, page_index);
+                for line in block.lines().filter(|l| !l.trim().is_empty()) {
+                    content.push_str(&format!("  {}\n", line));
+                }
+            }
+            content.push('\n');
+        }
+    }
+    if by_tag.is_empty() {
+        content.push_str("No tagged journal entries.\n");
+    }
+
+    fs::write(output_dir.join(format!("{}.md", TAG_INDEX_NAME)), content)?;
+    Ok(by_tag.len())
+}
+
+/// Top-level blocks of a journal, each rendered with its children
+fn top_level_blocks(outline: &outline::Outline) -> Vec<String> {
+    let mut blocks = vec![outline.preamble.join("\n")];
+    blocks.extend(outline.blocks.iter().map(outline::Block::render));
+    blocks.retain(|b| !b.trim().trim_start_matches('-').trim().is_empty());
+    blocks
+}
+
+/// Lowercased tags used anywhere in a block (#tag, #[[tag]], tags:: property)
+fn block_tags(block: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    let mut add = |tag: &str| {
+        let tag = tag.trim().trim_start_matches("[[").trim_end_matches("]]").trim().to_lowercase();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    };
+    for caps in BLOCK_TAGS_PROP_RE.captures_iter(block) {
+        caps[1].split(',').for_each(&mut add);
+    }
+    for caps in BLOCK_TAG_RE.captures_iter(block) {
+        if let Some(tag) = caps.get(1).or(caps.get(2)) {
+            add(tag.as_str());
+        }
+    }
+    tags
+}
+
+/// "January 2025" for a YYYY-MM-DD date
+fn month_title(date: &str) -> String {
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map(|d| d.format("%B %Y").to_string())
+        .unwrap_or_else(|_| date.to_string())
+}
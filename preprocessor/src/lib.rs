@@ -0,0 +1,58 @@
+//! Logseq graph to Quartz content conversion; the `logseq-to-quartz` binary
+//! drives these modules, and the fuzz targets in `fuzz/` call them directly.
+
+pub mod analyze;
+pub mod archive;
+pub mod badges;
+pub mod callouts;
+pub mod changes;
+pub mod config;
+pub mod content;
+pub mod edn;
+pub mod embeds;
+pub mod escape;
+pub mod export;
+pub mod external;
+pub mod favorites;
+pub mod feed;
+pub mod frontmatter;
+pub mod git;
+pub mod hiccup;
+pub mod home;
+pub mod hooks;
+pub mod i18n;
+pub mod icons;
+pub mod ics;
+pub mod index_file;
+pub mod journals;
+pub mod metrics;
+pub mod on_this_day;
+pub mod outline;
+pub mod page;
+pub mod pdf;
+pub mod previews;
+pub mod progress;
+pub mod properties;
+pub mod quartz;
+pub mod query;
+pub mod query_parser;
+pub mod random;
+pub mod references;
+pub mod related;
+pub mod rename;
+pub mod resolver;
+pub mod rng;
+pub mod rollups;
+pub mod sanitize;
+pub mod schedule;
+pub mod series;
+pub mod sidebar;
+pub mod sitemap;
+pub mod social;
+pub mod source;
+pub mod staging;
+pub mod suggest;
+pub mod timezone;
+pub mod toc;
+pub mod warnings;
+pub mod workflow;
@@ -1,15 +1,15 @@
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use log::{debug, error, info, warn};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::time::Instant;
 
-mod config;
-mod content;
-mod favorites;
-mod frontmatter;
-mod journals;
-mod page;
-mod query;
+// The modules, reachable as `crate::page`, ... here and in the tests
+use logseq_to_quartz::*;
+
+// Runs whole builds, so it lives with `run_preprocessor`
+mod bench;
 
 #[cfg(test)]
 mod tests;
@@ -20,6 +20,9 @@ use config::Config;
 #[command(name = "logseq-to-quartz")]
 #[command(about = "Fast Logseq to Quartz preprocessor")]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Path to Logseq graph root (contains pages/, journals/, logseq/)
     #[arg(short, long, default_value = ".")]
     input: PathBuf,
@@ -28,6 +31,14 @@ struct Cli {
     #[arg(short, long, default_value = "quartz-content")]
     output: PathBuf,
 
+    /// Quartz checkout to build into: content goes to its content/ folder and quartz.config.ts gets the site title, base URL and locale (backup in quartz.config.ts.bak)
+    #[arg(long, value_name = "DIR", conflicts_with = "output")]
+    quartz_dir: Option<PathBuf>,
+
+    /// How the build reaches the output directory: sync (build in <output>.staging, then only write changed files and remove stale ones, keeping files the preprocessor didn't write), swap (replace the whole output folder on success) or in-place
+    #[arg(long, value_enum, default_value_t = staging::OutputMode::Sync)]
+    output_mode: staging::OutputMode,
+
     /// Include private pages (private:: true)
     #[arg(long, default_value_t = false)]
     include_private: bool,
@@ -36,9 +47,173 @@ struct Cli {
     #[arg(long, default_value_t = false)]
     create_stubs: bool,
 
-    /// Verbose output
-    #[arg(short, long)]
-    verbose: bool,
+    /// Markdown template for stub page bodies ({{title}}, {{name}}, {{linked_from}}, {{linked_count}})
+    #[arg(long)]
+    stub_template: Option<PathBuf>,
+
+    /// Generate journals/by-tag: journal blocks grouped by tag and month (optionally only these comma-separated tags)
+    #[arg(long, num_args = 0..=1, default_missing_value = "")]
+    journal_tags: Option<String>,
+
+    /// Show config.edn :default-queries {:journals [...]} at the top of the journals index, like Logseq's journals view
+    #[arg(long, default_value_t = false)]
+    journal_queries: bool,
+
+    /// Generate journal roll-up pages embedding each period's days, comma-separated: week (journals/2025-W03), month (journals/2025-01)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    journal_rollups: Vec<rollups::Rollup>,
+
+    /// Add an "On this day" section to each journal linking the same date in earlier years
+    #[arg(long, default_value_t = false)]
+    on_this_day: bool,
+
+    /// Add a generated "Contents" section to pages with several headings (toc:: overrides per page)
+    #[arg(long, default_value_t = false)]
+    toc: bool,
+
+    /// Inline #tags: keep as text, link to the Quartz tag page, or strip (link and strip also add them to frontmatter tags)
+    #[arg(long, value_enum, default_value_t = content::TagStyle::Keep)]
+    inline_tags: content::TagStyle,
+
+    /// Comma-separated properties shown as status badges (frontmatter + cssclasses)
+    #[arg(long, default_value = "status")]
+    badge_properties: String,
+
+    /// Also show badges as a line at the top of the page
+    #[arg(long, default_value_t = false)]
+    badge_line: bool,
+
+    /// Show page properties at the top of the page: hidden (frontmatter only), table or list
+    #[arg(long, value_enum, default_value_t = properties::PageProperties::Hidden)]
+    page_properties: properties::PageProperties,
+
+    /// Comma-separated callout mappings KIND=TYPE for #+BEGIN_KIND blocks and > [!KIND] callouts (e.g. warning=danger,quote=quote; TYPE none = plain blockquote)
+    #[arg(long, value_name = "MAPPINGS")]
+    callout_map: Option<String>,
+
+    /// YouTube, Vimeo and tweet embeds: iframe (player/post) or link (nothing loaded from the provider)
+    #[arg(long, value_enum, default_value_t = embeds::EmbedStyle::Iframe)]
+    embeds: embeds::EmbedStyle,
+
+    /// What the site root shows: copy (of the home page), embed (the home page, keeping one URL), redirect (to the home page), journal (the latest journal) or journals (the most recent journals, newest first)
+    #[arg(long, value_enum, default_value_t = home::HomeMode::Copy)]
+    home_mode: home::HomeMode,
+
+    /// Journals shown with --home-mode journals
+    #[arg(long, default_value_t = home::HOME_JOURNALS, value_name = "N")]
+    home_journals: usize,
+
+    /// Aliases (alias::) as frontmatter aliases, or as redirect pages at the alias path (/cv → /cyber-valley)
+    #[arg(long, value_enum, default_value_t = page::AliasStyle::Frontmatter)]
+    alias_style: page::AliasStyle,
+
+    /// Pages named like a journal day (pages/2025-01-01.md next to journals/2025_01_01.md): concat (journal, then the page's blocks), prefer-journal or prefer-page
+    #[arg(long, value_enum, default_value_t = journals::DatePages::Concat)]
+    date_pages: journals::DatePages,
+
+    /// Levels of children shown with a block embed {{embed ((id))}} (default: the whole subtree; 0 = the block alone)
+    #[arg(long, value_name = "N")]
+    embed_depth: Option<usize>,
+
+    /// Blocks collapsed in Logseq (collapsed:: true): strip (shown open) or details (folded in a <details> element with the block as its summary)
+    #[arg(long, value_enum, default_value_t = outline::CollapsedStyle::Strip)]
+    collapsed: outline::CollapsedStyle,
+
+    /// Seed of (sample n) and (shuffle) query results, for reproducible builds (default: a new draw each build)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// How (page-tags ...) queries compare tags: ignore-case, or plural to also match singular/plural forms (book = books)
+    #[arg(long, value_enum, default_value_t = query::TagMatch::IgnoreCase)]
+    tag_match: query::TagMatch,
+
+    /// What a link naming no page resolves to: off (page names only), exact (also aliases and journal titles), prefix (also the longest page name it starts with) or fuzzy (also near misses)
+    #[arg(long, value_enum, default_value_t = resolver::LinkFallback::Prefix)]
+    link_fallback: resolver::LinkFallback,
+
+    /// Characters a prefix or fuzzy match must have to be taken
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    link_fallback_min_length: usize,
+
+    /// Share of the link (0 to 1) a prefix or fuzzy match must cover to be taken ("visit" covers 0.6 of "visit us")
+    #[arg(long, value_name = "RATIO", default_value_t = 0.0)]
+    link_fallback_min_ratio: f64,
+
+    /// Least confidence (0 to 1) of a --link-fallback fuzzy match; lower finds more near misses and more wrong ones
+    #[arg(long, value_name = "CONFIDENCE", default_value_t = resolver::FUZZY_THRESHOLD)]
+    fuzzy_threshold: f64,
+
+    /// Note the build date under each query's results ("Results generated 2025-01-15"), as they don't update until the next build
+    #[arg(long)]
+    query_dates: bool,
+
+    /// Width of embedded PDF viewers (CSS length)
+    #[arg(long, default_value = "100%", value_name = "LENGTH")]
+    pdf_width: String,
+
+    /// Height of embedded PDF viewers (CSS length)
+    #[arg(long, default_value = "600px", value_name = "LENGTH")]
+    pdf_height: String,
+
+    /// Reduce raw HTML and hiccup to an allowlist of tags and attributes (for graphs with untrusted contributors)
+    #[arg(long)]
+    sanitize_html: bool,
+
+    /// Words in the description derived for pages without description:: (for social cards; 0 = none)
+    #[arg(long, default_value_t = social::DESCRIPTION_WORDS, value_name = "N")]
+    description_words: usize,
+
+    /// Add wordcount: and readingTime: (minutes) to frontmatter, and content totals to the run report
+    #[arg(long)]
+    word_count: bool,
+
+    /// Fetch titles of external URLs: bare URLs become [Title](url) links, and _link_previews.json is written
+    #[arg(long)]
+    link_titles: bool,
+
+    /// Cache of fetched link titles (default: logseq/link-cache.json in the graph)
+    #[arg(long, value_name = "FILE")]
+    link_cache: Option<PathBuf>,
+
+    /// Link an archive.org snapshot after each external link (snapshots looked up once and cached)
+    #[arg(long)]
+    archive_links: bool,
+
+    /// With --archive-links, ask archive.org to capture URLs that have no snapshot yet
+    #[arg(long, requires = "archive_links")]
+    archive_save: bool,
+
+    /// Cache of archive.org snapshots (default: logseq/archive-cache.json in the graph)
+    #[arg(long, value_name = "FILE")]
+    archive_cache: Option<PathBuf>,
+
+    /// Don't fetch anything over the network; use cached link titles and snapshots only
+    #[arg(long)]
+    offline: bool,
+
+    /// More output: -v lists per-page diagnostics as they happen, -vv traces every file (RUST_LOG overrides)
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Abort on the first page that fails to process (default: skip it and fail at the end)
+    #[arg(long, default_value_t = false)]
+    strict: bool,
+
+    /// Only print errors
+    #[arg(short, long, default_value_t = false, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Print how long each phase of the build took (also in _stats.json)
+    #[arg(long, default_value_t = false)]
+    timings: bool,
+
+    /// Timezone that git and file times, timestamp properties and `today` in queries are turned into days in: local, UTC, +09:00 or a name like Europe/Berlin
+    #[arg(long, default_value = "local", value_name = "ZONE")]
+    timezone: timezone::Timezone,
+
+    /// Build the graph as it was at this git revision (commit, tag, branch, HEAD~N) instead of the working tree
+    #[arg(long, value_name = "REF")]
+    at_revision: Option<String>,
 
     /// Override default home page (from config.edn :default-home)
     #[arg(long)]
@@ -52,54 +227,576 @@ struct Cli {
     #[arg(long)]
     favorites: Option<String>,
 
+    /// What favorites are published as, comma-separated: pages (a redirect page each in favorites/), index (favorites/index.md linking to the pages), json (favorites.json)
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = favorites::DEFAULT_OUTPUTS.to_vec())]
+    favorites_outputs: Vec<favorites::FavoritesOutput>,
+
     /// Site name for meta tags (written to _site_config.json)
     #[arg(long)]
     site_name: Option<String>,
+
+    /// Persisted slug map (JSON) used to keep old URLs of renamed pages as aliases
+    #[arg(long)]
+    slug_map: Option<PathBuf>,
+
+    /// Output paths for non-ASCII page names: keep them (unicode) or transliterate (ascii)
+    #[arg(long, value_enum, default_value_t = resolver::SlugStyle::Unicode)]
+    slug_style: resolver::SlugStyle,
+
+    /// Affected pages listed per warning in the summary (0 = all; _warnings.json always lists all)
+    #[arg(long, default_value_t = 5)]
+    warning_pages: usize,
+
+    /// Print the JSON Schema of _site_config.json and exit
+    #[arg(long, default_value_t = false)]
+    print_schema: bool,
+
+    /// JSON file mapping icon:: emoji to icon assets or icon font classes (default: logseq/icons.json)
+    #[arg(long)]
+    icon_map: Option<PathBuf>,
+
+    /// Suggest tags for untagged pages (written to _tag_suggestions.json, never applied)
+    #[arg(long, default_value_t = false)]
+    suggest_tags: bool,
+
+    /// Report orphan pages, most-linked pages, dead ends and unused assets (written to _analysis.json)
+    #[arg(long, default_value_t = false)]
+    analyze: bool,
+
+    /// Write previews.json with title, icon and the first 280 characters of each page for hover popovers
+    #[arg(long, default_value_t = false)]
+    previews: bool,
+
+    /// Write the page index (names, aliases, tags, properties, dates, namespaces) for scripts: pages.json, or pages.ndjson with ndjson
+    #[arg(long, value_enum, value_name = "FORMAT", num_args = 0..=1, default_missing_value = "json")]
+    emit_index: Option<index_file::IndexFormat>,
+
+    /// Find related pages (shared tags, links, pages linking to both): a related: frontmatter list, or related.json with json
+    #[arg(long, value_enum, value_name = "OUTPUT", num_args = 0..=1, default_missing_value = "frontmatter")]
+    related: Option<related::RelatedOutput>,
+
+    /// Related pages kept per page
+    #[arg(long, value_name = "N", default_value_t = related::RELATED_COUNT)]
+    related_count: usize,
+
+    /// Write events.json: open SCHEDULED/DEADLINE items of all pages, by date (with repeaters)
+    #[arg(long, default_value_t = false)]
+    events: bool,
+
+    /// Write calendar.ics: an event per journal day and per open SCHEDULED/DEADLINE item, for calendar subscriptions
+    #[arg(long, default_value_t = false)]
+    emit_ics: bool,
+
+    /// Write feed.xml: an Atom feed of the most recently changed pages and journals
+    #[arg(long, default_value_t = false)]
+    feed: bool,
+
+    /// Number of entries in feed.xml
+    #[arg(long, default_value_t = 20, value_name = "N")]
+    feed_limit: usize,
+
+    /// Date that orders feed.xml: modified or created
+    #[arg(long, value_enum, default_value_t = feed::FeedSort::Modified)]
+    feed_sort: feed::FeedSort,
+
+    /// Write changes.md: pages modified in the last --changes-days days, grouped by day
+    #[arg(long, default_value_t = false)]
+    changes: bool,
+
+    /// Days covered by changes.md
+    #[arg(long, default_value_t = 30, value_name = "N")]
+    changes_days: usize,
+
+    /// Write random.json: the paths of listed pages, for a "random note" button
+    #[arg(long, default_value_t = false)]
+    random: bool,
+
+    /// Write sitemap.xml (listed pages with modified dates) and robots.txt (keeps crawlers off unlisted/draft pages); needs --base-url
+    #[arg(long, default_value_t = false, requires = "base_url")]
+    sitemap: bool,
+
+    /// Published site URL (e.g. https://example.org/garden), for absolute links in generated files
+    #[arg(long, value_name = "URL")]
+    base_url: Option<String>,
+
+    /// Mark the site as analytics-free in _site_config.json, so the Quartz config generator leaves analytics off
+    #[arg(long, default_value_t = false)]
+    no_analytics: bool,
+
+    /// Shell command run before the build; a non-zero exit aborts it
+    #[arg(long, value_name = "COMMAND")]
+    pre_build: Option<String>,
+
+    /// Shell command run after a successful build (env: LOGSEQ_QUARTZ_OUTPUT, LOGSEQ_QUARTZ_STATS, ...)
+    #[arg(long, value_name = "COMMAND")]
+    post_build: Option<String>,
+
+    /// Shell command run when the build fails (env: LOGSEQ_QUARTZ_ERROR, ...)
+    #[arg(long, value_name = "COMMAND")]
+    on_error: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Export the transformed graph in another format
+    Export {
+        /// Export format
+        #[arg(value_enum)]
+        format: ExportFormat,
+
+        /// Output file
+        file: PathBuf,
+
+        /// Path to Logseq graph root (contains pages/, journals/, logseq/)
+        #[arg(short, long, default_value = ".")]
+        input: PathBuf,
+
+        /// Only export this namespace (and its child pages)
+        #[arg(long)]
+        namespace: Option<String>,
+
+        /// Include private pages (private:: true)
+        #[arg(long, default_value_t = false)]
+        include_private: bool,
+    },
+
+    /// Rename a page in the source graph, rewriting links, tags and aliases that reference it
+    Rename {
+        /// Current page name
+        old: String,
+
+        /// New page name
+        new: String,
+
+        /// Path to Logseq graph root (contains pages/, journals/, logseq/)
+        #[arg(short, long, default_value = ".")]
+        input: PathBuf,
+
+        /// Show what would change without writing anything
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+
+        /// Don't copy touched files to logseq/bak/ first
+        #[arg(long, default_value_t = false)]
+        no_backup: bool,
+    },
+
+    /// Run a query against the graph and print the matching pages, to see why a published query shows what it shows
+    Query {
+        /// Query expression, with or without {{query ...}}: '(and (page-tags [[x]]) (task TODO))'
+        query: String,
+
+        /// Path to Logseq graph root (contains pages/, journals/, logseq/)
+        #[arg(short, long, default_value = ".")]
+        input: PathBuf,
+
+        /// Print the results as JSON (name, title, tags, properties, file) instead of a table
+        #[arg(long, default_value_t = false)]
+        json: bool,
+
+        /// How (page-tags ...) compares tags, as in the build
+        #[arg(long, value_enum, default_value_t = query::TagMatch::IgnoreCase)]
+        tag_match: query::TagMatch,
+
+        /// Seed of (sample n) and (shuffle), as in the build
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+
+    /// Build a generated graph several times and report how long it took
+    Bench {
+        /// Pages in the graph
+        #[arg(long, default_value_t = 1000)]
+        pages: usize,
+
+        /// Links to other pages on each page
+        #[arg(long, default_value_t = 10)]
+        links: usize,
+
+        /// Pages with a query block
+        #[arg(long, default_value_t = 50)]
+        queries: usize,
+
+        /// Journal days
+        #[arg(long, default_value_t = 365)]
+        journals: usize,
+
+        /// Builds to run; the report has the min, median and max
+        #[arg(long, default_value_t = 5)]
+        runs: usize,
+
+        /// Write the generated graph here and keep it (default: a temporary folder)
+        #[arg(long, value_name = "DIR")]
+        graph: Option<PathBuf>,
+
+        /// Also write the report as JSON, to compare runs over time
+        #[arg(long, value_name = "FILE")]
+        json: Option<PathBuf>,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ExportFormat {
+    /// One self-contained HTML file with inlined assets
+    SingleHtml,
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
     let start = Instant::now();
+    init_logging(cli.verbose, cli.quiet);
+
+    if let Some(command) = cli.command {
+        return run_command(command);
+    }
+
+    if cli.print_schema {
+        println!("{}", favorites::site_config_schema());
+        return Ok(());
+    }
 
     let favorites_override = cli.favorites.map(|f| {
         f.split(',').map(|s| s.trim().to_string()).collect::<Vec<_>>()
     });
 
-    let config = Config {
+    let mut config = Config {
+        graph_root: cli.input.clone(),
         input_dir: cli.input,
-        output_dir: cli.output,
+        output_dir: cli.quartz_dir.as_ref().map_or(cli.output, |dir| dir.join("content")),
+        quartz_dir: cli.quartz_dir,
+        output_mode: cli.output_mode,
         include_private: cli.include_private,
         create_stubs: cli.create_stubs,
-        verbose: cli.verbose,
+        stub_template: None,
+        strict: cli.strict,
+        timings: cli.timings,
+        journal_tags: cli.journal_tags.map(|t| t.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect()),
+        journal_queries: cli.journal_queries,
+        journal_rollups: cli.journal_rollups,
+        on_this_day: cli.on_this_day,
+        toc: cli.toc,
+        inline_tags: cli.inline_tags,
+        badge_properties: cli.badge_properties.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect(),
+        badge_line: cli.badge_line,
+        page_properties: cli.page_properties,
+        callout_map: callouts::CalloutMap::default(),
+        embeds: cli.embeds,
+        embed_depth: cli.embed_depth,
+        collapsed: cli.collapsed,
+        seed: cli.seed,
+        query_dates: cli.query_dates,
+        tag_match: cli.tag_match,
+        link_fallback: resolver::Fallback {
+            mode: cli.link_fallback,
+            min_length: cli.link_fallback_min_length,
+            min_ratio: cli.link_fallback_min_ratio,
+            fuzzy_threshold: cli.fuzzy_threshold,
+        },
+        alias_style: cli.alias_style,
+        home_mode: cli.home_mode,
+        home_journals: cli.home_journals,
+        date_pages: cli.date_pages,
+        pdf_frame: pdf::PdfFrame {
+            width: cli.pdf_width.clone(),
+            height: cli.pdf_height.clone(),
+        },
+        sanitize_html: cli.sanitize_html,
+        description_words: cli.description_words,
+        word_count: cli.word_count,
+        link_titles: cli.link_titles,
+        link_cache: cli.link_cache,
+        archive_links: cli.archive_links,
+        archive_save: cli.archive_save,
+        archive_cache: cli.archive_cache,
+        offline: cli.offline,
         home_override: cli.home,
         title_override: cli.title,
         favorites_override,
+        favorites_outputs: cli.favorites_outputs,
         site_name_override: cli.site_name,
+        suggest_tags: cli.suggest_tags,
+        analyze: cli.analyze,
+        previews: cli.previews,
+        emit_index: cli.emit_index,
+        related: cli.related,
+        related_count: cli.related_count,
+        events: cli.events,
+        emit_ics: cli.emit_ics,
+        feed: cli.feed.then_some(feed::FeedConfig { limit: cli.feed_limit, sort: cli.feed_sort }),
+        changes: cli.changes.then_some(cli.changes_days),
+        random: cli.random,
+        sitemap: cli.sitemap,
+        base_url: cli.base_url,
+        analytics: !cli.no_analytics,
+        slug_map: cli.slug_map,
+        slug_style: cli.slug_style,
+        warning_pages: cli.warning_pages,
+        reference_filters: Vec::new(),
+        hidden: source::Hidden::default(),
+        default_language: "en".to_string(),
+        icon_map: icons::IconMap::default(),
+        hooks: hooks::Hooks { pre_build: cli.pre_build, post_build: cli.post_build, on_error: cli.on_error },
+    };
+    // Before any dates are read (git dates are cached per graph)
+    timezone::set(cli.timezone);
+    // Everything below reads the graph from the snapshot, removed when main returns;
+    // caches stay in the graph folder (`graph_root`)
+    let snapshot = match &cli.at_revision {
+        Some(revision) => Some(git::Snapshot::create(&config.input_dir, revision)?),
+        None => None,
     };
+    if let Some(snapshot) = &snapshot {
+        info!("Building from revision {} ({})", snapshot.commit, snapshot.date);
+        config.input_dir = snapshot.path().to_path_buf();
+    }
+    config.reference_filters = references::graph_filters(&config.input_dir.join("logseq/config.edn"));
+    config.hidden = source::Hidden::load(&config.input_dir);
+    config.default_language = i18n::default_language(&favorites::locale(&config.input_dir.join("logseq/config.edn")));
+    if let Some(path) = icons::map_path(&config.input_dir, cli.icon_map.as_deref()) {
+        config.icon_map = icons::IconMap::load(&path)?;
+    }
+    if let Some(spec) = &cli.callout_map {
+        config.callout_map = callouts::CalloutMap::parse(spec)?;
+    }
+    if let Some(quartz_dir) = &config.quartz_dir {
+        if !quartz_dir.join(quartz::CONFIG_FILE).exists() {
+            anyhow::bail!("{} has no {}; is it a Quartz checkout?", quartz_dir.display(), quartz::CONFIG_FILE);
+        }
+    }
+    if let Some(path) = &cli.stub_template {
+        let template = source::read_to_string(path)
+            .with_context(|| format!("reading stub template {}", path.display()))?;
+        config.stub_template = Some(template);
+    }
+
+    let result = build(&config, start);
+    if let Err(e) = &result {
+        config.hooks.on_error(&config, e);
+    }
+    result
+}
+
+/// Run the hooks and the preprocessor, report, and write `_stats.json`
+fn build(config: &Config, start: Instant) -> Result<()> {
+    // What the last build's queries listed, to tell whose results changed
+    let previous_dependencies: Option<BTreeMap<String, Vec<String>>> =
+        hooks::previous_stat(&config.output_dir, "query_dependencies");
+    let in_place = config.output_mode == staging::OutputMode::InPlace;
+    if in_place {
+        // A stale stats file would be handed to on_error hooks of a failed run
+        let _ = std::fs::remove_file(config.output_dir.join(hooks::STATS_FILE));
+    }
+    config.hooks.pre_build(config)?;
 
-    println!("Preprocessing Logseq content for Quartz...\n");
+    // Unless in place, the build goes to a staging folder that only reaches
+    // the output when it succeeds, so a failed run leaves the output as it was
+    let staged;
+    let target = if in_place {
+        config
+    } else {
+        staged = Config { output_dir: staging::prepare(&config.output_dir)?, ..config.clone() };
+        &staged
+    };
+
+    info!("Preprocessing Logseq content for Quartz...");
 
     // Run the preprocessor
-    let stats = run_preprocessor(&config)?;
+    let mut stats = run_preprocessor(target)?;
+    if let Some(previous) = &previous_dependencies {
+        stats.query_changed = Some(query::changed_results(previous, &stats.query_dependencies));
+    }
 
     let duration = start.elapsed();
-    println!("\nPreprocessing complete!");
-    println!("  Pages: {} published, {} skipped", stats.pages_published, stats.pages_skipped);
-    println!("  Journals: {}", stats.journals_published);
-    println!("  Favorites: {}", stats.favorites_created);
-    println!("  Stubs: {}", stats.stubs_created);
-    println!("  Time: {:.2}s", duration.as_secs_f64());
+    stats.duration_ms = duration.as_millis() as u64;
+    info!("Preprocessing complete!");
+    info!("  Pages: {} published, {} skipped", stats.pages_published, stats.pages_skipped);
+    info!("  Journals: {}", stats.journals_published);
+    info!("  Favorites: {}", stats.favorites_created);
+    info!("  Stubs: {}", stats.stubs_created);
+    if let Some(metrics) = &stats.metrics {
+        info!(
+            "  Words: {} ({} per page on average, {} on the longest; {} min of reading)",
+            metrics.words,
+            metrics.average(),
+            metrics.longest,
+            metrics.reading_minutes
+        );
+    }
+    let queries = stats.queries;
+    if queries.run + queries.unsupported > 0 {
+        info!("  Queries: {} run, {} reused, {} unsupported", queries.run, queries.reused, queries.unsupported);
+    }
+    if !stats.query_pages.is_empty() {
+        info!("  Pages with queries (results as of this build): {}", stats.query_pages.len());
+        for name in &stats.query_pages {
+            info!("    {}", name);
+        }
+    }
+    if let Some(changed) = &stats.query_changed {
+        info!("  Pages whose query results changed since the last build: {}", changed.len());
+        for name in changed {
+            info!("    {}", name);
+        }
+    }
+    info!("  Time: {:.2}s", duration.as_secs_f64());
+    if config.timings {
+        for line in progress::report(&stats.phases) {
+            info!("    {}", line);
+        }
+    }
+
+    let warnings = warnings::global();
+    if !warnings.is_empty() {
+        let summary = warnings.summary(config.warning_pages);
+        stats.diagnostics = summary.len();
+        if !summary.is_empty() {
+            warn!("{} distinct diagnostics (details in _warnings.json)", summary.len());
+            for line in summary {
+                warn!("  {}", line);
+            }
+        }
+        warnings.write_report(&target.output_dir)?;
+    }
+
+    // Failed files leave holes in the site: list them and exit non-zero
+    let failures = warnings.errors();
+    stats.files_failed = failures.len();
+    hooks::write_stats(&target.output_dir, &stats)?;
+    if !failures.is_empty() {
+        error!("{} files failed to process:", failures.len());
+        for failure in &failures {
+            error!("  {}", failure.message);
+        }
+        if !in_place {
+            error!("{} is unchanged; this build is in {}", config.output_dir.display(), target.output_dir.display());
+        }
+        anyhow::bail!("{} files failed to process", failures.len());
+    }
 
+    match config.output_mode {
+        staging::OutputMode::Swap => staging::publish(&target.output_dir, &config.output_dir)?,
+        staging::OutputMode::Sync => {
+            let synced = staging::sync(&target.output_dir, &config.output_dir)?;
+            info!("  Output: {} files written, {} unchanged, {} removed", synced.written, synced.unchanged, synced.removed);
+        }
+        staging::OutputMode::InPlace => {}
+    }
+    config.hooks.post_build(config)
+}
+
+fn run_command(command: Command) -> Result<()> {
+    match command {
+        Command::Export { format, file, input, namespace, include_private } => match format {
+            ExportFormat::SingleHtml => {
+                let count = export::single_html(&input, &file, namespace.as_deref(), include_private)?;
+                info!("Exported {} pages to {}", count, file.display());
+            }
+        },
+        Command::Bench { pages, links, queries, journals, runs, graph, json } => {
+            let spec = bench::GraphSpec { pages, links, queries, journals };
+            info!("Benchmarking {} pages ({} links each, {} with queries) and {} journals, {} runs", pages, links, queries, journals, runs);
+            let report = bench::run(&spec, runs, graph.as_deref())?;
+            for line in bench::table(&report) {
+                info!("{}", line);
+            }
+            if let Some(path) = json {
+                std::fs::write(&path, serde_json::to_string_pretty(&report)?)?;
+                info!("Wrote {}", path.display());
+            }
+        }
+        Command::Rename { old, new, input, dry_run, no_backup } => {
+            let plan = rename::plan(&input, &old, &new)?;
+            if let Some((from, to)) = &plan.file {
+                info!("{} -> {}", from.display(), to.display());
+            }
+            for edit in &plan.edits {
+                info!("  {} ({} references)", edit.path.display(), edit.replacements);
+            }
+            if dry_run {
+                info!("Dry run: {} references in {} files would change", plan.replacements(), plan.edits.len());
+                return Ok(());
+            }
+            if let Some(backup) = rename::apply(&plan, &input, !no_backup)? {
+                info!("Backed up touched files to {}", backup.display());
+            }
+            info!("Renamed \"{}\" to \"{}\": {} references in {} files", old, new, plan.replacements(), plan.edits.len());
+        }
+        Command::Query { query, input, json, tag_match, seed } => {
+            let index = page::build_graph_index(&input)?
+                .with_tag_match(tag_match)
+                .with_seed(seed.unwrap_or_else(rng::time_seed));
+            let results = query::execute(&query, &index).map_err(|e| anyhow::anyhow!("Unsupported query: {}", e))?;
+            let rows = query::result_rows(&results, &input);
+            // Results go to stdout, so they can be piped; the count is logged
+            if json {
+                println!("{}", serde_json::to_string_pretty(&rows)?);
+            } else if !rows.is_empty() {
+                for line in query::rows_table(&rows) {
+                    println!("{}", line);
+                }
+            }
+            info!("{} of {} pages match", rows.len(), index.len());
+        }
+    }
     Ok(())
 }
 
-#[derive(Default)]
+/// Log level from -q/-v/-vv: errors only, progress (default), per-page diagnostics, everything.
+/// RUST_LOG overrides it, e.g. `RUST_LOG=logseq_to_quartz::query=trace`.
+fn init_logging(verbose: u8, quiet: bool) {
+    use std::io::Write;
+
+    let level = match (quiet, verbose) {
+        (true, _) => log::LevelFilter::Error,
+        (false, 0) => log::LevelFilter::Info,
+        (false, 1) => log::LevelFilter::Debug,
+        (false, _) => log::LevelFilter::Trace,
+    };
+    let logger = env_logger::Builder::new()
+        .filter_level(level)
+        .parse_default_env()
+        .format(|buf, record| match record.level() {
+            log::Level::Info => writeln!(buf, "{}", record.args()),
+            log::Level::Warn => writeln!(buf, "warning: {}", record.args()),
+            level => writeln!(buf, "{}: {}", level.as_str().to_lowercase(), record.args()),
+        })
+        .build();
+    if quiet {
+        progress::hide();
+    }
+    // Log lines go above the progress bars instead of through them
+    let max_level = logger.filter();
+    indicatif_log_bridge::LogWrapper::new(progress::bars().clone(), logger)
+        .try_init()
+        .expect("logger initialized once");
+    log::set_max_level(max_level);
+}
+
+#[derive(Debug, Default, serde::Serialize)]
 pub struct Stats {
     pub pages_published: usize,
     pub pages_skipped: usize,
     pub journals_published: usize,
     pub favorites_created: usize,
     pub stubs_created: usize,
+    /// Distinct diagnostics in the summary (errors excluded)
+    pub diagnostics: usize,
+    pub files_failed: usize,
+    pub duration_ms: u64,
+    /// Time per phase, in the order they ran
+    pub phases: Vec<progress::PhaseTime>,
+    /// Word counts and reading time (`--word-count`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<metrics::Totals>,
+    /// Queries evaluated, and reused from the query cache
+    pub queries: query::QueryCounts,
+    /// Pages with queries, whose results are frozen until the next build
+    pub query_pages: Vec<String>,
+    /// Pages each page's query results list, by page
+    pub query_dependencies: BTreeMap<String, Vec<String>>,
+    /// Pages whose query results differ from the last build's (none on a first build)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query_changed: Option<Vec<String>>,
 }
 
 fn run_preprocessor(config: &Config) -> Result<Stats> {
@@ -108,6 +805,9 @@ fn run_preprocessor(config: &Config) -> Result<Stats> {
     use std::fs;
 
     let mut stats = Stats::default();
+    let mut timings = progress::Timings::default();
+    // Word counts start from zero each run (bench builds several times)
+    metrics::take(&config.output_dir);
 
     // Create output directories
     // Pages go to content root (not in pages/ subfolder) for cleaner URLs
@@ -121,128 +821,335 @@ fn run_preprocessor(config: &Config) -> Result<Stats> {
     fs::create_dir_all(&favorites_output)?;
     fs::create_dir_all(&assets_output)?;
 
-    // Step 1: Get all git dates in one batch call
+    // Step 1: Created/modified dates for all files in one history walk
+    timings.phase("git history");
+    let spinner = progress::spinner("history");
     let repo_root = &config.input_dir;
-    let git_dates = page::get_all_git_dates(repo_root);
+    let git_dates = git::file_dates(repo_root);
+    spinner.finish_and_clear();
 
     // Step 2: Build page index for queries (includes pages and journals)
-    println!("Building page index...");
+    info!("Building page index...");
+    timings.phase("index");
+    let spinner = progress::spinner("index");
     let pages_dir = config.input_dir.join("pages");
-    let journals_dir = config.input_dir.join("journals");
-    let mut page_index = page::build_index(&pages_dir)?;
-    if journals_dir.exists() {
-        let journal_index = page::build_index(&journals_dir)?;
-        // Prefix journal pages with journals/ so query result links work
-        for mut page in journal_index {
-            page.name = format!("journals/{}", page.name);
-            page.name_lower = page.name.to_lowercase();
-            page_index.push(page);
+    let mut page_index = page::build_graph_index(&config.input_dir)?;
+    if !config.include_private {
+        page_index = page::drop_private_blocks(page_index);
+    }
+    let mut page_index = page::merge_date_pages(page_index, config.date_pages)
+        .with_slug_style(config.slug_style)
+        .with_callouts(config.callout_map.clone())
+        .with_embeds(config.embeds)
+        .with_embed_depth(config.embed_depth)
+        .with_collapsed(config.collapsed)
+        .with_seed(config.seed.unwrap_or_else(rng::time_seed))
+        .with_tag_match(config.tag_match)
+        .with_link_fallback(config.link_fallback)
+        .with_query_date(config.query_dates.then(|| timezone::current().today().format("%Y-%m-%d").to_string()))
+        .with_pdf_frame(config.pdf_frame.clone())
+        .with_sanitize_html(config.sanitize_html);
+    spinner.finish_and_clear();
+    info!("Indexed {} pages", page_index.len());
+
+    // Track slug history so renamed pages keep their old URLs
+    if let Some(slug_map_path) = &config.slug_map {
+        let mut slug_map = resolver::SlugMap::load(slug_map_path);
+        slug_map.update(&mut page_index);
+        slug_map.save(slug_map_path)?;
+    }
+
+    // Titles of external links, fetched once and cached between builds
+    if config.link_titles {
+        timings.phase("link titles");
+        let cache_path = config.cache_path(config.link_cache.as_deref(), external::DEFAULT_CACHE);
+        let mut cache = external::LinkCache::load(&cache_path);
+        let urls = external::collect(&page_index, config.include_private);
+        let previews = external::resolve(&urls, &mut cache, config.offline);
+        if !config.offline {
+            cache.save()?;
+        }
+        let count = external::write(&config.output_dir, &previews)?;
+        info!("Titled {} of {} external links (see {})", count, urls.len(), external::LINK_PREVIEWS_FILE);
+        page_index = page_index.with_link_previews(previews);
+    }
+
+    // archive.org snapshots of external links, looked up once and cached
+    if config.archive_links {
+        timings.phase("archived links");
+        let cache_path = config.cache_path(config.archive_cache.as_deref(), archive::DEFAULT_CACHE);
+        let mut cache = archive::ArchiveCache::load(&cache_path);
+        let urls = external::collect(&page_index, config.include_private);
+        let snapshots = archive::resolve(&urls, &mut cache, config.archive_save, config.offline);
+        if !config.offline {
+            cache.save()?;
+        }
+        info!("Found archive.org snapshots for {} of {} external links", snapshots.len(), urls.len());
+        page_index = page_index.with_archived_links(snapshots);
+    }
+
+    // Related pages by shared tags, links and co-citation
+    if let Some(output) = config.related {
+        timings.phase("related pages");
+        let related = related::compute(&page_index, config.related_count, config.include_private);
+        match output {
+            related::RelatedOutput::Frontmatter => {
+                info!("Found related pages for {} pages", related.len());
+                page_index = page_index.with_related(related);
+            }
+            related::RelatedOutput::Json => {
+                let count = related::write(&config.output_dir, &page_index, &related)?;
+                info!("Wrote related pages of {} pages to {}", count, related::RELATED_FILE);
+            }
+        }
+    }
+
+    // Links resolved by fuzzy matching, listed for review
+    if config.link_fallback.mode == resolver::LinkFallback::Fuzzy {
+        let links = resolver::fuzzy_links(&page_index, config.include_private);
+        resolver::write_fuzzy_report(&config.output_dir, &links)?;
+        info!("Resolved {} links by fuzzy matching (see {})", links.len(), resolver::FUZZY_LINKS_FILE);
+    }
+
+    // Optional analysis: suggest tags for untagged pages (report only)
+    if config.suggest_tags {
+        timings.phase("tag suggestions");
+        let suggestions = suggest::suggest_tags(&page_index, config.include_private);
+        suggest::write_report(&config.output_dir, &suggestions)?;
+        info!("Suggested tags for {} untagged pages (see _tag_suggestions.json)", suggestions.len());
+        for entry in &suggestions {
+            let tags: Vec<_> = entry.suggestions.iter().map(|s| s.tag.as_str()).collect();
+            debug!("  {}: {}", entry.page, tags.join(", "));
+        }
+    }
+    if config.analyze {
+        timings.phase("analysis");
+        let home = config
+            .home_override
+            .clone()
+            .or_else(|| favorites::get_default_home(&config.input_dir.join("logseq/config.edn")))
+            .unwrap_or_else(|| "index".to_string());
+        let analysis = analyze::analyze(&page_index, &config.input_dir, &home, &config.hidden, config.include_private);
+        analyze::write_report(&config.output_dir, &analysis)?;
+        info!(
+            "Analyzed {} pages and {} links: {} orphans, {} dead ends, {} unused assets (see {})",
+            analysis.pages,
+            analysis.links,
+            analysis.orphans.len(),
+            analysis.dead_ends.len(),
+            analysis.unused_assets.len(),
+            analyze::ANALYSIS_FILE
+        );
+        for orphan in &analysis.orphans {
+            debug!("  orphan: {}", orphan);
         }
     }
-    println!("Indexed {} pages", page_index.len());
 
     // Step 3: Process pages in parallel
-    println!("\nProcessing pages...");
+    info!("Processing pages...");
+    timings.phase("pages");
     let published = AtomicUsize::new(0);
     let skipped = AtomicUsize::new(0);
 
     let page_files: Vec<_> = walkdir::WalkDir::new(&pages_dir)
+        .sort_by_file_name()
         .into_iter()
         .filter_map(|e| e.ok())
-        .filter(|e| e.path().extension().map_or(false, |ext| ext == "md"))
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "md"))
+        .filter(|e| !config.hidden.contains(e.path()))
         .collect();
 
-    page_files.par_iter().for_each(|entry| {
+    let bar = progress::bar("pages", page_files.len());
+    page_files.par_iter().try_for_each(|entry| {
+        bar.inc(1);
         match page::process_page(entry.path(), &pages_output, &page_index, config, &git_dates, repo_root) {
             Ok(true) => { published.fetch_add(1, Ordering::Relaxed); }
             Ok(false) => { skipped.fetch_add(1, Ordering::Relaxed); }
+            Err(e) if config.strict => {
+                return Err(e.context(format!("failed to process {}", entry.path().display())));
+            }
             Err(e) => {
-                if config.verbose {
-                    eprintln!("Error processing {:?}: {}", entry.path(), e);
-                }
+                let page = entry.path().file_stem().map(|s| resolver::page_name(&s.to_string_lossy()));
+                warnings::global().error(&format!("{}: {}", entry.path().display(), e), page.as_deref());
             }
         }
-    });
+        Ok(())
+    })?;
+    bar.finish_and_clear();
 
     stats.pages_published = published.load(Ordering::Relaxed);
     stats.pages_skipped = skipped.load(Ordering::Relaxed);
-    println!("Published: {} files, Skipped: {} files", stats.pages_published, stats.pages_skipped);
+    info!("Published: {} files, Skipped: {} files", stats.pages_published, stats.pages_skipped);
 
     // Step 4: Process journals
-    println!("\nProcessing journals...");
+    info!("Processing journals...");
+    timings.phase("journals");
     let journals_dir = config.input_dir.join("journals");
     if journals_dir.exists() {
         stats.journals_published = journals::process_journals(&journals_dir, &journals_output, &page_index, config)?;
-        println!("Published: {} journal entries", stats.journals_published);
+        info!("Published: {} journal entries", stats.journals_published);
     }
 
     // Step 5: Process favorites
-    println!("\nProcessing favorites...");
+    info!("Processing favorites...");
+    timings.phase("site");
     let config_path = config.input_dir.join("logseq/config.edn");
     if config_path.exists() || config.favorites_override.is_some() {
         stats.favorites_created = favorites::process_favorites(
             &config_path,
             &favorites_output,
             &pages_output,
+            &page_index,
             config.favorites_override.as_ref(),
+            &config.icon_map,
+            &config.favorites_outputs,
         )?;
-        println!("Created: {} favorite pages", stats.favorites_created);
+        info!("Created: {} favorites", stats.favorites_created);
+    }
+    if let Some(count) = sidebar::write(&config.output_dir, &page_index, config.include_private)? {
+        info!("Wrote {} entries from the Contents page to {}", count, sidebar::SIDEBAR_FILE);
     }
 
     // Step 6: Write site config and create index.md by copying home page
+    let site_context = favorites::SiteContext {
+        base_url: config.base_url.clone(),
+        analytics: config.analytics,
+        navigation: favorites::navigation_tree(&page_index, config.include_private),
+        favorites: favorites::favorite_names(&config_path, config.favorites_override.as_ref())
+            .unwrap_or_default(),
+        journals: favorites::JournalsInfo {
+            enabled: stats.journals_published > 0,
+            count: stats.journals_published,
+            index_path: (stats.journals_published > 0).then(|| "journals".to_string()),
+            page_title_format: None,
+        },
+        features: favorites::Features::from_config(config),
+    };
     let site_config = favorites::write_site_config(
         &config_path,
         &config.output_dir,
         config.home_override.as_deref(),
         config.title_override.as_deref(),
         config.site_name_override.as_deref(),
+        site_context,
     );
-    let index_path = config.output_dir.join("index.md");
-    if !index_path.exists() {
-        let home_page = match &site_config {
-            Some(cfg) => cfg.home_page.clone(),
-            None => "index".to_string(),
+    if let Some(quartz_dir) = &config.quartz_dir {
+        let settings = quartz::Settings {
+            page_title: site_config.as_ref().map(|c| c.page_title.clone()),
+            base_url: config.base_url.as_deref().map(quartz::Settings::base_url),
+            locale: site_config.as_ref().map(|c| c.locale.clone()),
         };
-
-        // Try to find and copy the home page content directly
-        let home_file = config.output_dir.join(format!("{}.md", home_page));
-        if home_file.exists() {
-            // Copy home page to index.md (so / shows actual content, not embed)
-            fs::copy(&home_file, &index_path)?;
-            println!("\nCreated index.md (copied from: {})", home_page);
-        } else {
-            // Fallback: create minimal index
-            let index_content = format!(
-                "---\ntitle: \"{}\"\n---\n\n# Welcome\n\nSee [[{}]]\n",
-                home_page, home_page
-            );
-            fs::write(&index_path, index_content)?;
-            println!("\nCreated index.md (home page '{}' not found)", home_page);
+        if quartz::patch_config(quartz_dir, &settings)? {
+            info!("Patched {} (previous version in {})", quartz::CONFIG_FILE, quartz::BACKUP_FILE);
         }
     }
+    // A page named "index" is the site root already
+    if !config.output_dir.join("index.md").exists() {
+        let home_page = site_config.as_ref().map_or("index", |cfg| cfg.home_page.as_str());
+        let shown = home::write_index(
+            &config.output_dir,
+            &page_index,
+            home_page,
+            config.home_mode,
+            config.home_journals,
+            config.include_private,
+        )?;
+        info!("Created index.md ({})", shown);
+    }
 
     // Step 7: Copy assets
+    timings.phase("assets");
     let assets_source = config.input_dir.join("assets");
     if assets_source.exists() {
-        let count = copy_dir_recursive(&assets_source, &assets_output)?;
-        println!("\nCopied {} asset files", count);
+        let count = copy_dir_recursive(&assets_source, &assets_output, &config.hidden)?;
+        info!("Copied {} asset files", count);
+    }
+    if !config.icon_map.is_empty() {
+        let count = config.icon_map.copy_assets(&config.output_dir)?;
+        info!("Copied {} icon assets", count);
     }
 
-    // Step 8: Create stub pages for missing links
+    // Step 8: Redirect pages for aliases, series indexes, languages, stub pages for missing links
+    timings.phase("generated");
+    if config.alias_style == page::AliasStyle::Redirect {
+        let count = page::create_alias_redirects(&config.output_dir, &page_index, config)?;
+        info!("Created {} alias redirects", count);
+    }
+    let count = series::write_indexes(&config.output_dir, &page_index, config.include_private)?;
+    if count > 0 {
+        info!("Created {} series index pages", count);
+    }
+    let count = i18n::write(&config.output_dir, &page_index, &config.default_language, config.include_private)?;
+    if count > 0 {
+        info!("Wrote languages of {} translated pages to {}", count, i18n::LANGUAGES_FILE);
+    }
     if config.create_stubs {
-        println!("\nCreating stub pages...");
-        stats.stubs_created = page::create_stubs(&config.output_dir, &page_index)?;
-        println!("Created {} stub pages", stats.stubs_created);
+        info!("Creating stub pages...");
+        stats.stubs_created = page::create_stubs(&config.output_dir, &page_index, config)?;
+        info!("Created {} stub pages", stats.stubs_created);
+    }
+
+    // Step 9: Hover previews, page index
+    if config.previews {
+        let count = previews::write(&config.output_dir, &page_index, &config.icon_map, config.include_private)?;
+        info!("Wrote {} page previews", count);
+    }
+    if let Some(format) = config.emit_index {
+        let count = index_file::write(&config.output_dir, &page_index, format, config.include_private)?;
+        info!("Wrote {} pages to {}", count, format.file_name());
+    }
+
+    // Step 10: Calendar of scheduled items and deadlines
+    if config.events {
+        let count = schedule::write(&config.output_dir, &page_index, config.include_private)?;
+        info!("Wrote {} scheduled items and deadlines to {}", count, schedule::EVENTS_FILE);
+    }
+    if config.emit_ics {
+        let name = site_config.as_ref().map_or("Journal", |c| c.page_title.as_str());
+        let count = ics::write(&config.output_dir, &page_index, name, config.base_url.as_deref(), config.include_private)?;
+        info!("Wrote {} calendar events to {}", count, ics::ICS_FILE);
     }
 
+    // Step 11: Feed and page of recent changes, random page list, sitemap
+    if let Some(feed) = &config.feed {
+        let title = site_config.as_ref().map_or("Recent changes", |c| c.page_title.as_str());
+        let count = feed::write(&config.output_dir, &page_index, title, config.base_url.as_deref(), feed, config.include_private)?;
+        info!("Wrote {} entries to {}", count, feed::FEED_FILE);
+    }
+    if let Some(days) = config.changes {
+        let today = timezone::current().today();
+        match changes::write(&config.output_dir, &page_index, days, today, config.include_private)? {
+            Some(count) => info!("Listed {} changed pages in {}", count, changes::CHANGES_FILE),
+            None => warn!("{} is a page of the graph; no recent changes page written", changes::CHANGES_FILE),
+        }
+    }
+    if config.random {
+        let count = random::write(&config.output_dir, &page_index, config.include_private)?;
+        info!("Wrote {} pages to {}", count, random::RANDOM_FILE);
+    }
+    if let (true, Some(base_url)) = (config.sitemap, &config.base_url) {
+        let count = sitemap::write(&config.output_dir, &page_index, base_url, config.include_private)?;
+        info!("Wrote {} URLs to {} and {}", count, sitemap::SITEMAP_FILE, sitemap::ROBOTS_FILE);
+    }
+
+    if config.word_count {
+        stats.metrics = Some(metrics::take(&config.output_dir));
+    }
+    stats.query_pages = query::pages_with_queries(&page_index, config.include_private);
+    stats.queries = page_index.query_cache().lock().unwrap_or_else(|e| e.into_inner()).counts();
+    // After the counts: the pages ran these queries already, so they come from the cache
+    stats.query_dependencies = query::dependencies(&page_index, config.include_private);
+    stats.phases = timings.finish();
     Ok(stats)
 }
 
-fn copy_dir_recursive(src: &std::path::Path, dst: &std::path::Path) -> Result<usize> {
+/// Copy a folder, leaving out `:hidden` paths
+fn copy_dir_recursive(src: &std::path::Path, dst: &std::path::Path, hidden: &source::Hidden) -> Result<usize> {
     use std::fs;
     let mut count = 0;
+    let spinner = progress::spinner("assets");
 
-    for entry in walkdir::WalkDir::new(src) {
+    for entry in walkdir::WalkDir::new(src).into_iter().filter_entry(|e| !hidden.contains(e.path())) {
         let entry = entry?;
         let relative = entry.path().strip_prefix(src)?;
         let target = dst.join(relative);
@@ -255,8 +1162,10 @@ fn copy_dir_recursive(src: &std::path::Path, dst: &std::path::Path) -> Result<us
             }
             fs::copy(entry.path(), &target)?;
             count += 1;
+            spinner.set_message(format!("{} files", count));
         }
     }
+    spinner.finish_and_clear();
 
     Ok(count)
 }
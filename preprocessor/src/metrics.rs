@@ -0,0 +1,79 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::frontmatter;
+use crate::previews;
+
+/// Reading speed behind `readingTime:` (words per minute)
+pub const WORDS_PER_MINUTE: usize = 200;
+
+lazy_static! {
+    // Totals of the pages counted so far, per output directory (pages are
+    // processed in parallel, and builds into different folders may overlap)
+    static ref GLOBAL: Mutex<HashMap<PathBuf, Totals>> = Mutex::new(HashMap::new());
+
+    // Raw HTML tags (<details>, <span class=…>): markup, not words
+    static ref HTML_TAG_RE: Regex = Regex::new(r"</?[A-Za-z][^>]*>").unwrap();
+}
+
+/// Words of prose in a transformed page body: code blocks, properties, markup
+/// and HTML left out, links counted by their labels. Tokens without a letter or
+/// digit (table pipes, rules, quote markers) aren't words.
+pub fn word_count(body: &str) -> usize {
+    let text = HTML_TAG_RE.replace_all(body, " ");
+    previews::plain_text(&text)
+        .split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .count()
+}
+
+/// Minutes to read `words` at `WORDS_PER_MINUTE`, rounded up (0 for no words)
+pub fn reading_time(words: usize) -> usize {
+    words.div_ceil(WORDS_PER_MINUTE)
+}
+
+/// Add `wordcount:` and `readingTime:` (minutes) to generated frontmatter
+pub fn insert_frontmatter(fm: &str, words: usize) -> String {
+    let fm = frontmatter::insert_field(fm, "wordcount", &words.to_string());
+    frontmatter::insert_field(&fm, "readingTime", &reading_time(words).to_string())
+}
+
+/// Count a published page's body into the totals of the build into
+/// `output_dir`; returns its word count
+pub fn record(output_dir: &Path, body: &str) -> usize {
+    let words = word_count(body);
+    let mut totals = GLOBAL.lock().unwrap();
+    let totals = totals.entry(output_dir.to_path_buf()).or_default();
+    totals.pages += 1;
+    totals.words += words;
+    totals.reading_minutes += reading_time(words);
+    totals.longest = totals.longest.max(words);
+    words
+}
+
+/// Totals of the build into `output_dir` so far, which start over from zero
+pub fn take(output_dir: &Path) -> Totals {
+    GLOBAL.lock().unwrap().remove(output_dir).unwrap_or_default()
+}
+
+/// Content metrics of a run (`--word-count`), in the run report
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct Totals {
+    /// Pages and journals counted
+    pub pages: usize,
+    pub words: usize,
+    /// Sum of the pages' reading times
+    pub reading_minutes: usize,
+    /// Words on the longest page
+    pub longest: usize,
+}
+
+impl Totals {
+    /// Average words per page
+    pub fn average(&self) -> usize {
+        self.words.checked_div(self.pages).unwrap_or(0)
+    }
+}
@@ -0,0 +1,66 @@
+use chrono::{Datelike, NaiveDate};
+use std::collections::HashMap;
+
+use crate::journals;
+use crate::page::{Page, PageIndex};
+use crate::resolver;
+
+/// Published journal days by calendar date (month, day), for the "On this
+/// day" section of each journal (`--on-this-day`)
+#[derive(Debug, Default)]
+pub struct OnThisDay {
+    days: HashMap<(u32, u32), Vec<NaiveDate>>,
+}
+
+impl OnThisDay {
+    pub fn new(index: &PageIndex, include_private: bool) -> Self {
+        let mut days: HashMap<(u32, u32), Vec<NaiveDate>> = HashMap::new();
+        for page in index.iter().filter(|p| include_private || !p.is_private()) {
+            let Some(date) = journal_date(page) else {
+                continue;
+            };
+            days.entry((date.month(), date.day())).or_default().push(date);
+        }
+        for dates in days.values_mut() {
+            dates.sort_unstable_by(|a, b| b.cmp(a));
+            dates.dedup();
+        }
+        OnThisDay { days }
+    }
+
+    /// Journal days of the same calendar date in earlier years, newest first
+    pub fn earlier(&self, date: NaiveDate) -> Vec<NaiveDate> {
+        self.days
+            .get(&(date.month(), date.day()))
+            .map(|dates| dates.iter().copied().filter(|d| d.year() < date.year()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Section appended to the journal of `date` linking the same day of earlier
+    /// years, or None when there is none
+    pub fn section(&self, date: NaiveDate) -> Option<String> {
+        let earlier = self.earlier(date);
+        if earlier.is_empty() {
+            return None;
+        }
+        let mut section = String::from("\n---\n\n## On this day\n\n");
+        for day in earlier {
+            let years = date.year() - day.year();
+            section.push_str(&format!(
+                "- [[{}|{}]] ({} {} ago)\n",
+                resolver::journal_output_name(&day.format("%Y-%m-%d").to_string()),
+                day.format("%B %-d, %Y"),
+                years,
+                if years == 1 { "year" } else { "years" }
+            ));
+        }
+        Some(section)
+    }
+}
+
+/// Day of a journal page of the index
+fn journal_date(page: &Page) -> Option<NaiveDate> {
+    let (date, _) = journals::parse_journal_date(page.name.strip_prefix("journals/")?)?;
+    NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok()
+}
+
@@ -0,0 +1,367 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::escape;
+use crate::previews;
+
+lazy_static! {
+    // Block id property: id:: 6650a1b2-... (group: id)
+    static ref BLOCK_ID_RE: Regex = Regex::new(r"(?m)^\s*id::\s*([0-9a-fA-F-]{36})\s*$").unwrap();
+    // Block property line, bulleted or not: key:: value (groups: key, value)
+    static ref PROPERTY_RE: Regex = Regex::new(r"^\s*(?:-\s+)?([a-zA-Z_-]+)::\s*(.+)$").unwrap();
+    // Priority marker: [#A] (group: letter)
+    static ref PRIORITY_RE: Regex = Regex::new(r"\[#([A-Ca-c])\]").unwrap();
+}
+
+/// Task markers a block can start with
+const MARKERS: &[&str] = &["TODO", "DOING", "DONE", "LATER", "NOW", "WAITING", "WAIT", "CANCELED", "CANCELLED", "IN-PROGRESS", "STARTED"];
+
+/// What becomes of blocks folded in Logseq (`collapsed:: true`, `--collapsed`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum CollapsedStyle {
+    /// The property is dropped and the block shown open
+    #[default]
+    Strip,
+    /// The block and its children in a `<details>` element, folded, the block's text as its summary
+    Details,
+}
+
+/// Page content as Logseq sees it: the lines before the first bullet, then a
+/// tree of blocks. Rendering it gives back the content it was parsed from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Outline {
+    /// Lines before the first bullet (file-style page properties, plain markdown)
+    pub preamble: Vec<String>,
+    /// Top-level blocks
+    pub blocks: Vec<Block>,
+    trailing_newline: bool,
+}
+
+/// A block (`- ` bullet) with its continuation lines and children
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Block {
+    /// Nesting level, 0 for top-level blocks
+    pub level: usize,
+    /// First line of the block, without its bullet
+    pub text: String,
+    /// Lines as written: the bullet line, continuation lines, and blank lines
+    /// up to the next block
+    pub lines: Vec<String>,
+    /// Block properties (`key:: value`) as (lowercase key, value), in order
+    pub properties: Vec<(String, String)>,
+    /// Task marker the block starts with (`TODO`, `DONE`, ...)
+    pub marker: Option<String>,
+    /// Priority letter of a `[#A]` on the first line, in upper case
+    pub priority: Option<char>,
+    /// `id::` property, lowercase
+    pub id: Option<String>,
+    pub children: Vec<Block>,
+}
+
+/// A block with its children, cut out of a page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subtree {
+    /// First line of the block, without its bullet
+    pub text: String,
+    /// Following lines (continuation lines, then children), indented relative to the block
+    pub children: Vec<String>,
+}
+
+/// Parse page content into its outline. Bullets inside code fences and
+/// `#+BEGIN_...` directives are text.
+pub fn parse(content: &str) -> Outline {
+    let mut outline = Outline { trailing_newline: content.ends_with('\n'), ..Default::default() };
+    // Open blocks, outermost first, with the indent of their bullet
+    let mut open: Vec<(usize, Block)> = Vec::new();
+    let mut in_code = false;
+    let mut in_directive = false;
+    for line in content.lines() {
+        let starts_block = !in_code && !in_directive && is_bullet(line);
+        if is_fence(line) {
+            in_code = !in_code;
+        }
+        if !in_code {
+            in_directive = directive(line).map_or(in_directive, |begin| begin);
+        }
+        if !starts_block {
+            match open.last_mut() {
+                Some((_, block)) => block.lines.push(line.to_string()),
+                None => outline.preamble.push(line.to_string()),
+            }
+            continue;
+        }
+        while open.last().is_some_and(|(i, _)| *i >= indent(line)) {
+            close(&mut open, &mut outline.blocks);
+        }
+        let block = Block { level: open.len(), lines: vec![line.to_string()], ..Default::default() };
+        open.push((indent(line), block));
+    }
+    while !open.is_empty() {
+        close(&mut open, &mut outline.blocks);
+    }
+    outline
+}
+
+/// Finish the innermost open block and attach it to its parent
+fn close(open: &mut Vec<(usize, Block)>, roots: &mut Vec<Block>) {
+    let Some((_, mut block)) = open.pop() else { return };
+    block.read_lines();
+    match open.last_mut() {
+        Some((_, parent)) => parent.children.push(block),
+        None => roots.push(block),
+    }
+}
+
+impl Outline {
+    /// The content again, as Markdown
+    pub fn render(&self) -> String {
+        let mut lines: Vec<&str> = self.preamble.iter().map(String::as_str).collect();
+        for block in &self.blocks {
+            block.collect_lines(&mut lines, None);
+        }
+        let mut content = lines.join("\n");
+        if self.trailing_newline {
+            content.push('\n');
+        }
+        content
+    }
+
+    /// All blocks, parents before their children
+    pub fn iter(&self) -> impl Iterator<Item = &Block> {
+        let mut all = Vec::new();
+        for block in &self.blocks {
+            block.walk(&mut all);
+        }
+        all.into_iter()
+    }
+
+    /// The block with this `id::`
+    pub fn find(&self, id: &str) -> Option<&Block> {
+        self.iter().find(|block| block.id.as_deref().is_some_and(|own| own.eq_ignore_ascii_case(id)))
+    }
+
+    /// The page's property block when written as its first block (`- tags:: x`),
+    /// rather than as lines before the first bullet
+    pub fn page_block(&self) -> Option<&Block> {
+        if self.preamble.iter().any(|line| !line.trim().is_empty()) {
+            return None;
+        }
+        self.blocks.first().filter(|block| {
+            block.children.is_empty()
+                && block.lines.iter().all(|line| line.trim().is_empty() || PROPERTY_RE.is_match(line))
+        })
+    }
+
+    /// Task markers of the page: of its blocks, and of lines before the first bullet
+    pub fn markers(&self) -> impl Iterator<Item = &str> {
+        let preamble = self.preamble.iter().filter_map(|line| marker(line));
+        preamble.chain(self.iter().filter_map(|block| block.marker.as_deref()))
+    }
+
+    /// The outline without the blocks `drop` picks, and their children
+    pub fn without(&self, drop: impl Fn(&Block) -> bool) -> Outline {
+        fn retain(blocks: &[Block], drop: &dyn Fn(&Block) -> bool) -> Vec<Block> {
+            blocks
+                .iter()
+                .filter(|block| !drop(block))
+                .map(|block| Block { children: retain(&block.children, drop), ..block.clone() })
+                .collect()
+        }
+        Outline { blocks: retain(&self.blocks, &drop), ..self.clone() }
+    }
+
+    /// Remove the `private:: true` blocks and their children; `false` if there
+    /// were none. A page property block (`- private:: true` first) stays: it
+    /// makes the page private.
+    pub fn drop_private(&mut self) -> bool {
+        let page_block = self.page_block();
+        let private = |block: &Block| block.is_private() && !page_block.is_some_and(|own| std::ptr::eq(own, block));
+        if !self.iter().any(private) {
+            return false;
+        }
+        *self = self.without(private);
+        true
+    }
+
+    /// Fold each collapsed block that has children into a `<details>` element:
+    /// the block's text (as plain text) is the summary, the rest of the block
+    /// and its children the folded part
+    pub fn fold_collapsed(&mut self) {
+        fold(&mut self.blocks);
+    }
+
+    /// Rewrite the text of the preamble and of each block (its own lines,
+    /// without its children), parents before children
+    pub fn map_text(&mut self, mut f: impl FnMut(&str) -> String) {
+        fn map(blocks: &mut [Block], f: &mut dyn FnMut(&str) -> String) {
+            for block in blocks {
+                block.lines = f(&block.lines.join("\n")).split('\n').map(str::to_string).collect();
+                map(&mut block.children, f);
+            }
+        }
+        if !self.preamble.is_empty() {
+            self.preamble = f(&self.preamble.join("\n")).split('\n').map(str::to_string).collect();
+        }
+        map(&mut self.blocks, &mut f);
+    }
+}
+
+impl Block {
+    /// Value of a block property
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.properties.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    /// `collapsed:: true`: folded in Logseq
+    pub fn is_collapsed(&self) -> bool {
+        self.property("collapsed").is_some_and(|v| v.eq_ignore_ascii_case("true"))
+    }
+
+    /// `private:: true`: left out of the site unless `--include-private`
+    pub fn is_private(&self) -> bool {
+        self.property("private").is_some_and(|v| v.eq_ignore_ascii_case("true"))
+    }
+
+    /// The block and its children, as Markdown
+    pub fn render(&self) -> String {
+        let mut lines = Vec::new();
+        self.collect_lines(&mut lines, None);
+        lines.join("\n")
+    }
+
+    /// Put `lines`, nested below line `at` of the block, right after it:
+    /// continuation lines join the block's own, bullets become its first children
+    pub fn graft(&mut self, at: usize, lines: &[String]) {
+        let mut nested = parse(&lines.join("\n"));
+        relevel(&mut nested.blocks, self.level + 1);
+        self.lines.splice(at + 1..at + 1, nested.preamble);
+        self.children.splice(0..0, nested.blocks);
+    }
+
+    /// The block and its children cut out of the page. `depth` limits the
+    /// levels of children kept (0: the block alone); `None` keeps the whole subtree.
+    pub fn subtree(&self, depth: Option<usize>) -> Subtree {
+        let prefix = &self.lines[0][..indent(&self.lines[0])];
+        let mut lines = Vec::new();
+        lines.extend(self.lines[1..].iter().map(String::as_str));
+        if depth != Some(0) {
+            for child in &self.children {
+                child.collect_lines(&mut lines, depth.map(|d| d - 1));
+            }
+        }
+        let mut children: Vec<String> = lines
+            .into_iter()
+            .filter(|line| !BLOCK_ID_RE.is_match(line))
+            .map(|line| line.strip_prefix(prefix).unwrap_or(line).to_string())
+            .collect();
+        while children.last().is_some_and(|line| line.trim().is_empty()) {
+            children.pop();
+        }
+        Subtree { text: self.text.clone(), children }
+    }
+
+    /// Fill in what the block's lines say: text, properties, marker, priority, id
+    fn read_lines(&mut self) {
+        self.text = self.lines[0].trim_start().trim_start_matches('-').trim_start().to_string();
+        self.marker = marker(&self.text).map(str::to_string);
+        self.priority = PRIORITY_RE
+            .captures(&self.text)
+            .and_then(|caps| caps[1].chars().next())
+            .map(|c| c.to_ascii_uppercase());
+        let mut in_code = false;
+        for line in &self.lines {
+            if is_fence(line) {
+                in_code = !in_code;
+            }
+            if in_code {
+                continue;
+            }
+            if let Some(caps) = PROPERTY_RE.captures(line) {
+                self.properties.push((caps[1].to_lowercase(), caps[2].trim().to_string()));
+            }
+        }
+        self.id = BLOCK_ID_RE.captures(&self.lines[1..].join("\n")).map(|caps| caps[1].to_lowercase());
+    }
+
+    fn walk<'a>(&'a self, all: &mut Vec<&'a Block>) {
+        all.push(self);
+        for child in &self.children {
+            child.walk(all);
+        }
+    }
+
+    /// The block's lines, then its children's, `depth` levels of them
+    fn collect_lines<'a>(&'a self, lines: &mut Vec<&'a str>, depth: Option<usize>) {
+        lines.extend(self.lines.iter().map(String::as_str));
+        if depth == Some(0) {
+            return;
+        }
+        for child in &self.children {
+            child.collect_lines(lines, depth.map(|d| d - 1));
+        }
+    }
+}
+
+fn fold(blocks: &mut [Block]) {
+    for block in blocks {
+        // Inner blocks first: their </details> goes before this one's
+        fold(&mut block.children);
+        if !block.is_collapsed() || block.children.is_empty() {
+            continue;
+        }
+        let prefix = block.lines[0][..indent(&block.lines[0])].to_string();
+        let summary = escape::html(&previews::plain_text(&block.text));
+        block.lines[0] = format!("{}- <details class=\"collapsed-block\"><summary>{}</summary>", prefix, summary);
+        // A blank line ends the HTML block, so the children are read as Markdown again
+        block.lines.insert(1, String::new());
+        let mut last = block;
+        while let Some(child) = last.children.last_mut() {
+            last = child;
+        }
+        last.lines.push(String::new());
+        last.lines.push(format!("{}  </details>", prefix));
+    }
+}
+
+fn relevel(blocks: &mut [Block], level: usize) {
+    for block in blocks {
+        block.level = level;
+        relevel(&mut block.children, level + 1);
+    }
+}
+
+/// Task marker a line (or block text) starts with
+fn marker(text: &str) -> Option<&str> {
+    let word = text.split_whitespace().next()?;
+    MARKERS.iter().copied().find(|m| *m == word)
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn is_bullet(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed.starts_with("- ") || trimmed == "-"
+}
+
+/// `Some(true)` for a line opening an Org-mode directive (`#+BEGIN_NOTE`),
+/// `Some(false)` for one closing it, bulleted or not
+fn directive(line: &str) -> Option<bool> {
+    let text = line.trim_start();
+    let text = text.strip_prefix("- ").map_or(text, str::trim_start).to_ascii_uppercase();
+    if text.starts_with("#+BEGIN_") {
+        Some(true)
+    } else if text.starts_with("#+END_") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// A line opening or closing a code fence (```, ~~~), bulleted or not
+pub(crate) fn is_fence(line: &str) -> bool {
+    let text = line.trim_start();
+    let text = text.strip_prefix("- ").map_or(text, str::trim_start);
+    text.starts_with("```") || text.starts_with("~~~")
+}
@@ -1,15 +1,40 @@
 use anyhow::Result;
 use regex::Regex;
-use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
 
+use crate::badges;
+use crate::callouts::CalloutMap;
 use crate::config::Config;
 use crate::content;
+use crate::embeds::EmbedStyle;
+use crate::escape;
+use crate::external::LinkPreview;
 use crate::frontmatter;
+use crate::git;
+use crate::i18n;
+use crate::journals::{self, DatePages};
+use crate::metrics;
+use crate::outline::{self, CollapsedStyle};
+use crate::pdf::{self, Highlight, PdfFrame};
+use crate::properties;
+use crate::query::{QueryCache, TagMatch};
+use crate::references;
+use crate::related::{self, Related};
+use crate::resolver::{self, Fallback, SlugStyle};
+use crate::series;
+use crate::social;
+use crate::source;
+use crate::toc;
+use crate::warnings;
+use crate::workflow::Workflow;
 
 /// Represents a page in the index
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Page {
     pub name: String,
     pub name_lower: String,
@@ -20,30 +45,678 @@ pub struct Page {
     pub namespace: Option<String>,
     pub modified: Option<String>,
     pub created: Option<String>,
+    /// Custom output path from the `slug::` property
+    pub slug: Option<String>,
+    /// Former slugs of this page (from the persisted slug map), emitted as aliases
+    pub previous_slugs: Vec<String>,
+    /// File the page was read from
+    pub source: Option<PathBuf>,
+    /// Other files for the same page (names differing in case or namespace
+    /// separator), merged into this one
+    pub duplicates: Vec<PathBuf>,
+    /// `content` parsed into blocks, on first use (see [`Page::outline`])
+    pub outline: OnceLock<outline::Outline>,
 }
 
-/// Page index for query execution
-pub type PageIndex = Vec<Page>;
+impl Page {
+    /// The page's blocks: parsed from its content once, then shared by block
+    /// embeds, queries and the block index
+    pub fn outline(&self) -> &outline::Outline {
+        self.outline.get_or_init(|| outline::parse(&self.content))
+    }
+
+    /// Replace the page's content, and the outline parsed from it
+    pub fn set_content(&mut self, content: String) {
+        self.content = content;
+        self.outline = OnceLock::new();
+    }
+
+    /// Replace the page's blocks, and the content rendered from them
+    pub fn set_outline(&mut self, outline: outline::Outline) {
+        self.content = outline.render();
+        self.outline = OnceLock::from(outline);
+    }
+
+    /// Title to show: `title::`, the date of a journal (`January 2, 2025`), or the page name
+    pub fn title(&self) -> String {
+        match self.name.strip_prefix("journals/") {
+            Some(file) => journals::parse_journal_date(file).map_or(file.to_string(), |(_, title)| title),
+            None => self.properties.get("title").cloned().unwrap_or_else(|| self.name.clone()),
+        }
+    }
+
+    /// Left out of the site unless `--include-private` (`private:: true`)
+    pub fn is_private(&self) -> bool {
+        properties::is_private(&self.properties)
+    }
+
+    /// Published but kept out of listings (`unlisted:: true`), or a draft (`draft:: true`)
+    pub fn is_unlisted(&self) -> bool {
+        ["unlisted", "draft"]
+            .iter()
+            .any(|key| self.properties.get(*key).is_some_and(|v| v.eq_ignore_ascii_case("true")))
+    }
+}
+
+/// Page index for query execution and link resolution.
+///
+/// Pages keep their load order; lookup maps (names, aliases, slugs, tags,
+/// namespaces) are built once so wikilinks and query clauses don't scan every page.
+#[derive(Debug, Clone, Default)]
+pub struct PageIndex {
+    pages: Vec<Page>,
+    by_name: HashMap<String, Vec<usize>>,
+    by_alias: HashMap<String, Vec<usize>>,
+    by_slug: HashMap<String, usize>,
+    by_tag: HashMap<String, Vec<usize>>,
+    by_namespace: HashMap<String, Vec<usize>>,
+    /// Pages of each `series::`, by lowercase series name
+    by_series: HashMap<String, Vec<usize>>,
+    /// Translations of each page (`Garden.zh`, `translation-of::`), by lowercase name of the original
+    by_translation: HashMap<String, Vec<usize>>,
+    /// PDF of each `hls__` annotation page, by normalized page name
+    pdf_files: HashMap<String, String>,
+    /// PDF highlights by block id
+    pdf_highlights: HashMap<String, Highlight>,
+    /// Page of each block with an `id::`, by lowercase id
+    blocks: HashMap<String, usize>,
+    /// Task workflow of the graph (affects task markers and task queries)
+    workflow: Workflow,
+    /// Callout kinds → Quartz callout types (affects `#+BEGIN_*` and `> [!KIND]`)
+    callouts: CalloutMap,
+    /// How third-party embeds are rendered (`--embeds`)
+    embeds: EmbedStyle,
+    /// Size of embedded PDF viewers
+    pdf_frame: PdfFrame,
+    /// Reduce raw HTML and hiccup to an allowlist (`--sanitize-html`)
+    sanitize_html: bool,
+    /// Fetched titles of external URLs (`--link-titles`)
+    link_previews: BTreeMap<String, LinkPreview>,
+    /// Wayback Machine snapshots of external URLs (`--archive-links`)
+    archived_links: BTreeMap<String, String>,
+    /// Related pages of each page, by page name (`--related frontmatter`)
+    related: BTreeMap<String, Vec<Related>>,
+    /// Levels of children inlined with a block embed (`--embed-depth`; `None` = all)
+    embed_depth: Option<usize>,
+    /// How `collapsed:: true` blocks are rendered (`--collapsed`)
+    collapsed: CollapsedStyle,
+    /// Seed of `(sample n)` and `(shuffle)` queries (`--seed`)
+    seed: u64,
+    /// Build date noted under query results (`--query-dates`)
+    query_date: Option<String>,
+    /// How `(page-tags ...)` compares tags (`--tag-match`)
+    tag_match: TagMatch,
+    /// What links naming no page resolve to (`--link-fallback`)
+    link_fallback: Fallback,
+    /// Hash of what queries read from the pages, keying the query cache
+    fingerprint: u64,
+    /// Query results by query, shared by clones of the index
+    query_cache: Arc<Mutex<QueryCache>>,
+}
+
+impl PageIndex {
+    /// Build the lookup maps for a list of pages
+    pub fn new(pages: Vec<Page>) -> Self {
+        let mut index = PageIndex { pages, ..Default::default() };
+        index.reindex();
+        index
+    }
+
+    /// Rebuild the lookup maps after the pages changed; the settings (`with_*`) stay
+    fn reindex(&mut self) {
+        let pages = std::mem::take(&mut self.pages);
+        let mut index = PageIndex { pages, ..Default::default() };
+        for (i, page) in index.pages.iter().enumerate() {
+            index.by_name.entry(resolver::normalize_key(&page.name)).or_default().push(i);
+            for alias in &page.aliases {
+                let entry = index.by_alias.entry(resolver::normalize_key(alias)).or_default();
+                if !entry.contains(&i) {
+                    entry.push(i);
+                }
+            }
+            if let Some(slug) = &page.slug {
+                index.by_slug.entry(slug.to_lowercase()).or_insert(i);
+            }
+            for tag in &page.tags {
+                let entry = index.by_tag.entry(tag.to_lowercase()).or_default();
+                if !entry.contains(&i) {
+                    entry.push(i);
+                }
+            }
+            if let Some(ns) = &page.namespace {
+                index.by_namespace.entry(ns.to_lowercase()).or_default().push(i);
+            }
+            if let Some(name) = series::series_name(page) {
+                index.by_series.entry(name.to_lowercase()).or_default().push(i);
+            }
+            if pdf::is_highlights_page(&page.name) {
+                if let Some(file) = pdf::pdf_file(&page.properties) {
+                    index.pdf_files.insert(resolver::normalize_key(&page.name), file);
+                }
+                index.pdf_highlights.extend(pdf::highlights(page));
+            }
+            for id in page.outline().iter().filter_map(|block| block.id.clone()) {
+                index.blocks.entry(id).or_insert(i);
+            }
+        }
+        // Translations are found by name, so once all names are in
+        let translations: Vec<(String, usize)> = index
+            .pages
+            .iter()
+            .enumerate()
+            .filter_map(|(i, page)| Some((i18n::translation_of(page, &index)?, i)))
+            .collect();
+        for (original, i) in translations {
+            index.by_translation.entry(original).or_default().push(i);
+        }
+        index.fingerprint = fingerprint(&index.pages);
+        // Cached query results were read from the old pages
+        *self = PageIndex {
+            pages: index.pages,
+            by_name: index.by_name,
+            by_alias: index.by_alias,
+            by_slug: index.by_slug,
+            by_tag: index.by_tag,
+            by_namespace: index.by_namespace,
+            by_series: index.by_series,
+            by_translation: index.by_translation,
+            pdf_files: index.pdf_files,
+            pdf_highlights: index.pdf_highlights,
+            blocks: index.blocks,
+            fingerprint: index.fingerprint,
+            query_cache: Default::default(),
+            ..std::mem::take(self)
+        };
+    }
+
+    /// Set the graph's task workflow
+    pub fn with_workflow(mut self, workflow: Workflow) -> Self {
+        self.workflow = workflow;
+        // Task queries match by workflow
+        self.query_cache = Default::default();
+        self
+    }
+
+    pub fn workflow(&self) -> Workflow {
+        self.workflow
+    }
+
+    /// Set the callout mapping (`--callout-map`)
+    pub fn with_callouts(mut self, callouts: CalloutMap) -> Self {
+        self.callouts = callouts;
+        self
+    }
+
+    pub fn callouts(&self) -> &CalloutMap {
+        &self.callouts
+    }
+
+    /// Set how YouTube, Vimeo and tweet embeds are rendered
+    pub fn with_embeds(mut self, embeds: EmbedStyle) -> Self {
+        self.embeds = embeds;
+        self
+    }
+
+    pub fn embeds(&self) -> EmbedStyle {
+        self.embeds
+    }
+
+    /// Set the size of embedded PDF viewers
+    pub fn with_pdf_frame(mut self, frame: PdfFrame) -> Self {
+        self.pdf_frame = frame;
+        self
+    }
+
+    pub fn pdf_frame(&self) -> &PdfFrame {
+        &self.pdf_frame
+    }
+
+    /// Sanitize raw HTML and hiccup output
+    pub fn with_sanitize_html(mut self, sanitize: bool) -> Self {
+        self.sanitize_html = sanitize;
+        self
+    }
+
+    pub fn sanitize_html(&self) -> bool {
+        self.sanitize_html
+    }
+
+    /// Set the fetched metadata of external URLs (bare URLs with a title become links)
+    pub fn with_link_previews(mut self, previews: BTreeMap<String, LinkPreview>) -> Self {
+        self.link_previews = previews;
+        self
+    }
+
+    pub fn link_previews(&self) -> &BTreeMap<String, LinkPreview> {
+        &self.link_previews
+    }
+
+    /// Set the archive.org snapshots of external URLs (linked after each link)
+    pub fn with_archived_links(mut self, snapshots: BTreeMap<String, String>) -> Self {
+        self.archived_links = snapshots;
+        self
+    }
+
+    pub fn archived_links(&self) -> &BTreeMap<String, String> {
+        &self.archived_links
+    }
+
+    /// Set the related pages of each page (listed in its frontmatter)
+    pub fn with_related(mut self, related: BTreeMap<String, Vec<Related>>) -> Self {
+        self.related = related;
+        self
+    }
+
+    /// Related pages of a page, most related first
+    pub fn related(&self, name: &str) -> &[Related] {
+        self.related.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Set how many levels of children a block embed inlines (`None`: the whole subtree)
+    pub fn with_embed_depth(mut self, depth: Option<usize>) -> Self {
+        self.embed_depth = depth;
+        self
+    }
+
+    pub fn embed_depth(&self) -> Option<usize> {
+        self.embed_depth
+    }
+
+    /// Set how blocks collapsed in Logseq are rendered
+    pub fn with_collapsed(mut self, style: CollapsedStyle) -> Self {
+        self.collapsed = style;
+        self
+    }
+
+    pub fn collapsed(&self) -> CollapsedStyle {
+        self.collapsed
+    }
+
+    /// Set the seed random query results are drawn with
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self.query_cache = Default::default();
+        self
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Note this date (`YYYY-MM-DD`) under rendered query results
+    pub fn with_query_date(mut self, date: Option<String>) -> Self {
+        self.query_date = date;
+        self
+    }
+
+    pub fn query_date(&self) -> Option<&str> {
+        self.query_date.as_deref()
+    }
+
+    /// Set how tag queries compare tags
+    pub fn with_tag_match(mut self, tag_match: TagMatch) -> Self {
+        self.tag_match = tag_match;
+        self.query_cache = Default::default();
+        self
+    }
+
+    pub fn tag_match(&self) -> TagMatch {
+        self.tag_match
+    }
+
+    /// Set what links naming no page fall back to (`[[page]]` queries follow links too)
+    pub fn with_link_fallback(mut self, fallback: Fallback) -> Self {
+        self.link_fallback = fallback;
+        self.query_cache = Default::default();
+        self
+    }
+
+    pub fn link_fallback(&self) -> Fallback {
+        self.link_fallback
+    }
+
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    pub fn query_cache(&self) -> &Mutex<QueryCache> {
+        &self.query_cache
+    }
+
+    /// The page holding the block with this `id::`
+    pub fn block_page(&self, id: &str) -> Option<&Page> {
+        self.blocks.get(&id.to_lowercase()).map(|&i| &self.pages[i])
+    }
+
+    /// The PDF behind a `hls__` annotation page name
+    pub fn pdf_file(&self, name: &str) -> Option<&str> {
+        self.pdf_files.get(&resolver::normalize_key(name)).map(String::as_str)
+    }
+
+    /// A PDF highlight by block id
+    pub fn pdf_highlight(&self, id: &str) -> Option<&Highlight> {
+        self.pdf_highlights.get(&id.to_lowercase())
+    }
+
+    /// Give pages whose name the slug style respells (e.g. transliterated to ASCII)
+    /// that spelling as their slug; `slug::` properties and journal dates win
+    pub fn with_slug_style(mut self, style: SlugStyle) -> Self {
+        if style == SlugStyle::Unicode {
+            return self;
+        }
+        let derived = self
+            .pages
+            .iter_mut()
+            .filter(|p| !p.name.starts_with("journals/") && resolver::slug_property(&p.properties).is_none());
+        for page in derived {
+            page.slug = resolver::derived_slug(&page.name, style);
+        }
+        self.reindex();
+        self
+    }
+
+    /// Take the pages back out (to extend and re-index)
+    pub fn into_pages(self) -> Vec<Page> {
+        self.pages
+    }
+
+    /// Mutable page access for fields that are not indexed (e.g. `previous_slugs`)
+    pub fn pages_mut(&mut self) -> impl Iterator<Item = &mut Page> {
+        self.pages.iter_mut()
+    }
+
+    /// Pages whose name has this comparison key (see `resolver::normalize_key`)
+    pub fn named(&self, key: &str) -> impl Iterator<Item = &Page> {
+        self.lookup(self.by_name.get(key))
+    }
+
+    /// Pages with an alias that has this comparison key
+    pub fn aliased(&self, key: &str) -> impl Iterator<Item = &Page> {
+        self.lookup(self.by_alias.get(key))
+    }
+
+    /// Page published under this slug (case-insensitive)
+    pub fn with_slug(&self, slug: &str) -> Option<&Page> {
+        self.by_slug.get(&slug.to_lowercase()).map(|&i| &self.pages[i])
+    }
+
+    /// Pages tagged with this (lowercase) tag
+    pub fn tagged(&self, tag: &str) -> impl Iterator<Item = &Page> {
+        self.lookup(self.by_tag.get(tag))
+    }
+
+    /// Lowercase names of all tags
+    pub fn tag_names(&self) -> impl Iterator<Item = &str> {
+        self.by_tag.keys().map(String::as_str)
+    }
+
+    /// Pages whose top-level namespace is this (lowercase) name
+    pub fn in_namespace(&self, namespace: &str) -> impl Iterator<Item = &Page> {
+        self.lookup(self.by_namespace.get(namespace))
+    }
+
+    /// Pages of the series with this (lowercase) name, in load order
+    pub fn in_series(&self, series: &str) -> impl Iterator<Item = &Page> {
+        self.lookup(self.by_series.get(series))
+    }
+
+    /// Translations of the page with this lowercase name, in load order
+    pub fn translations(&self, original: &str) -> impl Iterator<Item = &Page> {
+        self.lookup(self.by_translation.get(original))
+    }
+
+    /// Lowercase names of all series
+    pub fn series_names(&self) -> impl Iterator<Item = &str> {
+        self.by_series.keys().map(String::as_str)
+    }
+
+    /// Position of one of the index's own pages
+    pub fn position(&self, page: &Page) -> Option<usize> {
+        let candidates = self.by_name.get(&resolver::normalize_key(&page.name))?;
+        candidates.iter().copied().find(|&i| std::ptr::eq(&self.pages[i], page))
+    }
+
+    fn lookup<'a>(&'a self, ids: Option<&'a Vec<usize>>) -> impl Iterator<Item = &'a Page> {
+        ids.into_iter().flatten().map(move |&i| &self.pages[i])
+    }
+}
+
+impl From<Vec<Page>> for PageIndex {
+    fn from(pages: Vec<Page>) -> Self {
+        PageIndex::new(pages)
+    }
+}
+
+/// Hash of the page fields queries read (names, content, properties, tags,
+/// aliases, namespaces, dates)
+fn fingerprint(pages: &[Page]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for page in pages {
+        page.name.hash(&mut hasher);
+        page.content.hash(&mut hasher);
+        let mut properties: Vec<_> = page.properties.iter().collect();
+        properties.sort();
+        properties.hash(&mut hasher);
+        page.tags.hash(&mut hasher);
+        page.aliases.hash(&mut hasher);
+        page.namespace.hash(&mut hasher);
+        page.created.hash(&mut hasher);
+        page.modified.hash(&mut hasher);
+        page.slug.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+impl std::ops::Deref for PageIndex {
+    type Target = [Page];
+
+    fn deref(&self) -> &[Page] {
+        &self.pages
+    }
+}
 
 /// Build index of all pages for query execution
 pub fn build_index(pages_dir: &Path) -> Result<PageIndex> {
     let mut index = Vec::new();
 
-    // Get all git dates in one batch call
+    // Created/modified dates for all files (git history, or file metadata)
     let repo_root = pages_dir.parent().unwrap_or(pages_dir);
-    let git_dates = get_all_git_dates(repo_root);
+    let git_dates = git::file_dates(repo_root);
+    let hidden = source::Hidden::load(repo_root);
 
+    // File name order, so the index (and everything listed from it) doesn't
+    // depend on the order the filesystem returns directory entries in
     for entry in walkdir::WalkDir::new(pages_dir)
+        .sort_by_file_name()
         .into_iter()
         .filter_map(|e| e.ok())
-        .filter(|e| e.path().extension().map_or(false, |ext| ext == "md"))
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "md"))
+        .filter(|e| !hidden.contains(e.path()))
     {
         if let Ok(page) = parse_page_for_index(entry.path(), &git_dates, repo_root) {
             index.push(page);
         }
     }
 
-    Ok(index)
+    Ok(PageIndex::new(merge_duplicates(index)))
+}
+
+/// Merge pages that Logseq treats as one (`Project X.md` and `project x.md`,
+/// `a___b.md` and `a%2Fb.md`): the earliest created (then first by name) keeps
+/// its name and gets the others' blocks appended, their missing properties
+/// added and their `tags::`/`alias::` values combined. Each merge is reported.
+pub fn merge_duplicates(pages: Vec<Page>) -> Vec<Page> {
+    let mut groups: Vec<Vec<Page>> = Vec::new();
+    let mut by_key: HashMap<String, usize> = HashMap::new();
+    for page in pages {
+        match by_key.get(&resolver::normalize_key(&page.name)) {
+            Some(&i) => groups[i].push(page),
+            None => {
+                by_key.insert(resolver::normalize_key(&page.name), groups.len());
+                groups.push(vec![page]);
+            }
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|mut group| {
+            if group.len() == 1 {
+                return group.pop().unwrap();
+            }
+            group.sort_by(|a, b| {
+                (a.created.is_none(), &a.created, &a.name).cmp(&(b.created.is_none(), &b.created, &b.name))
+            });
+            let mut others = group.split_off(1);
+            let mut page = group.pop().unwrap();
+            let file = |p: &Page| {
+                p.source.as_deref().and_then(Path::file_name).map_or(p.name.clone(), |f| f.to_string_lossy().into_owned())
+            };
+            for other in &others {
+                warnings::global().warn(
+                    "duplicate-page",
+                    &format!("{} is the same page as {}; merged into it", file(other), file(&page)),
+                    Some(&page.name),
+                );
+            }
+            merge_into(&mut page, &mut others);
+            page
+        })
+        .collect()
+}
+
+fn merge_into(page: &mut Page, others: &mut [Page]) {
+    let lines: Vec<&str> = page.content.lines().collect();
+    let (properties, end) = property_block(&lines);
+    let mut block: Vec<String> = lines[..end].iter().map(|l| l.to_string()).collect();
+    while block.last().is_some_and(|l| l.trim().is_empty()) {
+        block.pop();
+    }
+    let mut bodies = vec![lines[end..].join("\n")];
+
+    for other in others.iter() {
+        let (other_properties, other_body) = parse_properties(&other.content);
+        let mut keys: Vec<&String> = other_properties.keys().collect();
+        keys.sort();
+        for key in keys {
+            let value = &other_properties[key];
+            match properties.get(key) {
+                None => block.push(format!("{}:: {}", key, value)),
+                Some(own) if (key == "tags" || key == "alias") && own != value => {
+                    let line = block.iter_mut().find(|l| l.trim_start_matches('-').trim().to_lowercase().starts_with(&format!("{}::", key)));
+                    if let Some(line) = line {
+                        line.push_str(&format!(", {}", value));
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+        bodies.push(other_body);
+    }
+
+    let body = bodies.iter().map(|b| b.trim_matches('\n')).filter(|b| !b.is_empty()).collect::<Vec<_>>().join("\n");
+    page.set_content(if block.is_empty() { body } else { format!("{}\n\n{}", block.join("\n"), body) });
+    let (properties, _) = parse_properties(&page.content);
+    page.tags = extract_tags(&properties, &page.content);
+    page.aliases = extract_aliases(&properties);
+    page.properties = properties;
+    page.modified = others.iter().filter_map(|o| o.modified.clone()).chain(page.modified.clone()).max();
+    page.duplicates.extend(others.iter_mut().filter_map(|o| o.source.take()));
+}
+
+/// Merge each page named like a journal day (`2025-01-01`, `2025_01_01`) into
+/// the journal of that day, as `strategy` says, so the day is published once, at
+/// the journal's URL. Each merge is reported; links to the page's name reach the
+/// journal by date.
+pub fn merge_date_pages(mut index: PageIndex, strategy: DatePages) -> PageIndex {
+    let pages = &mut index.pages;
+    let journals: HashMap<String, usize> = pages
+        .iter()
+        .enumerate()
+        .filter_map(|(i, p)| Some((journals::parse_journal_date(p.name.strip_prefix("journals/")?)?.0, i)))
+        .collect();
+    let dated: Vec<(usize, usize)> = pages
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| !p.name.starts_with("journals/"))
+        .filter_map(|(i, p)| Some((i, *journals.get(&journals::parse_journal_date(&p.name)?.0)?)))
+        .collect();
+    if dated.is_empty() {
+        return index;
+    }
+
+    for &(i, j) in &dated {
+        let mut page = pages[i].clone();
+        let journal = &mut pages[j];
+        let outcome = match strategy {
+            DatePages::Concat => "its blocks were appended to the journal",
+            DatePages::PreferJournal => "the journal was kept",
+            DatePages::PreferPage => "the page was kept",
+        };
+        let file = |p: &Page| p.source.as_deref().map_or(p.name.clone(), |s| s.display().to_string());
+        warnings::global().warn(
+            "date-page",
+            &format!("{} is the same day as {}; {}", file(&page), file(journal), outcome),
+            journal.slug.as_deref(),
+        );
+        match strategy {
+            DatePages::Concat => merge_into(journal, std::slice::from_mut(&mut page)),
+            DatePages::PreferJournal => journal.duplicates.extend(page.source),
+            DatePages::PreferPage => {
+                journal.set_content(page.content);
+                journal.properties = page.properties;
+                journal.tags = page.tags;
+                journal.aliases = page.aliases;
+                journal.duplicates.extend(page.source);
+            }
+        }
+    }
+    let merged: HashSet<usize> = dated.iter().map(|&(i, _)| i).collect();
+    let mut i = 0;
+    pages.retain(|_| {
+        i += 1;
+        !merged.contains(&(i - 1))
+    });
+    index.reindex();
+    index
+}
+
+/// The index without the `private:: true` blocks of its pages, so nothing
+/// read from it (embeds, queries, previews, feeds, events, descriptions)
+/// publishes them
+pub fn drop_private_blocks(mut index: PageIndex) -> PageIndex {
+    for page in &mut index.pages {
+        let mut outline = page.outline().clone();
+        if outline.drop_private() {
+            page.set_outline(outline);
+            // Tags only the private blocks used go with them
+            page.tags = extract_tags(&page.properties, &page.content);
+        }
+    }
+    index.reindex();
+    index
+}
+
+/// Build the index for a whole graph: pages plus journals (prefixed with journals/)
+pub fn build_graph_index(input_dir: &Path) -> Result<PageIndex> {
+    let pages_dir = input_dir.join("pages");
+    let journals_dir = input_dir.join("journals");
+    let mut page_index = build_index(&pages_dir)?.into_pages();
+    if journals_dir.exists() {
+        let journal_index = build_index(&journals_dir)?;
+        // Prefix journal pages with journals/ so query result links work
+        for mut page in journal_index.into_pages() {
+            // Journals are published by date (journals/YYYY-MM-DD), whatever the file name
+            if let Some((date, _)) = journals::parse_journal_date(&page.name) {
+                page.slug = Some(resolver::journal_output_name(&date));
+            }
+            page.name = format!("journals/{}", page.name);
+            page.name_lower = page.name.to_lowercase();
+            page_index.push(page);
+        }
+    }
+    let workflow = Workflow::from_config(&input_dir.join("logseq/config.edn"));
+    Ok(PageIndex::new(page_index).with_workflow(workflow))
 }
 
 /// Parse a page file for indexing (properties, tags, content)
@@ -52,29 +725,24 @@ fn parse_page_for_index(
     git_dates: &HashMap<String, (String, String)>,
     repo_root: &Path,
 ) -> Result<Page> {
-    let content = fs::read_to_string(path)?;
+    let content = source::read_page(path)?;
     let filename = path.file_stem().unwrap().to_string_lossy().to_string();
 
     // Handle namespace (filename with ___)
-    let (name, namespace) = if filename.contains("___") {
-        let parts: Vec<&str> = filename.splitn(2, "___").collect();
-        (parts.join("/"), Some(parts[0].to_string()))
-    } else {
-        (filename.clone(), None)
-    };
+    let name = resolver::page_name(&filename);
+    let namespace = filename
+        .split_once("___")
+        .map(|(ns, _)| ns.to_string());
 
     let (properties, _remaining) = parse_properties(&content);
     let tags = extract_tags(&properties, &content);
     let aliases = extract_aliases(&properties);
+    let slug = resolver::slug_property(&properties).or_else(|| resolver::derived_slug(&name, SlugStyle::Unicode));
 
     // Get git dates from batch lookup
-    let relative_path = path.strip_prefix(repo_root)
-        .map(|p| p.to_string_lossy().to_string())
-        .unwrap_or_default();
-    let (modified, created) = git_dates
-        .get(&relative_path)
-        .map(|(m, c)| (Some(m.clone()), Some(c.clone())))
-        .unwrap_or((None, None));
+    let relative_path = git::relative_key(path, repo_root);
+    let file_dates = git_dates.get(&relative_path).map(|(m, c)| (m.as_str(), c.as_str()));
+    let (modified, created) = frontmatter::page_dates(&properties, file_dates);
 
     Ok(Page {
         name: name.clone(),
@@ -86,18 +754,30 @@ fn parse_page_for_index(
         namespace,
         modified,
         created,
+        slug,
+        previous_slugs: Vec::new(),
+        source: Some(path.to_path_buf()),
+        duplicates: Vec::new(),
+        outline: OnceLock::new(),
     })
 }
 
 /// Parse Logseq properties from content
 pub fn parse_properties(content: &str) -> (HashMap<String, String>, String) {
+    let lines: Vec<&str> = content.lines().collect();
+    let (properties, end_index) = property_block(&lines);
+    let remaining = lines[end_index..].join("\n");
+    (properties, remaining)
+}
+
+/// Page properties at the start of a page, and the number of lines they take
+fn property_block(lines: &[&str]) -> (HashMap<String, String>, usize) {
     lazy_static::lazy_static! {
         static ref PROP_RE: Regex = Regex::new(r"^-?\s*([a-zA-Z_-]+)::\s*(.+)$").unwrap();
     }
 
     let mut properties = HashMap::new();
     let mut end_index = 0;
-    let lines: Vec<&str> = content.lines().collect();
 
     for (i, line) in lines.iter().enumerate() {
         let clean_line = line.trim_start_matches('-').trim();
@@ -109,15 +789,14 @@ pub fn parse_properties(content: &str) -> (HashMap<String, String>, String) {
             end_index = i + 1;
         } else if clean_line.is_empty() && !properties.is_empty() {
             end_index = i + 1;
-        } else if !properties.is_empty() {
-            break;
-        } else if !clean_line.is_empty() && !clean_line.starts_with('-') {
+        } else if !properties.is_empty()
+            || (!clean_line.is_empty() && !clean_line.starts_with('-'))
+        {
             break;
         }
     }
 
-    let remaining = lines[end_index..].join("\n");
-    (properties, remaining)
+    (properties, end_index)
 }
 
 /// Extract tags from properties and content
@@ -126,24 +805,19 @@ fn extract_tags(properties: &HashMap<String, String>, content: &str) -> Vec<Stri
 
     // From properties
     if let Some(tags_str) = properties.get("tags") {
-        for tag in tags_str.split(',') {
-            let tag = tag.trim()
-                .trim_start_matches("[[")
-                .trim_end_matches("]]")
-                .to_lowercase();
-            if !tag.is_empty() {
-                tags.push(tag);
-            }
-        }
+        tags.extend(properties::list_texts(tags_str).iter().map(|t| t.to_lowercase()));
     }
 
-    // From content #tags
+    // From content #tags and #[[multi word tags]]
     lazy_static::lazy_static! {
-        static ref TAG_RE: Regex = Regex::new(r"#([a-zA-Z][a-zA-Z0-9_-]*)").unwrap();
+        static ref TAG_RE: Regex = Regex::new(r"#(?:\[\[([^\]]+)\]\]|([a-zA-Z][a-zA-Z0-9_-]*))").unwrap();
     }
 
     for caps in TAG_RE.captures_iter(content) {
-        let tag = caps.get(1).unwrap().as_str().to_lowercase();
+        let tag = caps.get(1).or(caps.get(2)).unwrap().as_str().trim().to_lowercase();
+        if tag.is_empty() {
+            continue;
+        }
         if !tags.contains(&tag) {
             tags.push(tag);
         }
@@ -154,59 +828,7 @@ fn extract_tags(properties: &HashMap<String, String>, content: &str) -> Vec<Stri
 
 /// Extract aliases from properties
 fn extract_aliases(properties: &HashMap<String, String>) -> Vec<String> {
-    let mut aliases = Vec::new();
-
-    if let Some(alias_str) = properties.get("alias") {
-        for alias in alias_str.split(',') {
-            let alias = alias.trim()
-                .trim_start_matches("[[")
-                .trim_end_matches("]]")
-                .to_string();
-            if !alias.is_empty() {
-                aliases.push(alias);
-            }
-        }
-    }
-
-    aliases
-}
-
-/// Get all git dates in batch (much faster than per-file)
-pub fn get_all_git_dates(repo_root: &Path) -> HashMap<String, (String, String)> {
-    use std::process::Command;
-
-    let mut dates: HashMap<String, (String, String)> = HashMap::new();
-
-    // Get last modified date for all files
-    if let Ok(output) = Command::new("git")
-        .args(["log", "--format=%aI", "--name-only", "--diff-filter=AM"])
-        .current_dir(repo_root)
-        .output()
-    {
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let mut current_date = String::new();
-
-        for line in stdout.lines() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-
-            // Date lines start with a year
-            if line.starts_with("20") && line.contains('T') {
-                current_date = line.split('T').next().unwrap_or("").to_string();
-            } else if line.ends_with(".md") && !current_date.is_empty() {
-                let entry = dates.entry(line.to_string()).or_insert_with(|| {
-                    (current_date.clone(), current_date.clone())
-                });
-                // First time we see the file = most recent (modified)
-                // Last time = oldest (created)
-                entry.1 = current_date.clone(); // Update created to older date
-            }
-        }
-    }
-
-    dates
+    properties.get("alias").map_or_else(Vec::new, |aliases| properties::list_texts(aliases))
 }
 
 /// Process a single page file
@@ -218,24 +840,61 @@ pub fn process_page(
     git_dates: &HashMap<String, (String, String)>,
     repo_root: &Path,
 ) -> Result<bool> {
-    let content = fs::read_to_string(source_path)?;
+    log::trace!("processing {}", source_path.display());
     let filename = source_path.file_stem().unwrap().to_string_lossy();
 
+    // Files Logseq treats as the same page are published once, merged (see `merge_duplicates`)
+    let name = resolver::page_name(&filename);
+    let indexed = resolver::find_page(&name, page_index);
+    if let Some(page) = indexed {
+        if page.duplicates.iter().any(|file| file == source_path) {
+            return Ok(false);
+        }
+    } else if let Some((date, _)) = journals::parse_journal_date(&name) {
+        // Merged into the journal of its day (see `merge_date_pages`)
+        let journal = page_index.with_slug(&resolver::journal_output_name(&date));
+        if journal.is_some_and(|j| j.duplicates.iter().any(|file| file == source_path)) {
+            return Ok(false);
+        }
+    }
+
+    // The index read the file already (and merged its duplicates in)
+    let content = match indexed.filter(|page| page.source.as_deref() == Some(source_path)) {
+        Some(page) => page.content.clone(),
+        None => source::read_page(source_path)?,
+    };
+
     // Parse properties
-    let (properties, remaining_content) = parse_properties(&content);
+    let (mut properties, mut remaining_content) = parse_properties(&content);
+
+    // A PDF's hls__ annotation page becomes a readable "Highlights from X.pdf" page
+    if pdf::is_highlights_page(&filename) {
+        if let Some(file) = pdf::pdf_file(&properties) {
+            remaining_content = pdf::highlights_body(&remaining_content, &file);
+            properties.entry("title".to_string()).or_insert_with(|| format!("Highlights from {}", pdf::display_name(&file)));
+            properties.remove("file");
+            properties.remove("file-path");
+        }
+    }
 
     // Skip private pages
+    if !config.include_private && properties::is_private(&properties) {
+        return Ok(false);
+    }
+    // And private blocks of published ones
+    let mut blocks = outline::parse(&remaining_content);
     if !config.include_private {
-        if let Some(private) = properties.get("private") {
-            if private.to_lowercase() == "true" {
-                return Ok(false);
-            }
-        }
+        blocks.drop_private();
     }
+    let remaining_content = blocks.render();
 
-    // Convert namespace separator
-    let output_filename = filename.replace("___", "/");
-    let output_path = output_dir.join(format!("{}.md", output_filename));
+    // Output path: slug:: property or page name (namespace separator becomes folder)
+    if let Some(slug) = properties.get("slug").filter(|slug| !resolver::is_contained(slug)) {
+        let message = format!("slug {} leaves the output folder; the page name is used", slug);
+        warnings::global().warn("slug", &message, Some(&resolver::page_name(&filename)));
+    }
+    let output_name = resolver::output_name(&filename, &properties, config.slug_style);
+    let output_path = resolver::output_path(output_dir, &output_name);
 
     // Create parent directories if needed
     if let Some(parent) = output_path.parent() {
@@ -243,171 +902,253 @@ pub fn process_page(
     }
 
     // Get git dates for this file
-    let relative_path = source_path.strip_prefix(repo_root)
-        .map(|p| p.to_string_lossy().to_string())
-        .unwrap_or_default();
+    let relative_path = git::relative_key(source_path, repo_root);
     let dates = git_dates.get(&relative_path)
         .map(|(m, c)| (m.as_str(), c.as_str()));
 
-    // Generate frontmatter
-    let frontmatter = frontmatter::generate(&filename, &properties, dates);
+    // Links to pages that don't exist (stubs fill these in when enabled)
+    if !config.create_stubs {
+        let page_name = resolver::page_name(&filename);
+        for link in content::unresolved_links(&remaining_content, page_index) {
+            warnings::global().warn("broken-link", &link, Some(&page_name));
+        }
+    }
+    // Section links to headings the page doesn't have, and links resolved by a guess
+    for link in content::missing_anchors(&remaining_content, page_index) {
+        warnings::global().warn("broken-anchor", &link, Some(&resolver::page_name(&filename)));
+    }
+    for (link, page) in content::fallback_links(&remaining_content, page_index) {
+        warnings::global().warn("link-fallback", &format!("{} → {}", link, page), Some(&resolver::page_name(&filename)));
+    }
+
+    // Inline #tags: linked or stripped per --inline-tags, and listed in frontmatter
+    let inline_tags = content::inline_tags(&mut blocks, config.inline_tags);
 
     // Transform content
-    let transformed = content::transform(&remaining_content, page_index);
+    let transformed = content::transform_outline(blocks, page_index);
+    warnings::global().page_diagnostics(&transformed.diagnostics, &resolver::page_name(&filename));
 
-    // Write output
-    let output = format!("{}\n{}", frontmatter, transformed);
-    fs::write(output_path, output)?;
+    // Translations (`Garden.zh`, `translation-of::`): titled like the original
+    let variants = indexed.map(|page| i18n::variants(page, page_index, config.include_private)).unwrap_or_default();
+    if let Some(title) = indexed.and_then(|page| i18n::variant_title(page, page_index)) {
+        properties.insert("title".to_string(), title);
+    }
 
-    Ok(true)
-}
+    // Generate frontmatter (including any query-output:: frontmatter listing);
+    // with --alias-style redirect, aliases get pages of their own instead
+    let frontmatter = match config.alias_style {
+        AliasStyle::Frontmatter => frontmatter::generate(&filename, &properties, dates, &config.icon_map),
+        AliasStyle::Redirect => {
+            let mut without_aliases = properties.clone();
+            without_aliases.remove("alias");
+            frontmatter::generate(&filename, &without_aliases, dates, &config.icon_map)
+        }
+    };
+    let frontmatter = frontmatter::insert_list(&frontmatter, "tags", &frontmatter::new_tags(&properties, &inline_tags));
+    let frontmatter = frontmatter::insert_list(&frontmatter, "listing", &transformed.listing);
+    let frontmatter = match indexed {
+        Some(page) if !variants.is_empty() || properties.contains_key("lang") => {
+            let lang = i18n::language(page, page_index, &config.default_language);
+            frontmatter::insert_field(&frontmatter, "lang", &escape::yaml_scalar(&lang))
+        }
+        _ => frontmatter,
+    };
 
-/// Create stub pages for missing linked pages
-pub fn create_stubs(output_dir: &Path, _page_index: &PageIndex) -> Result<usize> {
-    // Collect all existing files from output_dir (content root)
-    let existing: HashSet<String> = walkdir::WalkDir::new(output_dir)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.path().extension().map_or(false, |ext| ext == "md"))
-        .filter_map(|e| {
-            e.path()
-                .strip_prefix(output_dir)
-                .ok()
-                .map(|p| {
-                    // Normalize: remove .md extension, lowercase, replace spaces with various formats
-                    let s = p.to_string_lossy();
-                    let s = s.trim_end_matches(".md").to_lowercase();
-                    s.to_string()
-                })
-        })
-        .collect();
+    // Social card: description excerpt and image
+    let frontmatter = social::insert_frontmatter(
+        &frontmatter,
+        &properties,
+        &transformed.content,
+        config.description_words,
+        config.base_url.as_deref(),
+    );
 
-    // Collect all wikilinks from output files
-    let mut all_links = HashSet::new();
-    for entry in walkdir::WalkDir::new(output_dir)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.path().extension().map_or(false, |ext| ext == "md"))
-    {
-        if let Ok(content) = fs::read_to_string(entry.path()) {
-            extract_wikilinks(&content, &mut all_links);
-        }
-    }
+    // Word count and reading time, counted into the run totals
+    let frontmatter = if config.word_count {
+        metrics::insert_frontmatter(&frontmatter, metrics::record(&config.output_dir, &transformed.content))
+    } else {
+        frontmatter
+    };
 
-    // Create stubs for missing pages
-    let mut created = 0;
-    for link in all_links {
-        // Strip pages/ prefix if present
-        let link = link.strip_prefix("pages/").unwrap_or(&link);
+    // Former slugs become aliases so old URLs keep working (Quartz redirects on aliases)
+    let previous_slugs = resolver::find_page(&resolver::page_name(&filename), page_index)
+        .map(|p| p.previous_slugs.clone())
+        .unwrap_or_default();
+    let frontmatter = frontmatter::insert_list(&frontmatter, "aliases", &previous_slugs);
 
-        // Skip special folders
-        if link.starts_with("journals/") || link.starts_with("favorites/") || link.starts_with("assets/") {
-            continue;
-        }
+    // Status badges: status:: active → status + cssclasses: status-active
+    let page_badges = badges::badges(&properties, &config.badge_properties);
+    let frontmatter = badges::insert_frontmatter(&frontmatter, &page_badges);
 
-        // Check if page exists with various name formats
-        let link_lower = link.to_lowercase();
-        let link_normalized = link_lower.replace(' ', "-");
-        let link_with_spaces = link_lower.replace('-', " ");
-
-        if existing.contains(&link_lower)
-            || existing.contains(&link_normalized)
-            || existing.contains(&link_with_spaces)
-            || existing.iter().any(|e| {
-                let e_normalized = e.replace(' ', "-").replace('_', "-");
-                let link_norm = link_lower.replace(' ', "-").replace('_', "-");
-                e_normalized == link_norm
-            })
-            // Prefix matching: "visit us" matches "visit" if link starts with existing page + separator
-            || existing.iter().any(|e| {
-                let link_norm = link_lower.replace('-', " ").replace('_', " ");
-                let e_norm = e.replace('-', " ").replace('_', " ");
-                // Check if link starts with existing page name followed by a space
-                if link_norm.len() > e_norm.len() {
-                    link_norm.starts_with(&e_norm) &&
-                    link_norm.chars().nth(e_norm.len()) == Some(' ')
+    // Linked references narrowed by filters:: (page) or :filters (config.edn), for the Backlinks panel
+    let frontmatter = match resolver::find_page(&resolver::page_name(&filename), page_index) {
+        Some(page) => {
+            let filters = references::page_filters(page, &config.reference_filters);
+            if filters.is_empty() {
+                frontmatter
+            } else {
+                let linked = references::linked_references(page, page_index, &filters, config.include_private);
+                if linked.is_empty() {
+                    // An empty list still tells the theme the page is filtered
+                    frontmatter::insert_field(&frontmatter, "linked-references", "[]")
                 } else {
-                    false
+                    frontmatter::insert_list(&frontmatter, "linked-references", &linked)
                 }
-            })
-        {
-            continue;
+            }
         }
+        None => frontmatter,
+    };
 
-        // Skip non-page links
-        if link.starts_with("http") || link.starts_with('#') || link.contains("://") {
-            continue;
-        }
-        if link.len() <= 1 || link.len() > 200 {
-            continue;
+    // Related pages (--related frontmatter), for the theme's RelatedNotes panel
+    let frontmatter = match indexed {
+        Some(page) => frontmatter::insert_list(&frontmatter, "related", &related::frontmatter_targets(page, page_index)),
+        None => frontmatter,
+    };
+
+    // Write output
+    let mut body = with_toc(transformed.content, &properties, config);
+    body = properties::metadata_block(&properties, &content, config.page_properties, page_index) + &body;
+    if config.badge_line && !page_badges.is_empty() {
+        body = format!("{}{}", badges::render_line(&page_badges), body);
+    }
+    if let Some(page) = indexed.filter(|_| !variants.is_empty()) {
+        body = format!("{}{}", i18n::switcher(page, &variants, page_index, &config.default_language), body);
+    }
+    // Previous/next links for pages of a series::
+    if let Some(page) = indexed {
+        if let Some(nav) = series::navigation(page, page_index, config.include_private) {
+            body = format!("{}\n{}", body.trim_end(), nav);
         }
+    }
+    let output = format!("{}\n{}", frontmatter, body);
+    fs::write(output_path, output)?;
 
-        // Skip date patterns like 2024-01-15, 2024_01_15, 2024 01 15
-        if link.len() >= 8 && link.len() <= 12 {
-            let is_date = link.chars().all(|c| c.is_ascii_digit() || c == '-' || c == '_' || c == ' ');
-            if is_date {
+    Ok(true)
+}
+
+/// Prepend a generated contents section when `--toc` or `toc::` asks for one
+pub fn with_toc(content: String, properties: &HashMap<String, String>, config: &Config) -> String {
+    let headings = toc::headings(&content);
+    if toc::wants_toc(properties.get("toc").map(String::as_str), config.toc, &headings) {
+        toc::insert(&content, &headings)
+    } else {
+        content
+    }
+}
+
+/// How `alias::` names are published (`--alias-style`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum AliasStyle {
+    /// Listed in frontmatter `aliases:`, for Quartz's alias redirects
+    #[default]
+    Frontmatter,
+    /// A redirect page per alias at the path the alias name maps to
+    Redirect,
+}
+
+/// Write a redirect page for each alias of each published page (`--alias-style redirect`):
+/// `/cv` forwards to `/cyber-valley` with a meta refresh and links to it.
+/// Aliases whose path is taken by a page (or another alias) are skipped with a warning.
+pub fn create_alias_redirects(output_dir: &Path, page_index: &PageIndex, config: &Config) -> Result<usize> {
+    let mut created = 0;
+    let pages = page_index.iter().filter(|p| {
+        !p.name.starts_with("journals/")
+            && (config.include_private || !p.is_private())
+    });
+    for page in pages {
+        for alias in &page.aliases {
+            if !resolver::is_contained(alias) {
+                warnings::global().warn("alias", &format!("{} leaves the output folder; no redirect written", alias), Some(&page.name));
                 continue;
             }
+            let name = resolver::derived_slug(alias, config.slug_style).unwrap_or_else(|| alias.clone());
+            let path = resolver::output_path(output_dir, &name);
+            if path.exists() {
+                warnings::global().warn("alias", &format!("{} is taken by another page; no redirect written", name), Some(&page.name));
+                continue;
+            }
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, alias_redirect(alias, &name, page))?;
+            created += 1;
         }
+    }
+    Ok(created)
+}
 
-        // Unescape dollar signs first (from \$ to $), then sanitize for filesystem
-        // Keep '/' for namespace folder structure, only replace invalid chars
-        let unescaped_link = link.replace("\\$", "$");
-        let safe_link = unescaped_link.replace([':', '*', '?', '"', '<', '>', '|', '\\'], "_");
+/// Redirect page for one alias (`name`: its output name)
+pub fn alias_redirect(alias: &str, name: &str, page: &Page) -> String {
+    // Relative, so the redirect works under any base path
+    let url = format!("{}{}", "../".repeat(name.matches('/').count()), resolver::site_path(page));
+    format!(
+        "---\ntitle: {}\nredirect: {}\nnoindex: true\n---\n\n<meta http-equiv=\"refresh\" content=\"0; url={}\">\n\nMoved to [[{}|{}]].\n",
+        escape::yaml_quoted(alias),
+        escape::yaml_quoted(&resolver::site_path(page)),
+        escape::html(&url),
+        resolver::link_target(page),
+        page.title()
+    )
+}
+
+/// Stub page body used without `--stub-template`
+pub const DEFAULT_STUB_TEMPLATE: &str =
+    "> [!note] Stub Page\n> This page was auto-generated.\n\n**Linked from:**\n{{linked_from}}\n";
 
-        let stub_path = output_dir.join(format!("{}.md", safe_link));
+/// Create stub pages for link targets without a page, at the output path links
+/// point to (namespaces as folders). Stubs are tagged `stub` and marked `noindex`;
+/// the body comes from `config.stub_template` or `DEFAULT_STUB_TEMPLATE`.
+pub fn create_stubs(output_dir: &Path, page_index: &PageIndex, config: &Config) -> Result<usize> {
+    let template = config.stub_template.as_deref().unwrap_or(DEFAULT_STUB_TEMPLATE);
+    let mut created = 0;
+    for stub in resolver::plan_stubs(page_index, config.include_private) {
+        let stub_path = resolver::output_path(output_dir, &stub.name);
         if stub_path.exists() {
             continue;
         }
 
-        // Create stub
         if let Some(parent) = stub_path.parent() {
             if let Err(e) = fs::create_dir_all(parent) {
-                eprintln!("Failed to create dir for stub '{}': {}", link, e);
+                warnings::global().warn("stub", &format!("failed to create directory: {}", e), Some(&stub.title));
                 continue;
             }
         }
 
-        // Unescape dollar signs for YAML title (backslash escape is invalid in YAML)
-        let title = link.replace('_', " ").replace("\\$", "$");
         let stub_content = format!(
-            "---\ntitle: \"{}\"\nstub: true\n---\n\n> [!note] Stub Page\n> This page was auto-generated.\n",
-            title
+            "---\ntitle: {}\nstub: true\nnoindex: true\ntags:\n  - stub\n---\n\n{}",
+            escape::yaml_quoted(&stub.title),
+            render_stub(template, &stub, page_index)
         );
 
         match fs::write(&stub_path, &stub_content) {
             Ok(_) => created += 1,
-            Err(e) => eprintln!("Failed to write stub '{}': {}", stub_path.display(), e),
+            Err(e) => {
+                warnings::global().warn("stub", &format!("failed to write stub: {}", e), Some(&stub.title));
+            }
         }
     }
 
     Ok(created)
 }
 
-/// Extract wikilinks from content (both [[...]] syntax and HTML anchors)
-fn extract_wikilinks(content: &str, links: &mut HashSet<String>) {
-    lazy_static::lazy_static! {
-        // Standard wikilink syntax
-        static ref LINK_RE: Regex = Regex::new(r"\[\[([^\]|]+)(?:\|[^\]]+)?\]\]").unwrap();
-        // HTML anchor with data-slug attribute (used for $ pages to avoid KaTeX)
-        static ref HTML_LINK_RE: Regex = Regex::new(r#"<a\s+href="([^"]+)"\s+class="internal[^"]*""#).unwrap();
-    }
-
-    // Extract from wikilink syntax
-    for caps in LINK_RE.captures_iter(content) {
-        let link = caps.get(1).unwrap().as_str().trim();
-        // Decode HTML entities back to original characters for stub matching
-        let link = link.replace("&#36;", "$");
-        if !link.starts_with("http") && !link.starts_with('#') && !link.starts_with('!') {
-            links.insert(link.to_lowercase());
-        }
-    }
-
-    // Extract from HTML anchors (dollar sign pages)
-    for caps in HTML_LINK_RE.captures_iter(content) {
-        let link = caps.get(1).unwrap().as_str().trim();
-        if !link.starts_with("http") && !link.starts_with('#') {
-            links.insert(link.to_lowercase());
-        }
-    }
+/// Fill a stub template: `{{title}}` (link text), `{{name}}` (output path),
+/// `{{linked_from}}` (list of links to the referencing pages), `{{linked_count}}`
+pub fn render_stub(template: &str, stub: &resolver::Stub, page_index: &PageIndex) -> String {
+    let linked_from: Vec<String> = stub
+        .linked_from
+        .iter()
+        .map(|name| {
+            let target = resolver::find_page(name, page_index).map_or(name.as_str(), resolver::link_target);
+            if target == name {
+                format!("- [[{}]]", name)
+            } else {
+                format!("- [[{}|{}]]", target, name)
+            }
+        })
+        .collect();
+    template
+        .replace("{{title}}", &stub.title)
+        .replace("{{name}}", &stub.name)
+        .replace("{{linked_from}}", &linked_from.join("\n"))
+        .replace("{{linked_count}}", &stub.linked_from.len().to_string())
 }
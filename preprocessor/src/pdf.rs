@@ -0,0 +1,189 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+
+use crate::escape;
+use crate::page::Page;
+use crate::resolver;
+
+lazy_static! {
+    // file:: [name.pdf](../assets/name.pdf)
+    static ref FILE_LINK_RE: Regex = Regex::new(r"^\[([^\]]*)\]\(([^)]+)\)$").unwrap();
+
+    // Logseq's upload suffix on asset names: report_1700000000000_0.pdf
+    static ref UPLOAD_SUFFIX_RE: Regex = Regex::new(r"_\d{13}_\d+(\.pdf)$").unwrap();
+
+    // A block property line: key:: value
+    static ref BLOCK_PROP_RE: Regex = Regex::new(r"^\s*([\w-]+)::\s*(.*)$").unwrap();
+}
+
+/// Size of PDF embed iframes (`--pdf-width`, `--pdf-height`), as CSS lengths
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdfFrame {
+    pub width: String,
+    pub height: String,
+}
+
+impl Default for PdfFrame {
+    fn default() -> Self {
+        PdfFrame {
+            width: "100%".to_string(),
+            height: "600px".to_string(),
+        }
+    }
+}
+
+/// A PDF highlight: a block on a `hls__` page with `ls-type:: annotation`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlight {
+    /// The PDF, as linked from the annotation page's `file-path::`
+    pub file: String,
+    /// 1-based PDF page (`hl-page::`)
+    pub page: Option<u32>,
+    /// Highlighted text; empty for area (image) highlights
+    pub text: String,
+    /// Snapshot Logseq saves for an area highlight (`../assets/<pdf>/<page>_<id>_<stamp>.png`)
+    pub image: Option<String>,
+}
+
+/// Block properties Logseq writes on annotation blocks (dropped from the rendered page)
+const ANNOTATION_KEYS: &[&str] = &["ls-type", "hl-page", "hl-color", "hl-type", "hl-stamp", "hl-value", "id"];
+
+/// Is this the annotation page Logseq keeps for a PDF (`hls__report_1700000000000_0`)?
+pub fn is_highlights_page(name: &str) -> bool {
+    name.get(..5).is_some_and(|prefix| prefix.eq_ignore_ascii_case("hls__"))
+}
+
+/// The PDF an annotation page belongs to: `file-path::`, or the link in `file::`
+pub fn pdf_file(properties: &HashMap<String, String>) -> Option<String> {
+    if let Some(path) = properties.get("file-path").filter(|p| !p.trim().is_empty()) {
+        return Some(path.trim().to_string());
+    }
+    let file = properties.get("file")?;
+    FILE_LINK_RE.captures(file.trim()).map(|caps| caps[2].to_string())
+}
+
+/// Highlights on an annotation page by block id (lowercase)
+pub fn highlights(page: &Page) -> Vec<(String, Highlight)> {
+    let Some(file) = pdf_file(&page.properties) else {
+        return Vec::new();
+    };
+    top_level_blocks(&page.content)
+        .iter()
+        .filter_map(|block| annotation(block, &file))
+        .collect()
+}
+
+/// Body of an annotation page: a link to the PDF, then each highlight as a
+/// quote linked to its PDF page (area highlights as their snapshot). Notes
+/// under highlights and other blocks are kept as written.
+pub fn highlights_body(content: &str, file: &str) -> String {
+    let mut out = vec![format!("Highlights from [📄 {}]({})", display_name(file), file), String::new()];
+    for block in top_level_blocks(content) {
+        let Some((_, highlight)) = annotation(&block, file) else {
+            out.extend(block.iter().map(|line| line.to_string()));
+            continue;
+        };
+        let page = highlight.page.map_or_else(|| "PDF".to_string(), |p| format!("p. {}", p));
+        let link = format!("[{}]({})", page, page_url(file, highlight.page));
+        out.push(match &highlight.image {
+            Some(image) => format!("- ![Area highlight, {}]({}) — {}", page, image, link),
+            None if highlight.text.is_empty() => format!("- Area highlight — {}", link),
+            None => format!("- > {} — {}", highlight.text, link),
+        });
+        let own = own_lines(&block);
+        let notes = block[1..=own].iter().filter(|line| {
+            BLOCK_PROP_RE
+                .captures(line)
+                .is_none_or(|caps| !ANNOTATION_KEYS.contains(&caps[1].to_lowercase().as_str()))
+        });
+        out.extend(notes.chain(&block[own + 1..]).map(|line| line.to_string()));
+    }
+    out.join("\n")
+}
+
+/// Lines of each top-level block (starting at a `- ` bullet in the first column)
+fn top_level_blocks(content: &str) -> Vec<Vec<&str>> {
+    let mut blocks: Vec<Vec<&str>> = Vec::new();
+    for line in content.lines() {
+        if line.starts_with("- ") || line == "-" {
+            blocks.push(vec![line]);
+        } else if let Some(block) = blocks.last_mut() {
+            block.push(line);
+        }
+    }
+    blocks
+}
+
+/// Number of lines after the first that belong to the block itself, before any child block
+fn own_lines(block: &[&str]) -> usize {
+    block[1..].iter().take_while(|line| !line.trim_start().starts_with("- ")).count()
+}
+
+/// The highlight an annotation block records, with its block id (lowercase)
+fn annotation(block: &[&str], file: &str) -> Option<(String, Highlight)> {
+    let text = block[0].trim_start_matches('-').trim();
+    let props: HashMap<String, String> = block[1..=own_lines(block)]
+        .iter()
+        .filter_map(|line| BLOCK_PROP_RE.captures(line))
+        .map(|caps| (caps[1].to_lowercase(), caps[2].trim().to_string()))
+        .collect();
+    if props.get("ls-type")? != "annotation" {
+        return None;
+    }
+    let id = props.get("id")?.to_lowercase();
+    let page: Option<u32> = props.get("hl-page").and_then(|p| p.parse().ok());
+    let area = props.get("hl-type").is_some_and(|t| t == "area") || text.starts_with("[:span]");
+    let image = match (area, page, props.get("hl-stamp")) {
+        (true, Some(page), Some(stamp)) => Some(format!("{}/{}_{}_{}.png", file.trim_end_matches(".pdf"), page, id, stamp)),
+        _ => None,
+    };
+    let highlight = Highlight {
+        file: file.to_string(),
+        page,
+        text: if area { String::new() } else { text.to_string() },
+        image,
+    };
+    Some((id, highlight))
+}
+
+/// File name of a PDF for display, without Logseq's upload suffix
+/// (`../assets/report_1700000000000_0.pdf` → `report.pdf`)
+pub fn display_name(file: &str) -> String {
+    let name = file.rsplit('/').next().unwrap_or(file);
+    UPLOAD_SUFFIX_RE.replace(&resolver::decode(name), "$1").into_owned()
+}
+
+/// Link to a PDF, at a page when known (`report.pdf#page=3`)
+pub fn page_url(file: &str, page: Option<u32>) -> String {
+    match page {
+        Some(page) => format!("{}#page={}", file, page),
+        None => file.to_string(),
+    }
+}
+
+/// Embedded PDF viewer with an open/download link below it
+pub fn embed(src: &str, frame: &PdfFrame) -> String {
+    let name = escape::html(&display_name(src));
+    let src = escape::html(src);
+    format!(
+        r#"<iframe src="{}" width="{}" height="{}" style="border: 1px solid #333; border-radius: 4px;"></iframe><br><a class="pdf-link" href="{}" target="_blank" rel="noopener">📄 Open {}</a>"#,
+        src,
+        escape::html(&frame.width),
+        escape::html(&frame.height),
+        src,
+        name
+    )
+}
+
+/// A reference to a highlight: the quoted text with a link to its PDF page
+/// (`“text” [📄 report.pdf, p. 3](../assets/report.pdf#page=3)`)
+pub fn highlight_ref(highlight: &Highlight) -> String {
+    let page = highlight.page.map_or(String::new(), |p| format!(", p. {}", p));
+    let link = format!("[📄 {}{}]({})", display_name(&highlight.file), page, page_url(&highlight.file, highlight.page));
+    if highlight.text.is_empty() {
+        link
+    } else {
+        format!("“{}” {}", highlight.text, link)
+    }
+}
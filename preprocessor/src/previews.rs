@@ -0,0 +1,143 @@
+use anyhow::Result;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::icons::{Icon, IconMap};
+use crate::journals;
+use crate::outline;
+use crate::page::{self, Page, PageIndex};
+use crate::resolver;
+
+/// Preview file written to the output root, fetched by the theme's popover script
+pub const PREVIEWS_FILE: &str = "previews.json";
+
+/// Characters of plain text kept per preview
+pub const PREVIEW_LENGTH: usize = 280;
+
+lazy_static! {
+    // Lines without readable text
+    static ref PROPERTY_LINE_RE: Regex = Regex::new(r"^\s*-?\s*[\w-]+::").unwrap();
+
+    // Block prefixes: bullets, headings, task markers, priorities
+    static ref BULLET_RE: Regex = Regex::new(r"^\s*(?:[-*+]\s+)?(?:#{1,6}\s+)?").unwrap();
+    static ref MARKER_RE: Regex = Regex::new(r"^(?:TODO|DOING|DONE|LATER|NOW|WAITING|CANCELED|CANCELLED)\s+").unwrap();
+    static ref PRIORITY_RE: Regex = Regex::new(r"\[#[ABC]\]\s*").unwrap();
+
+    // Inline markup: dropped entirely
+    static ref MACRO_RE: Regex = Regex::new(r"\{\{[^}]*\}\}").unwrap();
+    static ref IMAGE_RE: Regex = Regex::new(r"!\[[^\]]*\]\([^)]*\)").unwrap();
+    static ref BLOCK_REF_RE: Regex = Regex::new(r"\(\([0-9a-f-]{36}\)\)").unwrap();
+    static ref EMPHASIS_RE: Regex = Regex::new(r"\*\*|__|~~|==|\^\^|`").unwrap();
+
+    // Inline markup: replaced by its label
+    static ref LABELED_WIKILINK_RE: Regex = Regex::new(r"\[\[[^\]|]*\|([^\]]*)\]\]").unwrap();
+    static ref WIKILINK_RE: Regex = Regex::new(r"#?\[\[([^\]]*)\]\]").unwrap();
+    static ref MD_LINK_RE: Regex = Regex::new(r"\[([^\]]*)\]\([^)]*\)").unwrap();
+}
+
+/// Hover preview for one page
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Preview {
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// Mapped icon asset (see `--icon-map`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon_src: Option<String>,
+    /// Mapped icon font class
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon_class: Option<String>,
+    /// Plain text of the page, at most `PREVIEW_LENGTH` characters
+    pub text: String,
+}
+
+/// Previews of all published pages and journals, keyed by Quartz slug
+pub fn build(index: &PageIndex, icons: &IconMap, include_private: bool) -> BTreeMap<String, Preview> {
+    index
+        .iter()
+        .filter(|p| include_private || !p.is_private())
+        .map(|p| (resolver::site_path(p), preview(p, icons)))
+        .collect()
+}
+
+/// Write `previews.json` (compact: it is fetched by every visitor). Returns the number of previews.
+pub fn write(output_dir: &Path, index: &PageIndex, icons: &IconMap, include_private: bool) -> Result<usize> {
+    let previews = build(index, icons, include_private);
+    fs::write(output_dir.join(PREVIEWS_FILE), serde_json::to_string(&previews)?)?;
+    Ok(previews.len())
+}
+
+fn preview(page: &Page, icons: &IconMap) -> Preview {
+    let title = match page.properties.get("title") {
+        Some(title) => title.clone(),
+        None => match page.name.strip_prefix("journals/") {
+            Some(file) => journals::parse_journal_date(file).map_or(file.to_string(), |(_, title)| title),
+            None => page.name.clone(),
+        },
+    };
+    let icon = page.properties.get("icon").cloned();
+    let mapped = icon.as_deref().and_then(|i| icons.get(i));
+    let (_, body) = page::parse_properties(&page.content);
+
+    Preview {
+        title,
+        icon_src: match mapped {
+            Some(Icon::Asset { src, .. }) => Some(src.clone()),
+            _ => None,
+        },
+        icon_class: match mapped {
+            Some(Icon::Class(class)) => Some(class.clone()),
+            _ => None,
+        },
+        icon,
+        text: truncate(&plain_text(&body), PREVIEW_LENGTH),
+    }
+}
+
+/// Readable text of page content: markup, properties and code blocks removed,
+/// links replaced by their labels, whitespace collapsed
+pub fn plain_text(content: &str) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    let mut in_code = false;
+
+    for line in content.lines() {
+        if outline::is_fence(line) {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code || PROPERTY_LINE_RE.is_match(line) {
+            continue;
+        }
+
+        let text = BULLET_RE.replace(line, "");
+        let text = MARKER_RE.replace(&text, "");
+        let text = PRIORITY_RE.replace_all(&text, "");
+        let text = MACRO_RE.replace_all(&text, "");
+        let text = IMAGE_RE.replace_all(&text, "");
+        let text = BLOCK_REF_RE.replace_all(&text, "");
+        let text = LABELED_WIKILINK_RE.replace_all(&text, "$1");
+        let text = WIKILINK_RE.replace_all(&text, "$1");
+        let text = MD_LINK_RE.replace_all(&text, "$1");
+        let text = EMPHASIS_RE.replace_all(&text, "");
+        parts.extend(text.split_whitespace().map(str::to_string));
+    }
+
+    parts.join(" ")
+}
+
+/// Cut text to at most `max` characters, at a word boundary when possible
+pub fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max - 1).collect();
+    let cut = match cut.rfind(' ') {
+        Some(pos) if pos > cut.len() / 2 => &cut[..pos],
+        _ => cut.as_str(),
+    };
+    format!("{}…", cut.trim_end())
+}
+
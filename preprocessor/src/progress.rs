@@ -0,0 +1,91 @@
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressFinish, ProgressStyle};
+use lazy_static::lazy_static;
+use std::time::{Duration, Instant};
+
+lazy_static! {
+    // All progress bars; log lines are printed above them (see init_logging)
+    static ref BARS: MultiProgress = MultiProgress::new();
+}
+
+/// The bars of the run. Nothing is drawn when stderr isn't a terminal.
+pub fn bars() -> &'static MultiProgress {
+    &BARS
+}
+
+/// Never draw progress bars (`--quiet`)
+pub fn hide() {
+    BARS.set_draw_target(ProgressDrawTarget::hidden());
+}
+
+/// Bar for a phase with a known number of items, removed when finished (or
+/// dropped, when the phase fails)
+pub fn bar(phase: &str, len: usize) -> ProgressBar {
+    let style = ProgressStyle::with_template("{prefix:>10} [{bar:30}] {pos}/{len} {elapsed}")
+        .unwrap()
+        .progress_chars("=> ");
+    BARS.add(
+        ProgressBar::new(len as u64)
+            .with_style(style)
+            .with_prefix(phase.to_string())
+            .with_finish(ProgressFinish::AndClear),
+    )
+}
+
+/// Spinner for a phase whose size isn't known up front (its message can
+/// show what was done so far)
+pub fn spinner(phase: &str) -> ProgressBar {
+    let style = ProgressStyle::with_template("{prefix:>10} {spinner} {elapsed} {msg}").unwrap();
+    let spinner = BARS.add(
+        ProgressBar::new_spinner()
+            .with_style(style)
+            .with_prefix(phase.to_string())
+            .with_finish(ProgressFinish::AndClear),
+    );
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    spinner
+}
+
+/// Time spent in a phase of the build
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PhaseTime {
+    pub phase: &'static str,
+    pub ms: u64,
+}
+
+/// Wall time per phase, one phase after the other
+#[derive(Debug, Default)]
+pub struct Timings {
+    phases: Vec<PhaseTime>,
+    current: Option<(&'static str, Instant)>,
+}
+
+impl Timings {
+    /// End the current phase and start `phase`
+    pub fn phase(&mut self, phase: &'static str) {
+        self.end();
+        self.current = Some((phase, Instant::now()));
+    }
+
+    /// End the current phase
+    pub fn end(&mut self) {
+        if let Some((phase, start)) = self.current.take() {
+            self.phases.push(PhaseTime { phase, ms: start.elapsed().as_millis() as u64 });
+        }
+    }
+
+    pub fn finish(mut self) -> Vec<PhaseTime> {
+        self.end();
+        self.phases
+    }
+}
+
+/// `--timings` lines: each phase's time and share of the total, slowest first
+pub fn report(phases: &[PhaseTime]) -> Vec<String> {
+    let total = phases.iter().map(|p| p.ms).sum::<u64>().max(1);
+    let mut sorted: Vec<&PhaseTime> = phases.iter().collect();
+    sorted.sort_by_key(|p| std::cmp::Reverse(p.ms));
+    sorted
+        .into_iter()
+        .map(|p| format!("{:<16} {:>8.2}s {:>5.1}%", p.phase, p.ms as f64 / 1000.0, p.ms as f64 * 100.0 / total as f64))
+        .collect()
+}
@@ -0,0 +1,243 @@
+use chrono::NaiveDate;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::content;
+use crate::page::PageIndex;
+use crate::query;
+
+/// Page properties that already end up somewhere else (frontmatter, badges,
+/// queries, Logseq internals) and are left out of the metadata block
+const CONSUMED_KEYS: &[&str] = &[
+    "title", "tags", "alias", "aliases", "icon", "description", "private", "public", "slug",
+    "created", "updated", "modified", "date", "toc", "filters", "collapsed", "id",
+    "exclude-from-graph-view", "template", "template-including-parent", "lang", "translation-of",
+];
+
+lazy_static! {
+    // A whole list item that is a page reference: [[page]], #tag, #[[multi word]]
+    static ref PAGE_REF_RE: Regex = Regex::new(r"^(?:#?\[\[([^\]]+)\]\]|#([^\s#\[\],]+))$").unwrap();
+
+    // Wikilinks inside text values (brackets dropped for plain text)
+    static ref WIKILINK_RE: Regex = Regex::new(r"#?\[\[([^\]]+)\]\]").unwrap();
+
+    static ref NUMBER_RE: Regex = Regex::new(r"^-?\d+(?:\.\d+)?$").unwrap();
+}
+
+/// `private:: true`: the page (or block) is left out unless `--include-private`
+pub fn is_private(properties: &HashMap<String, String>) -> bool {
+    properties.get("private").is_some_and(|v| v.eq_ignore_ascii_case("true"))
+}
+
+/// A property value, typed the way Logseq reads `key:: value`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    /// `[[page]]`, `#tag` or `#[[multi word]]`
+    PageRef(String),
+    Number(f64),
+    Bool(bool),
+    Date(NaiveDate),
+}
+
+impl Value {
+    /// Plain text: page refs and wikilinks without brackets, dates as `YYYY-MM-DD`
+    pub fn text(&self) -> String {
+        match self {
+            Value::Text(text) => WIKILINK_RE.replace_all(text, "$1").into_owned(),
+            Value::PageRef(name) => name.clone(),
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Date(d) => d.format("%Y-%m-%d").to_string(),
+        }
+    }
+
+    /// Does a query value (`(property key value)`) select this value?
+    /// Page refs, numbers, booleans and dates must match exactly (ignoring case);
+    /// text matches on a substring.
+    pub fn matches(&self, wanted: &Value) -> bool {
+        match (self, wanted) {
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Text(_), _) => text_key(&self.text()).contains(&text_key(&wanted.text())),
+            _ => text_key(&self.text()) == text_key(&wanted.text()),
+        }
+    }
+}
+
+fn text_key(text: &str) -> String {
+    text.trim().to_lowercase()
+}
+
+/// Parse a raw property value. A comma-separated list of page refs
+/// (`[[project]], [[active]]`, `#a, #b`) gives one value per ref; anything
+/// else is a single value: boolean, number, date or text.
+pub fn parse(raw: &str) -> Vec<Value> {
+    let items = split_items(raw);
+    let refs: Vec<Value> = items.iter().filter_map(|item| page_ref(item)).collect();
+    if !refs.is_empty() && refs.len() == items.len() {
+        return refs;
+    }
+    let raw = raw.trim();
+    if raw.is_empty() {
+        Vec::new()
+    } else {
+        vec![scalar(raw)]
+    }
+}
+
+/// Items of a value that is always a list (`tags::`, `alias::`, badge properties),
+/// as written but with page ref brackets removed (`[[a]], #b, c` → `a`, `b`, `c`)
+pub fn list_texts(raw: &str) -> Vec<String> {
+    split_items(raw)
+        .into_iter()
+        .map(|item| page_ref(item).map_or_else(|| item.to_string(), |v| v.text()))
+        .collect()
+}
+
+/// Plain text of a value: refs without brackets (`[[project]], [[active]]` →
+/// `project, active`), anything else as written minus wikilink brackets
+pub fn plain(raw: &str) -> String {
+    let values = parse(raw);
+    if !values.is_empty() && values.iter().all(|v| matches!(v, Value::PageRef(_))) {
+        values.iter().map(Value::text).collect::<Vec<_>>().join(", ")
+    } else {
+        WIKILINK_RE.replace_all(raw.trim(), "$1").into_owned()
+    }
+}
+
+/// Markdown for a value shown in a page body: a list of refs becomes wikilinks
+/// (`#a, #b` → `[[a]], [[b]]`), anything else is kept as written
+pub fn render(raw: &str) -> String {
+    let values = parse(raw);
+    if !values.is_empty() && values.iter().all(|v| matches!(v, Value::PageRef(_))) {
+        values
+            .iter()
+            .map(|v| format!("[[{}]]", v.text()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    } else {
+        raw.to_string()
+    }
+}
+
+/// How page (block 0) properties are shown in the page body (`--page-properties`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum PageProperties {
+    /// Not shown (frontmatter only)
+    #[default]
+    Hidden,
+    /// Two-column table at the top of the page
+    Table,
+    /// `**Key:** value` list at the top of the page
+    List,
+}
+
+/// Display label for a property key (`due-date` → `Due Date`)
+pub fn label(key: &str) -> String {
+    key.split('-')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Metadata block for the top of a page body: user page properties in the
+/// order `raw` (the page source) lists them, values transformed like page text.
+/// Empty when hidden or when there is nothing to show.
+pub fn metadata_block(
+    properties: &HashMap<String, String>,
+    raw: &str,
+    style: PageProperties,
+    index: &PageIndex,
+) -> String {
+    if style == PageProperties::Hidden {
+        return String::new();
+    }
+    let order: Vec<String> = raw
+        .lines()
+        .filter_map(|line| line.trim_start_matches('-').trim().split_once("::"))
+        .map(|(key, _)| key.trim().to_lowercase())
+        .collect();
+    let mut keys: Vec<&String> = properties
+        .keys()
+        .filter(|k| !CONSUMED_KEYS.contains(&k.as_str()) && !k.starts_with("query-"))
+        .collect();
+    keys.sort_by_key(|k| order.iter().position(|o| o == *k).unwrap_or(usize::MAX));
+    if keys.is_empty() {
+        return String::new();
+    }
+
+    let value = |key: &String| content::transform(&render(&properties[key]), index).replace('\n', " ");
+    match style {
+        PageProperties::Table => {
+            let mut table = String::from("| Property | Value |\n| --- | --- |\n");
+            for key in keys {
+                // `\|` keeps wikilink aliases and literal pipes inside the cell
+                table.push_str(&format!("| {} | {} |\n", label(key), value(key).replace('|', "\\|")));
+            }
+            table + "\n"
+        }
+        _ => {
+            let list: String = keys.iter().map(|k| format!("- **{}:** {}\n", label(k), value(k))).collect();
+            list + "\n"
+        }
+    }
+}
+
+/// Order two raw values for sorting: numbers numerically, dates
+/// chronologically, everything else by text (ignoring case)
+pub fn compare(a: &str, b: &str) -> Ordering {
+    match (parse(a).first(), parse(b).first()) {
+        (Some(Value::Number(x)), Some(Value::Number(y))) => x.partial_cmp(y).unwrap_or(Ordering::Equal),
+        (Some(Value::Date(x)), Some(Value::Date(y))) => x.cmp(y),
+        _ => text_key(&plain(a)).cmp(&text_key(&plain(b))),
+    }
+}
+
+/// Split on commas outside `[[...]]`, trimming items and dropping empty ones
+fn split_items(raw: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    let bytes = raw.as_bytes();
+    for (i, &b) in bytes.iter().enumerate() {
+        match b {
+            b'[' => depth += 1,
+            b']' => depth = depth.saturating_sub(1),
+            b',' if depth == 0 => {
+                items.push(&raw[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&raw[start..]);
+    items.into_iter().map(str::trim).filter(|i| !i.is_empty()).collect()
+}
+
+fn page_ref(item: &str) -> Option<Value> {
+    let caps = PAGE_REF_RE.captures(item)?;
+    let name = caps.get(1).or(caps.get(2))?.as_str().trim();
+    (!name.is_empty()).then(|| Value::PageRef(name.to_string()))
+}
+
+fn scalar(item: &str) -> Value {
+    if item.eq_ignore_ascii_case("true") {
+        Value::Bool(true)
+    } else if item.eq_ignore_ascii_case("false") {
+        Value::Bool(false)
+    } else if let Some(n) = NUMBER_RE.is_match(item).then(|| item.parse().ok()).flatten() {
+        Value::Number(n)
+    } else if let Some(date) = query::parse_date(item) {
+        Value::Date(date)
+    } else {
+        Value::Text(item.to_string())
+    }
+}
@@ -0,0 +1,74 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+use crate::warnings;
+
+/// Quartz's site configuration, in the Quartz folder (`--quartz-dir`)
+pub const CONFIG_FILE: &str = "quartz.config.ts";
+
+/// Copy of `quartz.config.ts` as it was before the last patch
+pub const BACKUP_FILE: &str = "quartz.config.ts.bak";
+
+/// Values written into `quartz.config.ts`; `None` leaves a setting as it is
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Settings {
+    pub page_title: Option<String>,
+    /// Host and path, without the scheme (`example.org/garden`)
+    pub base_url: Option<String>,
+    pub locale: Option<String>,
+}
+
+impl Settings {
+    /// `--base-url` as Quartz wants it: no scheme, no trailing slash
+    pub fn base_url(url: &str) -> String {
+        let url = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://")).unwrap_or(url);
+        url.trim_end_matches('/').to_string()
+    }
+}
+
+/// `quartz.config.ts` with the string values of `pageTitle`, `baseUrl` and
+/// `locale` replaced. Only the first occurrence of each is touched, so
+/// patching twice gives the same file. Returns the settings that weren't found.
+pub fn patch(source: &str, settings: &Settings) -> (String, Vec<&'static str>) {
+    let mut patched = source.to_string();
+    let mut missing = Vec::new();
+    let fields = [
+        ("pageTitle", &settings.page_title),
+        ("baseUrl", &settings.base_url),
+        ("locale", &settings.locale),
+    ];
+    for (key, value) in fields {
+        let Some(value) = value else { continue };
+        // key: "value" or key: 'value' (the string literal is group 2)
+        let re = Regex::new(&format!(r#"(\b{}\s*:\s*)("(?:[^"\\\n]|\\.)*"|'(?:[^'\\\n]|\\.)*')"#, key)).unwrap();
+        let Some(caps) = re.captures(&patched) else {
+            missing.push(key);
+            continue;
+        };
+        let literal = caps.get(2).unwrap();
+        // A JSON string is a valid TypeScript string literal
+        let quoted = serde_json::to_string(value).unwrap_or_default();
+        patched = format!("{}{}{}", &patched[..literal.start()], quoted, &patched[literal.end()..]);
+    }
+    (patched, missing)
+}
+
+/// Patch `quartz.config.ts` in a Quartz folder. The file is only written when
+/// something changes, after copying the current version to `quartz.config.ts.bak`.
+/// Returns whether the file changed.
+pub fn patch_config(quartz_dir: &Path, settings: &Settings) -> Result<bool> {
+    let path = quartz_dir.join(CONFIG_FILE);
+    let source = fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+    let (patched, missing) = patch(&source, settings);
+    for key in missing {
+        warnings::global().warn("quartz-config", &format!("{} not found in {}; left as it is", key, path.display()), None);
+    }
+    if patched == source {
+        return Ok(false);
+    }
+    fs::copy(&path, quartz_dir.join(BACKUP_FILE))?;
+    fs::write(&path, patched)?;
+    Ok(true)
+}
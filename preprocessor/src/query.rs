@@ -1,320 +1,364 @@
 use lazy_static::lazy_static;
 use regex::Regex;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
+use crate::content;
+use crate::escape;
+use crate::outline::Block;
 use crate::page::{Page, PageIndex};
+use crate::properties;
+use crate::query_parser::{self, Expr};
+use crate::references;
+use crate::resolver;
+use crate::rng::Rng;
+use crate::schedule;
+use crate::social;
+use crate::timezone;
 
 lazy_static! {
-    // Query patterns - allow optional whitespace before closing paren
-    static ref PAGE_TAGS_RE: Regex = Regex::new(r"(?i)^\(page-tags\s+\[\[([^\]]+)\]\]\s*\)$").unwrap();
-    static ref PAGE_RE: Regex = Regex::new(r"(?i)^\(page\s+\[\[([^\]]+)\]\]\s*\)$").unwrap();
-    static ref NAMESPACE_RE: Regex = Regex::new(r"(?i)^\(namespace\s+\[\[([^\]]+)\]\]\s*\)$").unwrap();
-    static ref PROPERTY_RE: Regex = Regex::new(r#"(?i)^\((?:page-)?property\s+:?(\w+[-\w]*)(?:\s+(?:"([^"]+)"|(\S+)))?\s*\)$"#).unwrap();
-    // Matches (task STATE) or (task STATE1 STATE2 ...)
-    static ref TASK_RE: Regex = Regex::new(r"(?i)^\(task\s+((?:TODO|DONE|NOW|DOING|LATER|WAITING|CANCELLED)(?:\s+(?:TODO|DONE|NOW|DOING|LATER|WAITING|CANCELLED))*)\s*\)$").unwrap();
-    static ref PAGE_REF_RE: Regex = Regex::new(r"^\[\[([^\]]+)\]\]$").unwrap();
-    static ref TEXT_SEARCH_RE: Regex = Regex::new(r#"^"([^"]+)"$"#).unwrap();
-
-    // New query patterns - allow optional whitespace before closing paren
-    static ref PRIORITY_RE: Regex = Regex::new(r"(?i)^\(priority\s+([abc])\s*\)$").unwrap();
-    static ref BETWEEN_RE: Regex = Regex::new(r"(?i)^\(between\s+\[\[([^\]]+)\]\]\s+\[\[([^\]]+)\]\]\s*\)$").unwrap();
-    static ref SORT_BY_RE: Regex = Regex::new(r"(?i)^\(sort-by\s+:?(\w+[-\w]*)\s*(asc|desc)?\s*\)$").unwrap();
-    static ref ALL_PAGE_TAGS_RE: Regex = Regex::new(r"(?i)^\(all-page-tags\s*\)$").unwrap();
+    // (between -7d today) bounds: days, weeks, months or years from today
+    static ref RELATIVE_DATE_RE: Regex = Regex::new(r"(?i)^([+-]?)(\d+)([dwmy])$").unwrap();
+    // Table cells: ISO dates and timestamps (2025-01-15, 2025-01-15T10:00:00Z), numbers
+    static ref ISO_DATE_RE: Regex = Regex::new(r"^\d{4}-\d{2}-\d{2}(?:T\S+)?$").unwrap();
+    static ref NUMBER_CELL_RE: Regex = Regex::new(r"^-?\d+(?:\.\d+)?$").unwrap();
 
     // Query options (inline properties)
     static ref QUERY_PROPS_RE: Regex = Regex::new(r"query-properties::\s*\[:?([^\]]+)\]").unwrap();
     static ref QUERY_SORT_BY_RE: Regex = Regex::new(r"query-sort-by::\s*:?(\S+)").unwrap();
     static ref QUERY_SORT_DESC_RE: Regex = Regex::new(r"query-sort-desc::\s*(true|false)").unwrap();
     static ref QUERY_TABLE_RE: Regex = Regex::new(r"query-table::\s*(true|false)").unwrap();
-}
+    static ref QUERY_OUTPUT_RE: Regex = Regex::new(r"query-output::\s*(\w+)").unwrap();
+    static ref QUERY_VIEW_RE: Regex = Regex::new(r"query-view::\s*(\w+)").unwrap();
 
-/// Execute a Logseq query and return matching pages
-pub fn execute<'a>(query_str: &str, index: &'a PageIndex) -> Vec<&'a Page> {
-    // Extract the query expression from {{query ...}}
-    let expr = query_str
-        .trim()
-        .trim_start_matches("{{query")
-        .trim_end_matches("}}")
-        .trim();
-
-    execute_expr(expr, index)
-}
-
-fn execute_expr<'a>(expr: &str, index: &'a PageIndex) -> Vec<&'a Page> {
-    // Normalize whitespace: trim and collapse multiple spaces
-    let expr = expr.trim();
-
-    // Helper to extract inner content from (keyword ...) with flexible whitespace
-    fn extract_inner<'b>(expr: &'b str, keyword: &str) -> Option<&'b str> {
-        let lower = expr.to_lowercase();
-        if lower.starts_with(&format!("({}", keyword)) && expr.ends_with(')') {
-            // Find where the keyword ends and content begins
-            let after_paren = &expr[1..]; // skip '('
-            let after_keyword = after_paren.strip_prefix(keyword)
-                .or_else(|| after_paren.strip_prefix(&keyword.to_uppercase()))?;
-            let inner = after_keyword.trim_start(); // skip whitespace after keyword
-            // Remove trailing ')'
-            if inner.ends_with(')') {
-                return Some(&inner[..inner.len() - 1]);
-            }
-        }
-        None
-    }
+    // A `{{query ...}}` in page text
+    static ref QUERY_RE: Regex = Regex::new(r"\{\{query[^}]*\}\}").unwrap();
+}
 
-    // Handle (and ...)
-    if let Some(inner) = extract_inner(expr, "and") {
-        return execute_and(inner, index);
-    }
+/// Results of the queries run against an index, by query expression, so a
+/// query used on several pages (`(task NOW)` on every project page) scans the
+/// index once. Entries hold for the index fingerprint they were computed
+/// against; a query on an index with another fingerprint starts over.
+#[derive(Debug, Default)]
+pub struct QueryCache {
+    fingerprint: u64,
+    /// Positions in the index of each query's results, in result order
+    results: HashMap<String, Vec<usize>>,
+    counts: QueryCounts,
+}
 
-    // Handle (or ...)
-    if let Some(inner) = extract_inner(expr, "or") {
-        return execute_or(inner, index);
-    }
+/// How many queries a build evaluated, how many it took from the cache, and
+/// how many it could not read
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct QueryCounts {
+    pub run: usize,
+    pub reused: usize,
+    pub unsupported: usize,
+}
 
-    // Handle (not ...)
-    if let Some(inner) = extract_inner(expr, "not") {
-        let excluded = execute_expr(inner, index);
-        let excluded_names: std::collections::HashSet<_> =
-            excluded.iter().map(|p| &p.name).collect();
-        return index.iter().filter(|p| !excluded_names.contains(&p.name)).collect();
-    }
+/// Why a query expression can't be run
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum QueryError {
+    #[error("empty query")]
+    Empty,
+    #[error("unbalanced parentheses, brackets or quotes")]
+    Unbalanced,
+    #[error("nested too deeply")]
+    TooDeep,
+    #[error("unsupported clause `{0}`")]
+    Unsupported(String),
+    #[error("unreadable date in `{0}`")]
+    Date(String),
+}
 
-    // Handle (task STATE) or (task STATE1 STATE2 ...)
-    if let Some(caps) = TASK_RE.captures(expr) {
-        let states_str = caps.get(1).unwrap().as_str().to_uppercase();
-        let states: Vec<&str> = states_str.split_whitespace().collect();
-        return index
-            .iter()
-            .filter(|p| {
-                states.iter().any(|state| {
-                    p.content.contains(&format!("- {} ", state))
-                        || p.content.contains(&format!("\n{} ", state))
-                })
-            })
-            .collect();
+impl QueryCache {
+    pub fn counts(&self) -> QueryCounts {
+        self.counts
     }
+}
 
-    // Handle (priority a/b/c)
-    if let Some(caps) = PRIORITY_RE.captures(expr) {
-        let priority = caps.get(1).unwrap().as_str().to_uppercase();
-        let pattern = format!("[#{}]", priority);
-        return index
-            .iter()
-            .filter(|p| p.content.contains(&pattern))
-            .collect();
-    }
+/// Execute a Logseq query and return matching pages (from the index's query
+/// cache when the same query ran before), or why the query can't be read
+pub fn execute<'a>(query_str: &str, index: &'a PageIndex) -> Result<Vec<&'a Page>, QueryError> {
+    let expr = query_expr(query_str);
 
-    // Handle (between [[date1]] [[date2]]) - for journal pages
-    if let Some(caps) = BETWEEN_RE.captures(expr) {
-        let start_date = caps.get(1).unwrap().as_str();
-        let end_date = caps.get(2).unwrap().as_str();
-        if let (Some(start), Some(end)) = (parse_date(start_date), parse_date(end_date)) {
-            return index
-                .iter()
-                .filter(|p| {
-                    // Check if page name looks like a date (strip journals/ prefix if present)
-                    let name = p.name.strip_prefix("journals/").unwrap_or(&p.name);
-                    if let Some(page_date) = parse_date(name) {
-                        page_date >= start && page_date <= end
-                    } else {
-                        false
-                    }
-                })
-                .collect();
+    {
+        let mut cache = index.query_cache().lock().unwrap_or_else(|e| e.into_inner());
+        if cache.fingerprint != index.fingerprint() {
+            cache.results.clear();
+            cache.fingerprint = index.fingerprint();
         }
-    }
-
-    // Handle (all-page-tags) - returns all unique tags as virtual results
-    if ALL_PAGE_TAGS_RE.is_match(expr) {
-        // This is a special case - we return pages that have the tag names
-        // For now, collect all unique tags and return pages tagged with them
-        let mut all_tags: std::collections::HashSet<String> = std::collections::HashSet::new();
-        for page in index.iter() {
-            for tag in &page.tags {
-                all_tags.insert(tag.clone());
-            }
+        if let Some(positions) = cache.results.get(expr) {
+            let results = positions.iter().map(|&i| &index[i]).collect();
+            cache.counts.reused += 1;
+            return Ok(results);
         }
-        // Return pages that match tag names (if they exist as pages)
-        return index
-            .iter()
-            .filter(|p| all_tags.contains(&p.name_lower))
-            .collect();
-    }
-
-    // Handle (page [[name]])
-    if let Some(caps) = PAGE_RE.captures(expr) {
-        let page_name = caps.get(1).unwrap().as_str().to_lowercase();
-        // Strip pages/ prefix if present
-        let page_name = page_name.strip_prefix("pages/").unwrap_or(&page_name);
-        return index.iter().filter(|p| p.name_lower == page_name).collect();
     }
 
-    // Handle (page-tags [[tag]])
-    if let Some(caps) = PAGE_TAGS_RE.captures(expr) {
-        let tag = caps.get(1).unwrap().as_str().to_lowercase();
-        // Strip pages/ prefix if present
-        let tag = tag.strip_prefix("pages/").unwrap_or(&tag);
-        return index.iter().filter(|p| p.tags.contains(&tag.to_string())).collect();
-    }
-
-    // Handle (namespace [[x]])
-    if let Some(caps) = NAMESPACE_RE.captures(expr) {
-        let ns = caps.get(1).unwrap().as_str().to_lowercase();
-        let ns = ns.strip_prefix("pages/").unwrap_or(&ns);
-        return index
-            .iter()
-            .filter(|p| p.namespace.as_ref().map_or(false, |n| n.to_lowercase() == ns))
-            .collect();
-    }
-
-    // Handle (property :key value)
-    if let Some(caps) = PROPERTY_RE.captures(expr) {
-        let key = caps.get(1).unwrap().as_str().to_lowercase().replace('-', "");
-        let value = caps
-            .get(2)
-            .or_else(|| caps.get(3))
-            .map(|m| m.as_str().to_lowercase().trim_matches('"').to_string())
-            .unwrap_or_default();
-
-        return index
-            .iter()
-            .filter(|p| {
-                let prop_val = p.properties.get(&key).map(|v| v.to_lowercase()).unwrap_or_default();
-                if value.is_empty() {
-                    !prop_val.is_empty()
-                } else {
-                    prop_val == value || prop_val.contains(&value)
-                }
-            })
-            .collect();
-    }
-
-    // Handle [[page]] reference
-    if let Some(caps) = PAGE_REF_RE.captures(expr) {
-        let page_name = caps.get(1).unwrap().as_str().to_lowercase();
-        let page_name = page_name.strip_prefix("pages/").unwrap_or(&page_name);
-        return index
-            .iter()
-            .filter(|p| {
-                p.name_lower == page_name
-                    || p.content.to_lowercase().contains(&format!("[[{}]]", page_name))
-            })
-            .collect();
-    }
-
-    // Handle "text" search
-    if let Some(caps) = TEXT_SEARCH_RE.captures(expr) {
-        let search = caps.get(1).unwrap().as_str().to_lowercase();
-        return index
-            .iter()
-            .filter(|p| p.content.to_lowercase().contains(&search))
-            .collect();
-    }
-
-    // Plain text search
-    if !expr.starts_with('(') && !expr.starts_with('[') {
-        let search = expr.to_lowercase().replace(['"', '\''], "");
-        if search.len() > 2 {
-            return index
-                .iter()
-                .filter(|p| p.content.to_lowercase().contains(&search))
-                .collect();
+    // Evaluated outside the lock, so pages are transformed in parallel
+    let positions = evaluate(expr, index);
+    let mut cache = index.query_cache().lock().unwrap_or_else(|e| e.into_inner());
+    let positions = match positions {
+        Ok(positions) => positions,
+        Err(error) => {
+            cache.counts.unsupported += 1;
+            return Err(error);
         }
+    };
+    let results = positions.iter().map(|&i| &index[i]).collect();
+    if cache.fingerprint == index.fingerprint() {
+        cache.results.insert(expr.to_string(), positions);
     }
-
-    Vec::new()
+    cache.counts.run += 1;
+    Ok(results)
 }
 
-fn execute_and<'a>(inner: &str, index: &'a PageIndex) -> Vec<&'a Page> {
-    let parts = parse_query_parts(inner);
-    if parts.is_empty() {
-        return Vec::new();
+/// Run a query expression (without `{{query ...}}`): positions in the index
+/// of the matching pages, in result order
+fn evaluate(expr: &str, index: &PageIndex) -> Result<Vec<usize>, QueryError> {
+    let query = query_parser::parse(expr)?;
+    let mut results: Vec<usize> = eval(&query.filter, index)?.into_iter().filter_map(|p| index.position(p)).collect();
+    // (sample n) and (shuffle) modify the results of the rest of the query,
+    // seeded per query so a build with the same --seed draws the same results
+    if query.sample.is_some() || query.shuffle {
+        Rng::salted(index.seed(), expr).shuffle(&mut results);
     }
-
-    let mut result: Vec<&Page> = execute_expr(&parts[0], index);
-    for part in parts.iter().skip(1) {
-        let matching = execute_expr(part, index);
-        let matching_names: std::collections::HashSet<_> =
-            matching.iter().map(|p| &p.name).collect();
-        result.retain(|p| matching_names.contains(&p.name));
+    if let Some(n) = query.sample {
+        results.truncate(n);
     }
-    result
+    Ok(results)
 }
 
-fn execute_or<'a>(inner: &str, index: &'a PageIndex) -> Vec<&'a Page> {
-    let parts = parse_query_parts(inner);
-    let mut seen = std::collections::HashSet::new();
-    let mut result = Vec::new();
-
-    for part in parts {
-        for page in execute_expr(&part, index) {
-            if seen.insert(&page.name) {
-                result.push(page);
-            }
-        }
-    }
-    result
+/// The query expression of `{{query ...}}`
+fn query_expr(query_str: &str) -> &str {
+    query_str.trim().trim_start_matches("{{query").trim_end_matches("}}").trim()
 }
 
-/// Parse query parts handling nested parentheses
-fn parse_query_parts(expr: &str) -> Vec<String> {
-    let mut parts = Vec::new();
-    let mut current = String::new();
-    let mut depth = 0;
-    let mut in_brackets = 0;
+/// Whether a query lists its results in random order (`(shuffle)`)
+pub fn is_shuffled(query_str: &str) -> bool {
+    query_parser::parse(query_expr(query_str)).is_ok_and(|query| query.shuffle)
+}
 
-    for c in expr.chars() {
-        match c {
-            '(' => {
-                depth += 1;
-                current.push(c);
+/// Pages matching a query clause
+fn eval<'a>(expr: &Expr, index: &'a PageIndex) -> Result<Vec<&'a Page>, QueryError> {
+    Ok(match expr {
+        Expr::All => index.iter().collect(),
+        // Pages matching every clause, in the order of the first
+        Expr::And(clauses) => {
+            let Some((first, rest)) = clauses.split_first() else {
+                return Ok(Vec::new());
+            };
+            let mut result = eval(first, index)?;
+            for clause in rest {
+                let matching = eval(clause, index)?;
+                let matching_names: HashSet<_> = matching.iter().map(|p| &p.name).collect();
+                result.retain(|p| matching_names.contains(&p.name));
             }
-            ')' => {
-                depth -= 1;
-                current.push(c);
-                if depth == 0 {
-                    let trimmed = current.trim().to_string();
-                    if !trimmed.is_empty() {
-                        parts.push(trimmed);
+            result
+        }
+        Expr::Or(clauses) => {
+            let mut seen = HashSet::new();
+            let mut result = Vec::new();
+            for clause in clauses {
+                for page in eval(clause, index)? {
+                    if seen.insert(&page.name) {
+                        result.push(page);
                     }
-                    current.clear();
                 }
             }
-            '[' => {
-                in_brackets += 1;
-                current.push(c);
+            result
+        }
+        Expr::Not(clauses) => {
+            let mut excluded_names = HashSet::new();
+            for clause in clauses {
+                excluded_names.extend(eval(clause, index)?.into_iter().map(|p| &p.name));
             }
-            ']' => {
-                in_brackets -= 1;
-                current.push(c);
-                if depth == 0 && in_brackets == 0 {
-                    let trimmed = current.trim().to_string();
-                    if !trimmed.is_empty() && (trimmed.starts_with('(') || trimmed.starts_with('[')) {
-                        parts.push(trimmed);
+            index.iter().filter(|p| !excluded_names.contains(&p.name)).collect()
+        }
+        Expr::Task(states) => {
+            // Equivalent states of the graph's workflow match too (TODO ↔ LATER, DOING ↔ NOW)
+            let states: Vec<String> = states.iter().flat_map(|state| index.workflow().query_states(state)).collect();
+            index
+                .iter()
+                .filter(|p| p.outline().markers().any(|marker| states.iter().any(|state| state == marker)))
+                .collect()
+        }
+        Expr::Priority(priority) => {
+            let priority = priority.chars().next();
+            index.iter().filter(|p| p.outline().iter().any(|block| block.priority == priority)).collect()
+        }
+        // Journal pages, and pages with blocks scheduled in the range
+        Expr::Between(start, end) => {
+            let today = timezone::current().today();
+            let (Some(start_day), Some(end_day)) = (between_date(start, today), between_date(end, today)) else {
+                return Err(QueryError::Date(format!("(between {} {})", start, end)));
+            };
+            index
+                .iter()
+                .filter(|p| {
+                    // Check if page name looks like a date (strip journals/ prefix if present)
+                    let name = p.name.strip_prefix("journals/").unwrap_or(&p.name);
+                    let journal_day = parse_date(name).is_some_and(|d| d >= start_day && d <= end_day);
+                    // Pages with a SCHEDULED/DEADLINE date in the range match too
+                    journal_day || schedule::has_date_between(p, start_day, end_day)
+                })
+                .collect()
+        }
+        // (all-page-tags) lists tags, not pages (see `tag_listing`)
+        Expr::AllPageTags => Vec::new(),
+        Expr::Page(name) => {
+            let page_name = name.to_lowercase();
+            let page_name = page_name.strip_prefix("pages/").unwrap_or(&page_name);
+            let key = resolver::normalize_key(page_name);
+            index.named(&key).filter(|p| p.name_lower == page_name).collect()
+        }
+        // Pages with any of the tags
+        Expr::PageTags(tags) => {
+            let mut results: Vec<&Page> = Vec::new();
+            for tag in tags {
+                let tag = tag.to_lowercase();
+                let tag = tag.strip_prefix("pages/").unwrap_or(&tag);
+                for name in tag_names(tag, index) {
+                    let tags: Vec<&str> = match index.tag_match() {
+                        TagMatch::IgnoreCase => vec![name.as_str()],
+                        TagMatch::Plural => index.tag_names().filter(|t| singular(t) == singular(&name)).collect(),
+                    };
+                    for page in tags.into_iter().flat_map(|t| index.tagged(t)) {
+                        if !results.iter().any(|p| std::ptr::eq(*p, page)) {
+                            results.push(page);
+                        }
                     }
-                    current.clear();
                 }
             }
-            ' ' | '\n' | '\t' if depth == 0 && in_brackets == 0 => {
-                let trimmed = current.trim().to_string();
-                if !trimmed.is_empty() && (trimmed.starts_with('(') || trimmed.starts_with('[')) {
-                    parts.push(trimmed);
-                }
-                current.clear();
+            results
+        }
+        Expr::Namespace(name) => {
+            let ns = name.to_lowercase();
+            let ns = ns.strip_prefix("pages/").unwrap_or(&ns);
+            index.in_namespace(ns).collect()
+        }
+        // Page properties, and for (property) block properties too, the page's own
+        // property block included, as in Logseq. Results are the pages the
+        // matching blocks are on; `render_blocks` lists the blocks themselves.
+        Expr::Property { page_only, key, value } => {
+            let plain_key = key.replace('-', "");
+            index
+                .iter()
+                .filter(|p| {
+                    let page_value = p.properties.iter().find(|(k, _)| k.replace('-', "") == plain_key);
+                    property_matches(page_value.map(|(_, v)| v.as_str()), value.as_deref())
+                        || (!page_only && !matching_blocks(p, key, value.as_deref()).is_empty())
+                })
+                .collect()
+        }
+        // Pages linking to the page (also through its aliases, embeds and tags)
+        Expr::Ref(name) => {
+            let name = name.strip_prefix("pages/").unwrap_or(name);
+            references::linking_to(name, index)
+        }
+        Expr::Text(text) => {
+            let search = text.to_lowercase();
+            index.iter().filter(|p| p.content.to_lowercase().contains(&search)).collect()
+        }
+    })
+}
+
+/// How `(page-tags ...)` compares tags (`--tag-match`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum TagMatch {
+    /// The same tag, ignoring case (`Book` = `book`)
+    #[default]
+    IgnoreCase,
+    /// Also the singular or plural form (`book` = `books`, `story` = `stories`)
+    Plural,
+}
+
+/// Lowercase tags a `(page-tags)` argument stands for: the tag itself and,
+/// when it names a page or one of its aliases, that page's name and aliases
+fn tag_names(tag: &str, index: &PageIndex) -> Vec<String> {
+    let mut names = vec![tag.to_string()];
+    let key = resolver::normalize_key(tag);
+    for page in index.named(&key).chain(index.aliased(&key)) {
+        for name in std::iter::once(&page.name).chain(&page.aliases) {
+            let name = name.to_lowercase();
+            if !names.contains(&name) {
+                names.push(name);
             }
-            _ => current.push(c),
         }
     }
+    names
+}
 
-    let trimmed = current.trim().to_string();
-    if !trimmed.is_empty() && (trimmed.starts_with('(') || trimmed.starts_with('[')) {
-        parts.push(trimmed);
+/// Singular of an English plural, by suffix (`stories` → `story`,
+/// `boxes` → `box`, `books` → `book`); other words as they are
+pub fn singular(word: &str) -> String {
+    if word.len() > 4 && word.ends_with("ies") {
+        format!("{}y", &word[..word.len() - 3])
+    } else if ["sses", "shes", "ches", "xes", "zes"].iter().any(|suffix| word.ends_with(suffix)) {
+        word[..word.len() - 2].to_string()
+    } else if word.len() > 3 && word.ends_with('s') && !["ss", "us", "is"].iter().any(|suffix| word.ends_with(suffix)) {
+        word[..word.len() - 1].to_string()
+    } else {
+        word.to_string()
     }
+}
 
-    parts
+/// Whether a property value matches a `(property)` value, compared by type:
+/// [[project]] matches `type:: [[project]], [[active]]`, 5 matches 5.0.
+/// Without a wanted value, any value matches.
+fn property_matches(value: Option<&str>, wanted: Option<&str>) -> bool {
+    let values = value.map(properties::parse).unwrap_or_default();
+    match wanted.map(properties::parse).unwrap_or_default().first() {
+        None => !values.is_empty(),
+        Some(wanted) => values.iter().any(|v| v.matches(wanted)),
+    }
+}
+
+/// Blocks of a page other than its page property block whose property `key`
+/// (hyphens ignored) matches `wanted`
+fn matching_blocks<'a>(page: &'a Page, key: &str, wanted: Option<&str>) -> Vec<&'a Block> {
+    let key = key.replace('-', "");
+    let outline = page.outline();
+    let page_block = outline.page_block();
+    outline
+        .iter()
+        .filter(|block| !page_block.is_some_and(|own| std::ptr::eq(own, *block)))
+        .filter(|block| {
+            block.properties.iter().any(|(k, v)| k.replace('-', "") == key && property_matches(Some(v), wanted))
+        })
+        .collect()
+}
+
+/// A `(between ...)` bound: a date (`[[Jan 1st, 2025]]`), `today`, `yesterday`,
+/// `tomorrow`, or days, weeks, months or years from today (`-7d`, `+2w`, `-1m`)
+fn between_date(bound: &str, today: chrono::NaiveDate) -> Option<chrono::NaiveDate> {
+    use chrono::{Days, Months};
+
+    let bound = bound.trim_start_matches("[[").trim_end_matches("]]").trim();
+    match bound.to_lowercase().as_str() {
+        "today" | "now" => return Some(today),
+        "yesterday" => return today.checked_sub_days(Days::new(1)),
+        "tomorrow" => return today.checked_add_days(Days::new(1)),
+        _ => {}
+    }
+    let Some(caps) = RELATIVE_DATE_RE.captures(bound) else {
+        return parse_date(bound);
+    };
+    let n: u32 = caps[2].parse().ok()?;
+    let back = &caps[1] == "-";
+    match caps[3].to_lowercase().as_str() {
+        "d" | "w" => {
+            let days = Days::new(u64::from(n) * if caps[3].eq_ignore_ascii_case("w") { 7 } else { 1 });
+            if back { today.checked_sub_days(days) } else { today.checked_add_days(days) }
+        }
+        unit => {
+            let months = Months::new(n.checked_mul(if unit == "y" { 12 } else { 1 })?);
+            if back { today.checked_sub_months(months) } else { today.checked_add_months(months) }
+        }
+    }
 }
 
 /// Parse date strings in various formats (journal page names, natural language dates)
-fn parse_date(date_str: &str) -> Option<chrono::NaiveDate> {
+pub fn parse_date(date_str: &str) -> Option<chrono::NaiveDate> {
     use chrono::NaiveDate;
 
     // Try common formats
@@ -366,6 +410,49 @@ pub struct QueryOptions {
     pub sort_desc: bool,
     /// None = default (table), Some(true) = force table, Some(false) = force list
     pub table: Option<bool>,
+    /// Where results go: rendered markdown (default) or the page frontmatter
+    pub output: QueryOutput,
+    /// Keep the results in query order instead of sorting them (`(shuffle)`)
+    pub shuffled: bool,
+    /// How results are presented (`query-view::`; overrides `query-table::`)
+    pub view: Option<QueryView>,
+}
+
+/// Presentation of query results (query-view:: table|list|cards|embed)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryView {
+    Table,
+    List,
+    /// A grid of cards: icon, title and description of each page
+    Cards,
+    /// Each page transcluded
+    Embed,
+}
+
+impl QueryView {
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "table" => Some(QueryView::Table),
+            "list" => Some(QueryView::List),
+            "cards" => Some(QueryView::Cards),
+            "embed" => Some(QueryView::Embed),
+            _ => None,
+        }
+    }
+
+    /// Whether results render as blocks of their own rather than list items
+    pub fn is_block(self) -> bool {
+        matches!(self, QueryView::Cards | QueryView::Embed)
+    }
+}
+
+/// Destination of query results (query-output:: markdown|frontmatter)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum QueryOutput {
+    #[default]
+    Markdown,
+    /// Write result page names into a frontmatter `listing:` array
+    Frontmatter,
 }
 
 /// Parse query options from surrounding context (the block containing the query)
@@ -397,11 +484,22 @@ pub fn parse_query_options(context: &str) -> QueryOptions {
         opts.table = Some(caps.get(1).unwrap().as_str() == "true");
     }
 
+    // Parse query-output:: frontmatter (results go to frontmatter listing)
+    if let Some(caps) = QUERY_OUTPUT_RE.captures(context) {
+        if caps.get(1).unwrap().as_str().eq_ignore_ascii_case("frontmatter") {
+            opts.output = QueryOutput::Frontmatter;
+        }
+    }
+
+    // Parse query-view:: table|list|cards|embed
+    if let Some(caps) = QUERY_VIEW_RE.captures(context) {
+        opts.view = QueryView::parse(&caps[1]);
+    }
+
     opts
 }
 
 /// Convert query results to markdown (with optional table view)
-#[allow(dead_code)]
 pub fn results_to_markdown(results: &[&Page], query_str: &str) -> String {
     results_to_markdown_with_options(results, query_str, &QueryOptions::default())
 }
@@ -413,30 +511,34 @@ pub fn results_to_markdown_with_options(
     options: &QueryOptions,
 ) -> String {
     if results.is_empty() {
-        return format!(
-            "> [!info] Query Results\n> No pages match this query.\n> `{}`",
-            if query_str.len() > 80 {
-                format!("{}...", &query_str[..80])
-            } else {
-                query_str.to_string()
+        return format!("> [!info] Query Results\n> No pages match this query.\n> `{}`", shorten(query_str));
+    }
+
+    let sorted = sort_results(results, options);
+
+    // (property) matching blocks lists them rather than their pages (pages
+    // matching by page properties alone keep the table)
+    if let Ok(query) = query_parser::parse(query_expr(query_str)) {
+        if let Expr::Property { page_only: false, key, value } = &query.filter {
+            if let Some(blocks) = render_blocks(&sorted, key, value.as_deref()) {
+                return blocks;
             }
-        );
+        }
     }
 
-    // Sort results
-    let mut sorted: Vec<_> = results.iter().copied().collect();
-    if let Some(ref sort_key) = options.sort_by {
-        sorted.sort_by(|a, b| {
-            let a_val = get_page_property(a, sort_key);
-            let b_val = get_page_property(b, sort_key);
-            if options.sort_desc {
-                b_val.cmp(&a_val)
+    match options.view {
+        Some(QueryView::List) => return render_list(&sorted),
+        Some(QueryView::Cards) => return render_cards(&sorted),
+        Some(QueryView::Embed) => return render_embeds(&sorted),
+        Some(QueryView::Table) => {
+            let properties = if options.properties.is_empty() {
+                detect_common_properties(&sorted)
             } else {
-                a_val.cmp(&b_val)
-            }
-        });
-    } else {
-        sorted.sort_by(|a, b| a.name.cmp(&b.name));
+                options.properties.clone()
+            };
+            return render_table(&sorted, &properties);
+        }
+        None => {}
     }
 
     // If properties are specified, render as table with those properties
@@ -454,31 +556,194 @@ pub fn results_to_markdown_with_options(
     render_table(&sorted, &auto_props)
 }
 
+/// Note under query results that they are as of the build (`--query-dates`)
+pub fn freshness_note(date: &str) -> String {
+    format!("*Results generated {}*", date)
+}
+
+/// Names of the pages with a `{{query}}`, whose published results go stale as
+/// the graph changes (private pages only with `include_private`)
+pub fn pages_with_queries(index: &PageIndex, include_private: bool) -> Vec<String> {
+    let mut names: Vec<String> = index
+        .iter()
+        .filter(|p| p.content.contains("{{query") && (include_private || !p.is_private()))
+        .map(|p| p.name.clone())
+        .collect();
+    names.sort();
+    names
+}
+
+/// Pages the queries of each page list, by page name (private pages only
+/// with `include_private`): what a page's published results depend on. Editing
+/// a page changes other pages only where it enters or leaves these lists, so
+/// comparing them between builds (`changed_results`) finds the pages to rebuild.
+pub fn dependencies(index: &PageIndex, include_private: bool) -> BTreeMap<String, Vec<String>> {
+    let mut dependencies = BTreeMap::new();
+    for page in index.iter().filter(|p| include_private || !p.is_private()) {
+        let queries: Vec<&str> = QUERY_RE.find_iter(&page.content).map(|query| query.as_str()).collect();
+        if queries.is_empty() {
+            continue;
+        }
+        // Results come from the query cache: the pages ran these queries already
+        let mut results: Vec<String> = queries
+            .into_iter()
+            .filter(|query| !is_all_page_tags(query))
+            .flat_map(|query| execute(query, index).unwrap_or_default())
+            .filter(|p| include_private || !p.is_private())
+            .map(|p| p.name.clone())
+            .collect();
+        results.sort();
+        results.dedup();
+        dependencies.insert(page.name.clone(), results);
+    }
+    dependencies
+}
+
+/// Pages whose query results differ from the last build's (pages with new
+/// queries included), sorted by name
+pub fn changed_results(
+    previous: &BTreeMap<String, Vec<String>>,
+    current: &BTreeMap<String, Vec<String>>,
+) -> Vec<String> {
+    current.iter().filter(|(name, results)| previous.get(*name) != Some(results)).map(|(name, _)| name.clone()).collect()
+}
+
+/// Callout in place of a query that can't be read: why, and the expression
+/// as written (without `{{query}}`, which would read as a macro left over)
+pub fn unsupported_to_markdown(query_str: &str, error: &QueryError) -> String {
+    format!("> [!warning] Unsupported query\n> {}\n> `{}`", error, shorten(query_expr(query_str)))
+}
+
+/// At most 80 characters of a query (a byte offset could split one)
+fn shorten(query_str: &str) -> String {
+    match query_str.char_indices().nth(80) {
+        Some((end, _)) => format!("{}...", &query_str[..end]),
+        None => query_str.to_string(),
+    }
+}
+
+/// Page names of query results in display order, for frontmatter listings
+pub fn results_to_listing(results: &[&Page], options: &QueryOptions) -> Vec<String> {
+    sort_results(results, options)
+        .iter()
+        .map(|p| p.name.clone())
+        .collect()
+}
+
+/// Sort results by query-sort-by:: (or page name when unset and not shuffled)
+fn sort_results<'a>(results: &[&'a Page], options: &QueryOptions) -> Vec<&'a Page> {
+    let mut sorted: Vec<_> = results.to_vec();
+    if let Some(ref sort_key) = options.sort_by {
+        sorted.sort_by(|a, b| {
+            let a_val = get_page_property(a, sort_key);
+            let b_val = get_page_property(b, sort_key);
+            if options.sort_desc {
+                properties::compare(&b_val, &a_val)
+            } else {
+                properties::compare(&a_val, &b_val)
+            }
+        });
+    } else if !options.shuffled {
+        sorted.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+    sorted
+}
+
 /// Render results as a markdown list
 fn render_list(results: &[&Page]) -> String {
     results
         .iter()
-        .map(|p| {
-            let icon = p.properties.get("icon").map_or("", |s| s.as_str());
-            let title = p
-                .properties
-                .get("title")
-                .map_or(p.name.replace('_', " "), |t| t.clone());
-            format!(
-                "- [[{}|{}{}]]",
-                p.name,
-                if icon.is_empty() {
-                    String::new()
-                } else {
-                    format!("{} ", icon)
-                },
-                title
-            )
-        })
+        .map(|p| format!("- [[{}|{}]]", resolver::link_target(p), display_title(p)))
         .collect::<Vec<_>>()
         .join("\n")
 }
 
+/// Results of `(property key value)`: each matching block's first line with a
+/// link to its page, and pages matching by their page properties as a link.
+/// `None` when no block matches, only pages.
+fn render_blocks(results: &[&Page], key: &str, wanted: Option<&str>) -> Option<String> {
+    let mut lines = Vec::new();
+    let mut any_block = false;
+    for page in results {
+        let link = format!("[[{}|{}]]", resolver::link_target(page), display_title(page));
+        let blocks = matching_blocks(page, key, wanted);
+        if blocks.is_empty() {
+            lines.push(format!("- {}", link));
+        }
+        for block in blocks {
+            any_block = true;
+            lines.push(format!("- {} ({})", block_text(block), link));
+        }
+    }
+    any_block.then(|| lines.join("\n"))
+}
+
+/// First line of a block result; a block of properties only shows its first
+/// one the way page text shows properties (`**Status:** done`)
+fn block_text(block: &Block) -> String {
+    match block.text.split_once("::") {
+        Some((key, value)) if block.properties.first().is_some_and(|(k, _)| *k == key.trim().to_lowercase()) => {
+            format!("**{}:** {}", properties::label(key.trim()), properties::render(value.trim()))
+        }
+        _ => block.text.clone(),
+    }
+}
+
+/// Icon and title of a result (`icon::`, `title::` or the page name)
+fn display_title(page: &Page) -> String {
+    let icon = page.properties.get("icon").map_or("", |s| s.as_str());
+    let title = page
+        .properties
+        .get("title")
+        .map_or(page.name.replace('_', " "), |t| t.clone());
+    if icon.is_empty() {
+        title
+    } else {
+        format!("{} {}", icon, title)
+    }
+}
+
+/// Wikilink to a result inside a table cell: the alias pipe escaped as `\|`
+/// (Quartz and Obsidian read it as the alias separator, tables as cell text).
+/// A page name with a pipe of its own is linked by its output path, where the
+/// pipe is `_`
+fn table_link(page: &Page) -> String {
+    let target = resolver::link_target(page);
+    let target = if target.contains('|') { resolver::safe_path(target) } else { target.to_string() };
+    format!("[[{}\\|{}]]", target, display_title(page).replace('|', "\\|"))
+}
+
+/// Words of a page's excerpt on a card without `description::`
+const CARD_WORDS: usize = 20;
+
+/// Render results as a grid of cards: a list in a `query-cards` wrapper,
+/// each item the linked title and a description
+fn render_cards(results: &[&Page]) -> String {
+    let mut output = String::from("<div class=\"query-cards\">\n\n");
+    for page in results {
+        output.push_str(&format!("- **[[{}|{}]]**", resolver::link_target(page), display_title(page)));
+        let description = match page.properties.get("description") {
+            Some(description) => properties::plain(description),
+            None => social::excerpt(&page.content, CARD_WORDS),
+        };
+        if !description.is_empty() {
+            output.push_str(&format!("  \n  {}", description));
+        }
+        output.push('\n');
+    }
+    output.push_str("\n</div>");
+    output
+}
+
+/// Render results as transclusions, one after the other
+fn render_embeds(results: &[&Page]) -> String {
+    results
+        .iter()
+        .map(|p| format!("![[{}]]", resolver::link_target(p)))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
 /// Get a property value from a page (supports special properties)
 fn get_page_property(page: &Page, key: &str) -> String {
     match key.to_lowercase().as_str() {
@@ -495,6 +760,24 @@ fn get_page_property(page: &Page, key: &str) -> String {
     }
 }
 
+/// A property value in a table cell, formatted by type: page refs as
+/// wikilinks, booleans as ✅/❌, ISO dates and timestamps as short dates
+/// (`Jan 15, 2025`), anything else as written
+fn format_cell(raw: &str) -> String {
+    let raw = raw.trim();
+    if ISO_DATE_RE.is_match(raw) {
+        if let Some(date) = timezone::current().parse_date(raw) {
+            return date.format("%b %-d, %Y").to_string();
+        }
+    }
+    match properties::parse(raw).as_slice() {
+        [properties::Value::Bool(true)] => "✅".to_string(),
+        [properties::Value::Bool(false)] => "❌".to_string(),
+        // Pipes, angle brackets and line breaks would break the table
+        _ => escape::table_cell(&properties::render(raw)),
+    }
+}
+
 /// Render results as a markdown table
 fn render_table(results: &[&Page], properties: &[String]) -> String {
     let mut output = String::new();
@@ -517,27 +800,33 @@ fn render_table(results: &[&Page], properties: &[String]) -> String {
     }
     output.push('\n');
 
-    // Separator row
+    // Data rows
+    let rows: Vec<Vec<String>> = results
+        .iter()
+        .map(|page| {
+            properties
+                .iter()
+                .map(|prop| match prop.to_lowercase().as_str() {
+                    "page" | "name" => table_link(page),
+                    _ => format_cell(&get_page_property(page, prop)),
+                })
+                .collect()
+        })
+        .collect();
+
+    // Separator row: columns of numbers are right-aligned
     output.push('|');
-    for _ in properties {
-        output.push_str(" --- |");
+    for (column, prop) in properties.iter().enumerate() {
+        let numeric = !matches!(prop.to_lowercase().as_str(), "page" | "name")
+            && rows.iter().any(|row| !row[column].is_empty())
+            && rows.iter().all(|row| row[column].is_empty() || NUMBER_CELL_RE.is_match(&row[column]));
+        output.push_str(if numeric { " ---: |" } else { " --- |" });
     }
     output.push('\n');
 
-    // Data rows
-    for page in results {
+    for row in rows {
         output.push('|');
-        for prop in properties {
-            let value = match prop.to_lowercase().as_str() {
-                "page" | "name" => {
-                    // Use plain wikilink without alias to avoid pipe conflicts in tables
-                    format!("[[{}]]", page.name)
-                }
-                _ => {
-                    // Escape any pipes in cell values using HTML entity
-                    get_page_property(page, prop).replace('|', "&#124;")
-                }
-            };
+        for value in row {
             output.push_str(&format!(" {} |", value));
         }
         output.push('\n');
@@ -587,16 +876,74 @@ fn detect_common_properties(results: &[&Page]) -> Vec<String> {
     props
 }
 
-/// Get all unique tags from the index
-#[allow(dead_code)]
-pub fn get_all_tags(index: &PageIndex) -> Vec<String> {
-    let mut tags: std::collections::HashSet<String> = std::collections::HashSet::new();
+/// Tags of the graph's pages with the number of pages using each, most used
+/// first (spelled as first seen; counted ignoring case)
+pub fn tag_counts(index: &PageIndex) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, (String, usize)> = HashMap::new();
     for page in index.iter() {
         for tag in &page.tags {
-            tags.insert(tag.clone());
+            counts.entry(tag.to_lowercase()).or_insert_with(|| (tag.clone(), 0)).1 += 1;
         }
     }
-    let mut sorted: Vec<_> = tags.into_iter().collect();
-    sorted.sort();
-    sorted
+    let mut tags: Vec<(String, usize)> = counts.into_values().collect();
+    tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.to_lowercase().cmp(&b.0.to_lowercase())));
+    tags
+}
+
+/// Is this an `(all-page-tags)` query?
+pub fn is_all_page_tags(query_str: &str) -> bool {
+    query_parser::parse(query_expr(query_str)).is_ok_and(|query| query.filter == Expr::AllPageTags)
+}
+
+/// Result of `(all-page-tags)`: each tag linked to its Quartz tag page, with
+/// the number of pages tagged
+pub fn tag_listing(index: &PageIndex, query_str: &str) -> String {
+    let tags = tag_counts(index);
+    if tags.is_empty() {
+        return results_to_markdown(&[], query_str);
+    }
+    tags.iter()
+        .map(|(tag, count)| format!("- {} ({})", content::tag_link(tag), count))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A query result as the `query` subcommand prints it
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ResultRow {
+    pub name: String,
+    pub title: String,
+    pub tags: Vec<String>,
+    pub properties: std::collections::BTreeMap<String, String>,
+    /// File the page was read from, relative to the graph
+    pub file: Option<String>,
+}
+
+/// Rows of query results, in result order
+pub fn result_rows(results: &[&Page], graph: &std::path::Path) -> Vec<ResultRow> {
+    results
+        .iter()
+        .map(|page| ResultRow {
+            name: page.name.clone(),
+            title: page.title(),
+            tags: page.tags.clone(),
+            properties: page.properties.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            file: page.source.as_ref().map(|path| {
+                path.strip_prefix(graph).unwrap_or(path).to_string_lossy().replace('\\', "/")
+            }),
+        })
+        .collect()
+}
+
+/// Query results as columns for the terminal: page, tags and file
+pub fn rows_table(rows: &[ResultRow]) -> Vec<String> {
+    let tags: Vec<String> = rows.iter().map(|row| row.tags.join(", ")).collect();
+    let name_width = rows.iter().map(|row| row.name.chars().count()).max().unwrap_or(0).max(4);
+    let tags_width = tags.iter().map(|t| t.chars().count()).max().unwrap_or(0).max(4);
+    let mut lines = vec![format!("{:<name_width$}  {:<tags_width$}  file", "page", "tags")];
+    for (row, tags) in rows.iter().zip(&tags) {
+        let line = format!("{:<name_width$}  {:<tags_width$}  {}", row.name, tags, row.file.as_deref().unwrap_or(""));
+        lines.push(line.trim_end().to_string());
+    }
+    lines
 }
@@ -0,0 +1,322 @@
+use crate::query::QueryError;
+
+/// Nesting deeper than this isn't anything anyone wrote by hand; give up instead of recursing
+const MAX_DEPTH: usize = 64;
+
+/// Task states `(task ...)` takes
+const TASK_STATES: &[&str] = &["TODO", "DONE", "NOW", "DOING", "LATER", "WAITING", "CANCELLED"];
+
+/// A parsed query: the filter, and the modifiers applied to its results
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub filter: Expr,
+    /// `(sample n)`: draw n random results
+    pub sample: Option<usize>,
+    /// `(shuffle)`: results in random order
+    pub shuffle: bool,
+}
+
+/// A query clause
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// Every page (a query of modifiers only, like `(and (sample 5))`)
+    All,
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    /// `(not a b)`: pages matching neither
+    Not(Vec<Expr>),
+    /// `[[page]]`, `#tag`, `(links-to [[page]])`, `(page-ref [[page]])`:
+    /// pages referencing the page
+    Ref(String),
+    /// `(page-tags [[a]] #b c)`: pages tagged with any of them
+    PageTags(Vec<String>),
+    /// `(page [[name]])`
+    Page(String),
+    /// `(namespace [[name]])`
+    Namespace(String),
+    /// `(property :key value)` (block properties, page properties included) or
+    /// `(page-property :key value)`; without a value, pages having the property
+    Property { page_only: bool, key: String, value: Option<String> },
+    /// `(task TODO DOING)`, states in upper case
+    Task(Vec<String>),
+    /// `(priority a)`, the letter in upper case
+    Priority(String),
+    /// `(between start end)`, bounds as written (`[[Jan 1st, 2025]]`, `-7d`, `today`)
+    Between(String, String),
+    /// `(all-page-tags)`
+    AllPageTags,
+    /// `"text"` or plain words: full-text search
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    /// `[[page]]`
+    Ref(String),
+    /// `#tag` or `#[[multi word]]`
+    Tag(String),
+    /// `"quoted"`
+    Str(String),
+    /// Anything else up to whitespace or a paren: `page-tags`, `:key`, `TODO`, `-7d`
+    Word(String),
+}
+
+/// A token and where it sits in the source (byte range)
+#[derive(Debug, Clone)]
+struct Spanned {
+    token: Token,
+    start: usize,
+    end: usize,
+}
+
+/// Parse a query expression (the inside of `{{query ...}}`)
+pub fn parse(src: &str) -> Result<Query, QueryError> {
+    let src = src.trim();
+    let tokens = tokenize(src)?;
+    // Commas are separators, so `,` is as empty as nothing at all
+    let Some(first) = tokens.first() else {
+        return Err(QueryError::Empty);
+    };
+    // Plain words are a full-text search for all of them
+    if matches!(first.token, Token::Word(_)) {
+        let search = src.replace(['"', '\''], "");
+        if search.trim().chars().count() <= 2 {
+            return Err(QueryError::Unsupported(src.to_string()));
+        }
+        return Ok(Query { filter: Expr::Text(search.trim().to_string()), sample: None, shuffle: false });
+    }
+
+    let mut parser = Parser { src, tokens, pos: 0, sample: None, shuffle: false };
+    let mut clauses = Vec::new();
+    let mut modifiers = 0;
+    while parser.pos < parser.tokens.len() {
+        match parser.clause(0)? {
+            Some(clause) => clauses.push(clause),
+            None => modifiers += 1,
+        }
+    }
+    let filter = match clauses.len() {
+        0 if modifiers > 0 => Expr::All,
+        1 => clauses.remove(0),
+        _ => Expr::And(clauses),
+    };
+    Ok(Query { filter, sample: parser.sample, shuffle: parser.shuffle })
+}
+
+/// Split a query into tokens; unterminated brackets and quotes and unpaired
+/// parentheses are errors
+fn tokenize(src: &str) -> Result<Vec<Spanned>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    let mut chars = src.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        let rest = &src[start..];
+        let (token, len) = match c {
+            c if c.is_whitespace() || c == ',' => {
+                chars.next();
+                continue;
+            }
+            '(' => {
+                depth += 1;
+                (Token::Open, 1)
+            }
+            ')' => {
+                depth = depth.checked_sub(1).ok_or(QueryError::Unbalanced)?;
+                (Token::Close, 1)
+            }
+            '"' => {
+                let end = rest[1..].find('"').ok_or(QueryError::Unbalanced)?;
+                (Token::Str(rest[1..end + 1].to_string()), end + 2)
+            }
+            '[' if rest.starts_with("[[") => {
+                let (name, len) = page_ref(rest)?;
+                (Token::Ref(name), len)
+            }
+            '#' if rest.starts_with("#[[") => {
+                let (name, len) = page_ref(&rest[1..])?;
+                (Token::Tag(name), len + 1)
+            }
+            '#' => match word_len(&rest[1..]) {
+                0 => (Token::Word("#".to_string()), 1),
+                len => (Token::Tag(rest[1..len + 1].to_string()), len + 1),
+            },
+            '[' | ']' => return Err(QueryError::Unbalanced),
+            _ => {
+                let len = word_len(rest);
+                (Token::Word(rest[..len].to_string()), len)
+            }
+        };
+        tokens.push(Spanned { token, start, end: start + len });
+        while chars.peek().is_some_and(|&(i, _)| i < start + len) {
+            chars.next();
+        }
+    }
+    if depth > 0 {
+        return Err(QueryError::Unbalanced);
+    }
+    Ok(tokens)
+}
+
+/// The name of the `[[page]]` at the start of `src` and the bytes it takes
+fn page_ref(src: &str) -> Result<(String, usize), QueryError> {
+    let end = src.find("]]").ok_or(QueryError::Unbalanced)?;
+    let name = &src[2..end];
+    if name.contains('[') {
+        return Err(QueryError::Unbalanced);
+    }
+    Ok((name.trim().to_string(), end + 2))
+}
+
+/// Bytes up to the next whitespace, comma, paren, bracket or quote
+fn word_len(src: &str) -> usize {
+    src.find(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '[' | ']' | '"' | ','))
+        .unwrap_or(src.len())
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    tokens: Vec<Spanned>,
+    pos: usize,
+    sample: Option<usize>,
+    shuffle: bool,
+}
+
+impl Parser<'_> {
+    /// Read one clause. Modifiers (`(sample n)`, `(shuffle)`) are recorded on
+    /// the parser and read as `None`.
+    fn clause(&mut self, depth: usize) -> Result<Option<Expr>, QueryError> {
+        if depth > MAX_DEPTH {
+            return Err(QueryError::TooDeep);
+        }
+        let Some(spanned) = self.tokens.get(self.pos).cloned() else {
+            return Err(QueryError::Unbalanced);
+        };
+        self.pos += 1;
+        match spanned.token {
+            Token::Ref(name) | Token::Tag(name) => Ok(Some(Expr::Ref(name))),
+            Token::Str(text) => Ok(Some(Expr::Text(text))),
+            Token::Open => self.list(spanned.start, depth),
+            Token::Close | Token::Word(_) => Err(QueryError::Unsupported(self.src[spanned.start..spanned.end].to_string())),
+        }
+    }
+
+    /// Read a `(keyword args...)` list, its `(` just read at `start`
+    fn list(&mut self, start: usize, depth: usize) -> Result<Option<Expr>, QueryError> {
+        let keyword = match self.tokens.get(self.pos).map(|t| &t.token) {
+            Some(Token::Word(word)) => {
+                self.pos += 1;
+                word.to_lowercase()
+            }
+            _ => String::new(),
+        };
+
+        // Boolean operators take clauses; everything else takes plain arguments
+        if matches!(keyword.as_str(), "and" | "or" | "not") {
+            let mut clauses = Vec::new();
+            let mut modifiers = 0;
+            while !self.at_close() {
+                match self.clause(depth + 1)? {
+                    Some(clause) => clauses.push(clause),
+                    None => modifiers += 1,
+                }
+            }
+            self.pos += 1;
+            return Ok(Some(match keyword.as_str() {
+                "and" if clauses.is_empty() && modifiers > 0 => Expr::All,
+                "and" => Expr::And(clauses),
+                "or" => Expr::Or(clauses),
+                _ => Expr::Not(clauses),
+            }));
+        }
+
+        let mut args = Vec::new();
+        while !self.at_close() {
+            let arg = &self.tokens[self.pos];
+            if arg.token == Token::Open {
+                self.skip_list();
+                return Err(self.unsupported(start));
+            }
+            args.push(arg.token.clone());
+            self.pos += 1;
+        }
+        self.pos += 1;
+
+        let text = |token: &Token| match token {
+            Token::Ref(s) | Token::Tag(s) | Token::Str(s) | Token::Word(s) => Some(s.clone()),
+            _ => None,
+        };
+        let expr = match (keyword.as_str(), args.as_slice()) {
+            ("page-tags" | "page-tag", args) if !args.is_empty() => Expr::PageTags(args.iter().filter_map(text).collect()),
+            ("page", [arg]) => Expr::Page(text(arg).unwrap_or_default()),
+            ("namespace", [arg]) => Expr::Namespace(text(arg).unwrap_or_default()),
+            ("links-to" | "page-ref", [Token::Ref(name)]) => Expr::Ref(name.clone()),
+            ("property" | "page-property", [Token::Word(key), value @ ..]) if value.len() <= 1 => Expr::Property {
+                page_only: keyword == "page-property",
+                key: key.trim_start_matches(':').to_lowercase(),
+                value: value.first().map(|value| match value {
+                    Token::Ref(name) => format!("[[{}]]", name),
+                    Token::Tag(name) => format!("#{}", name),
+                    other => text(other).unwrap_or_default(),
+                }),
+            },
+            ("task", states) if !states.is_empty() => {
+                let states: Option<Vec<String>> = states
+                    .iter()
+                    .map(|state| match state {
+                        Token::Word(w) if TASK_STATES.contains(&w.to_uppercase().as_str()) => Some(w.to_uppercase()),
+                        _ => None,
+                    })
+                    .collect();
+                Expr::Task(states.ok_or_else(|| self.unsupported(start))?)
+            }
+            ("priority", [Token::Word(p)]) if matches!(p.to_lowercase().as_str(), "a" | "b" | "c") => {
+                Expr::Priority(p.to_uppercase())
+            }
+            ("between", [a, b]) => {
+                let bound = |token: &Token| match token {
+                    Token::Ref(name) => format!("[[{}]]", name),
+                    other => text(other).unwrap_or_default(),
+                };
+                Expr::Between(bound(a), bound(b))
+            }
+            ("all-page-tags", []) => Expr::AllPageTags,
+            ("sample", [Token::Word(n)]) if n.parse::<usize>().is_ok() => {
+                self.sample = n.parse().ok();
+                return Ok(None);
+            }
+            ("shuffle", []) => {
+                self.shuffle = true;
+                return Ok(None);
+            }
+            _ => return Err(self.unsupported(start)),
+        };
+        Ok(Some(expr))
+    }
+
+    /// Skip to after the `)` closing the current list
+    fn skip_list(&mut self) {
+        let mut depth = 0usize;
+        while let Some(spanned) = self.tokens.get(self.pos) {
+            self.pos += 1;
+            match spanned.token {
+                Token::Open => depth += 1,
+                Token::Close if depth == 0 => return,
+                Token::Close => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+
+    /// Is the next token the `)` closing the current list?
+    fn at_close(&self) -> bool {
+        matches!(self.tokens.get(self.pos).map(|t| &t.token), Some(Token::Close))
+    }
+
+    /// The list from `start` to the current token, as written
+    fn unsupported(&self, start: usize) -> QueryError {
+        let end = self.tokens.get(self.pos.saturating_sub(1)).map_or(self.src.len(), |t| t.end);
+        QueryError::Unsupported(self.src[start..end.max(start)].to_string())
+    }
+}
@@ -0,0 +1,30 @@
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+
+use crate::page::PageIndex;
+use crate::resolver;
+
+/// Page list written to the output root for a "random note" button (`--random`)
+pub const RANDOM_FILE: &str = "random.json";
+
+/// Site-relative paths of the pages a random pick can land on, sorted: listed
+/// pages only, so journals, private and unlisted pages and drafts are left out
+pub fn paths(index: &PageIndex, include_private: bool) -> Vec<String> {
+    let mut paths: Vec<String> = index
+        .iter()
+        .filter(|p| !p.name.starts_with("journals/") && !p.is_unlisted() && (include_private || !p.is_private()))
+        .map(resolver::site_path)
+        .collect();
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+/// Write `random.json` (compact: it is fetched by every visitor). Returns the number of pages.
+pub fn write(output_dir: &Path, index: &PageIndex, include_private: bool) -> Result<usize> {
+    let paths = paths(index, include_private);
+    fs::write(output_dir.join(RANDOM_FILE), serde_json::to_string(&paths)?)?;
+    Ok(paths.len())
+}
+
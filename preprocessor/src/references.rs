@@ -0,0 +1,126 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::content;
+use crate::page::{Page, PageIndex};
+use crate::resolver;
+use crate::source;
+
+lazy_static! {
+    // Filter entries in an EDN map: {"tag" true, "other" false}
+    static ref FILTER_ENTRY_RE: Regex = Regex::new(r#""([^"]+)"\s+(true|false)"#).unwrap();
+
+    // Graph-wide :filters {...} map in config.edn
+    static ref CONFIG_FILTERS_RE: Regex = Regex::new(r":filters\s+\{([^}]*)\}").unwrap();
+
+    // References in page content: [[page]], #tag, #[[multi word tag]]
+    static ref REF_LINK_RE: Regex = Regex::new(r"\[\[([^\]|]+)(?:\|[^\]]*)?\]\]").unwrap();
+    static ref REF_TAG_RE: Regex = Regex::new(r"#([\p{L}\p{N}][\p{L}\p{N}_/-]*)").unwrap();
+}
+
+/// A linked-reference filter: page name and whether it is included (true) or excluded (false)
+pub type Filter = (String, bool);
+
+/// Parse a Logseq filters map (`{"tag" true, "other" false}`)
+pub fn parse_filters(value: &str) -> Vec<Filter> {
+    FILTER_ENTRY_RE
+        .captures_iter(value)
+        .map(|caps| (caps[1].to_lowercase(), &caps[2] == "true"))
+        .collect()
+}
+
+/// Graph-wide filters from config.edn `:filters {...}` (applied to every page)
+pub fn graph_filters(config_path: &Path) -> Vec<Filter> {
+    let Ok(content) = source::read_to_string(config_path) else {
+        return Vec::new();
+    };
+    let content: String = content
+        .lines()
+        .filter(|line| !line.trim().starts_with(';'))
+        .collect::<Vec<_>>()
+        .join("\n");
+    CONFIG_FILTERS_RE
+        .captures(&content)
+        .map(|caps| parse_filters(&caps[1]))
+        .unwrap_or_default()
+}
+
+/// Effective filters for a page: graph-wide filters overridden by the page's `filters::` property
+pub fn page_filters(page: &Page, graph: &[Filter]) -> Vec<Filter> {
+    let own = page.properties.get("filters").map(|v| parse_filters(v)).unwrap_or_default();
+    let mut filters: Vec<Filter> = graph
+        .iter()
+        .filter(|(name, _)| !own.iter().any(|(n, _)| n == name))
+        .cloned()
+        .collect();
+    filters.extend(own);
+    filters
+}
+
+/// Pages referencing `target` (through its aliases too), in index order
+pub fn linking_to<'a>(target: &str, index: &'a PageIndex) -> Vec<&'a Page> {
+    let key = match resolver::resolve(target, index) {
+        Some(resolved) if !resolved.kind.is_guess() => resolver::normalize_key(&resolved.page.name),
+        _ => resolver::normalize_key(target),
+    };
+    index.iter().filter(|p| references(p, index).contains(&key)).collect()
+}
+
+/// Output names of pages referencing `target` that pass the filters.
+///
+/// Filters apply per referencing page, like Logseq's linked references view:
+/// a page is kept if it references every included page and none of the excluded ones.
+/// Private pages are left out unless `include_private`.
+pub fn linked_references(target: &Page, index: &PageIndex, filters: &[Filter], include_private: bool) -> Vec<String> {
+    let target_key = resolver::normalize_key(&target.name);
+    let mut refs: Vec<String> = index
+        .iter()
+        .filter(|p| p.name_lower != target.name_lower)
+        .filter(|p| include_private || !p.is_private())
+        .filter_map(|p| {
+            let page_refs = references(p, index);
+            if !page_refs.contains(&target_key) {
+                return None;
+            }
+            let passes = filters.iter().all(|(name, include)| {
+                page_refs.contains(&resolver::normalize_key(name)) == *include
+            });
+            passes.then(|| resolver::link_target(p).to_string())
+        })
+        .collect();
+    refs.sort();
+    refs
+}
+
+/// Comparison keys of all pages a page references (links, embeds, tags),
+/// resolved through aliases. Links in code don't count; `[[page#heading]]`
+/// references the page.
+pub fn references(page: &Page, index: &PageIndex) -> HashSet<String> {
+    let mut refs: HashSet<String> = HashSet::new();
+    let mut add = |name: &str| {
+        let name = name.trim();
+        if name.is_empty() {
+            return;
+        }
+        refs.insert(resolver::normalize_key(name));
+        if let Some(resolved) = resolver::resolve(name, index) {
+            if !resolved.kind.is_guess() {
+                refs.insert(resolver::normalize_key(&resolved.page.name));
+            }
+        }
+    };
+
+    let text = content::without_code(&page.content);
+    for caps in REF_LINK_RE.captures_iter(&text) {
+        add(resolver::split_anchor(&caps[1], index).0);
+    }
+    for caps in REF_TAG_RE.captures_iter(&text) {
+        add(&caps[1]);
+    }
+    for tag in &page.tags {
+        add(tag);
+    }
+    refs
+}
@@ -0,0 +1,142 @@
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::page::{Page, PageIndex};
+use crate::references;
+use crate::resolver;
+
+/// Related pages of each page, keyed by Quartz slug (`--related json`)
+pub const RELATED_FILE: &str = "related.json";
+
+/// Related pages kept per page unless `--related-count` says otherwise
+pub const RELATED_COUNT: usize = 5;
+
+/// Score of a direct link, counted once per direction
+const LINK_WEIGHT: usize = 3;
+/// Score of each tag two pages share
+const TAG_WEIGHT: usize = 2;
+/// Score of each page (or journal) linking to both
+const CO_CITATION_WEIGHT: usize = 1;
+
+/// Where related pages are published (`--related`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RelatedOutput {
+    /// A `related:` list in each page's frontmatter
+    Frontmatter,
+    /// related.json at the site root
+    Json,
+}
+
+/// A page related to another, and how strongly
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Related {
+    /// Page name as in the graph
+    pub page: String,
+    pub score: usize,
+}
+
+/// Entry of `related.json`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct RelatedEntry {
+    pub slug: String,
+    pub title: String,
+    pub score: usize,
+}
+
+/// Up to `limit` related pages for each published page, by page name, most
+/// related first. Pages are related by links between them (either way), tags
+/// they share, and pages linking to both (co-citation). Journals count as
+/// linking pages but are never related pages themselves; neither are
+/// unlisted pages and drafts.
+pub fn compute(index: &PageIndex, limit: usize, include_private: bool) -> BTreeMap<String, Vec<Related>> {
+    let pages: Vec<&Page> = index.iter().filter(|p| include_private || !p.is_private()).collect();
+    let by_key: HashMap<String, usize> = pages.iter().enumerate().map(|(i, p)| (resolver::normalize_key(&p.name), i)).collect();
+    let candidate = |i: usize| !pages[i].name.starts_with("journals/") && !pages[i].is_unlisted();
+
+    let mut scores: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut add = |a: usize, b: usize, score: usize| {
+        if a != b && candidate(a) && candidate(b) {
+            *scores.entry((a.min(b), a.max(b))).or_default() += score;
+        }
+    };
+
+    for (i, page) in pages.iter().enumerate() {
+        let mut targets: Vec<usize> = references::references(page, index)
+            .iter()
+            .filter_map(|key| by_key.get(key).copied())
+            .filter(|&target| target != i)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        targets.sort_unstable();
+        for &target in &targets {
+            add(i, target, LINK_WEIGHT);
+        }
+        for (n, &a) in targets.iter().enumerate() {
+            for &b in &targets[n + 1..] {
+                add(a, b, CO_CITATION_WEIGHT);
+            }
+        }
+    }
+
+    let mut tagged: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, page) in pages.iter().enumerate() {
+        let tags: HashSet<String> = page.tags.iter().map(|t| resolver::normalize_key(t)).collect();
+        for tag in tags {
+            tagged.entry(tag).or_default().push(i);
+        }
+    }
+    for members in tagged.values() {
+        for (n, &a) in members.iter().enumerate() {
+            for &b in &members[n + 1..] {
+                add(a, b, TAG_WEIGHT);
+            }
+        }
+    }
+
+    let mut related: BTreeMap<String, Vec<Related>> = BTreeMap::new();
+    for (&(a, b), &score) in &scores {
+        for (from, to) in [(a, b), (b, a)] {
+            related.entry(pages[from].name.clone()).or_default().push(Related { page: pages[to].name.clone(), score });
+        }
+    }
+    for list in related.values_mut() {
+        list.sort_by(|x, y| y.score.cmp(&x.score).then_with(|| x.page.cmp(&y.page)));
+        list.truncate(limit);
+    }
+    related
+}
+
+/// Write `related.json`: each page's related pages with their slugs and
+/// titles, keyed by Quartz slug. Returns the number of pages with related pages.
+pub fn write(output_dir: &Path, index: &PageIndex, related: &BTreeMap<String, Vec<Related>>) -> Result<usize> {
+    let entries: BTreeMap<String, Vec<RelatedEntry>> = related
+        .iter()
+        .filter_map(|(name, list)| {
+            let page = resolver::find_page(name, index)?;
+            let entries = list
+                .iter()
+                .filter_map(|r| {
+                    let other = resolver::find_page(&r.page, index)?;
+                    Some(RelatedEntry { slug: resolver::site_path(other), title: other.title(), score: r.score })
+                })
+                .collect();
+            Some((resolver::site_path(page), entries))
+        })
+        .collect();
+    fs::write(output_dir.join(RELATED_FILE), serde_json::to_string(&entries)?)?;
+    Ok(entries.len())
+}
+
+/// Link targets of a page's related pages, for its `related:` frontmatter
+pub fn frontmatter_targets(page: &Page, index: &PageIndex) -> Vec<String> {
+    index
+        .related(&page.name)
+        .iter()
+        .filter_map(|r| resolver::find_page(&r.page, index))
+        .map(|p| resolver::link_target(p).to_string())
+        .collect()
+}
+
@@ -0,0 +1,231 @@
+use anyhow::{bail, Context, Result};
+use regex::{Captures, Regex};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::resolver;
+use crate::source;
+
+/// A page rename across the source graph: the file move plus every rewritten reference
+#[derive(Debug, Default)]
+pub struct RenamePlan {
+    /// Page file to move (None when the page is only referenced, never created)
+    pub file: Option<(PathBuf, PathBuf)>,
+    pub edits: Vec<FileEdit>,
+}
+
+/// New content for one graph file
+#[derive(Debug)]
+pub struct FileEdit {
+    pub path: PathBuf,
+    pub replacements: usize,
+    pub content: String,
+}
+
+impl RenamePlan {
+    pub fn replacements(&self) -> usize {
+        self.edits.iter().map(|e| e.replacements).sum()
+    }
+}
+
+/// Plan renaming page `old` to `new` in the graph at `input_dir`
+pub fn plan(input_dir: &Path, old: &str, new: &str) -> Result<RenamePlan> {
+    let (old, new) = (source::nfc(old.trim()), source::nfc(new.trim()));
+    let (old, new) = (old.as_str(), new.as_str());
+    if old.is_empty() || new.is_empty() {
+        bail!("page names must not be empty");
+    }
+    if old == new {
+        bail!("\"{}\" is already the page name", old);
+    }
+
+    let pages_dir = input_dir.join("pages");
+    let from = graph_files(&pages_dir).into_iter().find(|path| {
+        path.file_stem()
+            .is_some_and(|stem| resolver::page_name(&stem.to_string_lossy()).to_lowercase() == old.to_lowercase())
+    });
+    let file = match from {
+        Some(from) => {
+            let to = pages_dir.join(format!("{}.md", resolver::file_stem(new)));
+            // Case-only renames move onto the same file on case-insensitive filesystems
+            if to.exists() && !same_file(&from, &to) {
+                bail!("page \"{}\" already exists ({})", new, to.display());
+            }
+            Some((from, to))
+        }
+        None => None,
+    };
+
+    let rewriter = Rewriter::new(old, new);
+    let mut edits = Vec::new();
+    let mut files = graph_files(&pages_dir);
+    files.extend(graph_files(&input_dir.join("journals")));
+    for path in files {
+        let original = fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+        let (content, replacements) = rewriter.rewrite(&source::normalize(&original));
+        if replacements > 0 {
+            let content = keep_file_format(&original, content);
+            edits.push(FileEdit { path, replacements, content });
+        }
+    }
+
+    let config_path = input_dir.join("logseq/config.edn");
+    if let Ok(original) = fs::read_to_string(&config_path) {
+        let (content, replacements) = rewriter.rewrite_config(&source::normalize(&original));
+        if replacements > 0 {
+            let content = keep_file_format(&original, content);
+            edits.push(FileEdit { path: config_path, replacements, content });
+        }
+    }
+
+    Ok(RenamePlan { file, edits })
+}
+
+/// Apply a plan. With `backup`, every touched file is first copied under
+/// `logseq/bak/rename-<timestamp>/` (Logseq's own backup folder). Returns the backup folder.
+pub fn apply(plan: &RenamePlan, input_dir: &Path, backup: bool) -> Result<Option<PathBuf>> {
+    let backup_dir = if backup {
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+        Some(input_dir.join("logseq/bak").join(format!("rename-{}", stamp)))
+    } else {
+        None
+    };
+
+    if let Some(backup_dir) = &backup_dir {
+        let mut touched: Vec<&Path> = plan.edits.iter().map(|e| e.path.as_path()).collect();
+        if let Some((from, _)) = &plan.file {
+            touched.push(from);
+        }
+        for path in touched {
+            let target = backup_dir.join(path.strip_prefix(input_dir).unwrap_or(path));
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(path, &target)?;
+        }
+    }
+
+    for edit in &plan.edits {
+        fs::write(&edit.path, &edit.content)?;
+    }
+    if let Some((from, to)) = &plan.file {
+        fs::rename(from, to)?;
+    }
+
+    Ok(backup_dir)
+}
+
+/// Rewrites references to one page name
+struct Rewriter {
+    new: String,
+    wikilink: Regex,
+    hashtag: Regex,
+    list_property: Regex,
+    title_property: Regex,
+    config_string: Regex,
+    old_lower: String,
+}
+
+impl Rewriter {
+    fn new(old: &str, new: &str) -> Self {
+        let old_re = regex::escape(old);
+        Rewriter {
+            new: new.to_string(),
+            // [[Old]], [[Old|label]], #[[Old]]
+            wikilink: Regex::new(&format!(r"(?i)\[\[\s*{}\s*(\|[^\]]*)?\]\]", old_re)).unwrap(),
+            // #Old (single-word names)
+            hashtag: Regex::new(&format!(r"(?im)(^|\s)#{}([\s,.!?;:)]|$)", old_re)).unwrap(),
+            // tags:: / alias:: items reference pages without brackets
+            list_property: Regex::new(r"(?im)^(\s*(?:-\s+)?(?:tags|alias)::[ \t]*)(.+)$").unwrap(),
+            title_property: Regex::new(&format!(r"(?im)^(\s*(?:-\s+)?title::[ \t]*){}[ \t]*$", old_re)).unwrap(),
+            // "old page" in :favorites / :default-home
+            config_string: Regex::new(&format!(r#"(?i)"{}""#, old_re)).unwrap(),
+            old_lower: old.to_lowercase(),
+        }
+    }
+
+    /// Rewrite page content, returning the new content and the number of references changed
+    fn rewrite(&self, content: &str) -> (String, usize) {
+        let mut count = 0;
+
+        let content = self.wikilink.replace_all(content, |caps: &Captures| {
+            count += 1;
+            format!("[[{}{}]]", self.new, caps.get(1).map_or("", |m| m.as_str()))
+        });
+
+        let content = self.hashtag.replace_all(&content, |caps: &Captures| {
+            count += 1;
+            format!("{}{}{}", &caps[1], hashtag(&self.new), &caps[2])
+        });
+
+        let content = self.list_property.replace_all(&content, |caps: &Captures| {
+            let items: Vec<String> = caps[2]
+                .split(',')
+                .map(|item| {
+                    if item.trim().to_lowercase() == self.old_lower {
+                        count += 1;
+                        let leading = &item[..item.len() - item.trim_start().len()];
+                        format!("{}{}", leading, self.new)
+                    } else {
+                        item.to_string()
+                    }
+                })
+                .collect();
+            format!("{}{}", &caps[1], items.join(","))
+        });
+
+        let content = self.title_property.replace_all(&content, |caps: &Captures| {
+            count += 1;
+            format!("{}{}", &caps[1], self.new)
+        });
+
+        (content.into_owned(), count)
+    }
+
+    /// Rewrite page-name strings in config.edn (favorites are stored lowercase)
+    fn rewrite_config(&self, content: &str) -> (String, usize) {
+        let mut count = 0;
+        let content = self.config_string.replace_all(content, |_: &Captures| {
+            count += 1;
+            format!("\"{}\"", self.new.to_lowercase())
+        });
+        (content.into_owned(), count)
+    }
+}
+
+/// Rewritten files keep their CRLF line endings and byte order mark
+fn keep_file_format(original: &str, content: String) -> String {
+    let content = if original.contains("\r\n") { content.replace('\n', "\r\n") } else { content };
+    if original.starts_with('\u{feff}') {
+        format!("\u{feff}{}", content)
+    } else {
+        content
+    }
+}
+
+/// `#tag` for single words, `#[[multi word]]` otherwise
+fn hashtag(name: &str) -> String {
+    if name.chars().any(char::is_whitespace) {
+        format!("#[[{}]]", name)
+    } else {
+        format!("#{}", name)
+    }
+}
+
+fn graph_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "md"))
+        .map(|e| e.into_path())
+        .collect();
+    files.sort();
+    files
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
@@ -0,0 +1,630 @@
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::page::{Page, PageIndex};
+use crate::source;
+
+/// Characters not allowed in Windows file names; replaced by `_` in output paths
+const UNSAFE_FILE_CHARS: [char; 8] = [':', '*', '?', '"', '<', '>', '|', '\\'];
+
+/// Least confidence of a fuzzy link match unless `--fuzzy-threshold` says otherwise
+pub const FUZZY_THRESHOLD: f64 = 0.85;
+
+/// Fuzzy link resolutions (`--link-fallback fuzzy`)
+pub const FUZZY_LINKS_FILE: &str = "_fuzzy_links.json";
+
+/// Page name from a Logseq filename stem (namespace separator `___` becomes `/`).
+/// Logseq percent-encodes characters it can't put in file names (`What%3F` is
+/// `What?`), and macOS stores names decomposed, so names are decoded and NFC-normalized.
+pub fn page_name(filename: &str) -> String {
+    source::nfc(&decode(&filename.replace("___", "/")))
+}
+
+/// Logseq file name stem for a page name, the inverse of `page_name`: `/` becomes
+/// `___` and characters Windows rejects in file names are percent-encoded
+pub fn file_stem(name: &str) -> String {
+    let mut stem = String::with_capacity(name.len());
+    for c in name.chars() {
+        match c {
+            '/' => stem.push_str("___"),
+            c if UNSAFE_FILE_CHARS.contains(&c) => stem.push_str(&format!("%{:02X}", c as u32)),
+            c => stem.push(c),
+        }
+    }
+    stem
+}
+
+/// Output name (site-relative path without `.md`) for a page.
+/// A `slug::` property overrides the name-derived path.
+pub fn output_name(filename: &str, properties: &HashMap<String, String>, style: SlugStyle) -> String {
+    slug_property(properties).unwrap_or_else(|| {
+        let name = page_name(filename);
+        derived_slug(&name, style).unwrap_or(name)
+    })
+}
+
+/// Output name derived from a page name when it can't be the name itself:
+/// respelled by the slug style and/or with unsafe file name characters replaced
+pub fn derived_slug(name: &str, style: SlugStyle) -> Option<String> {
+    let slug = safe_path(&style.slug(name).unwrap_or_else(|| name.to_string()));
+    (slug != name).then_some(slug)
+}
+
+/// Decode `%XX` escapes in a link or file name (`My%20Page` → `My Page`).
+/// URLs and text that doesn't decode to valid UTF-8 are returned unchanged.
+pub fn decode(text: &str) -> String {
+    if !text.contains('%') || text.contains("://") {
+        return text.to_string();
+    }
+    percent_encoding::percent_decode_str(text)
+        .decode_utf8()
+        .map_or_else(|_| text.to_string(), |decoded| decoded.into_owned())
+}
+
+/// File-system safe form of a `/`-separated site path: characters Windows
+/// rejects in file names become `_`. Output files and the links pointing at
+/// them both go through this, so they stay in sync.
+pub fn safe_path(name: &str) -> String {
+    name.replace(UNSAFE_FILE_CHARS, "_")
+}
+
+/// Slug Quartz derives from an output name or tag (`data-slug` on internal links,
+/// `tags/…` pages): spaces become `-`, `&` `-and-`, `%` `-percent`, `?` and `#` are dropped
+pub fn quartz_slug(name: &str) -> String {
+    name.split('/')
+        .map(|segment| {
+            segment
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join("-")
+                .replace('&', "-and-")
+                .replace('%', "-percent")
+                .replace(['?', '#'], "")
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// How name-derived output paths spell non-ASCII page names
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SlugStyle {
+    /// Keep the page name as is (`Москва` → `Москва`)
+    #[default]
+    Unicode,
+    /// Transliterate to ASCII (`Москва` → `Moskva`, `東京` → `Dong Jing`)
+    Ascii,
+}
+
+impl SlugStyle {
+    /// Output name for a page name, when it differs from the name itself
+    pub fn slug(self, name: &str) -> Option<String> {
+        if self == SlugStyle::Unicode || name.is_ascii() {
+            return None;
+        }
+        let parts: Vec<String> = name
+            .split('/')
+            .map(|part| {
+                let ascii = deunicode::deunicode(part);
+                let ascii = ascii.split_whitespace().collect::<Vec<_>>().join(" ");
+                if ascii.is_empty() { part.to_string() } else { ascii }
+            })
+            .collect();
+        Some(parts.join("/"))
+    }
+}
+
+/// Custom slug from the `slug::` property, normalized
+pub fn slug_property(properties: &HashMap<String, String>) -> Option<String> {
+    properties.get("slug").and_then(|s| clean_slug(s))
+}
+
+/// Output file path for an output name, relative to the content root
+pub fn output_path(output_dir: &Path, name: &str) -> PathBuf {
+    join_site_path(output_dir, &format!("{}.md", name))
+}
+
+/// Join a `/`-separated site path (output name, asset link) onto a directory,
+/// one component at a time so the result uses the platform's separators.
+/// The result never leaves `dir`: `..`, roots and drive prefixes are skipped.
+pub fn join_site_path(dir: &Path, site_path: &str) -> PathBuf {
+    let mut path = dir.to_path_buf();
+    for part in site_path.split('/') {
+        let mut components = Path::new(part).components();
+        if let (Some(Component::Normal(name)), None) = (components.next(), components.next()) {
+            path.push(name);
+        }
+    }
+    path
+}
+
+/// Does a `/`-separated site path stay inside the folder it's joined to?
+/// (no `..` part; `slug::` and `alias::` values must not climb out of the output)
+pub fn is_contained(site_path: &str) -> bool {
+    site_path.split(['/', '\\']).all(|part| part.trim() != "..")
+}
+
+/// Link target for a page: its slug when set, otherwise its name
+pub fn link_target(page: &Page) -> &str {
+    page.slug.as_deref().unwrap_or(&page.name)
+}
+
+/// Site-relative URL path of a page as Quartz serves it (`Rust & C` → `Rust--and--C`),
+/// for links in files outside the markdown (feeds, calendars, sitemaps)
+pub fn site_path(page: &Page) -> String {
+    quartz_slug(link_target(page))
+}
+
+/// Look up a page by name (case-insensitive)
+pub fn find_page<'a>(name: &str, index: &'a PageIndex) -> Option<&'a Page> {
+    let lower = name.to_lowercase();
+    index.named(&normalize_key(name)).find(|p| p.name_lower == lower)
+}
+
+/// Output name for a page referenced by name, following aliases and slugs
+pub fn resolve_output_name(name: &str, index: &PageIndex) -> String {
+    resolve(name, index)
+        .filter(|r| !r.kind.is_guess())
+        .map(|r| link_target(r.page).to_string())
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// Output name of a journal day (`journals/YYYY-MM-DD`)
+pub fn journal_output_name(date: &str) -> String {
+    format!("journals/{}", date)
+}
+
+/// Comparison key for page names and links: %XX-decoded, NFC, lowercase,
+/// `-` and `_` treated as spaces
+pub fn normalize_key(name: &str) -> String {
+    source::nfc(&decode(name).to_lowercase()).replace(['-', '_'], " ")
+}
+
+/// Shorter keys a link key can fall back to in prefix matching, longest first:
+/// cut before a space, or between two characters of a script written without
+/// spaces (Chinese, Japanese, Thai), so `東京タワー` can fall back to `東京`
+pub fn word_prefixes(key: &str) -> impl Iterator<Item = &str> {
+    let mut breaks = Vec::new();
+    let mut prev: Option<char> = None;
+    for (pos, c) in key.char_indices() {
+        if c == ' ' || prev.is_some_and(|p| unspaced_script(p) && unspaced_script(c)) {
+            breaks.push(pos);
+        }
+        prev = Some(c);
+    }
+    breaks.into_iter().rev().map(move |pos| key[..pos].trim_end()).filter(|p| !p.is_empty())
+}
+
+/// Characters of scripts that don't separate words with spaces
+fn unspaced_script(c: char) -> bool {
+    matches!(c,
+        '\u{0E00}'..='\u{0E7F}'     // Thai
+        | '\u{3040}'..='\u{30FF}'   // Hiragana, Katakana
+        | '\u{3400}'..='\u{4DBF}'   // CJK Extension A
+        | '\u{4E00}'..='\u{9FFF}'   // CJK Unified Ideographs
+        | '\u{F900}'..='\u{FAFF}'   // CJK Compatibility Ideographs
+    )
+}
+
+/// How a link was matched to a page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchKind {
+    /// Page name (ignoring case and space/dash/underscore differences)
+    Exact,
+    /// One of the page's `alias::` values
+    Alias,
+    /// `alias/suffix` expanded to `page/suffix`
+    NamespaceAlias,
+    /// Date title like `Jan 15th, 2024` matched to a journal
+    Journal,
+    /// Longest page name that prefixes the link (`visit us` → `visit`)
+    Prefix,
+    /// Page name or alias a few edits away from the link (`viist` → `visit`)
+    Fuzzy,
+}
+
+impl MatchKind {
+    /// A fallback guess for a link naming no page, rather than the page it names
+    pub fn is_guess(self) -> bool {
+        matches!(self, MatchKind::Prefix | MatchKind::Fuzzy)
+    }
+}
+
+/// What a link naming no page falls back to (`--link-fallback`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LinkFallback {
+    /// Page names only; aliases, journal titles and the rest are left to Quartz
+    Off,
+    /// Page names, aliases, namespace aliases and journal date titles; nothing guessed
+    Exact,
+    /// Also the longest page name the link starts with (`visit us` → `visit`)
+    #[default]
+    Prefix,
+    /// Also a page name or alias a few typos away (`viist` → `visit`)
+    Fuzzy,
+}
+
+/// Link fallback mode and how close a guess has to be to be taken
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fallback {
+    pub mode: LinkFallback,
+    /// Characters the matched name must have (`--link-fallback-min-length`)
+    pub min_length: usize,
+    /// Share of the link the matched name must cover, 0 to 1 (`--link-fallback-min-ratio`)
+    pub min_ratio: f64,
+    /// Least confidence of a fuzzy match, 0 to 1 (`--fuzzy-threshold`)
+    pub fuzzy_threshold: f64,
+}
+
+impl Default for Fallback {
+    fn default() -> Self {
+        Fallback { mode: LinkFallback::default(), min_length: 1, min_ratio: 0.0, fuzzy_threshold: FUZZY_THRESHOLD }
+    }
+}
+
+impl Fallback {
+    /// Whether a guessed name (a comparison key) is close enough to the link's
+    fn accepts(&self, matched: &str, link: &str) -> bool {
+        let (matched, link) = (matched.chars().count(), link.chars().count());
+        matched >= self.min_length && link > 0 && matched as f64 / link as f64 >= self.min_ratio
+    }
+}
+
+/// A link resolved to a page
+#[derive(Debug, Clone, Copy)]
+pub struct Resolved<'a> {
+    pub page: &'a Page,
+    pub kind: MatchKind,
+}
+
+/// Resolve a link to a page.
+/// Tries, in order:
+/// 1. Exact page name match
+/// 2. Exact alias match (e.g., "cv/districts" matches page with alias "cv/districts")
+/// 3. Namespace alias expansion (e.g., "cv/districts" → "cyber valley/districts" if "cv" is alias for "cyber valley")
+/// 4. Journal date titles (e.g., "Jan 15th, 2024" → journals/2024_01_15)
+/// 5. Prefix matching (e.g., "visit us" matches "visit" if "visit us" doesn't exist)
+/// 6. With `--link-fallback fuzzy`, the closest page name or alias by edit distance
+///
+/// `--link-fallback` stops after 1 (`off`) or 4 (`exact`).
+pub fn resolve<'a>(link: &str, index: &'a PageIndex) -> Option<Resolved<'a>> {
+    let key = normalize_key(link);
+    let found = |page: &'a Page, kind| Some(Resolved { page, kind });
+    let fallback = index.link_fallback();
+
+    // 1. Exact page name
+    if let Some(page) = index.named(&key).next() {
+        return found(page, MatchKind::Exact);
+    }
+    if fallback.mode == LinkFallback::Off {
+        return None;
+    }
+
+    // 2. Exact alias
+    if let Some(page) = index.aliased(&key).next() {
+        return found(page, MatchKind::Alias);
+    }
+
+    // 3. Namespace alias expansion: "prefix/suffix" where "prefix" is an alias
+    if let Some((prefix, suffix)) = link.split_once('/') {
+        for owner in index.aliased(&normalize_key(prefix)) {
+            let expanded = normalize_key(&format!("{}/{}", owner.name, suffix));
+            if let Some(page) = index.named(&expanded).next() {
+                return found(page, MatchKind::NamespaceAlias);
+            }
+        }
+    }
+
+    // 4. Journal date titles
+    if let Some(date) = crate::query::parse_date(link) {
+        let journal = journal_output_name(&date.format("%Y-%m-%d").to_string());
+        if let Some(page) = index.with_slug(&journal) {
+            return found(page, MatchKind::Journal);
+        }
+    }
+
+    if fallback.mode == LinkFallback::Exact {
+        return None;
+    }
+
+    // 5. Prefix matching: longest page name ending at a word break in the link
+    let prefix_match = word_prefixes(&key)
+        .filter(|prefix| fallback.accepts(prefix, &key))
+        .find_map(|prefix| index.named(prefix).find(|p| !p.name.starts_with("journals/")));
+    if let Some(page) = prefix_match {
+        return found(page, MatchKind::Prefix);
+    }
+
+    // 6. Fuzzy matching: the most similar page name or alias
+    if fallback.mode == LinkFallback::Fuzzy {
+        return fuzzy_match(&key, index, &fallback).and_then(|page| found(page, MatchKind::Fuzzy));
+    }
+    None
+}
+
+/// Page whose name or alias is most similar to a link key, if similar enough
+fn fuzzy_match<'a>(key: &str, index: &'a PageIndex, fallback: &Fallback) -> Option<&'a Page> {
+    let link = fuzzy_key(key);
+    let mut best: Option<(f64, &Page)> = None;
+    for page in index.iter().filter(|p| !p.name.starts_with("journals/")) {
+        for name in std::iter::once(&page.name).chain(&page.aliases) {
+            let score = similarity(&link, &fuzzy_key(name));
+            if score >= fallback.fuzzy_threshold
+                && fallback.accepts(&normalize_key(name), key)
+                && best.is_none_or(|(s, _)| score > s)
+            {
+                best = Some((score, page));
+            }
+        }
+    }
+    best.map(|(_, page)| page)
+}
+
+/// How closely a link matches a page's name or best alias, 0 to 1, ignoring
+/// case, punctuation and plurals (`C.S. Lewis's books` matches `CS Lewis book` fully)
+pub fn fuzzy_confidence(link: &str, page: &Page) -> f64 {
+    let link = fuzzy_key(link);
+    std::iter::once(&page.name)
+        .chain(&page.aliases)
+        .map(|name| similarity(&link, &fuzzy_key(name)))
+        .fold(0.0, f64::max)
+}
+
+/// Comparison key for fuzzy matching: `normalize_key` without punctuation
+/// (namespace `/` kept, `'s` dropped), each word singular
+fn fuzzy_key(name: &str) -> String {
+    let key = normalize_key(name).replace("'s", "").replace('’', "");
+    let key: String = key.chars().filter(|c| c.is_alphanumeric() || c.is_whitespace() || *c == '/').collect();
+    key.split_whitespace().map(crate::query::singular).collect::<Vec<_>>().join(" ")
+}
+
+/// 1 minus the edit distance over the longer length: 1 for equal strings
+fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1).min(row[j] + 1).min(diagonal + usize::from(ca != cb));
+            diagonal = above;
+        }
+    }
+    1.0 - row[b.len()] as f64 / longest as f64
+}
+
+/// Target to write into a wikilink: the resolved page's output name, or the link as written.
+/// Exact matches keep the author's spelling unless the page has a custom slug.
+pub fn link_target_for(link: &str, index: &PageIndex) -> String {
+    match resolve(link, index) {
+        // Spelled as written, unless other spellings were merged into the page
+        Some(r) if r.kind == MatchKind::Exact && r.page.slug.is_none() && r.page.duplicates.is_empty() => decode(link),
+        Some(r) => link_target(r.page).to_string(),
+        None if link.contains("://") => link.to_string(),
+        // Unresolved links point at their stub page
+        None => safe_path(&decode(link)),
+    }
+}
+
+/// Split a `page#heading` link into the page and the heading, unless the
+/// whole link names a page (`[[C#]]`) or either side is empty. A prefix match
+/// of the whole link doesn't count: `[[Rust guide#Setup]]` is a section of
+/// `Rust guide` (or of `Rust`, by prefix), not a link to `Rust`.
+pub fn split_anchor<'a>(link: &'a str, index: &PageIndex) -> (&'a str, Option<&'a str>) {
+    let names_page = |link: &str| resolve(link, index).is_some_and(|r| !r.kind.is_guess());
+    match link.split_once('#') {
+        Some((page, heading)) if !page.is_empty() && !heading.trim().is_empty() && !names_page(link) => {
+            (page.trim_end(), Some(heading.trim()))
+        }
+        _ => (link, None),
+    }
+}
+
+/// Page to create for a link target that has no page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stub {
+    /// Output name, the same target links to it are rewritten to
+    pub name: String,
+    /// Link text as first written
+    pub title: String,
+    /// Names of the pages linking here, in page-name order
+    pub linked_from: Vec<String>,
+}
+
+/// Stubs for unresolved wikilinks in published pages and journals, one per
+/// comparison key (first spelling in page-name order wins), sorted by name
+pub fn plan_stubs(index: &PageIndex, include_private: bool) -> Vec<Stub> {
+    let mut pages: Vec<&Page> = index
+        .iter()
+        .filter(|p| include_private || !p.is_private())
+        .collect();
+    pages.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut stubs: BTreeMap<String, Stub> = BTreeMap::new();
+    for page in pages {
+        for link in crate::content::unresolved_links(&page.content, index) {
+            let link = link.strip_prefix("pages/").unwrap_or(&link);
+            if !is_stub_target(link) {
+                continue;
+            }
+            let stub = stubs.entry(normalize_key(link)).or_insert_with(|| Stub {
+                name: link_target_for(link, index),
+                title: decode(link),
+                linked_from: Vec::new(),
+            });
+            if !stub.linked_from.contains(&page.name) {
+                stub.linked_from.push(page.name.clone());
+            }
+        }
+    }
+
+    let mut stubs: Vec<Stub> = stubs.into_values().collect();
+    stubs.sort_by(|a, b| a.name.cmp(&b.name));
+    stubs
+}
+
+/// A link resolved by fuzzy matching, for the report
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct FuzzyLink {
+    /// Link as written
+    pub link: String,
+    /// Page it was resolved to
+    pub page: String,
+    /// How closely they match, 0 to 1
+    pub confidence: f64,
+    /// Names of the pages with the link, in page-name order
+    pub linked_from: Vec<String>,
+}
+
+/// Every link of the published pages and journals resolved by fuzzy matching,
+/// least confident first (the likeliest mis-links), then by link
+pub fn fuzzy_links(index: &PageIndex, include_private: bool) -> Vec<FuzzyLink> {
+    let mut pages: Vec<&Page> = index
+        .iter()
+        .filter(|p| include_private || !p.is_private())
+        .collect();
+    pages.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut links: BTreeMap<String, FuzzyLink> = BTreeMap::new();
+    for page in pages {
+        for (link, _) in crate::content::fallback_links(&page.content, index) {
+            let Some(resolved) = resolve(&link, index).filter(|r| r.kind == MatchKind::Fuzzy) else {
+                continue;
+            };
+            let entry = links.entry(normalize_key(&link)).or_insert_with(|| FuzzyLink {
+                confidence: fuzzy_confidence(&link, resolved.page),
+                page: resolved.page.name.clone(),
+                link: decode(&link),
+                linked_from: Vec::new(),
+            });
+            if !entry.linked_from.contains(&page.name) {
+                entry.linked_from.push(page.name.clone());
+            }
+        }
+    }
+
+    let mut links: Vec<FuzzyLink> = links.into_values().collect();
+    links.sort_by(|a, b| a.confidence.total_cmp(&b.confidence).then_with(|| a.link.cmp(&b.link)));
+    links
+}
+
+/// Write `_fuzzy_links.json`
+pub fn write_fuzzy_report(output_dir: &Path, links: &[FuzzyLink]) -> Result<()> {
+    fs::write(output_dir.join(FUZZY_LINKS_FILE), serde_json::to_string_pretty(links)?)?;
+    Ok(())
+}
+
+/// Whether an unresolved link should get a stub page: not an internal folder,
+/// anchor, URL or bare date, and of reasonable length
+fn is_stub_target(link: &str) -> bool {
+    if ["journals/", "favorites/", "assets/"].iter().any(|dir| link.starts_with(dir)) {
+        return false;
+    }
+    if link.starts_with('#') || link.starts_with("http") || link.contains("://") {
+        return false;
+    }
+    if link.chars().count() <= 1 || link.len() > 200 {
+        return false;
+    }
+    // Dates like 2024-01-15, 2024_01_15, 2024 01 15
+    let is_date = (8..=12).contains(&link.len())
+        && link.chars().all(|c| c.is_ascii_digit() || c == '-' || c == '_' || c == ' ');
+    !is_date
+}
+
+/// Normalize a user-provided slug: trim whitespace and surrounding slashes.
+/// Slugs climbing out of the output (`../page`) are ignored.
+fn clean_slug(slug: &str) -> Option<String> {
+    let slug = slug.trim().trim_matches('"').trim_matches('/').trim();
+    if slug.is_empty() || !is_contained(slug) {
+        None
+    } else {
+        Some(safe_path(&slug.replace(' ', "-")))
+    }
+}
+
+/// Slug history for one page
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct SlugEntry {
+    pub current: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub previous: Vec<String>,
+}
+
+/// Persisted page → slug map, so pages keep their old URLs after a rename or slug change
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct SlugMap {
+    #[serde(flatten)]
+    pub entries: BTreeMap<String, SlugEntry>,
+}
+
+impl SlugMap {
+    /// Load the slug map (missing or unreadable file gives an empty map)
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    /// Save the slug map as pretty JSON (sorted keys for stable diffs)
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Record current slugs and fill each page's `previous_slugs`.
+    ///
+    /// A page whose slug changed keeps its old slug as a previous slug. A page
+    /// that lists a former page name in `alias::` inherits that page's slugs,
+    /// which covers renames done in Logseq (old name kept as alias).
+    pub fn update(&mut self, index: &mut PageIndex) {
+        let live: HashSet<String> = index.iter().map(|p| p.name_lower.clone()).collect();
+        let mut taken_over: Vec<String> = Vec::new();
+
+        for page in index.pages_mut().filter(|p| !p.name.starts_with("journals/")) {
+            let current = link_target(page).to_string();
+            let mut previous: Vec<String> = Vec::new();
+
+            let mut keys = vec![page.name_lower.clone()];
+            keys.extend(page.aliases.iter().map(|a| a.to_lowercase()));
+
+            for key in &keys {
+                // Only take over an alias entry if that page no longer exists under its own name
+                if key != &page.name_lower && live.contains(key) {
+                    continue;
+                }
+                if let Some(entry) = self.entries.get(key) {
+                    if key != &page.name_lower {
+                        taken_over.push(key.clone());
+                    }
+                    for slug in entry.previous.iter().chain(std::iter::once(&entry.current)) {
+                        if slug != &current && !previous.contains(slug) {
+                            previous.push(slug.clone());
+                        }
+                    }
+                }
+            }
+
+            page.previous_slugs = previous.clone();
+            self.entries.insert(
+                page.name_lower.clone(),
+                SlugEntry { current, previous },
+            );
+        }
+
+        // Renamed pages now live under their new name
+        for key in taken_over {
+            self.entries.remove(&key);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Small deterministic generator (splitmix64): the same seed always gives the
+/// same numbers, so seeded builds and generated graphs are reproducible
+pub struct Rng(pub u64);
+
+impl Rng {
+    /// A generator for one use of a seed: `seed` mixed with a stable hash of
+    /// `salt`, so each query block of a build draws its own numbers
+    pub fn salted(seed: u64, salt: &str) -> Self {
+        // FNV-1a: stable across Rust versions, unlike the std hashers
+        let hash = salt
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100_0000_01b3));
+        Rng(seed ^ hash)
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        ((z ^ (z >> 31)) % n.max(1) as u64) as usize
+    }
+
+    /// Fisher-Yates shuffle
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+/// Seed of a build without `--seed`: different every run
+pub fn time_seed() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64)
+}
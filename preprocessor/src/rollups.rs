@@ -0,0 +1,90 @@
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::escape;
+use crate::resolver;
+
+/// Period a roll-up page covers (`--journal-rollups`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Rollup {
+    /// ISO week: `journals/2025-W03`
+    Week,
+    /// Calendar month: `journals/2025-01`
+    Month,
+}
+
+impl Rollup {
+    /// Output name (within journals/) of the period containing `date`, sortable by period
+    pub fn key(self, date: NaiveDate) -> String {
+        match self {
+            Rollup::Week => {
+                let week = date.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            }
+            Rollup::Month => date.format("%Y-%m").to_string(),
+        }
+    }
+
+    /// Title of the period containing `date`: "Week 3, 2025", "January 2025"
+    pub fn title(self, date: NaiveDate) -> String {
+        match self {
+            Rollup::Week => {
+                let week = date.iso_week();
+                format!("Week {}, {}", week.week(), week.year())
+            }
+            Rollup::Month => date.format("%B %Y").to_string(),
+        }
+    }
+}
+
+/// Write a roll-up page for every period with published journals, embedding
+/// the period's days in order, with links to the previous and next periods.
+/// `days` are the published journal dates (`YYYY-MM-DD`); `output_dir` is the
+/// journals/ folder. Returns the number of pages written.
+pub fn write(output_dir: &Path, days: &[String], rollups: &[Rollup]) -> Result<usize> {
+    let mut dates: Vec<NaiveDate> = days.iter().filter_map(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()).collect();
+    dates.sort();
+    dates.dedup();
+
+    let mut written = 0;
+    for &rollup in rollups {
+        let mut periods: BTreeMap<String, Vec<NaiveDate>> = BTreeMap::new();
+        for &date in &dates {
+            periods.entry(rollup.key(date)).or_default().push(date);
+        }
+        let keys: Vec<&String> = periods.keys().collect();
+        for (i, (key, days)) in periods.iter().enumerate() {
+            let previous = i.checked_sub(1).map(|j| (keys[j], &periods[keys[j]]));
+            let next = keys.get(i + 1).map(|k| (*k, &periods[*k]));
+            let content = page(rollup, days, previous, next);
+            fs::write(output_dir.join(format!("{}.md", key)), content)?;
+            written += 1;
+        }
+    }
+    Ok(written)
+}
+
+type Period<'a> = (&'a String, &'a Vec<NaiveDate>);
+
+fn page(rollup: Rollup, days: &[NaiveDate], previous: Option<Period>, next: Option<Period>) -> String {
+    let mut content = format!("---\ntitle: {}\n---\n\n", escape::yaml_quoted(&rollup.title(days[0])));
+
+    let link = |(key, days): Period| format!("[[{}|{}]]", resolver::journal_output_name(key), rollup.title(days[0]));
+    let nav: Vec<String> = [previous.map(|p| format!("← {}", link(p))), next.map(|p| format!("{} →", link(p)))]
+        .into_iter()
+        .flatten()
+        .collect();
+    if !nav.is_empty() {
+        content.push_str(&format!("{}\n\n", nav.join(" · ")));
+    }
+
+    for day in days {
+        let date = day.format("%Y-%m-%d").to_string();
+        let target = resolver::journal_output_name(&date);
+        content.push_str(&format!("## [[{}|{}]]\n\n![[{}]]\n\n", target, day.format("%A, %B %-d"), target));
+    }
+    content
+}
@@ -0,0 +1,238 @@
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+lazy_static! {
+    // Character references that can spell out a URL scheme: &#106; &#x6A; &colon; &Tab;
+    static ref ENTITY_RE: Regex = Regex::new(r"(?i)&#x([0-9a-f]+);?|&#(\d+);?|&(colon|tab|newline);").unwrap();
+
+    // Markdown autolinks, which look like tags: <https://x.io>, <me@x.io>
+    static ref AUTOLINK_RE: Regex = Regex::new(
+        r"^<(?:([a-zA-Z][a-zA-Z0-9+.-]{1,31}:[^\s<>]*)|[a-zA-Z0-9.!#$%&'*+/=?^_`{|}~-]+@[a-zA-Z0-9](?:[a-zA-Z0-9-]{0,61}[a-zA-Z0-9])?(?:\.[a-zA-Z0-9](?:[a-zA-Z0-9-]{0,61}[a-zA-Z0-9])?)*)>"
+    ).unwrap();
+}
+
+/// Elements removed together with their content
+const DROPPED_TAGS: &[&str] =
+    &["script", "style", "template", "noscript", "textarea", "title", "xmp", "iframe", "noembed", "noframes"];
+
+/// Tags kept by `--sanitize-html`; any other tag is removed (its text is kept)
+const ALLOWED_TAGS: &[&str] = &[
+    "a", "abbr", "b", "blockquote", "br", "caption", "cite", "code", "col", "colgroup", "dd", "del", "details",
+    "dfn", "div", "dl", "dt", "em", "figcaption", "figure", "h1", "h2", "h3", "h4", "h5", "h6", "hr", "i", "img",
+    "ins", "kbd", "li", "mark", "ol", "p", "pre", "q", "rp", "rt", "ruby", "s", "samp", "small", "span", "strong",
+    "sub", "summary", "sup", "table", "tbody", "td", "tfoot", "th", "thead", "time", "tr", "u", "ul", "var",
+];
+
+/// Attributes kept on any allowed tag
+const GLOBAL_ATTRS: &[&str] = &["class", "id", "title", "lang", "dir"];
+
+/// Attributes kept on specific tags
+const TAG_ATTRS: &[(&str, &[&str])] = &[
+    ("a", &["href"]),
+    ("img", &["src", "alt", "width", "height"]),
+    ("td", &["colspan", "rowspan", "align"]),
+    ("th", &["colspan", "rowspan", "align", "scope"]),
+    ("col", &["span"]),
+    ("ol", &["start", "type", "reversed"]),
+    ("li", &["value"]),
+    ("details", &["open"]),
+    ("time", &["datetime"]),
+    ("blockquote", &["cite"]),
+    ("q", &["cite"]),
+    ("del", &["cite", "datetime"]),
+    ("ins", &["cite", "datetime"]),
+];
+
+/// URL schemes allowed in `href`/`src`/`cite` (relative URLs and anchors are always allowed)
+const ALLOWED_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Reduce raw HTML to an allowlist of tags and attributes (`--sanitize-html`):
+/// other tags are removed but their text kept, `script`/`style` and similar
+/// elements are removed with their content, comments are dropped, and URLs
+/// must be relative or use http(s)/mailto. Text outside tags is left as is,
+/// so this also works on markdown with inline HTML.
+///
+/// Tags are read the way browsers read them (attributes split by `/`,
+/// unquoted values running to the next space or `>`), so nothing a browser
+/// would take for a tag gets through unread; a `<` that doesn't start a
+/// complete tag, comment or autolink is escaped.
+pub fn html(fragment: &str) -> String {
+    let mut out = String::with_capacity(fragment.len());
+    let mut rest = fragment;
+    while let Some(open) = rest.find('<') {
+        out.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let next = rest[1..].chars().next();
+
+        if let Some(caps) = AUTOLINK_RE.captures(rest) {
+            if caps.get(1).is_none_or(|url| safe_url(url.as_str())) {
+                out.push_str(&caps[0]);
+                rest = &rest[caps[0].len()..];
+                continue;
+            }
+        }
+        let skip = if rest.starts_with("<!--") {
+            // Comments (`<!-->` is an empty one); unterminated, they run to the end
+            Some(rest[2..].find("-->").map_or(rest.len(), |end| end + 5))
+        } else if matches!(next, Some('!' | '?'))
+            || (next == Some('/') && !rest[2..].starts_with(|c: char| c.is_ascii_alphabetic()))
+        {
+            // Doctypes, processing instructions and other bogus comments, up to `>`
+            Some(rest.find('>').map_or(rest.len(), |end| end + 1))
+        } else {
+            None
+        };
+        if let Some(skip) = skip {
+            rest = &rest[skip..];
+            continue;
+        }
+        if !next.is_some_and(|c| c.is_ascii_alphabetic() || c == '/') {
+            out.push_str("&lt;");
+            rest = &rest[1..];
+            continue;
+        }
+        let Some(tag) = read_tag(rest) else {
+            // Never closed: a browser would swallow the rest; keep it as text
+            out.push_str("&lt;");
+            rest = &rest[1..];
+            continue;
+        };
+        rest = &rest[tag.len..];
+
+        if !tag.closing && DROPPED_TAGS.contains(&tag.name.as_str()) {
+            rest = after_end_tag(rest, &tag.name);
+            continue;
+        }
+        if ALLOWED_TAGS.contains(&tag.name.as_str()) {
+            out.push_str(&render(&tag));
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// A start or end tag as read from the source
+struct Tag {
+    name: String,
+    closing: bool,
+    attrs: Vec<(String, String)>,
+    self_closing: bool,
+    /// Bytes of source it takes, `<` to `>`
+    len: usize,
+}
+
+/// Read the tag at the start of `src` (which starts with `<`); `None` if it
+/// isn't closed by a `>`
+fn read_tag(src: &str) -> Option<Tag> {
+    let is_space = |c: char| matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0c');
+    let closing = src[1..].starts_with('/');
+    let mut pos = if closing { 2 } else { 1 };
+    let name_len = src[pos..].find(|c: char| is_space(c) || c == '/' || c == '>')?;
+    let name = src[pos..pos + name_len].to_lowercase();
+    pos += name_len;
+
+    let mut attrs = Vec::new();
+    let self_closing = loop {
+        let rest = &src[pos..];
+        let trimmed = rest.trim_start_matches(|c: char| is_space(c) || c == '/');
+        let skipped = &rest[..rest.len() - trimmed.len()];
+        pos += skipped.len();
+        let first = trimmed.chars().next()?;
+        if first == '>' {
+            break skipped.ends_with('/');
+        }
+        // A leading `=` belongs to the name, as in browsers
+        let name_len = trimmed[first.len_utf8()..]
+            .find(|c: char| is_space(c) || matches!(c, '/' | '>' | '='))
+            .map_or(trimmed.len(), |end| end + first.len_utf8());
+        let attr = trimmed[..name_len].to_lowercase();
+        pos += name_len;
+
+        let rest = &src[pos..];
+        let mut value = String::new();
+        if let Some(unspaced) = rest.trim_start_matches(is_space).strip_prefix('=') {
+            let unspaced = unspaced.trim_start_matches(is_space);
+            pos += rest.len() - unspaced.len();
+            pos += match unspaced.chars().next()? {
+                quote @ ('"' | '\'') => {
+                    let end = unspaced[1..].find(quote)?;
+                    value = unspaced[1..end + 1].to_string();
+                    end + 2
+                }
+                _ => {
+                    let end = unspaced.find(|c: char| is_space(c) || c == '>')?;
+                    value = unspaced[..end].to_string();
+                    end
+                }
+            };
+        }
+        attrs.push((attr, value));
+    };
+    Some(Tag { name, closing, attrs, self_closing, len: pos + 1 })
+}
+
+/// An allowed tag with only its allowed attributes and safe URLs, re-quoted
+fn render(tag: &Tag) -> String {
+    if tag.closing {
+        return format!("</{}>", tag.name);
+    }
+    let allowed = TAG_ATTRS.iter().find(|(t, _)| *t == tag.name).map_or(&[][..], |(_, attrs)| *attrs);
+    let mut out = format!("<{}", tag.name);
+    for (name, value) in &tag.attrs {
+        if !GLOBAL_ATTRS.contains(&name.as_str()) && !allowed.contains(&name.as_str()) {
+            continue;
+        }
+        if matches!(name.as_str(), "href" | "src" | "cite") && !safe_url(value) {
+            continue;
+        }
+        // Entities stay as written: values are already HTML, only quotes and brackets need care
+        let value = value.replace('"', "&quot;").replace('<', "&lt;").replace('>', "&gt;");
+        out.push_str(&format!(" {}=\"{}\"", name, value));
+    }
+    if tag.self_closing {
+        out.push('/');
+    }
+    out.push('>');
+    out
+}
+
+/// The source after the end tag closing a dropped element (`</script>`); an
+/// element never closed takes the rest with it
+fn after_end_tag<'a>(src: &'a str, name: &str) -> &'a str {
+    let lower = src.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(at) = lower[from..].find("</").map(|i| from + i) {
+        let after = &lower[at + 2..];
+        if after.starts_with(name) && after[name.len()..].starts_with(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>') {
+            return src[at..].find('>').map_or("", |end| &src[at + end + 1..]);
+        }
+        from = at + 2;
+    }
+    ""
+}
+
+/// Relative, anchor or allowed-scheme URL (entities and whitespace can't hide a scheme)
+fn safe_url(url: &str) -> bool {
+    let decoded: String = ENTITY_RE
+        .replace_all(url, |caps: &Captures| {
+            let code = match (caps.get(1), caps.get(2)) {
+                (Some(hex), _) => u32::from_str_radix(hex.as_str(), 16).ok(),
+                (_, Some(dec)) => dec.as_str().parse().ok(),
+                _ => Some(match caps[3].to_lowercase().as_str() {
+                    "colon" => ':',
+                    "tab" => '\t',
+                    _ => '\n',
+                } as u32),
+            };
+            code.and_then(char::from_u32).map_or(String::new(), String::from)
+        })
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect();
+    match decoded.split_once(':') {
+        Some((scheme, _)) if !scheme.contains(['/', '?', '#']) => {
+            ALLOWED_SCHEMES.contains(&scheme.to_lowercase().as_str())
+        }
+        _ => true,
+    }
+}
@@ -0,0 +1,208 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use std::fs;
+use std::path::Path;
+
+use crate::page::{Page, PageIndex};
+use crate::previews;
+use crate::resolver;
+
+/// Calendar of scheduled and deadline items written to the output root (`--events`)
+pub const EVENTS_FILE: &str = "events.json";
+
+lazy_static! {
+    // SCHEDULED: <2025-01-01 Wed 10:00 .+1w> and DEADLINE: <...> (groups: kind, timestamp)
+    static ref SCHEDULE_RE: Regex = Regex::new(r"(SCHEDULED|DEADLINE):\s*<([^>]+)>").unwrap();
+
+    // Logseq/Org timestamp: date, optional weekday and time, optional repeater
+    // (groups: date, time, repeater kind, count, unit)
+    static ref TIMESTAMP_RE: Regex = Regex::new(
+        r"^(\d{4}-\d{2}-\d{2})(?:\s+\p{L}{2,9}\.?)?(?:\s+(\d{1,2}:\d{2}))?(?:\s+(\.\+|\+\+|\+)(\d+)([hdwmy]))?\s*$"
+    ).unwrap();
+
+    // Task marker at the start of a block
+    static ref MARKER_RE: Regex = Regex::new(r"^\s*-\s+(TODO|DOING|DONE|LATER|NOW|WAITING|CANCELED|CANCELLED)\s").unwrap();
+}
+
+/// Which kind of date a block carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Scheduled,
+    Deadline,
+}
+
+/// A parsed `<2025-01-01 Wed 10:00 .+1w>` timestamp
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timestamp {
+    pub date: NaiveDate,
+    /// `HH:MM`, when given
+    pub time: Option<String>,
+    /// Repeater as written (`+1w`, `++2d`, `.+1m`)
+    pub repeater: Option<String>,
+    /// The repeater in words (`every week after completion`)
+    pub repeat_text: Option<String>,
+    /// The timestamp without its repeater (`2025-01-01 Wed 10:00`)
+    pub display: String,
+}
+
+/// A SCHEDULED or DEADLINE date found in a block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    pub kind: Kind,
+    pub timestamp: Timestamp,
+    /// Task marker of the block (`TODO`, `DONE`, ...)
+    pub marker: Option<String>,
+    /// Plain text of the block's first line
+    pub text: String,
+}
+
+impl Item {
+    /// Finished tasks don't belong on a calendar of what's coming
+    pub fn is_done(&self) -> bool {
+        matches!(self.marker.as_deref(), Some("DONE" | "CANCELED" | "CANCELLED"))
+    }
+}
+
+/// One entry of `events.json`
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Event {
+    pub kind: Kind,
+    /// `YYYY-MM-DD`
+    pub date: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub marker: Option<String>,
+    pub text: String,
+    /// Page name, and its Quartz slug to link to
+    pub page: String,
+    pub slug: String,
+}
+
+impl Timestamp {
+    /// Parse the text between `<` and `>`; `None` if it doesn't start with a date
+    pub fn parse(text: &str) -> Option<Self> {
+        let caps = TIMESTAMP_RE.captures(text.trim())?;
+        let date = NaiveDate::parse_from_str(&caps[1], "%Y-%m-%d").ok()?;
+        let repeater = caps.get(3).map(|kind| format!("{}{}{}", kind.as_str(), &caps[4], &caps[5]));
+        let repeat_text = caps.get(3).map(|kind| repeat_text(kind.as_str(), &caps[4], &caps[5]));
+        let display = match caps.get(3) {
+            Some(kind) => text.trim()[..kind.start()].trim_end().to_string(),
+            None => text.trim().to_string(),
+        };
+        Some(Timestamp {
+            date,
+            time: caps.get(2).map(|t| t.as_str().to_string()),
+            repeater,
+            repeat_text,
+            display,
+        })
+    }
+}
+
+/// A repeater in words: `+1w` → `every week`, `.+2d` → `every 2 days after completion`,
+/// `++1m` → `every month, skipping missed dates`
+fn repeat_text(kind: &str, count: &str, unit: &str) -> String {
+    let unit = match unit {
+        "h" => "hour",
+        "d" => "day",
+        "w" => "week",
+        "m" => "month",
+        _ => "year",
+    };
+    let every = match count {
+        "1" => format!("every {}", unit),
+        n => format!("every {} {}s", n, unit),
+    };
+    match kind {
+        ".+" => format!("{} after completion", every),
+        "++" => format!("{}, skipping missed dates", every),
+        _ => every,
+    }
+}
+
+/// Replace `SCHEDULED: <...>` / `DEADLINE: <...>` with readable dates
+/// (`📅 Scheduled: 2025-01-01 Wed · 🔁 every week`)
+pub fn render(content: &str) -> String {
+    SCHEDULE_RE
+        .replace_all(content, |caps: &Captures| {
+            let label = if &caps[1] == "SCHEDULED" { "📅 Scheduled" } else { "⏰ Deadline" };
+            match Timestamp::parse(&caps[2]) {
+                Some(Timestamp { display, repeat_text: Some(repeat), .. }) => {
+                    format!("{}: {} · 🔁 {}", label, display, repeat)
+                }
+                Some(Timestamp { display, .. }) => format!("{}: {}", label, display),
+                None => format!("{}: {}", label, &caps[2]),
+            }
+        })
+        .into_owned()
+}
+
+/// SCHEDULED and DEADLINE dates in page content, with the block each belongs to
+pub fn items(content: &str) -> Vec<Item> {
+    let mut items = Vec::new();
+    let mut block = "";
+    for line in content.lines() {
+        if line.trim_start().starts_with("- ") {
+            block = line;
+        }
+        for caps in SCHEDULE_RE.captures_iter(line) {
+            let Some(timestamp) = Timestamp::parse(&caps[2]) else {
+                continue;
+            };
+            let first_line = SCHEDULE_RE.replace_all(block, "");
+            items.push(Item {
+                kind: if &caps[1] == "SCHEDULED" { Kind::Scheduled } else { Kind::Deadline },
+                timestamp,
+                marker: MARKER_RE.captures(block).map(|m| m[1].to_string()),
+                text: previews::plain_text(&first_line),
+            });
+        }
+    }
+    items
+}
+
+/// Does any block of the page have a SCHEDULED or DEADLINE date in the range?
+pub fn has_date_between(page: &Page, start: NaiveDate, end: NaiveDate) -> bool {
+    items(&page.content).iter().any(|item| (start..=end).contains(&item.timestamp.date))
+}
+
+/// Open (not done) scheduled and deadline items of all published pages, by date
+pub fn events(index: &PageIndex, include_private: bool) -> Vec<Event> {
+    let mut events: Vec<Event> = index
+        .iter()
+        .filter(|p| include_private || !p.is_private())
+        .flat_map(|page| {
+            items(&page.content)
+                .into_iter()
+                .filter(|item| !item.is_done())
+                .map(move |item| Event {
+                    kind: item.kind,
+                    date: item.timestamp.date.format("%Y-%m-%d").to_string(),
+                    time: item.timestamp.time,
+                    repeat: item.timestamp.repeater,
+                    repeat_text: item.timestamp.repeat_text,
+                    marker: item.marker,
+                    text: item.text,
+                    page: page.title(),
+                    slug: resolver::site_path(page),
+                })
+        })
+        .collect();
+    events.sort_by(|a, b| (&a.date, &a.time, &a.page).cmp(&(&b.date, &b.time, &b.page)));
+    events
+}
+
+/// Write `events.json`. Returns the number of events.
+pub fn write(output_dir: &Path, index: &PageIndex, include_private: bool) -> Result<usize> {
+    let events = events(index, include_private);
+    fs::write(output_dir.join(EVENTS_FILE), serde_json::to_string_pretty(&events)?)?;
+    Ok(events.len())
+}
@@ -0,0 +1,118 @@
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+
+use crate::escape;
+use crate::page::{Page, PageIndex};
+use crate::properties;
+use crate::resolver;
+use crate::warnings;
+
+/// Folder of the generated series index pages (`series/Rust Tutorial`)
+pub const SERIES_DIR: &str = "series";
+
+/// Series a page belongs to (`series:: [[Rust Tutorial]]` → `Rust Tutorial`).
+/// Journals aren't part of series.
+pub fn series_name(page: &Page) -> Option<String> {
+    if page.name.starts_with("journals/") {
+        return None;
+    }
+    let name = properties::plain(page.properties.get("series")?);
+    (!name.is_empty()).then_some(name)
+}
+
+/// Published, listed pages of a series in reading order: by `series-order::`
+/// (pages without one after those with one), then created date, then name
+pub fn members<'a>(index: &'a PageIndex, series: &str, include_private: bool) -> Vec<&'a Page> {
+    let mut pages: Vec<&Page> = index
+        .in_series(&series.to_lowercase())
+        .filter(|p| !p.is_unlisted() && (include_private || !p.is_private()))
+        .collect();
+    pages.sort_by(|a, b| {
+        let (order_a, order_b) = (order(a), order(b));
+        order_b
+            .is_some()
+            .cmp(&order_a.is_some())
+            .then_with(|| order_a.partial_cmp(&order_b).unwrap_or(std::cmp::Ordering::Equal))
+            .then_with(|| a.created.cmp(&b.created))
+            .then_with(|| a.name_lower.cmp(&b.name_lower))
+    });
+    pages
+}
+
+/// `series-order::` as a number (`2`, `2.5`)
+fn order(page: &Page) -> Option<f64> {
+    page.properties.get("series-order")?.trim().parse().ok()
+}
+
+/// Output name of a series index page
+pub fn index_name(series: &str) -> String {
+    format!("{}/{}", SERIES_DIR, series)
+}
+
+/// Navigation appended to a page of a series: its place in the series, a link
+/// to the series index and links to the previous and next pages
+pub fn navigation(page: &Page, index: &PageIndex, include_private: bool) -> Option<String> {
+    let pages = members(index, &series_name(page)?, include_private);
+    let position = pages.iter().position(|p| p.name == page.name)?;
+    // Named as on the index page, whatever the case on this page
+    let series = series_name(pages[0])?;
+
+    let mut nav = format!(
+        "\n---\n\nPart {} of {} in [[{}|{}]]\n",
+        position + 1,
+        pages.len(),
+        index_name(&series),
+        series
+    );
+    let previous = position.checked_sub(1).map(|i| pages[i]);
+    let next = pages.get(position + 1);
+    let links: Vec<String> = [
+        previous.map(|p| format!("← [[{}|{}]]", resolver::link_target(p), p.title())),
+        next.map(|p| format!("[[{}|{}]] →", resolver::link_target(p), p.title())),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if !links.is_empty() {
+        nav.push_str(&format!("\n{}\n", links.join(" · ")));
+    }
+    Some(nav)
+}
+
+/// Index page of a series: its pages in reading order
+pub fn index_page(series: &str, pages: &[&Page]) -> String {
+    let mut content = format!("---\ntitle: {}\n---\n\n", escape::yaml_quoted(series));
+    for (i, page) in pages.iter().enumerate() {
+        content.push_str(&format!("{}. [[{}|{}]]\n", i + 1, resolver::link_target(page), page.title()));
+    }
+    content
+}
+
+/// Write an index page for each series in `series/`. A page of the graph at
+/// the same path is kept (with a warning). Returns the number written.
+pub fn write_indexes(output_dir: &Path, index: &PageIndex, include_private: bool) -> Result<usize> {
+    let mut names: Vec<&str> = index.series_names().collect();
+    names.sort_unstable();
+
+    let mut written = 0;
+    for key in names {
+        let pages = members(index, key, include_private);
+        // Named as written on its first page
+        let Some(series) = pages.first().and_then(|p| series_name(p)) else {
+            continue;
+        };
+        let path = resolver::output_path(output_dir, &index_name(&series));
+        if path.exists() {
+            warnings::global().warn("series", &format!("{} is taken by another page; no series index written", index_name(&series)), None);
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, index_page(&series, &pages))?;
+        written += 1;
+    }
+    Ok(written)
+}
+
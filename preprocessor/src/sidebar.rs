@@ -0,0 +1,98 @@
+use anyhow::Result;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+use crate::favorites::NavNode;
+use crate::outline;
+use crate::page::{self, PageIndex};
+use crate::previews;
+use crate::resolver;
+
+/// Link tree of the graph's `Contents` page, for a sidebar component
+pub const SIDEBAR_FILE: &str = "sidebar.json";
+
+/// Logseq's sidebar page
+pub const CONTENTS_PAGE: &str = "contents";
+
+lazy_static! {
+    // Block properties (collapsed:: true, id:: ...)
+    static ref PROPERTY_LINE_RE: Regex = Regex::new(r"^[\w-]+::").unwrap();
+
+    // First link of a block: [[page]], #[[page]], #tag or [label](https://...)
+    // (groups: wikilink, tag, url)
+    static ref LINK_RE: Regex = Regex::new(
+        r"\[\[([^\]]+)\]\]|(?:^|\s)#([^\s#\[\],.!?;:]+)|\[[^\]]*\]\((https?://[^)\s]+)\)"
+    ).unwrap();
+}
+
+/// Write `sidebar.json` from the `Contents` page: one node per block, nested as
+/// in the outline, linking to the block's first page link (or external URL).
+/// Returns the number of nodes, or `None` without a published Contents page.
+pub fn write(output_dir: &Path, index: &PageIndex, include_private: bool) -> Result<Option<usize>> {
+    let Some(contents) = resolver::find_page(CONTENTS_PAGE, index) else {
+        return Ok(None);
+    };
+    if !include_private && contents.is_private() {
+        return Ok(None);
+    }
+    let (_, body) = page::parse_properties(&contents.content);
+    let nodes = tree(&body, index, include_private);
+    fs::write(output_dir.join(SIDEBAR_FILE), serde_json::to_string_pretty(&nodes)?)?;
+    Ok(Some(count(&nodes)))
+}
+
+/// Nodes for the blocks of an outline. A block's title is its plain text;
+/// links to private or missing pages are left out.
+pub fn tree(content: &str, index: &PageIndex, include_private: bool) -> Vec<NavNode> {
+    let mut root: Vec<NavNode> = Vec::new();
+    // Indents of the blocks on the path to the current one
+    let mut open: Vec<usize> = Vec::new();
+    let mut in_code = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        let block = trimmed.trim_start_matches("- ").trim();
+        if outline::is_fence(line) {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code || !(trimmed.starts_with("- ") || trimmed == "-") || PROPERTY_LINE_RE.is_match(block) {
+            continue;
+        }
+        let title = previews::plain_text(block);
+        if title.is_empty() {
+            continue;
+        }
+
+        let indent = line.len() - trimmed.len();
+        while open.last().is_some_and(|&i| i >= indent) {
+            open.pop();
+        }
+        let mut level = &mut root;
+        for _ in 0..open.len() {
+            level = &mut level.last_mut().expect("open block").children;
+        }
+        level.push(NavNode { title, path: link(block, index, include_private), children: Vec::new() });
+        open.push(indent);
+    }
+    root
+}
+
+/// Target of a block's first link: a page's output path or an external URL
+fn link(block: &str, index: &PageIndex, include_private: bool) -> Option<String> {
+    let caps = LINK_RE.captures(block)?;
+    if let Some(url) = caps.get(3) {
+        return Some(url.as_str().to_string());
+    }
+    let name = caps.get(1).or(caps.get(2))?.as_str();
+    let name = name.split_once('|').map_or(name, |(target, _)| target);
+    let page = resolver::resolve(name, index)?.page;
+    (include_private || !page.is_private()).then(|| resolver::link_target(page).to_string())
+}
+
+fn count(nodes: &[NavNode]) -> usize {
+    nodes.iter().map(|n| 1 + count(&n.children)).sum()
+}
+
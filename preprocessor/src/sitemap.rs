@@ -0,0 +1,91 @@
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+
+use crate::escape;
+use crate::page::PageIndex;
+use crate::resolver;
+
+/// Sitemap and crawler rules written to the output root (`--sitemap`)
+pub const SITEMAP_FILE: &str = "sitemap.xml";
+pub const ROBOTS_FILE: &str = "robots.txt";
+
+/// Site-relative paths of everything published and listed: the home page,
+/// the journals index, then pages and journals with their modified dates,
+/// sorted by path. Private and unlisted pages and drafts are left out.
+pub fn urls(index: &PageIndex, include_private: bool) -> Vec<(String, Option<String>)> {
+    let mut pages: Vec<(String, Option<String>)> = index
+        .iter()
+        .filter(|p| (include_private || !p.is_private()) && !p.is_unlisted())
+        .map(|p| (resolver::site_path(p), p.modified.clone()))
+        .collect();
+    pages.sort();
+    pages.dedup_by(|a, b| a.0 == b.0);
+
+    let mut urls = vec![(String::new(), None)];
+    if index.iter().any(|p| p.name.starts_with("journals/")) {
+        urls.push(("journals/".to_string(), None));
+    }
+    urls.extend(pages);
+    urls
+}
+
+/// `sitemap.xml` for the site at `base_url`
+pub fn sitemap(index: &PageIndex, base_url: &str, include_private: bool) -> String {
+    let base = base_url.trim_end_matches('/');
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for (path, modified) in urls(index, include_private) {
+        xml.push_str(&format!("  <url>\n    <loc>{}/{}</loc>\n", escape::html(base), escape::html(&encode_path(&path))));
+        if let Some(modified) = modified {
+            xml.push_str(&format!("    <lastmod>{}</lastmod>\n", modified));
+        }
+        xml.push_str("  </url>\n");
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+/// `robots.txt`: everything may be crawled except unlisted pages and drafts
+/// (published, or linked from other pages, but not meant to be found), with
+/// the sitemap's address
+pub fn robots(index: &PageIndex, base_url: &str) -> String {
+    let base = base_url.trim_end_matches('/');
+    // Rules are matched against the URL path, which includes the site's base path
+    let prefix = base.split_once("://").map_or(base, |(_, rest)| rest);
+    let prefix = prefix.find('/').map_or("", |i| &prefix[i..]);
+
+    let mut hidden: Vec<String> = index.iter().filter(|p| p.is_unlisted()).map(resolver::site_path).collect();
+    hidden.sort();
+    hidden.dedup();
+
+    let mut robots = String::from("User-agent: *\n");
+    if hidden.is_empty() {
+        robots.push_str("Disallow:\n");
+    }
+    for path in hidden {
+        // `$` ends the match so `draft` doesn't also block `draft-notes`
+        robots.push_str(&format!("Disallow: {}/{}$\n", prefix, encode_path(&path)));
+    }
+    robots.push_str(&format!("\nSitemap: {}/{}\n", base, SITEMAP_FILE));
+    robots
+}
+
+/// Write `sitemap.xml` and `robots.txt`. Returns the number of URLs in the sitemap.
+pub fn write(output_dir: &Path, index: &PageIndex, base_url: &str, include_private: bool) -> Result<usize> {
+    fs::write(output_dir.join(SITEMAP_FILE), sitemap(index, base_url, include_private))?;
+    fs::write(output_dir.join(ROBOTS_FILE), robots(index, base_url))?;
+    Ok(urls(index, include_private).len())
+}
+
+/// Percent-encode a site path for a URL (`/` kept, non-ASCII as UTF-8 bytes)
+fn encode_path(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => out.push(byte as char),
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
@@ -0,0 +1,104 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+
+use crate::escape;
+use crate::frontmatter;
+use crate::outline;
+use crate::previews;
+
+/// Words in a derived `description` (`--description-words`)
+pub const DESCRIPTION_WORDS: usize = 40;
+
+lazy_static! {
+    // First markdown image: ![alt](src) or ![alt|400x300](src) (group: src)
+    static ref IMAGE_RE: Regex = Regex::new(r"!\[[^\]]*\]\(([^)\s]+)(?:\s+[^)]*)?\)").unwrap();
+
+    // Lines that carry no prose of their own: headings, tables, HTML, embeds,
+    // callout headers (quote marker already removed), rules, properties
+    static ref SKIPPED_LINE_RE: Regex = Regex::new(
+        r"^(?:#{1,6}\s|\||<|!\[\[|\[!\w|---+\s*$|\*\*\*+\s*$|[\w-]+::)"
+    ).unwrap();
+}
+
+/// Image file extensions a social card can show
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "avif", "svg"];
+
+/// Add `description` (unless `description::` is set) and `socialImage` to generated
+/// frontmatter, from the transformed page body. `words` of 0 turns off derived descriptions.
+pub fn insert_frontmatter(
+    fm: &str,
+    properties: &HashMap<String, String>,
+    body: &str,
+    words: usize,
+    base_url: Option<&str>,
+) -> String {
+    let mut fm = fm.to_string();
+    if words > 0 && !properties.contains_key("description") {
+        let excerpt = excerpt(body, words);
+        if !excerpt.is_empty() {
+            fm = frontmatter::insert_field(&fm, "description", &escape::yaml_quoted(&excerpt));
+        }
+    }
+    if let Some(image) = image(properties, body, base_url) {
+        fm = frontmatter::insert_field(&fm, "socialImage", &escape::yaml_quoted(&image));
+    }
+    fm
+}
+
+/// The first `words` words of prose in an outline: blocks are read in order
+/// (children after their parent), continuation lines included, while
+/// headings, properties, code, tables, embeds and raw HTML are skipped.
+/// Ends with `…` when the text was cut.
+pub fn excerpt(content: &str, words: usize) -> String {
+    let mut out: Vec<String> = Vec::new();
+    let mut in_code = false;
+
+    for line in content.lines() {
+        let block = line.trim_start().trim_start_matches("- ").trim_start();
+        if outline::is_fence(line) {
+            in_code = !in_code;
+            continue;
+        }
+        // Quoted lines (callout bodies, blockquotes) read as prose
+        let block = block.trim_start_matches('>').trim_start();
+        if in_code || block.is_empty() || SKIPPED_LINE_RE.is_match(block) {
+            continue;
+        }
+        for word in previews::plain_text(block).split_whitespace() {
+            if out.len() == words {
+                return format!("{}…", out.join(" ").trim_end_matches(['.', ',', ';', ':']));
+            }
+            out.push(word.to_string());
+        }
+    }
+    out.join(" ")
+}
+
+/// Social card image: the `cover::` property, else the first image in the body.
+/// Assets become site paths (`assets/cover.png`), absolute with `base_url`;
+/// remote images are used as they are.
+pub fn image(properties: &HashMap<String, String>, body: &str, base_url: Option<&str>) -> Option<String> {
+    let cover = properties.get("cover").map(|cover| {
+        // cover:: ![](../assets/x.png) or a bare path
+        IMAGE_RE.captures(cover).map_or(cover.trim().to_string(), |caps| caps[1].to_string())
+    });
+    let src = cover
+        .filter(|src| is_image(src))
+        .or_else(|| IMAGE_RE.captures_iter(body).map(|caps| caps[1].to_string()).find(|src| is_image(src)))?;
+
+    if src.starts_with("http://") || src.starts_with("https://") {
+        return Some(src);
+    }
+    let path = src.trim_start_matches("./").trim_start_matches("../").trim_start_matches('/');
+    Some(match base_url {
+        Some(base) => format!("{}/{}", base.trim_end_matches('/'), path),
+        None => path.to_string(),
+    })
+}
+
+fn is_image(src: &str) -> bool {
+    let path = src.split(['?', '#']).next().unwrap_or(src);
+    path.rsplit_once('.')
+        .is_some_and(|(_, ext)| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
@@ -0,0 +1,118 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+use crate::edn;
+use crate::warnings;
+
+/// Pages and journals bigger than this fail to process instead of being read
+/// (a page that size is a paste or an export gone wrong, not something to publish)
+pub const MAX_PAGE_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Read a graph file (page, journal, config.edn) as text with `\n` line endings.
+/// Windows-authored files (CRLF, UTF-8 BOM) read the same as Unix ones, and
+/// decomposed Unicode (common in text pasted on macOS) reads as NFC.
+pub fn read_to_string(path: &Path) -> io::Result<String> {
+    fs::read_to_string(path).map(|text| normalize(&text))
+}
+
+/// Read a page or journal like `read_to_string`, without failing on its
+/// content: UTF-16 files (with a byte order mark) are decoded and invalid
+/// UTF-8 is replaced with `�`, each reported as a warning. A file over
+/// `MAX_PAGE_BYTES` is an error rather than published in part.
+pub fn read_page(path: &Path) -> io::Result<String> {
+    let mut bytes = Vec::new();
+    fs::File::open(path)?.take(MAX_PAGE_BYTES + 1).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > MAX_PAGE_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("larger than {} MB; split it into smaller pages", MAX_PAGE_BYTES >> 20),
+        ));
+    }
+    Ok(normalize(&decode(path, bytes)))
+}
+
+/// Text of a file's bytes: UTF-8, or UTF-16 after its byte order mark
+fn decode(path: &Path, bytes: Vec<u8>) -> String {
+    let utf16 = match bytes.get(..2) {
+        Some([0xff, 0xfe]) => Some(u16::from_le_bytes as fn([u8; 2]) -> u16),
+        Some([0xfe, 0xff]) => Some(u16::from_be_bytes as fn([u8; 2]) -> u16),
+        _ => None,
+    };
+    if let Some(unit) = utf16 {
+        let pairs = bytes[2..].chunks_exact(2);
+        // A dangling last byte is half a code unit: unreadable, like a lone surrogate
+        let dangling = (!pairs.remainder().is_empty()).then_some(char::REPLACEMENT_CHARACTER);
+        let units = pairs.map(|pair| unit([pair[0], pair[1]]));
+        let text = char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER));
+        return text.chain(dangling).collect();
+    }
+    match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => {
+            warnings::global().warn(
+                "encoding",
+                &format!("{} is not valid UTF-8 (first bad byte at {}); unreadable bytes shown as �", path.display(), e.utf8_error().valid_up_to()),
+                None,
+            );
+            String::from_utf8_lossy(e.as_bytes()).into_owned()
+        }
+    }
+}
+
+/// Convert CRLF and lone CR line endings to `\n`, drop a leading byte order mark
+/// and compose Unicode to NFC
+pub fn normalize(text: &str) -> String {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let text = nfc(text);
+    if !text.contains('\r') {
+        return text;
+    }
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Unicode NFC form, so `é` typed as `e` + combining accent (macOS file names,
+/// some input methods) matches the precomposed `é`
+pub fn nfc(text: &str) -> String {
+    if is_nfc_quick(text.chars()) == IsNormalized::Yes {
+        text.to_string()
+    } else {
+        text.nfc().collect()
+    }
+}
+
+/// Paths config.edn `:hidden` excludes from the graph (`["/archive" "/pages/draft.md"]`),
+/// relative to the graph root
+#[derive(Debug, Clone, Default)]
+pub struct Hidden {
+    root: PathBuf,
+    paths: Vec<PathBuf>,
+}
+
+impl Hidden {
+    /// `:hidden` of the graph at `root` (nothing hidden without config.edn)
+    pub fn load(root: &Path) -> Self {
+        let paths = read_to_string(&root.join("logseq/config.edn"))
+            .ok()
+            .and_then(|config| edn::setting(&config, ":hidden"))
+            .map(|value| {
+                value
+                    .items()
+                    .iter()
+                    .filter_map(edn::Form::as_str)
+                    .map(|p| p.trim().trim_start_matches('/').trim_end_matches('/'))
+                    .filter(|p| !p.is_empty())
+                    .map(PathBuf::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Hidden { root: root.to_path_buf(), paths }
+    }
+
+    /// Is `path` (under the graph root) a hidden file or inside a hidden folder?
+    pub fn contains(&self, path: &Path) -> bool {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        self.paths.iter().any(|hidden| relative.starts_with(hidden))
+    }
+}
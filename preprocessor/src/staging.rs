@@ -0,0 +1,172 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How a build reaches the output directory (`--output-mode`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputMode {
+    /// Build in a staging folder and swap it in when the build succeeds;
+    /// files the preprocessor didn't write are lost with the old folder
+    Swap,
+    /// Build in a staging folder, then copy over only the files that changed and
+    /// remove the ones the last build wrote but this one didn't; other files stay
+    #[default]
+    Sync,
+    /// Write straight into the output directory
+    InPlace,
+}
+
+/// Files written by the last synced build, relative to the output directory
+pub const MANIFEST_FILE: &str = "_manifest.json";
+
+/// What `sync` did to the output directory
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SyncStats {
+    pub written: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+/// Folder next to the output that a build writes to (`<output>.staging`).
+/// A failed build leaves it there for inspection; the next build starts over.
+pub fn staging_dir(output_dir: &Path) -> PathBuf {
+    sibling(output_dir, ".staging")
+}
+
+/// Where the published output is moved while the new one takes its place
+fn previous_dir(output_dir: &Path) -> PathBuf {
+    sibling(output_dir, ".previous")
+}
+
+/// `<output><suffix>` in the output's parent, so renames stay on one filesystem
+fn sibling(output_dir: &Path, suffix: &str) -> PathBuf {
+    let dir = std::path::absolute(output_dir).unwrap_or_else(|_| output_dir.to_path_buf());
+    let mut name = dir.file_name().unwrap_or("output".as_ref()).to_os_string();
+    name.push(suffix);
+    dir.with_file_name(name)
+}
+
+/// Create an empty staging folder for a build into `output_dir`
+pub fn prepare(output_dir: &Path) -> Result<PathBuf> {
+    let staging = staging_dir(output_dir);
+    if staging.exists() {
+        fs::remove_dir_all(&staging).with_context(|| format!("removing {}", staging.display()))?;
+    }
+    fs::create_dir_all(&staging).with_context(|| format!("creating {}", staging.display()))?;
+    Ok(staging)
+}
+
+/// Replace `output_dir` with the finished build in `staging`: the published
+/// folder is renamed aside, the staging folder renamed into its place, then
+/// the old one removed. Files the build didn't write are gone with it. A
+/// folder that can't be renamed (a mount point) has its contents replaced.
+pub fn publish(staging: &Path, output_dir: &Path) -> Result<()> {
+    let previous = previous_dir(output_dir);
+    if previous.exists() {
+        fs::remove_dir_all(&previous).with_context(|| format!("removing {}", previous.display()))?;
+    }
+    if output_dir.exists() && fs::rename(output_dir, &previous).is_err() {
+        return replace_contents(staging, output_dir);
+    }
+    if let Err(e) = fs::rename(staging, output_dir) {
+        // Put the published output back
+        let _ = fs::rename(&previous, output_dir);
+        return Err(e).with_context(|| format!("moving {} to {}", staging.display(), output_dir.display()));
+    }
+    if previous.exists() {
+        if let Err(e) = fs::remove_dir_all(&previous) {
+            log::warn!("could not remove {}: {}", previous.display(), e);
+        }
+    }
+    Ok(())
+}
+
+/// Empty `output_dir` and move the staged entries into it
+fn replace_contents(staging: &Path, output_dir: &Path) -> Result<()> {
+    for entry in fs::read_dir(output_dir)? {
+        let path = entry?.path();
+        if path.is_dir() && !path.is_symlink() {
+            fs::remove_dir_all(&path)?;
+        } else {
+            fs::remove_file(&path)?;
+        }
+    }
+    for entry in fs::read_dir(staging)? {
+        let entry = entry?;
+        fs::rename(entry.path(), output_dir.join(entry.file_name()))?;
+    }
+    fs::remove_dir(staging)?;
+    Ok(())
+}
+
+/// Bring `output_dir` up to date with the finished build in `staging`, leaving
+/// files the preprocessor never wrote alone. Files whose content is unchanged
+/// aren't touched (their mtime stays, so Quartz doesn't rebuild them); files
+/// listed in the previous `_manifest.json` but not built again are removed.
+pub fn sync(staging: &Path, output_dir: &Path) -> Result<SyncStats> {
+    let mut stats = SyncStats::default();
+    let previous = read_manifest(output_dir);
+    let mut files = BTreeSet::new();
+
+    for entry in walkdir::WalkDir::new(staging).sort_by_file_name() {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(staging)?.to_string_lossy().replace('\\', "/");
+        if relative == MANIFEST_FILE {
+            continue;
+        }
+        let target = output_dir.join(&relative);
+        let content = fs::read(entry.path())?;
+        if fs::read(&target).is_ok_and(|current| current == content) {
+            stats.unchanged += 1;
+        } else {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&target, content).with_context(|| format!("writing {}", target.display()))?;
+            stats.written += 1;
+        }
+        files.insert(relative);
+    }
+
+    for relative in previous.difference(&files) {
+        let path = output_dir.join(relative);
+        if fs::remove_file(&path).is_ok() {
+            stats.removed += 1;
+            remove_empty_parents(&path, output_dir);
+        }
+    }
+
+    let manifest = serde_json::to_string_pretty(&files)?;
+    if fs::read_to_string(output_dir.join(MANIFEST_FILE)).ok().as_deref() != Some(manifest.as_str()) {
+        fs::write(output_dir.join(MANIFEST_FILE), manifest)?;
+    }
+    fs::remove_dir_all(staging)?;
+    Ok(stats)
+}
+
+/// Paths in `_manifest.json`; none when there is no readable manifest
+fn read_manifest(output_dir: &Path) -> BTreeSet<String> {
+    fs::read_to_string(output_dir.join(MANIFEST_FILE))
+        .ok()
+        .and_then(|json| serde_json::from_str::<BTreeSet<String>>(&json).ok())
+        .unwrap_or_default()
+        .into_iter()
+        // Never reach outside the output directory
+        .filter(|path| !path.split('/').any(|part| part == ".." || part.is_empty()))
+        .collect()
+}
+
+/// Remove folders left empty by a removed file, up to the output directory
+fn remove_empty_parents(path: &Path, output_dir: &Path) {
+    let mut dir = path.parent();
+    while let Some(current) = dir.filter(|d| *d != output_dir && d.starts_with(output_dir)) {
+        if fs::remove_dir(current).is_err() {
+            break;
+        }
+        dir = current.parent();
+    }
+}
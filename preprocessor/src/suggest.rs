@@ -0,0 +1,166 @@
+use anyhow::Result;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::page::{Page, PageIndex};
+
+lazy_static! {
+    // Words for term counting (letters/digits, allowing inner dashes and underscores)
+    static ref WORD_RE: Regex = Regex::new(r"[\p{L}\p{N}][\p{L}\p{N}_-]*").unwrap();
+
+    // Markup stripped before counting: properties, wikilink brackets, macros, urls
+    static ref PROPERTY_LINE_RE: Regex = Regex::new(r"(?m)^\s*-?\s*[\w-]+::.*$").unwrap();
+    static ref MACRO_RE: Regex = Regex::new(r"\{\{[^\}]*\}\}").unwrap();
+    static ref URL_RE: Regex = Regex::new(r"https?://\S+").unwrap();
+}
+
+/// Minimum occurrences of a tag term in a page before it is suggested
+const MIN_OCCURRENCES: usize = 2;
+
+/// Maximum suggestions reported per page
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Suggested tag for a page with its relevance score
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TagSuggestion {
+    /// The tag as the graph spells it (`machine-learning`)
+    pub tag: String,
+    pub occurrences: usize,
+    pub score: f64,
+}
+
+/// Tag suggestions for a single untagged page
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PageSuggestions {
+    pub page: String,
+    pub suggestions: Vec<TagSuggestion>,
+}
+
+/// Suggest tags for untagged pages using the existing tag vocabulary.
+///
+/// Each tag already used somewhere in the graph is scored against an untagged
+/// page by term frequency (occurrences / page length) weighted by inverse
+/// document frequency across the whole index, so common words rank lower.
+/// Suggestions are only reported, never written back to pages. Private pages
+/// get no suggestions and lend no tags unless `include_private`.
+pub fn suggest_tags(index: &PageIndex, include_private: bool) -> Vec<PageSuggestions> {
+    let pages: Vec<&Page> = index.iter().filter(|p| include_private || !p.is_private()).collect();
+    let vocabulary = tag_vocabulary(&pages);
+    if vocabulary.is_empty() {
+        return Vec::new();
+    }
+
+    let documents: Vec<Vec<String>> = pages.iter().map(|p| tokenize(&p.content)).collect();
+
+    // Document frequency of each tag term across all pages
+    let total_docs = documents.len() as f64;
+    let doc_freq: HashMap<&str, usize> = vocabulary
+        .keys()
+        .map(|tag| {
+            let terms: Vec<&str> = tag.split_whitespace().collect();
+            let df = documents.iter().filter(|doc| count_phrase(doc, &terms) > 0).count();
+            (tag.as_str(), df)
+        })
+        .collect();
+
+    let mut results = Vec::new();
+    for (page, words) in pages.iter().zip(documents.iter()) {
+        if !is_candidate(page) || words.is_empty() {
+            continue;
+        }
+
+        let mut suggestions: Vec<TagSuggestion> = vocabulary
+            .iter()
+            .filter(|(tag, _)| **tag != page.name_lower)
+            .filter_map(|(tag, spelling)| {
+                let terms: Vec<&str> = tag.split_whitespace().collect();
+                let occurrences = count_phrase(words, &terms);
+                if occurrences < MIN_OCCURRENCES {
+                    return None;
+                }
+                let df = doc_freq.get(tag.as_str()).copied().unwrap_or(0).max(1) as f64;
+                let tf = occurrences as f64 / words.len() as f64;
+                let idf = (total_docs / df).ln() + 1.0;
+                Some(TagSuggestion {
+                    tag: spelling.clone(),
+                    occurrences,
+                    score: (tf * idf * 1000.0).round() / 1000.0,
+                })
+            })
+            .collect();
+
+        if suggestions.is_empty() {
+            continue;
+        }
+
+        suggestions.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.tag.cmp(&b.tag))
+        });
+        suggestions.truncate(MAX_SUGGESTIONS);
+
+        results.push(PageSuggestions {
+            page: page.name.clone(),
+            suggestions,
+        });
+    }
+
+    results.sort_by(|a, b| a.page.cmp(&b.page));
+    results
+}
+
+/// Write tag suggestions as a JSON report (_tag_suggestions.json)
+pub fn write_report(output_dir: &Path, suggestions: &[PageSuggestions]) -> Result<()> {
+    let json = serde_json::to_string_pretty(suggestions)?;
+    fs::write(output_dir.join("_tag_suggestions.json"), json)?;
+    Ok(())
+}
+
+/// All tags used on the pages, normalized to space-separated words (what
+/// page text is matched against), each with the tag as written. A tag spelled
+/// several ways keeps its first spelling in sort order.
+fn tag_vocabulary(pages: &[&Page]) -> BTreeMap<String, String> {
+    let spellings: BTreeSet<&String> = pages.iter().flat_map(|p| p.tags.iter()).collect();
+    let mut vocabulary = BTreeMap::new();
+    for tag in spellings {
+        let words = tag.replace(['-', '_'], " ").trim().to_string();
+        if words.chars().count() > 2 {
+            vocabulary.entry(words).or_insert_with(|| tag.clone());
+        }
+    }
+    vocabulary
+}
+
+/// Only untagged regular pages get suggestions (journals are dated, not topical)
+fn is_candidate(page: &Page) -> bool {
+    page.tags.is_empty() && !page.name.starts_with("journals/")
+}
+
+/// Split page content into lowercase words, ignoring properties, macros and URLs
+fn tokenize(content: &str) -> Vec<String> {
+    let text = PROPERTY_LINE_RE.replace_all(content, "");
+    let text = MACRO_RE.replace_all(&text, " ");
+    let text = URL_RE.replace_all(&text, " ");
+
+    WORD_RE
+        .find_iter(&text)
+        .map(|m| m.as_str().to_lowercase().replace(['-', '_'], " "))
+        .flat_map(|w| w.split_whitespace().map(str::to_string).collect::<Vec<_>>())
+        .collect()
+}
+
+/// Count occurrences of a (possibly multi-word) phrase in a word list
+fn count_phrase(words: &[String], terms: &[&str]) -> usize {
+    if terms.is_empty() || words.len() < terms.len() {
+        return 0;
+    }
+    words
+        .windows(terms.len())
+        .filter(|window| window.iter().zip(terms.iter()).all(|(w, t)| w == t))
+        .count()
+}
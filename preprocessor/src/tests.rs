@@ -4,7 +4,7 @@ mod path_tests {
     use crate::page::PageIndex;
 
     fn empty_index() -> PageIndex {
-        Vec::new()
+        PageIndex::default()
     }
 
     #[test]
@@ -221,7 +221,7 @@ mod frontmatter_tests {
         props.insert("icon".to_string(), "🔵".to_string());
         props.insert("title".to_string(), "Test Page".to_string());
 
-        let fm = frontmatter::generate("test", &props, None);
+        let fm = frontmatter::generate("test", &props, None, &Default::default());
         assert!(fm.contains("title: \"🔵 Test Page\""));
         assert!(fm.contains("icon: \"🔵\""));
     }
@@ -231,7 +231,7 @@ mod frontmatter_tests {
         let mut props = HashMap::new();
         props.insert("tags".to_string(), "foo, bar, baz".to_string());
 
-        let fm = frontmatter::generate("test", &props, None);
+        let fm = frontmatter::generate("test", &props, None, &Default::default());
         assert!(fm.contains("tags:"));
         assert!(fm.contains("  - foo"));
         assert!(fm.contains("  - bar"));
@@ -241,7 +241,7 @@ mod frontmatter_tests {
     #[test]
     fn test_frontmatter_with_dates() {
         let props = HashMap::new();
-        let fm = frontmatter::generate("test", &props, Some(("2025-01-01", "2024-01-01")));
+        let fm = frontmatter::generate("test", &props, Some(("2025-01-01", "2024-01-01")), &Default::default());
         assert!(fm.contains("modified: 2025-01-01"));
         assert!(fm.contains("created: 2024-01-01"));
     }
@@ -251,13 +251,14 @@ mod frontmatter_tests {
         let mut props = HashMap::new();
         props.insert("title".to_string(), "Test \"quoted\" page".to_string());
 
-        let fm = frontmatter::generate("test", &props, None);
+        let fm = frontmatter::generate("test", &props, None, &Default::default());
         assert!(fm.contains("Test \\\"quoted\\\" page"));
     }
 }
 
 #[cfg(test)]
 mod favorites_tests {
+    use crate::page::PageIndex;
     use std::fs;
     use tempfile::tempdir;
 
@@ -282,7 +283,7 @@ mod favorites_tests {
         fs::write(&config_path, config_content).unwrap();
 
         // Process favorites
-        let result = crate::favorites::process_favorites(&config_path, &favorites_dir, &pages_dir, None);
+        let result = crate::favorites::process_favorites(&config_path, &favorites_dir, &pages_dir, &PageIndex::default(), None, &Default::default(), crate::favorites::DEFAULT_OUTPUTS);
         assert!(result.is_ok());
 
         // Check index.md format
@@ -301,6 +302,37 @@ mod favorites_tests {
         );
     }
 
+    #[test]
+    fn test_favorites_json_without_redirect_pages() {
+        use crate::favorites::FavoritesOutput;
+
+        let temp = tempdir().unwrap();
+        let favorites_dir = temp.path().join("favorites");
+        let pages_dir = temp.path().join("pages");
+        fs::create_dir_all(&favorites_dir).unwrap();
+        fs::create_dir_all(&pages_dir).unwrap();
+        fs::write(pages_dir.join("zeta.md"), "---\ntitle: Zeta\nicon: 🚀\n---\nContent").unwrap();
+        fs::write(pages_dir.join("alpha.md"), "---\ntitle: Alpha\n---\nContent").unwrap();
+        let config_path = temp.path().join("config.edn");
+        fs::write(&config_path, r#"{:favorites ["zeta" "alpha"]}"#).unwrap();
+
+        let outputs = [FavoritesOutput::Index, FavoritesOutput::Json];
+        let result = crate::favorites::process_favorites(&config_path, &favorites_dir, &pages_dir, &PageIndex::default(), None, &Default::default(), &outputs);
+        assert_eq!(result.unwrap(), 2);
+
+        assert!(!favorites_dir.join("zeta.md").exists(), "No redirect pages without the pages output");
+        assert!(fs::read_to_string(favorites_dir.join("index.md")).unwrap().contains("- [[zeta|🚀 zeta]]"));
+        let manifest = fs::read_to_string(pages_dir.join("favorites.json")).unwrap();
+        let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+        assert_eq!(
+            manifest,
+            serde_json::json!([
+                {"name": "zeta", "path": "zeta", "icon": "🚀"},
+                {"name": "alpha", "path": "alpha"}
+            ])
+        );
+    }
+
     #[test]
     fn test_favorites_with_dots_in_name() {
         let temp = tempdir().unwrap();
@@ -319,7 +351,7 @@ mod favorites_tests {
         let config_path = temp.path().join("config.edn");
         fs::write(&config_path, config_content).unwrap();
 
-        let result = crate::favorites::process_favorites(&config_path, &favorites_dir, &pages_dir, None);
+        let result = crate::favorites::process_favorites(&config_path, &favorites_dir, &pages_dir, &PageIndex::default(), None, &Default::default(), crate::favorites::DEFAULT_OUTPUTS);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), 1, "Should create 1 favorite");
 
@@ -345,7 +377,7 @@ mod favorites_tests {
         let config_path = temp.path().join("config.edn");
         fs::write(&config_path, config_content).unwrap();
 
-        let result = crate::favorites::process_favorites(&config_path, &favorites_dir, &pages_dir, None);
+        let result = crate::favorites::process_favorites(&config_path, &favorites_dir, &pages_dir, &PageIndex::default(), None, &Default::default(), crate::favorites::DEFAULT_OUTPUTS);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), 1, "Should create 1 favorite");
 
@@ -407,7 +439,7 @@ mod favorites_tests {
 
         fs::write(&config_path, r#"{:default-home {:page "cyberia"}}"#).unwrap();
 
-        let result = crate::favorites::write_site_config(&config_path, &output_dir, None, None, None);
+        let result = crate::favorites::write_site_config(&config_path, &output_dir, None, None, None, Default::default());
         assert!(result.is_some());
 
         let config = result.unwrap();
@@ -423,6 +455,45 @@ mod favorites_tests {
         assert!(json_content.contains("Cyberia"));
     }
 
+    #[test]
+    fn test_write_site_config_theme_and_build_fields() {
+        let temp = tempdir().unwrap();
+        let config_path = temp.path().join("config.edn");
+        let output_dir = temp.path().join("output");
+        fs::create_dir_all(&output_dir).unwrap();
+
+        fs::write(&config_path, r##"{:ui/radix-color :purple
+ ;; :ui/accent-color "#000000"
+ :ui/show-brackets? false
+ :journal/page-title-format "yyyy-MM-dd"}"##).unwrap();
+
+        let context = crate::favorites::SiteContext {
+            base_url: Some("https://example.org/garden".to_string()),
+            analytics: true,
+            journals: crate::favorites::JournalsInfo {
+                enabled: true,
+                count: 3,
+                index_path: Some("journals".to_string()),
+                page_title_format: None,
+            },
+            ..Default::default()
+        };
+        let config = crate::favorites::write_site_config(&config_path, &output_dir, None, None, None, context).unwrap();
+        assert_eq!(config.date_format, "yyyy-MM-dd");
+        assert_eq!(config.theme.get("radix-color").map(String::as_str), Some("purple"));
+        assert_eq!(config.theme.len(), 1);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output_dir.join("_site_config.json")).unwrap()).unwrap();
+        assert_eq!(json["base_url"], "https://example.org/garden");
+        assert_eq!(json["analytics"], true);
+        assert_eq!(json["journals"]["index_path"], "journals");
+
+        fs::write(&config_path, "{}").unwrap();
+        let config = crate::favorites::write_site_config(&config_path, &output_dir, None, None, None, Default::default()).unwrap();
+        assert_eq!(config.date_format, "MMM do, yyyy");
+    }
+
     #[test]
     fn test_write_site_config_with_overrides() {
         let temp = tempdir().unwrap();
@@ -438,6 +509,7 @@ mod favorites_tests {
             Some("custom-home"),
             Some("Custom Title"),
             Some("my site docs"),
+            Default::default(),
         );
         assert!(result.is_some());
 
@@ -468,6 +540,7 @@ mod favorites_tests {
             Some("new-home"),
             None,
             None,
+            Default::default(),
         );
         assert!(result.is_some());
 
@@ -506,7 +579,10 @@ mod favorites_tests {
             &config_path,
             &favorites_dir,
             &pages_dir,
+            &PageIndex::default(),
             Some(&override_favs),
+            &Default::default(),
+            crate::favorites::DEFAULT_OUTPUTS,
         );
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), 2, "Should create 2 favorites from override");
@@ -527,12 +603,12 @@ mod favorites_tests {
         fs::write(&config_path, r#"{:default-home {:page "test"}}"#).unwrap();
 
         // Without site_name - should not appear in JSON
-        let _result = crate::favorites::write_site_config(&config_path, &output_dir, None, None, None);
+        let _result = crate::favorites::write_site_config(&config_path, &output_dir, None, None, None, Default::default());
         let json = fs::read_to_string(output_dir.join("_site_config.json")).unwrap();
         assert!(!json.contains("site_name"), "site_name should not appear when not set, got: {}", json);
 
         // With site_name - should appear in JSON
-        let result = crate::favorites::write_site_config(&config_path, &output_dir, None, None, Some("cyber docs"));
+        let result = crate::favorites::write_site_config(&config_path, &output_dir, None, None, Some("cyber docs"), Default::default());
         assert!(result.is_some());
         let json = fs::read_to_string(output_dir.join("_site_config.json")).unwrap();
         assert!(json.contains("cyber docs"), "site_name should appear in JSON, got: {}", json);
@@ -541,6 +617,7 @@ mod favorites_tests {
 
 #[cfg(test)]
 mod journals_tests {
+    use crate::page::PageIndex;
     use std::fs;
     use tempfile::tempdir;
     use crate::config::Config;
@@ -564,11 +641,10 @@ mod journals_tests {
             output_dir: output_dir.clone(),
             include_private: false,
             create_stubs: false,
-            verbose: false,
             ..Default::default()
         };
 
-        let page_index = Vec::new();
+        let page_index = PageIndex::default();
         let result = crate::journals::process_journals(&journals_dir, &output_dir, &page_index, &config);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), 1);
@@ -605,11 +681,10 @@ mod journals_tests {
             output_dir: output_dir.clone(),
             include_private: false,
             create_stubs: false,
-            verbose: false,
             ..Default::default()
         };
 
-        let page_index = Vec::new();
+        let page_index = PageIndex::default();
         crate::journals::process_journals(&journals_dir, &output_dir, &page_index, &config).unwrap();
 
         let index_content = fs::read_to_string(output_dir.join("index.md")).unwrap();
@@ -624,67 +699,128 @@ mod journals_tests {
     }
 }
 
+/// A page for tests, with only the fields a test sets:
+/// `TestPage::new("Garden").tags(&["plants"]).content("- beds").build()`.
+/// Properties written in the content are parsed the way the index parses them.
+#[cfg(test)]
+struct TestPage(crate::page::Page);
+
+#[cfg(test)]
+impl TestPage {
+    fn new(name: &str) -> Self {
+        TestPage(crate::page::Page { name: name.to_string(), name_lower: name.to_lowercase(), ..Default::default() })
+    }
+
+    fn content(mut self, content: &str) -> Self {
+        let (properties, _) = crate::page::parse_properties(content);
+        self.0.properties.extend(properties);
+        self.0.content = content.to_string();
+        self
+    }
+
+    fn properties(mut self, properties: &[(&str, &str)]) -> Self {
+        self.0.properties.extend(properties.iter().map(|(k, v)| (k.to_string(), v.to_string())));
+        self
+    }
+
+    fn tags(mut self, tags: &[&str]) -> Self {
+        self.0.tags = tags.iter().map(|t| t.to_string()).collect();
+        self
+    }
+
+    fn aliases(mut self, aliases: &[&str]) -> Self {
+        self.0.aliases = aliases.iter().map(|a| a.to_string()).collect();
+        self
+    }
+
+    fn namespace(mut self, namespace: Option<&str>) -> Self {
+        self.0.namespace = namespace.map(String::from);
+        self
+    }
+
+    fn slug(mut self, slug: Option<&str>) -> Self {
+        self.0.slug = slug.map(String::from);
+        self
+    }
+
+    fn created(mut self, created: Option<&str>) -> Self {
+        self.0.created = created.map(String::from);
+        self
+    }
+
+    fn modified(mut self, modified: Option<&str>) -> Self {
+        self.0.modified = modified.map(String::from);
+        self
+    }
+
+    fn source(mut self, file: &str) -> Self {
+        self.0.source = Some(file.into());
+        self
+    }
+
+    fn build(self) -> crate::page::Page {
+        self.0
+    }
+}
+
+/// The common case: a page and its content
+#[cfg(test)]
+fn page(name: &str, content: &str) -> crate::page::Page {
+    TestPage::new(name).content(content).build()
+}
+
 #[cfg(test)]
 mod query_tests {
-    use crate::page::Page;
+    use crate::content;
+    use crate::page::{Page, PageIndex};
     use crate::query;
-    use std::collections::HashMap;
+    use super::TestPage;
 
     fn create_test_page(name: &str, tags: Vec<&str>) -> Page {
-        Page {
-            name: name.to_string(),
-            name_lower: name.to_lowercase(),
-            content: String::new(),
-            properties: HashMap::new(),
-            tags: tags.into_iter().map(|s| s.to_string()).collect(),
-            aliases: vec![],
-            namespace: None,
-            modified: None,
-            created: None,
-        }
+        TestPage::new(name).tags(&tags).build()
     }
 
     #[test]
     fn test_query_page_tags() {
-        let pages = vec![
+        let pages = PageIndex::from(vec![
             create_test_page("page1", vec!["rust", "programming"]),
             create_test_page("page2", vec!["rust"]),
             create_test_page("page3", vec!["python"]),
-        ];
+        ]);
 
-        let results = query::execute("{{query (page-tags [[rust]])}}", &pages);
+        let results = query::execute("{{query (page-tags [[rust]])}}", &pages).unwrap();
         assert_eq!(results.len(), 2);
     }
 
     #[test]
     fn test_query_page_tags_strips_pages_prefix() {
-        let pages = vec![
+        let pages = PageIndex::from(vec![
             create_test_page("page1", vec!["rust"]),
-        ];
+        ]);
 
-        let results = query::execute("{{query (page-tags [[pages/rust]])}}", &pages);
+        let results = query::execute("{{query (page-tags [[pages/rust]])}}", &pages).unwrap();
         assert_eq!(results.len(), 1, "Should strip pages/ prefix from query");
     }
 
     #[test]
     fn test_query_and() {
-        let pages = vec![
+        let pages = PageIndex::from(vec![
             create_test_page("page1", vec!["rust", "programming"]),
             create_test_page("page2", vec!["rust"]),
-        ];
+        ]);
 
-        let results = query::execute("{{query (and (page-tags [[rust]]) (page-tags [[programming]]))}}", &pages);
+        let results = query::execute("{{query (and (page-tags [[rust]]) (page-tags [[programming]]))}}", &pages).unwrap();
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].name, "page1");
     }
 
     #[test]
     fn test_query_results_default_table() {
-        let pages = vec![
+        let pages = PageIndex::from(vec![
             create_test_page("my-page", vec!["test"]),
-        ];
+        ]);
 
-        let results = query::execute("{{query (page-tags [[test]])}}", &pages);
+        let results = query::execute("{{query (page-tags [[test]])}}", &pages).unwrap();
         let markdown = query::results_to_markdown_with_options(&results, "test query", &query::QueryOptions::default());
 
         // Default is now table view (like Logseq)
@@ -694,19 +830,152 @@ mod query_tests {
             markdown
         );
         assert!(
-            markdown.contains("[[my-page]]"),
+            markdown.contains("[[my-page\\|my-page]]"),
             "Table should contain page link, got: {}",
             markdown
         );
     }
 
+    #[test]
+    fn test_query_table_links_show_titles() {
+        let mut garden = create_test_page("garden", vec!["moc"]);
+        garden.properties.insert("icon".to_string(), "🌱".to_string());
+        garden.properties.insert("title".to_string(), "Garden | Notes".to_string());
+        let index = PageIndex::from(vec![garden, create_test_page("a|b", vec!["moc"])]);
+
+        let results = query::execute("{{query (page-tags [[moc]])}}", &index).unwrap();
+        let markdown = query::results_to_markdown_with_options(&results, "q", &query::QueryOptions::default());
+        assert!(markdown.contains("| [[garden\\|🌱 Garden \\| Notes]] |"), "got: {}", markdown);
+        // A pipe in the page name: linked by its output path
+        assert!(markdown.contains("| [[a_b\\|a\\|b]] |"), "got: {}", markdown);
+
+        // Wikilink processing keeps the escaped pipe
+        let page = content::transform("- {{query (page-tags [[moc]])}}", &index);
+        assert!(page.contains("| [[garden\\|🌱 Garden \\| Notes]] |"), "got: {}", page);
+    }
+
+    #[test]
+    fn test_query_table_formats_cells_by_type() {
+        let mut a = create_test_page("a", vec!["x"]);
+        for (key, value) in [("due", "2025-01-15"), ("owner", "[[Ann]]"), ("done", "true"), ("cost", "12.5")] {
+            a.properties.insert(key.to_string(), value.to_string());
+        }
+        let mut b = create_test_page("b", vec!["x"]);
+        for (key, value) in [("due", "someday"), ("owner", "Bob <b@x>"), ("done", "false"), ("cost", "3")] {
+            b.properties.insert(key.to_string(), value.to_string());
+        }
+        let index = PageIndex::from(vec![a, b]);
+        let options = query::QueryOptions {
+            properties: ["page", "due", "owner", "done", "cost"].iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        };
+        let results = query::execute("{{query (page-tags [[x]])}}", &index).unwrap();
+        let table = query::results_to_markdown_with_options(&results, "q", &options);
+
+        // Numbers right-aligned
+        assert!(table.contains("| --- | --- | --- | --- | ---: |"), "got: {}", table);
+        assert!(table.contains("| Jan 15, 2025 | [[Ann]] | ✅ | 12.5 |"), "got: {}", table);
+        assert!(table.contains("| someday | Bob &lt;b@x&gt; | ❌ | 3 |"), "got: {}", table);
+    }
+
+    #[test]
+    fn test_all_page_tags_lists_tags_with_counts() {
+        let index = PageIndex::from(vec![
+            create_test_page("a", vec!["Rust", "web dev"]),
+            create_test_page("b", vec!["rust"]),
+            create_test_page("rust", vec![]),
+        ]);
+        assert_eq!(query::tag_counts(&index), vec![("Rust".to_string(), 2), ("web dev".to_string(), 1)]);
+        assert!(query::execute("{{query (all-page-tags)}}", &index).unwrap().is_empty());
+
+        let page = content::transform("- {{query (all-page-tags)}}", &index);
+        assert_eq!(page, "- [[tags/Rust|Rust]] (2)\n- [[tags/web-dev|web dev]] (1)");
+
+        let empty = content::transform("- {{query (all-page-tags)}}", &PageIndex::from(vec![]));
+        assert!(empty.contains("No pages match"), "got: {}", empty);
+    }
+
+    #[test]
+    fn test_query_command_rows() {
+        let mut page = create_test_page("rust", vec!["lang", "systems"]);
+        page.source = Some(std::path::PathBuf::from("graph/pages/rust.md"));
+        page.properties.insert("type".to_string(), "[[language]]".to_string());
+        let index = PageIndex::from(vec![page, create_test_page("go", vec!["lang"])]);
+        let results = query::execute("(page-tags [[systems]])", &index).unwrap();
+
+        let rows = query::result_rows(&results, std::path::Path::new("graph"));
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].file.as_deref(), Some("pages/rust.md"));
+        assert_eq!(rows[0].properties.get("type").map(String::as_str), Some("[[language]]"));
+        assert_eq!(
+            query::rows_table(&rows),
+            vec!["page  tags           file", "rust  lang, systems  pages/rust.md"]
+        );
+    }
+
+    #[test]
+    fn test_page_tags_through_aliases_and_plurals() {
+        let mut book = create_test_page("book", vec![]);
+        book.aliases = vec!["reading".to_string()];
+        let index = PageIndex::from(vec![
+            book,
+            create_test_page("dune", vec!["Book"]),
+            create_test_page("sapiens", vec!["reading"]),
+            create_test_page("essays", vec!["books"]),
+            create_test_page("tales", vec!["stories"]),
+        ]);
+        let names = |query: &str, index: &PageIndex| {
+            let mut names: Vec<String> = query::execute(query, index).unwrap().iter().map(|p| p.name.clone()).collect();
+            names.sort();
+            names
+        };
+
+        // An alias of the tag page, or the page the alias names
+        assert_eq!(names("{{query (page-tags [[book]])}}", &index), vec!["dune", "sapiens"]);
+        assert_eq!(names("{{query (page-tags [[reading]])}}", &index), vec!["dune", "sapiens"]);
+
+        let index = index.with_tag_match(query::TagMatch::Plural);
+        assert_eq!(names("{{query (page-tags [[book]])}}", &index), vec!["dune", "essays", "sapiens"]);
+        assert_eq!(names("{{query (page-tags [[story]])}}", &index), vec!["tales"]);
+    }
+
+    #[test]
+    fn test_separator_only_query_is_empty() {
+        let index = PageIndex::from(vec![create_test_page("a", vec!["x"])]);
+        // `{{query ,}}` in a page, `logseq-to-quartz query ','` on the command line
+        for query_str in ["{{query ,}}", ","] {
+            assert_eq!(query::execute(query_str, &index).unwrap_err(), query::QueryError::Empty, "{}", query_str);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_query_renders_warning_callout() {
+        let index = PageIndex::from(vec![create_test_page("a", vec!["x"])]);
+        assert_eq!(
+            query::execute("{{query (and (page-tags [[x]]) (frobnicate 3))}}", &index).unwrap_err(),
+            query::QueryError::Unsupported("(frobnicate 3)".to_string())
+        );
+        assert_eq!(query::execute("{{query (page-tags [[x]]}}", &index).unwrap_err(), query::QueryError::Unbalanced);
+        assert_eq!(query::execute("{{query (between [[soon]] today)}}", &index).unwrap_err().to_string(), "unreadable date in `(between [[soon]] today)`");
+        assert_eq!(index.query_cache().lock().unwrap().counts().unsupported, 3);
+
+        let page = content::transform_page("- {{query (frobnicate 3)}}\n- After", &index);
+        assert!(
+            page.content.contains("\n> [!warning] Unsupported query\n> unsupported clause `(frobnicate 3)`\n> `(frobnicate 3)`\n\n- After"),
+            "got: {}",
+            page.content
+        );
+        assert_eq!(page.diagnostics.len(), 1, "{:?}", page.diagnostics);
+        assert!(!page.content.contains("No pages match"));
+    }
+
     #[test]
     fn test_query_results_explicit_list() {
-        let pages = vec![
+        let pages = PageIndex::from(vec![
             create_test_page("my-page", vec!["test"]),
-        ];
+        ]);
 
-        let results = query::execute("{{query (page-tags [[test]])}}", &pages);
+        let results = query::execute("{{query (page-tags [[test]])}}", &pages).unwrap();
         let opts = query::QueryOptions {
             table: Some(false),  // Explicitly request list
             ..Default::default()
@@ -723,17 +992,17 @@ mod query_tests {
     #[test]
     fn test_query_nested_and() {
         // Test: (and (page-tags [[genus]]) (not (page-tags [[class]])) (and (page-tags [[research]])))
-        let pages = vec![
+        let pages = PageIndex::from(vec![
             create_test_page("page1", vec!["genus", "research"]),           // should match
             create_test_page("page2", vec!["genus", "class", "research"]),  // should NOT (has class)
             create_test_page("page3", vec!["genus"]),                        // should NOT (no research)
             create_test_page("page4", vec!["genus", "research", "other"]),  // should match
-        ];
+        ]);
 
         let results = query::execute(
             "{{query (and (page-tags [[genus]]) (not (page-tags [[class]])) (and (page-tags [[research]])))}}",
             &pages
-        );
+        ).unwrap();
 
         assert_eq!(results.len(), 2, "Should match pages with genus AND research but NOT class");
         let names: Vec<_> = results.iter().map(|p| &p.name).collect();
@@ -744,19 +1013,19 @@ mod query_tests {
     #[test]
     fn test_query_multiple_nots() {
         // Test: (and (page-tags [[genus]]) (not (page-tags [[class]])) (not (page-tags [[research]])) (not (page-tags [[prohibited]])))
-        let pages = vec![
+        let pages = PageIndex::from(vec![
             create_test_page("page1", vec!["genus"]),                              // should match
             create_test_page("page2", vec!["genus", "class"]),                     // should NOT
             create_test_page("page3", vec!["genus", "research"]),                  // should NOT
             create_test_page("page4", vec!["genus", "prohibited"]),                // should NOT
             create_test_page("page5", vec!["genus", "allowed"]),                   // should match
             create_test_page("page6", vec!["genus", "class", "research"]),         // should NOT
-        ];
+        ]);
 
         let results = query::execute(
             "{{query (and (page-tags [[genus]]) (not (page-tags [[class]])) (not (page-tags [[research]])) (not (page-tags [[prohibited]])))}}",
             &pages
-        );
+        ).unwrap();
 
         assert_eq!(results.len(), 2, "Should match pages with genus but NOT class, research, or prohibited");
         let names: Vec<_> = results.iter().map(|p| &p.name).collect();
@@ -767,17 +1036,17 @@ mod query_tests {
     #[test]
     fn test_query_complex_nested_or_and() {
         // Test complex: (or (and (page-tags [[a]]) (page-tags [[b]])) (and (page-tags [[c]]) (page-tags [[d]])))
-        let pages = vec![
+        let pages = PageIndex::from(vec![
             create_test_page("page1", vec!["a", "b"]),           // matches first AND
             create_test_page("page2", vec!["c", "d"]),           // matches second AND
             create_test_page("page3", vec!["a"]),                // no match
             create_test_page("page4", vec!["a", "b", "c", "d"]), // matches both
-        ];
+        ]);
 
         let results = query::execute(
             "{{query (or (and (page-tags [[a]]) (page-tags [[b]])) (and (page-tags [[c]]) (page-tags [[d]])))}}",
             &pages
-        );
+        ).unwrap();
 
         assert_eq!(results.len(), 3, "Should match pages with (a AND b) OR (c AND d)");
         let names: Vec<_> = results.iter().map(|p| &p.name).collect();
@@ -789,17 +1058,17 @@ mod query_tests {
     #[test]
     fn test_query_with_extra_spaces() {
         // Test query with extra spaces before closing parens (common in Logseq)
-        let pages = vec![
+        let pages = PageIndex::from(vec![
             create_test_page("page1", vec!["genus", "prohibited"]),
             create_test_page("page2", vec!["genus", "class"]),
             create_test_page("page3", vec!["genus"]),
-        ];
+        ]);
 
         // Query with extra space before closing paren: [[prohibited]] ))
         let results = query::execute(
             "{{query (and (page-tags [[genus]]) (not (page-tags [[class]])) (and (page-tags [[prohibited]] )))}}",
             &pages
-        );
+        ).unwrap();
 
         assert_eq!(results.len(), 1, "Should match page with genus+prohibited but not class");
         assert_eq!(results[0].name, "page1");
@@ -808,16 +1077,16 @@ mod query_tests {
     #[test]
     fn test_query_with_various_whitespace() {
         // Test query with extra spaces in various positions
-        let pages = vec![
+        let pages = PageIndex::from(vec![
             create_test_page("page1", vec!["a", "b"]),
             create_test_page("page2", vec!["a"]),
-        ];
+        ]);
 
         // Extra spaces after keywords
         let results = query::execute(
             "{{query (and   (page-tags [[a]])  (page-tags [[b]]) )}}",
             &pages
-        );
+        ).unwrap();
         assert_eq!(results.len(), 1, "Should handle extra spaces after 'and'");
         assert_eq!(results[0].name, "page1");
 
@@ -825,10 +1094,85 @@ mod query_tests {
         let results2 = query::execute(
             "{{query (and (page-tags [[a]]) (not   (page-tags [[b]]) ))}}",
             &pages
-        );
+        ).unwrap();
         assert_eq!(results2.len(), 1, "Should handle extra spaces after 'not'");
         assert_eq!(results2[0].name, "page2");
     }
+
+    #[test]
+    fn test_sample_and_shuffle_are_seeded() {
+        let pages: Vec<Page> = (0..20)
+            .map(|i| create_test_page(&format!("page{:02}", i), if i % 2 == 0 { vec!["even"] } else { vec![] }))
+            .collect();
+        let index = PageIndex::from(pages.clone()).with_seed(7);
+        let names = |q: &str, index: &PageIndex| query::execute(q, index).unwrap().iter().map(|p| p.name.clone()).collect::<Vec<_>>();
+
+        let sample = names("{{query (and (page-tags [[even]]) (sample 3))}}", &index);
+        assert_eq!(sample.len(), 3);
+        assert!(sample.iter().all(|n| n[4..].parse::<usize>().unwrap() % 2 == 0), "{:?}", sample);
+        // Same seed, same draw; another seed draws again
+        assert_eq!(names("{{query (and (page-tags [[even]]) (sample 3))}}", &index), sample);
+        let reseeded = PageIndex::from(pages).with_seed(8);
+        assert_ne!(names("{{query (and (page-tags [[even]]) (sample 3))}}", &reseeded), sample);
+
+        assert_eq!(names("{{query (sample 5)}}", &index).len(), 5);
+        let mut shuffled = names("{{query (and (page-tags [[even]]) (shuffle))}}", &index);
+        assert_ne!(shuffled, names("{{query (page-tags [[even]])}}", &index));
+        shuffled.sort();
+        assert_eq!(shuffled, names("{{query (page-tags [[even]])}}", &index));
+        assert!(query::is_shuffled("{{query (and [[x]] (shuffle))}}"));
+    }
+
+    #[test]
+    fn test_query_cache_reuses_results_per_index() {
+        let index = PageIndex::from(vec![
+            create_test_page("page1", vec!["rust"]),
+            create_test_page("page2", vec!["python"]),
+        ]);
+        let names = |index: &PageIndex| {
+            query::execute("{{query (page-tags [[rust]])}}", index).unwrap().iter().map(|p| p.name.clone()).collect::<Vec<_>>()
+        };
+        let counts = |index: &PageIndex| index.query_cache().lock().unwrap().counts();
+
+        assert_eq!(names(&index), vec!["page1"]);
+        assert_eq!(names(&index), vec!["page1"]);
+        query::execute("{{query   (page-tags [[rust]])  }}", &index).unwrap();
+        assert_eq!(counts(&index), query::QueryCounts { run: 1, reused: 2, unsupported: 0 });
+
+        // Another graph has another fingerprint: its own results
+        let changed = PageIndex::from(vec![
+            create_test_page("page1", vec!["rust"]),
+            create_test_page("page2", vec!["rust"]),
+        ]);
+        assert_ne!(changed.fingerprint(), index.fingerprint());
+        assert_eq!(names(&changed), vec!["page1", "page2"]);
+        assert_eq!(counts(&changed).run, 1);
+    }
+
+    #[test]
+    fn test_query_dependencies_show_whose_results_changed() {
+        let graph = |tags: Vec<&str>| {
+            let mut listing = create_test_page("listing", vec![]);
+            listing.content = "- {{query (page-tags [[rust]])}}\n- {{query (page-tags [[python]])}}".to_string();
+            PageIndex::from(vec![
+                create_test_page("page1", vec!["rust"]),
+                create_test_page("page2", tags),
+                create_test_page("other", vec![]),
+                listing,
+            ])
+        };
+        let before = query::dependencies(&graph(vec!["python"]), false);
+        assert_eq!(before.len(), 1);
+        assert_eq!(before["listing"], vec!["page1", "page2"]);
+
+        // Another page edited: the listing's results are the same
+        assert!(query::changed_results(&before, &query::dependencies(&graph(vec!["python", "go"]), false)).is_empty());
+        // page2 leaves the results
+        let after = query::dependencies(&graph(vec!["go"]), false);
+        assert_eq!(after["listing"], vec!["page1"]);
+        assert_eq!(query::changed_results(&before, &after), vec!["listing"]);
+        assert_eq!(query::changed_results(&Default::default(), &after), vec!["listing"]);
+    }
 }
 
 #[cfg(test)]
@@ -837,7 +1181,7 @@ mod table_and_pdf_tests {
     use crate::page::PageIndex;
 
     fn empty_index() -> PageIndex {
-        Vec::new()
+        PageIndex::default()
     }
 
     // ===========================================
@@ -994,13 +1338,14 @@ mod table_and_pdf_tests {
             namespace: None,
             modified: None,
             created: None,
+            ..Default::default()
         }
     }
 
     #[test]
     fn test_wikilink_prefix_match_visit_us_to_visit() {
         // "visit us" should match "visit" page when "visit us" doesn't exist
-        let page_index = vec![create_page("visit"), create_page("other page")];
+        let page_index = PageIndex::from(vec![create_page("visit"), create_page("other page")]);
         let input = "- Check out [[visit us]] for info";
         let result = content::transform(input, &page_index);
 
@@ -1014,7 +1359,7 @@ mod table_and_pdf_tests {
     #[test]
     fn test_wikilink_exact_match_not_rewritten() {
         // Exact match should not be rewritten
-        let page_index = vec![create_page("visit"), create_page("visit us")];
+        let page_index = PageIndex::from(vec![create_page("visit"), create_page("visit us")]);
         let input = "- Check out [[visit us]] for info";
         let result = content::transform(input, &page_index);
 
@@ -1033,7 +1378,7 @@ mod table_and_pdf_tests {
     #[test]
     fn test_wikilink_prefix_match_preserves_existing_alias() {
         // If link already has an alias, preserve it
-        let page_index = vec![create_page("visit")];
+        let page_index = PageIndex::from(vec![create_page("visit")]);
         let input = "- Check out [[visit us|come see us]] for info";
         let result = content::transform(input, &page_index);
 
@@ -1047,11 +1392,11 @@ mod table_and_pdf_tests {
     #[test]
     fn test_wikilink_prefix_match_longest_wins() {
         // "cyber valley estate" should match "cyber valley" not "cyber"
-        let page_index = vec![
+        let page_index = PageIndex::from(vec![
             create_page("cyber"),
             create_page("cyber valley"),
             create_page("other"),
-        ];
+        ]);
         let input = "- Visit [[cyber valley estate]] today";
         let result = content::transform(input, &page_index);
 
@@ -1065,7 +1410,7 @@ mod table_and_pdf_tests {
     #[test]
     fn test_wikilink_no_match_unchanged() {
         // No matching page - link should remain unchanged
-        let page_index = vec![create_page("other"), create_page("something")];
+        let page_index = PageIndex::from(vec![create_page("other"), create_page("something")]);
         let input = "- Check out [[completely different]] for info";
         let result = content::transform(input, &page_index);
 
@@ -1079,7 +1424,7 @@ mod table_and_pdf_tests {
     #[test]
     fn test_wikilink_prefix_match_case_insensitive() {
         // Matching should be case-insensitive
-        let page_index = vec![create_page("Visit")];
+        let page_index = PageIndex::from(vec![create_page("Visit")]);
         let input = "- Check out [[visit us]] for info";
         let result = content::transform(input, &page_index);
 
@@ -1115,10 +1460,10 @@ mod table_and_pdf_tests {
     #[test]
     fn test_alias_exact_match() {
         // Link "cv/districts" should match page with alias "cv/districts"
-        let page_index = vec![
+        let page_index = PageIndex::from(vec![
             create_page_with_aliases("cyber valley/districts", vec!["cv/districts"]),
             create_page("other page"),
-        ];
+        ]);
         let input = "- Discover [[cv/districts]] here";
         let result = content::transform(input, &page_index);
 
@@ -1132,10 +1477,10 @@ mod table_and_pdf_tests {
     #[test]
     fn test_alias_simple_match() {
         // Link "cv" should match page "cyber valley" with alias "cv"
-        let page_index = vec![
+        let page_index = PageIndex::from(vec![
             create_page_with_aliases("cyber valley", vec!["cv", "about"]),
             create_page("other"),
-        ];
+        ]);
         let input = "- Visit [[cv]] today";
         let result = content::transform(input, &page_index);
 
@@ -1150,10 +1495,10 @@ mod table_and_pdf_tests {
     fn test_namespace_alias_expansion() {
         // Link "cv/districts" where "cv" is alias for "cyber valley"
         // should match "cyber valley/districts"
-        let page_index = vec![
+        let page_index = PageIndex::from(vec![
             create_page_with_aliases("cyber valley", vec!["cv"]),
             create_page("cyber valley/districts"),
-        ];
+        ]);
         let input = "- Discover [[cv/districts]] here";
         let result = content::transform(input, &page_index);
 
@@ -1167,10 +1512,10 @@ mod table_and_pdf_tests {
     #[test]
     fn test_alias_does_not_override_exact_page() {
         // If both page "cv" and alias "cv" exist, page should win
-        let page_index = vec![
+        let page_index = PageIndex::from(vec![
             create_page("cv"),
             create_page_with_aliases("cyber valley", vec!["cv"]),
-        ];
+        ]);
         let input = "- Visit [[cv]] today";
         let result = content::transform(input, &page_index);
 
@@ -1189,9 +1534,9 @@ mod table_and_pdf_tests {
     #[test]
     fn test_multiple_aliases() {
         // Page with multiple aliases
-        let page_index = vec![
+        let page_index = PageIndex::from(vec![
             create_page_with_aliases("visit", vec!["residency", "come visit"]),
-        ];
+        ]);
 
         let input1 = "- Check [[residency]] options";
         let result1 = content::transform(input1, &page_index);
@@ -1213,9 +1558,9 @@ mod table_and_pdf_tests {
     #[test]
     fn test_alias_case_insensitive() {
         // Alias matching should be case-insensitive
-        let page_index = vec![
+        let page_index = PageIndex::from(vec![
             create_page_with_aliases("Cyber Valley", vec!["CV"]),
-        ];
+        ]);
         let input = "- Visit [[cv]] today";
         let result = content::transform(input, &page_index);
 
@@ -1285,7 +1630,6 @@ mod table_and_pdf_tests {
         let result = content::transform(input, &empty_index());
 
         // The $ before x should not be escaped (it's math mode, not currency)
-        // Note: The current implementation may escape this - if so, we need smarter detection
         assert!(
             result.contains("$x^2"),
             "Math mode should be preserved, got: {}",
@@ -1347,9 +1691,9 @@ mod table_and_pdf_tests {
     #[test]
     fn test_alias_dollar_uses_html_anchor() {
         // When alias resolution creates [[Page|Display]] with $, output HTML anchor
-        let page_index = vec![
+        let page_index = PageIndex::from(vec![
             create_page_with_aliases("$C", vec!["$TOCYB"]),
-        ];
+        ]);
         let input = "- [[$TOCYB]] is a token";
         let result = content::transform(input, &page_index);
 
@@ -1364,9 +1708,9 @@ mod table_and_pdf_tests {
     #[test]
     fn test_simple_dollar_wikilink_uses_html_anchor() {
         // Simple wikilinks to $ pages use HTML anchor to prevent KaTeX
-        let page_index = vec![
+        let page_index = PageIndex::from(vec![
             create_page("$V"),
-        ];
+        ]);
         let input = "- [[$V]] is will";
         let result = content::transform(input, &page_index);
 
@@ -1377,3 +1721,5066 @@ mod table_and_pdf_tests {
         );
     }
 }
+
+#[cfg(test)]
+mod suggest_tests {
+    use crate::page::{Page, PageIndex};
+    use crate::suggest;
+    use super::TestPage;
+
+    fn page(name: &str, tags: Vec<&str>, content: &str) -> Page {
+        TestPage::new(name).tags(&tags).content(content).build()
+    }
+
+    #[test]
+    fn test_suggests_existing_tags_for_untagged_pages() {
+        let index = PageIndex::from(vec![
+            page("rust intro", vec!["rust"], "- Rust is a language"),
+            page("python intro", vec!["python"], "- Python is a language"),
+            page(
+                "notes",
+                vec![],
+                "- Learning rust this week\n- rust ownership and rust lifetimes\n- python once",
+            ),
+        ]);
+
+        let results = suggest::suggest_tags(&index, false);
+        assert_eq!(results.len(), 1, "Only the untagged page gets suggestions");
+        assert_eq!(results[0].page, "notes");
+        assert_eq!(results[0].suggestions[0].tag, "rust");
+        assert!(
+            !results[0].suggestions.iter().any(|s| s.tag == "python"),
+            "Tags below the occurrence threshold are not suggested"
+        );
+    }
+
+    #[test]
+    fn test_multi_word_tags_matched_as_phrases() {
+        let index = PageIndex::from(vec![
+            page("a", vec!["machine-learning"], ""),
+            page(
+                "b",
+                vec![],
+                "- machine learning basics\n- more machine learning\n- a machine that is learning",
+            ),
+        ]);
+
+        let results = suggest::suggest_tags(&index, false);
+        assert_eq!(results.len(), 1);
+        // Reported as the graph spells the tag
+        assert_eq!(results[0].suggestions[0].tag, "machine-learning");
+        assert_eq!(results[0].suggestions[0].occurrences, 2);
+    }
+
+    #[test]
+    fn test_journals_and_properties_ignored() {
+        let index = PageIndex::from(vec![
+            page("topic", vec!["rust"], ""),
+            page("journals/2025-01-01", vec![], "- rust rust rust"),
+            page("plain", vec![], "type:: rust rust\n- nothing relevant"),
+        ]);
+
+        let results = suggest::suggest_tags(&index, false);
+        assert!(results.is_empty(), "got: {:?}", results);
+    }
+
+    #[test]
+    fn test_private_pages_get_and_lend_no_suggestions() {
+        let mut secret = page("secret plans", vec![], "- rust here\n- more rust");
+        secret.properties.insert("private".to_string(), "true".to_string());
+        let mut hidden_tag = page("hidden", vec!["codename"], "");
+        hidden_tag.properties.insert("private".to_string(), "true".to_string());
+        let index = PageIndex::from(vec![
+            page("topic", vec!["rust"], ""),
+            secret,
+            hidden_tag,
+            page("notes", vec![], "- codename here\n- codename again"),
+        ]);
+
+        assert!(suggest::suggest_tags(&index, false).is_empty());
+        let names: Vec<String> = suggest::suggest_tags(&index, true).into_iter().map(|s| s.page).collect();
+        assert_eq!(names, vec!["notes", "secret plans"]);
+    }
+}
+
+#[cfg(test)]
+mod query_parser_tests {
+    use crate::page::{Page, PageIndex};
+    use crate::query::{self, QueryError};
+    use crate::query_parser::{parse, Expr};
+
+    fn filter(src: &str) -> Expr {
+        parse(src).unwrap().filter
+    }
+
+    fn refs(name: &str) -> Expr {
+        Expr::Ref(name.to_string())
+    }
+
+    #[test]
+    fn test_parse_bare_refs_in_boolean_clauses() {
+        assert_eq!(
+            filter("(and [[tag]] (not [[other]]))"),
+            Expr::And(vec![refs("tag"), Expr::Not(vec![refs("other")])])
+        );
+        assert_eq!(filter("(or #a #[[multi word]])"), Expr::Or(vec![refs("a"), refs("multi word")]));
+        assert_eq!(filter("[[page]]"), refs("page"));
+        assert_eq!(filter("(links-to [[page]])"), refs("page"));
+    }
+
+    #[test]
+    fn test_parse_nests_every_clause_type() {
+        let task = |states: &[&str]| Expr::Task(states.iter().map(|s| s.to_string()).collect());
+        assert_eq!(filter("(or (task TODO) (task doing NOW))"), Expr::Or(vec![task(&["TODO"]), task(&["DOING", "NOW"])]));
+        assert_eq!(
+            filter("(and (page-tags [[a]] #b c) (not (priority a) (namespace [[x]])))"),
+            Expr::And(vec![
+                Expr::PageTags(vec!["a".to_string(), "b".to_string(), "c".to_string()]),
+                Expr::Not(vec![Expr::Priority("A".to_string()), Expr::Namespace("x".to_string())]),
+            ])
+        );
+        assert_eq!(
+            filter(r#"(or (property :type [[project]]) (page-property status "in progress") (property due))"#),
+            Expr::Or(vec![
+                Expr::Property { page_only: false, key: "type".to_string(), value: Some("[[project]]".to_string()) },
+                Expr::Property { page_only: true, key: "status".to_string(), value: Some("in progress".to_string()) },
+                Expr::Property { page_only: false, key: "due".to_string(), value: None },
+            ])
+        );
+        assert_eq!(
+            filter("(and (between [[Jan 1st, 2025]] -7d) \"needle\")"),
+            Expr::And(vec![
+                Expr::Between("[[Jan 1st, 2025]]".to_string(), "-7d".to_string()),
+                Expr::Text("needle".to_string()),
+            ])
+        );
+        assert_eq!(filter("rust lang"), Expr::Text("rust lang".to_string()));
+    }
+
+    #[test]
+    fn test_parse_modifiers() {
+        let query = parse("(and (page-tags x) (sample 3) (shuffle))").unwrap();
+        assert_eq!(query.filter, Expr::And(vec![Expr::PageTags(vec!["x".to_string()])]));
+        assert_eq!((query.sample, query.shuffle), (Some(3), true));
+        assert_eq!(filter("(and (sample 2))"), Expr::All);
+        assert_eq!(filter("(shuffle)"), Expr::All);
+        assert_eq!(filter("(and)"), Expr::And(vec![]));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse("  "), Err(QueryError::Empty));
+        assert_eq!(parse(","), Err(QueryError::Empty));
+        assert_eq!(parse(" , ,"), Err(QueryError::Empty));
+        for unbalanced in ["(and [[x]]", "(page-tags [[x)", "\"open", ")(", "(and [x])"] {
+            assert_eq!(parse(unbalanced), Err(QueryError::Unbalanced), "{}", unbalanced);
+        }
+        assert_eq!(
+            parse("(and [[y]] (frobnicate 1 (x)) [[z]])"),
+            Err(QueryError::Unsupported("(frobnicate 1 (x))".to_string()))
+        );
+        assert_eq!(parse("(task SOMEDAY)"), Err(QueryError::Unsupported("(task SOMEDAY)".to_string())));
+        assert_eq!(parse("(and foo)"), Err(QueryError::Unsupported("foo".to_string())));
+        assert_eq!(parse("ab"), Err(QueryError::Unsupported("ab".to_string())));
+        let deep = format!("{}[[x]]{}", "(and ".repeat(100), ")".repeat(100));
+        assert_eq!(parse(&deep), Err(QueryError::TooDeep));
+    }
+
+    #[test]
+    fn test_bare_refs_filter_results() {
+        let page = |name: &str, content: &str| Page {
+            name: name.to_string(),
+            name_lower: name.to_lowercase(),
+            content: content.to_string(),
+            ..Default::default()
+        };
+        let index = PageIndex::from(vec![
+            page("a", "- about [[tag]]"),
+            page("b", "- about [[tag]] and [[other]]"),
+            page("c", "- TODO about [[other]]"),
+            page("d", "- DOING nothing"),
+        ]);
+        let names = |query: &str| {
+            let mut names: Vec<String> = query::execute(query, &index).unwrap().iter().map(|p| p.name.clone()).collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names("{{query (and [[tag]] (not [[other]]))}}"), vec!["a"]);
+        assert_eq!(names("{{query (or (task TODO) (task DOING))}}"), vec!["c", "d"]);
+        assert_eq!(names("{{query (and (or [[tag]] (task DOING)) (not [[other]]))}}"), vec!["a", "d"]);
+    }
+}
+
+#[cfg(test)]
+mod query_output_tests {
+    use crate::content;
+    use crate::frontmatter;
+    use crate::page::{Page, PageIndex};
+    use crate::query;
+    use std::collections::HashMap;
+
+    fn tagged(name: &str, tag: &str) -> Page {
+        Page {
+            name: name.to_string(),
+            name_lower: name.to_lowercase(),
+            tags: vec![tag.to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_query_output_frontmatter_collects_listing() {
+        let index = PageIndex::from(vec![tagged("beta", "project"), tagged("alpha", "project")]);
+        let input = "- query-output:: frontmatter\n  {{query (page-tags [[project]])}}\n- After";
+        let result = content::transform_page(input, &index);
+
+        assert_eq!(result.listing, vec!["alpha".to_string(), "beta".to_string()]);
+        assert!(
+            !result.content.contains("[[alpha]]") && !result.content.contains("Query-Output"),
+            "Frontmatter queries should not render in the body, got: {}",
+            result.content
+        );
+        assert!(result.content.contains("After"));
+    }
+
+    #[test]
+    fn test_query_output_respects_sort_options() {
+        let mut b = tagged("b", "x");
+        b.properties.insert("rank".to_string(), "1".to_string());
+        let mut a = tagged("a", "x");
+        a.properties.insert("rank".to_string(), "2".to_string());
+        let index = PageIndex::from(vec![a, b]);
+
+        let input = "- query-sort-by:: rank\n  query-output:: frontmatter\n  {{query (page-tags [[x]])}}";
+        let result = content::transform_page(input, &index);
+        assert_eq!(result.listing, vec!["b".to_string(), "a".to_string()]);
+    }
+
+    #[test]
+    fn test_query_view_cards_embed_and_list() {
+        let mut garden = tagged("garden", "moc");
+        garden.properties.insert("icon".to_string(), "🌱".to_string());
+        garden.properties.insert("description".to_string(), "Notes that [[grow]]".to_string());
+        let mut rust = tagged("rust", "moc");
+        rust.content = "- A language empowering everyone".to_string();
+        let index = PageIndex::from(vec![garden, rust]);
+
+        let cards = content::transform_page("- query-view:: cards\n  {{query (page-tags [[moc]])}}\n- After", &index).content;
+        assert!(cards.contains("<div class=\"query-cards\">"), "{}", cards);
+        assert!(cards.contains("**[[garden|🌱 garden]]**  \n    Notes that grow"), "{}", cards);
+        assert!(cards.contains("A language empowering everyone"), "{}", cards);
+        assert!(cards.contains("</div>\n\n- After"), "{}", cards);
+
+        let embeds = content::transform_page("- query-view:: embed\n  {{query (page-tags [[moc]])}}", &index).content;
+        assert!(embeds.contains("  ![[garden]]\n\n  ![[rust]]"), "{}", embeds);
+
+        // query-view:: list wins over query-table:: true
+        let list = content::transform_page("- query-table:: true\n  query-view:: list\n  {{query (page-tags [[moc]])}}", &index).content;
+        assert!(list.contains("[[garden|🌱 garden]]") && !list.contains("---"), "{}", list);
+    }
+
+    #[test]
+    fn test_query_dates_note_and_pages_with_queries() {
+        let mut dashboard = tagged("dashboard", "moc");
+        dashboard.content = "- {{query (page-tags [[moc]])}}".to_string();
+        let mut secret = tagged("secret", "moc");
+        secret.content = "- {{query (task TODO)}}".to_string();
+        secret.properties.insert("private".to_string(), "true".to_string());
+        let index = PageIndex::from(vec![dashboard, secret, tagged("garden", "moc")]);
+
+        let plain = content::transform_page("- query-table:: false\n  {{query (page-tags [[moc]])}}", &index).content;
+        assert!(!plain.contains("Results generated"), "{}", plain);
+
+        let index = index.with_query_date(Some("2025-01-15".to_string()));
+        let dated = content::transform_page("- query-table:: false\n  {{query (page-tags [[moc]])}}\n- After", &index).content;
+        assert!(dated.contains("[[secret|secret]]\n\n  *Results generated 2025-01-15*\n- After"), "{}", dated);
+
+        assert_eq!(query::pages_with_queries(&index, false), vec!["dashboard"]);
+        assert_eq!(query::pages_with_queries(&index, true), vec!["dashboard", "secret"]);
+    }
+
+    #[test]
+    fn test_insert_list_into_frontmatter() {
+        let fm = frontmatter::generate("index", &HashMap::new(), None, &Default::default());
+        let fm = frontmatter::insert_list(&fm, "listing", &["Projects/Web App".to_string()]);
+        assert!(fm.contains("listing:\n  - \"Projects/Web App\"\n---\n"), "got: {}", fm);
+        assert!(fm.starts_with("---\ntitle:"));
+    }
+}
+
+#[cfg(test)]
+mod slug_tests {
+    use crate::config::Config;
+    use crate::content;
+    use crate::frontmatter;
+    use crate::page::{Page, PageIndex};
+    use crate::resolver::{self, SlugMap};
+    use std::collections::HashMap;
+    use std::fs;
+    use tempfile::tempdir;
+    use super::TestPage;
+
+    fn page(name: &str, slug: Option<&str>, aliases: Vec<&str>) -> Page {
+        TestPage::new(name).slug(slug).aliases(&aliases).build()
+    }
+
+    #[test]
+    fn test_output_name_uses_slug_property() {
+        let mut props = HashMap::new();
+        assert_eq!(resolver::output_name("Projects___Web App", &props, Default::default()), "Projects/Web App");
+
+        props.insert("slug".to_string(), " /web-app/ ".to_string());
+        assert_eq!(resolver::output_name("Projects___Web App", &props, Default::default()), "web-app");
+    }
+
+    #[test]
+    fn test_wikilinks_follow_slug() {
+        let index = PageIndex::from(vec![page("Web App", Some("web-app"), vec![])]);
+        let result = content::transform("- See [[Web App]] and [[Web App|the app]]", &index);
+        assert!(result.contains("[[web-app|Web App]]"), "got: {}", result);
+        assert!(result.contains("[[web-app|the app]]"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_slug_map_records_previous_slugs() {
+        let mut map = SlugMap::default();
+        let mut index = PageIndex::from(vec![page("Web App", None, vec![])]);
+        map.update(&mut index);
+        assert!(index[0].previous_slugs.is_empty());
+
+        // Slug changes on a later build
+        let mut index = PageIndex::from(vec![page("Web App", Some("web-app"), vec![])]);
+        map.update(&mut index);
+        assert_eq!(index[0].previous_slugs, vec!["Web App".to_string()]);
+        assert_eq!(map.entries["web app"].current, "web-app");
+    }
+
+    #[test]
+    fn test_slug_map_follows_rename_via_alias() {
+        let mut map = SlugMap::default();
+        let mut index = PageIndex::from(vec![page("Old Name", None, vec![])]);
+        map.update(&mut index);
+
+        // Page renamed, old name kept as alias
+        let mut index = PageIndex::from(vec![page("New Name", None, vec!["Old Name"])]);
+        map.update(&mut index);
+        assert_eq!(index[0].previous_slugs, vec!["Old Name".to_string()]);
+        assert!(!map.entries.contains_key("old name"), "Stale entry should be merged away");
+    }
+
+    #[test]
+    fn test_insert_list_appends_to_existing_aliases() {
+        let mut props = HashMap::new();
+        props.insert("alias".to_string(), "wa".to_string());
+        let fm = frontmatter::generate("Web App", &props, None, &Default::default());
+        let fm = frontmatter::insert_list(&fm, "aliases", &["old-url".to_string()]);
+        assert!(fm.contains("aliases:\n  - wa\n  - \"old-url\"\n"), "got: {}", fm);
+        assert_eq!(fm.matches("aliases:").count(), 1);
+    }
+
+    #[test]
+    fn test_process_page_writes_to_slug_path_with_aliases() {
+        let temp = tempdir().unwrap();
+        let pages_dir = temp.path().join("pages");
+        let output_dir = temp.path().join("out");
+        fs::create_dir_all(&pages_dir).unwrap();
+        fs::create_dir_all(&output_dir).unwrap();
+        let source = pages_dir.join("Web App.md");
+        fs::write(&source, "slug:: web-app\n\n- Content").unwrap();
+
+        let mut indexed = page("Web App", Some("web-app"), vec![]);
+        indexed.previous_slugs = vec!["Web App".to_string()];
+        let index = PageIndex::from(vec![indexed]);
+
+        let config = Config::default();
+        let published = crate::page::process_page(
+            &source, &output_dir, &index, &config, &HashMap::new(), temp.path(),
+        ).unwrap();
+        assert!(published);
+
+        let output = fs::read_to_string(output_dir.join("web-app.md")).unwrap();
+        assert!(output.contains("aliases:\n  - \"Web App\""), "got: {}", output);
+    }
+
+    #[test]
+    fn test_slugs_never_leave_the_output() {
+        let mut props = HashMap::new();
+        props.insert("slug".to_string(), "../escaped-slug".to_string());
+        assert_eq!(resolver::output_name("Escape", &props, Default::default()), "Escape");
+        props.insert("slug".to_string(), "docs/../../up".to_string());
+        assert_eq!(resolver::output_name("Escape", &props, Default::default()), "Escape");
+
+        let out = std::path::Path::new("out");
+        assert_eq!(resolver::output_path(out, "../escaped"), out.join("escaped.md"));
+        assert_eq!(resolver::join_site_path(out, "/etc/./../passwd"), out.join("etc").join("passwd"));
+
+        let temp = tempdir().unwrap();
+        let pages_dir = temp.path().join("pages");
+        let output_dir = temp.path().join("out");
+        fs::create_dir_all(&pages_dir).unwrap();
+        fs::create_dir_all(&output_dir).unwrap();
+        let source = pages_dir.join("Escape.md");
+        fs::write(&source, "slug:: ../escaped-slug\n\n- Content").unwrap();
+        let index = PageIndex::from(vec![page("Escape", None, vec![])]);
+        crate::page::process_page(&source, &output_dir, &index, &Config::default(), &HashMap::new(), temp.path()).unwrap();
+        assert!(output_dir.join("Escape.md").exists());
+        assert!(!temp.path().join("escaped-slug.md").exists());
+    }
+}
+
+#[cfg(test)]
+mod export_tests {
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn write_graph(root: &std::path::Path) {
+        let pages = root.join("pages");
+        let assets = root.join("assets");
+        fs::create_dir_all(&pages).unwrap();
+        fs::create_dir_all(&assets).unwrap();
+        fs::write(pages.join("Projects.md"), "- See [[Projects/Web App]] and [[Elsewhere]]").unwrap();
+        fs::write(
+            pages.join("Projects___Web App.md"),
+            "- Logo ![logo](../assets/logo.svg)\n- Back to [[Projects]]",
+        ).unwrap();
+        fs::write(pages.join("Elsewhere.md"), "- Outside the namespace").unwrap();
+        fs::write(pages.join("Secret.md"), "private:: true\n\n- hidden").unwrap();
+        fs::write(assets.join("logo.svg"), "<svg/>").unwrap();
+    }
+
+    #[test]
+    fn test_single_html_inlines_assets_and_links() {
+        let temp = tempdir().unwrap();
+        write_graph(temp.path());
+        let out = temp.path().join("archive.html");
+
+        let count = crate::export::single_html(temp.path(), &out, None, false).unwrap();
+        assert_eq!(count, 3, "Private page should be excluded");
+
+        let html = fs::read_to_string(&out).unwrap();
+        assert!(html.contains("<section id=\"page-projects-web-app\">"), "got: {}", html);
+        assert!(html.contains("<a href=\"#page-projects-web-app\">Projects/Web App</a>"));
+        assert!(html.contains("src=\"data:image/svg+xml;base64,"), "Assets should be inlined");
+        assert!(!html.contains("hidden"));
+    }
+
+    #[test]
+    fn test_single_html_namespace_filter() {
+        let temp = tempdir().unwrap();
+        write_graph(temp.path());
+        let out = temp.path().join("projects.html");
+
+        let count = crate::export::single_html(temp.path(), &out, Some("projects"), false).unwrap();
+        assert_eq!(count, 2);
+
+        let html = fs::read_to_string(&out).unwrap();
+        assert!(
+            html.contains("<span class=\"missing\">Elsewhere</span>"),
+            "Links outside the export should not dangle, got: {}",
+            html
+        );
+    }
+}
+
+#[cfg(test)]
+mod resolver_tests {
+    use crate::content;
+    use crate::page::{Page, PageIndex};
+    use crate::resolver::{self, MatchKind};
+    use crate::query;
+    use super::TestPage;
+
+    fn page(name: &str, aliases: Vec<&str>) -> Page {
+        TestPage::new(name).aliases(&aliases).build()
+    }
+
+    fn journal(file_stem: &str, date: &str) -> Page {
+        let mut p = page(&format!("journals/{}", file_stem), vec![]);
+        p.slug = Some(resolver::journal_output_name(date));
+        p
+    }
+
+    #[test]
+    fn test_resolve_match_kinds() {
+        let index = PageIndex::from(vec![
+            page("cyber valley", vec!["cv"]),
+            page("cyber valley/districts", vec![]),
+            page("visit", vec![]),
+            journal("2024_01_15", "2024-01-15"),
+        ]);
+
+        let kind = |link: &str| resolver::resolve(link, &index).map(|r| r.kind);
+        assert_eq!(kind("Cyber-Valley"), Some(MatchKind::Exact));
+        assert_eq!(kind("cv"), Some(MatchKind::Alias));
+        assert_eq!(kind("cv/districts"), Some(MatchKind::NamespaceAlias));
+        assert_eq!(kind("Jan 15th, 2024"), Some(MatchKind::Journal));
+        assert_eq!(kind("visit us"), Some(MatchKind::Prefix));
+        assert_eq!(kind("nowhere"), None);
+    }
+
+    #[test]
+    fn test_link_fallback_modes_and_thresholds() {
+        let pages = || PageIndex::from(vec![page("cyber valley", vec!["cv"]), page("visit", vec![]), journal("2024_01_15", "2024-01-15")]);
+        let with = |mode, min_length, min_ratio| pages().with_link_fallback(resolver::Fallback { mode, min_length, min_ratio, ..Default::default() });
+        let kind = |link: &str, index: &PageIndex| resolver::resolve(link, index).map(|r| r.kind);
+
+        let off = with(resolver::LinkFallback::Off, 1, 0.0);
+        assert_eq!(kind("Cyber Valley", &off), Some(MatchKind::Exact));
+        assert_eq!(kind("cv", &off), None);
+        let exact = with(resolver::LinkFallback::Exact, 1, 0.0);
+        assert_eq!(kind("cv", &exact), Some(MatchKind::Alias));
+        assert_eq!(kind("Jan 15th, 2024", &exact), Some(MatchKind::Journal));
+        assert_eq!(kind("visit us", &exact), None);
+
+        // "visit" is 5 of the 8 characters of "visit us"
+        assert_eq!(kind("visit us", &with(resolver::LinkFallback::Prefix, 1, 0.6)), Some(MatchKind::Prefix));
+        assert_eq!(kind("visit us", &with(resolver::LinkFallback::Prefix, 1, 0.7)), None);
+        assert_eq!(kind("visit us", &with(resolver::LinkFallback::Prefix, 6, 0.0)), None);
+        assert_eq!(kind("viist", &pages()), None);
+
+        let fuzzy = with(resolver::LinkFallback::Fuzzy, 1, 0.0);
+        let typo = resolver::resolve("cyber vally", &fuzzy).unwrap();
+        assert_eq!((typo.page.name.as_str(), typo.kind), ("cyber valley", MatchKind::Fuzzy));
+        assert_eq!(kind("visit us", &fuzzy), Some(MatchKind::Prefix));
+        assert_eq!(kind("valley", &fuzzy), None);
+
+        // Guessed links are listed for the diagnostics report; named pages aren't
+        assert_eq!(
+            content::fallback_links("- [[visit us]] [[cv]] [[cyber vally]] `[[visit me]]`", &fuzzy),
+            vec![("visit us".to_string(), "visit".to_string()), ("cyber vally".to_string(), "cyber valley".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_links_ignore_punctuation_and_plurals() {
+        let fuzzy = |threshold| resolver::Fallback {
+            mode: resolver::LinkFallback::Fuzzy,
+            fuzzy_threshold: threshold,
+            ..Default::default()
+        };
+        let mut notes = page("Reading Notes", vec![]);
+        notes.content = "- [[reading note]] [[C.S. Lewis's books]]\n- [[Readng Notes]] [[Reading Nots]]".to_string();
+        let index = PageIndex::from(vec![notes, page("CS Lewis book", vec![]), page("Writing", vec!["drafts"])])
+            .with_link_fallback(fuzzy(resolver::FUZZY_THRESHOLD));
+        let name = |link: &str, index: &PageIndex| resolver::resolve(link, index).map(|r| r.page.name.clone());
+
+        assert_eq!(name("reading note", &index).as_deref(), Some("Reading Notes"));
+        assert_eq!(name("C.S. Lewis's books", &index).as_deref(), Some("CS Lewis book"));
+        assert_eq!(name("draft", &index).as_deref(), Some("Writing"));
+        assert_eq!(name("Reding", &index), None);
+        assert_eq!(name("Reding", &index.clone().with_link_fallback(fuzzy(0.5))).as_deref(), Some("Writing"));
+
+        let cs = resolver::find_page("CS Lewis book", &index).unwrap();
+        assert_eq!(resolver::fuzzy_confidence("C.S. Lewis's books", cs), 1.0);
+
+        // Least confident first, then by link
+        let report = resolver::fuzzy_links(&index, false);
+        let links: Vec<(&str, &str)> = report.iter().map(|l| (l.link.as_str(), l.page.as_str())).collect();
+        assert_eq!(
+            links,
+            vec![
+                ("Reading Nots", "Reading Notes"),
+                ("Readng Notes", "Reading Notes"),
+                ("C.S. Lewis's books", "CS Lewis book"),
+                ("reading note", "Reading Notes"),
+            ]
+        );
+        assert!(report[0].confidence < 1.0 && report[3].confidence == 1.0);
+        assert_eq!(report[0].linked_from, vec!["Reading Notes"]);
+    }
+
+    #[test]
+    fn test_journal_links_point_at_dated_output() {
+        let index = PageIndex::from(vec![journal("2024_01_15", "2024-01-15")]);
+        let result = content::transform("- Met on [[Jan 15th, 2024]]", &index);
+        assert!(
+            result.contains("[[journals/2024-01-15|Jan 15th, 2024]]"),
+            "got: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_query_results_link_journals_by_date() {
+        let mut j = journal("2024_01_15", "2024-01-15");
+        j.tags = vec!["meeting".to_string()];
+        let index = PageIndex::from(vec![j]);
+        let results = query::execute("{{query (page-tags [[meeting]])}}", &index).unwrap();
+        let md = query::results_to_markdown_with_options(&results, "q", &query::QueryOptions::default());
+        assert!(md.contains("[[journals/2024-01-15\\|"), "got: {}", md);
+    }
+
+    #[test]
+    fn test_prefix_match_handles_non_ascii() {
+        let index = PageIndex::from(vec![page("café", vec![])]);
+        let resolved = resolver::resolve("café menu", &index).unwrap();
+        assert_eq!(resolved.page.name, "café");
+    }
+
+    #[test]
+    fn test_favorites_resolve_through_alias() {
+        use std::fs;
+        let temp = tempfile::tempdir().unwrap();
+        let favorites_dir = temp.path().join("favorites");
+        let pages_dir = temp.path().join("pages");
+        fs::create_dir_all(&favorites_dir).unwrap();
+        fs::create_dir_all(&pages_dir).unwrap();
+        fs::write(pages_dir.join("cyber valley.md"), "---\ntitle: CV\n---\n").unwrap();
+
+        let index = PageIndex::from(vec![page("cyber valley", vec!["cv"])]);
+        let favs = vec!["cv".to_string()];
+        let count = crate::favorites::process_favorites(
+            &temp.path().join("config.edn"), &favorites_dir, &pages_dir, &index, Some(&favs), &Default::default(),
+            crate::favorites::DEFAULT_OUTPUTS,
+        ).unwrap();
+        assert_eq!(count, 1);
+        let index_md = fs::read_to_string(favorites_dir.join("index.md")).unwrap();
+        assert!(index_md.contains("[[cyber valley|cv]]"), "got: {}", index_md);
+    }
+}
+
+#[cfg(test)]
+mod warnings_tests {
+    use crate::content;
+    use crate::page::{Page, PageIndex};
+    use crate::warnings::Warnings;
+
+    #[test]
+    fn test_identical_warnings_are_deduplicated() {
+        let warnings = Warnings::default();
+        assert!(warnings.warn("broken-link", "missing page", Some("a")));
+        assert!(!warnings.warn("broken-link", "missing page", Some("b")));
+        assert!(!warnings.warn("broken-link", "missing page", Some("a")));
+        assert!(warnings.warn("broken-link", "other page", Some("a")));
+
+        let entries = warnings.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].message, "missing page");
+        assert_eq!(entries[0].count, 3);
+        assert_eq!(entries[0].pages.len(), 2);
+    }
+
+    #[test]
+    fn test_warnings_from_many_threads() {
+        use rayon::prelude::*;
+        let warnings = Warnings::default();
+        (0..100).into_par_iter().for_each(|i| {
+            warnings.warn("broken-link", "missing page", Some(&format!("page {:03}", i)));
+        });
+        let entries = warnings.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].count, 100);
+        assert_eq!(entries[0].pages.len(), 100);
+    }
+
+    #[test]
+    fn test_summary_limits_listed_pages() {
+        let warnings = Warnings::default();
+        for page in ["a", "b", "c", "d"] {
+            warnings.warn("broken-link", "missing page", Some(page));
+        }
+        let summary = warnings.summary(2);
+        assert_eq!(summary, vec!["[broken-link] missing page (x4) in a, b and 2 more"]);
+        let summary = warnings.summary(0);
+        assert_eq!(summary, vec!["[broken-link] missing page (x4) in a, b, c, d"]);
+    }
+
+    #[test]
+    fn test_report_written() {
+        let temp = tempfile::tempdir().unwrap();
+        let warnings = Warnings::default();
+        warnings.warn("favorite", "favorite page not found: x", None);
+        warnings.write_report(temp.path()).unwrap();
+        let report = std::fs::read_to_string(temp.path().join("_warnings.json")).unwrap();
+        assert!(report.contains("\"count\": 1"));
+        assert!(!report.contains("pages"));
+    }
+
+    #[test]
+    fn test_unresolved_links() {
+        let index = PageIndex::from(vec![Page {
+            name: "existing".to_string(),
+            name_lower: "existing".to_string(),
+            aliases: vec!["ex".to_string()],
+            ..Default::default()
+        }]);
+        let links = content::unresolved_links(
+            "- [[existing]] [[ex]] [[missing]] and [[missing|again]]",
+            &index,
+        );
+        assert_eq!(links, vec!["missing"]);
+    }
+}
+
+#[cfg(test)]
+mod page_index_tests {
+    use crate::page::{Page, PageIndex};
+    use crate::query;
+    use super::TestPage;
+
+    fn page(name: &str, namespace: Option<&str>, tags: Vec<&str>, aliases: Vec<&str>) -> Page {
+        TestPage::new(name).namespace(namespace).tags(&tags).aliases(&aliases).build()
+    }
+
+    fn index() -> PageIndex {
+        PageIndex::from(vec![
+            page("Project Alpha", None, vec!["project"], vec!["alpha"]),
+            page("cyber valley/districts", Some("cyber valley"), vec![], vec![]),
+            page("Project Beta", None, vec!["project", "draft"], vec![]),
+        ])
+    }
+
+    #[test]
+    fn test_lookup_maps() {
+        let index = index();
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.named("project alpha").count(), 1);
+        assert_eq!(index.aliased("alpha").next().unwrap().name, "Project Alpha");
+        assert_eq!(index.in_namespace("cyber valley").count(), 1);
+        let tagged: Vec<_> = index.tagged("project").map(|p| p.name.as_str()).collect();
+        assert_eq!(tagged, vec!["Project Alpha", "Project Beta"]);
+        assert_eq!(index.tagged("missing").count(), 0);
+    }
+
+    #[test]
+    fn test_queries_use_lookup_maps() {
+        let index = index();
+        assert_eq!(query::execute("{{query (page-tags [[draft]])}}", &index).unwrap().len(), 1);
+        assert_eq!(query::execute("{{query (namespace [[cyber valley]])}}", &index).unwrap().len(), 1);
+        assert_eq!(query::execute("{{query (page [[Project Beta]])}}", &index).unwrap().len(), 1);
+        assert_eq!(
+            query::execute("{{query (and (page-tags [[project]]) (not (page-tags [[draft]])))}}", &index).unwrap().len(),
+            1
+        );
+    }
+}
+
+#[cfg(test)]
+mod site_config_tests {
+    use crate::favorites::{self, NavNode, SiteContext, SITE_CONFIG_SCHEMA_VERSION};
+    use crate::page::{Page, PageIndex};
+    use std::fs;
+    use tempfile::tempdir;
+    use super::TestPage;
+
+    fn page(name: &str, private: bool) -> Page {
+        TestPage::new(name).properties(if private { &[("private", "true")] } else { &[] }).build()
+    }
+
+    #[test]
+    fn test_navigation_tree() {
+        let index = PageIndex::from(vec![
+            page("projects/beta", false),
+            page("projects", false),
+            page("projects/alpha", false),
+            page("areas/health/sleep", false),
+            page("secret", true),
+            page("journals/2024_01_15", false),
+        ]);
+
+        let nav = favorites::navigation_tree(&index, false);
+        let titles: Vec<_> = nav.iter().map(|n| n.title.as_str()).collect();
+        assert_eq!(titles, vec!["areas", "projects"]);
+
+        // Intermediate namespace without a page has no path
+        assert_eq!(nav[0].path, None);
+        assert_eq!(nav[0].children[0].children[0].path.as_deref(), Some("areas/health/sleep"));
+
+        let projects = &nav[1];
+        assert_eq!(projects.path.as_deref(), Some("projects"));
+        assert_eq!(
+            projects.children,
+            vec![
+                NavNode { title: "alpha".into(), path: Some("projects/alpha".into()), children: vec![] },
+                NavNode { title: "beta".into(), path: Some("projects/beta".into()), children: vec![] },
+            ]
+        );
+
+        assert_eq!(favorites::navigation_tree(&index, true).len(), 3);
+    }
+
+    #[test]
+    fn test_site_config_versioned_fields() {
+        let temp = tempdir().unwrap();
+        let config_path = temp.path().join("config.edn");
+        fs::write(
+            &config_path,
+            ";; :preferred-language \"xx\"\n{:preferred-language \"de-DE\"\n :journal/page-title-format \"yyyy-MM-dd\"}",
+        ).unwrap();
+
+        let context = SiteContext { favorites: vec!["home".to_string()], ..Default::default() };
+        let config = favorites::write_site_config(&config_path, temp.path(), None, None, None, context).unwrap();
+        assert_eq!(config.schema_version, SITE_CONFIG_SCHEMA_VERSION);
+        assert_eq!(config.locale, "de-DE");
+
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(temp.path().join("_site_config.json")).unwrap()).unwrap();
+        assert_eq!(json["schema_version"], SITE_CONFIG_SCHEMA_VERSION);
+        assert_eq!(json["journals"]["page_title_format"], "yyyy-MM-dd");
+        assert_eq!(json["favorites"][0], "home");
+        assert_eq!(json["features"]["create_stubs"], false);
+    }
+
+    #[test]
+    fn test_schema_documents_every_field() {
+        let schema: serde_json::Value = serde_json::from_str(&favorites::site_config_schema()).unwrap();
+        let properties = schema["properties"].as_object().unwrap();
+        for field in [
+            "schema_version", "page_title", "home_page", "site_name", "locale",
+            "navigation", "favorites", "journals", "features",
+        ] {
+            assert!(properties.contains_key(field), "schema missing {}", field);
+        }
+    }
+}
+
+#[cfg(test)]
+mod references_tests {
+    use crate::config::Config;
+    use crate::page::{self, Page, PageIndex};
+    use crate::query;
+    use crate::references;
+    use std::collections::HashMap;
+    use std::fs;
+    use tempfile::tempdir;
+    use super::TestPage;
+
+    fn page(name: &str, content: &str, tags: Vec<&str>) -> Page {
+        TestPage::new(name).content(content).tags(&tags).build()
+    }
+
+    fn index() -> PageIndex {
+        PageIndex::from(vec![
+            page("rust", "", vec![]),
+            page("parser", "- built in [[rust]]", vec!["project"]),
+            page("old notes", "- about #rust", vec!["archived"]),
+            page("web app", "- [[Rust]] and [[project]]", vec![]),
+            page("unrelated", "- nothing here", vec![]),
+        ])
+    }
+
+    #[test]
+    fn test_parse_filters() {
+        let filters = references::parse_filters(r#"{"Project" true, "archived" false}"#);
+        assert_eq!(filters, vec![("project".to_string(), true), ("archived".to_string(), false)]);
+    }
+
+    #[test]
+    fn test_linked_references_unfiltered() {
+        let index = index();
+        let refs = references::linked_references(&index[0], &index, &[], false);
+        assert_eq!(refs, vec!["old notes", "parser", "web app"]);
+    }
+
+    #[test]
+    fn test_linked_references_exclude_and_include() {
+        let index = index();
+        let exclude = vec![("archived".to_string(), false)];
+        assert_eq!(references::linked_references(&index[0], &index, &exclude, false), vec!["parser", "web app"]);
+
+        let include = vec![("project".to_string(), true)];
+        assert_eq!(references::linked_references(&index[0], &index, &include, false), vec!["parser", "web app"]);
+    }
+
+    #[test]
+    fn test_linked_references_skip_private_pages() {
+        let mut secret = page("secret project", "- uses [[rust]]", vec![]);
+        secret.properties.insert("private".to_string(), "true".to_string());
+        let mut pages = index().into_pages();
+        pages.push(secret);
+        let index = PageIndex::from(pages);
+
+        assert_eq!(references::linked_references(&index[0], &index, &[], false), vec!["old notes", "parser", "web app"]);
+        assert_eq!(
+            references::linked_references(&index[0], &index, &[], true),
+            vec!["old notes", "parser", "secret project", "web app"]
+        );
+    }
+
+    #[test]
+    fn test_links_to_query_uses_resolved_links() {
+        let mut rust = page("rust", "", vec![]);
+        rust.aliases = vec!["rustlang".to_string()];
+        let index = PageIndex::from(vec![
+            rust,
+            page("aliased", "- written in [[RustLang]]", vec![]),
+            page("embedded", "- {{embed [[rust#Install]]}}", vec![]),
+            page("code", "- `[[rust]]`\n- ```\n[[rust]]\n```", vec![]),
+            page("mention", "- rust is fast", vec![]),
+        ]);
+        let names = |q: &str| query::execute(q, &index).unwrap().iter().map(|p| p.name.clone()).collect::<Vec<_>>();
+
+        assert_eq!(names("{{query [[rust]]}}"), vec!["aliased", "embedded"]);
+        assert_eq!(names("{{query (links-to [[rustlang]])}}"), vec!["aliased", "embedded"]);
+        assert_eq!(names("{{query (page-ref [[Rust]])}}"), vec!["aliased", "embedded"]);
+    }
+
+    #[test]
+    fn test_page_filters_override_graph_filters() {
+        let mut p = page("rust", "", vec![]);
+        p.properties = HashMap::from([("filters".to_string(), r#"{"archived" true}"#.to_string())]);
+        let graph = vec![("archived".to_string(), false), ("draft".to_string(), false)];
+        let filters = references::page_filters(&p, &graph);
+        assert_eq!(filters, vec![("draft".to_string(), false), ("archived".to_string(), true)]);
+    }
+
+    #[test]
+    fn test_graph_filters_from_config() {
+        let temp = tempdir().unwrap();
+        let config_path = temp.path().join("config.edn");
+        fs::write(&config_path, ";; :filters {\"x\" true}\n{:filters {\"archived\" false}}").unwrap();
+        assert_eq!(references::graph_filters(&config_path), vec![("archived".to_string(), false)]);
+    }
+
+    #[test]
+    fn test_filtered_page_gets_linked_references_frontmatter() {
+        let temp = tempdir().unwrap();
+        let pages_dir = temp.path().join("pages");
+        let output_dir = temp.path().join("out");
+        fs::create_dir_all(&pages_dir).unwrap();
+        fs::create_dir_all(&output_dir).unwrap();
+        fs::write(pages_dir.join("rust.md"), "filters:: {\"archived\" false}\n\n- Rust page").unwrap();
+        fs::write(pages_dir.join("old notes.md"), "tags:: archived\n\n- about [[rust]]").unwrap();
+        fs::write(pages_dir.join("parser.md"), "- built in [[rust]]").unwrap();
+
+        let index = page::build_index(&pages_dir).unwrap();
+        let config = Config { create_stubs: true, ..Default::default() };
+        page::process_page(&pages_dir.join("rust.md"), &output_dir, &index, &config, &HashMap::new(), temp.path()).unwrap();
+        page::process_page(&pages_dir.join("parser.md"), &output_dir, &index, &config, &HashMap::new(), temp.path()).unwrap();
+
+        let rust = fs::read_to_string(output_dir.join("rust.md")).unwrap();
+        assert!(rust.contains("linked-references:\n  - \"parser\"\n---"), "got: {}", rust);
+        let parser = fs::read_to_string(output_dir.join("parser.md")).unwrap();
+        assert!(!parser.contains("linked-references"));
+    }
+}
+
+#[cfg(test)]
+mod toc_tests {
+    use crate::config::Config;
+    use crate::page;
+    use crate::toc;
+    use std::collections::HashMap;
+
+    const CONTENT: &str = "- ## Setup\n  - install\n- ### Build [[Rust]]\n```\n# not a heading\n```\n- ## Setup\n- ## Usage & FAQ\n";
+
+    #[test]
+    fn test_headings_skip_code_and_dedupe_anchors() {
+        let headings = toc::headings(CONTENT);
+        let anchors: Vec<_> = headings.iter().map(|h| h.anchor.as_str()).collect();
+        assert_eq!(anchors, vec!["setup", "build-rust", "setup-1", "usage--faq"]);
+        assert_eq!(headings[1].text, "Build Rust");
+        assert_eq!(headings[1].level, 3);
+    }
+
+    #[test]
+    fn test_render_nests_by_level() {
+        let rendered = toc::render(&toc::headings(CONTENT));
+        assert!(rendered.starts_with("## Contents\n\n- [Setup](#setup)\n  - [Build Rust](#build-rust)\n"));
+    }
+
+    #[test]
+    fn test_toc_property_overrides_option() {
+        let headings = toc::headings(CONTENT);
+        assert!(toc::wants_toc(None, true, &headings));
+        assert!(!toc::wants_toc(None, false, &headings));
+        assert!(!toc::wants_toc(Some("false"), true, &headings));
+        let short = toc::headings("- ## Only one");
+        assert!(!toc::wants_toc(None, true, &short));
+        assert!(toc::wants_toc(Some("true"), false, &short));
+    }
+
+    #[test]
+    fn test_with_toc() {
+        let config = Config { toc: true, ..Default::default() };
+        let body = page::with_toc(CONTENT.to_string(), &HashMap::new(), &config);
+        assert!(body.starts_with("## Contents\n"));
+        assert!(body.ends_with(CONTENT));
+
+        let props = HashMap::from([("toc".to_string(), "false".to_string())]);
+        assert_eq!(page::with_toc(CONTENT.to_string(), &props, &config), CONTENT);
+    }
+}
+
+#[cfg(test)]
+mod badges_tests {
+    use crate::badges;
+    use crate::config::Config;
+    use crate::page::{self, PageIndex};
+    use std::collections::HashMap;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_badges_from_properties() {
+        let props = HashMap::from([
+            ("status".to_string(), "Active, [[On Hold]]".to_string()),
+            ("stage".to_string(), "beta".to_string()),
+        ]);
+        let keys = vec!["status".to_string()];
+        let result = badges::badges(&props, &keys);
+        let classes: Vec<_> = result.iter().map(|b| b.class.as_str()).collect();
+        assert_eq!(classes, vec!["status-active", "status-on-hold"]);
+        assert_eq!(result[1].value, "On Hold");
+    }
+
+    #[test]
+    fn test_badge_frontmatter_and_line() {
+        let props = HashMap::from([("status".to_string(), "archived".to_string())]);
+        let result = badges::badges(&props, &["status".to_string()]);
+        let fm = badges::insert_frontmatter("---\ntitle: \"x\"\n---\n", &result);
+        assert_eq!(fm, "---\ntitle: \"x\"\nstatus:\n  - \"archived\"\ncssclasses:\n  - \"status-archived\"\n---\n");
+        assert_eq!(
+            badges::render_line(&result),
+            "<span class=\"badge status-archived\" title=\"status\">Archived</span>\n\n"
+        );
+    }
+
+    #[test]
+    fn test_page_badges_written() {
+        let temp = tempdir().unwrap();
+        let pages_dir = temp.path().join("pages");
+        let output_dir = temp.path().join("out");
+        fs::create_dir_all(&pages_dir).unwrap();
+        fs::create_dir_all(&output_dir).unwrap();
+        fs::write(pages_dir.join("project.md"), "status:: active\n\n- Body").unwrap();
+
+        let config = Config { badge_line: true, ..Default::default() };
+        page::process_page(
+            &pages_dir.join("project.md"), &output_dir, &PageIndex::default(), &config, &HashMap::new(), temp.path(),
+        ).unwrap();
+        let output = fs::read_to_string(output_dir.join("project.md")).unwrap();
+        assert!(output.contains("cssclasses:\n  - \"status-active\""), "got: {}", output);
+        assert!(output.contains("---\n\n<span class=\"badge status-active\""), "got: {}", output);
+    }
+}
+
+#[cfg(test)]
+mod git_dates_tests {
+    use crate::config::Config;
+    use crate::external;
+    use crate::git;
+    use std::fs;
+    use std::path::Path;
+    use std::process::Command;
+    use tempfile::tempdir;
+
+    fn git(dir: &Path, args: &[&str], date: &str) {
+        let status = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .env("GIT_AUTHOR_DATE", date)
+            .env("GIT_COMMITTER_DATE", date)
+            .env("GIT_AUTHOR_NAME", "test")
+            .env("GIT_AUTHOR_EMAIL", "test@example.com")
+            .env("GIT_COMMITTER_NAME", "test")
+            .env("GIT_COMMITTER_EMAIL", "test@example.com")
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    #[test]
+    fn test_history_dates_follow_renames_in_subdirectory_graph() {
+        let temp = tempdir().unwrap();
+        let repo = temp.path();
+        let graph = repo.join("graph");
+        fs::create_dir_all(graph.join("pages")).unwrap();
+        git(repo, &["init", "-q"], "2024-01-01T10:00:00+00:00");
+
+        fs::write(graph.join("pages/old.md"), "- a fairly long line of content so rename detection is confident\n- second line\n").unwrap();
+        git(repo, &["add", "-A"], "2024-01-01T10:00:00+00:00");
+        git(repo, &["commit", "-q", "-m", "add"], "2024-01-01T10:00:00+00:00");
+
+        fs::write(graph.join("pages/other.md"), "- other\n").unwrap();
+        git(repo, &["add", "-A"], "2024-02-01T10:00:00+00:00");
+        git(repo, &["commit", "-q", "-m", "other"], "2024-02-01T10:00:00+00:00");
+
+        git(repo, &["mv", "graph/pages/old.md", "graph/pages/new.md"], "2024-03-01T10:00:00+00:00");
+        git(repo, &["commit", "-q", "-m", "rename"], "2024-03-01T10:00:00+00:00");
+
+        let dates = git::file_dates(&graph);
+        assert_eq!(
+            dates.get("pages/new.md"),
+            Some(&("2024-03-01".to_string(), "2024-01-01".to_string()))
+        );
+        assert_eq!(
+            dates.get("pages/other.md"),
+            Some(&("2024-02-01".to_string(), "2024-02-01".to_string()))
+        );
+        assert!(!dates.contains_key("pages/old.md"));
+    }
+
+    #[test]
+    fn test_filesystem_fallback_outside_git() {
+        let temp = tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("pages")).unwrap();
+        fs::write(temp.path().join("pages/note.md"), "- note").unwrap();
+
+        let dates = git::file_dates(temp.path());
+        let (modified, created) = dates.get("pages/note.md").expect("fallback date");
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        assert_eq!(modified, &today);
+        assert_eq!(created, &today);
+    }
+
+    #[test]
+    fn test_relative_key_uses_forward_slashes() {
+        let root = Path::new("graph");
+        assert_eq!(git::relative_key(&root.join("pages").join("a.md"), root), "pages/a.md");
+    }
+
+    #[test]
+    fn test_snapshot_reads_graph_at_revision() {
+        let temp = tempdir().unwrap();
+        let repo = temp.path();
+        let graph = repo.join("graph");
+        fs::create_dir_all(graph.join("pages")).unwrap();
+        git(repo, &["init", "-q"], "2024-01-01T10:00:00+00:00");
+
+        fs::write(graph.join("pages/garden.md"), "- first version\n").unwrap();
+        git(repo, &["add", "-A"], "2024-01-01T10:00:00+00:00");
+        git(repo, &["commit", "-q", "-m", "first"], "2024-01-01T10:00:00+00:00");
+        git(repo, &["tag", "last-year"], "2024-01-01T10:00:00+00:00");
+
+        fs::write(graph.join("pages/garden.md"), "- second version\n").unwrap();
+        fs::write(graph.join("pages/new.md"), "- added later\n").unwrap();
+        git(repo, &["add", "-A"], "2025-01-01T10:00:00+00:00");
+        git(repo, &["commit", "-q", "-m", "second"], "2025-01-01T10:00:00+00:00");
+
+        let snapshot = git::Snapshot::create(&graph, "last-year").unwrap();
+        let root = snapshot.path().to_path_buf();
+        assert_eq!(snapshot.date, "2024-01-01");
+        assert_eq!(fs::read_to_string(root.join("pages/garden.md")).unwrap(), "- first version\n");
+        assert!(!root.join("pages/new.md").exists());
+
+        // Dates as of the revision, not HEAD
+        let dates = git::file_dates(&root);
+        assert_eq!(
+            dates.get("pages/garden.md"),
+            Some(&("2024-01-01".to_string(), "2024-01-01".to_string()))
+        );
+
+        // Caches stay in the graph folder, not in the snapshot removed below
+        let config = Config { input_dir: root.clone(), graph_root: graph.clone(), ..Default::default() };
+        assert_eq!(config.cache_path(None, external::DEFAULT_CACHE), graph.join(external::DEFAULT_CACHE));
+        assert_eq!(config.cache_path(Some(Path::new("links.json")), external::DEFAULT_CACHE), Path::new("links.json"));
+
+        drop(snapshot);
+        assert!(!root.exists());
+    }
+
+    #[test]
+    fn test_snapshot_unknown_revision() {
+        let temp = tempdir().unwrap();
+        git(temp.path(), &["init", "-q"], "2024-01-01T10:00:00+00:00");
+        let err = git::Snapshot::create(temp.path(), "no-such-ref").unwrap_err();
+        assert!(err.to_string().contains("unknown revision"));
+    }
+}
+
+#[cfg(test)]
+mod date_property_tests {
+    use crate::frontmatter;
+    use crate::git;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::process::Command;
+    use tempfile::tempdir;
+
+    fn props(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_properties_override_file_dates() {
+        let file_dates = Some(("2025-01-01", "2024-01-01"));
+        let p = props(&[("created", "[[Mar 3rd, 2020]]"), ("updated", "2021-06-01T09:30")]);
+        assert_eq!(
+            frontmatter::page_dates(&p, file_dates),
+            (Some("2021-06-01".to_string()), Some("2020-03-03".to_string()))
+        );
+
+        let fm = frontmatter::generate("test", &p, file_dates, &Default::default());
+        assert!(fm.contains("modified: 2021-06-01\n"));
+        assert!(fm.contains("created: 2020-03-03\n"));
+    }
+
+    #[test]
+    fn test_date_property_fills_both() {
+        let p = props(&[("date", "2022-02-02")]);
+        assert_eq!(
+            frontmatter::page_dates(&p, None),
+            (Some("2022-02-02".to_string()), Some("2022-02-02".to_string()))
+        );
+        let p = props(&[("date", "2022-02-02"), ("updated", "2023-03-03")]);
+        assert_eq!(frontmatter::page_dates(&p, None).0, Some("2023-03-03".to_string()));
+    }
+
+    #[test]
+    fn test_invalid_date_property_ignored() {
+        let p = props(&[("created", "someday")]);
+        assert_eq!(
+            frontmatter::page_dates(&p, Some(("2025-01-01", "2024-01-01"))),
+            (Some("2025-01-01".to_string()), Some("2024-01-01".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_untracked_files_in_repo_get_file_dates() {
+        let temp = tempdir().unwrap();
+        let ok = Command::new("git").args(["init", "-q"]).current_dir(temp.path()).status().unwrap();
+        assert!(ok.success());
+        fs::create_dir_all(temp.path().join("pages")).unwrap();
+        fs::write(temp.path().join("pages/synced.md"), "- not committed").unwrap();
+
+        let dates = git::file_dates(temp.path());
+        assert!(dates.contains_key("pages/synced.md"));
+    }
+}
+
+#[cfg(test)]
+mod escape_tests {
+    use crate::escape;
+    use crate::frontmatter;
+    use crate::page::{Page, PageIndex};
+    use crate::query;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_yaml_escaping() {
+        assert_eq!(escape::yaml(r#"say "hi" \ now"#), r#"say \"hi\" \\ now"#);
+        assert_eq!(escape::yaml("two\nlines"), "two\\nlines");
+        assert_eq!(escape::yaml_scalar("rust"), "rust");
+        assert_eq!(escape::yaml_scalar("100% done"), "100% done");
+        assert_eq!(escape::yaml_scalar("%done"), "\"%done\"");
+        assert_eq!(escape::yaml_scalar("> quote"), "\"> quote\"");
+        assert_eq!(escape::yaml_scalar("key: value"), "\"key: value\"");
+        assert_eq!(escape::yaml_scalar("true"), "\"true\"");
+    }
+
+    #[test]
+    fn test_html_and_markdown_escaping() {
+        assert_eq!(escape::html("<b>\"R&D\"</b>"), "&lt;b&gt;&quot;R&amp;D&quot;&lt;/b&gt;");
+        assert_eq!(escape::markdown_text("a <tag> b"), "a &lt;tag&gt; b");
+        assert_eq!(escape::table_cell("a | b <c>\nd"), "a &#124; b &lt;c&gt; d");
+    }
+
+    #[test]
+    fn test_frontmatter_quotes_unsafe_tags() {
+        let props = HashMap::from([("tags".to_string(), "%wip, >later, ok".to_string())]);
+        let fm = frontmatter::generate("test", &props, None, &Default::default());
+        assert!(fm.contains("tags:\n  - \"%wip\"\n  - \">later\"\n  - ok\n"), "got: {}", fm);
+    }
+
+    #[test]
+    fn test_query_table_escapes_cells() {
+        let page = Page {
+            name: "p".to_string(),
+            name_lower: "p".to_string(),
+            properties: HashMap::from([("range".to_string(), "<10 | >5".to_string())]),
+            tags: vec!["x".to_string()],
+            ..Default::default()
+        };
+        let index = PageIndex::from(vec![page]);
+        let results = query::execute("{{query (page-tags [[x]])}}", &index).unwrap();
+        let options = query::QueryOptions {
+            properties: vec!["page".to_string(), "range".to_string()],
+            table: Some(true),
+            ..Default::default()
+        };
+        let md = query::results_to_markdown_with_options(&results, "q", &options);
+        assert!(md.contains("&lt;10 &#124; &gt;5"), "got: {}", md);
+    }
+
+    #[test]
+    fn test_favorite_titles_escaped() {
+        use std::fs;
+        let temp = tempfile::tempdir().unwrap();
+        let favorites_dir = temp.path().join("favorites");
+        let pages_dir = temp.path().join("pages");
+        fs::create_dir_all(&favorites_dir).unwrap();
+        fs::create_dir_all(&pages_dir).unwrap();
+        fs::write(pages_dir.join("Q&A \"<draft>\".md"), "- x").unwrap();
+
+        let favs = vec!["Q&A \"<draft>\"".to_string()];
+        crate::favorites::process_favorites(
+            &temp.path().join("config.edn"), &favorites_dir, &pages_dir, &PageIndex::default(), Some(&favs), &Default::default(),
+            crate::favorites::DEFAULT_OUTPUTS,
+        ).unwrap();
+        let index = fs::read_to_string(favorites_dir.join("index.md")).unwrap();
+        assert!(index.contains("|Q&A \"&lt;draft&gt;\"]]"), "got: {}", index);
+        let redirect = fs::read_dir(&favorites_dir).unwrap()
+            .filter_map(|e| e.ok())
+            .find(|e| e.file_name() != "index.md")
+            .map(|e| fs::read_to_string(e.path()).unwrap())
+            .unwrap();
+        assert!(redirect.contains("title: \"Q&A \\\"<draft>\\\"\""), "got: {}", redirect);
+    }
+}
+
+#[cfg(test)]
+mod icon_map_tests {
+    use crate::frontmatter;
+    use crate::icons::{Icon, IconMap};
+    use crate::page::PageIndex;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::Path;
+
+    fn icon_map(base: &Path) -> IconMap {
+        IconMap::from_entries(
+            HashMap::from([
+                ("🚀".to_string(), "icons/rocket.svg".to_string()),
+                ("⭐️".to_string(), "ph ph-star".to_string()),
+            ]),
+            base,
+        )
+    }
+
+    #[test]
+    fn test_assets_and_classes() {
+        let map = icon_map(Path::new("/graph/logseq"));
+        assert_eq!(
+            map.get("🚀"),
+            Some(&Icon::Asset {
+                source: Path::new("/graph/logseq/icons/rocket.svg").to_path_buf(),
+                src: "/assets/icons/rocket.svg".to_string(),
+            })
+        );
+        // Variation selectors don't matter
+        assert_eq!(map.get("⭐"), Some(&Icon::Class("ph ph-star".to_string())));
+        assert_eq!(map.get("🌱"), None);
+    }
+
+    #[test]
+    fn test_frontmatter_uses_mapped_icon() {
+        let map = icon_map(Path::new("."));
+        let props = HashMap::from([("icon".to_string(), "🚀".to_string())]);
+        let fm = frontmatter::generate("Launch", &props, None, &map);
+        assert!(fm.contains("title: \"Launch\"\n"), "got: {}", fm);
+        assert!(fm.contains("icon: \"🚀\"\n"));
+        assert!(fm.contains("icon-src: \"/assets/icons/rocket.svg\"\n"));
+
+        // Unmapped emoji stay in the title
+        let props = HashMap::from([("icon".to_string(), "🌱".to_string())]);
+        let fm = frontmatter::generate("Garden", &props, None, &map);
+        assert!(fm.contains("title: \"🌱 Garden\"\n"), "got: {}", fm);
+        assert!(!fm.contains("icon-src"));
+    }
+
+    #[test]
+    fn test_load_and_copy_assets() {
+        let temp = tempfile::tempdir().unwrap();
+        let logseq = temp.path().join("logseq");
+        fs::create_dir_all(logseq.join("icons")).unwrap();
+        fs::write(logseq.join("icons/rocket.svg"), "<svg/>").unwrap();
+        fs::write(logseq.join("icons.json"), r#"{"🚀": "icons/rocket.svg", "🐛": "icons/missing.svg"}"#).unwrap();
+
+        let path = crate::icons::map_path(temp.path(), None).unwrap();
+        let map = IconMap::load(&path).unwrap();
+        let output = temp.path().join("out");
+        assert_eq!(map.copy_assets(&output).unwrap(), 1);
+        assert!(output.join("assets/icons/rocket.svg").exists());
+
+        let empty = tempfile::tempdir().unwrap();
+        assert!(crate::icons::map_path(empty.path(), None).is_none());
+    }
+
+    #[test]
+    fn test_favorites_render_mapped_icon() {
+        let temp = tempfile::tempdir().unwrap();
+        let favorites_dir = temp.path().join("favorites");
+        let pages_dir = temp.path().join("pages");
+        fs::create_dir_all(&favorites_dir).unwrap();
+        fs::create_dir_all(&pages_dir).unwrap();
+        fs::write(pages_dir.join("launch.md"), "---\ntitle: \"launch\"\nicon: \"🚀\"\n---\n").unwrap();
+
+        let favs = vec!["launch".to_string()];
+        crate::favorites::process_favorites(
+            &temp.path().join("config.edn"), &favorites_dir, &pages_dir, &PageIndex::default(), Some(&favs),
+            &icon_map(temp.path()), crate::favorites::DEFAULT_OUTPUTS,
+        ).unwrap();
+
+        let index = fs::read_to_string(favorites_dir.join("index.md")).unwrap();
+        assert!(index.contains("- <img class=\"icon\" src=\"/assets/icons/rocket.svg\" alt=\"🚀\"> [[launch|launch]]"), "got: {}", index);
+        let redirect = fs::read_to_string(favorites_dir.join("launch.md")).unwrap();
+        assert!(redirect.contains("title: \"launch\"\n"), "got: {}", redirect);
+        assert!(redirect.contains("icon-src: \"/assets/icons/rocket.svg\"\n"));
+    }
+}
+
+#[cfg(test)]
+mod diagnostics_tests {
+    use crate::content;
+    use crate::page::PageIndex;
+    use crate::warnings::{Diagnostic, Warnings};
+
+    #[test]
+    fn test_fixed_tables_are_reported() {
+        let input = "- | a | b |\n  | 1 | 2 |";
+        let transformed = content::transform_page(input, &PageIndex::default());
+        assert_eq!(
+            transformed.diagnostics,
+            vec![Diagnostic::new("table", "added missing table separator row")]
+        );
+
+        let input = "- | a | b |\n  |---|\n  | 1 | 2 |";
+        let transformed = content::transform_page(input, &PageIndex::default());
+        assert!(transformed.diagnostics.iter().any(|d| d.message.contains("dropped separator row with 1 columns")));
+    }
+
+    #[test]
+    fn test_unknown_macros_are_reported() {
+        let input = "- {{poll Lunch?}}\n- {{renderer :todomaster}}\n- ```\n  {{template}}\n  ```\n- ~~~\n  {{other}}\n  ~~~";
+        let transformed = content::transform_page(input, &PageIndex::default());
+        let messages: Vec<&str> = transformed.diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec!["dropped unsupported {{renderer :todomaster}}", "unknown macro {{poll}} left as text"]
+        );
+    }
+
+    #[test]
+    fn test_clean_page_has_no_diagnostics() {
+        let input = "- | a | b |\n  |---|---|\n  | 1 | 2 |\n- {{youtube https://youtu.be/x}}";
+        assert!(content::transform_page(input, &PageIndex::default()).diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_page_diagnostics_and_errors_recorded() {
+        let warnings = Warnings::default();
+        let diagnostics = vec![Diagnostic::new("macro", "unknown macro {{tweet}} left as text")];
+        warnings.page_diagnostics(&diagnostics, "a");
+        warnings.page_diagnostics(&diagnostics, "b");
+        assert!(warnings.error("pages/c.md: invalid UTF-8", Some("c")));
+        assert!(!warnings.error("pages/c.md: invalid UTF-8", Some("c")));
+
+        let entries = warnings.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].kind, "error");
+        assert_eq!(entries[1].kind, "macro");
+        assert_eq!(entries[1].pages.len(), 2);
+    }
+}
+
+#[cfg(test)]
+mod strict_mode_tests {
+    use crate::config::Config;
+    use crate::page::PageIndex;
+    use crate::warnings::Warnings;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn graph_with_broken_page() -> tempfile::TempDir {
+        let temp = tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("pages")).unwrap();
+        fs::create_dir_all(temp.path().join("journals")).unwrap();
+        fs::write(temp.path().join("pages/good.md"), "- fine").unwrap();
+        // A folder named like a page fails to read
+        fs::create_dir_all(temp.path().join("pages/broken.md")).unwrap();
+        fs::create_dir_all(temp.path().join("journals/2025_01_15.md")).unwrap();
+        temp
+    }
+
+    fn config(temp: &tempfile::TempDir, strict: bool) -> Config {
+        Config {
+            input_dir: temp.path().to_path_buf(),
+            output_dir: temp.path().join("out"),
+            create_stubs: false,
+            strict,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_failed_pages_are_skipped_by_default() {
+        let temp = graph_with_broken_page();
+        let stats = crate::run_preprocessor(&config(&temp, false)).unwrap();
+        assert_eq!(stats.pages_published, 1);
+        assert!(temp.path().join("out/good.md").exists());
+        assert!(!temp.path().join("out/broken.md").exists());
+    }
+
+    #[test]
+    fn test_strict_aborts_on_first_failure() {
+        let temp = graph_with_broken_page();
+        let err = crate::run_preprocessor(&config(&temp, true)).unwrap_err();
+        assert!(format!("{:#}", err).contains("broken.md"), "got: {:#}", err);
+    }
+
+    #[test]
+    fn test_strict_journals() {
+        let temp = graph_with_broken_page();
+        let journals = temp.path().join("journals");
+        let output = temp.path().join("out/journals");
+        fs::create_dir_all(&output).unwrap();
+        let index = PageIndex::default();
+
+        let count = crate::journals::process_journals(&journals, &output, &index, &config(&temp, false)).unwrap();
+        assert_eq!(count, 0);
+        let err = crate::journals::process_journals(&journals, &output, &index, &config(&temp, true)).unwrap_err();
+        assert!(format!("{:#}", err).contains("2025_01_15.md"), "got: {:#}", err);
+    }
+
+    #[test]
+    fn test_errors_listed_separately_from_warnings() {
+        let warnings = Warnings::default();
+        warnings.warn("broken-link", "missing", Some("a"));
+        warnings.error("pages/b.md: stream did not contain valid UTF-8", Some("b"));
+
+        let errors = warnings.errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "pages/b.md: stream did not contain valid UTF-8");
+        assert_eq!(warnings.summary(0), vec!["[broken-link] missing in a"]);
+    }
+}
+
+#[cfg(test)]
+mod journal_tag_index_tests {
+    use crate::config::Config;
+    use crate::journals;
+    use crate::page;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn graph() -> tempfile::TempDir {
+        let temp = tempdir().unwrap();
+        let journals_dir = temp.path().join("journals");
+        fs::create_dir_all(&journals_dir).unwrap();
+        fs::create_dir_all(temp.path().join("pages")).unwrap();
+        fs::write(
+            journals_dir.join("2025_01_15.md"),
+            "- Standup #meeting\n\t- discussed [[Roadmap]]\n- Lunch\n- Retro #[[team sync]] #meeting",
+        ).unwrap();
+        fs::write(journals_dir.join("2025_02_03.md"), "- Planning\n  tags:: meeting\n- see https://x.com/#anchor").unwrap();
+        fs::write(journals_dir.join("2025_02_04.md"), "private:: true\n\n- Secret #meeting").unwrap();
+        temp
+    }
+
+    #[test]
+    fn test_blocks_grouped_by_tag_and_month() {
+        let temp = graph();
+        let index = page::build_graph_index(temp.path()).unwrap();
+        let output = temp.path().join("out");
+        fs::create_dir_all(&output).unwrap();
+
+        let count = journals::create_tag_index(&output, &index, &[], false).unwrap();
+        assert_eq!(count, 2);
+        let md = fs::read_to_string(output.join("by-tag.md")).unwrap();
+
+        let meeting = md.find("## meeting").unwrap();
+        let team = md.find("## team sync").unwrap();
+        let february = md.find("### February 2025").unwrap();
+        let january = md.find("### January 2025").unwrap();
+        assert!(meeting < february && february < january && january < team, "got: {}", md);
+
+        assert!(md.contains("- [[journals/2025-01-15|January 15, 2025]]\n  - Standup #meeting\n  \t- discussed [[Roadmap]]\n  - Retro"), "got: {}", md);
+        assert!(md.contains("  - Planning\n"), "got: {}", md);
+        assert!(!md.contains("Lunch"));
+        assert!(!md.contains("Secret"), "private journals are skipped: {}", md);
+        assert!(!md.contains("#anchor"));
+    }
+
+    #[test]
+    fn test_only_requested_tags() {
+        let temp = graph();
+        let index = page::build_graph_index(temp.path()).unwrap();
+        let output = temp.path().join("out");
+        fs::create_dir_all(&output).unwrap();
+
+        journals::create_tag_index(&output, &index, &["Team Sync".to_string()], false).unwrap();
+        let md = fs::read_to_string(output.join("by-tag.md")).unwrap();
+        assert!(md.contains("## team sync"));
+        assert!(!md.contains("## meeting"));
+    }
+
+    #[test]
+    fn test_generated_with_journals_when_enabled() {
+        let temp = graph();
+        let index = page::build_graph_index(temp.path()).unwrap();
+        let output = temp.path().join("out");
+        fs::create_dir_all(&output).unwrap();
+        let config = Config {
+            input_dir: temp.path().to_path_buf(),
+            output_dir: output.clone(),
+            journal_tags: Some(Vec::new()),
+            ..Default::default()
+        };
+        journals::process_journals(&temp.path().join("journals"), &output, &index, &config).unwrap();
+        assert!(output.join("by-tag.md").exists());
+    }
+}
+
+#[cfg(test)]
+mod workflow_tests {
+    use crate::content;
+    use crate::page::{Page, PageIndex};
+    use crate::query;
+    use crate::workflow::Workflow;
+
+    fn tasks() -> Vec<Page> {
+        vec![
+            Page { name: "a".to_string(), name_lower: "a".to_string(), content: "- TODO write".to_string(), ..Default::default() },
+            Page { name: "b".to_string(), name_lower: "b".to_string(), content: "- LATER read".to_string(), ..Default::default() },
+            Page { name: "c".to_string(), name_lower: "c".to_string(), content: "- NOW ship".to_string(), ..Default::default() },
+        ]
+    }
+
+    fn names(pages: Vec<&Page>) -> Vec<&str> {
+        let mut names: Vec<&str> = pages.iter().map(|p| p.name.as_str()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_parse_preferred_workflow() {
+        assert_eq!(Workflow::from_edn(":preferred-workflow :now"), Workflow::Now);
+        assert_eq!(Workflow::from_edn("{:preferred-workflow :todo}"), Workflow::Todo);
+        assert_eq!(Workflow::from_edn("{}"), Workflow::Todo);
+    }
+
+    #[test]
+    fn test_task_queries_match_workflow_equivalents() {
+        let now = PageIndex::from(tasks()).with_workflow(Workflow::Now);
+        assert_eq!(names(query::execute("{{query (task TODO)}}", &now).unwrap()), vec!["a", "b"]);
+        assert_eq!(names(query::execute("{{query (task DOING)}}", &now).unwrap()), vec!["c"]);
+
+        let todo = PageIndex::from(tasks());
+        assert_eq!(names(query::execute("{{query (task LATER)}}", &todo).unwrap()), vec!["a", "b"]);
+        assert_eq!(names(query::execute("{{query (task TODO)}}", &todo).unwrap()), vec!["a"]);
+    }
+
+    #[test]
+    fn test_later_renders_as_plain_todo_in_now_workflow() {
+        let now = PageIndex::default().with_workflow(Workflow::Now);
+        assert_eq!(content::transform("- LATER read", &now), "- [ ] read");
+        assert_eq!(content::transform("- LATER read", &PageIndex::default()), "- [ ] 📅 read");
+    }
+
+    #[test]
+    fn test_graph_index_reads_workflow() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(temp.path().join("pages")).unwrap();
+        std::fs::create_dir_all(temp.path().join("logseq")).unwrap();
+        std::fs::write(temp.path().join("logseq/config.edn"), "{:preferred-workflow :now}").unwrap();
+        let index = crate::page::build_graph_index(temp.path()).unwrap();
+        assert_eq!(index.workflow(), Workflow::Now);
+    }
+}
+
+#[cfg(test)]
+mod windows_compat_tests {
+    use crate::config::Config;
+    use crate::page::{self, PageIndex};
+    use crate::resolver;
+    use crate::source;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    // Windows-authored page: BOM, CRLF line endings, properties and a table
+    const CRLF_PAGE: &str = "\u{feff}tags:: project, rust\r\nalias:: Proj\r\n\r\n- Intro #idea\r\n- | a | b |\r\n  | 1 | 2 |\r\n- see [[Other]]\r\n";
+
+    #[test]
+    fn test_normalize_line_endings() {
+        assert_eq!(source::normalize("a\r\nb\rc\n"), "a\nb\nc\n");
+        assert_eq!(source::normalize("\u{feff}title"), "title");
+        assert_eq!(source::normalize("plain\n"), "plain\n");
+    }
+
+    #[test]
+    fn test_crlf_page_indexed_like_lf() {
+        let temp = tempdir().unwrap();
+        let pages = temp.path().join("pages");
+        fs::create_dir_all(&pages).unwrap();
+        fs::write(pages.join("crlf.md"), CRLF_PAGE).unwrap();
+        fs::write(pages.join("lf.md"), source::normalize(CRLF_PAGE)).unwrap();
+
+        let index = page::build_index(&pages).unwrap();
+        let crlf = index.iter().find(|p| p.name == "crlf").unwrap();
+        let lf = index.iter().find(|p| p.name == "lf").unwrap();
+        assert_eq!(crlf.properties, lf.properties);
+        assert_eq!(crlf.properties.get("alias").map(String::as_str), Some("Proj"));
+        assert_eq!(crlf.tags, vec!["project", "rust", "idea"]);
+        assert_eq!(crlf.aliases, lf.aliases);
+        assert!(!crlf.content.contains('\r'));
+    }
+
+    #[test]
+    fn test_crlf_page_output_has_no_carriage_returns() {
+        let temp = tempdir().unwrap();
+        let pages = temp.path().join("pages");
+        let output = temp.path().join("out");
+        fs::create_dir_all(&pages).unwrap();
+        fs::create_dir_all(&output).unwrap();
+        fs::write(pages.join("crlf.md"), CRLF_PAGE).unwrap();
+
+        let config = Config { create_stubs: false, ..Default::default() };
+        let dates = Default::default();
+        page::process_page(&pages.join("crlf.md"), &output, &PageIndex::default(), &config, &dates, temp.path()).unwrap();
+
+        let md = fs::read_to_string(output.join("crlf.md")).unwrap();
+        assert!(!md.contains('\r'), "got: {:?}", md);
+        assert!(md.starts_with("---\ntitle: \"crlf\"\n"), "got: {:?}", md);
+        assert!(md.contains("tags:\n  - project\n  - rust\n"));
+        assert!(md.contains("|---|---|"), "table separator added: {:?}", md);
+    }
+
+    #[test]
+    fn test_crlf_journal_and_config() {
+        let temp = tempdir().unwrap();
+        let journals = temp.path().join("journals");
+        let output = temp.path().join("out");
+        fs::create_dir_all(&journals).unwrap();
+        fs::create_dir_all(&output).unwrap();
+        fs::create_dir_all(temp.path().join("logseq")).unwrap();
+        fs::write(journals.join("2025_01_15.md"), "tags:: meeting\r\n\r\n- LATER call\r\n").unwrap();
+        fs::write(temp.path().join("logseq/config.edn"), "{:preferred-workflow :now\r\n :favorites [\"a\"\r\n \"b\"]}\r\n").unwrap();
+
+        assert_eq!(
+            crate::favorites::favorite_names(&temp.path().join("logseq/config.edn"), None).unwrap(),
+            vec!["a", "b"]
+        );
+        let index = page::build_graph_index(temp.path()).unwrap();
+        crate::journals::process_journals(&journals, &output, &index, &Config::default()).unwrap();
+        let md = fs::read_to_string(output.join("2025-01-15.md")).unwrap();
+        assert!(!md.contains('\r'), "got: {:?}", md);
+        assert!(md.contains("- [ ] call"), "LATER is a plain task in the NOW workflow: {:?}", md);
+    }
+
+    #[test]
+    fn test_site_paths_joined_per_component() {
+        let dir = Path::new("out");
+        let path = resolver::output_path(dir, "projects/web app/v1.2");
+        let parts: Vec<_> = path.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+        assert_eq!(parts, vec!["out", "projects", "web app", "v1.2.md"]);
+        assert_eq!(resolver::join_site_path(dir, "./img//a.png"), dir.join("img").join("a.png"));
+    }
+}
+
+#[cfg(test)]
+mod rename_tests {
+    use crate::rename;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn graph() -> tempfile::TempDir {
+        let temp = tempdir().unwrap();
+        for dir in ["pages", "journals", "logseq"] {
+            fs::create_dir_all(temp.path().join(dir)).unwrap();
+        }
+        fs::write(temp.path().join("pages/Old Page.md"), "title:: Old Page\n\n- links to [[Other]]").unwrap();
+        fs::write(
+            temp.path().join("pages/Other.md"),
+            "tags:: old page, rust\nalias:: Elsewhere\n\n- see [[Old Page]] and [[old page|the old one]]\n- #[[Old Page]] but not [[Old Page/child]] or [[Old Pages]]",
+        ).unwrap();
+        fs::write(temp.path().join("journals/2025_01_15.md"), "- met about [[Old Page]]\r\n- unrelated\r\n").unwrap();
+        fs::write(temp.path().join("logseq/config.edn"), "{:favorites [\"old page\" \"other\"]\n :default-home {:page \"Old Page\"}}").unwrap();
+        temp
+    }
+
+    #[test]
+    fn test_plan_rewrites_references() {
+        let temp = graph();
+        let plan = rename::plan(temp.path(), "Old Page", "New Page").unwrap();
+
+        let (from, to) = plan.file.as_ref().unwrap();
+        assert!(from.ends_with("pages/Old Page.md"));
+        assert!(to.ends_with("pages/New Page.md"));
+
+        let other = plan.edits.iter().find(|e| e.path.ends_with("pages/Other.md")).unwrap();
+        assert_eq!(other.replacements, 4);
+        assert!(other.content.starts_with("tags:: New Page, rust\n"), "got: {}", other.content);
+        assert!(other.content.contains("see [[New Page]] and [[New Page|the old one]]"));
+        assert!(other.content.contains("#[[New Page]] but not [[Old Page/child]] or [[Old Pages]]"));
+
+        let page = plan.edits.iter().find(|e| e.path.ends_with("pages/Old Page.md")).unwrap();
+        assert!(page.content.starts_with("title:: New Page\n"));
+
+        let config = plan.edits.iter().find(|e| e.path.ends_with("logseq/config.edn")).unwrap();
+        assert_eq!(config.content, "{:favorites [\"new page\" \"other\"]\n :default-home {:page \"new page\"}}");
+
+        // Planning writes nothing (dry run)
+        assert!(temp.path().join("pages/Old Page.md").exists());
+        assert!(fs::read_to_string(temp.path().join("pages/Other.md")).unwrap().contains("[[Old Page]]"));
+    }
+
+    #[test]
+    fn test_apply_moves_file_and_backs_up() {
+        let temp = graph();
+        let plan = rename::plan(temp.path(), "old page", "Projects/New Page").unwrap();
+        let backup = rename::apply(&plan, temp.path(), true).unwrap().unwrap();
+
+        assert!(!temp.path().join("pages/Old Page.md").exists());
+        let moved = fs::read_to_string(temp.path().join("pages/Projects___New Page.md")).unwrap();
+        assert!(moved.starts_with("title:: Projects/New Page\n"));
+
+        // CRLF files stay CRLF
+        let journal = fs::read_to_string(temp.path().join("journals/2025_01_15.md")).unwrap();
+        assert_eq!(journal, "- met about [[Projects/New Page]]\r\n- unrelated\r\n");
+
+        assert!(backup.starts_with(temp.path().join("logseq/bak")));
+        let saved = fs::read_to_string(backup.join("pages/Other.md")).unwrap();
+        assert!(saved.contains("[[Old Page]]"));
+        assert!(backup.join("pages/Old Page.md").exists());
+    }
+
+    #[test]
+    fn test_hashtags_and_existing_target() {
+        let temp = graph();
+        fs::write(temp.path().join("pages/draft.md"), "- #draft idea, #drafts stay\n#draft").unwrap();
+        let plan = rename::plan(temp.path(), "draft", "wip").unwrap();
+        let edit = plan.edits.iter().find(|e| e.path.ends_with("pages/draft.md")).unwrap();
+        assert_eq!(edit.content, "- #wip idea, #drafts stay\n#wip");
+
+        let err = rename::plan(temp.path(), "Old Page", "Other").unwrap_err();
+        assert!(err.to_string().contains("already exists"), "got: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod unicode_name_tests {
+    use crate::config::Config;
+    use crate::page::{self, Page, PageIndex};
+    use crate::query::TagMatch;
+    use crate::resolver::{self, MatchKind, SlugStyle};
+    use crate::source;
+    use std::collections::HashMap;
+    use std::fs;
+    use tempfile::tempdir;
+    use super::TestPage;
+
+    fn page(name: &str) -> Page {
+        TestPage::new(name).build()
+    }
+
+    #[test]
+    fn test_decomposed_names_match_composed_links() {
+        // "Café" with e + U+0301, as macOS stores file names
+        let decomposed = "Cafe\u{301}";
+        assert_eq!(resolver::page_name(decomposed), "Café");
+        assert_eq!(resolver::normalize_key(decomposed), resolver::normalize_key("café"));
+        assert_eq!(source::normalize("- [[Cafe\u{301}]]"), "- [[Café]]");
+
+        let index = PageIndex::new(vec![page(&resolver::page_name(decomposed))]);
+        let resolved = resolver::resolve("CAFÉ", &index).unwrap();
+        assert_eq!(resolved.kind, MatchKind::Exact);
+    }
+
+    #[test]
+    fn test_non_ascii_names_resolve() {
+        let index = PageIndex::new(vec![page("Москва"), page("東京"), page("🚀 Launch"), page("visit")]);
+
+        assert_eq!(resolver::resolve("москва", &index).unwrap().page.name, "Москва");
+        assert_eq!(resolver::resolve("🚀 launch", &index).unwrap().page.name, "🚀 Launch");
+
+        // Prefix matching cuts between CJK characters, not only at spaces
+        let tower = resolver::resolve("東京タワー", &index).unwrap();
+        assert_eq!((tower.page.name.as_str(), tower.kind), ("東京", MatchKind::Prefix));
+        let moscow = resolver::resolve("Москва сити", &index).unwrap();
+        assert_eq!((moscow.page.name.as_str(), moscow.kind), ("Москва", MatchKind::Prefix));
+        assert!(resolver::resolve("Москвасити", &index).is_none());
+        assert_eq!(resolver::resolve("visit us", &index).unwrap().page.name, "visit");
+    }
+
+    #[test]
+    fn test_ascii_slug_style() {
+        assert_eq!(SlugStyle::Unicode.slug("Москва"), None);
+        assert_eq!(SlugStyle::Ascii.slug("Plain page"), None);
+        assert_eq!(SlugStyle::Ascii.slug("Москва").as_deref(), Some("Moskva"));
+        assert_eq!(SlugStyle::Ascii.slug("Города/Москва").as_deref(), Some("Goroda/Moskva"));
+        assert_eq!(SlugStyle::Ascii.slug("Café crème").as_deref(), Some("Cafe creme"));
+        assert_eq!(SlugStyle::Ascii.slug("東京").as_deref(), Some("Dong Jing"));
+
+        let mut props = HashMap::new();
+        assert_eq!(resolver::output_name("Москва", &props, SlugStyle::Ascii), "Moskva");
+        assert_eq!(resolver::output_name("Москва", &props, SlugStyle::Unicode), "Москва");
+        props.insert("slug".to_string(), "moscow".to_string());
+        assert_eq!(resolver::output_name("Москва", &props, SlugStyle::Ascii), "moscow");
+
+        // Links to the page follow the transliterated slug
+        let index = PageIndex::new(vec![page("Москва"), page("Plain")])
+            .with_seed(7)
+            .with_tag_match(TagMatch::Plural)
+            .with_sanitize_html(true)
+            .with_slug_style(SlugStyle::Ascii);
+        assert_eq!(resolver::link_target_for("москва", &index), "Moskva");
+        assert_eq!(resolver::link_target_for("Plain", &index), "Plain");
+        // Settings made before the slugs are kept
+        assert_eq!((index.seed(), index.tag_match(), index.sanitize_html()), (7, TagMatch::Plural, true));
+    }
+
+    #[test]
+    fn test_ascii_slug_style_output() {
+        let temp = tempdir().unwrap();
+        let input = temp.path().join("graph");
+        let output = temp.path().join("out");
+        fs::create_dir_all(input.join("pages")).unwrap();
+        fs::write(input.join("pages/Москва.md"), "- capital").unwrap();
+        fs::write(input.join("pages/Trip.md"), "- went to [[Москва]]").unwrap();
+
+        let config = Config {
+            input_dir: input.clone(),
+            output_dir: output.clone(),
+            slug_style: SlugStyle::Ascii,
+            ..Default::default()
+        };
+        let index = page::build_graph_index(&input).unwrap().with_slug_style(config.slug_style);
+        for name in ["Москва", "Trip"] {
+            let path = input.join(format!("pages/{}.md", name));
+            page::process_page(&path, &output, &index, &config, &HashMap::new(), &input).unwrap();
+        }
+
+        assert!(output.join("Moskva.md").exists());
+        let trip = fs::read_to_string(output.join("Trip.md")).unwrap();
+        assert!(trip.contains("[[Moskva|Москва]]"), "got: {}", trip);
+    }
+}
+
+#[cfg(test)]
+mod previews_tests {
+    use crate::icons::IconMap;
+    use crate::page::{Page, PageIndex};
+    use crate::previews::{self, PREVIEW_LENGTH};
+    use std::collections::HashMap;
+    use std::fs;
+    use tempfile::tempdir;
+    use super::page;
+
+    #[test]
+    fn test_plain_text_strips_markup() {
+        let content = "tags:: rust\n\n- ## Intro\n- TODO [#A] read **the** [[Rust Book|book]] and [[Cargo]]\n  id:: 123\n- see [docs](https://x.io) ![img](../assets/a.png) {{youtube abc}}\n- ```rust\n  fn main() {}\n  ```\n- done #[[big idea]] ^^now^^";
+        assert_eq!(
+            previews::plain_text(content),
+            "Intro read the book and Cargo see docs done big idea now"
+        );
+        assert_eq!(previews::plain_text("- ~~~\n  secret = 1\n  ~~~\n- shown"), "shown");
+    }
+
+    #[test]
+    fn test_build_previews() {
+        let long = format!("- {}", "word ".repeat(100));
+        let index = PageIndex::new(vec![
+            page("Web App", "title:: The Web App\nicon:: 🚀\n\n- Fast & small"),
+            page("Projects/Q&A?", &long),
+            page("Secret", "private:: true\n\n- hidden"),
+            Page {
+                slug: Some("journals/2025-01-15".to_string()),
+                ..page("journals/2025_01_15", "- met Bob")
+            },
+        ]);
+        let icons = IconMap::from_entries(
+            HashMap::from([("🚀".to_string(), "icons/rocket.svg".to_string())]),
+            std::path::Path::new("/graph/logseq"),
+        );
+
+        let built = previews::build(&index, &icons, false);
+        let keys: Vec<&str> = built.keys().map(String::as_str).collect();
+        assert_eq!(keys, ["Projects/Q-and-A", "Web-App", "journals/2025-01-15"]);
+
+        let web = &built["Web-App"];
+        assert_eq!(web.title, "The Web App");
+        assert_eq!(web.icon.as_deref(), Some("🚀"));
+        assert_eq!(web.icon_src.as_deref(), Some("/assets/icons/rocket.svg"));
+        assert_eq!(web.text, "Fast & small");
+
+        let text = &built["Projects/Q-and-A"].text;
+        assert!(text.chars().count() <= PREVIEW_LENGTH);
+        assert!(text.ends_with("word…"), "got: {}", text);
+
+        assert_eq!(built["journals/2025-01-15"].title, "January 15, 2025");
+        assert!(previews::build(&index, &icons, true).contains_key("Secret"));
+
+        let temp = tempdir().unwrap();
+        assert_eq!(previews::write(temp.path(), &index, &icons, false).unwrap(), 3);
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(temp.path().join("previews.json")).unwrap()).unwrap();
+        assert_eq!(json["journals/2025-01-15"]["text"], "met Bob");
+        assert!(json["journals/2025-01-15"].get("icon").is_none());
+    }
+}
+
+#[cfg(test)]
+mod index_file_tests {
+    use crate::index_file::{self, IndexFormat};
+    use crate::page::{Page, PageIndex};
+    use std::fs;
+    use tempfile::tempdir;
+    use super::page;
+
+    #[test]
+    fn test_index_entries() {
+        let index = PageIndex::new(vec![
+            Page {
+                tags: vec!["project".to_string()],
+                aliases: vec!["WA".to_string()],
+                namespace: Some("Projects".to_string()),
+                modified: Some("2025-01-15".to_string()),
+                ..page("Projects/Web App", "title:: The Web App\nstatus:: active\n\n- Fast")
+            },
+            page("Secret", "private:: true\n\n- hidden"),
+            Page {
+                slug: Some("journals/2025-01-15".to_string()),
+                ..page("journals/2025_01_15", "- met Bob")
+            },
+        ]);
+
+        let entries = index_file::build(&index, false);
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["Projects/Web App", "journals/2025_01_15"]);
+        let web = &entries[0];
+        assert_eq!(web.title, "The Web App");
+        assert_eq!(web.slug, "Projects/Web-App");
+        assert_eq!(web.namespace.as_deref(), Some("Projects"));
+        assert_eq!(web.properties.get("status").map(String::as_str), Some("active"));
+        assert!(!web.journal && entries[1].journal);
+        assert_eq!(entries[1].slug, "journals/2025-01-15");
+        assert_eq!(index_file::build(&index, true).len(), 3);
+
+        let temp = tempdir().unwrap();
+        assert_eq!(index_file::write(temp.path(), &index, IndexFormat::Json, false).unwrap(), 2);
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(temp.path().join("pages.json")).unwrap()).unwrap();
+        assert_eq!(json[0]["aliases"][0], "WA");
+        assert_eq!(json[0]["modified"], "2025-01-15");
+        assert!(json[1]["created"].is_null());
+
+        index_file::write(temp.path(), &index, IndexFormat::Ndjson, false).unwrap();
+        let ndjson = fs::read_to_string(temp.path().join("pages.ndjson")).unwrap();
+        let lines: Vec<serde_json::Value> = ndjson.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["name"], "journals/2025_01_15");
+    }
+}
+
+#[cfg(test)]
+mod related_tests {
+    use crate::page::{Page, PageIndex};
+    use crate::related::{self, Related};
+    use std::fs;
+    use tempfile::tempdir;
+    use super::TestPage;
+
+    fn page(name: &str, tags: &[&str], content: &str) -> Page {
+        TestPage::new(name).tags(tags).content(content).build()
+    }
+
+    #[test]
+    fn test_related_by_links_tags_and_co_citation() {
+        let index = PageIndex::new(vec![
+            page("Rust", &["lang"], "- see [[Cargo]]"),
+            page("Cargo", &["tool"], "- builds crates"),
+            page("Go", &["lang"], "- other"),
+            page("Zig", &[], "- nothing shared"),
+            page("Draft", &["lang"], "draft:: true\n\n- [[Rust]]"),
+            page("journals/2025_01_15", &[], "- compared [[Go]] and [[Cargo]]"),
+        ]);
+        let related = related::compute(&index, 5, false);
+
+        // Link (3), tag (2), co-cited by the journal (1); drafts and journals aren't listed
+        let rel = |page: &str, score| Related { page: page.to_string(), score };
+        assert_eq!(related["Rust"], vec![rel("Cargo", 3), rel("Go", 2)]);
+        assert_eq!(related["Cargo"], vec![rel("Rust", 3), rel("Go", 1)]);
+        assert_eq!(related["Go"], vec![rel("Rust", 2), rel("Cargo", 1)]);
+        assert!(!related.contains_key("Zig") && !related.contains_key("Draft"));
+        assert_eq!(related::compute(&index, 1, false)["Rust"], vec![rel("Cargo", 3)]);
+
+        let index = index.with_related(related.clone());
+        let rust = crate::resolver::find_page("Rust", &index).unwrap();
+        assert_eq!(related::frontmatter_targets(rust, &index), vec!["Cargo", "Go"]);
+
+        let temp = tempdir().unwrap();
+        assert_eq!(related::write(temp.path(), &index, &related).unwrap(), 3);
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(temp.path().join("related.json")).unwrap()).unwrap();
+        assert_eq!(json["Rust"][0]["slug"], "Cargo");
+        assert_eq!(json["Rust"][1]["score"], 2);
+    }
+}
+
+#[cfg(test)]
+mod link_encoding_tests {
+    use crate::config::Config;
+    use crate::content;
+    use crate::page::{self, Page, PageIndex};
+    use crate::resolver;
+    use std::collections::HashMap;
+    use std::fs;
+    use tempfile::tempdir;
+    use super::TestPage;
+
+    fn page(name: &str) -> Page {
+        TestPage::new(name).slug(resolver::derived_slug(name, Default::default()).as_deref()).build()
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(resolver::decode("My%20Page"), "My Page");
+        assert_eq!(resolver::decode("%E6%9D%B1%E4%BA%AC"), "東京");
+        assert_eq!(resolver::decode("100%"), "100%");
+        assert_eq!(resolver::decode("bad%FFbyte"), "bad%FFbyte");
+        assert_eq!(resolver::decode("https://example.com/a%20b"), "https://example.com/a%20b");
+    }
+
+    #[test]
+    fn test_file_names_round_trip() {
+        assert_eq!(resolver::page_name("What%3F"), "What?");
+        assert_eq!(resolver::page_name("Q&A___Why%3A Now"), "Q&A/Why: Now");
+        assert_eq!(resolver::file_stem("Q&A/Why: Now?"), "Q&A___Why%3A Now%3F");
+        for name in ["Q&A/Why: Now?", "a \"quoted\" <name>", "plain"] {
+            assert_eq!(resolver::page_name(&resolver::file_stem(name)), name);
+        }
+
+        assert_eq!(resolver::safe_path("Why: Now?/a|b"), "Why_ Now_/a_b");
+        assert_eq!(resolver::derived_slug("plain/page", Default::default()), None);
+        assert_eq!(resolver::derived_slug("What?", Default::default()).as_deref(), Some("What_"));
+    }
+
+    #[test]
+    fn test_links_and_output_paths_agree() {
+        let index = PageIndex::new(vec![page("My Page"), page("What?")]);
+
+        let result = content::transform("- [[My%20Page]] and [[what?]] and [[Missing%3A Page]]", &index);
+        assert!(result.contains("[[My Page]]"), "got: {}", result);
+        assert!(result.contains("[[What_|what?]]"), "got: {}", result);
+        assert!(result.contains("[[Missing_ Page|Missing: Page]]"), "got: {}", result);
+
+        let temp = tempdir().unwrap();
+        let input = temp.path().join("graph");
+        let output = temp.path().join("out");
+        fs::create_dir_all(input.join("pages")).unwrap();
+        fs::write(input.join("pages/What%3F.md"), "- a question\n- see [[Missing%3A Page]]").unwrap();
+
+        let config = Config { input_dir: input.clone(), output_dir: output.clone(), ..Default::default() };
+        let index = page::build_graph_index(&input).unwrap();
+        assert_eq!(index[0].name, "What?");
+        assert_eq!(resolver::link_target(&index[0]), "What_");
+
+        page::process_page(&input.join("pages/What%3F.md"), &output, &index, &config, &HashMap::new(), &input)
+            .unwrap();
+        let published = fs::read_to_string(output.join("What_.md")).unwrap();
+        assert!(published.contains("title: \"What?\""), "got: {}", published);
+
+        assert_eq!(page::create_stubs(&output, &index, &config).unwrap(), 1);
+        let stub = fs::read_to_string(output.join("Missing_ Page.md")).unwrap();
+        assert!(stub.contains("title: \"Missing: Page\""), "got: {}", stub);
+    }
+}
+
+#[cfg(test)]
+mod export_block_tests {
+    use crate::content;
+    use crate::page::PageIndex;
+
+    #[test]
+    fn test_html_export_block_passes_through() {
+        let input = "- intro [[Page]]\n- #+BEGIN_EXPORT html\n  <div class=\"card\">\n    <b>$100 [[Page]] key:: value</b>\n\n  </div>\n  #+END_EXPORT\n- TODO after";
+        let result = content::transform(input, &PageIndex::default());
+        assert!(
+            result.contains("- <div class=\"card\">\n    <b>$100 [[Page]] key:: value</b>\n  </div>\n"),
+            "got: {}",
+            result
+        );
+        assert!(!result.contains("BEGIN_EXPORT"));
+        assert!(result.contains("- [ ] after"));
+    }
+
+    #[test]
+    fn test_top_level_and_lowercase_export_block() {
+        let input = "#+begin_export html\n<iframe src=\"https://example.com\"></iframe>\n#+end_export\n\ntext";
+        let result = content::transform(input, &PageIndex::default());
+        assert_eq!(result, "<iframe src=\"https://example.com\"></iframe>\n\ntext");
+    }
+
+    #[test]
+    fn test_other_export_backends_become_code() {
+        let input = "- #+BEGIN_EXPORT latex\n  \\textbf{$x$}\n  #+END_EXPORT";
+        let transformed = content::transform_page(input, &PageIndex::default());
+        assert_eq!(transformed.content, "- ```latex\n  \\textbf{$x$}\n  ```");
+        assert_eq!(transformed.diagnostics.len(), 1);
+        assert_eq!(transformed.diagnostics[0].kind, "export");
+    }
+}
+
+#[cfg(test)]
+mod directive_tests {
+    use crate::content;
+    use crate::page::PageIndex;
+
+    #[test]
+    fn test_tip_becomes_callout_in_bullet() {
+        let input = "- #+BEGIN_TIP\n  Use [[Cargo]] workspaces.\n\n  Really.\n  #+END_TIP\n- after";
+        let result = content::transform(input, &PageIndex::default());
+        assert_eq!(result, "- > [!tip]\n  > Use [[Cargo]] workspaces.\n  >\n  > Really.\n- after");
+    }
+
+    #[test]
+    fn test_quote_and_titled_warning() {
+        let input = "#+BEGIN_QUOTE\nTo be or not to be\n#+END_QUOTE\n\n#+begin_warning Careful\nHot\n#+end_warning";
+        let result = content::transform(input, &PageIndex::default());
+        assert_eq!(result, "> To be or not to be\n\n> [!warning] Careful\n> Hot");
+    }
+
+    #[test]
+    fn test_src_becomes_fenced_code_untouched() {
+        let input = "- #+BEGIN_SRC rust :results output\n  let price = \"$100\"; // [[not a link]]\n  #+END_SRC";
+        let result = content::transform(input, &PageIndex::default());
+        assert_eq!(result, "- ```rust\n  let price = \"$100\"; // [[not a link]]\n  ```");
+    }
+
+    #[test]
+    fn test_comment_dropped_and_unknown_kept() {
+        let transformed = content::transform_page("#+BEGIN_COMMENT\nsecret\n#+END_COMMENT\n#+BEGIN_VERSE\nline\n#+END_VERSE", &PageIndex::default());
+        assert!(!transformed.content.contains("secret"));
+        assert!(transformed.content.contains("#+BEGIN_VERSE\nline\n#+END_VERSE"));
+        assert_eq!(transformed.diagnostics.len(), 1);
+        assert_eq!(transformed.diagnostics[0].kind, "directive");
+    }
+}
+
+#[cfg(test)]
+mod callout_tests {
+    use crate::callouts::CalloutMap;
+    use crate::content;
+    use crate::page::PageIndex;
+
+    fn index(spec: &str) -> PageIndex {
+        PageIndex::default().with_callouts(CalloutMap::parse(spec).unwrap())
+    }
+
+    #[test]
+    fn test_parse_overrides_and_rejects_malformed() {
+        let map = CalloutMap::parse("WARNING=danger, quote=quote,custom=info,tip=none").unwrap();
+        assert_eq!(map.get("warning"), Some(Some("danger")));
+        assert_eq!(map.get("quote"), Some(Some("quote")));
+        assert_eq!(map.get("custom"), Some(Some("info")));
+        assert_eq!(map.get("tip"), Some(None));
+        assert_eq!(map.get("note"), Some(Some("note")));
+        assert_eq!(map.get("verse"), None);
+        assert!(CalloutMap::parse("warning").is_err());
+        assert!(CalloutMap::parse("=danger").is_err());
+    }
+
+    #[test]
+    fn test_mapping_applies_to_directives_and_existing_callouts() {
+        let input = "#+BEGIN_WARNING\nHot\n#+END_WARNING\n\n- > [!WARNING] Careful\n  > Sharp";
+        let result = content::transform(input, &index("warning=danger"));
+        assert_eq!(result, "> [!danger]\n> Hot\n\n- > [!danger] Careful\n  > Sharp");
+    }
+
+    #[test]
+    fn test_unmapped_callouts_left_as_written() {
+        let input = "> [!NOTE]\n> Keep\n\n> [!faq]- Why?\n> Because";
+        assert_eq!(content::transform(input, &PageIndex::default()), input);
+    }
+
+    #[test]
+    fn test_quote_and_custom_kinds() {
+        let input = "#+BEGIN_QUOTE\nWise\n#+END_QUOTE\n\n#+BEGIN_VERSE\nRoses\n#+END_VERSE";
+        let result = content::transform(input, &index("quote=quote,verse=abstract"));
+        assert_eq!(result, "> [!quote]\n> Wise\n\n> [!abstract]\n> Roses");
+    }
+
+    #[test]
+    fn test_none_renders_plain_blockquote() {
+        let result = content::transform("#+BEGIN_TIP\nPsst\n#+END_TIP", &index("tip=none"));
+        assert_eq!(result, "> Psst");
+    }
+}
+
+#[cfg(test)]
+mod code_protection_tests {
+    use crate::content;
+    use crate::page::PageIndex;
+
+    #[test]
+    fn test_fenced_code_untouched() {
+        let input = "- Setup\n  ```bash\n  export PATH=$HOME/bin:$PATH # costs $100\n  TODO not a task\n  key:: value\n  [[Not Link]] ((abc)) {{query (todo now)}}\n  ```\n- TODO real task with $HOME";
+        let result = content::transform(input, &PageIndex::default());
+        assert!(result.contains("  ```bash\n  export PATH=$HOME/bin:$PATH # costs $100\n  TODO not a task\n  key:: value\n  [[Not Link]] ((abc)) {{query (todo now)}}\n  ```"));
+        assert!(result.contains("- [ ] real task with \\$HOME"));
+    }
+
+    #[test]
+    fn test_fence_opening_a_bullet_and_tilde_fence() {
+        let input = "- ```\n  DONE $TOKEN\n  ```\n~~~~\n$HOME\n~~~~";
+        let result = content::transform(input, &PageIndex::default());
+        assert_eq!(result, input);
+    }
+
+    #[test]
+    fn test_inline_code_untouched() {
+        let input = "- Run `echo $HOME` or ``a `$PATH` b`` then see [[pages/Target]] and `[[pages/Target]]`";
+        let result = content::transform(input, &PageIndex::default());
+        assert_eq!(result, "- Run `echo $HOME` or ``a `$PATH` b`` then see [[Target]] and `[[pages/Target]]`");
+    }
+
+    #[test]
+    fn test_unclosed_fence_still_transformed() {
+        let result = content::transform("```\ncosts $100", &PageIndex::default());
+        assert_eq!(result, "```\ncosts \\$100");
+    }
+}
+
+#[cfg(test)]
+mod math_tests {
+    use crate::content;
+    use crate::page::PageIndex;
+
+    fn transform(input: &str) -> String {
+        content::transform(input, &PageIndex::default())
+    }
+
+    #[test]
+    fn test_inline_math_kept_and_currency_escaped_around_it() {
+        assert_eq!(transform("- $N$ nodes cost $2^n$ or $100"), "- $N$ nodes cost $2^n$ or \\$100");
+        assert_eq!(transform("- $\\frac{a}{b}$ and $ETH"), "- $\\frac{a}{b}$ and \\$ETH");
+    }
+
+    #[test]
+    fn test_display_math_spanning_lines_untouched() {
+        let input = "- Energy:\n  $$\n  E = mc^2 \\quad \\text{TODO } {{x}} $HOME\n  $$\n- TODO next";
+        let result = transform(input);
+        assert!(result.contains("  $$\n  E = mc^2 \\quad \\text{TODO } {{x}} $HOME\n  $$"), "got: {}", result);
+        assert!(result.contains("- [ ] next"));
+    }
+
+    #[test]
+    fn test_prices_and_tickers_between_dollars_are_not_math() {
+        assert_eq!(transform("$5 and $10"), "\\$5 and \\$10");
+        assert_eq!(transform("$100-$200"), "\\$100-\\$200");
+        assert_eq!(transform("$ETH/$BTC"), "\\$ETH/\\$BTC");
+    }
+
+    #[test]
+    fn test_dollar_wikilinks_not_paired_as_math() {
+        let result = transform("$ETH is [[$BOOT]]");
+        assert!(result.starts_with("\\$ETH is <a href=\"$BOOT\""), "got: {}", result);
+    }
+
+    #[test]
+    fn test_unclosed_display_math_across_blocks_is_text() {
+        assert_eq!(transform("- $$ cheap\n\n- $$ $HOME"), "- $$ cheap\n\n- $$ \\$HOME");
+    }
+}
+
+#[cfg(test)]
+mod formatting_tests {
+    use crate::content;
+    use crate::page::PageIndex;
+
+    fn transform(input: &str) -> String {
+        content::transform(input, &PageIndex::default())
+    }
+
+    #[test]
+    fn test_logseq_highlight_becomes_quartz_highlight() {
+        assert_eq!(transform("- Remember ^^this part^^ and ^^that^^"), "- Remember ==this part== and ==that==");
+        assert_eq!(transform("- `^^code^^` and $x^^2$"), "- `^^code^^` and $x^^2$");
+    }
+
+    #[test]
+    fn test_emphasis_commonmark_renders_is_unchanged() {
+        let input = "- **bold**, *italic*, __strong__, _em_ and **Note:** text";
+        assert_eq!(transform(input), input);
+    }
+
+    #[test]
+    fn test_punctuation_flanked_emphasis_becomes_html() {
+        assert_eq!(transform("- **Note:**text"), "- <strong>Note:</strong>text");
+        assert_eq!(transform("- a*(aside)*b"), "- a<em>(aside)</em>b");
+        assert_eq!(transform("- 中文**「强调」**中文"), "- 中文<strong>「强调」</strong>中文");
+    }
+
+    #[test]
+    fn test_intraword_underscore() {
+        assert_eq!(transform("- 中文_斜体_中文"), "- 中文<em>斜体</em>中文");
+        assert_eq!(transform("- call snake_case_name or __init__"), "- call snake_case_name or __init__");
+    }
+
+    #[test]
+    fn test_arithmetic_and_padded_markers_untouched() {
+        assert_eq!(transform("- 2 * 3 * 4 and * not * emphasis"), "- 2 * 3 * 4 and * not * emphasis");
+    }
+}
+
+#[cfg(test)]
+mod image_size_tests {
+    use crate::content;
+    use crate::page::PageIndex;
+
+    fn transform(input: &str) -> String {
+        content::transform(input, &PageIndex::default())
+    }
+
+    #[test]
+    fn test_pixel_sizes_become_obsidian_suffix() {
+        assert_eq!(
+            transform("- ![diagram](../assets/d.png){:height 300, :width 400}"),
+            "- ![diagram|400x300](../assets/d.png)"
+        );
+        assert_eq!(transform("- ![](../assets/d.png){:width 250.6}"), "- ![|251](../assets/d.png)");
+    }
+
+    #[test]
+    fn test_percentage_and_height_only_become_img() {
+        assert_eq!(
+            transform("- ![a \"b\"](../assets/d.png){:width \"50%\"}"),
+            r#"- <img src="../assets/d.png" alt="a &quot;b&quot;" style="width: 50%">"#
+        );
+        assert_eq!(
+            transform("- ![x](../assets/d.png){:height 120px}"),
+            r#"- <img src="../assets/d.png" alt="x" style="height: 120px">"#
+        );
+    }
+
+    #[test]
+    fn test_other_attributes_and_stray_sizes_dropped() {
+        assert_eq!(transform("- ![x](../assets/d.png){:class \"wide\"}"), "- ![x](../assets/d.png)");
+        assert_eq!(transform("- text {:height 10, :width 20} more"), "- text  more");
+    }
+}
+
+#[cfg(test)]
+mod video_embed_tests {
+    use crate::content;
+    use crate::embeds::{self, EmbedStyle};
+    use crate::page::PageIndex;
+
+    #[test]
+    fn test_local_and_remote_video_files_use_video_tag() {
+        let result = content::transform("- {{video ../assets/demo clip.MP4}}", &PageIndex::default());
+        assert_eq!(
+            result,
+            r#"- <video controls preload="metadata" src="../assets/demo clip.MP4" style="width: 100%;"></video>"#
+        );
+        assert!(embeds::video("https://cdn.example.com/a.webm?v=2", EmbedStyle::Iframe).starts_with(r#"<video controls preload="metadata" src="https://cdn.example.com/a.webm?v=2""#));
+    }
+
+    #[test]
+    fn test_youtube_uses_nocookie_iframe() {
+        let expected = r#"<iframe src="https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ?start=90" width="100%""#;
+        for url in [
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=90",
+            "https://youtu.be/dQw4w9WgXcQ?t=1m30s",
+            "https://youtube.com/shorts/dQw4w9WgXcQ?start=90",
+        ] {
+            assert!(embeds::video(url, EmbedStyle::Iframe).starts_with(expected), "{}: {}", url, embeds::video(url, EmbedStyle::Iframe));
+        }
+        let result = content::transform("{{youtube dQw4w9WgXcQ}}", &PageIndex::default());
+        assert!(result.starts_with(r#"<iframe src="https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ" "#), "{}", result);
+    }
+
+    #[test]
+    fn test_vimeo_uses_do_not_track_iframe() {
+        assert!(embeds::video("https://vimeo.com/76979871#t=30s", EmbedStyle::Iframe)
+            .starts_with(r#"<iframe src="https://player.vimeo.com/video/76979871?dnt=1#t=30s""#));
+    }
+
+    #[test]
+    fn test_tweets_embed_or_link() {
+        let result = content::transform("- {{tweet https://x.com/rustlang/status/1234567890}}", &PageIndex::default());
+        assert!(
+            result.starts_with(r#"- <iframe src="https://platform.twitter.com/embed/Tweet.html?id=1234567890&amp;dnt=true""#),
+            "{}",
+            result
+        );
+        assert_eq!(
+            embeds::tweet("https://twitter.com/rustlang/status/1234567890?s=20", EmbedStyle::Link),
+            "[𝕏 Post by @rustlang](https://twitter.com/rustlang/status/1234567890?s=20)"
+        );
+        assert_eq!(embeds::tweet("https://x.com/rustlang", EmbedStyle::Iframe), "[𝕏 Post](https://x.com/rustlang)");
+    }
+
+    #[test]
+    fn test_link_style_loads_nothing_from_providers() {
+        let index = PageIndex::default().with_embeds(EmbedStyle::Link);
+        let input = "- {{youtube dQw4w9WgXcQ}}\n- {{vimeo https://vimeo.com/76979871}}\n- {{twitter https://x.com/a/status/1}}\n- {{video ../assets/a.mp4}}";
+        let result = content::transform(input, &index);
+        assert!(!result.contains("<iframe"), "{}", result);
+        assert!(result.contains("- [▶ YouTube video](https://www.youtube.com/watch?v=dQw4w9WgXcQ)"));
+        assert!(result.contains("- [▶ Vimeo video](https://vimeo.com/76979871)"));
+        assert!(result.contains("- [𝕏 Post by @a](https://x.com/a/status/1)"));
+        assert!(result.contains("<video controls"));
+    }
+
+    #[test]
+    fn test_other_providers_keep_image_syntax() {
+        let url = "https://www.loom.com/share/abc";
+        assert_eq!(embeds::video(url, EmbedStyle::Iframe), format!("![{}]({})", url, url));
+    }
+}
+
+#[cfg(test)]
+mod pdf_tests {
+    use crate::content;
+    use crate::page::{self, Page, PageIndex};
+    use crate::pdf::{self, PdfFrame};
+
+    const HLS_PAGE: &str = "file:: [report_1700000000000_0.pdf](../assets/report_1700000000000_0.pdf)\nfile-path:: ../assets/report_1700000000000_0.pdf\n\n- Growth was 5%\n  ls-type:: annotation\n  hl-page:: 3\n  hl-color:: yellow\n  id:: 6543a1b2-0000-4000-8000-00000000000a\n- [:span]\n  ls-type:: annotation\n  hl-page:: 7\n  hl-type:: area\n  id:: 6543a1b2-0000-4000-8000-00000000000b\n- my own note\n  id:: 6543a1b2-0000-4000-8000-00000000000c";
+
+    fn index() -> PageIndex {
+        let (properties, _) = page::parse_properties(HLS_PAGE);
+        PageIndex::new(vec![Page {
+            name: "hls__report_1700000000000_0".to_string(),
+            content: HLS_PAGE.to_string(),
+            properties,
+            ..Default::default()
+        }])
+    }
+
+    #[test]
+    fn test_embed_has_open_link_and_configurable_size() {
+        let frame = PdfFrame {
+            width: "80%".to_string(),
+            height: "90vh".to_string(),
+        };
+        let index = PageIndex::default().with_pdf_frame(frame);
+        let result = content::transform("- {{pdf ../assets/report_1700000000000_0.pdf}}", &index);
+        assert_eq!(
+            result,
+            r#"- <iframe src="../assets/report_1700000000000_0.pdf" width="80%" height="90vh" style="border: 1px solid #333; border-radius: 4px;"></iframe><br><a class="pdf-link" href="../assets/report_1700000000000_0.pdf" target="_blank" rel="noopener">📄 Open report.pdf</a>"#
+        );
+    }
+
+    #[test]
+    fn test_highlights_parsed_from_annotation_page() {
+        let index = index();
+        let text = index.pdf_highlight("6543A1B2-0000-4000-8000-00000000000A").unwrap();
+        assert_eq!(text.page, Some(3));
+        assert_eq!(text.text, "Growth was 5%");
+        assert_eq!(text.file, "../assets/report_1700000000000_0.pdf");
+        assert_eq!(index.pdf_highlight("6543a1b2-0000-4000-8000-00000000000b").unwrap().text, "");
+        assert!(index.pdf_highlight("6543a1b2-0000-4000-8000-00000000000c").is_none());
+        assert!(!pdf::is_highlights_page("hlsfoo"));
+    }
+
+    #[test]
+    fn test_highlight_refs_link_to_pdf_page() {
+        let input = "- See ((6543a1b2-0000-4000-8000-00000000000a)) and ((6543a1b2-0000-4000-8000-00000000000b)) and ((6543a1b2-0000-4000-8000-00000000000c))";
+        let result = content::transform(input, &index());
+        assert_eq!(
+            result,
+            "- See “Growth was 5%” [📄 report.pdf, p. 3](../assets/report_1700000000000_0.pdf#page=3) and [📄 report.pdf, p. 7](../assets/report_1700000000000_0.pdf#page=7) and [→ block](#^6543a1b2-0000-4000-8000-00000000000c)"
+        );
+    }
+
+    #[test]
+    fn test_annotation_page_rendered_as_highlights() {
+        let (_, body) = page::parse_properties(HLS_PAGE);
+        let content = body.replace("00000000000a\n", "00000000000a\n  - why it matters\n    id:: 6543a1b2-0000-4000-8000-0000000000ff\n").replace("  hl-type:: area\n", "  hl-type:: area\n  hl-stamp:: 1700000000001\n");
+        assert_eq!(
+            pdf::highlights_body(&content, "../assets/report_1700000000000_0.pdf"),
+            "Highlights from [📄 report.pdf](../assets/report_1700000000000_0.pdf)\n\n\
+             - > Growth was 5% — [p. 3](../assets/report_1700000000000_0.pdf#page=3)\n  - why it matters\n    id:: 6543a1b2-0000-4000-8000-0000000000ff\n\
+             - ![Area highlight, p. 7](../assets/report_1700000000000_0/7_6543a1b2-0000-4000-8000-00000000000b_1700000000001.png) — [p. 7](../assets/report_1700000000000_0.pdf#page=7)\n\
+             - my own note\n  id:: 6543a1b2-0000-4000-8000-00000000000c"
+        );
+    }
+
+    #[test]
+    fn test_annotation_page_published_with_title() {
+        let temp = tempfile::tempdir().unwrap();
+        let pages_dir = temp.path().join("pages");
+        let output_dir = temp.path().join("out");
+        std::fs::create_dir_all(&pages_dir).unwrap();
+        std::fs::create_dir_all(&output_dir).unwrap();
+        let source = pages_dir.join("hls__report_1700000000000_0.md");
+        std::fs::write(&source, HLS_PAGE).unwrap();
+
+        let config = crate::config::Config::default();
+        page::process_page(&source, &output_dir, &index(), &config, &Default::default(), temp.path()).unwrap();
+        let output = std::fs::read_to_string(output_dir.join("hls__report_1700000000000_0.md")).unwrap();
+        assert!(output.contains("title: \"Highlights from report.pdf\""), "{}", output);
+        assert!(output.contains("- > Growth was 5% — [p. 3](../assets/report_1700000000000_0.pdf#page=3)"), "{}", output);
+        assert!(!output.contains("ls-type") && !output.contains("hl-page"), "{}", output);
+    }
+
+    #[test]
+    fn test_annotation_page_links_go_to_pdf() {
+        let result = content::transform("- [[hls__report_1700000000000_0]] and [[hls__report_1700000000000_0|the report]]", &index());
+        assert_eq!(
+            result,
+            "- [📄 report.pdf](../assets/report_1700000000000_0.pdf) and [📄 the report](../assets/report_1700000000000_0.pdf)"
+        );
+    }
+}
+
+#[cfg(test)]
+mod hiccup_tests {
+    use crate::content;
+    use crate::hiccup;
+    use crate::page::PageIndex;
+
+    fn transform(input: &str) -> String {
+        content::transform(input, &PageIndex::default())
+    }
+
+    fn html(src: &str) -> String {
+        let (nodes, len) = hiccup::parse(src).expect("parses");
+        assert_eq!(len, src.len());
+        hiccup::render(&nodes)
+    }
+
+    #[test]
+    fn test_text_interleaved_with_elements_and_links() {
+        assert_eq!(
+            html(r#"[:p "Read " [:a {:href "https://x.io/?a=1&b=2"} "the docs"] ", then " [:b "build"] "."]"#),
+            r#"<p>Read <a href="https://x.io/?a=1&amp;b=2">the docs</a>, then <b>build</b>.</p>"#
+        );
+    }
+
+    #[test]
+    fn test_ordered_and_nested_lists() {
+        assert_eq!(
+            html(r#"[:ol [:li "one"] [:li "two" [:ul [:li "a"] [:li "b"]]]]"#),
+            "<ol><li>one</li><li>two<ul><li>a</li><li>b</li></ul></li></ol>"
+        );
+        // A vector of elements (no tag) is spliced in
+        assert_eq!(html(r#"[:ul ([:li "x"] [:li "y"])]"#), "<ul><li>x</li><li>y</li></ul>");
+    }
+
+    #[test]
+    fn test_tag_shorthand_attributes_and_void_elements() {
+        assert_eq!(
+            html(r#"[:div.card.wide#intro {:class "extra" :style {:color "red" :font-size "2em"} :hidden true :title nil} [:img {:src "../assets/a.png" :alt "A \"quoted\" <b>"}] [:br] 42]"#),
+            r#"<div class="card wide extra" id="intro" style="color: red; font-size: 2em;" hidden=""><img src="../assets/a.png" alt="A &quot;quoted&quot; &lt;b&gt;"><br>42</div>"#
+        );
+    }
+
+    #[test]
+    fn test_event_handlers_and_script_urls_removed() {
+        assert_eq!(
+            html(r#"[:div {:onclick "alert(1)" :onMouseOver "x()" :class "card"} [:a {:href " JaVa\tScript:alert(1)"} "x"] [:script "evil()"] [:SCRIPT "evil()"]]"#),
+            r#"<div class="card"><a>x</a></div>"#
+        );
+    }
+
+    #[test]
+    fn test_frames_and_srcdoc_documents_removed() {
+        // The entity-escaped srcdoc would be decoded back into a document that runs the script
+        assert_eq!(html(r#"[:iframe {:srcdoc "<script>alert(1)</script>"}]"#), "");
+        assert_eq!(html(r#"[:div {:srcdoc "<script>alert(1)</script>"} "x"]"#), "<div>x</div>");
+        assert_eq!(
+            html(r#"[:div [:iframe {:src "https://ok.example"}] [:object {:data "x.swf"}] [:embed {:src "x.swf"}] [:base {:href "https://evil.example/"}] [:meta {:http-equiv "refresh"}] "kept"]"#),
+            "<div>kept</div>"
+        );
+    }
+
+    #[test]
+    fn test_multiline_block_in_page() {
+        let input = "- Stats\n- [:div\n   [:h2 \"brain $100 [[state]]\"]\n   [:ul [:li \"pages: 1,299\"]]]\n- [:span \"inline\"]\n- after $100";
+        assert_eq!(
+            transform(input),
+            "- Stats\n<div><h2>brain $100 [[state]]</h2><ul><li>pages: 1,299</li></ul></div>\n- <span>inline</span>\n- after \\$100"
+        );
+    }
+
+    #[test]
+    fn test_malformed_or_trailing_text_left_alone() {
+        assert_eq!(transform("- [:b \"unclosed\"\n- next"), "- [:b \"unclosed\"\n- next");
+        assert_eq!(transform("- [:b \"x\"] and more"), "- [:b \"x\"] and more");
+        assert!(hiccup::parse(&format!("{}{}", "[:div ".repeat(100), "]".repeat(100))).is_none());
+    }
+}
+
+#[cfg(test)]
+mod sanitize_tests {
+    use crate::content;
+    use crate::page::PageIndex;
+    use crate::sanitize;
+
+    fn transform(input: &str) -> String {
+        content::transform(input, &PageIndex::default().with_sanitize_html(true))
+    }
+
+    #[test]
+    fn test_disallowed_tags_attributes_and_urls_are_removed() {
+        assert_eq!(
+            sanitize::html(r#"<p class="x" onclick="steal()" style="position: fixed">Hi <form action="/x"><b>there</b></form><script>alert(1)</script><!-- note --></p>"#),
+            r#"<p class="x">Hi <b>there</b></p>"#
+        );
+        assert_eq!(
+            sanitize::html(r#"<a href="jav&#x61;script:alert(1)">x</a> <a href='https://x.io/?a=1&amp;b=2' target=_blank>y</a> <img src=../assets/a.png alt="A">"#),
+            r#"<a>x</a> <a href="https://x.io/?a=1&amp;b=2">y</a> <img src="../assets/a.png" alt="A">"#
+        );
+        // Not tags: a stray `<` is escaped, autolinks stay as they are
+        assert_eq!(sanitize::html("1 < 2 and <https://x.io>"), "1 &lt; 2 and <https://x.io>");
+    }
+
+    #[test]
+    fn test_tags_are_read_the_way_browsers_read_them() {
+        assert_eq!(sanitize::html("<svg/onload=alert(1)>"), "");
+        assert_eq!(sanitize::html("<img src=x onerror=alert(2) a=<>"), r#"<img src="x">"#);
+        assert_eq!(sanitize::html(r#"<img/src="a.png"/onerror=x() />"#), r#"<img src="a.png"/>"#);
+        assert_eq!(sanitize::html("<b =onclick=x()>hi</b >"), "<b>hi</b>");
+        assert_eq!(sanitize::html("<ScRiPt>x()</sCrIpT foo>ok<!--> <style>"), "ok ");
+        // Never closed: escaped rather than left for the browser to finish
+        assert_eq!(sanitize::html("a <img src=x onerror=y()"), "a &lt;img src=x onerror=y()");
+        assert_eq!(sanitize::html("<a href='x>y"), "&lt;a href='x>y");
+    }
+
+    #[test]
+    fn test_raw_html_export_blocks_and_hiccup_are_sanitized() {
+        let out = transform("- Text <iframe src=\"https://evil.example\"></iframe><b onmouseover=\"x()\">bold</b>\n- `<script>kept as code</script>`");
+        assert!(out.contains("Text <b>bold</b>"), "{}", out);
+        assert!(out.contains("`<script>kept as code</script>`"), "{}", out);
+
+        let out = transform("#+BEGIN_EXPORT html\n<div style=\"color: red\">Hi<script>x()</script></div>\n#+END_EXPORT");
+        assert!(out.contains("<div>Hi</div>"), "{}", out);
+
+        let out = transform(r#"- [:div {:style {:color "red"}} [:iframe {:src "https://x.io"}] [:em "ok"]]"#);
+        assert!(out.contains("<div><em>ok</em></div>"), "{}", out);
+    }
+
+    #[test]
+    fn test_generated_embeds_are_kept() {
+        let out = transform("- {{video https://youtu.be/dQw4w9WgXcQ}}");
+        assert!(out.contains("<iframe src=\"https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ\""), "{}", out);
+    }
+}
+
+#[cfg(test)]
+mod graph_config_tests {
+    use crate::config::Config;
+    use crate::edn;
+    use crate::journals;
+    use crate::page;
+    use crate::source::Hidden;
+    use std::fs;
+    use tempfile::tempdir;
+
+    const CONFIG: &str = r#"{:meta/version 1
+ ;; :hidden ["/pages"]
+ :hidden ["/archive" "pages/drafts/" "/pages/secret.md"]
+ :property-pages/excludelist #{:icon :status}
+ :default-queries
+ {:journals [{:title "🔨 NOW" :query [:find (pull ?h [*]) :where [?h :block/marker ?m]]}
+             {:title [:h3 "📅 Open tasks"] :query "(task TODO DOING)" :collapsed? false}
+             {:query "(page-tags [[project]])"}]}}"#;
+
+    fn graph() -> tempfile::TempDir {
+        let temp = tempdir().unwrap();
+        for dir in ["logseq", "pages/drafts", "archive", "journals"] {
+            fs::create_dir_all(temp.path().join(dir)).unwrap();
+        }
+        fs::write(temp.path().join("logseq/config.edn"), CONFIG).unwrap();
+        temp
+    }
+
+    #[test]
+    fn test_setting_reads_forms_and_skips_comments() {
+        let hidden = edn::setting(CONFIG, ":hidden").unwrap();
+        assert_eq!(hidden.items().len(), 3);
+        assert_eq!(edn::setting(CONFIG, ":property-pages/excludelist").unwrap().items().len(), 2);
+        assert!(edn::setting(CONFIG, ":favorites").is_none());
+    }
+
+    #[test]
+    fn test_hidden_paths_are_left_out_of_the_index() {
+        let temp = graph();
+        let pages = temp.path().join("pages");
+        fs::write(pages.join("public.md"), "- [[secret]]").unwrap();
+        fs::write(pages.join("secret.md"), "- hidden file").unwrap();
+        fs::write(pages.join("secretive.md"), "- not hidden: only whole path components match").unwrap();
+        fs::write(pages.join("drafts/wip.md"), "- hidden folder").unwrap();
+
+        let hidden = Hidden::load(temp.path());
+        assert!(hidden.contains(&temp.path().join("archive/2020/old.md")));
+        assert!(!hidden.contains(&temp.path().join("archived.md")));
+
+        let index = page::build_index(&pages).unwrap();
+        let mut names: Vec<_> = index.iter().map(|p| p.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["public", "secretive"]);
+    }
+
+    #[test]
+    fn test_default_journal_queries_on_journals_index() {
+        let temp = graph();
+        fs::write(temp.path().join("journals/2025_01_02.md"), "- TODO write report").unwrap();
+        fs::write(temp.path().join("pages/roadmap.md"), "tags:: project\n\n- plan").unwrap();
+
+        let queries = journals::default_queries(&temp.path().join("logseq/config.edn"));
+        assert_eq!(
+            queries.iter().map(|q| (q.title.as_str(), q.query.as_str())).collect::<Vec<_>>(),
+            vec![("📅 Open tasks", "(task TODO DOING)"), ("(page-tags [[project]])", "(page-tags [[project]])")]
+        );
+
+        let out = temp.path().join("out");
+        fs::create_dir_all(&out).unwrap();
+        let index = page::build_graph_index(temp.path()).unwrap();
+        let config = Config { input_dir: temp.path().to_path_buf(), journal_queries: true, ..Default::default() };
+        journals::process_journals(&temp.path().join("journals"), &out, &index, &config).unwrap();
+        let journal_index = fs::read_to_string(out.join("index.md")).unwrap();
+        let tasks = journal_index.find("## 📅 Open tasks").expect("query section");
+        assert!(journal_index.contains("roadmap"), "{}", journal_index);
+        assert!(tasks < journal_index.find("## [[journals/2025-01-02").unwrap(), "{}", journal_index);
+    }
+}
+
+#[cfg(test)]
+mod schedule_tests {
+    use crate::content;
+    use crate::page::PageIndex;
+    use crate::query;
+    use crate::schedule::{self, Kind, Timestamp};
+    use super::page;
+
+    #[test]
+    fn test_timestamps_and_repeaters() {
+        let ts = Timestamp::parse("2025-01-01 Wed 10:00 .+1w").unwrap();
+        assert_eq!(ts.date.to_string(), "2025-01-01");
+        assert_eq!(ts.time.as_deref(), Some("10:00"));
+        assert_eq!(ts.repeater.as_deref(), Some(".+1w"));
+        assert_eq!(ts.repeat_text.as_deref(), Some("every week after completion"));
+        assert_eq!(ts.display, "2025-01-01 Wed 10:00");
+        assert_eq!(Timestamp::parse("2025-03-10 Mon ++2d").unwrap().repeat_text.as_deref(), Some("every 2 days, skipping missed dates"));
+        assert!(Timestamp::parse("next tuesday").is_none());
+
+        let out = content::transform("- TODO Review\n  SCHEDULED: <2025-01-01 Wed +1m>\n  DEADLINE: <2025-01-05 Sun>", &PageIndex::default());
+        assert!(out.contains("📅 Scheduled: 2025-01-01 Wed · 🔁 every month"), "{}", out);
+        assert!(out.contains("⏰ Deadline: 2025-01-05 Sun"), "{}", out);
+    }
+
+    #[test]
+    fn test_items_and_between_queries() {
+        let items = schedule::items("- TODO [#A] Pay [[rent]]\n  SCHEDULED: <2025-02-01 Sat .+1m>\n- note\n- DONE Send invoice\n  DEADLINE: <2025-01-20 Mon>");
+        assert_eq!(items.len(), 2);
+        assert_eq!((items[0].kind, items[0].marker.as_deref(), items[0].text.as_str()), (Kind::Scheduled, Some("TODO"), "Pay rent"));
+        assert!(items[1].is_done());
+
+        let index = PageIndex::new(vec![
+            page("bills", "- TODO Pay rent\n  SCHEDULED: <2025-02-01 Sat>"),
+            page("journals/2025_02_03", "- standup"),
+            page("ideas", "- someday"),
+        ]);
+        let mut names: Vec<_> = query::execute("(between [[2025-02-01]] [[2025-02-05]])", &index).unwrap().iter().map(|p| p.name.clone()).collect();
+        names.sort();
+        assert_eq!(names, vec!["bills", "journals/2025_02_03"]);
+    }
+
+    #[test]
+    fn test_events_skip_done_and_private_and_sort_by_date() {
+        let index = PageIndex::new(vec![
+            page("work", "- TODO Ship\n  DEADLINE: <2025-03-01 Sat 17:00>\n- DONE Old\n  DEADLINE: <2025-01-01 Wed>"),
+            page("home", "- LATER Clean\n  SCHEDULED: <2025-02-01 Sat ++1w>"),
+            page("secret", "private:: true\n\n- TODO Hide\n  SCHEDULED: <2025-01-15 Wed>"),
+        ]);
+        let events = schedule::events(&index, false);
+        assert_eq!(events.iter().map(|e| e.text.as_str()).collect::<Vec<_>>(), vec!["Clean", "Ship"]);
+        let json = serde_json::to_string(&events[1]).unwrap();
+        assert_eq!(json, r#"{"kind":"deadline","date":"2025-03-01","time":"17:00","marker":"TODO","text":"Ship","page":"work","slug":"work"}"#);
+    }
+}
+
+#[cfg(test)]
+mod ics_tests {
+    use crate::ics;
+    use crate::journals;
+    use crate::page::{Page, PageIndex};
+    use crate::resolver;
+    use super::TestPage;
+
+    fn page(name: &str, content: &str) -> Page {
+        // Journals are published by date, as build_graph_index sets up
+        let slug = name
+            .strip_prefix("journals/")
+            .and_then(journals::parse_journal_date)
+            .map(|(date, _)| resolver::journal_output_name(&date));
+        TestPage::new(name).content(content).slug(slug.as_deref()).build()
+    }
+
+    fn index() -> PageIndex {
+        PageIndex::new(vec![
+            page("journals/2025_01_02", "- \n- Met [[Ana]], planned Q1; long day\n- other"),
+            page("journals/2025_01_03", "private:: true\n\n- secret"),
+            page("tasks", "- TODO Water plants\n  SCHEDULED: <2025-01-04 Sat 9:30 .+2w>\n- TODO Pay rent\n  DEADLINE: <2025-01-04 Sat>\n- TODO Pay tax\n  DEADLINE: <2025-01-04 Sat>"),
+        ])
+    }
+
+    #[test]
+    fn test_journal_days_and_scheduled_items() {
+        let ics = ics::calendar(&index(), "My Garden", Some("https://example.org/garden/"), false);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(ics.contains("X-WR-CALNAME:My Garden\r\n"));
+        assert!(ics.contains(
+            "UID:journal-2025-01-02@logseq-to-quartz\r\nDTSTAMP:20250102T000000Z\r\nDTSTART;VALUE=DATE:20250102\r\nSUMMARY:Met Ana\\, planned Q1\\; long day\r\nURL:https://example.org/garden/journals/2025-01-02\r\n"
+        ), "{}", ics);
+        assert!(!ics.contains("secret"));
+        assert!(ics.contains("DTSTART:20250104T093000\r\nSUMMARY:📅 Water plants\r\n"), "{}", ics);
+        assert!(ics.contains("RRULE:FREQ=WEEKLY;INTERVAL=2\r\n"), "{}", ics);
+        // Two deadlines on the same page and day get distinct UIDs
+        assert!(ics.contains("UID:deadline-2025-01-04-tasks@logseq-to-quartz\r\n"));
+        assert!(ics.contains("UID:deadline-2025-01-04-tasks-1@logseq-to-quartz\r\n"));
+    }
+
+    #[test]
+    fn test_long_lines_are_folded() {
+        let index = PageIndex::new(vec![page("journals/2025_01_05", &format!("- {}", "déjà vu ".repeat(20)))]);
+        let ics = ics::calendar(&index, "Journal", None, false);
+        for line in ics.split("\r\n") {
+            assert!(line.len() <= 75, "{:?}", line);
+        }
+        assert!(ics.contains("URL:/journals/2025-01-05\r\n"));
+        let unfolded = ics.replace("\r\n ", "");
+        assert!(unfolded.contains(&format!("SUMMARY:{}", "déjà vu ".repeat(20).trim_end())));
+    }
+}
+
+#[cfg(test)]
+mod feed_tests {
+    use crate::feed::{self, FeedConfig, FeedSort};
+    use crate::page::{Page, PageIndex};
+    use super::TestPage;
+
+    fn page(name: &str, content: &str, created: &str, modified: &str) -> Page {
+        TestPage::new(name)
+            .content(content)
+            .created((!created.is_empty()).then_some(created))
+            .modified((!modified.is_empty()).then_some(modified))
+            .build()
+    }
+
+    fn index() -> PageIndex {
+        PageIndex::new(vec![
+            page("Rust & C", "title:: Rust & C\n\n- Notes on <FFI> and [[Cargo]]", "2024-01-01", "2025-03-01"),
+            page("Cargo", "- Build tool", "2024-06-01", "2025-02-01"),
+            page("Secret", "private:: true\n\n- hidden", "2025-04-01", "2025-04-01"),
+            page("Undated", "- no history", "", ""),
+        ])
+    }
+
+    #[test]
+    fn test_atom_feed_newest_first_with_excerpts() {
+        let xml = feed::build(&index(), "My Garden", Some("https://example.org/garden/"), &FeedConfig::default(), false);
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">"));
+        assert!(xml.contains("<updated>2025-03-01T00:00:00Z</updated>\n  <author>"), "{}", xml);
+        assert!(xml.contains("<link rel=\"self\" href=\"https://example.org/garden/feed.xml\"/>"));
+        assert!(xml.contains(
+            "<title>Rust &amp; C</title>\n    <link href=\"https://example.org/garden/Rust--and--C\"/>\n    <id>https://example.org/garden/Rust--and--C</id>"
+        ), "{}", xml);
+        assert!(xml.contains("<summary>Notes on &lt;FFI&gt; and Cargo</summary>"), "{}", xml);
+        assert!(xml.contains("<published>2024-01-01T00:00:00Z</published>"));
+        assert!(!xml.contains("Secret") && !xml.contains("Undated"));
+        assert!(xml.find("Rust &amp; C").unwrap() < xml.find("<title>Cargo").unwrap());
+    }
+
+    #[test]
+    fn test_feed_sort_and_limit() {
+        let config = FeedConfig { limit: 1, sort: FeedSort::Created };
+        let xml = feed::build(&index(), "Garden", None, &config, false);
+        assert_eq!(xml.matches("<entry>").count(), 1);
+        assert!(xml.contains("<title>Cargo</title>"), "{}", xml);
+        assert!(xml.contains("<id>urn:logseq-to-quartz:Cargo</id>"), "{}", xml);
+    }
+}
+
+#[cfg(test)]
+mod sitemap_tests {
+    use crate::page::{Page, PageIndex};
+    use crate::sitemap;
+    use super::TestPage;
+
+    fn page(name: &str, content: &str, modified: Option<&str>) -> Page {
+        TestPage::new(name).content(content).modified(modified).build()
+    }
+
+    fn index() -> PageIndex {
+        PageIndex::new(vec![
+            page("Rust & C", "- notes", Some("2025-03-01")),
+            page("Café", "- coffee", None),
+            page("draft", "draft:: true\n\n- wip", None),
+            page("Hidden gem", "unlisted:: true\n\n- shh", Some("2025-01-01")),
+            page("Secret", "private:: true\n\n- no", None),
+        ])
+    }
+
+    #[test]
+    fn test_sitemap_lists_published_pages() {
+        let xml = sitemap::sitemap(&index(), "https://example.org/garden/", false);
+        assert!(xml.contains("<loc>https://example.org/garden/</loc>"));
+        assert!(xml.contains("<loc>https://example.org/garden/Rust--and--C</loc>\n    <lastmod>2025-03-01</lastmod>"), "{}", xml);
+        assert!(xml.contains("<loc>https://example.org/garden/Caf%C3%A9</loc>"), "{}", xml);
+        assert!(!xml.contains("draft") && !xml.contains("Hidden") && !xml.contains("Secret"), "{}", xml);
+    }
+
+    #[test]
+    fn test_robots_disallows_unlisted_and_drafts() {
+        assert_eq!(
+            sitemap::robots(&index(), "https://example.org/garden"),
+            "User-agent: *\nDisallow: /garden/Hidden-gem$\nDisallow: /garden/draft$\n\nSitemap: https://example.org/garden/sitemap.xml\n"
+        );
+        let open = PageIndex::new(vec![page("a", "- a", None)]);
+        assert_eq!(sitemap::robots(&open, "https://example.org"), "User-agent: *\nDisallow:\n\nSitemap: https://example.org/sitemap.xml\n");
+    }
+}
+
+#[cfg(test)]
+mod social_tests {
+    use std::collections::HashMap;
+
+    use crate::social;
+
+    #[test]
+    fn test_excerpt_reads_blocks_in_order_and_skips_non_prose() {
+        let body = "## Overview\n- First **block** with a [[Link]].\n  - Nested child\n    ```rust\n    fn main() {}\n    ```\n- | a | b |\n- > [!note] Callout\n  > quoted text\n- last words here";
+        assert_eq!(social::excerpt(body, 40), "First block with a Link. Nested child quoted text last words here");
+        assert_eq!(social::excerpt(body, 4), "First block with a…");
+        assert_eq!(social::excerpt("## Only a heading", 40), "");
+        assert_eq!(social::excerpt("- ~~~\n  let x = 1;\n  ~~~\n- Prose", 40), "Prose");
+    }
+
+    #[test]
+    fn test_social_image_prefers_cover_then_first_image() {
+        let mut properties = HashMap::new();
+        let body = "- see [doc](../assets/doc.pdf)\n- ![chart|400](../assets/chart.png)";
+        assert_eq!(social::image(&properties, body, None).as_deref(), Some("assets/chart.png"));
+        assert_eq!(
+            social::image(&properties, body, Some("https://example.org/garden/")).as_deref(),
+            Some("https://example.org/garden/assets/chart.png")
+        );
+
+        properties.insert("cover".to_string(), "![](https://cdn.example.org/cover.jpg)".to_string());
+        assert_eq!(social::image(&properties, body, None).as_deref(), Some("https://cdn.example.org/cover.jpg"));
+        assert_eq!(social::image(&HashMap::new(), "- ![](../assets/clip.mp4)", None), None);
+    }
+
+    #[test]
+    fn test_description_property_is_not_replaced() {
+        let fm = "---\ntitle: \"Page\"\ndescription: \"Mine\"\n---\n";
+        let mut properties = HashMap::new();
+        properties.insert("description".to_string(), "Mine".to_string());
+        let out = social::insert_frontmatter(fm, &properties, "- derived text", 40, None);
+        assert_eq!(out, fm);
+
+        let out = social::insert_frontmatter("---\n---\n", &HashMap::new(), "- derived \"text\"\n- ![](../assets/a.webp)", 40, None);
+        assert_eq!(out, "---\ndescription: \"derived \\\"text\\\"\"\nsocialImage: \"assets/a.webp\"\n---\n");
+        assert_eq!(social::insert_frontmatter("---\n---\n", &HashMap::new(), "- derived", 0, None), "---\n---\n");
+    }
+}
+
+#[cfg(test)]
+mod external_tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use crate::external::{self, LinkCache, LinkPreview};
+
+    fn titled(url: &str, title: &str) -> BTreeMap<String, LinkPreview> {
+        let preview = LinkPreview { title: Some(title.to_string()), ..Default::default() };
+        BTreeMap::from([(url.to_string(), preview)])
+    }
+
+    #[test]
+    fn test_parse_prefers_open_graph_and_decodes_entities() {
+        let html = r#"<head><title>Home | Site</title>
+            <meta content="Tom &amp; Jerry &#8212; cartoons" property="og:title">
+            <meta name="description" content="  All
+              the episodes ">
+            <meta property='og:image' content='https://example.org/card.png'></head>"#;
+        let preview = external::parse(html);
+        assert_eq!(preview.title.as_deref(), Some("Tom & Jerry — cartoons"));
+        assert_eq!(preview.description.as_deref(), Some("All the episodes"));
+        assert_eq!(preview.image.as_deref(), Some("https://example.org/card.png"));
+        assert_eq!(external::parse("<title>\n Plain </title>").title.as_deref(), Some("Plain"));
+    }
+
+    #[test]
+    fn test_urls_skip_code_and_trailing_punctuation() {
+        let content = "- See https://example.org/a, and (https://en.wikipedia.org/wiki/Rust_(language)).\n- `https://example.org/inline`\n- ```\n  https://example.org/fenced\n  ```\n- ~~~\n  https://example.org/tilde\n  ~~~\n- [label](https://example.org/b)";
+        assert_eq!(
+            external::urls(content),
+            vec![
+                "https://example.org/a",
+                "https://en.wikipedia.org/wiki/Rust_(language)",
+                "https://example.org/b",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bare_urls_become_titled_links() {
+        let previews = titled("https://example.org/a", "A *starred* [page]");
+        assert_eq!(
+            external::link_bare_urls("- read https://example.org/a.\n- [mine](https://example.org/a) <https://example.org/a>", &previews),
+            "- read [A \\*starred\\* \\[page\\]](https://example.org/a).\n- [mine](https://example.org/a) <https://example.org/a>"
+        );
+        assert_eq!(external::link_bare_urls("https://example.org/b", &previews), "https://example.org/b");
+    }
+
+    #[test]
+    fn test_offline_resolve_uses_cache_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("link-cache.json");
+        std::fs::write(&path, r#"{"https://example.org/a": {"title": "Cached", "fetched": "2025-01-01"}}"#).unwrap();
+        let mut cache = LinkCache::load(&path);
+        let urls = BTreeSet::from(["https://example.org/a".to_string(), "https://example.org/new".to_string()]);
+        let previews = external::resolve(&urls, &mut cache, true);
+        assert_eq!(previews, titled("https://example.org/a", "Cached"));
+        assert!(cache.get("https://example.org/new").is_none());
+    }
+}
+
+#[cfg(test)]
+mod archive_tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use crate::archive::{self, ArchiveCache};
+
+    fn snapshots() -> BTreeMap<String, String> {
+        BTreeMap::from([(
+            "https://example.org/a".to_string(),
+            "https://web.archive.org/web/2024/https://example.org/a".to_string(),
+        )])
+    }
+
+    #[test]
+    fn test_annotate_links_and_bare_urls_but_not_images() {
+        let content = "- [A](https://example.org/a) and https://example.org/a.\n- ![pic](https://example.org/a)\n- [B](https://example.org/b)";
+        let archived = " <sup class=\"archived\">[archived](https://web.archive.org/web/2024/https://example.org/a)</sup>";
+        assert_eq!(
+            archive::annotate(content, &snapshots()),
+            format!(
+                "- [A](https://example.org/a){0} and https://example.org/a{0}.\n- ![pic](https://example.org/a)\n- [B](https://example.org/b)",
+                archived
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_availability_response() {
+        let json = r#"{"url": "example.org/a", "archived_snapshots": {"closest": {"status": "200", "available": true,
+            "url": "http://web.archive.org/web/20240101000000/https://example.org/a", "timestamp": "20240101000000"}}}"#;
+        assert_eq!(
+            archive::parse_availability(json).as_deref(),
+            Some("https://web.archive.org/web/20240101000000/https://example.org/a")
+        );
+        assert_eq!(archive::parse_availability(r#"{"archived_snapshots": {}}"#), None);
+    }
+
+    #[test]
+    fn test_offline_resolve_uses_cache_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("archive-cache.json");
+        std::fs::write(
+            &path,
+            r#"{"https://example.org/a": {"snapshot": "https://web.archive.org/web/2024/https://example.org/a", "checked": "2025-01-01"}}"#,
+        )
+        .unwrap();
+        let mut cache = ArchiveCache::load(&path);
+        let urls = BTreeSet::from(["https://example.org/a".to_string(), "https://example.org/b".to_string()]);
+        assert_eq!(archive::resolve(&urls, &mut cache, true, true), snapshots());
+        assert_eq!(cache.snapshot("https://example.org/b"), None);
+    }
+}
+
+#[cfg(test)]
+mod block_embed_tests {
+    use crate::content;
+    use crate::outline;
+    use crate::page::{Page, PageIndex};
+
+    const SOURCE: &str = "- ## Setup\n- Parent with [[Rust]]\n  id:: 6650a1b2-1111-2222-3333-444455556666\n\t- child\n\t\t- grandchild\n\t- second\n- after";
+    const ID: &str = "6650a1b2-1111-2222-3333-444455556666";
+
+    fn index() -> PageIndex {
+        let page = |name: &str, content: &str| Page {
+            name: name.to_string(),
+            name_lower: name.to_lowercase(),
+            content: content.to_string(),
+            ..Default::default()
+        };
+        PageIndex::new(vec![
+            page("Source", SOURCE),
+            page("Rust", "- lang"),
+            page("Loop", "- loops {{embed ((7770a1b2-1111-2222-3333-444455556666))}}\n  id:: 7770a1b2-1111-2222-3333-444455556666"),
+        ])
+    }
+
+    #[test]
+    fn test_subtree_depth() {
+        let subtree = |id: &str, depth: Option<usize>| outline::parse(SOURCE).find(id).map(|block| block.subtree(depth));
+        let full = subtree(ID, None).unwrap();
+        assert_eq!(full.text, "Parent with [[Rust]]");
+        assert_eq!(full.children, vec!["\t- child", "\t\t- grandchild", "\t- second"]);
+        assert_eq!(subtree(ID, Some(1)).unwrap().children, vec!["\t- child", "\t- second"]);
+        assert!(subtree(ID, Some(0)).unwrap().children.is_empty());
+        assert_eq!(subtree("00000000-0000-0000-0000-000000000000", None), None);
+    }
+
+    #[test]
+    fn test_block_embed_inlines_subtree() {
+        let out = content::transform_page(&format!("- {{{{embed (({}))}}}}\n  - mine", ID), &index());
+        assert_eq!(
+            out.content,
+            "- Parent with [[Rust]] [[Source|↗]]\n\t- child\n\t\t- grandchild\n\t- second\n  - mine"
+        );
+        let shallow = content::transform(&format!("  - {{{{embed (({}))}}}}", ID), &index().with_embed_depth(Some(0)));
+        assert_eq!(shallow, "  - Parent with [[Rust]] [[Source|↗]]");
+    }
+
+    #[test]
+    fn test_missing_and_cyclic_block_embeds() {
+        let out = content::transform_page("- {{embed ((00000000-0000-0000-0000-000000000000))}}", &index());
+        assert!(out.content.contains("*Block embed - view in Logseq*"), "{}", out.content);
+        assert!(out.diagnostics.iter().any(|d| d.kind == "block-embed"), "{:?}", out.diagnostics);
+
+        let out = content::transform_page("- {{embed ((7770a1b2-1111-2222-3333-444455556666))}}", &index());
+        assert_eq!(out.content, "- loops *Block embed - view in Logseq* [[Loop|↗]]");
+        assert!(out.diagnostics.iter().any(|d| d.message.contains("nested in itself")), "{:?}", out.diagnostics);
+    }
+
+    #[test]
+    fn test_page_embed_keeps_heading_anchor() {
+        assert_eq!(content::transform("- {{embed [[Source#Setup]]}}", &index()), "- ![[Source#Setup]]");
+        assert!(content::unresolved_links("- {{embed [[source#Setup]]}}", &index()).is_empty());
+        // The page of a section link is what's missing
+        assert_eq!(content::unresolved_links("- [[Missing#x]]", &index()), vec!["Missing"]);
+    }
+
+    #[test]
+    fn test_section_links_keep_anchor_through_resolution() {
+        let page = |name: &str, content: &str| Page {
+            name: name.to_string(),
+            name_lower: name.to_lowercase(),
+            content: content.to_string(),
+            ..Default::default()
+        };
+        let index = PageIndex::new(vec![
+            Page { aliases: vec!["Rustlang".to_string()], ..page("Rust", "- ## Getting Started\n\t- text") },
+            page("$C", "- ## Prices"),
+        ]);
+        assert_eq!(content::transform("- [[Rust#Getting Started]]", &index), "- [[Rust#Getting Started]]");
+        // Resolved through the alias, the anchor kept and shown
+        assert_eq!(
+            content::transform("- [[rustlang#Getting Started]]", &index),
+            "- [[Rust#Getting Started|rustlang#Getting Started]]"
+        );
+        assert_eq!(content::transform("- [[Rust#Getting Started|setup]]", &index), "- [[Rust#Getting Started|setup]]");
+        // Prefix matching applies to the page only
+        assert_eq!(content::transform("- [[Rust guide#Getting Started]]", &index), "- [[Rust#Getting Started|Rust guide#Getting Started]]");
+        assert!(content::transform("- [[$C#Prices]]", &index).contains(r#"href="$C#prices""#));
+
+        assert!(content::missing_anchors("- [[Rust#getting started]] [[Nowhere#x]]", &index).is_empty());
+        assert_eq!(content::missing_anchors("- [[Rust#Install]]\n- `[[Rust#Code]]`", &index), vec!["Rust#Install"]);
+    }
+}
+
+#[cfg(test)]
+mod alias_redirect_tests {
+    use crate::config::Config;
+    use crate::page::{self, AliasStyle, Page, PageIndex};
+    use super::TestPage;
+
+    fn page(name: &str, aliases: &[&str]) -> Page {
+        TestPage::new(name).aliases(aliases).build()
+    }
+
+    #[test]
+    fn test_alias_redirect_page() {
+        assert_eq!(
+            page::alias_redirect("Work/CV", "Work/CV", &page("Cyber Valley", &[])),
+            "---\ntitle: \"Work/CV\"\nredirect: \"Cyber-Valley\"\nnoindex: true\n---\n\n<meta http-equiv=\"refresh\" content=\"0; url=../Cyber-Valley\">\n\nMoved to [[Cyber Valley|Cyber Valley]].\n"
+        );
+    }
+
+    #[test]
+    fn test_redirects_skip_taken_paths_and_private_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Taken.md"), "real page").unwrap();
+        let mut secret = page("Secret", &["hidden"]);
+        secret.properties.insert("private".to_string(), "true".to_string());
+        let index = PageIndex::new(vec![page("Cyber Valley", &["cv", "Taken"]), secret]);
+        let config = Config { alias_style: AliasStyle::Redirect, ..Default::default() };
+
+        assert_eq!(page::create_alias_redirects(dir.path(), &index, &config).unwrap(), 1);
+        assert!(std::fs::read_to_string(dir.path().join("cv.md")).unwrap().contains("url=Cyber-Valley"));
+        assert_eq!(std::fs::read_to_string(dir.path().join("Taken.md")).unwrap(), "real page");
+        assert!(!dir.path().join("hidden.md").exists());
+    }
+
+    #[test]
+    fn test_redirects_stay_in_the_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("out");
+        std::fs::create_dir_all(&output).unwrap();
+        let index = PageIndex::new(vec![page("Cyber Valley", &["../escaped-alias", "docs/../../up"])]);
+        let config = Config { alias_style: AliasStyle::Redirect, ..Default::default() };
+
+        assert_eq!(page::create_alias_redirects(&output, &index, &config).unwrap(), 0);
+        assert!(!dir.path().join("escaped-alias.md").exists());
+        assert!(!output.join("escaped-alias.md").exists());
+        assert!(!dir.path().join("up.md").exists());
+    }
+}
+
+#[cfg(test)]
+mod duplicate_page_tests {
+    use crate::page::{self, Page, PageIndex};
+    use crate::resolver;
+    use std::path::PathBuf;
+    use super::TestPage;
+
+    fn page(name: &str, file: &str, created: Option<&str>, content: &str) -> Page {
+        let mut page = TestPage::new(name).content(content).created(created).source(file).build();
+        page.tags = page.properties.get("tags").map(|t| t.split(", ").map(String::from).collect()).unwrap_or_default();
+        page
+    }
+
+    #[test]
+    fn test_merges_case_variants_into_earliest_page() {
+        let merged = page::merge_duplicates(vec![
+            page("project x", "pages/project x.md", Some("2024-03-01"), "tags:: b\nstatus:: done\n\n- later notes"),
+            page("Project X", "pages/Project X.md", Some("2023-01-01"), "tags:: a\n\n- first notes"),
+            page("Other", "pages/Other.md", None, "- other"),
+        ]);
+
+        assert_eq!(merged.len(), 2);
+        let project = merged.iter().find(|p| p.name == "Project X").unwrap();
+        assert_eq!(project.content, "tags:: a, b\nstatus:: done\n\n- first notes\n- later notes");
+        assert_eq!(project.tags, vec!["a", "b"]);
+        assert_eq!(project.properties.get("status").map(String::as_str), Some("done"));
+        assert_eq!(project.duplicates, vec![PathBuf::from("pages/project x.md")]);
+        assert!(merged.iter().find(|p| p.name == "Other").unwrap().duplicates.is_empty());
+    }
+
+    #[test]
+    fn test_merges_namespace_separator_variants() {
+        let merged = page::merge_duplicates(vec![
+            page("a/b", "pages/a___b.md", None, "- triple lowbar"),
+            page("a/b", "pages/a%2Fb.md", None, "- encoded"),
+        ]);
+
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].content, "- triple lowbar\n- encoded");
+        assert_eq!(merged[0].duplicates, vec![PathBuf::from("pages/a%2Fb.md")]);
+    }
+
+    #[test]
+    fn test_links_canonicalize_to_surviving_page() {
+        let index = PageIndex::new(page::merge_duplicates(vec![
+            page("Project X", "pages/Project X.md", None, "- one"),
+            page("project x", "pages/project x.md", None, "- two"),
+        ]));
+
+        assert_eq!(resolver::link_target_for("project x", &index), "Project X");
+        assert_eq!(resolver::link_target_for("Project X", &index), "Project X");
+    }
+}
+
+#[cfg(test)]
+mod date_page_tests {
+    use crate::journals::DatePages;
+    use crate::page::{self, Page, PageIndex};
+    use crate::resolver;
+    use std::path::PathBuf;
+    use super::TestPage;
+
+    fn page(name: &str, file: &str, content: &str) -> Page {
+        let slug = name.strip_prefix("journals/").map(|_| "journals/2025-01-01");
+        TestPage::new(name).content(content).slug(slug).source(file).build()
+    }
+
+    fn index() -> PageIndex {
+        PageIndex::new(vec![
+            page("journals/2025_01_01", "journals/2025_01_01.md", "- journal entry"),
+            page("2025-01-01", "pages/2025-01-01.md", "tags:: imported\n\n- imported entry"),
+            page("2025-01-02", "pages/2025-01-02.md", "- no journal that day"),
+        ])
+    }
+
+    fn journal(index: &PageIndex) -> &Page {
+        index.with_slug("journals/2025-01-01").unwrap()
+    }
+
+    #[test]
+    fn test_concat_appends_page_to_journal() {
+        let index = page::merge_date_pages(index(), DatePages::Concat);
+
+        assert_eq!(index.len(), 2);
+        assert_eq!(journal(&index).content, "tags:: imported\n\n- journal entry\n- imported entry");
+        assert_eq!(journal(&index).duplicates, vec![PathBuf::from("pages/2025-01-01.md")]);
+        assert!(resolver::find_page("2025-01-02", &index).is_some());
+        assert_eq!(resolver::link_target_for("2025-01-01", &index), "journals/2025-01-01");
+    }
+
+    #[test]
+    fn test_prefer_journal_or_page() {
+        let kept_journal = page::merge_date_pages(index(), DatePages::PreferJournal);
+        assert_eq!(journal(&kept_journal).content, "- journal entry");
+        assert!(resolver::find_page("2025-01-01", &kept_journal).is_none());
+
+        let kept_page = page::merge_date_pages(index(), DatePages::PreferPage);
+        assert_eq!(journal(&kept_page).content, "tags:: imported\n\n- imported entry");
+        assert_eq!(journal(&kept_page).properties.get("tags").map(String::as_str), Some("imported"));
+        assert_eq!(journal(&kept_page).duplicates, vec![PathBuf::from("pages/2025-01-01.md")]);
+    }
+
+    #[test]
+    fn test_merging_keeps_index_settings() {
+        let index = index().with_seed(7).with_embed_depth(Some(1)).with_sanitize_html(true);
+        let merged = page::merge_date_pages(index, DatePages::Concat);
+        assert_eq!(merged.len(), 2);
+        assert_eq!((merged.seed(), merged.embed_depth(), merged.sanitize_html()), (7, Some(1), true));
+        let private = page::drop_private_blocks(merged);
+        assert_eq!((private.seed(), private.embed_depth(), private.sanitize_html()), (7, Some(1), true));
+    }
+}
+
+#[cfg(test)]
+mod sidebar_tests {
+    use crate::favorites::NavNode;
+    use crate::page::{Page, PageIndex};
+    use crate::sidebar;
+    use super::TestPage;
+
+    fn page(name: &str, private: bool) -> Page {
+        TestPage::new(name).properties(if private { &[("private", "true")] } else { &[] }).build()
+    }
+
+    fn node(title: &str, path: Option<&str>, children: Vec<NavNode>) -> NavNode {
+        NavNode { title: title.to_string(), path: path.map(String::from), children }
+    }
+
+    #[test]
+    fn test_tree_follows_outline() {
+        let index = PageIndex::new(vec![page("Cyber Valley", false), page("Secret", true)]);
+        let content = "- [[cyber valley]]\n- **Projects**\n  collapsed:: true\n\t- [[Secret]]\n\t- Docs [site](https://example.org)\n\t\t- [[Nowhere]]\n- Last";
+
+        assert_eq!(
+            sidebar::tree(content, &index, false),
+            vec![
+                node("cyber valley", Some("Cyber Valley"), vec![]),
+                node("Projects", None, vec![
+                    node("Secret", None, vec![]),
+                    node("Docs site", Some("https://example.org"), vec![node("Nowhere", None, vec![])]),
+                ]),
+                node("Last", None, vec![]),
+            ]
+        );
+        assert_eq!(sidebar::tree("- [[Secret]]", &index, true), vec![node("Secret", Some("Secret"), vec![])]);
+        assert_eq!(
+            sidebar::tree("- Tools\n  ~~~\n  - not a node\n  ~~~\n- After", &index, false),
+            vec![node("Tools", None, vec![]), node("After", None, vec![])]
+        );
+    }
+
+    #[test]
+    fn test_write_needs_contents_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = PageIndex::new(vec![page("Home", false)]);
+        assert_eq!(sidebar::write(dir.path(), &index, false).unwrap(), None);
+
+        let mut contents = page("contents", false);
+        contents.content = "- [[Home]]\n\t- Child".to_string();
+        let index = PageIndex::new(vec![page("Home", false), contents]);
+        assert_eq!(sidebar::write(dir.path(), &index, false).unwrap(), Some(2));
+        let json = std::fs::read_to_string(dir.path().join("sidebar.json")).unwrap();
+        assert!(json.contains("\"path\": \"Home\""));
+    }
+}
+
+#[cfg(test)]
+mod quartz_config_tests {
+    use crate::quartz::{self, Settings};
+    use std::fs;
+
+    const CONFIG: &str = "const config = {\n  configuration: {\n    pageTitle: \"Cyber\",\n    locale: 'en-US',\n    baseUrl: \"example.org\",\n  },\n}\n";
+
+    fn settings() -> Settings {
+        Settings {
+            page_title: Some("Ann's \"Garden\"".to_string()),
+            base_url: Some(Settings::base_url("https://garden.dev/notes/")),
+            locale: Some("de-DE".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_patch_replaces_values_once() {
+        let (patched, missing) = quartz::patch(CONFIG, &settings());
+        assert!(missing.is_empty());
+        assert!(patched.contains("pageTitle: \"Ann's \\\"Garden\\\"\","), "got: {}", patched);
+        assert!(patched.contains("locale: \"de-DE\","));
+        assert!(patched.contains("baseUrl: \"garden.dev/notes\","));
+        assert_eq!(quartz::patch(&patched, &settings()).0, patched);
+    }
+
+    #[test]
+    fn test_patch_leaves_unset_and_missing_settings() {
+        let settings = Settings { locale: Some("fr-FR".to_string()), ..Default::default() };
+        let (patched, missing) = quartz::patch("pageTitle: \"Cyber\"\n", &settings);
+        assert_eq!(patched, "pageTitle: \"Cyber\"\n");
+        assert_eq!(missing, vec!["locale"]);
+    }
+
+    #[test]
+    fn test_patch_config_keeps_backup_of_changed_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(quartz::CONFIG_FILE), CONFIG).unwrap();
+
+        assert!(quartz::patch_config(dir.path(), &settings()).unwrap());
+        assert_eq!(fs::read_to_string(dir.path().join(quartz::BACKUP_FILE)).unwrap(), CONFIG);
+        let patched = fs::read_to_string(dir.path().join(quartz::CONFIG_FILE)).unwrap();
+
+        // Nothing to change: no write, the backup still holds the original
+        assert!(!quartz::patch_config(dir.path(), &settings()).unwrap());
+        assert_eq!(fs::read_to_string(dir.path().join(quartz::CONFIG_FILE)).unwrap(), patched);
+        assert_eq!(fs::read_to_string(dir.path().join(quartz::BACKUP_FILE)).unwrap(), CONFIG);
+    }
+}
+
+#[cfg(test)]
+mod home_tests {
+    use crate::home::{self, HomeMode};
+    use crate::page::{Page, PageIndex};
+    use std::fs;
+    use super::TestPage;
+
+    fn page(name: &str, slug: Option<&str>, private: bool) -> Page {
+        TestPage::new(name).slug(slug).properties(if private { &[("private", "true")] } else { &[] }).build()
+    }
+
+    fn index() -> PageIndex {
+        PageIndex::new(vec![
+            page("Cyber Valley", None, false),
+            page("journals/2024_05_01", Some("journals/2024-05-01"), false),
+            page("journals/2024_06_01", Some("journals/2024-06-01"), true),
+            page("journals/2024_01_09", Some("journals/2024-01-09"), false),
+        ])
+    }
+
+    fn write(mode: HomeMode, home: &str) -> String {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Cyber Valley.md"), "---\ntitle: \"Cyber Valley\"\n---\n\nbody").unwrap();
+        home::write_index(dir.path(), &index(), home, mode, 2, false).unwrap();
+        fs::read_to_string(dir.path().join("index.md")).unwrap()
+    }
+
+    #[test]
+    fn test_home_modes() {
+        assert_eq!(write(HomeMode::Copy, "cyber valley"), "---\ntitle: \"Cyber Valley\"\n---\n\nbody");
+        assert_eq!(write(HomeMode::Embed, "cyber valley"), "---\ntitle: \"Cyber Valley\"\n---\n\n![[Cyber Valley]]\n");
+        assert_eq!(
+            write(HomeMode::Redirect, "cyber valley"),
+            "---\ntitle: \"Cyber Valley\"\nredirect: \"Cyber-Valley\"\n---\n\n<meta http-equiv=\"refresh\" content=\"0; url=Cyber-Valley\">\n\nGo to [[Cyber Valley|Cyber Valley]].\n"
+        );
+    }
+
+    #[test]
+    fn test_journal_mode_embeds_latest_published_journal() {
+        assert_eq!(
+            write(HomeMode::Journal, "cyber valley"),
+            "---\ntitle: \"May 1, 2024\"\n---\n\n![[journals/2024-05-01]]\n"
+        );
+    }
+
+    #[test]
+    fn test_journals_mode_streams_recent_journals() {
+        assert_eq!(
+            write(HomeMode::Journals, "index"),
+            "---\ntitle: \"📅 Journals\"\n---\n\n\
+             ## [[journals/2024-05-01|May 1, 2024]]\n\n![[journals/2024-05-01]]\n\n---\n\n\
+             ## [[journals/2024-01-09|January 9, 2024]]\n\n![[journals/2024-01-09]]\n\n---\n\n\
+             [[journals/index|All journals]]\n"
+        );
+    }
+
+    #[test]
+    fn test_missing_home_page_gets_welcome_index() {
+        assert_eq!(write(HomeMode::Embed, "nowhere"), "---\ntitle: \"nowhere\"\n---\n\n# Welcome\n\nSee [[nowhere]]\n");
+    }
+}
+
+#[cfg(test)]
+mod determinism_tests {
+    use crate::config::Config;
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::Path;
+
+    const GRAPH: &[(&str, &str)] = &[
+        ("pages/Projects.md", "tags:: hub\n\n- {{query (page-tags [[project]])}}\n- [[Missing One]] [[missing two]]"),
+        ("pages/Alpha.md", "tags:: project\nstatus:: active\nowner:: ann\n\n- alpha [[Missing One]] #idea"),
+        ("pages/beta.md", "tags:: project\nowner:: bob\n\n- beta [[Missing Three]]"),
+        ("pages/Gamma.md", "tags:: project\nstatus:: done\n\n- gamma #idea"),
+        ("pages/a___b.md", "- from a___b"),
+        ("pages/a%2Fb.md", "- from a%2Fb"),
+        ("pages/Project X.md", "- upper"),
+        ("pages/project x.md", "- lower"),
+        ("journals/2025_01_01.md", "- underscore day #meeting\n- TODO call SCHEDULED: <2025-01-02 Thu>"),
+        ("journals/2025-01-01.md", "- dash day #meeting"),
+        ("journals/2025_01_03.md", "- later day [[Missing Two]]"),
+    ];
+
+    /// Write the graph, files in the given order (tmpfs and others list
+    /// directory entries in creation order or by hash, not by name)
+    fn write_graph(root: &Path, reverse: bool) {
+        fs::create_dir_all(root.join("pages")).unwrap();
+        fs::create_dir_all(root.join("journals")).unwrap();
+        let mut files: Vec<_> = GRAPH.to_vec();
+        if reverse {
+            files.reverse();
+        }
+        for (path, content) in files {
+            fs::write(root.join(path), content).unwrap();
+        }
+    }
+
+    fn build(reverse: bool) -> BTreeMap<String, String> {
+        let temp = tempfile::tempdir().unwrap();
+        let input = temp.path().join("graph");
+        write_graph(&input, reverse);
+        let config = Config {
+            input_dir: input,
+            output_dir: temp.path().join("out"),
+            create_stubs: true,
+            previews: true,
+            events: true,
+            suggest_tags: true,
+            journal_tags: Some(Vec::new()),
+            ..Default::default()
+        };
+        crate::run_preprocessor(&config).unwrap();
+
+        walkdir::WalkDir::new(&config.output_dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| {
+                let name = e.path().strip_prefix(&config.output_dir).unwrap().display().to_string();
+                (name, fs::read_to_string(e.path()).unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_output_does_not_depend_on_file_order() {
+        let first = build(false);
+        let second = build(true);
+        assert_eq!(first.keys().collect::<Vec<_>>(), second.keys().collect::<Vec<_>>());
+        for (name, content) in &first {
+            assert_eq!(content, &second[name], "{} differs between builds", name);
+        }
+        assert!(first["journals/index.md"].matches("journals/2025-01-01|").count() == 1, "one entry per day");
+    }
+}
+
+#[cfg(test)]
+mod staging_tests {
+    use crate::staging;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_publish_replaces_output() {
+        let temp = tempdir().unwrap();
+        let output = temp.path().join("site");
+        fs::create_dir_all(output.join("old")).unwrap();
+        fs::write(output.join("old/deleted-page.md"), "stale").unwrap();
+        fs::write(output.join("index.md"), "old home").unwrap();
+
+        let staging = staging::prepare(&output).unwrap();
+        assert_eq!(staging, temp.path().join("site.staging"));
+        fs::write(staging.join("index.md"), "new home").unwrap();
+        // The published output is untouched until the build is done
+        assert_eq!(fs::read_to_string(output.join("index.md")).unwrap(), "old home");
+
+        staging::publish(&staging, &output).unwrap();
+        assert_eq!(fs::read_to_string(output.join("index.md")).unwrap(), "new home");
+        assert!(!output.join("old").exists());
+        let mut left: Vec<_> = fs::read_dir(temp.path()).unwrap().map(|e| e.unwrap().file_name()).collect();
+        left.sort();
+        assert_eq!(left, vec!["site"]);
+    }
+
+    #[test]
+    fn test_prepare_clears_failed_build_and_publishes_first_build() {
+        let temp = tempdir().unwrap();
+        let output = temp.path().join("site");
+        let stale = staging::staging_dir(&output);
+        fs::create_dir_all(&stale).unwrap();
+        fs::write(stale.join("half-written.md"), "x").unwrap();
+
+        let staging = staging::prepare(&output).unwrap();
+        assert_eq!(fs::read_dir(&staging).unwrap().count(), 0);
+        fs::write(staging.join("index.md"), "home").unwrap();
+        staging::publish(&staging, &output).unwrap();
+        assert_eq!(fs::read_to_string(output.join("index.md")).unwrap(), "home");
+        assert!(!staging.exists());
+    }
+
+    #[test]
+    fn test_sync_keeps_user_files_and_unchanged_mtimes() {
+        let temp = tempdir().unwrap();
+        let output = temp.path().join("content");
+        fs::create_dir_all(output.join("old")).unwrap();
+        fs::write(output.join("notes.md"), "hand-written").unwrap();
+        fs::write(output.join("old/removed.md"), "built last time").unwrap();
+        fs::write(output.join("index.md"), "home").unwrap();
+        fs::write(output.join(staging::MANIFEST_FILE), r#"["index.md", "old/removed.md", "../outside.md"]"#).unwrap();
+        fs::write(temp.path().join("outside.md"), "not ours").unwrap();
+        let old = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        fs::File::options().write(true).open(output.join("index.md")).unwrap().set_modified(old).unwrap();
+
+        let staging = staging::prepare(&output).unwrap();
+        fs::write(staging.join("index.md"), "home").unwrap();
+        fs::create_dir_all(staging.join("journals")).unwrap();
+        fs::write(staging.join("journals/2025-01-01.md"), "day").unwrap();
+
+        let stats = staging::sync(&staging, &output).unwrap();
+        assert_eq!(stats, staging::SyncStats { written: 1, unchanged: 1, removed: 1 });
+        assert_eq!(fs::metadata(output.join("index.md")).unwrap().modified().unwrap(), old);
+        assert_eq!(fs::read_to_string(output.join("notes.md")).unwrap(), "hand-written");
+        assert!(!output.join("old").exists());
+        assert!(temp.path().join("outside.md").exists());
+        assert!(!staging.exists());
+        let manifest: Vec<String> =
+            serde_json::from_str(&fs::read_to_string(output.join(staging::MANIFEST_FILE)).unwrap()).unwrap();
+        assert_eq!(manifest, vec!["index.md", "journals/2025-01-01.md"]);
+    }
+
+    #[test]
+    fn test_default_mode_keeps_files_it_did_not_write() {
+        // Swapping drops hand-written files in the output; it has to be asked for
+        assert_eq!(staging::OutputMode::default(), staging::OutputMode::Sync);
+        assert_eq!(crate::config::Config::default().output_mode, staging::OutputMode::Sync);
+    }
+}
+
+#[cfg(test)]
+mod bench_tests {
+    use crate::bench::{self, GraphSpec};
+    use std::fs;
+    use tempfile::tempdir;
+
+    const SPEC: GraphSpec = GraphSpec { pages: 12, links: 4, queries: 5, journals: 3 };
+
+    #[test]
+    fn test_generated_graph_is_reproducible() {
+        let (a, b) = (tempdir().unwrap(), tempdir().unwrap());
+        assert_eq!(bench::generate(a.path(), &SPEC).unwrap(), 16);
+        bench::generate(b.path(), &SPEC).unwrap();
+        for file in ["pages/Page 00003.md", "journals/2024_12_30.md", "logseq/config.edn"] {
+            assert_eq!(fs::read_to_string(a.path().join(file)).unwrap(), fs::read_to_string(b.path().join(file)).unwrap());
+        }
+        let page = fs::read_to_string(a.path().join("pages/Page 00001.md")).unwrap();
+        assert!(page.starts_with("tags:: topic-1\nstatus:: done\n"));
+        assert!(page.contains("{{query (and (page-property status \"done\") (page-tags [[topic-1]]))}}"));
+        assert_eq!(page.matches("[[Page ").count(), 4);
+        assert!(!fs::read_to_string(a.path().join("pages/Page 00005.md")).unwrap().contains("{{query"));
+    }
+
+    #[test]
+    fn test_run_reports_every_measurement() {
+        let temp = tempdir().unwrap();
+        let graph = temp.path().join("graph");
+        let report = bench::run(&SPEC, 2, Some(&graph)).unwrap();
+        assert_eq!(report.runs, 2);
+        for name in ["build", "build/pages", "build/journals", "transform", "queries"] {
+            let summary = &report.timings[name];
+            assert!(summary.min <= summary.median && summary.median <= summary.max, "{}", name);
+        }
+        // --graph keeps the generated graph
+        assert!(graph.join("pages/Page 00011.md").exists());
+        assert_eq!(bench::table(&report).len(), 1 + report.timings.len());
+    }
+}
+
+#[cfg(test)]
+mod golden_tests {
+    //! End-to-end build of the graph in `tests/golden/graph`, compared with the
+    //! output in `tests/golden/expected`. After an intended output change,
+    //! regenerate it with `UPDATE_GOLDEN=1 cargo test golden` and review the diff.
+    use crate::config::Config;
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+
+    const GOLDEN: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+    /// Relative path → content of every file under `dir`
+    fn files(dir: &Path) -> BTreeMap<String, Vec<u8>> {
+        walkdir::WalkDir::new(dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| {
+                let name = e.path().strip_prefix(dir).unwrap().to_string_lossy().replace('\\', "/");
+                (name, fs::read(e.path()).unwrap())
+            })
+            .collect()
+    }
+
+    /// Build the fixture from a copy outside any git repository, with fixed
+    /// modification times. Creation times can't be set, so today's date (a
+    /// fresh copy's creation date) is replaced by `<today>`.
+    fn build() -> BTreeMap<String, Vec<u8>> {
+        let temp = tempfile::tempdir().unwrap();
+        let graph = temp.path().join("graph");
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_736_942_400); // 2025-01-15 12:00 UTC
+        for (name, content) in files(&Path::new(GOLDEN).join("graph")) {
+            let path = graph.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, content).unwrap();
+            fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        }
+
+        let config = Config {
+            input_dir: graph.clone(),
+            output_dir: temp.path().join("out"),
+            create_stubs: true,
+            previews: true,
+            events: true,
+            journal_tags: Some(Vec::new()),
+            journal_queries: true,
+            // Loaded from the graph as main() does
+            hidden: crate::source::Hidden::load(&graph),
+            reference_filters: crate::references::graph_filters(&graph.join("logseq/config.edn")),
+            ..Default::default()
+        };
+        crate::run_preprocessor(&config).unwrap();
+
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        files(&config.output_dir)
+            .into_iter()
+            .filter(|(name, _)| name != crate::hooks::STATS_FILE)
+            .map(|(name, content)| match String::from_utf8(content) {
+                Ok(text) => (name, text.replace(&today, "<today>").into_bytes()),
+                Err(e) => (name, e.into_bytes()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_golden_output() {
+        let actual = build();
+        let expected_dir = Path::new(GOLDEN).join("expected");
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            let _ = fs::remove_dir_all(&expected_dir);
+            for (name, content) in &actual {
+                let path = expected_dir.join(name);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, content).unwrap();
+            }
+            return;
+        }
+
+        let expected = files(&expected_dir);
+        let hint = "run `UPDATE_GOLDEN=1 cargo test golden` if the change is intended";
+        assert_eq!(actual.keys().collect::<Vec<_>>(), expected.keys().collect::<Vec<_>>(), "output files differ; {}", hint);
+        for (name, content) in &expected {
+            assert_eq!(
+                String::from_utf8_lossy(&actual[name]),
+                String::from_utf8_lossy(content),
+                "{} differs from tests/golden/expected; {}",
+                name,
+                hint
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod fuzz_tests {
+    //! Random inputs built from Logseq syntax fragments, fed to the content
+    //! transform, the hiccup reader and the query engine: none of them may panic
+    //! (string slicing at a byte that isn't a char boundary, unbalanced
+    //! brackets, ...). `FUZZ_ITERATIONS=100000 cargo test fuzz --release` runs longer;
+    //! the libFuzzer targets in `fuzz/` search for such inputs instead of guessing.
+    use crate::content;
+    use crate::hiccup;
+    use crate::page::{Page, PageIndex};
+    use crate::query;
+    use crate::rng::Rng;
+    use std::panic::{self, AssertUnwindSafe};
+
+    const FRAGMENTS: &[&str] = &[
+        "- ", "  - ", "\n", "\t", " ", "[[", "]]", "[", "]", "((", "))", "(", ")", "{{", "}}", "{", "}", "#", "#[[",
+        "::", "tags:: ", "id:: ", "$", "$$", "\\", "`", "```", "~~~", "|", "> ", "[!note]", "#+BEGIN_QUOTE",
+        "#+END_QUOTE", "TODO ", "DONE ", "SCHEDULED: <2025-01-01 Wed>", "DEADLINE: <2025-13-45>", "query ",
+        "embed ", "video ", "(and ", "(or ", "(not ", "(page-tags ", "(property ", "(task TODO)", "(between ", "(sample 3)", "(shuffle)",
+        "[:div ", "[:a {:href \"", "javascript:", "\"", ":", ":class", "nil", "![](", "](", "http://x.y/", "é",
+        "🌱", "日本語", "\u{200b}", "ß", "<", ">", "&", "*", "**", "_", "~~", "^^", "==", "6650a1b2-0000-4000-8000-000000000001",
+        "Alpha", "beta", "Gamma/Delta", "___", "%2F", "..", "/",
+    ];
+
+    fn input(rng: &mut Rng) -> String {
+        let len = 1 + rng.below(40);
+        (0..len).map(|_| FRAGMENTS[rng.below(FRAGMENTS.len())]).collect()
+    }
+
+    fn index() -> PageIndex {
+        let page = |name: &str, content: &str| {
+            let (properties, _) = crate::page::parse_properties(content);
+            Page { name: name.to_string(), properties, content: content.to_string(), ..Default::default() }
+        };
+        PageIndex::new(vec![
+            page("Alpha", "tags:: beta\n\n- TODO task [[Gamma/Delta]]\n  id:: 6650a1b2-0000-4000-8000-000000000001"),
+            page("Gamma/Delta", "status:: é\n\n- 🌱 $5 ((6650a1b2-0000-4000-8000-000000000001))"),
+            page("日本語", "alias:: 🌱\n\n- DONE [[Alpha]]"),
+        ])
+    }
+
+    #[test]
+    fn test_no_panics_on_random_input() {
+        let iterations = std::env::var("FUZZ_ITERATIONS").ok().and_then(|n| n.parse().ok()).unwrap_or(2000);
+        let index = index();
+        let mut rng = Rng(0x5eed);
+        for _ in 0..iterations {
+            let text = input(&mut rng);
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                content::transform(&text, &index);
+                hiccup::parse(&text).map(|(nodes, _)| hiccup::render(&nodes));
+                let query = format!("{{{{query {}}}}}", text);
+                match query::execute(&query, &index) {
+                    Ok(results) => query::results_to_markdown(&results, &text),
+                    Err(error) => query::unsupported_to_markdown(&text, &error),
+                };
+                query::results_to_markdown(&[], &text);
+            }));
+            assert!(result.is_ok(), "panicked on input {:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_no_results_message_cuts_at_a_char_boundary() {
+        let query = format!("{}日本語", "x".repeat(79));
+        let markdown = query::results_to_markdown(&[], &query);
+        assert!(markdown.ends_with(&format!("`{}日...`", "x".repeat(79))), "got: {}", markdown);
+    }
+}
+
+#[cfg(test)]
+mod encoding_tests {
+    use crate::config::Config;
+    use crate::source;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_invalid_utf8_page_is_published_lossily() {
+        let temp = tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("pages")).unwrap();
+        fs::write(temp.path().join("pages/latin1.md"), b"- caf\xe9 [[good]]\n").unwrap();
+        fs::write(temp.path().join("pages/good.md"), "- fine").unwrap();
+        let config = Config { input_dir: temp.path().to_path_buf(), output_dir: temp.path().join("out"), ..Default::default() };
+
+        let stats = crate::run_preprocessor(&config).unwrap();
+        assert_eq!(stats.pages_published, 2);
+        let page = fs::read_to_string(temp.path().join("out/latin1.md")).unwrap();
+        assert!(page.contains("- caf\u{fffd} [[good]]"), "got: {}", page);
+    }
+
+    #[test]
+    fn test_utf16_page_is_decoded() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("notepad.md");
+        let mut bytes = vec![0xff, 0xfe];
+        bytes.extend("- héllo 🌱\r\n".encode_utf16().flat_map(u16::to_le_bytes));
+        fs::write(&path, bytes).unwrap();
+        assert_eq!(source::read_page(&path).unwrap(), "- héllo 🌱\n");
+
+        let mut bytes = vec![0xfe, 0xff];
+        bytes.extend("- big endian".encode_utf16().flat_map(u16::to_be_bytes));
+        fs::write(&path, bytes).unwrap();
+        assert_eq!(source::read_page(&path).unwrap(), "- big endian");
+    }
+
+    #[test]
+    fn test_huge_page_fails_instead_of_being_cut() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("huge.md");
+        let line = "- 0123456789012345678901234567890123456789012345678901234567890\n";
+        let lines = source::MAX_PAGE_BYTES as usize / line.len() + 10;
+        fs::write(&path, line.repeat(lines)).unwrap();
+        let error = source::read_page(&path).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("larger than 16 MB"), "{}", error);
+
+        // UTF-16 just under the limit is read whole, up to a dangling last byte
+        let mut bytes = vec![0xff, 0xfe];
+        bytes.extend("- 🌱\n".encode_utf16().flat_map(u16::to_le_bytes).cycle().take(source::MAX_PAGE_BYTES as usize - 3));
+        fs::write(&path, &bytes).unwrap();
+        let content = source::read_page(&path).unwrap();
+        assert!(content.starts_with("- 🌱\n- 🌱\n"));
+        assert!(content.ends_with('\u{fffd}'));
+        assert_eq!(content.matches('\u{fffd}').count(), 1);
+    }
+}
+
+#[cfg(test)]
+mod analyze_tests {
+    use crate::analyze;
+    use crate::page;
+    use crate::source::Hidden;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_orphans_dead_ends_and_unused_assets() {
+        let temp = tempdir().unwrap();
+        for dir in ["pages", "journals", "assets/img"] {
+            fs::create_dir_all(temp.path().join(dir)).unwrap();
+        }
+        let files = [
+            ("pages/Home.md", "- start at [[Hub]]"),
+            ("pages/Hub.md", "- [[Leaf]] and [[Other]] #topic ![x](../assets/img/used%20one.png)"),
+            ("pages/Leaf.md", "alias:: Other\n\n- nothing links out"),
+            ("pages/topic.md", "- back to [[hub]]"),
+            ("pages/Lonely.md", "- links to [[Hub]] but nobody links here"),
+            ("pages/Secret.md", "private:: true\n\n- [[Lonely]] ![](../assets/secret.png)"),
+            ("journals/2025_01_01.md", "- [[Leaf]]"),
+            ("assets/img/used one.png", "png"),
+            ("assets/secret.png", "png"),
+            ("assets/unused.png", "png"),
+        ];
+        for (path, content) in files {
+            fs::write(temp.path().join(path), content).unwrap();
+        }
+        let index = page::build_graph_index(temp.path()).unwrap();
+
+        let analysis = analyze::analyze(&index, temp.path(), "home", &Hidden::default(), false);
+        assert_eq!(analysis.pages, 6);
+        assert_eq!(analysis.orphans, vec!["Lonely"]);
+        assert_eq!(analysis.dead_ends, vec!["Leaf"]);
+        assert_eq!(analysis.most_linked[0], analyze::LinkCount { page: "Hub".to_string(), inbound: 3 });
+        assert_eq!(analysis.most_linked[1], analyze::LinkCount { page: "Leaf".to_string(), inbound: 2 });
+        // An asset used only by a private page is still used
+        assert_eq!(analysis.unused_assets, vec!["unused.png"]);
+        // Home, Hub, Leaf (alias Other counts once), topic, Lonely, journal
+        assert_eq!(analysis.links, 1 + 2 + 1 + 1 + 1);
+    }
+}
+
+#[cfg(test)]
+mod metrics_tests {
+    use crate::config::Config;
+    use crate::metrics;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_word_count_skips_code_markup_and_html() {
+        let body = "- Read [[Rust Book|the book]] and **this** [post](https://example.com/a-b)
+                    - <details><summary>More</summary>
+                    - ```rust
+  let not = words;
+  ```
+                    | a | b |
+|---|---|
+";
+        // Read the book and this post, More, a b
+        assert_eq!(metrics::word_count(body), 9);
+        assert_eq!(metrics::word_count(""), 0);
+    }
+
+    #[test]
+    fn test_reading_time_rounds_up() {
+        assert_eq!(metrics::reading_time(0), 0);
+        assert_eq!(metrics::reading_time(1), 1);
+        assert_eq!(metrics::reading_time(metrics::WORDS_PER_MINUTE), 1);
+        assert_eq!(metrics::reading_time(metrics::WORDS_PER_MINUTE + 1), 2);
+    }
+
+    #[test]
+    fn test_frontmatter_fields_and_run_totals() {
+        let temp = tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("pages")).unwrap();
+        fs::create_dir_all(temp.path().join("journals")).unwrap();
+        let long = vec!["word"; 450].join(" ");
+        fs::write(temp.path().join("pages/Long.md"), format!("- {}", long)).unwrap();
+        fs::write(temp.path().join("pages/Short.md"), "- three short words").unwrap();
+        fs::write(temp.path().join("journals/2025_01_01.md"), "- one").unwrap();
+        let config = Config {
+            input_dir: temp.path().to_path_buf(),
+            output_dir: temp.path().join("out"),
+            word_count: true,
+            ..Default::default()
+        };
+
+        let stats = crate::run_preprocessor(&config).unwrap();
+        let long = fs::read_to_string(temp.path().join("out/Long.md")).unwrap();
+        assert!(long.contains("wordcount: 450\nreadingTime: 3\n---"));
+        let journal = fs::read_to_string(temp.path().join("out/journals/2025-01-01.md")).unwrap();
+        assert!(journal.contains("wordcount: 1\nreadingTime: 1\n"));
+
+        let metrics = stats.metrics.unwrap();
+        assert_eq!(metrics, metrics::Totals { pages: 3, words: 454, reading_minutes: 5, longest: 450 });
+        assert_eq!(metrics.average(), 151);
+    }
+
+    #[test]
+    fn test_off_by_default() {
+        let temp = tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("pages")).unwrap();
+        fs::write(temp.path().join("pages/Note.md"), "- some words").unwrap();
+        let config = Config { input_dir: temp.path().to_path_buf(), output_dir: temp.path().join("out"), ..Default::default() };
+
+        let stats = crate::run_preprocessor(&config).unwrap();
+        assert!(stats.metrics.is_none());
+        assert!(!fs::read_to_string(temp.path().join("out/Note.md")).unwrap().contains("wordcount"));
+    }
+}
+
+#[cfg(test)]
+mod changes_tests {
+    use crate::changes;
+    use crate::page::{Page, PageIndex};
+    use chrono::NaiveDate;
+    use std::fs;
+    use tempfile::tempdir;
+    use super::TestPage;
+
+    fn page(name: &str, modified: &str, created: &str, properties: &[(&str, &str)]) -> Page {
+        TestPage::new(name).modified(Some(modified)).created(Some(created)).properties(properties).build()
+    }
+
+    fn index() -> PageIndex {
+        PageIndex::new(vec![
+            page("Zebra", "2025-03-10", "2024-01-01", &[]),
+            page("apple", "2025-03-10", "2025-03-10", &[("title", "Apple Notes")]),
+            page("Older", "2025-03-01", "2024-01-01", &[]),
+            page("Ancient", "2025-01-01", "2024-01-01", &[]),
+            page("Secret", "2025-03-10", "2024-01-01", &[("private", "true")]),
+            page("Draft", "2025-03-10", "2024-01-01", &[("draft", "true")]),
+            page("journals/2025_03_10", "2025-03-10", "2025-03-10", &[]),
+        ])
+    }
+
+    #[test]
+    fn test_recent_pages_grouped_by_day() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let content = changes::build(&index(), 30, today, false);
+        let day = content.find("## March 10, 2025").unwrap();
+        let older = content.find("## March 1, 2025").unwrap();
+        assert!(day < older);
+        assert!(content.contains("- [[apple|Apple Notes]] (new)\n- [[Zebra|Zebra]]\n"));
+        assert!(content.contains("- [[Older|Older]]\n"));
+        for left_out in ["Ancient", "Secret", "Draft", "journals/"] {
+            assert!(!content.contains(left_out), "{} listed", left_out);
+        }
+
+        let content = changes::build(&index(), 30, today, true);
+        assert!(content.contains("[[Secret|Secret]]"));
+    }
+
+    #[test]
+    fn test_days_window_includes_today() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let content = changes::build(&index(), 1, today, false);
+        assert!(content.contains("[[Zebra|Zebra]]"));
+        assert!(!content.contains("Older"));
+
+        let later = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        assert!(changes::build(&index(), 7, later, false).contains("No pages changed in the last 7 days."));
+    }
+
+    #[test]
+    fn test_page_named_changes_is_kept() {
+        let temp = tempdir().unwrap();
+        let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        assert_eq!(changes::write(temp.path(), &index(), 30, today, false).unwrap(), Some(3));
+
+        fs::write(temp.path().join(changes::CHANGES_FILE), "mine").unwrap();
+        assert_eq!(changes::write(temp.path(), &index(), 30, today, false).unwrap(), None);
+        assert_eq!(fs::read_to_string(temp.path().join(changes::CHANGES_FILE)).unwrap(), "mine");
+    }
+}
+
+#[cfg(test)]
+mod random_tests {
+    use crate::page::PageIndex;
+    use crate::random;
+    use std::fs;
+    use tempfile::tempdir;
+    use super::page;
+
+    fn index() -> PageIndex {
+        PageIndex::new(vec![
+            page("Rust & C", "- notes"),
+            page("Projects/Garden", "- beds"),
+            page("Moved", "slug:: new-home\n\n- here"),
+            page("draft", "draft:: true\n\n- wip"),
+            page("Secret", "private:: true\n\n- no"),
+            page("journals/2025_01_01", "- a day"),
+        ])
+    }
+
+    #[test]
+    fn test_listed_pages_only() {
+        let mut index = index();
+        for page in index.pages_mut() {
+            page.slug = page.properties.get("slug").cloned();
+        }
+        assert_eq!(random::paths(&index, false), vec!["Projects/Garden", "Rust--and--C", "new-home"]);
+        assert!(random::paths(&index, true).contains(&"Secret".to_string()));
+    }
+
+    #[test]
+    fn test_write_json_list() {
+        let temp = tempdir().unwrap();
+        assert_eq!(random::write(temp.path(), &index(), false).unwrap(), 3);
+        let json = fs::read_to_string(temp.path().join(random::RANDOM_FILE)).unwrap();
+        assert_eq!(json, r#"["Moved","Projects/Garden","Rust--and--C"]"#);
+    }
+}
+
+#[cfg(test)]
+mod series_tests {
+    use crate::config::Config;
+    use crate::page::{Page, PageIndex};
+    use crate::series;
+    use std::fs;
+    use tempfile::tempdir;
+    use super::TestPage;
+
+    fn page(name: &str, content: &str, created: &str) -> Page {
+        TestPage::new(name).content(content).created(Some(created)).build()
+    }
+
+    fn index() -> PageIndex {
+        PageIndex::new(vec![
+            page("Ownership", "series:: [[Rust Tutorial]]\nseries-order:: 2\n\n- move", "2024-05-01"),
+            page("Setup", "series:: [[Rust Tutorial]]\nseries-order:: 1\n\n- install", "2024-06-01"),
+            page("Lifetimes", "series:: rust tutorial\n\n- 'a", "2024-01-01"),
+            page("Traits", "series:: [[Rust Tutorial]]\n\n- impl", "2024-02-01"),
+            page("Unsafe", "series:: [[Rust Tutorial]]\nprivate:: true\n\n- raw", "2024-03-01"),
+            page("Other", "- no series", "2024-01-01"),
+        ])
+    }
+
+    #[test]
+    fn test_members_in_reading_order() {
+        let index = index();
+        let names: Vec<&str> = series::members(&index, "Rust Tutorial", false).iter().map(|p| p.name.as_str()).collect();
+        // series-order first, then created date; private pages left out
+        assert_eq!(names, vec!["Setup", "Ownership", "Lifetimes", "Traits"]);
+        assert_eq!(series::members(&index, "rust tutorial", true).len(), 5);
+    }
+
+    #[test]
+    fn test_navigation_links() {
+        let index = index();
+        let nav = |name: &str| series::navigation(crate::resolver::find_page(name, &index).unwrap(), &index, false);
+        let first = nav("Setup").unwrap();
+        assert!(first.contains("Part 1 of 4 in [[series/Rust Tutorial|Rust Tutorial]]"));
+        assert!(first.contains("\n[[Ownership|Ownership]] →\n"));
+        assert!(!first.contains("←"));
+        // Named as on the first page, whatever the case here
+        let middle = nav("Lifetimes").unwrap();
+        assert!(middle.contains("Part 3 of 4 in [[series/Rust Tutorial|Rust Tutorial]]"));
+        assert!(middle.contains("← [[Ownership|Ownership]] · [[Traits|Traits]] →"));
+        assert!(nav("Other").is_none());
+        assert!(nav("Unsafe").is_none());
+    }
+
+    #[test]
+    fn test_series_pages_published_with_index() {
+        let temp = tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("pages")).unwrap();
+        fs::write(temp.path().join("pages/Part One.md"), "series:: [[Guide]]\nseries-order:: 1\n\n- start").unwrap();
+        fs::write(temp.path().join("pages/Part Two.md"), "series:: [[Guide]]\nseries-order:: 2\n\n- go on").unwrap();
+        let config = Config { input_dir: temp.path().to_path_buf(), output_dir: temp.path().join("out"), ..Default::default() };
+        crate::run_preprocessor(&config).unwrap();
+
+        let one = fs::read_to_string(temp.path().join("out/Part One.md")).unwrap();
+        assert!(one.ends_with("- start\n\n---\n\nPart 1 of 2 in [[series/Guide|Guide]]\n\n[[Part Two|Part Two]] →\n"), "{}", one);
+        let index = fs::read_to_string(temp.path().join("out/series/Guide.md")).unwrap();
+        assert_eq!(index, "---\ntitle: \"Guide\"\n---\n\n1. [[Part One|Part One]]\n2. [[Part Two|Part Two]]\n");
+    }
+}
+
+#[cfg(test)]
+mod i18n_tests {
+    use crate::config::Config;
+    use crate::i18n;
+    use crate::page::PageIndex;
+    use crate::resolver;
+    use std::fs;
+    use tempfile::tempdir;
+    use super::page;
+
+    fn index() -> PageIndex {
+        PageIndex::new(vec![
+            page("Garden", "- beds"),
+            page("Garden.zh", "- 花园"),
+            page("Jardin", "translation-of:: [[Garden]]\nlang:: fr\n\n- potager"),
+            page("Node.js", "- runtime"),
+            page("Notes.de", "- no original"),
+        ])
+    }
+
+    #[test]
+    fn test_non_language_suffix_is_not_a_translation() {
+        let index = PageIndex::new(vec![
+            page("Node", "- a vertex"),
+            page("Node.js", "- runtime"),
+            page("Chart", "- a map"),
+            page("Chart.js", "- charts"),
+        ]);
+        for name in ["Node.js", "Chart.js"] {
+            let page = resolver::find_page(name, &index).unwrap();
+            assert_eq!(i18n::translation_of(page, &index), None, "{}", name);
+            assert_eq!(i18n::language(page, &index, "en"), "en");
+            assert_eq!(i18n::variant_title(page, &index), None);
+        }
+        assert!(i18n::variants(resolver::find_page("Node", &index).unwrap(), &index, false).is_empty());
+    }
+
+    #[test]
+    fn test_variants_by_suffix_and_property() {
+        let index = index();
+        let garden = resolver::find_page("Garden", &index).unwrap();
+        let names: Vec<&str> = i18n::variants(garden, &index, false).iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["Garden", "Garden.zh", "Jardin"]);
+        let zh = resolver::find_page("Garden.zh", &index).unwrap();
+        assert_eq!(i18n::variants(zh, &index, false).len(), 3);
+
+        assert_eq!(i18n::language(garden, &index, "en"), "en");
+        assert_eq!(i18n::language(zh, &index, "en"), "zh");
+        assert_eq!(i18n::language(resolver::find_page("Jardin", &index).unwrap(), &index, "en"), "fr");
+        assert_eq!(i18n::variant_title(zh, &index).as_deref(), Some("Garden"));
+    }
+
+    #[test]
+    fn test_dotted_names_without_original_are_pages() {
+        let index = index();
+        for name in ["Node.js", "Notes.de"] {
+            let page = resolver::find_page(name, &index).unwrap();
+            assert!(i18n::variants(page, &index, false).is_empty(), "{}", name);
+            assert_eq!(i18n::language(page, &index, "en"), "en");
+        }
+        assert_eq!(i18n::default_language("pt-BR"), "pt");
+    }
+
+    #[test]
+    fn test_translations_published_with_switcher() {
+        let temp = tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("pages")).unwrap();
+        fs::create_dir_all(temp.path().join("logseq")).unwrap();
+        fs::write(temp.path().join("logseq/config.edn"), "{:preferred-language \"en-GB\"}").unwrap();
+        fs::write(temp.path().join("pages/Garden.md"), "- beds").unwrap();
+        fs::write(temp.path().join("pages/Garden.zh.md"), "- 花园").unwrap();
+        fs::write(temp.path().join("pages/Solo.md"), "- alone").unwrap();
+        let config = Config {
+            input_dir: temp.path().to_path_buf(),
+            output_dir: temp.path().join("out"),
+            default_language: i18n::default_language(&crate::favorites::locale(&temp.path().join("logseq/config.edn"))),
+            ..Default::default()
+        };
+        crate::run_preprocessor(&config).unwrap();
+
+        let zh = fs::read_to_string(temp.path().join("out/Garden.zh.md")).unwrap();
+        assert!(zh.contains("title: \"Garden\"\n"), "{}", zh);
+        assert!(zh.contains("lang: zh\n"));
+        assert!(zh.contains("*Also in: [[Garden|en]]*\n\n- 花园"));
+        let en = fs::read_to_string(temp.path().join("out/Garden.md")).unwrap();
+        assert!(en.contains("lang: en\n"));
+        assert!(en.contains("*Also in: [[Garden.zh|zh]]*"));
+        assert!(!fs::read_to_string(temp.path().join("out/Solo.md")).unwrap().contains("lang:"));
+
+        let languages: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(temp.path().join("out").join(i18n::LANGUAGES_FILE)).unwrap()).unwrap();
+        assert_eq!(languages["Garden.zh"], serde_json::json!({"en": "Garden", "zh": "Garden.zh"}));
+        assert_eq!(languages.as_object().unwrap().len(), 2);
+    }
+}
+
+#[cfg(test)]
+mod timezone_tests {
+    use crate::page::{Page, PageIndex};
+    use crate::query;
+    use crate::timezone::{self, Timezone};
+    use chrono::{DateTime, NaiveDate, Utc};
+
+    fn day(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_parse_zones() {
+        assert_eq!("local".parse::<Timezone>(), Ok(Timezone::Local));
+        assert!(matches!("UTC".parse::<Timezone>(), Ok(Timezone::Fixed(o)) if o.local_minus_utc() == 0));
+        assert!(matches!("+09:00".parse::<Timezone>(), Ok(Timezone::Fixed(o)) if o.local_minus_utc() == 9 * 3600));
+        assert!(matches!("-0530".parse::<Timezone>(), Ok(Timezone::Fixed(o)) if o.local_minus_utc() == -(5 * 3600 + 30 * 60)));
+        assert!(matches!("Asia/Tokyo".parse::<Timezone>(), Ok(Timezone::Named(_))));
+        for bad in ["+9:0", "+09:75", "Mars/Olympus"] {
+            assert!(bad.parse::<Timezone>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_instants_become_days_in_the_zone() {
+        let late = DateTime::parse_from_rfc3339("2024-01-15T23:30:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!("+09:00".parse::<Timezone>().unwrap().date(late), day("2024-01-16"));
+        assert_eq!("UTC".parse::<Timezone>().unwrap().date(late), day("2024-01-15"));
+        // Daylight saving time: Berlin is +2 in July
+        let summer = DateTime::parse_from_rfc3339("2024-07-01T22:30:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!("Europe/Berlin".parse::<Timezone>().unwrap().date(summer), day("2024-07-02"));
+    }
+
+    #[test]
+    fn test_timestamp_values() {
+        let tokyo: Timezone = "Asia/Tokyo".parse().unwrap();
+        assert_eq!(tokyo.parse_date("2024-01-15T23:30:00Z"), Some(day("2024-01-16")));
+        assert_eq!(tokyo.parse_date("2024-01-15T23:30:00-05:00"), Some(day("2024-01-16")));
+        // Local times and dates are days as written
+        assert_eq!(tokyo.parse_date("2024-01-15T23:30"), Some(day("2024-01-15")));
+        assert_eq!(tokyo.parse_date("Jan 15th, 2024"), Some(day("2024-01-15")));
+        assert_eq!(tokyo.parse_date("soon"), None);
+    }
+
+    #[test]
+    fn test_between_relative_dates() {
+        let today = timezone::current().today();
+        let journal = |days_ago: u64| {
+            let date = today - chrono::Days::new(days_ago);
+            let name = format!("journals/{}", date.format("%Y_%m_%d"));
+            Page { name_lower: name.clone(), name, ..Default::default() }
+        };
+        let index = PageIndex::new(vec![journal(0), journal(3), journal(10), journal(40)]);
+        let count = |q: &str| query::execute(q, &index).unwrap().len();
+        assert_eq!(count("(between -7d today)"), 2);
+        assert_eq!(count("(between -2w [[today]])"), 3);
+        assert_eq!(count("(between -2m yesterday)"), 3);
+        assert_eq!(count("(between -1y +1d)"), 4);
+    }
+}
+
+#[cfg(test)]
+mod on_this_day_tests {
+    use crate::config::Config;
+    use crate::journals;
+    use crate::on_this_day::OnThisDay;
+    use crate::page;
+    use chrono::NaiveDate;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_on_this_day_links_earlier_years() {
+        let temp = tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("journals")).unwrap();
+        for (file, content) in [
+            ("2025_01_15", "- today"),
+            ("2024_01_15", "- last year"),
+            ("2022_01_15", "- long ago"),
+            ("2023_01_15", "private:: true\n\n- hidden"),
+            ("2024_01_16", "- next day"),
+        ] {
+            fs::write(temp.path().join(format!("journals/{}.md", file)), content).unwrap();
+        }
+        let index = page::build_graph_index(temp.path()).unwrap();
+
+        let days = OnThisDay::new(&index, false);
+        assert_eq!(days.earlier(date("2025-01-15")), vec![date("2024-01-15"), date("2022-01-15")]);
+        assert!(days.section(date("2022-01-15")).is_none());
+        assert!(days.section(date("2024-01-16")).is_none());
+
+        let out = temp.path().join("out");
+        fs::create_dir_all(&out).unwrap();
+        let config = Config { input_dir: temp.path().to_path_buf(), on_this_day: true, ..Default::default() };
+        journals::process_journals(&temp.path().join("journals"), &out, &index, &config).unwrap();
+        let journal = fs::read_to_string(out.join("2025-01-15.md")).unwrap();
+        let section = journal.find("## On this day").expect("section");
+        assert!(section > journal.find("today").unwrap(), "{}", journal);
+        assert!(journal.contains("- [[journals/2024-01-15|January 15, 2024]] (1 year ago)\n- [[journals/2022-01-15|January 15, 2022]] (3 years ago)"), "{}", journal);
+        assert!(!journal.contains("2023-01-15"), "{}", journal);
+        assert!(!fs::read_to_string(out.join("2022-01-15.md")).unwrap().contains("On this day"));
+    }
+}
+
+#[cfg(test)]
+mod rollups_tests {
+    use crate::rollups::{self, Rollup};
+    use chrono::NaiveDate;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_period_keys_and_titles() {
+        assert_eq!(Rollup::Week.key(date("2025-01-15")), "2025-W03");
+        assert_eq!(Rollup::Week.title(date("2025-01-15")), "Week 3, 2025");
+        // ISO weeks can belong to the neighbouring year
+        assert_eq!(Rollup::Week.key(date("2024-12-30")), "2025-W01");
+        assert_eq!(Rollup::Month.key(date("2025-01-15")), "2025-01");
+        assert_eq!(Rollup::Month.title(date("2025-01-15")), "January 2025");
+    }
+
+    #[test]
+    fn test_rollup_pages_embed_days_with_navigation() {
+        let temp = tempdir().unwrap();
+        let days: Vec<String> = ["2025-02-03", "2025-01-14", "2025-01-13", "2025-01-28"].iter().map(|d| d.to_string()).collect();
+        let written = rollups::write(temp.path(), &days, &[Rollup::Week, Rollup::Month]).unwrap();
+        assert_eq!(written, 5);
+
+        let week = fs::read_to_string(temp.path().join("2025-W03.md")).unwrap();
+        assert!(week.starts_with("---\ntitle: \"Week 3, 2025\"\n---"), "{}", week);
+        let monday = week.find("## [[journals/2025-01-13|Monday, January 13]]").expect("first day");
+        assert!(monday < week.find("![[journals/2025-01-14]]").unwrap(), "{}", week);
+        assert!(week.contains("[[journals/2025-W05|Week 5, 2025]] →"), "{}", week);
+        assert!(!week.contains("←"), "{}", week);
+
+        let month = fs::read_to_string(temp.path().join("2025-02.md")).unwrap();
+        assert!(month.contains("← [[journals/2025-01|January 2025]]"), "{}", month);
+        assert!(month.contains("![[journals/2025-02-03]]"), "{}", month);
+    }
+
+    #[test]
+    fn test_no_rollups_by_default() {
+        let temp = tempdir().unwrap();
+        assert_eq!(rollups::write(temp.path(), &["2025-01-13".to_string()], &[]).unwrap(), 0);
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 0);
+    }
+}
+
+#[cfg(test)]
+mod stub_tests {
+    use crate::config::Config;
+    use crate::page::{self, PageIndex};
+    use crate::resolver::{self, Stub};
+    use std::fs;
+    use tempfile::tempdir;
+    use super::page;
+
+    fn index() -> PageIndex {
+        PageIndex::new(vec![
+            page("Home", "- [[Projects/New Idea]] and [[Existing]] and [[existing thing]]\n- [[https://x.io]] [[2024-01-15]] [[journals/2024_01_16]]"),
+            page("Existing", "- links [[projects/new idea]] and [[pages/Someday]]"),
+            page("Secret", "private:: true\n\n- [[Hidden Plan]]"),
+        ])
+    }
+
+    #[test]
+    fn test_plan_stubs() {
+        let stubs = resolver::plan_stubs(&index(), false);
+        assert_eq!(
+            stubs,
+            vec![
+                // First spelling in page-name order wins ("Existing" sorts before "Home")
+                Stub {
+                    name: "Someday".to_string(),
+                    title: "Someday".to_string(),
+                    linked_from: vec!["Existing".to_string()],
+                },
+                Stub {
+                    name: "projects/new idea".to_string(),
+                    title: "projects/new idea".to_string(),
+                    linked_from: vec!["Existing".to_string(), "Home".to_string()],
+                },
+            ]
+        );
+
+        let with_private = resolver::plan_stubs(&index(), true);
+        assert!(with_private.iter().any(|s| s.name == "Hidden Plan"));
+    }
+
+    #[test]
+    fn test_create_stubs_at_link_targets() {
+        let temp = tempdir().unwrap();
+        let mut linking = page("Home", "- [[Projects/New Idea]] and [[Kept]]");
+        linking.slug = Some("start".to_string());
+        let index = PageIndex::new(vec![linking, page("journals/2025_01_15", "- [[projects/new idea]]")]);
+        fs::write(temp.path().join("Kept.md"), "already here").unwrap();
+
+        assert_eq!(page::create_stubs(temp.path(), &index, &Config::default()).unwrap(), 1);
+        let stub = fs::read_to_string(temp.path().join("Projects/New Idea.md")).unwrap();
+        assert_eq!(
+            stub,
+            "---\ntitle: \"Projects/New Idea\"\nstub: true\nnoindex: true\ntags:\n  - stub\n---\n\n> [!note] Stub Page\n> This page was auto-generated.\n\n**Linked from:**\n- [[start|Home]]\n- [[journals/2025_01_15]]\n"
+        );
+        assert_eq!(fs::read_to_string(temp.path().join("Kept.md")).unwrap(), "already here");
+    }
+
+    #[test]
+    fn test_stub_template() {
+        let temp = tempdir().unwrap();
+        let index = PageIndex::new(vec![page("Home", "- [[Idea]]"), page("Notes", "- [[idea]] again")]);
+        let config = Config {
+            stub_template: Some("# {{title}}\n\nNot written yet ({{name}}), {{linked_count}} links:\n{{linked_from}}\n".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(page::create_stubs(temp.path(), &index, &config).unwrap(), 1);
+        let stub = fs::read_to_string(temp.path().join("Idea.md")).unwrap();
+        assert!(stub.starts_with("---\ntitle: \"Idea\"\nstub: true\n"), "got: {}", stub);
+        assert!(
+            stub.ends_with("---\n\n# Idea\n\nNot written yet (Idea), 2 links:\n- [[Home]]\n- [[Notes]]\n"),
+            "got: {}",
+            stub
+        );
+    }
+}
+
+#[cfg(all(test, unix))]
+mod hooks_tests {
+    use crate::config::Config;
+    use crate::hooks::{self, Hooks};
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn config(temp: &std::path::Path, hooks: Hooks) -> Config {
+        Config { input_dir: temp.join("graph"), output_dir: temp.join("out"), hooks, ..Default::default() }
+    }
+
+    #[test]
+    fn test_hooks_get_environment() {
+        let temp = tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("out")).unwrap();
+        let config = config(
+            temp.path(),
+            Hooks {
+                pre_build: Some("echo \"$LOGSEQ_QUARTZ_HOOK $LOGSEQ_QUARTZ_INPUT\" > \"$LOGSEQ_QUARTZ_OUTPUT/pre.txt\"".to_string()),
+                post_build: Some("cp \"$LOGSEQ_QUARTZ_STATS\" \"$LOGSEQ_QUARTZ_OUTPUT/stats-copy.json\"".to_string()),
+                on_error: Some("echo \"$LOGSEQ_QUARTZ_ERROR\" > \"$LOGSEQ_QUARTZ_OUTPUT/error.txt\"".to_string()),
+            },
+        );
+
+        config.hooks.pre_build(&config).unwrap();
+        let pre = fs::read_to_string(temp.path().join("out/pre.txt")).unwrap();
+        assert_eq!(pre.trim(), format!("pre_build {}", temp.path().join("graph").display()));
+
+        let stats = crate::Stats { pages_published: 3, ..Default::default() };
+        hooks::write_stats(&config.output_dir, &stats).unwrap();
+        config.hooks.post_build(&config).unwrap();
+        let copied: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(temp.path().join("out/stats-copy.json")).unwrap()).unwrap();
+        assert_eq!(copied["pages_published"], 3);
+        assert_eq!(hooks::previous_stat::<usize>(&config.output_dir, "pages_published"), Some(3));
+        assert_eq!(hooks::previous_stat::<usize>(&config.output_dir, "no_such_stat"), None);
+
+        config.hooks.on_error(&config, &anyhow::anyhow!("2 files failed to process"));
+        let error = fs::read_to_string(temp.path().join("out/error.txt")).unwrap();
+        assert_eq!(error.trim(), "2 files failed to process");
+    }
+
+    #[test]
+    fn test_failing_hook_is_an_error() {
+        let temp = tempdir().unwrap();
+        let config = config(temp.path(), Hooks { pre_build: Some("exit 3".to_string()), ..Default::default() });
+        let err = config.hooks.pre_build(&config).unwrap_err();
+        assert!(err.to_string().contains("pre_build hook failed"), "got: {}", err);
+
+        // Unset hooks do nothing
+        assert!(Hooks::default().post_build(&config).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod inline_tag_tests {
+    use crate::config::Config;
+    use crate::content::{self, TagStyle};
+    use crate::outline;
+    use crate::page::{self, PageIndex};
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn inline_tags(text: &str, style: TagStyle) -> (String, Vec<String>) {
+        let mut outline = outline::parse(text);
+        let tags = content::inline_tags(&mut outline, style);
+        (outline.render(), tags)
+    }
+
+    #[test]
+    fn test_keep_leaves_content() {
+        let text = "- idea #rust\n";
+        assert_eq!(inline_tags(text, TagStyle::Keep), (text.to_string(), Vec::new()));
+    }
+
+    #[test]
+    fn test_link_style() {
+        let (text, tags) = inline_tags("- learning #rust and #[[type theory]].\n", TagStyle::Link);
+        assert_eq!(text, "- learning [[tags/rust|rust]] and [[tags/type-theory|type theory]].\n");
+        assert_eq!(tags, vec!["rust", "type theory"]);
+    }
+
+    #[test]
+    fn test_strip_style() {
+        let (text, tags) = inline_tags("- see #a here.\n- #b #c\n#d starts\n- end #e.", TagStyle::Strip);
+        assert_eq!(text, "- see here.\n-\nstarts\n- end.");
+        assert_eq!(tags, vec!["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn test_not_tags() {
+        let text = "# Heading\n- [#A] task [link](#anchor) http://x.com/#frag a#b\n#+BEGIN_QUOTE\n- ```\n  #comment\n  ```\n- ~~~\n  #also-code\n  ~~~\n- `#code`";
+        assert_eq!(inline_tags(text, TagStyle::Strip), (text.to_string(), Vec::new()));
+    }
+
+    #[test]
+    fn test_inline_tags_added_to_frontmatter() {
+        let temp = tempdir().unwrap();
+        let pages = temp.path().join("pages");
+        let output = temp.path().join("out");
+        fs::create_dir_all(&pages).unwrap();
+        fs::create_dir_all(&output).unwrap();
+        fs::write(pages.join("note.md"), "tags:: Rust\n\n- about #rust and #Wasm\n").unwrap();
+
+        let config = Config { create_stubs: false, inline_tags: TagStyle::Strip, ..Default::default() };
+        let dates = Default::default();
+        page::process_page(&pages.join("note.md"), &output, &PageIndex::default(), &config, &dates, temp.path()).unwrap();
+
+        let md = fs::read_to_string(output.join("note.md")).unwrap();
+        assert!(md.contains("tags:\n  - Rust\n  - \"Wasm\"\n"), "got: {}", md);
+        assert!(md.contains("\n- about and"), "got: {}", md);
+        assert!(!md.contains('#'), "got: {}", md);
+    }
+}
+
+#[cfg(test)]
+mod multi_word_tag_tests {
+    use crate::content;
+    use crate::page::{self, Page, PageIndex};
+    use crate::query;
+    use std::fs;
+    use tempfile::tempdir;
+    use super::TestPage;
+
+    fn page(name: &str, tags: &[&str]) -> Page {
+        TestPage::new(name).tags(tags).build()
+    }
+
+    #[test]
+    fn test_multi_word_tags_extracted() {
+        let temp = tempdir().unwrap();
+        let pages = temp.path().join("pages");
+        fs::create_dir_all(&pages).unwrap();
+        fs::write(pages.join("note.md"), "- reading #[[Type Theory]] and #rust\n").unwrap();
+
+        let index = page::build_index(&pages).unwrap();
+        let note = index.iter().next().unwrap();
+        assert_eq!(note.tags, vec!["type theory", "rust"]);
+    }
+
+    #[test]
+    fn test_page_tags_query_forms() {
+        let index = PageIndex::new(vec![
+            page("a", &["type theory"]),
+            page("b", &["rust"]),
+            page("c", &["python"]),
+        ]);
+        let names = |q: &str| query::execute(q, &index).unwrap().iter().map(|p| p.name.clone()).collect::<Vec<_>>();
+
+        assert_eq!(names("{{query (page-tags #[[Type Theory]])}}"), vec!["a"]);
+        assert_eq!(names("{{query (page-tags [[type theory]])}}"), vec!["a"]);
+        assert_eq!(names("{{query (page-tags #rust)}}"), vec!["b"]);
+        assert_eq!(names("{{query (page-tags rust)}}"), vec!["b"]);
+        assert_eq!(names("{{query (page-tags [[rust]] #[[type theory]])}}"), vec!["b", "a"]);
+    }
+
+    #[test]
+    fn test_multi_word_tag_becomes_tag_link() {
+        let index = PageIndex::new(vec![page("type theory", &[]), page("tags", &[])]);
+        let out = content::transform("- reading #[[type theory]] today", &index);
+        assert_eq!(out, "- reading [[tags/type-theory|type theory]] today");
+    }
+}
+
+#[cfg(test)]
+mod property_value_tests {
+    use crate::content;
+    use crate::frontmatter;
+    use crate::page::{Page, PageIndex};
+    use crate::properties::{self, Value};
+    use crate::query;
+    use std::collections::HashMap;
+    use super::TestPage;
+
+    fn page(name: &str, props: &[(&str, &str)]) -> Page {
+        TestPage::new(name).properties(props).build()
+    }
+
+    #[test]
+    fn test_parse_typed_values() {
+        assert_eq!(
+            properties::parse("[[project]], [[active]]"),
+            vec![Value::PageRef("project".into()), Value::PageRef("active".into())]
+        );
+        assert_eq!(
+            properties::parse("#a, #[[multi word]]"),
+            vec![Value::PageRef("a".into()), Value::PageRef("multi word".into())]
+        );
+        assert_eq!(properties::parse("42"), vec![Value::Number(42.0)]);
+        assert_eq!(properties::parse("TRUE"), vec![Value::Bool(true)]);
+        assert_eq!(
+            properties::parse("Jan 5th, 2024"),
+            vec![Value::Date(chrono::NaiveDate::from_ymd_opt(2024, 1, 5).unwrap())]
+        );
+        assert_eq!(properties::parse("see [[x]], then y"), vec![Value::Text("see [[x]], then y".into())]);
+        assert_eq!(properties::parse("  "), Vec::<Value>::new());
+    }
+
+    #[test]
+    fn test_list_texts_and_plain() {
+        assert_eq!(properties::list_texts("[[a, b]], #c, d"), vec!["a, b", "c", "d"]);
+        assert_eq!(properties::list_texts("007"), vec!["007"]);
+        assert_eq!(properties::plain("[[project]], [[active]]"), "project, active");
+        assert_eq!(properties::plain("A [[rust]] library"), "A rust library");
+        assert_eq!(properties::plain("5.50"), "5.50");
+    }
+
+    #[test]
+    fn test_property_query_matches_refs() {
+        let index = PageIndex::new(vec![
+            page("a", &[("type", "[[project]], [[active]]")]),
+            page("b", &[("type", "[[inactive project]]")]),
+            page("c", &[("rating", "5.0")]),
+        ]);
+        let names = |q: &str| query::execute(q, &index).unwrap().iter().map(|p| p.name.clone()).collect::<Vec<_>>();
+
+        assert_eq!(names("{{query (property type [[active]])}}"), vec!["a"]);
+        assert_eq!(names("{{query (property type active)}}"), vec!["a"]);
+        assert_eq!(names("{{query (property type [[inactive project]])}}"), vec!["b"]);
+        assert_eq!(names("{{query (property rating 5)}}"), vec!["c"]);
+        assert_eq!(names("{{query (property type)}}"), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_property_and_page_property_scope() {
+        let mut task = page("task", &[]);
+        task.content = "- Write report\n  status:: [[done]]\n- Other".to_string();
+        let mut project = page("project", &[("status", "done"), ("series-order", "2")]);
+        project.content = "status:: done\nseries-order:: 2\n\n- notes".to_string();
+        let index = PageIndex::new(vec![task, project]);
+        let names = |q: &str| query::execute(q, &index).unwrap().iter().map(|p| p.name.clone()).collect::<Vec<_>>();
+
+        // Block properties, the page's own property block included
+        assert_eq!(names("{{query (property status done)}}"), vec!["task", "project"]);
+        assert_eq!(names("{{query (property status)}}"), vec!["task", "project"]);
+        // Page properties only
+        assert_eq!(names("{{query (page-property status done)}}"), vec!["project"]);
+        assert_eq!(names("{{query (page-property :series-order 2)}}"), vec!["project"]);
+    }
+
+    #[test]
+    fn test_block_property_queries_list_blocks() {
+        let mut task = page("task", &[]);
+        task.content = "- Write report\n  status:: [[done]]\n- Other\n- status:: done".to_string();
+        let mut project = page("project", &[("status", "done")]);
+        project.content = "status:: done\n\n- notes".to_string();
+        let index = PageIndex::new(vec![task, project]);
+
+        let out = content::transform("- {{query (property status done)}}", &index);
+        assert!(out.contains("- Write report ([[task|task]])"), "{}", out);
+        assert!(out.contains("- **Status:** done ([[task|task]])"), "{}", out);
+        assert!(out.contains("- [[project|project]]"), "{}", out);
+        assert!(!out.contains("Other"), "{}", out);
+
+        // Pages only: the usual table
+        let out = content::transform("- {{query (page-property status done)}}", &index);
+        assert!(out.contains("| Page |"), "{}", out);
+    }
+
+    #[test]
+    fn test_sort_by_number_and_date() {
+        let a = page("a", &[("rank", "10"), ("due", "Feb 1st, 2024")]);
+        let b = page("b", &[("rank", "9"), ("due", "2024-01-15")]);
+        let results = vec![&a, &b];
+        let sorted = |key: &str| {
+            let options = query::QueryOptions { sort_by: Some(key.to_string()), ..Default::default() };
+            query::results_to_listing(&results, &options)
+        };
+        assert_eq!(sorted("rank"), vec!["b", "a"]);
+        assert_eq!(sorted("due"), vec!["b", "a"]);
+    }
+
+    #[test]
+    fn test_frontmatter_and_body_use_typed_values() {
+        let props: HashMap<String, String> = [
+            ("description".to_string(), "A [[rust]] library".to_string()),
+            ("tags".to_string(), "#rust, [[type theory]]".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let fm = frontmatter::generate("lib", &props, None, &Default::default());
+        assert!(fm.contains("description: \"A rust library\"\n"), "got: {}", fm);
+        assert!(fm.contains("tags:\n  - rust\n  - type theory\n"), "got: {}", fm);
+
+        let body = content::transform("- type:: #project, #active", &PageIndex::default());
+        assert_eq!(body, "- **Type:** [[project]], [[active]]");
+    }
+}
+
+#[cfg(test)]
+mod page_properties_tests {
+    use crate::config::Config;
+    use crate::page::{self, Page, PageIndex};
+    use crate::properties::{self, PageProperties};
+    use std::collections::HashMap;
+    use std::fs;
+    use tempfile::tempdir;
+
+    const RAW: &str = "status:: active\ntitle:: Roadmap\ndue-date:: 2025-03-01\nowner:: [[Ann]], [[Bob]]\ndate:: 2025-01-01\n\n- body\n";
+
+    fn props() -> HashMap<String, String> {
+        page::parse_properties(RAW).0
+    }
+
+    #[test]
+    fn test_hidden_by_default() {
+        assert_eq!(properties::metadata_block(&props(), RAW, PageProperties::Hidden, &PageIndex::default()), "");
+    }
+
+    #[test]
+    fn test_table_in_source_order() {
+        let index = PageIndex::new(vec![Page { name: "Ann".into(), name_lower: "ann".into(), ..Default::default() }]);
+        let table = properties::metadata_block(&props(), RAW, PageProperties::Table, &index);
+        assert_eq!(
+            table,
+            "| Property | Value |\n| --- | --- |\n| Status | active |\n| Due Date | 2025-03-01 |\n| Owner | [[Ann]], [[Bob]] |\n\n"
+        );
+    }
+
+    #[test]
+    fn test_list_style() {
+        let list = properties::metadata_block(&props(), RAW, PageProperties::List, &PageIndex::default());
+        assert_eq!(list, "- **Status:** active\n- **Due Date:** 2025-03-01\n- **Owner:** [[Ann]], [[Bob]]\n\n");
+    }
+
+    #[test]
+    fn test_table_cell_pipes_escaped() {
+        let props: HashMap<String, String> = [("note".to_string(), "a | b".to_string())].into_iter().collect();
+        let table = properties::metadata_block(&props, "note:: a | b", PageProperties::Table, &PageIndex::default());
+        assert!(table.contains("| Note | a \\| b |\n"), "got: {}", table);
+    }
+
+    #[test]
+    fn test_page_gets_metadata_table() {
+        let temp = tempdir().unwrap();
+        let pages = temp.path().join("pages");
+        let output = temp.path().join("out");
+        fs::create_dir_all(&pages).unwrap();
+        fs::create_dir_all(&output).unwrap();
+        fs::write(pages.join("roadmap.md"), RAW).unwrap();
+
+        let config = Config { create_stubs: false, page_properties: PageProperties::Table, ..Default::default() };
+        let dates = Default::default();
+        page::process_page(&pages.join("roadmap.md"), &output, &PageIndex::default(), &config, &dates, temp.path()).unwrap();
+
+        let md = fs::read_to_string(output.join("roadmap.md")).unwrap();
+        assert!(md.contains("---\n\n| Property | Value |\n"), "got: {}", md);
+        assert!(md.ends_with("|\n\n- body"), "got: {}", md);
+    }
+}
+
+mod outline_tests {
+    use crate::content;
+    use crate::outline;
+    use crate::page::{Page, PageIndex};
+    use crate::query;
+
+    const SOURCE: &str = "title:: Notes\n\n- TODO [#B] write\n  status:: open\n\t- child\n\n\t\t- grandchild\n- ```\n  - not a block\n  ```\n- done\n";
+
+    #[test]
+    fn test_parse_builds_tree_and_renders_back() {
+        let outline = outline::parse(SOURCE);
+        assert_eq!(outline.preamble, vec!["title:: Notes", ""]);
+        assert_eq!(outline.blocks.len(), 3);
+        let first = &outline.blocks[0];
+        assert_eq!(first.text, "TODO [#B] write");
+        assert_eq!((first.marker.as_deref(), first.priority), (Some("TODO"), Some('B')));
+        assert_eq!(first.property("status"), Some("open"));
+        assert_eq!(first.children[0].level, 1);
+        assert_eq!(first.children[0].children[0].text, "grandchild");
+        // Bullets in a code fence are its text
+        assert!(outline.blocks[1].children.is_empty());
+        assert_eq!(outline.iter().count(), 5);
+        assert_eq!(outline.render(), SOURCE);
+    }
+
+    #[test]
+    fn test_transforms_run_block_by_block_on_the_tree() {
+        // Bullets inside a directive are its text, so the directive is one block's
+        let content = "- #+BEGIN_NOTE\n  - first\n  #+END_NOTE\n- TODO [[next]]";
+        let outline = outline::parse(content);
+        assert_eq!(outline.blocks.len(), 2);
+        assert!(outline.blocks[0].children.is_empty());
+        let result = content::transform(content, &PageIndex::default());
+        assert_eq!(result, "- > [!note]\n  > - first\n- [ ] [[next]]");
+        assert_eq!(content::transform_outline(outline, &PageIndex::default()).content, result);
+    }
+
+    #[test]
+    fn test_private_blocks_are_dropped_with_children() {
+        let without_private = |content: &str| {
+            let mut outline = outline::parse(content);
+            let dropped = outline.drop_private();
+            (outline.render(), dropped)
+        };
+        let content = "- public\n- secret\n  private:: true\n\t- nested secret\n- also public";
+        assert_eq!(without_private(content), ("- public\n- also public".to_string(), true));
+        assert_eq!(without_private("- nothing hidden\n"), ("- nothing hidden\n".to_string(), false));
+        // A private page's property block is what makes it private; it stays
+        assert_eq!(without_private("- private:: true\n- text"), ("- private:: true\n- text".to_string(), false));
+    }
+
+    #[test]
+    fn test_private_blocks_stay_out_of_embeds_and_previews() {
+        const ID: &str = "6650a1b2-1111-2222-3333-444455556666";
+        let page = |name: &str, content: &str| Page {
+            name: name.to_string(),
+            name_lower: name.to_lowercase(),
+            content: content.to_string(),
+            ..Default::default()
+        };
+        let source = format!("- public note\n- secret plan #hidden\n  private:: true\n  id:: {}\n\t- secret child", ID);
+        let mut pages = vec![page("Source", &source), page("journals/2025_01_01", "- day\n- diary secret\n  private:: true")];
+        pages[0].tags = vec!["hidden".to_string()];
+
+        let index = crate::page::drop_private_blocks(PageIndex::new(pages.clone()));
+        let embedded = content::transform(&format!("- {{{{embed (({}))}}}}", ID), &index);
+        assert!(!embedded.contains("secret") && !embedded.contains("Private"), "got: {}", embedded);
+        let previews = crate::previews::build(&index, &Default::default(), false);
+        let texts: Vec<&str> = previews.values().map(|p| p.text.as_str()).collect();
+        assert!(texts.iter().all(|text| !text.contains("secret")), "got: {:?}", texts);
+        assert!(crate::resolver::find_page("Source", &index).unwrap().tags.is_empty());
+
+        // --include-private keeps them
+        let index = PageIndex::new(pages);
+        assert!(content::transform(&format!("- {{{{embed (({}))}}}}", ID), &index).contains("secret plan"));
+    }
+
+    #[test]
+    fn test_queries_read_blocks_not_code() {
+        let page = |name: &str, content: &str| Page {
+            name: name.to_string(),
+            name_lower: name.to_lowercase(),
+            content: content.to_string(),
+            ..Default::default()
+        };
+        let index = PageIndex::new(vec![
+            page("code", "- ```\n  - TODO [#A] in a snippet\n  status:: shown\n  ```"),
+            page("task", "- TODO [#A] real"),
+            page("props", "- status:: page\n\n- note\n  status:: block"),
+        ]);
+        let names = |q: &str| query::execute(q, &index).unwrap().iter().map(|p| p.name.clone()).collect::<Vec<_>>();
+
+        assert_eq!(names("{{query (task TODO)}}"), vec!["task"]);
+        assert_eq!(names("{{query (priority a)}}"), vec!["task"]);
+        assert_eq!(names("{{query (property status block)}}"), vec!["props"]);
+        assert!(names("{{query (property status shown)}}").is_empty());
+    }
+
+    #[test]
+    fn test_collapsed_blocks_fold_into_details() {
+        let content = "- Reading list for [[Rust]]\n  collapsed:: true\n\t- first\n\t\t- deeper\n- folded leaf\n  collapsed:: true\n- after";
+        let details = PageIndex::new(vec![]).with_collapsed(outline::CollapsedStyle::Details);
+        assert_eq!(
+            content::transform(content, &details),
+            "- <details class=\"collapsed-block\"><summary>Reading list for Rust</summary>\n\n\t- first\n\t\t- deeper\n\n  </details>\n- folded leaf\n\n- after"
+        );
+        // Stripped by default: the block is shown open
+        assert_eq!(content::transform(content, &PageIndex::new(vec![])), "- Reading list for [[Rust]]\n\n\t- first\n\t\t- deeper\n- folded leaf\n\n- after");
+    }
+}
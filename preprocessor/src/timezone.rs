@@ -0,0 +1,93 @@
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, Utc};
+use std::str::FromStr;
+use std::sync::RwLock;
+
+/// Zone that timestamps are turned into calendar days in (`--timezone`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Timezone {
+    /// The system's timezone
+    #[default]
+    Local,
+    /// A fixed offset from UTC (`UTC`, `+09:00`)
+    Fixed(FixedOffset),
+    /// An IANA zone with its daylight saving rules (`Europe/Berlin`)
+    Named(chrono_tz::Tz),
+}
+
+static CURRENT: RwLock<Timezone> = RwLock::new(Timezone::Local);
+
+/// Timezone of the run, set once from `--timezone` before anything is read
+pub fn set(timezone: Timezone) {
+    *CURRENT.write().unwrap_or_else(|e| e.into_inner()) = timezone;
+}
+
+pub fn current() -> Timezone {
+    *CURRENT.read().unwrap_or_else(|e| e.into_inner())
+}
+
+impl FromStr for Timezone {
+    type Err = String;
+
+    /// `local`, `UTC`, an offset (`+09:00`, `-0530`) or an IANA name (`America/New_York`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("local") {
+            return Ok(Timezone::Local);
+        }
+        if s.eq_ignore_ascii_case("utc") || s.eq_ignore_ascii_case("z") {
+            return Ok(Timezone::Fixed(FixedOffset::east_opt(0).unwrap()));
+        }
+        if s.starts_with(['+', '-']) {
+            return parse_offset(s)
+                .map(Timezone::Fixed)
+                .ok_or_else(|| format!("invalid UTC offset '{}' (expected e.g. +09:00)", s));
+        }
+        s.parse::<chrono_tz::Tz>()
+            .map(Timezone::Named)
+            .map_err(|_| format!("unknown timezone '{}' (expected local, UTC, +09:00 or a name like Europe/Berlin)", s))
+    }
+}
+
+/// `+09:00`, `+0900` or `+09` as an offset
+fn parse_offset(s: &str) -> Option<FixedOffset> {
+    let sign = if s.starts_with('-') { -1 } else { 1 };
+    let digits: String = s[1..].chars().filter(|c| *c != ':').collect();
+    if !digits.chars().all(|c| c.is_ascii_digit()) || !matches!(digits.len(), 2 | 4) {
+        return None;
+    }
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits.get(2..).filter(|m| !m.is_empty()).map_or(Some(0), |m| m.parse().ok())?;
+    if minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+impl Timezone {
+    /// Calendar day of an instant in this zone
+    pub fn date(&self, instant: DateTime<Utc>) -> NaiveDate {
+        match self {
+            Timezone::Local => instant.with_timezone(&Local).date_naive(),
+            Timezone::Fixed(offset) => instant.with_timezone(offset).date_naive(),
+            Timezone::Named(tz) => instant.with_timezone(tz).date_naive(),
+        }
+    }
+
+    /// Today in this zone
+    pub fn today(&self) -> NaiveDate {
+        self.date(Utc::now())
+    }
+
+    /// Day of a date or timestamp value. Timestamps with an offset
+    /// (`2024-01-15T23:30:00Z`, `…+02:00`) are converted to this zone;
+    /// dates (`Jan 15th, 2024`) and local times (`2024-01-15T10:00`) are
+    /// days as written.
+    pub fn parse_date(&self, value: &str) -> Option<NaiveDate> {
+        let value = value.trim();
+        if let Ok(instant) = DateTime::parse_from_rfc3339(value) {
+            return Some(self.date(instant.with_timezone(&Utc)));
+        }
+        let day = value.split_once('T').map_or(value, |(date, _)| date);
+        crate::query::parse_date(day)
+    }
+}
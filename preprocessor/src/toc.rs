@@ -0,0 +1,103 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+
+lazy_static! {
+    // Markdown headings, including Logseq bullet headings (- ## Heading)
+    static ref HEADING_RE: Regex = Regex::new(r"^\s*(?:-\s+)?(#{1,6})\s+(.+?)\s*#*\s*$").unwrap();
+
+    // Link markup inside heading text: [[page]], [[page|label]], [label](url)
+    static ref WIKILINK_TEXT_RE: Regex = Regex::new(r"\[\[(?:[^\]|]+\|)?([^\]]+)\]\]").unwrap();
+    static ref MD_LINK_TEXT_RE: Regex = Regex::new(r"\[([^\]]+)\]\([^)]*\)").unwrap();
+}
+
+/// Pages need at least this many headings before a contents section is generated
+pub const MIN_HEADINGS: usize = 3;
+
+/// A heading found in page content
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heading {
+    pub level: usize,
+    pub text: String,
+    pub anchor: String,
+}
+
+/// Whether a page gets a contents section: `toc::` property wins, otherwise the
+/// global option applies to pages with at least `MIN_HEADINGS` headings.
+pub fn wants_toc(toc_property: Option<&str>, enabled: bool, headings: &[Heading]) -> bool {
+    match toc_property.map(|v| v.trim().to_lowercase()) {
+        Some(v) if v == "true" => !headings.is_empty(),
+        Some(v) if v == "false" => false,
+        _ => enabled && headings.len() >= MIN_HEADINGS,
+    }
+}
+
+/// Collect headings outside fenced code blocks, with unique anchors
+pub fn headings(content: &str) -> Vec<Heading> {
+    let mut result = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut in_code = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim_start().trim_start_matches("- ").trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        if let Some(caps) = HEADING_RE.captures(line) {
+            let text = plain_text(&caps[2]);
+            if text.is_empty() {
+                continue;
+            }
+            let base = anchor(&text);
+            let count = seen.entry(base.clone()).or_insert(0);
+            let anchor = if *count == 0 { base } else { format!("{}-{}", base, count) };
+            *count += 1;
+            result.push(Heading { level: caps[1].len(), text, anchor });
+        }
+    }
+    result
+}
+
+/// Render a `## Contents` section linking to the headings, nested by level
+pub fn render(headings: &[Heading]) -> String {
+    let min_level = headings.iter().map(|h| h.level).min().unwrap_or(1);
+    let mut out = String::from("## Contents\n\n");
+    for heading in headings {
+        let indent = "  ".repeat(heading.level - min_level);
+        out.push_str(&format!("{}- [{}](#{})\n", indent, heading.text, heading.anchor));
+    }
+    out.push('\n');
+    out
+}
+
+/// Prepend a contents section to page content
+pub fn insert(content: &str, headings: &[Heading]) -> String {
+    format!("{}{}", render(headings), content.trim_start_matches('\n'))
+}
+
+/// Heading text without link markup
+fn plain_text(text: &str) -> String {
+    let text = WIKILINK_TEXT_RE.replace_all(text, "$1");
+    let text = MD_LINK_TEXT_RE.replace_all(&text, "$1");
+    text.trim().to_string()
+}
+
+/// Whether page content has a heading whose anchor is the heading's
+/// (`[[page#Some Heading]]` matches `## Some heading`)
+pub fn has_heading(content: &str, heading: &str) -> bool {
+    let wanted = anchor(heading);
+    headings(content).iter().any(|h| h.anchor == wanted)
+}
+
+/// Heading anchor the way Quartz (github-slugger) generates it
+pub fn anchor(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '-' || *c == '_')
+        .map(|c| if c == ' ' { '-' } else { c })
+        .collect()
+}
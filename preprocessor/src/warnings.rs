@@ -0,0 +1,142 @@
+use anyhow::Result;
+use lazy_static::lazy_static;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+lazy_static! {
+    // Registry shared by all pipeline steps (pages are processed in parallel)
+    static ref GLOBAL: Warnings = Warnings::default();
+}
+
+/// Global warning registry for the current run
+pub fn global() -> &'static Warnings {
+    &GLOBAL
+}
+
+/// One distinct warning with how often it occurred and where
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct WarningEntry {
+    pub kind: String,
+    pub message: String,
+    pub count: usize,
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub pages: BTreeSet<String>,
+}
+
+/// Thread-safe registry that deduplicates identical warnings.
+///
+/// Warnings are keyed by kind and message; repeated occurrences only bump the
+/// count and add the affected page, so a broken link used on 50 pages is
+/// reported once.
+#[derive(Debug, Default)]
+pub struct Warnings {
+    entries: Mutex<BTreeMap<(String, String), WarningEntry>>,
+}
+
+/// A problem noticed while transforming one page, recorded against that page
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub kind: &'static str,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(kind: &'static str, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into() }
+    }
+}
+
+impl Warnings {
+    /// Record a warning. Returns true the first time this warning is seen
+    /// (which is also when it's logged at debug level, for `-v`).
+    pub fn warn(&self, kind: &str, message: &str, page: Option<&str>) -> bool {
+        let first = self.record(kind, message, page);
+        if first {
+            match page {
+                Some(page) => log::debug!("[{}] {} ({})", kind, message, page),
+                None => log::debug!("[{}] {}", kind, message),
+            }
+        }
+        first
+    }
+
+    /// Record a processing error. Errors are always logged, once per distinct message.
+    pub fn error(&self, message: &str, page: Option<&str>) -> bool {
+        let first = self.record("error", message, page);
+        if first {
+            log::error!("{}", message);
+        }
+        first
+    }
+
+    /// Record a page's diagnostics
+    pub fn page_diagnostics(&self, diagnostics: &[Diagnostic], page: &str) {
+        for diagnostic in diagnostics {
+            self.warn(diagnostic.kind, &diagnostic.message, Some(page));
+        }
+    }
+
+    fn record(&self, kind: &str, message: &str, page: Option<&str>) -> bool {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (kind.to_string(), message.to_string());
+        let first = !entries.contains_key(&key);
+        let entry = entries.entry(key).or_insert_with(|| WarningEntry {
+            kind: kind.to_string(),
+            message: message.to_string(),
+            ..Default::default()
+        });
+        entry.count += 1;
+        if let Some(page) = page {
+            entry.pages.insert(page.to_string());
+        }
+        first
+    }
+
+    /// Distinct warnings sorted by kind, then message
+    pub fn entries(&self) -> Vec<WarningEntry> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.values().cloned().collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).is_empty()
+    }
+
+    /// Recorded processing errors (files that failed), one entry per failure
+    pub fn errors(&self) -> Vec<WarningEntry> {
+        self.entries().into_iter().filter(|e| e.kind == "error").collect()
+    }
+
+    /// Compact summary lines, listing at most `max_pages` affected pages per warning (0 = all).
+    /// Errors are listed separately (see `errors`).
+    pub fn summary(&self, max_pages: usize) -> Vec<String> {
+        self.entries()
+            .iter()
+            .filter(|entry| entry.kind != "error")
+            .map(|entry| {
+                let mut line = format!("[{}] {}", entry.kind, entry.message);
+                if entry.count > 1 {
+                    line.push_str(&format!(" (x{})", entry.count));
+                }
+                if !entry.pages.is_empty() {
+                    let shown = if max_pages == 0 { entry.pages.len() } else { max_pages.min(entry.pages.len()) };
+                    let pages: Vec<&str> = entry.pages.iter().take(shown).map(String::as_str).collect();
+                    line.push_str(&format!(" in {}", pages.join(", ")));
+                    if shown < entry.pages.len() {
+                        line.push_str(&format!(" and {} more", entry.pages.len() - shown));
+                    }
+                }
+                line
+            })
+            .collect()
+    }
+
+    /// Write all warnings with every affected page as a JSON report (_warnings.json)
+    pub fn write_report(&self, output_dir: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.entries())?;
+        fs::write(output_dir.join("_warnings.json"), json)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::path::Path;
+
+use crate::source;
+
+lazy_static! {
+    // Match :preferred-workflow :now / :todo in EDN
+    static ref PREFERRED_WORKFLOW_RE: Regex = Regex::new(r":preferred-workflow\s+:?(\w+)").unwrap();
+}
+
+/// Logseq task workflow (config.edn `:preferred-workflow`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Workflow {
+    /// TODO / DOING (Logseq's default)
+    #[default]
+    Todo,
+    /// LATER / NOW
+    Now,
+}
+
+impl Workflow {
+    /// Parse config.edn content; anything but `:now` is the TODO/DOING workflow
+    pub fn from_edn(content: &str) -> Self {
+        match PREFERRED_WORKFLOW_RE.captures(content) {
+            Some(caps) if caps[1].eq_ignore_ascii_case("now") => Workflow::Now,
+            _ => Workflow::Todo,
+        }
+    }
+
+    /// Workflow of a graph, from `logseq/config.edn` (default when missing)
+    pub fn from_config(config_path: &Path) -> Self {
+        source::read_to_string(config_path)
+            .map(|content| Self::from_edn(&content))
+            .unwrap_or_default()
+    }
+
+    /// Task states a `(task STATE)` query matches: the state itself plus its
+    /// equivalent in this workflow (TODO ↔ LATER, DOING ↔ NOW)
+    pub fn query_states(self, state: &str) -> Vec<String> {
+        let state = state.to_uppercase();
+        let equivalent = match (self, state.as_str()) {
+            (Workflow::Now, "TODO") => Some("LATER"),
+            (Workflow::Now, "DOING") => Some("NOW"),
+            (Workflow::Todo, "LATER") => Some("TODO"),
+            (Workflow::Todo, "NOW") => Some("DOING"),
+            _ => None,
+        };
+        let mut states = vec![state.clone()];
+        states.extend(equivalent.map(str::to_string));
+        states
+    }
+
+    /// Whether LATER is this workflow's plain "not started" state
+    pub fn later_is_todo(self) -> bool {
+        self == Workflow::Now
+    }
+}
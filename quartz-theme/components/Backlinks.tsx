@@ -0,0 +1,67 @@
+import { QuartzComponent, QuartzComponentConstructor, QuartzComponentProps } from "./types"
+import { resolveRelative, simplifySlug } from "../util/path"
+import { i18n } from "../i18n"
+import { classNames } from "../util/lang"
+
+// @ts-ignore
+import style from "./styles/backlinks.scss"
+
+interface BacklinksOptions {
+  hideWhenEmpty: boolean
+}
+
+const defaultOptions: BacklinksOptions = {
+  hideWhenEmpty: true,
+}
+
+// Output names from the preprocessor ("Projects/Web App") compared as Quartz slugs
+const toSlug = (name: string) => name.replace(/ /g, "-").toLowerCase()
+
+export default ((opts?: Partial<BacklinksOptions>) => {
+  const options: BacklinksOptions = { ...defaultOptions, ...opts }
+
+  const Backlinks: QuartzComponent = ({
+    fileData,
+    allFiles,
+    displayClass,
+    cfg,
+  }: QuartzComponentProps) => {
+    const slug = simplifySlug(fileData.slug!)
+    let backlinkFiles = allFiles.filter((file) => file.links?.includes(slug))
+
+    // Logseq linked-reference filters, applied by the preprocessor (filters:: / :filters)
+    const linkedReferences = fileData.frontmatter?.["linked-references"] as string[] | undefined
+    if (Array.isArray(linkedReferences)) {
+      const allowed = new Set(linkedReferences.map(toSlug))
+      backlinkFiles = backlinkFiles.filter((file) =>
+        allowed.has(simplifySlug(file.slug!).toLowerCase()),
+      )
+    }
+
+    if (options.hideWhenEmpty && backlinkFiles.length === 0) {
+      return null
+    }
+
+    return (
+      <div class={classNames(displayClass, "backlinks")}>
+        <h3>{i18n(cfg.locale).components.backlinks.title}</h3>
+        <ul class="overflow">
+          {backlinkFiles.length > 0 ? (
+            backlinkFiles.map((f) => (
+              <li>
+                <a href={resolveRelative(fileData.slug!, f.slug!)} class="internal">
+                  {f.frontmatter?.title}
+                </a>
+              </li>
+            ))
+          ) : (
+            <li>{i18n(cfg.locale).components.backlinks.noBacklinksFound}</li>
+          )}
+        </ul>
+      </div>
+    )
+  }
+
+  Backlinks.css = style
+  return Backlinks
+}) satisfies QuartzComponentConstructor
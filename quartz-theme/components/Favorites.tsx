@@ -73,6 +73,9 @@ export default ((opts?: Partial<FavoritesOptions>) => {
           <ul>
             {favoriteFiles.map((f) => {
               const icon = f!.frontmatter?.icon || ""
+              // Mapped icons (icon-map): an asset image or an icon font class
+              const iconSrc = f!.frontmatter?.["icon-src"] as string | undefined
+              const iconClass = f!.frontmatter?.["icon-class"] as string | undefined
               let title = f!.frontmatter?.title || simplifySlug(f!.slug!)
               if (icon && title.startsWith(icon)) {
                 title = title.slice(icon.length).trim()
@@ -81,7 +84,13 @@ export default ((opts?: Partial<FavoritesOptions>) => {
               return (
                 <li>
                   <a href={href} class="internal" data-for={f!.slug}>
-                    {icon && <span class="favorite-icon">{icon}</span>}
+                    {iconSrc ? (
+                      <img class="favorite-icon" src={iconSrc} alt={icon} />
+                    ) : iconClass ? (
+                      <i class={`favorite-icon ${iconClass}`} aria-label={icon}></i>
+                    ) : (
+                      icon && <span class="favorite-icon">{icon}</span>
+                    )}
                     {title}
                   </a>
                 </li>
@@ -52,6 +52,8 @@ export default (() => {
         )}
         <link rel="preconnect" href="https://cdnjs.cloudflare.com" crossOrigin="anonymous" />
         <meta name="viewport" content="width=device-width, initial-scale=1.0" />
+        {/* Stub pages generated for missing links stay out of search engines */}
+        {fileData.frontmatter?.noindex && <meta name="robots" content="noindex" />}
 
         <meta name="og:site_name" content={cfg.pageTitle}></meta>
         <meta property="og:title" content={title} />
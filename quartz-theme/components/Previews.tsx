@@ -0,0 +1,15 @@
+import { QuartzComponent, QuartzComponentConstructor } from "./types"
+
+// @ts-ignore
+import style from "./styles/previews.scss"
+// @ts-ignore
+import script from "./scripts/previews.inline"
+
+// Hover popovers for internal links, from the preprocessor's previews.json
+// (--previews). Links without a precomputed preview fall back to fetching the page.
+const Previews: QuartzComponent = () => null
+
+Previews.css = style
+Previews.afterDOMLoaded = script
+
+export default (() => Previews) satisfies QuartzComponentConstructor
@@ -0,0 +1,54 @@
+import { QuartzComponent, QuartzComponentConstructor } from "./types"
+
+// @ts-ignore
+import script from "./scripts/random.inline"
+
+// Button opening a random page, picked from the preprocessor's random.json
+// (--random). It stays hidden when the file isn't there.
+const RandomNote: QuartzComponent = () => {
+  return (
+    <button class="random-note" id="random-note" aria-label="Random note" title="Random note" hidden>
+      <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2" stroke-linecap="round" stroke-linejoin="round">
+        <rect x="3" y="3" width="18" height="18" rx="3"></rect>
+        <circle cx="8.5" cy="8.5" r="1"></circle>
+        <circle cx="15.5" cy="15.5" r="1"></circle>
+        <circle cx="12" cy="12" r="1"></circle>
+      </svg>
+    </button>
+  )
+}
+
+RandomNote.css = `
+.random-note {
+  cursor: pointer;
+  background: transparent;
+  border: none;
+  padding: 0;
+  margin: 0;
+  width: 20px;
+  height: 32px;
+  display: flex;
+  align-items: center;
+  justify-content: center;
+  flex-shrink: 0;
+  color: var(--darkgray);
+  transition: color 0.2s;
+}
+
+.random-note[hidden] {
+  display: none;
+}
+
+.random-note svg {
+  width: 20px;
+  height: 20px;
+}
+
+.random-note:hover {
+  color: var(--secondary);
+}
+`
+
+RandomNote.afterDOMLoaded = script
+
+export default (() => RandomNote) satisfies QuartzComponentConstructor
@@ -0,0 +1,43 @@
+import { QuartzComponent, QuartzComponentConstructor, QuartzComponentProps } from "./types"
+import { resolveRelative, simplifySlug } from "../util/path"
+import { classNames } from "../util/lang"
+
+// @ts-ignore
+import style from "./styles/backlinks.scss"
+
+// Output names from the preprocessor ("Projects/Web App") compared as Quartz slugs
+const toSlug = (name: string) => name.replace(/ /g, "-").toLowerCase()
+
+// Pages related by shared tags, links and co-citation, from the `related:`
+// frontmatter the preprocessor writes with --related. Hidden without it.
+const RelatedNotes: QuartzComponent = ({ fileData, allFiles, displayClass }: QuartzComponentProps) => {
+  const related = fileData.frontmatter?.["related"] as string[] | undefined
+  if (!Array.isArray(related) || related.length === 0) {
+    return null
+  }
+
+  const bySlug = new Map(allFiles.map((file) => [simplifySlug(file.slug!).toLowerCase(), file]))
+  const files = related.map((name) => bySlug.get(toSlug(name))).filter((file) => file !== undefined)
+  if (files.length === 0) {
+    return null
+  }
+
+  return (
+    <div class={classNames(displayClass, "backlinks", "related-notes")}>
+      <h3>Related</h3>
+      <ul class="overflow">
+        {files.map((f) => (
+          <li>
+            <a href={resolveRelative(fileData.slug!, f!.slug!)} class="internal">
+              {f!.frontmatter?.title}
+            </a>
+          </li>
+        ))}
+      </ul>
+    </div>
+  )
+}
+
+RelatedNotes.css = style
+
+export default (() => RelatedNotes) satisfies QuartzComponentConstructor
@@ -27,6 +27,9 @@ import SidebarToggle from "./SidebarToggle"
 import Favorites from "./Favorites"
 import Journals from "./Journals"
 import Redirect from "./Redirect"
+import Previews from "./Previews"
+import RandomNote from "./RandomNote"
+import RelatedNotes from "./RelatedNotes"
 
 export {
   ArticleTitle,
@@ -58,4 +61,7 @@ export {
   Favorites,
   Journals,
   Redirect,
+  Previews,
+  RandomNote,
+  RelatedNotes,
 }
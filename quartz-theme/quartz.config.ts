@@ -9,7 +9,8 @@ const config: QuartzConfig = {
     pageTitle: "Cyber",
     pageTitleSuffix: "",
     enableSPA: true,
-    enablePopovers: true,
+    // Popovers come from the Previews component (previews.json, page fetch fallback)
+    enablePopovers: false,
     analytics: {
       provider: "plausible",
       host: "https://metrics.cyb.ai",
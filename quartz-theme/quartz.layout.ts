@@ -8,7 +8,7 @@ import * as Component from "./quartz/components"
 export const sharedPageComponents: SharedLayout = {
   head: Component.Head(),
   header: [],
-  afterBody: [Component.Redirect()],
+  afterBody: [Component.Redirect(), Component.Previews()],
   footer: Component.Footer({
     links: {
       GitHub: "https://github.com/cybercongress/cyber",
@@ -34,6 +34,7 @@ export const defaultContentPageLayout: PageLayout = {
     Component.Flex({
       components: [
         { Component: Component.Search(), grow: true },
+        { Component: Component.RandomNote() },
         { Component: Component.ReaderMode() },
       ],
     }),
@@ -66,6 +67,7 @@ export const defaultContentPageLayout: PageLayout = {
     Component.Graph(),
     Component.DesktopOnly(Component.TableOfContents()),
     Component.Backlinks(),
+    Component.RelatedNotes(),
   ],
 }
 
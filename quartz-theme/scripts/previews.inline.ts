@@ -0,0 +1,125 @@
+import { computePosition, flip, inline, shift } from "@floating-ui/dom"
+
+interface Preview {
+  title: string
+  icon?: string
+  icon_src?: string
+  icon_class?: string
+  text: string
+}
+
+let previews: Promise<Record<string, Preview>> | undefined
+
+// previews.json sits at the site root; pages are rendered as folder/index.html
+function rootPath(): string {
+  const slug = document.body.dataset.slug ?? ""
+  const depth = slug.split("/").length - 1
+  return "../".repeat(depth) || "./"
+}
+
+function loadPreviews(): Promise<Record<string, Preview>> {
+  if (!previews) {
+    previews = fetch(rootPath() + "previews.json")
+      .then((res) => (res.ok ? res.json() : {}))
+      .catch(() => ({}))
+  }
+  return previews
+}
+
+function renderPreview(preview: Preview): HTMLElement {
+  const inner = document.createElement("div")
+  inner.classList.add("popover-inner", "preview")
+
+  const title = document.createElement("h3")
+  title.classList.add("preview-title")
+  if (preview.icon_src) {
+    const img = document.createElement("img")
+    img.classList.add("icon")
+    img.src = rootPath() + preview.icon_src.replace(/^\//, "")
+    img.alt = preview.icon ?? ""
+    title.append(img, " ")
+  } else if (preview.icon_class) {
+    const i = document.createElement("i")
+    i.className = `icon ${preview.icon_class}`
+    i.setAttribute("aria-label", preview.icon ?? "")
+    title.append(i, " ")
+  } else if (preview.icon) {
+    title.append(preview.icon + " ")
+  }
+  title.append(preview.title)
+  inner.append(title)
+
+  if (preview.text) {
+    const text = document.createElement("p")
+    text.classList.add("preview-text")
+    text.textContent = preview.text
+    inner.append(text)
+  }
+  return inner
+}
+
+// Fallback for pages without a preview (stubs, tag and folder pages)
+async function fetchPreview(href: string): Promise<HTMLElement | null> {
+  const res = await fetch(href).catch(() => null)
+  if (!res?.ok || !res.headers.get("Content-Type")?.includes("text/html")) return null
+
+  const html = new DOMParser().parseFromString(await res.text(), "text/html")
+  const hints = [...html.getElementsByClassName("popover-hint")]
+  if (hints.length === 0) return null
+
+  const inner = document.createElement("div")
+  inner.classList.add("popover-inner")
+  inner.append(...hints)
+  return inner
+}
+
+async function showPopover(this: HTMLAnchorElement, { clientX, clientY }: MouseEvent) {
+  const link = this
+  if (link.dataset.noPopover === "true") return
+
+  const url = new URL(link.href)
+  url.hash = ""
+  const id = `popover-${url.pathname}`
+  let popover = document.getElementById(id)
+
+  if (!popover) {
+    const slug = link.dataset.slug?.split("#")[0]
+    const preview = slug ? (await loadPreviews())[slug] : undefined
+    const inner = preview ? renderPreview(preview) : await fetchPreview(url.toString())
+    if (!inner) return
+
+    popover = document.getElementById(id)
+    if (!popover) {
+      popover = document.createElement("div")
+      popover.id = id
+      popover.classList.add("popover")
+      popover.append(inner)
+      document.body.append(popover)
+    }
+  }
+
+  const { x, y } = await computePosition(link, popover, {
+    strategy: "fixed",
+    middleware: [inline({ x: clientX, y: clientY }), shift(), flip()],
+  })
+  Object.assign(popover.style, { transform: `translate(${x.toFixed()}px, ${y.toFixed()}px)` })
+
+  document.querySelectorAll(".popover.active-popover").forEach((p) => p.classList.remove("active-popover"))
+  popover.classList.add("active-popover")
+}
+
+function hidePopovers() {
+  document.querySelectorAll(".popover.active-popover").forEach((p) => p.classList.remove("active-popover"))
+}
+
+document.addEventListener("nav", () => {
+  const links = [...document.querySelectorAll("a.internal")] as HTMLAnchorElement[]
+  for (const link of links) {
+    link.addEventListener("mouseenter", showPopover)
+    link.addEventListener("mouseleave", hidePopovers)
+    window.addCleanup(() => {
+      link.removeEventListener("mouseenter", showPopover)
+      link.removeEventListener("mouseleave", hidePopovers)
+    })
+  }
+})
@@ -0,0 +1,38 @@
+let paths: Promise<string[]> | undefined
+
+// random.json sits at the site root; pages are rendered as folder/index.html
+function rootPath(): string {
+  const slug = document.body.dataset.slug ?? ""
+  const depth = slug.split("/").length - 1
+  return "../".repeat(depth) || "./"
+}
+
+function loadPaths(): Promise<string[]> {
+  if (!paths) {
+    paths = fetch(rootPath() + "random.json")
+      .then((res) => (res.ok ? res.json() : []))
+      .catch(() => [])
+  }
+  return paths
+}
+
+async function openRandom() {
+  const current = document.body.dataset.slug
+  const choices = (await loadPaths()).filter((path) => path !== current)
+  if (choices.length === 0) return
+  const path = choices[Math.floor(Math.random() * choices.length)]
+  const url = new URL(rootPath() + path, window.location.href)
+  if (window.spaNavigate) {
+    window.spaNavigate(url)
+  } else {
+    window.location.assign(url)
+  }
+}
+
+document.addEventListener("nav", async () => {
+  const button = document.getElementById("random-note")
+  if (!button) return
+  button.hidden = (await loadPaths()).length === 0
+  button.addEventListener("click", openRandom)
+  window.addCleanup(() => button.removeEventListener("click", openRandom))
+})